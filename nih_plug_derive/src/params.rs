@@ -22,6 +22,48 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         }
     };
 
+    // With `#[params(auto_id)]` on the struct, parameter fields without any attributes get their
+    // field name as their parameter ID
+    let mut auto_id = false;
+    for attr in &ast.attrs {
+        if !attr.path.is_ident("params") {
+            continue;
+        }
+
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(syn::MetaList {
+                nested: struct_attrs,
+                ..
+            })) => {
+                for struct_attr in struct_attrs {
+                    match struct_attr {
+                        syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("auto_id") => {
+                            auto_id = true;
+                        }
+                        _ => {
+                            return syn::Error::new(
+                                struct_attr.span(),
+                                "Unknown attribute. See the Params trait documentation for more \
+                                 information.",
+                            )
+                            .to_compile_error()
+                            .into()
+                        }
+                    }
+                }
+            }
+            _ => {
+                return syn::Error::new(
+                    attr.span(),
+                    "The params attribute should be a list in the following format: \
+                     #[params(auto_id)]",
+                )
+                .to_compile_error()
+                .into()
+            }
+        }
+    }
+
     // We only care about fields with `id`, `persist`, and `nested` attributes. For the `id` fields
     // we'll build a mapping function that creates a hashmap containing pointers to those
     // parameters. For the `persist` function we'll create functions that serialize and deserialize
@@ -61,10 +103,7 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         // This is a vector since we want to preserve the order. If structs get
                         // large enough to the point where a linear search starts being expensive,
                         // then the plugin should probably start splitting up their parameters.
-                        if params.iter().any(|p| match p {
-                            Param::Single { id, .. } => &s == id,
-                            _ => false,
-                        }) {
+                        if contains_param_id(&params, &s) {
                             return syn::Error::new(
                                 field.span(),
                                 "Multiple parameters with the same ID found",
@@ -133,11 +172,13 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 };
             } else if attr.path.is_ident("nested") {
                 // This one is more complicated. Supports an `array` attribute, an `id_prefix =
-                // "foo"` attribute, and a `group = "group name"` attribute. All are optional, and
-                // the first two are mutually exclusive.
+                // "foo"` attribute, a `group = "group name"` attribute, and an `enabled =
+                // "param_id"` attribute. All are optional, the first two are mutually exclusive,
+                // and the last one requires a group name.
                 let mut nested_array = false;
                 let mut nested_id_prefix: Option<syn::LitStr> = None;
                 let mut nested_group: Option<syn::LitStr> = None;
+                let mut nested_enabled: Option<syn::LitStr> = None;
                 match attr.parse_meta() {
                     // In this case it's a plain `#[nested]` attribute without parameters
                     Ok(syn::Meta::Path(..)) => (),
@@ -196,6 +237,15 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                                         nested_group = Some(s.clone());
                                     }
                                 }
+                                syn::NestedMeta::Meta(syn::Meta::NameValue(
+                                    syn::MetaNameValue {
+                                        path,
+                                        lit: syn::Lit::Str(s),
+                                        ..
+                                    },
+                                )) if path.is_ident("enabled") => {
+                                    nested_enabled = Some(s.clone());
+                                }
                                 _ => {
                                     return syn::Error::new(
                                         nested_attr.span(),
@@ -212,26 +262,39 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         return syn::Error::new(
                             attr.span(),
                             "The nested attribute should be a list in the following format: \
-                             #[nested([array | id_prefix = \"foo\"], [group = \"group name\"])]",
+                             #[nested([array | id_prefix = \"foo\"], [group = \"group name\"], \
+                             [enabled = \"param_id\"])]",
                         )
                         .to_compile_error()
                         .into()
                     }
                 };
 
+                if nested_enabled.is_some() && nested_group.is_none() {
+                    return syn::Error::new(
+                        attr.span(),
+                        "'enabled' can only be used together with 'group'",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+
                 params.push(Param::Nested(match (nested_array, nested_id_prefix) {
                     (true, None) => NestedParams::Array {
                         field: field_name.clone(),
                         group: nested_group,
+                        enabled: nested_enabled,
                     },
                     (false, Some(id_prefix)) => NestedParams::Prefixed {
                         field: field_name.clone(),
                         id_prefix,
                         group: nested_group,
+                        enabled: nested_enabled,
                     },
                     (false, None) => NestedParams::Inline {
                         field: field_name.clone(),
                         group: nested_group,
+                        enabled: nested_enabled,
                     },
                     (true, Some(_)) => {
                         return syn::Error::new(
@@ -246,6 +309,28 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 processed_attribute = true;
             }
         }
+
+        if auto_id && !processed_attribute && is_param_type(&field.ty) {
+            let field_name_str = field_name.to_string();
+            let id = syn::LitStr::new(
+                field_name_str.strip_prefix("r#").unwrap_or(&field_name_str),
+                field_name.span(),
+            );
+            if contains_param_id(&params, &id) {
+                return syn::Error::new(
+                    field.span(),
+                    "Multiple parameters with the same ID found. The field name is used as the \
+                     parameter ID, add an #[id = \"...\"] attribute to use a different ID.",
+                )
+                .to_compile_error()
+                .into();
+            }
+
+            params.push(Param::Single {
+                id,
+                field: field_name.clone(),
+            });
+        }
     }
 
     // The next step is build the gathered information into tokens that can be spliced into a
@@ -265,6 +350,21 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         }
     };
 
+    let group_enable_params_tokens = {
+        let nested_group_enable_params_tokens = params.iter().filter_map(|p| match p {
+            Param::Single { .. } => None,
+            Param::Nested(nested) => Some(nested.group_enable_params_tokens()),
+        });
+
+        quote! {
+            #[allow(unused_mut)]
+            let mut group_enable_params = Vec::new();
+            #(#nested_group_enable_params_tokens)*
+
+            group_enable_params
+        }
+    };
+
     let (serialize_fields_tokens, deserialize_fields_tokens) = {
         // Like with `param_map()`, we'll try to do the serialization for this struct and then
         // recursively call the child parameter structs. We don't know anything about the actual
@@ -422,6 +522,10 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 #serialize_fields_tokens
             }
 
+            fn group_enable_params(&self) -> Vec<(String, nih_plug::prelude::ParamPtr)> {
+                #group_enable_params_tokens
+            }
+
             fn deserialize_fields(&self, serialized: &::std::collections::BTreeMap<String, String>) {
                 #deserialize_fields_tokens
             }
@@ -430,6 +534,32 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Whether `params` already contains a (non-nested) parameter with this ID.
+fn contains_param_id(params: &[Param], id: &syn::LitStr) -> bool {
+    params.iter().any(|p| match p {
+        Param::Single {
+            id: existing_id, ..
+        } => existing_id.value() == id.value(),
+        _ => false,
+    })
+}
+
+/// Whether a field's type is one of NIH-plug's parameter types. Used for `#[params(auto_id)]`.
+/// Custom parameter types still need an explicit `#[id = "..."]` attribute since we can't know
+/// whether a type implements `Param` from within the macro.
+fn is_param_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath { path, .. }) => match path.segments.last() {
+            Some(segment) => matches!(
+                segment.ident.to_string().as_str(),
+                "BoolParam" | "FloatParam" | "IntParam" | "EnumParam"
+            ),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
 /// A parameter defined on this struct using the `#[id = "..."]` attribute, or another object that
 /// also implements `Params` tagged with one of the variations on the `#[nested]` attribute.
 #[derive(Debug)]
@@ -469,13 +599,15 @@ struct PersistentField {
 }
 
 /// A field containing another object whose parameters and persistent fields should be added to this
-/// struct's.
+/// struct's. If `enabled` is set, then the nested struct's `BoolParam` with that (unprefixed) ID
+/// enables or disables the entire group.
 #[derive(Debug)]
 enum NestedParams {
     /// The nested struct's parameters are taken as is.
     Inline {
         field: syn::Ident,
         group: Option<syn::LitStr>,
+        enabled: Option<syn::LitStr>,
     },
     /// The nested struct's parameters will get an ID prefix. The original parameter with ID `foo`
     /// will become `{id_prefix}_foo`.
@@ -483,6 +615,7 @@ enum NestedParams {
         field: syn::Ident,
         id_prefix: syn::LitStr,
         group: Option<syn::LitStr>,
+        enabled: Option<syn::LitStr>,
     },
     /// This field is an array-like data structure containing nested parameter structs. The
     /// parameter `foo` will get the new parameter ID `foo_{array_idx + 1}`, and if the group name
@@ -490,6 +623,7 @@ enum NestedParams {
     Array {
         field: syn::Ident,
         group: Option<syn::LitStr>,
+        enabled: Option<syn::LitStr>,
     },
 }
 
@@ -509,6 +643,7 @@ impl NestedParams {
             NestedParams::Inline {
                 field,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     if nested_group_name.is_empty() {
@@ -518,13 +653,16 @@ impl NestedParams {
                     }
                 })
             },
-            NestedParams::Inline { field, group: None } => quote! {
+            NestedParams::Inline {
+                field, group: None, ..
+            } => quote! {
                 self.#field.param_map()
             },
             NestedParams::Prefixed {
                 field,
                 id_prefix,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     let param_id = format!("{}_{}", #id_prefix, param_id);
@@ -540,6 +678,7 @@ impl NestedParams {
                 field,
                 id_prefix,
                 group: None,
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     let param_id = format!("{}_{}", #id_prefix, param_id);
@@ -552,6 +691,7 @@ impl NestedParams {
             NestedParams::Array {
                 field,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
@@ -569,7 +709,9 @@ impl NestedParams {
                     })
                 })
             },
-            NestedParams::Array { field, group: None } => quote! {
+            NestedParams::Array {
+                field, group: None, ..
+            } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
 
@@ -582,4 +724,86 @@ impl NestedParams {
             },
         }
     }
+
+    /// Generate the tokens that add the nested struct's group enable parameters to the
+    /// `group_enable_params` vector. This includes the nested struct's own `enabled` parameter
+    /// and all group enable parameters from its nested structs, with this field's group prefixed
+    /// to their groups.
+    fn group_enable_params_tokens(&self) -> proc_macro2::TokenStream {
+        // These are `(group, params)` pairs for every nested object. Only arrays have more than
+        // one object.
+        let (groups_and_params, group, enabled) = match self {
+            NestedParams::Inline {
+                field,
+                group,
+                enabled,
+            }
+            | NestedParams::Prefixed {
+                field,
+                group,
+                enabled,
+                ..
+            } => {
+                let group_tokens = match group {
+                    Some(group) => quote! { Some(String::from(#group)) },
+                    None => quote! { None::<String> },
+                };
+
+                (
+                    quote! { ::std::iter::once((#group_tokens, &self.#field)) },
+                    group,
+                    enabled,
+                )
+            }
+            NestedParams::Array {
+                field,
+                group,
+                enabled,
+            } => {
+                let groups_and_params = match group {
+                    Some(group) => quote! {
+                        self.#field.iter().enumerate().map(|(idx, params)| {
+                            (Some(format!("{} {}", #group, idx + 1)), params)
+                        })
+                    },
+                    None => quote! { self.#field.iter().map(|params| (None::<String>, params)) },
+                };
+
+                (groups_and_params, group, enabled)
+            }
+        };
+
+        // The parser makes sure `enabled` is only set together with `group`
+        let enabled_tokens = match (group, enabled) {
+            (Some(_), Some(enabled)) => quote! {
+                match params
+                    .param_map()
+                    .into_iter()
+                    .find(|(param_id, _, _)| param_id == #enabled)
+                {
+                    Some((_, param_ptr @ nih_plug::prelude::ParamPtr::BoolParam(_), _)) => {
+                        group_enable_params.push((group.clone().unwrap_or_default(), param_ptr));
+                    }
+                    _ => ::nih_plug::nih_debug_assert_failure!(
+                        "The enabled parameter '{}' is not a BoolParam in the nested parameters",
+                        #enabled
+                    ),
+                }
+            },
+            _ => quote! {},
+        };
+
+        quote! {
+            for (group, params) in #groups_and_params {
+                #enabled_tokens
+
+                group_enable_params.extend(params.group_enable_params().into_iter().map(
+                    |(nested_group, param_ptr)| match &group {
+                        Some(group) => (format!("{}/{}", group, nested_group), param_ptr),
+                        None => (nested_group, param_ptr),
+                    },
+                ));
+            }
+        }
+    }
 }
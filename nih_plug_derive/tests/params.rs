@@ -109,6 +109,84 @@ impl Default for NestedArrayParams {
     }
 }
 
+#[derive(Params)]
+#[params(auto_id)]
+struct AutoIdParams {
+    pub gain: FloatParam,
+
+    #[id = "freq"]
+    pub cutoff_frequency: FloatParam,
+
+    pub bypass: BoolParam,
+
+    #[nested(id_prefix = "flat")]
+    pub flat: FlatParams,
+
+    // This is not a parameter, so it shouldn't get an ID
+    pub num_voices: u32,
+}
+
+impl Default for AutoIdParams {
+    fn default() -> Self {
+        AutoIdParams {
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            cutoff_frequency: FloatParam::new(
+                "Cutoff",
+                1000.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 20_000.0,
+                },
+            ),
+            bypass: BoolParam::new("Bypass", false),
+            flat: FlatParams::default(),
+            num_voices: 16,
+        }
+    }
+}
+
+#[derive(Params)]
+struct ModuleParams {
+    #[id = "enabled"]
+    pub enabled: BoolParam,
+
+    #[id = "gain"]
+    pub gain: FloatParam,
+}
+
+impl ModuleParams {
+    fn new(enabled: bool) -> Self {
+        ModuleParams {
+            enabled: BoolParam::new("Enabled", enabled),
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+        }
+    }
+}
+
+#[derive(Params)]
+struct ModularParams {
+    #[nested(group = "Filter", id_prefix = "filter", enabled = "enabled")]
+    pub filter: ModuleParams,
+
+    #[nested(array, group = "Voice", enabled = "enabled")]
+    pub voices: [ModuleParams; 2],
+}
+
+#[derive(Params)]
+struct NestedModularParams {
+    #[nested(group = "Modules", id_prefix = "modules", enabled = "enabled")]
+    pub modules: ModuleWithSubmodules,
+}
+
+#[derive(Params)]
+struct ModuleWithSubmodules {
+    #[id = "enabled"]
+    pub enabled: BoolParam,
+
+    #[nested(group = "Filter", id_prefix = "filter", enabled = "enabled")]
+    pub filter: ModuleParams,
+}
+
 mod param_order {
     use super::*;
 
@@ -209,6 +287,25 @@ mod param_order {
             ]
         );
     }
+
+    #[test]
+    fn auto_id() {
+        let p = AutoIdParams::default();
+
+        // Fields without an explicit ID use their field name, and non-parameter fields are skipped
+        let param_ids: Vec<String> = p.param_map().into_iter().map(|(id, _, _)| id).collect();
+        assert_eq!(
+            param_ids,
+            [
+                "gain",
+                "freq",
+                "bypass",
+                "flat_one",
+                "flat_two",
+                "flat_three"
+            ]
+        );
+    }
 }
 
 mod param_groups {
@@ -316,3 +413,72 @@ mod param_groups {
         );
     }
 }
+
+mod group_enable {
+    use super::*;
+
+    #[test]
+    fn toggles() {
+        let p = ModularParams {
+            filter: ModuleParams::new(true),
+            voices: [ModuleParams::new(true), ModuleParams::new(false)],
+        };
+
+        let groups: Vec<String> = p
+            .group_enable_params()
+            .into_iter()
+            .map(|(group, _)| group)
+            .collect();
+        assert_eq!(groups, ["Filter", "Voice 1", "Voice 2"]);
+
+        // The toggles point to the nested objects' `enabled` parameters
+        let param_map = p.param_map();
+        let enabled_ptr = |param_id: &str| {
+            param_map
+                .iter()
+                .find(|(id, _, _)| id == param_id)
+                .map(|(_, param_ptr, _)| *param_ptr)
+                .unwrap()
+        };
+        let group_ptrs: Vec<ParamPtr> = p
+            .group_enable_params()
+            .into_iter()
+            .map(|(_, param_ptr)| param_ptr)
+            .collect();
+        assert_eq!(
+            group_ptrs,
+            [
+                enabled_ptr("filter_enabled"),
+                enabled_ptr("enabled_1"),
+                enabled_ptr("enabled_2")
+            ]
+        );
+
+        assert!(p.is_group_enabled("Filter"));
+        assert!(p.is_group_enabled("Voice 1"));
+        assert!(!p.is_group_enabled("Voice 2"));
+        // Groups without a toggle are always enabled, and group names need to match exactly
+        assert!(p.is_group_enabled(""));
+        assert!(p.is_group_enabled("Voice 22"));
+    }
+
+    #[test]
+    fn disabled_parent_disables_children() {
+        let p = NestedModularParams {
+            modules: ModuleWithSubmodules {
+                enabled: BoolParam::new("Enabled", false),
+                filter: ModuleParams::new(true),
+            },
+        };
+
+        let groups: Vec<String> = p
+            .group_enable_params()
+            .into_iter()
+            .map(|(group, _)| group)
+            .collect();
+        assert_eq!(groups, ["Modules", "Modules/Filter"]);
+
+        assert!(!p.is_group_enabled("Modules"));
+        assert!(!p.is_group_enabled("Modules/Filter"));
+    }
+}
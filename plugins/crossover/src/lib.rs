@@ -148,6 +148,7 @@ impl Default for Crossover {
                 sample_rate: 1.0,
                 min_buffer_size: None,
                 max_buffer_size: 0,
+                preferred_buffer_size: None,
                 process_mode: ProcessMode::Realtime,
             },
 
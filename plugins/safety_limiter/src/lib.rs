@@ -130,6 +130,7 @@ impl Default for SafetyLimiter {
                 sample_rate: 1.0,
                 min_buffer_size: None,
                 max_buffer_size: 0,
+                preferred_buffer_size: None,
                 process_mode: ProcessMode::Realtime,
             },
 
@@ -170,6 +170,7 @@ impl Default for SpectralCompressor {
                 sample_rate: 1.0,
                 min_buffer_size: None,
                 max_buffer_size: 0,
+                preferred_buffer_size: None,
                 process_mode: ProcessMode::Realtime,
             },
             sample_rate: Arc::new(AtomicF32::new(1.0)),
@@ -32,6 +32,20 @@ pub struct AudioIOLayout {
     pub names: PortNames,
 }
 
+/// The channel counts for the plugin's active [`AudioIOLayout`]. Returned by
+/// [`ProcessContext::channel_counts()`][crate::prelude::ProcessContext::channel_counts()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelCounts {
+    /// The number of main input channels, or 0 if the layout does not have a main input port.
+    pub main_input: u32,
+    /// The number of main output channels, or 0 if the layout does not have a main output port.
+    pub main_output: u32,
+    /// The number of channels for each auxiliary input port.
+    pub aux_inputs: &'static [NonZeroU32],
+    /// The number of channels for each auxiliary output port.
+    pub aux_outputs: &'static [NonZeroU32],
+}
+
 /// Construct a `NonZeroU32` value at compile time. Equivalent to `NonZeroU32::new(n).unwrap()`.
 pub const fn new_nonzero_u32(n: u32) -> NonZeroU32 {
     match NonZeroU32::new(n) {
@@ -85,6 +99,10 @@ pub struct BufferConfig {
     /// sized buffers up to this size, or between the minimum and the maximum buffer size if both
     /// are set.
     pub max_buffer_size: u32,
+    /// The buffer size the host prefers to use, if it provided a hint. This can be used to size
+    /// scratch buffers or to tune the plugin's latency ahead of time. Buffers can still have any
+    /// size up to the maximum buffer size, so this should not be relied on for correctness.
+    pub preferred_buffer_size: Option<u32>,
     /// The current processing mode. The host will reinitialize the plugin any time this changes.
     pub process_mode: ProcessMode,
 }
@@ -118,6 +136,47 @@ impl AudioIOLayout {
         }
     }
 
+    /// Whether this is an analyzer layout. These layouts have a main input, but no main or
+    /// auxiliary outputs. This is useful for plugins like spectrum analyzers and tuners that only
+    /// display their input:
+    ///
+    /// ```
+    /// # use nih_plug::prelude::*;
+    /// const ANALYZER_LAYOUT: AudioIOLayout = AudioIOLayout {
+    ///     main_input_channels: Some(new_nonzero_u32(2)),
+    ///     ..AudioIOLayout::const_default()
+    /// };
+    /// assert!(ANALYZER_LAYOUT.is_analyzer());
+    /// ```
+    ///
+    /// The main input is passed to [`Plugin::process()`][crate::prelude::Plugin::process()] as the
+    /// main buffer, and any changes the plugin makes to that buffer are discarded. If all of a
+    /// plugin's audio IO layouts are analyzer layouts, then the wrappers add the CLAP `analyzer`
+    /// feature and the VST3 `Analyzer` subcategory automatically. Analyzers that pass their input
+    /// through unchanged should use a layout with the same number of input and output channels
+    /// instead, and add those categories themselves.
+    pub const fn is_analyzer(&self) -> bool {
+        self.main_input_channels.is_some()
+            && self.main_output_channels.is_none()
+            && self.aux_output_ports.is_empty()
+    }
+
+    /// The number of channels for each of this layout's ports.
+    pub fn channel_counts(&self) -> ChannelCounts {
+        ChannelCounts {
+            main_input: self
+                .main_input_channels
+                .map(NonZeroU32::get)
+                .unwrap_or_default(),
+            main_output: self
+                .main_output_channels
+                .map(NonZeroU32::get)
+                .unwrap_or_default(),
+            aux_inputs: self.aux_input_ports,
+            aux_outputs: self.aux_output_ports,
+        }
+    }
+
     /// A descriptive name for the layout. This is taken from `PortNames::layout` if set. Otherwise
     /// it is generated based on the layout.
     pub fn name(&self) -> String {
@@ -202,3 +261,36 @@ impl PortNames {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_counts_match_layout() {
+        const SIDECHAIN_LAYOUT: AudioIOLayout = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            aux_output_ports: &[new_nonzero_u32(2), new_nonzero_u32(6)],
+            names: PortNames::const_default(),
+        };
+        const INSTRUMENT_LAYOUT: AudioIOLayout = AudioIOLayout {
+            main_output_channels: Some(new_nonzero_u32(1)),
+            ..AudioIOLayout::const_default()
+        };
+
+        let counts = SIDECHAIN_LAYOUT.channel_counts();
+        assert_eq!(counts.main_input, 2);
+        assert_eq!(counts.main_output, 2);
+        assert_eq!(counts.aux_inputs, [new_nonzero_u32(1)]);
+        assert_eq!(counts.aux_outputs, [new_nonzero_u32(2), new_nonzero_u32(6)]);
+
+        // Missing main ports are reported as having zero channels
+        let counts = INSTRUMENT_LAYOUT.channel_counts();
+        assert_eq!(counts.main_input, 0);
+        assert_eq!(counts.main_output, 1);
+        assert!(counts.aux_inputs.is_empty());
+        assert!(counts.aux_outputs.is_empty());
+    }
+}
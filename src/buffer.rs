@@ -3,9 +3,11 @@
 use std::marker::PhantomData;
 
 mod blocks;
+mod frames;
 mod samples;
 
 pub use blocks::{Block, BlockChannelsIter, BlocksIter};
+pub use frames::{FrameArray, FramesArrayIter};
 pub use samples::{ChannelSamples, ChannelSamplesIter, SamplesIter};
 
 /// The audio buffers used during processing. This contains the output audio output buffers with the
@@ -20,6 +22,10 @@ pub struct Buffer<'a> {
     /// The number of samples contained within `output_slices`. This needs to be stored separately
     /// to be able to handle 0 channel IO for MIDI-only plugins.
     num_samples: usize,
+    /// The number of silent samples at the end of the buffer that were added to pad it to a
+    /// multiple of [`Plugin::SIMD_BLOCK_MULTIPLE`][crate::prelude::Plugin::SIMD_BLOCK_MULTIPLE].
+    /// These are included in `num_samples`.
+    num_padding_samples: usize,
 
     /// Contains slices for the plugin's outputs. You can't directly create a nested slice from a
     /// pointer to pointers, so this needs to be preallocated in the setup call and kept around
@@ -32,12 +38,24 @@ pub struct Buffer<'a> {
 }
 
 impl<'a> Buffer<'a> {
-    /// Returns the number of samples per channel in this buffer.
+    /// Returns the number of samples per channel in this buffer. This includes the padding added
+    /// when [`Plugin::SIMD_BLOCK_MULTIPLE`][crate::prelude::Plugin::SIMD_BLOCK_MULTIPLE] is set,
+    /// see [`unpadded_samples()`][Self::unpadded_samples()].
     #[inline]
     pub fn samples(&self) -> usize {
         self.num_samples
     }
 
+    /// Returns the number of samples per channel in this buffer that contain the host's audio. This
+    /// is the same as [`samples()`][Self::samples()] unless the buffer has been padded with silence
+    /// because of [`Plugin::SIMD_BLOCK_MULTIPLE`][crate::prelude::Plugin::SIMD_BLOCK_MULTIPLE]. Use
+    /// this to advance smoothers and other time-based state, since they would otherwise run ahead
+    /// of the host's timeline.
+    #[inline]
+    pub fn unpadded_samples(&self) -> usize {
+        self.num_samples - self.num_padding_samples
+    }
+
     /// Returns the number of channels in this buffer.
     #[inline]
     pub fn channels(&self) -> usize {
@@ -50,6 +68,58 @@ impl<'a> Buffer<'a> {
         self.num_samples == 0
     }
 
+    /// Returns true if no sample in any channel has an absolute value above `threshold`, which is
+    /// a linear gain value. Use [`util::db_to_gain()`][crate::util::db_to_gain()] to convert a
+    /// threshold in decibels. A threshold of 0.0 only considers digital silence to be silent. The
+    /// check stops at the first sample above the threshold. Buffers without any samples or
+    /// channels are always silent.
+    #[inline]
+    pub fn is_silent(&self, threshold: f32) -> bool {
+        self.output_slices
+            .iter()
+            .all(|channel| channel.iter().all(|sample| sample.abs() <= threshold))
+    }
+
+    /// Write the sum of all channels to `out` for every sample without modifying the buffer. This
+    /// is useful for analyzers that work on a mono signal. See
+    /// [`average_to_mono()`][Self::average_to_mono()] for a version that divides by the number of
+    /// channels. `out` is filled with zeroes if the buffer does not have any channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not contain exactly [`samples()`][Self::samples()] samples.
+    pub fn sum_to_mono(&self, out: &mut [f32]) {
+        assert_eq!(
+            out.len(),
+            self.num_samples,
+            "The output slice's length does not match the buffer's length"
+        );
+
+        out.fill(0.0);
+        for channel in &self.output_slices {
+            for (out_sample, sample) in out.iter_mut().zip(channel.iter()) {
+                *out_sample += *sample;
+            }
+        }
+    }
+
+    /// The same as [`sum_to_mono()`][Self::sum_to_mono()], but the sum is divided by the number of
+    /// channels. This keeps the level of signals that are the same on every channel intact.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not contain exactly [`samples()`][Self::samples()] samples.
+    pub fn average_to_mono(&self, out: &mut [f32]) {
+        self.sum_to_mono(out);
+
+        if self.output_slices.len() > 1 {
+            let scale = (self.output_slices.len() as f32).recip();
+            for sample in out {
+                *sample *= scale;
+            }
+        }
+    }
+
     /// Obtain the raw audio buffers.
     #[inline]
     pub fn as_slice(&mut self) -> &mut [&'a mut [f32]] {
@@ -74,6 +144,61 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Iterate over the samples, yielding every frame as an array containing one sample for each
+    /// of the `N` channels. This is convenient for stereo or quad processing where all channels
+    /// are processed together, and the arrays can be converted to SIMD vectors directly:
+    ///
+    /// ```ignore
+    /// for mut frame in buffer.iter_frames_array::<2>() {
+    ///     let [left, right] = &mut *frame;
+    ///     let mid = (*left + *right) / 2.0;
+    ///     *left = mid;
+    ///     *right = mid;
+    /// }
+    /// ```
+    ///
+    /// The channels are stored in separate slices, so every frame's samples are copied into an
+    /// array and written back to the buffer when the yielded [`FrameArray`] is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer does not have exactly `N` channels.
+    #[inline]
+    pub fn iter_frames_array<'slice, const N: usize>(
+        &'slice mut self,
+    ) -> FramesArrayIter<'slice, 'a, N> {
+        assert_eq!(
+            self.output_slices.len(),
+            N,
+            "The buffer has {} channels, but frames with {N} channels were requested",
+            self.output_slices.len()
+        );
+
+        FramesArrayIter {
+            buffers: self.output_slices.as_mut_slice(),
+            current_sample: 0,
+            samples_end: self.samples(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Iterate over the samples in a single channel. This is useful when only one of the channels
+    /// needs to be processed, for instance to apply an effect to only the right channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is out of bounds.
+    #[inline]
+    pub fn iter_channel_samples_mut(&mut self, channel: usize) -> impl Iterator<Item = &mut f32> {
+        assert!(
+            channel < self.output_slices.len(),
+            "Channel index {channel} is out of bounds for a buffer with {} channels",
+            self.output_slices.len()
+        );
+
+        self.output_slices[channel].iter_mut()
+    }
+
     /// Iterate over the buffer in blocks with the specified maximum size. The ideal maximum block
     /// size depends on the plugin in question, but 64 or 128 samples works for most plugins. Since
     /// the buffer's total size may not be cleanly divisible by the maximum size, the returned
@@ -108,6 +233,81 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Replace every sample in every channel with the result of `f(sample)`. This is convenient for
+    /// simple stateless processing like applying a gain or waveshaping:
+    ///
+    /// ```ignore
+    /// buffer.map_samples(|sample| sample.tanh());
+    /// ```
+    ///
+    /// Use [`map_channel_samples()`][Self::map_channel_samples()] if the closure needs to know
+    /// which channel it's processing.
+    #[inline]
+    pub fn map_samples(&mut self, mut f: impl FnMut(f32) -> f32) {
+        for channel in self.output_slices.iter_mut() {
+            for sample in channel.iter_mut() {
+                *sample = f(*sample);
+            }
+        }
+    }
+
+    /// The same as [`map_samples()`][Self::map_samples()], but the closure also receives the index
+    /// of the channel the sample belongs to as its first argument. The channels are processed one
+    /// after the other.
+    #[inline]
+    pub fn map_channel_samples(&mut self, mut f: impl FnMut(usize, f32) -> f32) {
+        for (channel_idx, channel) in self.output_slices.iter_mut().enumerate() {
+            for sample in channel.iter_mut() {
+                *sample = f(channel_idx, *sample);
+            }
+        }
+    }
+
+    /// Multiply every channel by a per-sample gain, for instance a gain envelope computed from a
+    /// [`Smoother`][crate::prelude::Smoother] with
+    /// [`next_block_exact()`][crate::prelude::Smoother::next_block_exact()] or from a modulator.
+    /// The sample at index `n` in each channel is multiplied by `gains[n]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `gains` does not match the number of samples in the buffer.
+    #[inline]
+    pub fn apply_gain_slice(&mut self, gains: &[f32]) {
+        assert_eq!(
+            gains.len(),
+            self.num_samples,
+            "The gain slice needs to contain a gain for every sample in the buffer"
+        );
+
+        for channel in self.output_slices.iter_mut() {
+            for (sample, gain) in channel.iter_mut().zip(gains) {
+                *sample *= gain;
+            }
+        }
+    }
+
+    /// Negate every sample in every channel. This flips the signal's polarity.
+    #[inline]
+    pub fn negate(&mut self) {
+        self.map_samples(|sample| -sample);
+    }
+
+    /// Replace every sample in every channel with its absolute value. This is a full wave
+    /// rectifier.
+    #[inline]
+    pub fn abs(&mut self) {
+        self.map_samples(f32::abs);
+    }
+
+    /// Reverse the order of the samples within each channel. This only reverses the current
+    /// block, so reverse effects still need to buffer the audio themselves.
+    #[inline]
+    pub fn reverse(&mut self) {
+        for channel in self.output_slices.iter_mut() {
+            channel.reverse();
+        }
+    }
+
     /// Set the slices in the raw output slice vector. This vector needs to be resized to match the
     /// number of output channels during the plugin's initialization. Then during audio processing,
     /// these slices should be updated to point to the plugin's audio buffers. The `num_samples`
@@ -124,6 +324,7 @@ impl<'a> Buffer<'a> {
         update: impl FnOnce(&mut Vec<&'a mut [f32]>),
     ) {
         self.num_samples = num_samples;
+        self.num_padding_samples = 0;
         update(&mut self.output_slices);
 
         #[cfg(debug_assertions)]
@@ -131,6 +332,60 @@ impl<'a> Buffer<'a> {
             nih_debug_assert_eq!(slice.len(), num_samples);
         }
     }
+
+    /// Mark the last `num_padding_samples` samples set with [`set_slices()`][Self::set_slices()]
+    /// as padding. This is reset again when the slices are set.
+    pub(crate) fn set_padding_samples(&mut self, num_padding_samples: usize) {
+        nih_debug_assert!(num_padding_samples <= self.num_samples);
+        self.num_padding_samples = num_padding_samples.min(self.num_samples);
+    }
+}
+
+/// Run `f` with a [`Buffer`] whose channels point to `data`. Used in the tests for the utilities
+/// that process [`Buffer`]s.
+#[cfg(test)]
+pub(crate) fn with_test_buffer<R>(data: &mut [Vec<f32>], f: impl FnOnce(&mut Buffer) -> R) -> R {
+    let num_samples = data.first().map_or(0, Vec::len);
+    let mut buffer = Buffer::default();
+    unsafe {
+        buffer.set_slices(num_samples, |slices| {
+            *slices = data.iter_mut().map(|c| c.as_mut_slice()).collect()
+        });
+    }
+
+    f(&mut buffer)
+}
+
+/// Like [`with_test_buffer()`], but `data` is split into consecutive blocks with the sizes from
+/// `block_sizes`, the same way a host would process it. Stops once all samples have been processed
+/// or when `block_sizes` runs out.
+#[cfg(test)]
+pub(crate) fn process_test_blocks(
+    data: &mut [Vec<f32>],
+    block_sizes: impl IntoIterator<Item = usize>,
+    mut f: impl FnMut(&mut Buffer),
+) {
+    let num_samples = data.first().map_or(0, Vec::len);
+    let mut block_start = 0;
+    for block_size in block_sizes {
+        if block_start >= num_samples {
+            break;
+        }
+
+        let block_end = (block_start + block_size).min(num_samples);
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(block_end - block_start, |slices| {
+                *slices = data
+                    .iter_mut()
+                    .map(|c| &mut c[block_start..block_end])
+                    .collect()
+            });
+        }
+
+        f(&mut buffer);
+        block_start = block_end;
+    }
 }
 
 #[cfg(any(miri, test))]
@@ -165,6 +420,255 @@ mod miri {
         assert_eq!(real_buffers[0][0], 0.003);
     }
 
+    #[test]
+    fn map_samples_soft_clip() {
+        let mut real_buffers = vec![vec![-4.0, -0.5, 0.0, 0.5, 4.0]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(5, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        buffer.map_samples(|sample| sample.tanh());
+
+        for channel in &real_buffers {
+            for (sample, input) in channel.iter().zip([-4.0f32, -0.5, 0.0, 0.5, 4.0]) {
+                assert_eq!(*sample, input.tanh());
+                assert!(sample.abs() < 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn map_channel_samples() {
+        let mut real_buffers = vec![vec![1.0; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        // Mute the right channel
+        buffer
+            .map_channel_samples(|channel_idx, sample| if channel_idx == 0 { sample } else { 0.0 });
+
+        assert_eq!(real_buffers[0], [1.0; 4]);
+        assert_eq!(real_buffers[1], [0.0; 4]);
+    }
+
+    #[test]
+    fn iter_channel_samples_mut() {
+        let mut real_buffers = vec![vec![0.5; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        // Only process the right channel
+        for (i, sample) in buffer.iter_channel_samples_mut(1).enumerate() {
+            *sample *= i as f32;
+        }
+
+        assert_eq!(real_buffers[0], [0.5; 4]);
+        assert_eq!(real_buffers[1], [0.0, 0.5, 1.0, 1.5]);
+    }
+
+    #[test]
+    fn is_silent() {
+        let mut real_buffers = vec![vec![0.0; 64]; 2];
+        real_buffers[0][10] = 1e-6;
+        real_buffers[1][40] = -0.01;
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(64, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        // A single sample in the second channel is above the threshold
+        assert!(!buffer.is_silent(0.0));
+        assert!(!buffer.is_silent(0.001));
+        assert!(buffer.is_silent(0.01));
+
+        buffer.as_slice()[1][40] = 0.0;
+        assert!(!buffer.is_silent(0.0));
+        assert!(buffer.is_silent(0.001));
+
+        assert!(Buffer::default().is_silent(0.0));
+    }
+
+    #[test]
+    fn sum_to_mono() {
+        let mut real_buffers = vec![vec![0.5, -0.25, 1.0, 0.0], vec![0.25, 0.25, -1.0, 0.5]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        let mut mono = [1.0; 4];
+        buffer.sum_to_mono(&mut mono);
+        assert_eq!(mono, [0.75, 0.0, 0.0, 0.5]);
+        buffer.average_to_mono(&mut mono);
+        assert_eq!(mono, [0.375, 0.0, 0.0, 0.25]);
+
+        // The buffer itself is left untouched
+        assert_eq!(real_buffers[0], [0.5, -0.25, 1.0, 0.0]);
+        assert_eq!(real_buffers[1], [0.25, 0.25, -1.0, 0.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match")]
+    fn sum_to_mono_length_mismatch() {
+        let mut real_buffers = vec![vec![0.0; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        buffer.sum_to_mono(&mut [0.0; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn iter_channel_samples_mut_out_of_bounds() {
+        let mut real_buffers = vec![vec![0.0; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        let _ = buffer.iter_channel_samples_mut(2);
+    }
+
+    #[test]
+    fn apply_gain_slice_ramp() {
+        let mut real_buffers = vec![vec![1.0; 5], vec![-2.0; 5]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(5, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        buffer.apply_gain_slice(&[0.0, 0.25, 0.5, 0.75, 1.0]);
+
+        assert_eq!(real_buffers[0], [0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(real_buffers[1], [-0.0, -0.5, -1.0, -1.5, -2.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "gain for every sample")]
+    fn apply_gain_slice_wrong_length() {
+        let mut real_buffers = vec![vec![0.0; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        buffer.apply_gain_slice(&[1.0; 3]);
+    }
+
+    #[test]
+    fn negate_abs_reverse() {
+        let mut real_buffers = vec![vec![-1.0, 0.5, -0.25], vec![2.0, -3.0, 0.0]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(3, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        buffer.negate();
+        assert_eq!(buffer.as_slice_immutable()[0], [1.0, -0.5, 0.25]);
+        assert_eq!(buffer.as_slice_immutable()[1], [-2.0, 3.0, -0.0]);
+
+        buffer.reverse();
+        assert_eq!(buffer.as_slice_immutable()[0], [0.25, -0.5, 1.0]);
+        assert_eq!(buffer.as_slice_immutable()[1], [-0.0, 3.0, -2.0]);
+
+        buffer.abs();
+        assert_eq!(real_buffers[0], [0.25, 0.5, 1.0]);
+        assert_eq!(real_buffers[1], [0.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn iter_frames_array_matches_channels() {
+        let input: Vec<Vec<f32>> = (0..2)
+            .map(|channel| {
+                (0..64)
+                    .map(|i| ((i * 5 + channel * 3) % 7) as f32 - 3.0)
+                    .collect()
+            })
+            .collect();
+
+        // A mid/side width adjustment, once processed per channel and once using stereo frames
+        let width = 0.5;
+        let mut channel_buffers = input.clone();
+        let (left, right) = channel_buffers.split_at_mut(1);
+        for (left, right) in left[0].iter_mut().zip(right[0].iter_mut()) {
+            let mid = (*left + *right) / 2.0;
+            let side = (*left - *right) / 2.0 * width;
+            *left = mid + side;
+            *right = mid - side;
+        }
+
+        let mut real_buffers = input;
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(64, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        assert_eq!(buffer.iter_frames_array::<2>().len(), 64);
+        for mut frame in buffer.iter_frames_array::<2>() {
+            let [left, right] = *frame;
+            let mid = (left + right) / 2.0;
+            let side = (left - right) / 2.0 * width;
+            *frame = [mid + side, mid - side];
+        }
+
+        assert_eq!(real_buffers, channel_buffers);
+    }
+
+    #[test]
+    #[should_panic(expected = "frames with 4 channels")]
+    fn iter_frames_array_channel_mismatch() {
+        let mut real_buffers = vec![vec![0.0; 4]; 2];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                let (first_channel, other_channels) = real_buffers.split_at_mut(1);
+                *output_slices = vec![&mut first_channel[0], &mut other_channels[0]];
+            })
+        };
+
+        let _ = buffer.iter_frames_array::<4>();
+    }
+
     #[test]
     fn repeated_slices() {
         let mut real_buffers = vec![vec![0.0; 512]; 2];
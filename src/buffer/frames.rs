@@ -0,0 +1,98 @@
+//! Per-sample iterators that yield all channels as a fixed-size array.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// An iterator over all samples in a buffer with exactly `N` channels, yielding a [`FrameArray`]
+/// for every sample. Created using
+/// [`Buffer::iter_frames_array()`][super::Buffer::iter_frames_array()].
+pub struct FramesArrayIter<'slice, 'sample: 'slice, const N: usize> {
+    /// The raw output buffers.
+    pub(super) buffers: *mut [&'sample mut [f32]],
+    pub(super) current_sample: usize,
+    /// The last sample index to iterate over plus one.
+    pub(super) samples_end: usize,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+}
+
+/// A single frame of a buffer, containing one sample for each of the `N` channels. This
+/// dereferences to a `[f32; N]` array. Since the buffer stores every channel in a separate slice,
+/// the channels' samples are copied into the array when the frame is created, and any changes
+/// made to the array are written back to the buffer when the frame is dropped.
+pub struct FrameArray<'slice, 'sample: 'slice, const N: usize> {
+    /// The raw output buffers.
+    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) current_sample: usize,
+    /// The samples for this frame, written back to `buffers` on drop.
+    pub(self) frame: [f32; N],
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+}
+
+impl<'slice, 'sample, const N: usize> Iterator for FramesArrayIter<'slice, 'sample, N> {
+    type Item = FrameArray<'slice, 'sample, N>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_sample < self.samples_end {
+            // SAFETY: The number of channels has been checked when creating the iterator, and the
+            //         sample index is in bounds
+            let frame = std::array::from_fn(|channel_idx| unsafe {
+                *(*self.buffers)
+                    .get_unchecked(channel_idx)
+                    .get_unchecked(self.current_sample)
+            });
+            let frame = FrameArray {
+                buffers: self.buffers,
+                current_sample: self.current_sample,
+                frame,
+                _marker: self._marker,
+            };
+
+            self.current_sample += 1;
+
+            Some(frame)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.samples_end - self.current_sample;
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for FramesArrayIter<'_, '_, N> {}
+
+impl<const N: usize> Deref for FrameArray<'_, '_, N> {
+    type Target = [f32; N];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.frame
+    }
+}
+
+impl<const N: usize> DerefMut for FrameArray<'_, '_, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.frame
+    }
+}
+
+impl<const N: usize> Drop for FrameArray<'_, '_, N> {
+    #[inline]
+    fn drop(&mut self) {
+        for (channel_idx, sample) in self.frame.iter().enumerate() {
+            // SAFETY: The bounds have already been checked, and no two frames refer to the same
+            //         sample
+            unsafe {
+                *(*self.buffers)
+                    .get_unchecked_mut(channel_idx)
+                    .get_unchecked_mut(self.current_sample) = *sample;
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Different contexts the plugin can use to make callbacks to the host in different...contexts.
 
+use std::ffi::c_void;
 use std::fmt::Display;
 
 pub mod gui;
@@ -18,6 +19,32 @@ pub enum PluginApi {
     Vst3,
 }
 
+/// A raw pointer to the host's own context object for the current plugin API. This can be used to
+/// interact with host-specific extensions that NIH-plug does not expose. Obtained through
+/// [`InitContext::raw_host_handle()`][init::InitContext::raw_host_handle()],
+/// [`ProcessContext::raw_host_handle()`][process::ProcessContext::raw_host_handle()], or
+/// [`GuiContext::raw_host_handle()`][gui::GuiContext::raw_host_handle()].
+///
+/// # Safety
+///
+/// These pointers are owned by the host and NIH-plug's wrappers. Anything you do with them bypasses
+/// all of NIH-plug's bookkeeping. You must adhere to the plugin API's threading and lifetime rules
+/// yourself, you must not store the pointer beyond the lifetime of the plugin instance, and you
+/// must not use it to do anything that interferes with the wrapper's own communication with the
+/// host (e.g. changing parameter values, latency, or state behind the wrapper's back).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawHostHandle {
+    /// A `*const clap_host` pointer, as passed to the plugin's factory when the plugin instance was
+    /// created. This pointer stays valid for as long as the plugin instance exists.
+    Clap(*const c_void),
+    /// The `FUnknown*` host context passed to `IPluginBase::initialize()`. Interfaces like
+    /// `IHostApplication` can be queried from this object. This will be a null pointer if the host
+    /// has not yet initialized the plugin, or if it has already been terminated.
+    Vst3(*mut c_void),
+    /// The standalone target does not have a host.
+    Standalone,
+}
+
 impl Display for PluginApi {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -2,8 +2,11 @@
 
 use std::sync::Arc;
 
-use super::PluginApi;
-use crate::prelude::{Param, ParamPtr, Plugin, PluginState};
+use super::{PluginApi, RawHostHandle};
+use crate::prelude::{ChainHint, Enum, EnumParam, Param, ParamPtr, Plugin, PluginState, Transport};
+
+#[cfg(test)]
+pub(crate) mod mock;
 
 /// Callbacks the plugin can make when the user interacts with its GUI such as updating parameter
 /// values. This is passed to the plugin during [`Editor::spawn()`][crate::prelude::Editor::spawn()]. All of
@@ -54,6 +57,27 @@ pub trait GuiContext: Send + Sync + 'static {
     /// mostly marked as unsafe for API reasons.
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr);
 
+    /// Returns who changed a parameter's value since the last time this function was called for
+    /// that parameter, and resets that information. Returns `None` if the parameter has not been
+    /// changed in the meantime. Create a [`ParamSetter`] and use
+    /// [`ParamSetter::take_change_source()`] instead for a safe, user friendly API.
+    ///
+    /// # Safety
+    ///
+    /// The implementing function still needs to check if `param` actually exists. This function is
+    /// mostly marked as unsafe for API reasons.
+    unsafe fn raw_take_param_change_source(&self, param: ParamPtr) -> Option<ParamChangeSource>;
+
+    /// Returns the host's current mapping and automation indication for a parameter, or `None` if
+    /// the host has not sent any. Create a [`ParamSetter`] and use
+    /// [`ParamSetter::indication()`] instead for a safe, user friendly API.
+    ///
+    /// # Safety
+    ///
+    /// The implementing function still needs to check if `param` actually exists. This function is
+    /// mostly marked as unsafe for API reasons.
+    unsafe fn raw_param_indication(&self, param: ParamPtr) -> Option<ParamIndication>;
+
     /// Serialize the plugin's current state to a serde-serializable object. Useful for implementing
     /// preset handling within a plugin's GUI.
     fn get_state(&self) -> PluginState;
@@ -63,6 +87,138 @@ pub trait GuiContext: Send + Sync + 'static {
     /// host. If the plugin is currently processing audio, then the parameter values will be
     /// restored at the end of the current processing cycle.
     fn set_state(&self, state: PluginState);
+
+    /// Get the index of the factory preset that was last loaded with
+    /// [`set_preset()`][Self::set_preset()], as an index into
+    /// [`Plugin::factory_presets()`][crate::prelude::Plugin::factory_presets()]. Returns `None` if
+    /// no preset has been loaded, or if another state has been loaded since then, for instance by
+    /// the host or through [`set_state()`][Self::set_state()]. Changing parameters after loading a
+    /// preset keeps the preset selected.
+    fn current_preset(&self) -> Option<usize>;
+
+    /// Load the factory preset at `index` using the same mechanism as
+    /// [`set_state()`][Self::set_state()], and make it the
+    /// [current preset][Self::current_preset()]. If `index` is out of range, then this logs a
+    /// message, returns `false`, and leaves both the plugin's state and the current preset alone.
+    fn set_preset(&self, index: usize) -> bool;
+
+    /// Get a value previously stored using [`set_editor_state()`][Self::set_editor_state()]. Use
+    /// this when the editor opens to restore things like the last selected tab.
+    fn editor_state(&self, key: &str) -> Option<String>;
+
+    /// Store a piece of the editor's own state, like the currently selected tab, the scroll
+    /// position, or the GUI's theme. These values are saved and restored together with the
+    /// plugin's state in [`PluginState::gui`], so they persist with the project and between
+    /// opening and closing the editor. Unlike parameters and `#[persist]` fields they are not
+    /// exposed to the host and don't need to be part of the plugin's
+    /// [`Params`][crate::prelude::Params]. The value can be anything, for instance a JSON string
+    /// created with serde. Passing `None` removes the key.
+    fn set_editor_state(&self, key: &str, value: Option<String>);
+
+    /// Get a snapshot of the host's transport information from the most recent processing cycle,
+    /// for instance to draw a tempo synced LFO. This is updated by the audio thread at the start
+    /// of every block, so it lags behind the audio thread by up to one block. Returns `None` if
+    /// the plugin has not processed any audio yet.
+    fn transport(&self) -> Option<Transport>;
+
+    /// Tell the host that the plugin's state has changed in a way the host can't know about, for
+    /// instance because a sample was loaded or because a persistent field was changed from the
+    /// GUI. The host can then mark the project or preset as modified. Parameter changes don't need
+    /// this since the host already tracks those. The notification is sent on the main thread, and
+    /// repeated calls before the host has been notified are coalesced into a single notification.
+    ///
+    /// In CLAP this uses `clap_host_state::mark_dirty()`, and in VST3 this uses
+    /// `IComponentHandler2::setDirty()`. Hosts that support neither ignore this. The standalone
+    /// target also ignores this.
+    fn mark_state_dirty(&self);
+
+    /// Ask the host to rescan all of the plugin's parameters, for instance after loading a preset
+    /// that changed parameter values in a way that leaves the host's automation or generic UI
+    /// stale. The request is sent on the main thread, and repeated calls before the host has been
+    /// notified are coalesced into a single request.
+    ///
+    /// In CLAP this uses `clap_host_params::rescan()` with `CLAP_PARAM_RESCAN_ALL` while the plugin
+    /// is deactivated. Since that flag cannot be used while the plugin is activated, the values,
+    /// value texts, and parameter infos are rescanned instead in that case. In VST3 this restarts
+    /// the component with the `kParamValuesChanged` and `kParamTitlesChanged` flags. The
+    /// standalone target ignores this.
+    fn request_param_rescan(&self);
+
+    /// Get the same best-effort hint about where the plugin has been inserted in the host's signal
+    /// flow as [`InitContext::chain_hint()`][crate::prelude::InitContext::chain_hint()]. This
+    /// always returns the most recent information from the host, so it also reflects changes to
+    /// the track and information VST3 hosts only send after the plugin has been initialized.
+    fn chain_hint(&self) -> ChainHint;
+
+    /// Get a raw pointer to the host's context object for the current plugin API. This is an escape
+    /// hatch for interacting with host-specific functionality that NIH-plug does not (yet) provide
+    /// an abstraction for. You should not need this in normal use.
+    ///
+    /// # Safety
+    ///
+    /// See the safety section on [`RawHostHandle`][crate::context::RawHostHandle].
+    unsafe fn raw_host_handle(&self) -> RawHostHandle;
+}
+
+/// Where a parameter change came from. See [`ParamSetter::take_change_source()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamChangeSource {
+    /// The parameter was changed by the host, for instance because it's playing back or writing
+    /// automation, or because the user changed the value in the host's generic UI.
+    Host,
+    /// The parameter was changed by the plugin's editor through a [`ParamSetter`].
+    Editor,
+}
+
+/// Information from the host about how a parameter is mapped to a hardware controller or
+/// automated, so the editor can show an indicator next to the parameter's control. See
+/// [`ParamSetter::indication()`]. This is only supported by CLAP, using the `param-indication`
+/// extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamIndication {
+    /// The controller the parameter is mapped to, if it's mapped to one.
+    pub mapping: Option<ParamMapping>,
+    /// The parameter's automation state.
+    pub automation: ParamAutomationState,
+    /// The color the host uses to show the automation state, if it specified one.
+    pub automation_color: Option<IndicationColor>,
+}
+
+/// A host-side mapping of a parameter to a physical controller. Part of a [`ParamIndication`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamMapping {
+    /// The color the host uses to show the mapping, if it specified one.
+    pub color: Option<IndicationColor>,
+    /// A short label describing the mapping, like the controller's name.
+    pub label: String,
+    /// A longer description of the mapping.
+    pub description: String,
+}
+
+/// A parameter's automation state as reported by the host. Part of a [`ParamIndication`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamAutomationState {
+    /// The parameter has no automation.
+    #[default]
+    None,
+    /// The parameter has automation, but it's not currently playing.
+    Present,
+    /// The host is playing back the parameter's automation.
+    Playing,
+    /// The host is recording automation for the parameter.
+    Recording,
+    /// The parameter has automation, but it's been overridden by the user.
+    Overriding,
+}
+
+/// An RGBA color sent by the host as part of a [`ParamIndication`] or a
+/// [`ChainHint`][crate::prelude::ChainHint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicationColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
 }
 
 /// An way to run background tasks from the plugin's GUI, equivalent to the
@@ -180,4 +336,139 @@ impl<'a> ParamSetter<'a> {
     pub fn end_set_parameter<P: Param>(&self, param: &P) {
         unsafe { self.raw_context.raw_end_set_parameter(param.as_ptr()) };
     }
+
+    /// Set an enum parameter to the variant with the specified name as a single automation gesture.
+    /// The name is matched against the variants' display names from [`Enum::variants()`], and
+    /// against their stable IDs from [`Enum::ids()`] if the enum has those. This is useful for
+    /// custom controllers and scripting interfaces that refer to variants by name. Returns `false`
+    /// and leaves the parameter alone if there is no variant with that name.
+    ///
+    /// Unlike [`set_parameter()`][Self::set_parameter()], this calls
+    /// [`begin_set_parameter()`][Self::begin_set_parameter()] and
+    /// [`end_set_parameter()`][Self::end_set_parameter()] itself.
+    pub fn set_enum_parameter_by_name<T: Enum + PartialEq + 'static>(
+        &self,
+        param: &EnumParam<T>,
+        name: &str,
+    ) -> bool {
+        let index = T::variants()
+            .iter()
+            .position(|variant| *variant == name)
+            .or_else(|| T::ids()?.iter().position(|id| *id == name));
+
+        match index {
+            Some(index) => self.set_enum_parameter_by_index(param, index),
+            None => false,
+        }
+    }
+
+    /// Set an enum parameter to the variant with the specified index in [`Enum::variants()`] as a
+    /// single automation gesture, like
+    /// [`set_enum_parameter_by_name()`][Self::set_enum_parameter_by_name()]. Returns `false` and
+    /// leaves the parameter alone if the index is out of range.
+    pub fn set_enum_parameter_by_index<T: Enum + PartialEq + 'static>(
+        &self,
+        param: &EnumParam<T>,
+        index: usize,
+    ) -> bool {
+        if index >= T::variants().len() {
+            return false;
+        }
+
+        self.begin_set_parameter(param);
+        self.set_parameter(param, T::from_index(index));
+        self.end_set_parameter(param);
+
+        true
+    }
+
+    /// Returns who last changed the parameter's value since the previous call to this function for
+    /// the same parameter, or `None` if the value has not changed in the meantime. This can be
+    /// polled once per frame to show a knob as being controlled by the host, for instance when the
+    /// host is writing or playing back automation, and to temporarily disable editing it from the
+    /// GUI.
+    ///
+    /// # Note
+    ///
+    /// With VST3, changes made from the editor may be sent back to the plugin by the host. These
+    /// echoed changes are still reported as [`ParamChangeSource::Editor`], as long as the host does
+    /// not change the value in between.
+    pub fn take_change_source<P: Param>(&self, param: &P) -> Option<ParamChangeSource> {
+        unsafe {
+            self.raw_context
+                .raw_take_param_change_source(param.as_ptr())
+        }
+    }
+
+    /// Returns the host's current mapping and automation indication for the parameter, or `None`
+    /// if the host has not sent any. The editor can use this to for instance draw a colored ring
+    /// around a knob that's mapped to a hardware controller. This is only supported by CLAP, and
+    /// it always returns `None` for the other plugin APIs. The host can change the indication at
+    /// any time, so this should be polled when redrawing the editor.
+    pub fn indication<P: Param>(&self, param: &P) -> Option<ParamIndication> {
+        unsafe { self.raw_context.raw_param_indication(param.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::mock::MockGuiContext;
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Waveform {
+        Sine,
+        Triangle,
+        Saw,
+    }
+
+    // The derive macro can't be used from within NIH-plug itself
+    impl Enum for Waveform {
+        fn variants() -> &'static [&'static str] {
+            &["Sine", "Triangle", "Sawtooth"]
+        }
+
+        fn ids() -> Option<&'static [&'static str]> {
+            Some(&["sine", "triangle", "saw"])
+        }
+
+        fn to_index(self) -> usize {
+            self as usize
+        }
+
+        fn from_index(index: usize) -> Self {
+            match index {
+                1 => Waveform::Triangle,
+                2 => Waveform::Saw,
+                _ => Waveform::Sine,
+            }
+        }
+    }
+
+    #[test]
+    fn set_enum_by_name_and_index() {
+        let param = EnumParam::new("Waveform", Waveform::Sine);
+        let context = MockGuiContext::default();
+        let setter = ParamSetter::new(&context);
+
+        assert!(setter.set_enum_parameter_by_name(&param, "Sawtooth"));
+        assert_eq!(param.value(), Waveform::Saw);
+        assert_eq!(param.unmodulated_normalized_value(), 1.0);
+
+        // Stable IDs work too
+        assert!(setter.set_enum_parameter_by_name(&param, "triangle"));
+        assert_eq!(param.value(), Waveform::Triangle);
+
+        assert!(setter.set_enum_parameter_by_index(&param, 0));
+        assert_eq!(param.value(), Waveform::Sine);
+
+        // Unknown variants don't touch the parameter and don't start a gesture
+        assert!(!setter.set_enum_parameter_by_name(&param, "Square"));
+        assert!(!setter.set_enum_parameter_by_index(&param, 3));
+        assert_eq!(param.value(), Waveform::Sine);
+        assert_eq!(context.gestures_started.load(Ordering::SeqCst), 3);
+        assert_eq!(context.gestures_ended.load(Ordering::SeqCst), 3);
+    }
 }
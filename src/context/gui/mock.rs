@@ -0,0 +1,88 @@
+//! A [`GuiContext`] for the crate's own tests.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{GuiContext, ParamChangeSource, ParamIndication};
+use crate::context::{PluginApi, RawHostHandle};
+use crate::prelude::{ChainHint, ParamPtr, PluginState, Transport};
+
+/// Applies parameter changes immediately, and counts the number of changes and gestures.
+#[derive(Default)]
+pub(crate) struct MockGuiContext {
+    pub changes: AtomicUsize,
+    pub gestures_started: AtomicUsize,
+    pub gestures_ended: AtomicUsize,
+}
+
+impl GuiContext for MockGuiContext {
+    fn plugin_api(&self) -> PluginApi {
+        PluginApi::Standalone
+    }
+
+    fn request_resize(&self) -> bool {
+        false
+    }
+
+    unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
+        self.gestures_started.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        param.set_normalized_value(normalized);
+        self.changes.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {
+        self.gestures_ended.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe fn raw_take_param_change_source(&self, _param: ParamPtr) -> Option<ParamChangeSource> {
+        None
+    }
+
+    unsafe fn raw_param_indication(&self, _param: ParamPtr) -> Option<ParamIndication> {
+        None
+    }
+
+    fn get_state(&self) -> PluginState {
+        PluginState {
+            version: String::new(),
+            params: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            gui: BTreeMap::new(),
+        }
+    }
+
+    fn set_state(&self, _state: PluginState) {}
+
+    fn current_preset(&self) -> Option<usize> {
+        None
+    }
+
+    fn set_preset(&self, _index: usize) -> bool {
+        false
+    }
+
+    fn editor_state(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    fn set_editor_state(&self, _key: &str, _value: Option<String>) {}
+
+    fn transport(&self) -> Option<Transport> {
+        None
+    }
+
+    fn mark_state_dirty(&self) {}
+
+    fn request_param_rescan(&self) {}
+
+    fn chain_hint(&self) -> ChainHint {
+        ChainHint::default()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Standalone
+    }
+}
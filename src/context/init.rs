@@ -1,7 +1,63 @@
 //! A context passed during plugin initialization.
 
-use super::PluginApi;
-use crate::prelude::Plugin;
+use super::{PluginApi, RawHostHandle};
+use crate::prelude::{IndicationColor, Plugin};
+
+/// A best-effort hint about where the plugin is located in the host's signal flow, returned by
+/// [`InitContext::chain_hint()`]. This should only be used to pick sensible defaults, for instance
+/// to start a metering plugin inserted on the master track in a different mode. The plugin should
+/// still work as expected if the hint is wrong or missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainHint {
+    /// The kind of track the plugin has been inserted on.
+    pub track_kind: TrackKind,
+    /// The track's name, if the host provides it.
+    pub track_name: Option<String>,
+    /// The track's number of audio channels, if the host provides it.
+    pub track_channel_count: Option<u32>,
+    /// The track's color, if the host provides it. Useful for theming the editor to match the
+    /// track.
+    pub track_color: Option<IndicationColor>,
+}
+
+/// The kind of track a plugin has been inserted on. Part of a [`ChainHint`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackKind {
+    /// The host did not provide any information about the track.
+    #[default]
+    Unknown,
+    /// A regular audio or instrument track.
+    Track,
+    /// A bus or group track that sums other tracks.
+    Bus,
+    /// A return or send track.
+    Return,
+    /// The master track. Plugins on this track are usually the last thing in the signal chain.
+    Master,
+}
+
+/// A callback for a timer registered through [`InitContext::register_host_timer()`]. This is
+/// called on the host's main thread.
+pub type HostTimerCallback = Box<dyn FnMut() + Send>;
+
+/// A callback for a file descriptor registered through [`InitContext::register_posix_fd()`]. This
+/// is called on the host's main thread with the events that occurred on the file descriptor.
+pub type PosixFdCallback = Box<dyn FnMut(PosixFdFlags) + Send>;
+
+bitflags::bitflags! {
+    /// The events a file descriptor registered through [`InitContext::register_posix_fd()`] should
+    /// be polled for. These have the same values as CLAP's `CLAP_POSIX_FD_*` flags.
+    #[repr(transparent)]
+    #[derive(Default)]
+    pub struct PosixFdFlags: u32 {
+        /// The file descriptor can be read from.
+        const READ = 1 << 0;
+        /// The file descriptor can be written to.
+        const WRITE = 1 << 1;
+        /// An error occurred on the file descriptor.
+        const ERROR = 1 << 2;
+    }
+}
 
 /// Callbacks the plugin can make while it is being initialized. This is passed to the plugin during
 /// [`Plugin::initialize()`][crate::plugin::Plugin::initialize()].
@@ -34,4 +90,53 @@ pub trait InitContext<P: Plugin> {
     /// runtime allows the host to better optimize polyphonic modulation, or to switch to strictly
     /// monophonic modulation when dropping the capacity down to 1.
     fn set_current_voice_capacity(&self, capacity: u32);
+
+    /// Get a best-effort hint about where the plugin has been inserted in the host's signal flow.
+    /// Neither CLAP nor VST3 tell the plugin where it's located in a plugin chain, so this is a
+    /// heuristic based on the track information the host provides. Every field is unknown if the
+    /// host does not provide that information. This uses CLAP's `track-info` extension and VST3's
+    /// `IInfoListener` interface. VST3 hosts only provide the track's name and color, and they
+    /// usually only do so after the plugin has been initialized. In that case this returns an
+    /// unknown hint, and the up to date hint can be queried later through
+    /// [`GuiContext::chain_hint()`][crate::prelude::GuiContext::chain_hint()].
+    fn chain_hint(&self) -> ChainHint;
+
+    /// Request `count` scratch buffers with enough room for the maximum block size from the
+    /// [`BufferConfig`][crate::prelude::BufferConfig]. The wrapper allocates these buffers once
+    /// after [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] returns when the plugin
+    /// gets activated, and they can then be accessed through
+    /// [`ProcessContext::scratch_buffers()`][crate::prelude::ProcessContext::scratch_buffers()]
+    /// without the plugin needing to allocate its own storage. Calling this again replaces the
+    /// previous request. Requests made when the plugin gets reinitialized after loading state
+    /// only take effect the next time the plugin is activated.
+    fn request_scratch_buffers(&self, count: usize);
+
+    /// Register a timer with the host's main thread event loop that calls `callback` every
+    /// `period_ms` milliseconds. This is useful for plugins that need to do periodic background
+    /// work like polling a network connection or watching files without having an editor open.
+    /// The host may adjust the period. This uses CLAP's `timer-support` extension. Returns `false`
+    /// if the host or plugin API does not support this, in which case the plugin needs to spawn
+    /// its own thread instead.
+    ///
+    /// Registrations can only be made when the plugin gets activated, and they stay active until
+    /// the plugin is deactivated again. Registering from a reinitialization after loading state
+    /// also returns `false`, and the timers from the last activation are kept in that case.
+    fn register_host_timer(&self, period_ms: u32, callback: HostTimerCallback) -> bool;
+
+    /// Register a POSIX file descriptor with the host's main thread event loop so `callback` is
+    /// called whenever one of the events in `flags` occurs on `fd`. This uses CLAP's
+    /// `posix-fd-support` extension, which is only available on Linux and other Unix-like
+    /// platforms. The same rules as in [`register_host_timer()`][Self::register_host_timer()]
+    /// apply. The plugin remains responsible for closing the file descriptor, which it may only
+    /// do after the plugin has been deactivated.
+    fn register_posix_fd(&self, fd: i32, flags: PosixFdFlags, callback: PosixFdCallback) -> bool;
+
+    /// Get a raw pointer to the host's context object for the current plugin API. This is an escape
+    /// hatch for interacting with host-specific functionality that NIH-plug does not (yet) provide
+    /// an abstraction for. You should not need this in normal use.
+    ///
+    /// # Safety
+    ///
+    /// See the safety section on [`RawHostHandle`][crate::context::RawHostHandle].
+    unsafe fn raw_host_handle(&self) -> RawHostHandle;
 }
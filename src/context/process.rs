@@ -1,7 +1,11 @@
 //! A context passed during the process function.
 
-use super::PluginApi;
-use crate::prelude::{Plugin, PluginNoteEvent};
+use std::iter::Chain;
+use std::ops::Deref;
+use std::slice;
+
+use super::{PluginApi, RawHostHandle};
+use crate::prelude::{ChannelCounts, Param, ParamPtr, Plugin, PluginNoteEvent};
 
 /// Contains both context data and callbacks the plugin can use during processing. Most notably this
 /// is how a plugin sends and receives note events, gets transport information, and accesses
@@ -40,6 +44,27 @@ pub trait ProcessContext<P: Plugin> {
     /// Get information about the current transport position and status.
     fn transport(&self) -> &Transport;
 
+    /// The absolute position of the first sample in this block, in samples. While the host's
+    /// transport is playing this is the same as [`Transport::pos_samples()`], compensated for any
+    /// block splitting. When the transport is stopped or the host does not provide a position, this
+    /// keeps advancing from the end of the previous block instead of standing still, so every
+    /// block gets a unique position. This makes it suitable for procedural content that needs to be
+    /// rendered deterministically across block boundaries. The position starts at 0 and gets reset
+    /// whenever the plugin is reset.
+    fn block_start_samples(&self) -> i64;
+
+    /// The channel counts for the audio IO layout the host and the plugin agreed on. This is the
+    /// same layout that was passed to [`Plugin::initialize()`][crate::prelude::Plugin::initialize()],
+    /// and it only changes when the plugin gets reinitialized.
+    fn channel_counts(&self) -> ChannelCounts;
+
+    /// Get the scratch buffers requested with
+    /// [`InitContext::request_scratch_buffers()`][crate::prelude::InitContext::request_scratch_buffers()].
+    /// Every buffer contains enough samples for the maximum block size, so only the first
+    /// `buffer.samples()` samples are needed for the current block. The contents are left as is
+    /// between process calls. This is empty if the plugin did not request any scratch buffers.
+    fn scratch_buffers(&mut self) -> &mut [Box<[f32]>];
+
     /// Returns the next note event, if there is one. Use
     /// [`NoteEvent::timing()`][crate::prelude::NoteEvent::timing()] to get the event's timing
     /// within the buffer. Only available when
@@ -75,6 +100,41 @@ pub trait ProcessContext<P: Plugin> {
     /// ```
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>>;
 
+    /// The same as [`next_event()`][Self::next_event()], but this also returns the index of the
+    /// note input port the event arrived on. This is always 0 unless the plugin declares multiple
+    /// ports through [`Plugin::MIDI_INPUT_PORTS`][crate::prelude::Plugin::MIDI_INPUT_PORTS].
+    /// Events the wrapper generates itself, like polyphonic modulation events, also arrive on the
+    /// first port.
+    fn next_event_with_port(&mut self) -> Option<(PluginNoteEvent<P>, u32)> {
+        self.next_event().map(|event| (event, 0))
+    }
+
+    /// Iterate over the note events that have not yet been returned by
+    /// [`next_event()`][Self::next_event()] without consuming them. This can be used to scan the
+    /// block's events ahead of time, for instance to find the highest velocity. The events are
+    /// yielded in the same order `next_event()` would return them in, and they can still be
+    /// consumed with `next_event()` afterwards. Only available when
+    /// [`Plugin::MIDI_INPUT`][crate::prelude::Plugin::MIDI_INPUT] is set.
+    fn events(&self) -> EventsIter<'_, PluginNoteEvent<P>>;
+
+    /// The timing of the event [`next_event()`][Self::next_event()] would return next, without
+    /// consuming that event. This is the event's sample offset within the current block, and it
+    /// returns `None` once all events have been consumed. This is useful for manual block
+    /// splitting, where the current block should be processed up until the next event.
+    #[inline]
+    fn next_event_timing(&self) -> Option<u32> {
+        self.events().next().map(|event| event.timing())
+    }
+
+    /// The number of note events that have not yet been returned by
+    /// [`next_event()`][Self::next_event()], without consuming any of them. This is a constant
+    /// time operation, so it can be used for diagnostics or to adapt the processing to the number
+    /// of queued events.
+    #[inline]
+    fn num_input_events(&self) -> usize {
+        self.events().len()
+    }
+
     /// Send an event to the host. Only available when
     /// [`Plugin::MIDI_OUTPUT`][crate::prelude::Plugin::MIDI_INPUT] is set. Will not do anything
     /// otherwise.
@@ -92,15 +152,107 @@ pub trait ProcessContext<P: Plugin> {
     /// monophonic modulation when dropping the capacity down to 1.
     fn set_current_voice_capacity(&self, capacity: u32);
 
-    // TODO: Add this, this works similar to [GuiContext::set_parameter] but it adds the parameter
-    //       change to a queue (or directly to the VST3 plugin's parameter output queues) instead of
-    //       using main thread host automation (and all the locks involved there).
-    // fn set_parameter<P: Param>(&self, param: &P, value: P::Plain);
+    /// Ask the host to fully restart the plugin. The host will deactivate the plugin, and then
+    /// call [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] and
+    /// [`Plugin::reset()`][crate::prelude::Plugin::reset()] again before resuming processing. This
+    /// is useful when some internal configuration changed in a way that requires the plugin to be
+    /// reinitialized. The request is deferred to the main thread, so this is safe to call from the
+    /// audio thread. Repeated calls before the host has been notified are coalesced into a single
+    /// request.
+    ///
+    /// In CLAP this uses `clap_host::request_restart()`, and in VST3 this triggers a restart with
+    /// the `kReloadComponent` flag. Not all VST3 hosts support this. The standalone target ignores
+    /// these requests.
+    fn request_restart(&self);
+
+    /// Get a raw pointer to the host's context object for the current plugin API. This is an escape
+    /// hatch for interacting with host-specific functionality that NIH-plug does not (yet) provide
+    /// an abstraction for. You should not need this in normal use.
+    ///
+    /// # Safety
+    ///
+    /// See the safety section on [`RawHostHandle`][crate::context::RawHostHandle].
+    ///
+    /// This is called from the audio thread, so anything done with the pointer must also be
+    /// realtime-safe.
+    unsafe fn raw_host_handle(&self) -> RawHostHandle;
+
+    /// Write an automation point for a parameter at `sample_offset` samples into the current
+    /// block, with an already normalized value. This is meant for internal automation sources like
+    /// step sequencers that should be recorded by the host, and unlike
+    /// [`ParamSetter::set_parameter()`][crate::prelude::ParamSetter::set_parameter()] it doesn't
+    /// involve automation gestures or the GUI thread. The parameter's value is updated at the end
+    /// of the block, so the plugin won't see the new value until the next block.
+    ///
+    /// In CLAP the sample offset is preserved in the parameter value event sent to the host. VST3
+    /// uses the last value set for a parameter during the block and writes it to the end of the
+    /// block instead. The standalone target has no host, so there the value is only changed on the
+    /// plugin.
+    fn set_parameter_automated<Pa: Param>(
+        &mut self,
+        param: &Pa,
+        sample_offset: u32,
+        normalized: f32,
+    ) where
+        Self: Sized,
+    {
+        unsafe { self.raw_set_parameter_automated(param.as_ptr(), sample_offset, normalized) }
+    }
+
+    /// Write an automation point for a parameter with an already normalized value. Use
+    /// [`set_parameter_automated()`][Self::set_parameter_automated()] instead for a safe, user
+    /// friendly API.
+    ///
+    /// # Safety
+    ///
+    /// The implementing function still needs to check if `param` actually exists. This function is
+    /// mostly marked as unsafe for API reasons.
+    unsafe fn raw_set_parameter_automated(
+        &mut self,
+        param: ParamPtr,
+        sample_offset: u32,
+        normalized: f32,
+    );
+}
+
+/// A non-consuming iterator over the remaining input events, returned by
+/// [`ProcessContext::events()`]. The wrappers may store their events in a ring buffer, so this
+/// iterates over up to two slices.
+#[derive(Debug, Clone)]
+pub struct EventsIter<'a, E> {
+    iter: Chain<slice::Iter<'a, E>, slice::Iter<'a, E>>,
+}
+
+impl<'a, E> EventsIter<'a, E> {
+    /// Iterate over the events in `first`, followed by the events in `second`. These can be the
+    /// two slices returned by `VecDeque::as_slices()`.
+    pub(crate) fn new(first: &'a [E], second: &'a [E]) -> Self {
+        Self {
+            iter: first.iter().chain(second.iter()),
+        }
+    }
+}
+
+impl<'a, E> Iterator for EventsIter<'a, E> {
+    type Item = &'a E;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
 }
 
+// The chained slice iterators always know their exact length
+impl<E> ExactSizeIterator for EventsIter<'_, E> {}
+
 /// Information about the plugin's transport. Depending on the plugin API and the host not all
 /// fields may be available.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Transport {
     /// Whether the transport is currently running.
     pub playing: bool,
@@ -150,6 +302,106 @@ pub struct Transport {
     /// that the end is exclusive. Can be calculated from the other loop range information if
     /// needed.
     pub(crate) loop_range_beats: Option<(f64, f64)>,
+
+    /// The tempo changes the host reported for the current buffer. Left empty if the host only
+    /// reported a single tempo. See [`tempo_map()`][Self::tempo_map()].
+    pub(crate) tempo_map: TempoMap,
+    /// Whether the playhead jumped to another position right before this block. See
+    /// [`just_seeked()`][Self::just_seeked()].
+    pub(crate) just_seeked: bool,
+}
+
+/// The maximum number of tempo changes [`Transport::tempo_map()`] can store for a single buffer.
+/// Any further tempo changes are dropped.
+pub const MAX_TEMPO_POINTS: usize = 16;
+
+/// A tempo change within the current buffer. Returned as part of [`Transport::tempo_map()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoPoint {
+    /// The sample index, relative to the start of the current block, from which this tempo
+    /// applies.
+    pub timing: u32,
+    /// The tempo in beats per minute.
+    pub tempo: f64,
+}
+
+/// The tempo changes within a buffer, sorted by their timing. This is a fixed capacity list of up
+/// to [`MAX_TEMPO_POINTS`] points so it can be copied around and created on the audio thread
+/// without allocating. It dereferences to a slice of [`TempoPoint`]s.
+#[derive(Debug, Clone, Copy)]
+pub struct TempoMap {
+    points: [TempoPoint; MAX_TEMPO_POINTS],
+    len: usize,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self {
+            points: [TempoPoint {
+                timing: 0,
+                tempo: 0.0,
+            }; MAX_TEMPO_POINTS],
+            len: 0,
+        }
+    }
+}
+
+impl Deref for TempoMap {
+    type Target = [TempoPoint];
+
+    fn deref(&self) -> &Self::Target {
+        &self.points[..self.len]
+    }
+}
+
+impl TempoMap {
+    /// Add a tempo change. Points must be added in order. If a point with the same timing already
+    /// exists then its tempo is replaced instead. Tempo changes that don't fit in the map are
+    /// dropped, and a debug assertion failure is triggered in that case.
+    pub(crate) fn push(&mut self, timing: u32, tempo: f64) {
+        if let Some(last) = self.points[..self.len].last_mut() {
+            nih_debug_assert!(timing >= last.timing);
+            if last.timing == timing {
+                last.tempo = tempo;
+                return;
+            }
+        }
+
+        if self.len < MAX_TEMPO_POINTS {
+            self.points[self.len] = TempoPoint { timing, tempo };
+            self.len += 1;
+        } else {
+            nih_debug_assert_failure!(
+                "Dropping tempo change at sample {}, the tempo map is full",
+                timing
+            );
+        }
+    }
+
+    /// Get the tempo that is in effect at a sample index relative to the start of the map.
+    /// Returns `None` if the map is empty.
+    pub fn tempo_at(&self, timing: u32) -> Option<f64> {
+        self.iter()
+            .take_while(|point| point.timing <= timing)
+            .last()
+            .or_else(|| self.first())
+            .map(|point| point.tempo)
+    }
+
+    /// Get the part of the map starting at `block_start`, with all timings made relative to
+    /// `block_start`. The tempo that is in effect at `block_start` becomes the first point at
+    /// timing 0. The wrappers use this when a buffer is split into smaller blocks.
+    pub(crate) fn starting_at(&self, block_start: u32) -> Self {
+        let mut map = Self::default();
+        if let Some(tempo) = self.tempo_at(block_start) {
+            map.push(0, tempo);
+        }
+        for point in self.iter().filter(|point| point.timing > block_start) {
+            map.push(point.timing - block_start, point.tempo);
+        }
+
+        map
+    }
 }
 
 impl Transport {
@@ -174,6 +426,41 @@ impl Transport {
             loop_range_samples: None,
             loop_range_seconds: None,
             loop_range_beats: None,
+
+            tempo_map: TempoMap::default(),
+            just_seeked: false,
+        }
+    }
+
+    /// Whether this is the first block after the host's playhead jumped to another position. This
+    /// is set for exactly one block per seek, so plugins that follow the host's timeline, like
+    /// tempo synced delays and sequencers, can use this to re-synchronize once. Seeks are detected
+    /// both while the transport is playing and while it is stopped. Jumping back to the loop start
+    /// at the end of a loop also counts as a seek.
+    ///
+    /// This is always `false` if the host does not report the playhead position, and for the first
+    /// block after the plugin has been reset.
+    pub fn just_seeked(&self) -> bool {
+        self.just_seeked
+    }
+
+    /// All tempo changes the host reported from the start of the current block until the end of
+    /// the host's buffer, with timings relative to the start of the current block. The first point
+    /// is always at timing 0 and contains the same tempo as [`tempo`][Self::tempo]. Points past
+    /// the end of the current block can occur when the wrapper splits the host's buffer into
+    /// smaller blocks. This can be used to compute sample accurate beat positions when the tempo
+    /// changes in the middle of a buffer.
+    ///
+    /// Only CLAP hosts can report more than one tempo per buffer. For the other plugin APIs this
+    /// contains a single point with the current tempo. The map is empty if the tempo is not known.
+    pub fn tempo_map(&self) -> TempoMap {
+        match self.tempo {
+            Some(tempo) if self.tempo_map.is_empty() => {
+                let mut map = TempoMap::default();
+                map.push(0, tempo);
+                map
+            }
+            _ => self.tempo_map,
         }
     }
 
@@ -196,7 +483,7 @@ impl Transport {
         }
     }
 
-    /// The position in the song in seconds. Can be used to calculate the time in samples if needed.
+    /// The position in the song in seconds. Will be calculated from other information if needed.
     pub fn pos_seconds(&self) -> Option<f64> {
         match (
             self.pos_samples,
@@ -337,3 +624,243 @@ impl Transport {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::midi::NoteEvent;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::AudioIOLayout;
+
+    struct Events;
+
+    impl TestPluginConfig for Events {
+        const NAME: &'static str = "Events";
+
+        type Params = NoParams;
+    }
+
+    /// A process context that only has an input event queue, for testing the trait's provided
+    /// methods.
+    struct MockProcessContext {
+        input_events: VecDeque<NoteEvent<()>>,
+        transport: Transport,
+    }
+
+    impl ProcessContext<TestPlugin<Events>> for MockProcessContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Standalone
+        }
+
+        fn execute_background(&self, _task: ()) {}
+
+        fn execute_gui(&self, _task: ()) {}
+
+        fn transport(&self) -> &Transport {
+            &self.transport
+        }
+
+        fn block_start_samples(&self) -> i64 {
+            0
+        }
+
+        fn channel_counts(&self) -> ChannelCounts {
+            AudioIOLayout::const_default().channel_counts()
+        }
+
+        fn scratch_buffers(&mut self) -> &mut [Box<[f32]>] {
+            &mut []
+        }
+
+        fn next_event(&mut self) -> Option<NoteEvent<()>> {
+            self.input_events.pop_front()
+        }
+
+        fn events(&self) -> EventsIter<'_, NoteEvent<()>> {
+            let (first, second) = self.input_events.as_slices();
+            EventsIter::new(first, second)
+        }
+
+        fn send_event(&mut self, _event: NoteEvent<()>) {}
+
+        fn set_latency_samples(&self, _samples: u32) {}
+
+        fn set_current_voice_capacity(&self, _capacity: u32) {}
+
+        fn request_restart(&self) {}
+
+        unsafe fn raw_host_handle(&self) -> RawHostHandle {
+            RawHostHandle::Standalone
+        }
+
+        unsafe fn raw_set_parameter_automated(
+            &mut self,
+            _param: ParamPtr,
+            _sample_offset: u32,
+            _normalized: f32,
+        ) {
+        }
+    }
+
+    fn note_on(timing: u32, velocity: f32) -> NoteEvent<()> {
+        NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note: 60,
+            velocity,
+        }
+    }
+
+    #[test]
+    fn events_prescan_then_consume() {
+        // Rotating the queue makes it wrap around, so the events are split over two slices like
+        // they can be in the wrappers
+        let mut input_events = VecDeque::with_capacity(4);
+        input_events.extend([note_on(0, 0.0), note_on(0, 0.0), note_on(0, 0.1)]);
+        input_events.pop_front();
+        input_events.pop_front();
+        input_events.extend([note_on(1, 0.9), note_on(2, 0.5)]);
+
+        let (first, second) = input_events.as_slices();
+        let highest_velocity = EventsIter::new(first, second)
+            .filter_map(|event| match event {
+                NoteEvent::NoteOn { velocity, .. } => Some(*velocity),
+                _ => None,
+            })
+            .fold(0.0f32, f32::max);
+        assert_eq!(highest_velocity, 0.9);
+
+        let (first, second) = input_events.as_slices();
+        let prescanned: Vec<_> = EventsIter::new(first, second).cloned().collect();
+        let consumed: Vec<_> = std::iter::from_fn(|| input_events.pop_front()).collect();
+        assert_eq!(prescanned, consumed);
+        assert_eq!(consumed.len(), 3);
+    }
+
+    #[test]
+    fn next_event_timing_matches_peeked_event() {
+        let mut input_events = VecDeque::with_capacity(4);
+        input_events.extend([note_on(0, 0.0), note_on(0, 0.0), note_on(3, 0.1)]);
+        input_events.pop_front();
+        input_events.pop_front();
+        input_events.extend([note_on(5, 0.9), note_on(12, 0.5)]);
+        let mut context = MockProcessContext {
+            input_events,
+            transport: Transport::new(44_100.0),
+        };
+
+        let mut timings = Vec::new();
+        loop {
+            let next_event_timing = context.next_event_timing();
+            assert_eq!(
+                next_event_timing,
+                context.input_events.front().map(|event| event.timing())
+            );
+
+            match context.next_event() {
+                Some(event) => {
+                    assert_eq!(next_event_timing, Some(event.timing()));
+                    timings.push(event.timing());
+                }
+                None => {
+                    assert_eq!(next_event_timing, None);
+                    break;
+                }
+            }
+        }
+        assert_eq!(timings, [3, 5, 12]);
+    }
+
+    #[test]
+    fn num_input_events_counts_queued_events() {
+        let mut input_events = VecDeque::with_capacity(4);
+        input_events.extend([note_on(0, 0.0), note_on(0, 0.0), note_on(3, 0.1)]);
+        input_events.pop_front();
+        input_events.pop_front();
+        input_events.extend([note_on(5, 0.9), note_on(12, 0.5), note_on(20, 0.2)]);
+
+        for expected_len in (0..=4).rev() {
+            let (first, second) = input_events.as_slices();
+            let events = EventsIter::new(first, second);
+            assert_eq!(events.len(), expected_len);
+            assert_eq!(events.count(), expected_len);
+
+            input_events.pop_front();
+        }
+    }
+
+    #[test]
+    fn tempo_map_two_points_in_one_block() {
+        // Two tempo changes at the start and in the middle of a single 128 sample block
+        let mut transport = Transport::new(44_100.0);
+        transport.tempo = Some(120.0);
+        transport.tempo_map.push(0, 120.0);
+        transport.tempo_map.push(64, 140.0);
+
+        let tempo_map = transport.tempo_map();
+        assert_eq!(
+            &*tempo_map,
+            &[
+                TempoPoint {
+                    timing: 0,
+                    tempo: 120.0
+                },
+                TempoPoint {
+                    timing: 64,
+                    tempo: 140.0
+                },
+            ]
+        );
+        assert_eq!(tempo_map.tempo_at(63), Some(120.0));
+        assert_eq!(tempo_map.tempo_at(64), Some(140.0));
+
+        // When the block gets split, the second block starts with the new tempo
+        let second_block = transport.tempo_map.starting_at(64);
+        assert_eq!(
+            &*second_block,
+            &[TempoPoint {
+                timing: 0,
+                tempo: 140.0
+            }]
+        );
+        let first_block = transport.tempo_map.starting_at(32);
+        assert_eq!(first_block.len(), 2);
+        assert_eq!(first_block[1].timing, 32);
+    }
+
+    #[test]
+    fn tempo_map_single_point_fallback() {
+        let mut transport = Transport::new(44_100.0);
+        assert!(transport.tempo_map().is_empty());
+
+        transport.tempo = Some(90.0);
+        assert_eq!(
+            &*transport.tempo_map(),
+            &[TempoPoint {
+                timing: 0,
+                tempo: 90.0
+            }]
+        );
+    }
+
+    #[test]
+    fn pos_seconds_from_samples() {
+        let mut transport = Transport::new(48_000.0);
+        assert_eq!(transport.pos_seconds(), None);
+
+        transport.pos_samples = Some(72_000);
+        assert_eq!(transport.pos_seconds(), Some(1.5));
+
+        // A position in seconds reported by the host takes precedence
+        transport.pos_seconds = Some(1.25);
+        assert_eq!(transport.pos_seconds(), Some(1.25));
+        assert_eq!(transport.pos_samples(), Some(72_000));
+
+        // Without a sample position, the samples are derived from the seconds instead
+        transport.pos_samples = None;
+        assert_eq!(transport.pos_samples(), Some(60_000));
+    }
+}
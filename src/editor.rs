@@ -68,6 +68,57 @@ pub trait Editor: Send {
     /// loaded.
     fn param_values_changed(&self);
 
+    /// The interval in milliseconds at which the editor would like
+    /// [`on_host_timer()`][Self::on_host_timer()] to be called by the host. This allows editors to
+    /// draw in response to the host's timers instead of running their own loop, which can save CPU
+    /// when the plugin is idle. This is currently only supported for CLAP plugins in hosts that
+    /// support the `timer-support` extension. Queried once every time the editor is opened.
+    fn host_timer_interval_ms(&self) -> Option<u32> {
+        None
+    }
+
+    /// Called periodically from the GUI thread when the editor is open and
+    /// [`host_timer_interval_ms()`][Self::host_timer_interval_ms()] returned a value. Since not
+    /// every plugin format and host supports this, editors should keep driving their own redraws
+    /// until the first time this function is called.
+    fn on_host_timer(&self) {}
+
+    /// Called when the host shows or hides the editor without closing it, for instance when the
+    /// plugin window gets minimized or when the host switches to another tab. Editors can use this
+    /// to pause rendering while they're hidden. The editor stays open and should resume drawing
+    /// once this is called with `true` again. This is currently only supported for CLAP plugins.
+    #[allow(unused_variables)]
+    fn set_visible(&self, visible: bool) {}
+
+    /// Adjust a `(width, height)` size in logical pixels proposed by the host, for instance while
+    /// the user is dragging the plugin window's border. The returned size is the closest size the
+    /// editor can take on, which makes it possible to snap the size to a grid or to keep a fixed
+    /// aspect ratio. This is consulted through CLAP's `gui.adjust_size()` and VST3's
+    /// `IPlugView::checkSizeConstraint()` before the host resizes the window. Returns the proposed
+    /// size as is by default.
+    fn adjust_size(&self, proposed: (u32, u32)) -> (u32, u32) {
+        proposed
+    }
+
+    /// Whether the editor currently wants exclusive keyboard focus, for instance while a text field
+    /// is being edited. When this returns `true`, key presses the host forwards to the plugin are
+    /// claimed by the editor instead of being passed back to the host, so they won't trigger the
+    /// host's keyboard shortcuts. This is polled by the wrapper every time the host forwards a key
+    /// press, so it can simply reflect the editor's current state.
+    ///
+    /// Support for this varies between plugin formats and hosts:
+    ///
+    /// - VST3 hosts that forward key events through `IPlugView::onKeyDown()` and
+    ///   `IPlugView::onKeyUp()` will respect this. Key events the editor doesn't claim are passed
+    ///   back to the host. Some hosts, most notably on macOS, don't forward key events at all and
+    ///   instead deliver them to the editor's window directly.
+    /// - CLAP has no way for a plugin to claim keyboard focus, so this is ignored there. The
+    ///   editor's window receives keyboard input whenever the host gives it focus.
+    /// - The standalone target always delivers keyboard input to the editor's window.
+    fn wants_exclusive_keyboard_focus(&self) -> bool {
+        false
+    }
+
     // TODO: Reconsider adding a tick function here for the Linux `IRunLoop`. To keep this platform
     //       and API agnostic, add a way to ask the GuiContext if the wrapper already provides a
     //       tick function. If it does not, then the Editor implementation must handle this by
@@ -4,6 +4,19 @@
 //! functions, while the `s2v_` functions are meant to be used wit the `.string_to_value()`.
 //! functions. Most of these formatters come as a pair. Check each formatter's documentation for any
 //! additional usage information.
+//!
+//! Formatters that already include the unit, like [`v2s_f32_hz_then_khz()`], don't set a unit label
+//! on the parameter. Those parameters can set their
+//! [`UnitClass`][crate::params::UnitClass] explicitly using `with_unit_class()` so custom editors
+//! can still tell what kind of value the parameter holds.
+//!
+//! # Locale
+//!
+//! The `v2s_` formatters always use a period as the decimal separator, regardless of the system's
+//! or the host's locale. Hosts and users in locales that use a decimal comma may still enter values
+//! like `1,5`, so the `s2v_` parsers and [`FloatParam`][crate::prelude::FloatParam]'s default
+//! parser accept both `1.5` and `1,5`. A comma is only treated as a decimal separator if the number
+//! does not also contain a period. Thousands separators are not supported.
 
 use std::cmp::Ordering;
 use std::sync::Arc;
@@ -17,15 +30,32 @@ use crate::util;
 /// negative zero values to make sure string->value->string roundtrips work correctly. Otherwise
 /// `-0.001` rounded to two digits would result in `-0.00`.
 pub fn v2s_f32_rounded(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + Sync> {
+    Arc::new(move |value| format_f32_rounded(value, digits))
+}
+
+/// The formatting used by [`v2s_f32_rounded()`]. This is also used by
+/// [`FloatParam`][crate::prelude::FloatParam]'s default formatter when a precision has been set,
+/// and by the other formatters here that take a number of digits.
+pub(crate) fn format_f32_rounded(value: f32, digits: usize) -> String {
     let rounding_multiplier = 10u32.pow(digits as u32) as f32;
-    Arc::new(move |value| {
-        // See above
-        if (value * rounding_multiplier).round() / rounding_multiplier == 0.0 {
-            format!("{:.digits$}", 0.0)
-        } else {
-            format!("{value:.digits$}")
+    // See above
+    if (value * rounding_multiplier).round() / rounding_multiplier == 0.0 {
+        format!("{:.digits$}", 0.0)
+    } else {
+        format!("{value:.digits$}")
+    }
+}
+
+/// Parse a floating point number that may use either a period or a comma as its decimal
+/// separator. See the module documentation for more information.
+pub(crate) fn parse_f32(string: &str) -> Option<f32> {
+    match string.parse() {
+        Ok(value) => Some(value),
+        Err(_) if !string.contains('.') && string.matches(',').count() == 1 => {
+            string.replacen(',', ".", 1).parse().ok()
         }
-    })
+        Err(_) => None,
+    }
 }
 
 /// Format a `[0, 1]` number as a percentage. Does not include the percent sign, you should specify
@@ -37,13 +67,7 @@ pub fn v2s_f32_percentage(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + S
 /// Parse a `[0, 100]` percentage to a `[0, 1]` number. Handles the percentage unit for you. Used in
 /// conjunction with [`v2s_f32_percentage()`].
 pub fn s2v_f32_percentage() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
-    Arc::new(|string| {
-        string
-            .trim_end_matches(&[' ', '%'])
-            .parse()
-            .ok()
-            .map(|x: f32| x / 100.0)
-    })
+    Arc::new(|string| parse_f32(string.trim_end_matches(&[' ', '%'])).map(|x| x / 100.0))
 }
 
 /// Format a positive number as a compression ratio. A value of 4 will be formatted as `4.0:1` while
@@ -67,13 +91,13 @@ pub fn s2v_compression_ratio() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync>
             .trim()
             .split_once(':')
             .and_then(|(numerator, denominator)| {
-                let numerator: f32 = numerator.trim().parse().ok()?;
-                let denominator: f32 = denominator.trim().parse().ok()?;
+                let numerator = parse_f32(numerator.trim())?;
+                let denominator = parse_f32(denominator.trim())?;
 
                 Some(numerator / denominator)
             })
             // Just parse the value directly if it doesn't contain a colon
-            .or_else(|| string.parse().ok())
+            .or_else(|| parse_f32(string))
     })
 }
 
@@ -86,10 +110,7 @@ pub fn v2s_f32_gain_to_db(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + S
             String::from("-inf")
         } else {
             // Never print -0.0 since that just looks weird and confusing
-            let value_db = util::gain_to_db(value);
-            let value_db = if value_db.abs() < 1e-6 { 0.0 } else { value_db };
-
-            format!("{value_db:.digits$}")
+            format_f32_rounded(util::gain_to_db(value), digits)
         }
     })
 }
@@ -103,7 +124,7 @@ pub fn s2v_f32_gain_to_db() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
         if string.eq_ignore_ascii_case("-in") {
             Some(0.0)
         } else {
-            string.parse().ok().map(util::db_to_gain)
+            parse_f32(string).map(util::db_to_gain)
         }
     })
 }
@@ -124,14 +145,12 @@ pub fn v2s_f32_panning() -> Arc<dyn Fn(f32) -> String + Send + Sync> {
 pub fn s2v_f32_panning() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
     Arc::new(|string| {
         let string = string.trim();
-        let cleaned_string = string
-            .trim_end_matches(&[' ', 'l', 'L', 'c', 'C', 'r', 'R'])
-            .parse()
-            .ok();
+        let cleaned_string =
+            parse_f32(string.trim_end_matches(&[' ', 'l', 'L', 'c', 'C', 'r', 'R']));
         match string.chars().last()?.to_uppercase().next()? {
-            'L' => cleaned_string.map(|x: f32| x / -100.0),
+            'L' => cleaned_string.map(|x| x / -100.0),
             'C' => Some(0.0),
-            'R' => cleaned_string.map(|x: f32| x / 100.0),
+            'R' => cleaned_string.map(|x| x / 100.0),
             _ => None,
         }
     })
@@ -142,9 +161,9 @@ pub fn s2v_f32_panning() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
 pub fn v2s_f32_hz_then_khz(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + Sync> {
     Arc::new(move |value| {
         if value < 1000.0 {
-            format!("{value:.digits$} Hz")
+            format!("{} Hz", format_f32_rounded(value, digits))
         } else {
-            format!("{:.digits$} kHz", value / 1000.0, digits = digits.max(1))
+            format!("{} kHz", format_f32_rounded(value / 1000.0, digits.max(1)))
         }
     })
 }
@@ -159,7 +178,7 @@ pub fn v2s_f32_hz_then_khz_with_note_name(
         // With 0.0 this would result in a subtraction below i32's minimum value, and it would look
         // ridiculous anyways so we'll just not even bother for tiny values
         if value.abs() < 1.0 {
-            return format!("{value:.digits$} Hz");
+            return format!("{} Hz", format_f32_rounded(value, digits));
         }
 
         // This is the inverse of the formula in `f32_midi_note_to_freq`
@@ -178,13 +197,11 @@ pub fn v2s_f32_hz_then_khz_with_note_name(
         };
 
         if value < 1000.0 {
-            format!("{value:.digits$} Hz, {note_str}")
+            format!("{} Hz, {note_str}", format_f32_rounded(value, digits))
         } else {
             format!(
-                "{:.digits$} kHz, {}",
-                value / 1000.0,
-                note_str,
-                digits = digits.max(1)
+                "{} kHz, {note_str}",
+                format_f32_rounded(value / 1000.0, digits.max(1))
             )
         }
     })
@@ -201,6 +218,12 @@ pub fn s2v_f32_hz_then_khz() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
     Arc::new(move |string| {
         let string = string.trim();
 
+        // A comma is also used to separate the frequency from the note name, so a frequency that
+        // uses a decimal comma needs to be parsed before splitting the string
+        if let Some(frequency) = parse_hz_then_khz(string) {
+            return Some(frequency);
+        }
+
         // The input can contain a frequency in Hz or kHz, a note name, a note name and cents, or
         // one of those two combined with a frequency. In the last case we'll ignore the frequency.
         // If the string cannot be parsed as a note name, we'll try parsing it as a frequency
@@ -236,19 +259,20 @@ pub fn s2v_f32_hz_then_khz() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
         }
 
         // Otherwise we'll accept values in either Hz (with or without unit) or kHz
-        let frequency_segment = segments.0?;
-        let cleaned_string = frequency_segment
-            .trim_end_matches([' ', 'k', 'K', 'h', 'H', 'z', 'Z'])
-            .parse()
-            .ok();
-        match frequency_segment.get(frequency_segment.len().saturating_sub(3)..) {
-            Some(unit) if unit.eq_ignore_ascii_case("khz") => cleaned_string.map(|x| x * 1000.0),
-            // Even if there's no unit at all, just assume the input is in Hertz
-            _ => cleaned_string,
-        }
+        parse_hz_then_khz(segments.0?)
     })
 }
 
+/// Parse a frequency in either Hz (with or without unit) or kHz. Used in [`s2v_f32_hz_then_khz()`].
+fn parse_hz_then_khz(string: &str) -> Option<f32> {
+    let cleaned_string = parse_f32(string.trim_end_matches([' ', 'k', 'K', 'h', 'H', 'z', 'Z']));
+    match string.get(string.len().saturating_sub(3)..) {
+        Some(unit) if unit.eq_ignore_ascii_case("khz") => cleaned_string.map(|x| x * 1000.0),
+        // Even if there's no unit at all, just assume the input is in Hertz
+        _ => cleaned_string,
+    }
+}
+
 /// Format an order/power of two. Useful in conjunction with [`s2v_i32_power_of_two()`] to limit
 /// integer parameter ranges to be only powers of two.
 pub fn v2s_i32_power_of_two() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
@@ -346,6 +370,29 @@ mod tests {
         assert_eq!("0.01", v2s(0.009));
     }
 
+    #[test]
+    fn hz_then_khz_digits() {
+        let v2s = v2s_f32_hz_then_khz(2);
+
+        assert_eq!("440.00 Hz", v2s(440.0));
+        assert_eq!("1.50 kHz", v2s(1500.0));
+
+        // kHz values always have at least one decimal
+        let v2s = v2s_f32_hz_then_khz(0);
+        assert_eq!("440 Hz", v2s(440.0));
+        assert_eq!("1.5 kHz", v2s(1500.0));
+    }
+
+    #[test]
+    fn gain_to_db_digits() {
+        let v2s = v2s_f32_gain_to_db(2);
+
+        assert_eq!("0.00", v2s(1.0));
+        assert_eq!("0.00", v2s(0.9999));
+        assert_eq!("-6.02", v2s(0.5));
+        assert_eq!("-inf", v2s(0.0));
+    }
+
     // More of these validators could use tests, but this one in particular is tricky and I noticed
     // an issue where it didn't roundtrip correctly
     #[test]
@@ -365,4 +412,29 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn decimal_comma() {
+        let s2v = s2v_f32_percentage();
+        assert_eq!(s2v("1,5"), s2v("1.5"));
+        assert_eq!(s2v("1,5 %"), Some(0.015));
+
+        let s2v = s2v_f32_gain_to_db();
+        assert_eq!(s2v("-1,5 dB"), s2v("-1.5 dB"));
+
+        let s2v = s2v_compression_ratio();
+        assert_eq!(s2v("1,5:1"), Some(1.5));
+
+        let s2v = s2v_f32_panning();
+        assert_eq!(s2v("1,5R"), s2v("1.5R"));
+
+        // The comma between the frequency and the note name must still work
+        let s2v = s2v_f32_hz_then_khz();
+        assert_eq!(s2v("1,5 kHz"), Some(1500.0));
+        assert_eq!(s2v("1,5"), s2v("1.5"));
+        assert_eq!(s2v("440.0 Hz, A4"), s2v("A4"));
+
+        // A comma is not a decimal separator if there's already a period
+        assert_eq!(parse_f32("1,000.5"), None);
+    }
 }
@@ -5,10 +5,17 @@ use midi_consts::channel_event as midi;
 use self::sysex::SysExMessage;
 use crate::prelude::Plugin;
 
+pub mod relative_encoder;
 pub mod sysex;
 
 pub use midi_consts::channel_event::control_change;
 
+/// The release velocity used for [`NoteEvent::NoteOff`] events when the host or the MIDI device
+/// did not provide one. Hosts and MIDI devices without release velocity support either send a
+/// release velocity of zero, or they send a note on event with a velocity of zero instead of a note
+/// off event.
+pub const DEFAULT_RELEASE_VELOCITY: f32 = 0.5;
+
 /// A plugin-specific note event type.
 ///
 /// The reason why this is defined like this instead of parameterizing `NoteEvent` with `P`` is
@@ -77,8 +84,10 @@ pub enum NoteEvent<S> {
         channel: u8,
         /// The note's MIDI key number, in `0..128`.
         note: u8,
-        /// The note's velocity, in `[0, 1]`. Some plugin APIs may allow higher precision than the
-        /// 128 levels available in MIDI.
+        /// The note's release velocity, in `(0, 1]`. Some plugin APIs may allow higher precision
+        /// than the 128 levels available in MIDI. If the host or MIDI device did not send a release
+        /// velocity, then this is set to [`DEFAULT_RELEASE_VELOCITY`]. Note on events with a
+        /// velocity of zero are also converted to note off events with this release velocity.
         velocity: f32,
     },
     /// A note choke event, available on [`MidiConfig::Basic`] and up. When the host sends this to
@@ -136,6 +145,12 @@ pub enum NoteEvent<S> {
     ///   - Finally, if the polyphonic modulation happens on the same sample as the `NoteOn` event,
     ///     then the smoothing should not start at the current global value. In this case, `reset()`
     ///     should be called with the voice's modulated value.
+    ///   - Alternatively, the offset can be applied on top of the global smoothed value without
+    ///     smoothing the offset itself using
+    ///     [`FloatParam::apply_poly_modulation()`][crate::prelude::FloatParam::apply_poly_modulation()].
+    ///     The host already sends smooth modulation, so this avoids the caveats above.
+    ///     [`PolyModulationOffsets`][crate::util::PolyModulationOffsets] can store the offsets and
+    ///     compute the voice's values this way.
     /// - If a `MonoAutomation` event is emitted for a parameter, then the values or target values
     ///   (if the parameter uses smoothing) for all voices must be updated. The normalized value
     ///   from the `MonoAutomation` and the voice's normalized modulation offset must be added and
@@ -224,6 +239,19 @@ pub enum NoteEvent<S> {
     },
     /// A tuning expression event, available on [`MidiConfig::Basic`] and up. Not all hosts may support
     /// these expressions.
+    ///
+    /// Hosts send a new event every time the note's tuning changes, so a glide or a continuous
+    /// pitch curve arrives as a series of events within the same block, each with its own
+    /// `timing`. The tuning is absolute rather than relative to the previous event, and it applies
+    /// from the event's timing until the next tuning event for the same note. Events should be
+    /// matched to voices using `voice_id` if it is set, and using the `channel` and `note`
+    /// combination otherwise. A new note always starts out with a tuning of zero. To glide
+    /// smoothly, either process the block in smaller chunks at the event timings, or ramp the
+    /// voice's pitch towards the new tuning over the time until the next event.
+    ///
+    /// MPE controllers send per-note pitch bend as [`MidiPitchBend`][Self::MidiPitchBend] events
+    /// on the note's member channel instead. Those need to be mapped to the voice playing on that
+    /// channel by the plugin.
     PolyTuning {
         timing: u32,
         /// A unique identifier for this note, if available. Using this to refer to a note is
@@ -233,7 +261,7 @@ pub enum NoteEvent<S> {
         channel: u8,
         /// The note's MIDI key number, in `0..128`.
         note: u8,
-        /// The note's tuning in semitones, in `[-128, 128]`.
+        /// The note's tuning in semitones, in `[-120, 120]`.
         tuning: f32,
     },
     /// A vibrato expression event, available on [`MidiConfig::Basic`] and up. Not all hosts may support
@@ -363,6 +391,36 @@ impl<S> NoteEvent<S> {
         }
     }
 
+    /// Change the sample within the current buffer this event belongs to.
+    pub fn set_timing(&mut self, timing: u32) {
+        *self.timing_mut() = timing;
+    }
+
+    /// A mutable reference to this event's timing. The match is exhaustive on purpose so adding a
+    /// new event type forces this to be updated.
+    fn timing_mut(&mut self) -> &mut u32 {
+        match self {
+            NoteEvent::NoteOn { timing, .. } => timing,
+            NoteEvent::NoteOff { timing, .. } => timing,
+            NoteEvent::Choke { timing, .. } => timing,
+            NoteEvent::VoiceTerminated { timing, .. } => timing,
+            NoteEvent::PolyModulation { timing, .. } => timing,
+            NoteEvent::MonoAutomation { timing, .. } => timing,
+            NoteEvent::PolyPressure { timing, .. } => timing,
+            NoteEvent::PolyVolume { timing, .. } => timing,
+            NoteEvent::PolyPan { timing, .. } => timing,
+            NoteEvent::PolyTuning { timing, .. } => timing,
+            NoteEvent::PolyVibrato { timing, .. } => timing,
+            NoteEvent::PolyExpression { timing, .. } => timing,
+            NoteEvent::PolyBrightness { timing, .. } => timing,
+            NoteEvent::MidiChannelPressure { timing, .. } => timing,
+            NoteEvent::MidiPitchBend { timing, .. } => timing,
+            NoteEvent::MidiCC { timing, .. } => timing,
+            NoteEvent::MidiProgramChange { timing, .. } => timing,
+            NoteEvent::MidiSysEx { timing, .. } => timing,
+        }
+    }
+
     /// Returns the event's voice ID, if it has any.
     pub fn voice_id(&self) -> Option<i32> {
         match self {
@@ -413,6 +471,58 @@ impl<S> NoteEvent<S> {
 }
 
 impl<S: SysExMessage> NoteEvent<S> {
+    /// Create a [`NoteEvent::NoteOn`] event, or a [`NoteEvent::NoteOff`] event with the default
+    /// release velocity if `velocity` is zero. Used by the wrappers so note events are translated
+    /// the same way for every plugin API.
+    pub(crate) fn note_on_or_off(
+        timing: u32,
+        voice_id: Option<i32>,
+        channel: u8,
+        note: u8,
+        velocity: f32,
+    ) -> Self {
+        if velocity <= 0.0 {
+            NoteEvent::NoteOff {
+                timing,
+                voice_id,
+                channel,
+                note,
+                velocity: DEFAULT_RELEASE_VELOCITY,
+            }
+        } else {
+            NoteEvent::NoteOn {
+                timing,
+                voice_id,
+                channel,
+                note,
+                velocity: velocity.min(1.0),
+            }
+        }
+    }
+
+    /// Create a [`NoteEvent::NoteOff`] event. A release velocity of zero means that the host or
+    /// MIDI device did not send a release velocity, so it's replaced with
+    /// [`DEFAULT_RELEASE_VELOCITY`].
+    pub(crate) fn note_off(
+        timing: u32,
+        voice_id: Option<i32>,
+        channel: u8,
+        note: u8,
+        velocity: f32,
+    ) -> Self {
+        NoteEvent::NoteOff {
+            timing,
+            voice_id,
+            channel,
+            note,
+            velocity: if velocity <= 0.0 {
+                DEFAULT_RELEASE_VELOCITY
+            } else {
+                velocity.min(1.0)
+            },
+        }
+    }
+
     /// Parse MIDI into a [`NoteEvent`]. Supports both basic three bytes messages as well as SysEx.
     /// Will return `Err(event_type)` if the parsing failed.
     pub fn from_midi(timing: u32, midi_data: &[u8]) -> Result<Self, u8> {
@@ -424,35 +534,26 @@ impl<S: SysExMessage> NoteEvent<S> {
             //       point, right now the plugin has to figure it out for itself
             let channel = status_byte & midi::MIDI_CHANNEL_MASK;
             match event_type {
-                // You thought this was a note on? Think again! This is a cleverly disguised note off
-                // event straight from the 80s when Baud rate was still a limiting factor!
-                midi::NOTE_ON if midi_data[2] == 0 => {
-                    return Ok(NoteEvent::NoteOff {
-                        timing,
-                        voice_id: None,
-                        channel,
-                        note: midi_data[1],
-                        // Few things use release velocity. Just having this be zero here is fine, right?
-                        velocity: 0.0,
-                    });
-                }
+                // You thought this was a note on? Think again! A note on with a velocity of zero is a
+                // cleverly disguised note off event straight from the 80s when Baud rate was still a
+                // limiting factor!
                 midi::NOTE_ON => {
-                    return Ok(NoteEvent::NoteOn {
+                    return Ok(NoteEvent::note_on_or_off(
                         timing,
-                        voice_id: None,
+                        None,
                         channel,
-                        note: midi_data[1],
-                        velocity: midi_data[2] as f32 / 127.0,
-                    });
+                        midi_data[1],
+                        midi_data[2] as f32 / 127.0,
+                    ));
                 }
                 midi::NOTE_OFF => {
-                    return Ok(NoteEvent::NoteOff {
+                    return Ok(NoteEvent::note_off(
                         timing,
-                        voice_id: None,
+                        None,
                         channel,
-                        note: midi_data[1],
-                        velocity: midi_data[2] as f32 / 127.0,
-                    });
+                        midi_data[1],
+                        midi_data[2] as f32 / 127.0,
+                    ));
                 }
                 midi::POLYPHONIC_KEY_PRESSURE => {
                     return Ok(NoteEvent::PolyPressure {
@@ -624,26 +725,7 @@ impl<S: SysExMessage> NoteEvent<S> {
     /// splitting in the VST3 wrapper implementation because all events have to be read upfront.
     #[cfg_attr(not(feature = "vst3"), allow(dead_code))]
     pub(crate) fn subtract_timing(&mut self, samples: u32) {
-        match self {
-            NoteEvent::NoteOn { timing, .. } => *timing -= samples,
-            NoteEvent::NoteOff { timing, .. } => *timing -= samples,
-            NoteEvent::Choke { timing, .. } => *timing -= samples,
-            NoteEvent::VoiceTerminated { timing, .. } => *timing -= samples,
-            NoteEvent::PolyModulation { timing, .. } => *timing -= samples,
-            NoteEvent::MonoAutomation { timing, .. } => *timing -= samples,
-            NoteEvent::PolyPressure { timing, .. } => *timing -= samples,
-            NoteEvent::PolyVolume { timing, .. } => *timing -= samples,
-            NoteEvent::PolyPan { timing, .. } => *timing -= samples,
-            NoteEvent::PolyTuning { timing, .. } => *timing -= samples,
-            NoteEvent::PolyVibrato { timing, .. } => *timing -= samples,
-            NoteEvent::PolyExpression { timing, .. } => *timing -= samples,
-            NoteEvent::PolyBrightness { timing, .. } => *timing -= samples,
-            NoteEvent::MidiChannelPressure { timing, .. } => *timing -= samples,
-            NoteEvent::MidiPitchBend { timing, .. } => *timing -= samples,
-            NoteEvent::MidiCC { timing, .. } => *timing -= samples,
-            NoteEvent::MidiProgramChange { timing, .. } => *timing -= samples,
-            NoteEvent::MidiSysEx { timing, .. } => *timing -= samples,
-        }
+        *self.timing_mut() -= samples;
     }
 }
 
@@ -663,6 +745,133 @@ mod tests {
         NoteEvent::from_midi(TIMING, &midi_data).unwrap()
     }
 
+    #[test]
+    fn test_timing_all_variants() {
+        let mut events: [NoteEvent<()>; 18] = [
+            NoteEvent::NoteOn {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                velocity: 1.0,
+            },
+            NoteEvent::NoteOff {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                velocity: 1.0,
+            },
+            NoteEvent::Choke {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+            },
+            NoteEvent::VoiceTerminated {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+            },
+            NoteEvent::PolyModulation {
+                timing: TIMING,
+                voice_id: 0,
+                poly_modulation_id: 0,
+                normalized_offset: 0.5,
+            },
+            NoteEvent::MonoAutomation {
+                timing: TIMING,
+                poly_modulation_id: 0,
+                normalized_value: 0.5,
+            },
+            NoteEvent::PolyPressure {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                pressure: 0.5,
+            },
+            NoteEvent::PolyVolume {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                gain: 0.5,
+            },
+            NoteEvent::PolyPan {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                pan: 0.5,
+            },
+            NoteEvent::PolyTuning {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                tuning: 0.5,
+            },
+            NoteEvent::PolyVibrato {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                vibrato: 0.5,
+            },
+            NoteEvent::PolyExpression {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                expression: 0.5,
+            },
+            NoteEvent::PolyBrightness {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                brightness: 0.5,
+            },
+            NoteEvent::MidiChannelPressure {
+                timing: TIMING,
+                channel: 1,
+                pressure: 0.5,
+            },
+            NoteEvent::MidiPitchBend {
+                timing: TIMING,
+                channel: 1,
+                value: 0.5,
+            },
+            NoteEvent::MidiCC {
+                timing: TIMING,
+                channel: 1,
+                cc: 2,
+                value: 0.5,
+            },
+            NoteEvent::MidiProgramChange {
+                timing: TIMING,
+                channel: 1,
+                program: 2,
+            },
+            NoteEvent::MidiSysEx {
+                timing: TIMING,
+                message: (),
+            },
+        ];
+
+        for event in &mut events {
+            assert_eq!(event.timing(), TIMING);
+
+            event.set_timing(TIMING + 10);
+            assert_eq!(event.timing(), TIMING + 10);
+
+            event.subtract_timing(10);
+            assert_eq!(event.timing(), TIMING);
+        }
+    }
+
     #[test]
     fn test_note_on_midi_conversion() {
         let event = NoteEvent::<()>::NoteOn {
@@ -690,6 +899,70 @@ mod tests {
         assert_eq!(roundtrip_basic_event(event), event);
     }
 
+    #[test]
+    fn test_note_off_zero_velocity() {
+        let event = NoteEvent::<()>::from_midi(TIMING, &[midi::NOTE_OFF | 1, 2, 0]).unwrap();
+        assert_eq!(
+            event,
+            NoteEvent::NoteOff {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                velocity: DEFAULT_RELEASE_VELOCITY,
+            }
+        );
+
+        // The wrappers use the same translation for plugin API note events
+        assert_eq!(NoteEvent::<()>::note_off(TIMING, None, 1, 2, 0.0), event);
+    }
+
+    #[test]
+    fn test_note_off_release_velocity() {
+        let event = NoteEvent::<()>::from_midi(TIMING, &[midi::NOTE_OFF | 1, 2, 127]).unwrap();
+        assert_eq!(
+            event,
+            NoteEvent::NoteOff {
+                timing: TIMING,
+                voice_id: None,
+                channel: 1,
+                note: 2,
+                velocity: 1.0,
+            }
+        );
+
+        assert_eq!(
+            NoteEvent::<()>::note_off(TIMING, Some(3), 1, 2, 0.25),
+            NoteEvent::NoteOff {
+                timing: TIMING,
+                voice_id: Some(3),
+                channel: 1,
+                note: 2,
+                velocity: 0.25,
+            }
+        );
+    }
+
+    #[test]
+    fn test_note_on_zero_velocity() {
+        let expected = NoteEvent::<()>::NoteOff {
+            timing: TIMING,
+            voice_id: None,
+            channel: 1,
+            note: 2,
+            velocity: DEFAULT_RELEASE_VELOCITY,
+        };
+
+        assert_eq!(
+            NoteEvent::<()>::from_midi(TIMING, &[midi::NOTE_ON | 1, 2, 0]).unwrap(),
+            expected
+        );
+        assert_eq!(
+            NoteEvent::<()>::note_on_or_off(TIMING, None, 1, 2, 0.0),
+            expected
+        );
+    }
+
     #[test]
     fn test_poly_pressure_midi_conversion() {
         let event = NoteEvent::<()>::PolyPressure {
@@ -0,0 +1,178 @@
+//! Support for mapping relative (endless) MIDI encoders to parameters.
+
+use crate::prelude::Param;
+
+/// The way a relative encoder encodes its deltas in a CC value. Relative encoders don't send an
+/// absolute position, they send how many ticks the encoder was turned by since the last message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeEncoding {
+    /// The delta is stored as a 7-bit two's complement number. 1 means one tick clockwise, 127
+    /// means one tick counterclockwise, and 0 means no change. Sometimes called "Relative 1".
+    TwosComplement,
+    /// The delta is offset by 64. 65 means one tick clockwise, 63 means one tick counterclockwise,
+    /// and 64 means no change. Sometimes called "Relative 2" or "binary offset".
+    Offset,
+}
+
+impl RelativeEncoding {
+    /// Decode a raw 7-bit CC value to the number of ticks the encoder was turned by. Positive
+    /// values mean that the encoder was turned clockwise.
+    pub fn decode(self, raw_value: u8) -> i32 {
+        let raw_value = (raw_value & 0x7f) as i32;
+        match self {
+            RelativeEncoding::TwosComplement if raw_value >= 64 => raw_value - 128,
+            RelativeEncoding::TwosComplement => raw_value,
+            RelativeEncoding::Offset => raw_value - 64,
+        }
+    }
+}
+
+/// Applies the deltas sent by a relative encoder to a parameter. This can be used when mapping
+/// [`NoteEvent::MidiCC`][super::NoteEvent::MidiCC] events to parameters, in place of setting the
+/// parameter to the CC's value directly.
+///
+/// Most encoders that support acceleration send larger deltas the faster they're turned. The
+/// acceleration amplifies those larger deltas further so that turning the encoder quickly moves
+/// the parameter across its range with only a few turns, while slow turns still allow fine
+/// adjustments. With an acceleration of `a`, a delta of `n` ticks moves the parameter by
+/// `n * (1 + a * (|n| - 1))` steps. An acceleration of 0 disables this.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeEncoder {
+    encoding: RelativeEncoding,
+    /// The change in the normalized value for a single tick.
+    step_size: f32,
+    /// How much larger deltas are amplified, where 0 disables acceleration.
+    acceleration: f32,
+}
+
+impl RelativeEncoder {
+    /// Create a relative encoder mapping without acceleration that changes the normalized value by
+    /// `step_size` for every tick. A value of `1.0 / 128.0` results in roughly the same resolution
+    /// as a regular 7-bit CC.
+    pub fn new(encoding: RelativeEncoding, step_size: f32) -> Self {
+        nih_debug_assert!(step_size > 0.0);
+
+        Self {
+            encoding,
+            step_size,
+            acceleration: 0.0,
+        }
+    }
+
+    /// Amplify larger deltas so fast turns move the parameter further. See the struct's
+    /// documentation for more information.
+    pub fn with_acceleration(mut self, acceleration: f32) -> Self {
+        nih_debug_assert!(acceleration >= 0.0);
+
+        self.acceleration = acceleration.max(0.0);
+        self
+    }
+
+    /// The encoding used to decode CC values.
+    pub fn encoding(&self) -> RelativeEncoding {
+        self.encoding
+    }
+
+    /// Compute the new normalized value after applying the delta from a CC event's `value` to the
+    /// normalized value `current`. `value` is the normalized `[0, 1]` value from
+    /// [`NoteEvent::MidiCC`][super::NoteEvent::MidiCC]. For stepped parameters with `step_count`
+    /// steps, every tick moves the value by at least one step, and the result is snapped to the
+    /// nearest step.
+    pub fn next_normalized_value(
+        &self,
+        current: f32,
+        value: f32,
+        step_count: Option<usize>,
+    ) -> f32 {
+        let ticks = self
+            .encoding
+            .decode((value.clamp(0.0, 1.0) * 127.0).round() as u8) as f32;
+        let accelerated_ticks = ticks * (1.0 + self.acceleration * (ticks.abs() - 1.0).max(0.0));
+
+        match step_count {
+            Some(step_count) if step_count > 0 => {
+                let step_count = step_count as f32;
+                let step_size = self.step_size.max(step_count.recip());
+                let new_value = (current + accelerated_ticks * step_size).clamp(0.0, 1.0);
+
+                (new_value * step_count).round() / step_count
+            }
+            _ => (current + accelerated_ticks * self.step_size).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Compute the new normalized value for `param` after applying the delta from a CC event's
+    /// `value`. See [`next_normalized_value()`][Self::next_normalized_value()]. The result can be
+    /// passed to [`ParamSetter::set_parameter_normalized()`][crate::prelude::ParamSetter::set_parameter_normalized()]
+    /// or sent to the editor to apply it.
+    pub fn apply<P: Param>(&self, param: &P, value: f32) -> f32 {
+        self.next_normalized_value(
+            param.unmodulated_normalized_value(),
+            value,
+            param.step_count(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Convert a raw 7-bit CC value to the normalized value used in `NoteEvent::MidiCC`.
+    fn cc(raw_value: u8) -> f32 {
+        raw_value as f32 / 127.0
+    }
+
+    #[test]
+    fn decode_encodings() {
+        assert_eq!(RelativeEncoding::TwosComplement.decode(0), 0);
+        assert_eq!(RelativeEncoding::TwosComplement.decode(1), 1);
+        assert_eq!(RelativeEncoding::TwosComplement.decode(63), 63);
+        assert_eq!(RelativeEncoding::TwosComplement.decode(64), -64);
+        assert_eq!(RelativeEncoding::TwosComplement.decode(127), -1);
+
+        assert_eq!(RelativeEncoding::Offset.decode(64), 0);
+        assert_eq!(RelativeEncoding::Offset.decode(65), 1);
+        assert_eq!(RelativeEncoding::Offset.decode(63), -1);
+        assert_eq!(RelativeEncoding::Offset.decode(0), -64);
+    }
+
+    #[test]
+    fn accelerated_burst() {
+        let linear = RelativeEncoder::new(RelativeEncoding::Offset, 0.01);
+        let accelerated = linear.with_acceleration(0.5);
+
+        // The encoder reports increasingly large deltas as it's turned faster
+        let burst = [65, 65, 66, 68, 72, 68, 65];
+        let mut linear_value = 0.0;
+        let mut accelerated_value = 0.0;
+        for raw_value in burst {
+            linear_value = linear.next_normalized_value(linear_value, cc(raw_value), None);
+            accelerated_value =
+                accelerated.next_normalized_value(accelerated_value, cc(raw_value), None);
+        }
+        approx::assert_relative_eq!(linear_value, 0.21, epsilon = 1e-5);
+        // 1 + 1 + 2 * 1.5 + 4 * 2.5 + 8 * 4.5 + 4 * 2.5 + 1
+        approx::assert_relative_eq!(accelerated_value, 0.62, epsilon = 1e-5);
+
+        // Single ticks are never accelerated so slow turns still allow fine adjustments, and
+        // turning the other way moves the value back down
+        let value = accelerated.next_normalized_value(0.5, cc(65), None);
+        approx::assert_relative_eq!(value, 0.51, epsilon = 1e-5);
+        let value = accelerated.next_normalized_value(value, cc(60), None);
+        approx::assert_relative_eq!(value, 0.41, epsilon = 1e-5);
+
+        // The value is clamped to the parameter's range
+        assert_eq!(accelerated.next_normalized_value(0.99, cc(80), None), 1.0);
+    }
+
+    #[test]
+    fn stepped_params_move_by_whole_steps() {
+        let encoder = RelativeEncoder::new(RelativeEncoding::TwosComplement, 0.001);
+
+        // Every tick moves a four step parameter by at least one step
+        assert_eq!(encoder.next_normalized_value(0.5, cc(1), Some(4)), 0.75);
+        assert_eq!(encoder.next_normalized_value(0.5, cc(127), Some(4)), 0.25);
+        assert_eq!(encoder.next_normalized_value(0.5, cc(0), Some(4)), 0.5);
+    }
+}
@@ -9,11 +9,13 @@ use std::fmt::{Debug, Display};
 use std::sync::Arc;
 
 use self::internals::ParamPtr;
+use crate::prelude::ParamSetter;
 
 // The proc-macro for deriving `Params`
 pub use nih_plug_derive::Params;
 
 // Parameter types
+mod appearance;
 mod boolean;
 pub mod enums;
 mod float;
@@ -23,11 +25,16 @@ pub mod internals;
 pub mod persist;
 pub mod range;
 pub mod smoothing;
+mod snapshot;
+mod unit;
 
+pub use appearance::ParamAppearance;
 pub use boolean::BoolParam;
 pub use enums::EnumParam;
 pub use float::FloatParam;
 pub use integer::IntParam;
+pub use snapshot::ParamSnapshot;
+pub use unit::UnitClass;
 
 bitflags::bitflags! {
     /// Flags for controlling a parameter's behavior.
@@ -38,6 +45,15 @@ bitflags::bitflags! {
         /// host's bypass control. Only a single parameter can be marked as a bypass parameter. If
         /// you don't have a bypass parameter, then NIH-plug will add one for you. You will need to
         /// implement this yourself if your plugin introduces latency.
+        ///
+        /// NIH-plug's wrappers do not crossfade between the processed and the dry signal
+        /// themselves, so the plugin is responsible for bypassing without clicks, for instance
+        /// using [`util::Crossfade`][crate::util::Crossfade]. With
+        /// [`Plugin::SAMPLE_ACCURATE_AUTOMATION`][crate::prelude::Plugin::SAMPLE_ACCURATE_AUTOMATION]
+        /// enabled, the block is split at the bypass parameter's automation event like it is for
+        /// any other parameter. A crossfade started from `process()` when the bypass value
+        /// changes then begins at the exact sample the host automated the parameter at, rather
+        /// than at the start of the host's block.
         const BYPASS = 1 << 0;
         /// The parameter cannot be changed from an automation lane. The parameter can however still
         /// be manually changed by the user from either the plugin's own GUI or from the host's
@@ -50,6 +66,11 @@ bitflags::bitflags! {
         /// Don't show this parameter when generating a generic UI for the plugin using one of
         /// NIH-plug's generic UI widgets.
         const HIDE_IN_GENERIC_UI = 1 << 3;
+        /// Changing this parameter triggers expensive recomputations, like regenerating a
+        /// wavetable, so it should not be modulated or automated at audio rate. The parameter can
+        /// still be automated, but in CLAP hosts it will not be exposed as modulatable. VST3 does
+        /// not have an equivalent hint, so this does not affect VST3 plugins.
+        const EXPENSIVE = 1 << 4;
     }
 }
 
@@ -83,6 +104,41 @@ pub trait Param: Display + Debug + sealed::Sealed {
     /// Get the unit label for this parameter, if any.
     fn unit(&self) -> &'static str;
 
+    /// Get the kind of unit this parameter's value is expressed in. This is inferred from the
+    /// [`unit()`][Self::unit()] label unless the parameter overrides it. See [`UnitClass`] for
+    /// more information.
+    fn unit_class(&self) -> UnitClass {
+        UnitClass::from_unit(self.unit())
+    }
+
+    /// Get how this parameter would like to be displayed by generic editors. Parameters with two
+    /// steps are shown as toggles by default, and all other parameters as knobs. The parameter
+    /// types override this with their own defaults. See [`ParamAppearance`] for more information.
+    fn appearance(&self) -> ParamAppearance {
+        match self.step_count() {
+            Some(1) => ParamAppearance::Toggle,
+            _ => ParamAppearance::Knob,
+        }
+    }
+
+    /// Get the name of the group of parameters this parameter is linked with, if any. Parameters
+    /// with the same link group are usually automated together, like the two channels of a stereo
+    /// pair. This is purely informational. Neither CLAP nor VST3 currently have a way to
+    /// communicate these links to the host, so they're only exposed to the plugin and its editor
+    /// through [`Params::link_groups()`].
+    fn link_group(&self) -> Option<&str> {
+        None
+    }
+
+    /// Get the normalized `(min, max)` range this parameter can be modulated within if it has been
+    /// marked as a destination for the plugin's own modulation matrix. Like
+    /// [`link_group()`][Self::link_group()] this is only metadata for the plugin and its editor,
+    /// exposed through [`Params::modulation_destinations()`]. The host's parameter modulation is
+    /// not restricted by this.
+    fn modulation_range(&self) -> Option<(f32, f32)> {
+        None
+    }
+
     /// Get this parameter's polyphonic modulation ID. If this is set for a parameter in a CLAP
     /// plugin, then polyphonic modulation will be enabled for that parameter. Polyphonic modulation
     /// is communicated to the plugin through
@@ -230,6 +286,28 @@ pub(crate) trait ParamMut: Param {
     /// restoring a plugin so everything is in sync. In that case the smoother should completely
     /// reset to the current value.
     fn update_smoother(&self, sample_rate: f32, reset: bool);
+
+    /// Inform the parameter about whether the plugin is currently bypassed. Parameters with a
+    /// neutral value for when the plugin is bypassed will smooth towards that value instead of
+    /// their actual value while the plugin is bypassed.
+    ///
+    /// Returns whether the smoother's target has changed, in which case
+    /// [`update_smoother()`][Self::update_smoother()] should be called afterwards.
+    #[allow(unused_variables)]
+    fn set_bypassed(&self, bypassed: bool) -> bool {
+        false
+    }
+
+    /// Multiply the parameter's smoothing time by `scale`. See
+    /// [`Smoother::set_time_scale()`][crate::prelude::Smoother::set_time_scale()]. Parameters
+    /// without a smoother ignore this.
+    #[allow(unused_variables)]
+    fn set_smoothing_time_scale(&self, scale: f32) {}
+
+    /// Emits debug assertions to make sure that the parameter's range is well-formed and that its
+    /// default value lies within that range. The wrappers call this for every parameter when the
+    /// plugin is created so misconfigured parameters are caught early.
+    fn assert_validity(&self) {}
 }
 
 /// Describes a struct containing parameters and other persistent fields.
@@ -242,6 +320,28 @@ pub(crate) trait ParamMut: Param {
 /// collisions) that will be used to identify the parameter internally so you can safely move it
 /// around and rename the field without breaking compatibility with old presets.
 ///
+/// ## `#[params(auto_id)]`
+///
+/// Writing out an ID for every parameter can get tedious. Adding `#[params(auto_id)]` to the struct
+/// makes every [`BoolParam`], [`FloatParam`], [`IntParam`], and [`EnumParam`] field without any
+/// attributes use its field name as its parameter ID. Fields can still have an explicit
+/// `#[id = "stable"]` attribute, which is also the way to keep a parameter's ID stable when
+/// renaming the field. Custom parameter types always need an explicit ID. If a generated ID
+/// collides with another parameter's ID, then this results in a compile error:
+///
+/// ```compile_fail
+/// use nih_plug::prelude::*;
+///
+/// #[derive(Params)]
+/// #[params(auto_id)]
+/// struct CollidingParams {
+///     #[id = "gain"]
+///     pub output_gain: FloatParam,
+///     // This would also get the `gain` ID
+///     pub gain: FloatParam,
+/// }
+/// ```
+///
 /// ## `#[persist = "key"]`
 ///
 /// The struct can also contain other fields that should be persisted along with the rest of the
@@ -275,6 +375,16 @@ pub(crate) trait ParamMut: Param {
 /// parameter will belong to the group `Foo {array_index + 1}`, and it will have the renamed
 /// parameter ID `bar_{array_index + 1}`. The same thing applies to persistent field keys.
 ///
+/// ## `#[nested(group = "Foo", enabled = "enabled")]`
+///
+/// This ties a [`BoolParam`] from the nested object to the group's enabled state, which is useful
+/// for plugins built from reusable modules that can be switched on and off. The `enabled` value is
+/// the parameter's ID within the nested object, without any ID prefixes or array suffixes. This
+/// can be combined with `id_prefix` and `array`, and it requires a group name. The toggles are
+/// exposed through [`group_enable_params()`][Self::group_enable_params()], and custom editors can
+/// use [`is_group_enabled()`][Self::is_group_enabled()] to find out whether a group's parameters
+/// currently have any effect.
+///
 /// # Safety
 ///
 /// This implementation is safe when using from the wrapper because the plugin's returned `Params`
@@ -311,6 +421,110 @@ pub unsafe trait Params: 'static + Send + Sync {
     /// [`persist::deserialize_field()`] under the hood.
     #[allow(unused_variables)]
     fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {}
+
+    /// Capture the current normalized values of all parameters. The snapshot can later be restored
+    /// with [`restore_snapshot()`][Self::restore_snapshot()], which makes it easy to implement A/B
+    /// comparisons. This allocates and it should only be called from the main thread, for instance
+    /// from the plugin's editor.
+    fn snapshot(&self) -> ParamSnapshot {
+        ParamSnapshot::from_param_map(&self.param_map())
+    }
+
+    /// Set all parameters back to the values from a snapshot created with
+    /// [`snapshot()`][Self::snapshot()]. The parameters are set through the [`ParamSetter`] so the
+    /// host gets informed about the changes, and only parameters that have changed since the
+    /// snapshot was taken are touched. This should only be called from the main thread.
+    fn restore_snapshot(&self, snapshot: &ParamSnapshot, setter: &ParamSetter) {
+        snapshot.restore(&self.param_map(), setter)
+    }
+
+    /// Multiply the smoothing times of all parameters by `scale`, for instance to implement a
+    /// global smoothing amount control that switches between snappy and zipper free parameter
+    /// changes. A scale of `1.0` uses the smoothing times the parameters were created with. The new
+    /// scale is only applied the next time a parameter's value changes, so this does not need to
+    /// touch any active smoothing. This allocates and it should only be called from the main
+    /// thread, for instance from the plugin's editor. See
+    /// [`Smoother::set_time_scale()`][crate::prelude::Smoother::set_time_scale()] for changing the
+    /// scale of a single parameter.
+    fn set_smoothing_time_scale(&self, scale: f32) {
+        for (_, param_ptr, _) in self.param_map() {
+            unsafe { param_ptr.set_smoothing_time_scale(scale) };
+        }
+    }
+
+    /// Get the [`UnitClass`] of every parameter as `(param_id, unit_class)` pairs, in the same
+    /// order as [`param_map()`][Self::param_map()]. Custom editors can use this to format related
+    /// parameters consistently. This allocates and it should only be called from the main thread.
+    fn unit_classes(&self) -> Vec<(String, UnitClass)> {
+        self.param_map()
+            .into_iter()
+            .map(|(param_id, param_ptr, _)| (param_id, unsafe { param_ptr.unit_class() }))
+            .collect()
+    }
+
+    /// Get the [`ParamAppearance`] of every parameter as `(param_id, appearance)` pairs, in the
+    /// same order as [`param_map()`][Self::param_map()]. Generic editors can use this to pick a
+    /// widget for every parameter. This allocates and it should only be called from the main
+    /// thread.
+    fn appearances(&self) -> Vec<(String, ParamAppearance)> {
+        self.param_map()
+            .into_iter()
+            .map(|(param_id, param_ptr, _)| (param_id, unsafe { param_ptr.appearance() }))
+            .collect()
+    }
+
+    /// Get the link group of every linked parameter as `(param_id, link_group)` pairs, in the same
+    /// order as [`param_map()`][Self::param_map()]. Parameters without a link group are skipped.
+    /// See [`Param::link_group()`]. This allocates and it should only be called from the main
+    /// thread.
+    fn link_groups(&self) -> Vec<(String, String)> {
+        self.param_map()
+            .into_iter()
+            .filter_map(|(param_id, param_ptr, _)| {
+                unsafe { param_ptr.link_group() }
+                    .map(|link_group| (param_id, link_group.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Get the normalized modulation range of every parameter that can be targeted by the plugin's
+    /// internal modulation matrix as `(param_id, (min, max))` pairs, in the same order as
+    /// [`param_map()`][Self::param_map()]. Parameters that are not modulation destinations are
+    /// skipped. See [`Param::modulation_range()`]. This allocates and it should only be called
+    /// from the main thread.
+    fn modulation_destinations(&self) -> Vec<(String, (f32, f32))> {
+        self.param_map()
+            .into_iter()
+            .filter_map(|(param_id, param_ptr, _)| {
+                unsafe { param_ptr.modulation_range() }.map(|range| (param_id, range))
+            })
+            .collect()
+    }
+
+    /// Get the [`BoolParam`]s that enable or disable entire groups as `(group, param_ptr)` pairs.
+    /// The groups use the same slash delimited format as [`param_map()`][Self::param_map()]. The
+    /// derive macro does this for all nested objects with the `#[nested(group = "...", enabled =
+    /// "...")]` attribute, and it also includes the toggles from nested objects.
+    fn group_enable_params(&self) -> Vec<(String, ParamPtr)> {
+        Vec::new()
+    }
+
+    /// Whether a group's parameters are currently enabled. A group is disabled when the toggle for
+    /// that group or for one of its parent groups is turned off, see
+    /// [`group_enable_params()`][Self::group_enable_params()]. Groups without a toggle are always
+    /// enabled. This allocates and it should only be called from the main thread.
+    fn is_group_enabled(&self, group: &str) -> bool {
+        self.group_enable_params()
+            .into_iter()
+            .all(|(enable_group, param_ptr)| {
+                let is_affected = match group.strip_prefix(enable_group.as_str()) {
+                    Some(remainder) => remainder.is_empty() || remainder.starts_with('/'),
+                    None => false,
+                };
+
+                !is_affected || unsafe { param_ptr.modulated_normalized_value() } > 0.5
+            })
+    }
 }
 
 /// This may be useful when building generic UIs using nested `Params` objects.
@@ -326,4 +540,8 @@ unsafe impl<P: Params> Params for Arc<P> {
     fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {
         self.as_ref().deserialize_fields(serialized)
     }
+
+    fn group_enable_params(&self) -> Vec<(String, ParamPtr)> {
+        self.as_ref().group_enable_params()
+    }
 }
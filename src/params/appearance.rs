@@ -0,0 +1,122 @@
+//! Appearance hints for parameters, so generic editors know which widget to use.
+
+/// How a parameter would like to be displayed. Generic editors can use this to render knobs,
+/// sliders, toggles, or dropdowns depending on the parameter instead of using the same widget for
+/// everything. This is metadata only and does not affect how the parameter behaves. Editors are
+/// free to ignore the hint, for instance when they don't have a widget for it.
+///
+/// Every parameter type has a sensible default: [`BoolParam`][super::BoolParam]s are toggles,
+/// [`EnumParam`][super::EnumParam]s are dropdowns, and [`FloatParam`][super::FloatParam]s and
+/// [`IntParam`][super::IntParam]s are knobs. This can be changed using `with_appearance()`. For
+/// CLAP plugins, stepped parameters that should be shown as dropdowns are also marked as
+/// enumerated parameters for the host's generic UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamAppearance {
+    /// A rotary knob.
+    Knob,
+    /// A horizontal or vertical slider.
+    Slider,
+    /// An on/off toggle or a button.
+    Toggle,
+    /// A dropdown listing all of the parameter's values.
+    Dropdown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{
+        BoolParam, Enum, EnumParam, FloatParam, FloatRange, IntParam, IntRange, Param, ParamPtr,
+        Params,
+    };
+
+    #[derive(Debug, PartialEq)]
+    enum Waveform {
+        Sine,
+        Saw,
+    }
+
+    // The derive macro uses absolute `nih_plug` paths, so it can't be used from within this crate
+    impl Enum for Waveform {
+        fn variants() -> &'static [&'static str] {
+            &["Sine", "Saw"]
+        }
+
+        fn ids() -> Option<&'static [&'static str]> {
+            None
+        }
+
+        fn to_index(self) -> usize {
+            match self {
+                Waveform::Sine => 0,
+                Waveform::Saw => 1,
+            }
+        }
+
+        fn from_index(index: usize) -> Self {
+            match index {
+                1 => Waveform::Saw,
+                _ => Waveform::Sine,
+            }
+        }
+    }
+
+    struct OscillatorParams {
+        gain: FloatParam,
+        detune: FloatParam,
+        voices: IntParam,
+        octave: IntParam,
+        waveform: EnumParam<Waveform>,
+        sync: BoolParam,
+    }
+
+    unsafe impl Params for OscillatorParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("detune"), self.detune.as_ptr(), String::new()),
+                (String::from("voices"), self.voices.as_ptr(), String::new()),
+                (String::from("octave"), self.octave.as_ptr(), String::new()),
+                (
+                    String::from("waveform"),
+                    self.waveform.as_ptr(),
+                    String::new(),
+                ),
+                (String::from("sync"), self.sync.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    #[test]
+    fn params_report_appearances() {
+        let params = OscillatorParams {
+            gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            detune: FloatParam::new("Detune", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_appearance(ParamAppearance::Slider),
+            voices: IntParam::new("Voices", 1, IntRange::Linear { min: 1, max: 16 }),
+            octave: IntParam::new("Octave", 0, IntRange::Linear { min: -2, max: 2 })
+                .with_appearance(ParamAppearance::Dropdown),
+            waveform: EnumParam::new("Waveform", Waveform::Sine),
+            sync: BoolParam::new("Sync", false),
+        };
+
+        assert_eq!(
+            params.appearances(),
+            [
+                (String::from("gain"), ParamAppearance::Knob),
+                (String::from("detune"), ParamAppearance::Slider),
+                (String::from("voices"), ParamAppearance::Knob),
+                (String::from("octave"), ParamAppearance::Dropdown),
+                (String::from("waveform"), ParamAppearance::Dropdown),
+                (String::from("sync"), ParamAppearance::Toggle),
+            ]
+        );
+        assert_eq!(params.sync.appearance(), ParamAppearance::Toggle);
+        assert_eq!(
+            BoolParam::new("Trigger", false)
+                .with_appearance(ParamAppearance::Knob)
+                .appearance(),
+            ParamAppearance::Knob
+        );
+    }
+}
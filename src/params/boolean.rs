@@ -6,7 +6,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use super::internals::ParamPtr;
-use super::{Param, ParamFlags, ParamMut};
+use super::{Param, ParamAppearance, ParamFlags, ParamMut};
 
 /// A simple boolean parameter.
 pub struct BoolParam {
@@ -37,6 +37,12 @@ pub struct BoolParam {
 
     /// The parameter's human readable display name.
     name: String,
+    /// How this parameter would like to be displayed by generic editors, if it has been set
+    /// explicitly. Defaults to [`ParamAppearance::Toggle`] otherwise.
+    appearance: Option<ParamAppearance>,
+    /// The group of parameters this parameter is linked with, if any. See
+    /// [`Param::link_group()`].
+    link_group: Option<String>,
     /// If this parameter has been marked as polyphonically modulatable, then this will be a unique
     /// integer identifying the parameter. Because this value is determined by the plugin itself,
     /// the plugin can easily map
@@ -86,6 +92,14 @@ impl Param for BoolParam {
         ""
     }
 
+    fn appearance(&self) -> ParamAppearance {
+        self.appearance.unwrap_or(ParamAppearance::Toggle)
+    }
+
+    fn link_group(&self) -> Option<&str> {
+        self.link_group.as_deref()
+    }
+
     fn poly_modulation_id(&self) -> Option<u32> {
         self.poly_modulation_id
     }
@@ -242,6 +256,8 @@ impl BoolParam {
             value_changed: None,
 
             name: name.into(),
+            appearance: None,
+            link_group: None,
             poly_modulation_id: None,
             value_to_string: None,
             string_to_value: None,
@@ -301,6 +317,20 @@ impl BoolParam {
         self
     }
 
+    /// Set how this parameter would like to be displayed by generic editors. Parameters of this
+    /// type are shown as a [`ParamAppearance::Toggle`] by default. This is only a hint, and it does
+    /// not affect how the parameter behaves. See [`ParamAppearance`] for more information.
+    pub fn with_appearance(mut self, appearance: ParamAppearance) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    /// Add this parameter to a named link group. See [`Param::link_group()`].
+    pub fn with_link_group(mut self, link_group: impl Into<String>) -> Self {
+        self.link_group = Some(link_group.into());
+        self
+    }
+
     /// Mark this parameter as a bypass parameter. Plugin hosts can integrate this parameter into
     /// their UI. Only a single [`BoolParam`] can be a bypass parameter, and NIH-plug will add one
     /// if you don't create one yourself. You will need to implement this yourself if your plugin
@@ -332,4 +362,12 @@ impl BoolParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Mark the parameter as expensive to change, for parameters that trigger expensive
+    /// recomputations like regenerating a wavetable. Hosts that support it will then not modulate
+    /// this parameter at audio rate. See [`ParamFlags::EXPENSIVE`].
+    pub fn expensive(mut self) -> Self {
+        self.flags.insert(ParamFlags::EXPENSIVE);
+        self
+    }
 }
@@ -6,7 +6,7 @@ use std::sync::Arc;
 
 use super::internals::ParamPtr;
 use super::range::IntRange;
-use super::{IntParam, Param, ParamFlags, ParamMut};
+use super::{IntParam, Param, ParamAppearance, ParamFlags, ParamMut};
 
 // Re-export the derive macro
 pub use nih_plug_derive::Enum;
@@ -92,6 +92,12 @@ pub struct EnumParamInner {
     /// these identifiers are used when saving enum parameter values to the state. Otherwise the
     /// index is used.
     ids: Option<&'static [&'static str]>,
+    /// How this parameter would like to be displayed by generic editors, if it has been set
+    /// explicitly. Defaults to [`ParamAppearance::Dropdown`] otherwise.
+    appearance: Option<ParamAppearance>,
+    /// The group of parameters this parameter is linked with, if any. See
+    /// [`Param::link_group()`].
+    link_group: Option<String>,
 }
 
 impl<T: Enum + PartialEq> Display for EnumParam<T> {
@@ -141,6 +147,14 @@ impl<T: Enum + PartialEq> Param for EnumParam<T> {
         self.inner.unit()
     }
 
+    fn appearance(&self) -> ParamAppearance {
+        self.inner.appearance()
+    }
+
+    fn link_group(&self) -> Option<&str> {
+        self.inner.link_group()
+    }
+
     fn poly_modulation_id(&self) -> Option<u32> {
         self.inner.poly_modulation_id()
     }
@@ -224,6 +238,14 @@ impl Param for EnumParamInner {
         ""
     }
 
+    fn appearance(&self) -> ParamAppearance {
+        self.appearance.unwrap_or(ParamAppearance::Dropdown)
+    }
+
+    fn link_group(&self) -> Option<&str> {
+        self.link_group.as_deref()
+    }
+
     fn poly_modulation_id(&self) -> Option<u32> {
         self.inner.poly_modulation_id()
     }
@@ -313,6 +335,14 @@ impl<T: Enum + PartialEq> ParamMut for EnumParam<T> {
     fn update_smoother(&self, sample_rate: f32, reset: bool) {
         self.inner.update_smoother(sample_rate, reset)
     }
+
+    fn set_smoothing_time_scale(&self, scale: f32) {
+        self.inner.set_smoothing_time_scale(scale)
+    }
+
+    fn assert_validity(&self) {
+        self.inner.assert_validity()
+    }
 }
 
 impl ParamMut for EnumParamInner {
@@ -331,6 +361,14 @@ impl ParamMut for EnumParamInner {
     fn update_smoother(&self, sample_rate: f32, reset: bool) {
         self.inner.update_smoother(sample_rate, reset)
     }
+
+    fn set_smoothing_time_scale(&self, scale: f32) {
+        self.inner.set_smoothing_time_scale(scale)
+    }
+
+    fn assert_validity(&self) {
+        self.inner.assert_validity()
+    }
 }
 
 impl<T: Enum + PartialEq + 'static> EnumParam<T> {
@@ -352,6 +390,8 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
                 ),
                 variants,
                 ids,
+                appearance: None,
+                link_group: None,
             },
             _marker: PhantomData,
         }
@@ -391,6 +431,20 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         self
     }
 
+    /// Set how this parameter would like to be displayed by generic editors. Parameters of this
+    /// type are shown as a [`ParamAppearance::Dropdown`] by default. This is only a hint, and it
+    /// does not affect how the parameter behaves. See [`ParamAppearance`] for more information.
+    pub fn with_appearance(mut self, appearance: ParamAppearance) -> Self {
+        self.inner.appearance = Some(appearance);
+        self
+    }
+
+    /// Add this parameter to a named link group. See [`Param::link_group()`].
+    pub fn with_link_group(mut self, link_group: impl Into<String>) -> Self {
+        self.inner.link_group = Some(link_group.into());
+        self
+    }
+
     /// Mark the parameter as non-automatable. This means that the parameter cannot be changed from
     /// an automation lane. The parameter can however still be manually changed by the user from
     /// either the plugin's own GUI or from the host's generic UI.
@@ -413,6 +467,14 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         self.inner.inner = self.inner.inner.hide_in_generic_ui();
         self
     }
+
+    /// Mark the parameter as expensive to change, for parameters that trigger expensive
+    /// recomputations like regenerating a wavetable. Hosts that support it will then not modulate
+    /// this parameter at audio rate. See [`ParamFlags::EXPENSIVE`].
+    pub fn expensive(mut self) -> Self {
+        self.inner.inner = self.inner.inner.expensive();
+        self
+    }
 }
 
 impl EnumParamInner {
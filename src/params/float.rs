@@ -2,13 +2,14 @@
 
 use atomic_float::AtomicF32;
 use std::fmt::{Debug, Display};
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use super::internals::ParamPtr;
 use super::range::FloatRange;
 use super::smoothing::{Smoother, SmoothingStyle};
-use super::{Param, ParamFlags, ParamMut};
+use super::{Param, ParamAppearance, ParamFlags, ParamMut, UnitClass};
+use crate::formatters;
 
 /// A floating point parameter that's stored unnormalized. The range is used for the normalization
 /// process.
@@ -32,6 +33,11 @@ pub struct FloatParam {
     /// An optional smoother that will automatically interpolate between the new automation values
     /// set by the host.
     pub smoothed: Smoother<f32>,
+    /// The plain value the smoother should move towards while the plugin is bypassed, if set. See
+    /// [`with_neutral_on_bypass()`][Self::with_neutral_on_bypass()].
+    neutral_on_bypass: Option<f32>,
+    /// Whether the plugin is currently bypassed. Only used when `neutral_on_bypass` is set.
+    bypassed: AtomicBool,
 
     /// Flags to control the parameter's behavior. See [`ParamFlags`].
     flags: ParamFlags,
@@ -51,11 +57,28 @@ pub struct FloatParam {
     /// input. If this is set and if [`value_to_string`][Self::value_to_string] is not set, then
     /// this is also used when formatting the parameter. This must be a positive, nonzero number.
     step_size: Option<f32>,
+    /// The number of decimal digits to display when formatting the parameter with the default
+    /// formatter. Takes precedence over the number of digits derived from
+    /// [`step_size`][Self::step_size]. Not used when [`value_to_string`][Self::value_to_string] is
+    /// set.
+    precision: Option<usize>,
     /// The parameter's human readable display name.
     name: String,
     /// The parameter value's unit, added after [`value_to_string`][Self::value_to_string] if that
     /// is set. NIH-plug will not automatically add a space before the unit.
     unit: &'static str,
+    /// The kind of unit this parameter's value is expressed in, if it has been set explicitly.
+    /// Otherwise this is inferred from [`unit`][Self::unit].
+    unit_class: Option<UnitClass>,
+    /// How this parameter would like to be displayed by generic editors, if it has been set
+    /// explicitly. Defaults to [`ParamAppearance::Knob`] otherwise.
+    appearance: Option<ParamAppearance>,
+    /// The group of parameters this parameter is linked with, if any. See
+    /// [`Param::link_group()`].
+    link_group: Option<String>,
+    /// The plain `(min, max)` range the plugin's internal modulation matrix may modulate this
+    /// parameter within, if it's a modulation destination. See [`Param::modulation_range()`].
+    modulation_range: Option<(f32, f32)>,
     /// If this parameter has been marked as polyphonically modulatable, then this will be a unique
     /// integer identifying the parameter. Because this value is determined by the plugin itself,
     /// the plugin can easily map
@@ -75,13 +98,9 @@ pub struct FloatParam {
 
 impl Display for FloatParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match (&self.value_to_string, &self.step_size) {
-            (Some(func), _) => write!(f, "{}{}", func(self.value()), self.unit),
-            (None, Some(step_size)) => {
-                let num_digits = decimals_from_step_size(*step_size);
-                write!(f, "{:.num_digits$}{}", self.value(), self.unit)
-            }
-            _ => write!(f, "{}{}", self.value(), self.unit),
+        match &self.value_to_string {
+            Some(func) => write!(f, "{}{}", func(self.value()), self.unit),
+            None => write!(f, "{}{}", self.format_default(self.value()), self.unit),
         }
     }
 }
@@ -111,6 +130,26 @@ impl Param for FloatParam {
         self.unit
     }
 
+    fn unit_class(&self) -> UnitClass {
+        self.unit_class
+            .unwrap_or_else(|| UnitClass::from_unit(self.unit))
+    }
+
+    fn appearance(&self) -> ParamAppearance {
+        self.appearance.unwrap_or(ParamAppearance::Knob)
+    }
+
+    fn link_group(&self) -> Option<&str> {
+        self.link_group.as_deref()
+    }
+
+    fn modulation_range(&self) -> Option<(f32, f32)> {
+        self.modulation_range.map(|(min, max)| {
+            let (min, max) = (self.preview_normalized(min), self.preview_normalized(max));
+            (min.min(max), min.max(max))
+        })
+    }
+
     fn poly_modulation_id(&self) -> Option<u32> {
         self.poly_modulation_id
     }
@@ -154,19 +193,11 @@ impl Param for FloatParam {
 
     fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String {
         let value = self.preview_plain(normalized);
-        match (&self.value_to_string, &self.step_size, include_unit) {
-            (Some(f), _, true) => format!("{}{}", f(value), self.unit),
-            (Some(f), _, false) => f(value),
-            (None, Some(step_size), true) => {
-                let num_digits = decimals_from_step_size(*step_size);
-                format!("{:.num_digits$}{}", value, self.unit)
-            }
-            (None, Some(step_size), false) => {
-                let num_digits = decimals_from_step_size(*step_size);
-                format!("{value:.num_digits$}")
-            }
-            (None, None, true) => format!("{}{}", value, self.unit),
-            (None, None, false) => format!("{value}"),
+        match (&self.value_to_string, include_unit) {
+            (Some(f), true) => format!("{}{}", f(value), self.unit),
+            (Some(f), false) => f(value),
+            (None, true) => format!("{}{}", self.format_default(value), self.unit),
+            (None, false) => self.format_default(value),
         }
     }
 
@@ -174,7 +205,7 @@ impl Param for FloatParam {
         let value = match &self.string_to_value {
             Some(f) => f(string.trim()),
             // In the CLAP wrapper the unit will be included, so make sure to handle that
-            None => string.trim().trim_end_matches(self.unit).parse().ok(),
+            None => formatters::parse_f32(string.trim().trim_end_matches(self.unit)),
         }?;
 
         Some(self.preview_normalized(value))
@@ -256,11 +287,42 @@ impl ParamMut for FloatParam {
     }
 
     fn update_smoother(&self, sample_rate: f32, reset: bool) {
+        let target = match self.neutral_on_bypass {
+            Some(neutral) if self.bypassed.load(Ordering::Relaxed) => neutral,
+            _ => self.modulated_plain_value(),
+        };
+
         if reset {
-            self.smoothed.reset(self.modulated_plain_value());
+            self.smoothed.reset(target);
         } else {
-            self.smoothed
-                .set_target(sample_rate, self.modulated_plain_value());
+            self.smoothed.set_target(sample_rate, target);
+        }
+    }
+
+    fn set_smoothing_time_scale(&self, scale: f32) {
+        self.smoothed.set_time_scale(scale);
+    }
+
+    fn set_bypassed(&self, bypassed: bool) -> bool {
+        let was_bypassed = self.bypassed.swap(bypassed, Ordering::Relaxed);
+        self.neutral_on_bypass.is_some() && was_bypassed != bypassed
+    }
+
+    fn assert_validity(&self) {
+        self.range.assert_validity();
+        nih_debug_assert!(
+            self.range.contains(self.default),
+            "The default value ({}) of parameter '{}' lies outside of its range",
+            self.default,
+            self.name
+        );
+        if let Some(neutral) = self.neutral_on_bypass {
+            nih_debug_assert!(
+                self.range.contains(neutral),
+                "The neutral bypass value ({}) of parameter '{}' lies outside of its range",
+                neutral,
+                self.name
+            );
         }
     }
 }
@@ -279,14 +341,21 @@ impl FloatParam {
             modulation_offset: AtomicF32::new(0.0),
             default,
             smoothed: Smoother::none(),
+            neutral_on_bypass: None,
+            bypassed: AtomicBool::new(false),
 
             flags: ParamFlags::default(),
             value_changed: None,
 
             range,
             step_size: None,
+            precision: None,
             name: name.into(),
             unit: "",
+            unit_class: None,
+            appearance: None,
+            link_group: None,
+            modulation_range: None,
             poly_modulation_id: None,
             value_to_string: None,
             string_to_value: None,
@@ -300,6 +369,24 @@ impl FloatParam {
         self.modulated_plain_value()
     }
 
+    /// Apply a per-voice polyphonic modulation offset to a plain value. `plain` is usually the
+    /// parameter's smoothed value from `param.smoothed.next()`, and `normalized_offset` is the
+    /// offset from a [`NoteEvent::PolyModulation`][crate::prelude::NoteEvent::PolyModulation]
+    /// event. The offset is added in the normalized domain and applied immediately, so the
+    /// parameter's smoother only ever smooths the base value and never fights the host's per-voice
+    /// modulation. See
+    /// [`PolyModulationOffsets::modulated_value()`][crate::util::PolyModulationOffsets::modulated_value()]
+    /// for a convenient way to use this.
+    #[inline]
+    pub fn apply_poly_modulation(&self, plain: f32, normalized_offset: f32) -> f32 {
+        if normalized_offset == 0.0 {
+            plain
+        } else {
+            let normalized = (self.preview_normalized(plain) + normalized_offset).clamp(0.0, 1.0);
+            self.preview_plain(normalized)
+        }
+    }
+
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
     /// parameter in [`NoteEvent::PolyModulation`][crate::prelude::NoteEvent::PolyModulation]
     /// events, and must thus be unique between _all_ polyphonically modulatable parameters. See the
@@ -319,6 +406,8 @@ impl FloatParam {
 
     /// Set up a smoother that can gradually interpolate changes made to this parameter, preventing
     /// clicks and zipper noises.
+    /// The smoothing style can later be changed at runtime using
+    /// [`Smoother::set_style()`][crate::prelude::Smoother::set_style()] on the `smoothed` field.
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multiplying by zero
@@ -340,6 +429,18 @@ impl FloatParam {
         self
     }
 
+    /// Make the parameter's smoother move towards `neutral` while the plugin is bypassed, and back
+    /// to the parameter's actual value when the plugin is no longer bypassed. This can be used to
+    /// fade out an internal gain stage or a wet signal without clicks when the plugin gets
+    /// bypassed. The parameter's value itself is not changed, so this only has an effect when the
+    /// plugin reads the parameter's value from `smoothed`. `neutral` is a plain value and should
+    /// lie within the parameter's range. This requires the plugin to have a bypass parameter, see
+    /// [`BoolParam::make_bypass()`][super::BoolParam::make_bypass()].
+    pub fn with_neutral_on_bypass(mut self, neutral: f32) -> Self {
+        self.neutral_on_bypass = Some(neutral);
+        self
+    }
+
     /// Run a callback whenever this parameter's value changes. The argument passed to this function
     /// is the parameter's new value. This should not do anything expensive as it may be called
     /// multiple times in rapid succession, and it can be run from both the GUI and the audio
@@ -357,6 +458,39 @@ impl FloatParam {
         self
     }
 
+    /// Set the kind of unit this parameter's value is expressed in. By default this is inferred
+    /// from the unit label set with [`with_unit()`][Self::with_unit()], so this only needs to be
+    /// set when the unit is part of the [`value_to_string`][Self::with_value_to_string()]
+    /// function or when the label is not recognized. See [`UnitClass`] for more information.
+    pub fn with_unit_class(mut self, unit_class: UnitClass) -> Self {
+        self.unit_class = Some(unit_class);
+        self
+    }
+
+    /// Set how this parameter would like to be displayed by generic editors. Parameters of this
+    /// type are shown as a [`ParamAppearance::Knob`] by default. This is only a hint, and it does
+    /// not affect how the parameter behaves. See [`ParamAppearance`] for more information.
+    pub fn with_appearance(mut self, appearance: ParamAppearance) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    /// Add this parameter to a named link group. See [`Param::link_group()`].
+    pub fn with_link_group(mut self, link_group: impl Into<String>) -> Self {
+        self.link_group = Some(link_group.into());
+        self
+    }
+
+    /// Mark this parameter as a destination for the plugin's own modulation matrix that may be
+    /// modulated between the plain values `min` and `max`. Like link groups, this is only metadata
+    /// for the plugin and its editor and it does not affect how the parameter behaves. See
+    /// [`Param::modulation_range()`].
+    pub fn with_modulation_range(mut self, min: f32, max: f32) -> Self {
+        nih_debug_assert!(min <= max);
+        self.modulation_range = Some((min, max));
+        self
+    }
+
     /// Set the distance between steps of a [FloatParam]. Mostly useful for quantizing GUI input. If
     /// this is set and a [`value_to_string`][Self::with_value_to_string()] function is not set,
     /// then this is also used when formatting the parameter. This must be a positive, nonzero
@@ -366,6 +500,18 @@ impl FloatParam {
         self
     }
 
+    /// Display this many decimal digits when formatting the parameter's value. This takes
+    /// precedence over the number of digits derived from the
+    /// [step size][Self::with_step_size()]. Custom
+    /// [`value_to_string`][Self::with_value_to_string()] functions are not affected by this, so
+    /// when using formatters like [`v2s_f32_gain_to_db()`][crate::formatters::v2s_f32_gain_to_db()]
+    /// or [`v2s_f32_hz_then_khz()`][crate::formatters::v2s_f32_hz_then_khz()] the number of digits
+    /// should be passed to the formatter instead. Those format their values the same way.
+    pub fn with_precision(mut self, digits: usize) -> Self {
+        self.precision = Some(digits);
+        self
+    }
+
     /// Use a custom conversion function to convert the plain, unnormalized value to a
     /// string.
     pub fn with_value_to_string(
@@ -412,6 +558,27 @@ impl FloatParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Mark the parameter as expensive to change, for parameters that trigger expensive
+    /// recomputations like regenerating a wavetable. Hosts that support it will then not modulate
+    /// this parameter at audio rate. See [`ParamFlags::EXPENSIVE`].
+    pub fn expensive(mut self) -> Self {
+        self.flags.insert(ParamFlags::EXPENSIVE);
+        self
+    }
+
+    /// Format a plain value using the default formatter. This uses the configured precision if it
+    /// is set, or the step size's number of decimals otherwise.
+    fn format_default(&self, value: f32) -> String {
+        match (self.precision, self.step_size) {
+            (Some(digits), _) => formatters::format_f32_rounded(value, digits),
+            (None, Some(step_size)) => {
+                let num_digits = decimals_from_step_size(step_size);
+                format!("{value:.num_digits$}")
+            }
+            (None, None) => format!("{value}"),
+        }
+    }
 }
 
 /// Calculate how many decimals to round to when displaying a floating point value with a specific
@@ -431,3 +598,86 @@ fn decimals_from_step_size(step_size: f32) -> usize {
 
     num_digits as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_parser_accepts_decimal_comma() {
+        let param =
+            FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 2.0 }).with_unit(" x");
+        assert_eq!(param.string_to_normalized_value("1,5 x"), Some(0.75));
+        assert_eq!(
+            param.string_to_normalized_value("1,5"),
+            param.string_to_normalized_value("1.5")
+        );
+        // The output still uses a period
+        assert_eq!(param.normalized_value_to_string(0.75, false), "1.5");
+    }
+
+    #[test]
+    fn valid_param() {
+        let param = FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 });
+        param.assert_validity();
+    }
+
+    #[test]
+    #[should_panic(expected = "lies outside of its range")]
+    fn default_outside_of_range() {
+        let param = FloatParam::new("Gain", 2.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+        param.assert_validity();
+    }
+
+    #[test]
+    fn precision() {
+        let param = FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+            .with_precision(3);
+        assert_eq!(param.to_string(), "0.500");
+        assert_eq!(param.normalized_value_to_string(0.25, false), "0.250");
+
+        let param = FloatParam::new(
+            "Frequency",
+            1000.0,
+            FloatRange::Skewed {
+                min: 20.0,
+                max: 20_000.0,
+                factor: FloatRange::skew_factor(-2.0),
+            },
+        )
+        .with_precision(0)
+        .with_unit(" Hz");
+        assert_eq!(param.to_string(), "1000 Hz");
+        assert_eq!(param.normalized_value_to_string(1.0, true), "20000 Hz");
+
+        let param = FloatParam::new(
+            "Pan",
+            0.0,
+            FloatRange::Linear {
+                min: -1.0,
+                max: 1.0,
+            },
+        )
+        .with_precision(2);
+        assert_eq!(param.normalized_value_to_string(0.4999, false), "0.00");
+        assert_eq!(param.normalized_value_to_string(0.0, false), "-1.00");
+    }
+
+    #[test]
+    fn precision_overrides_step_size() {
+        let param = FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+            .with_step_size(0.1);
+        assert_eq!(param.to_string(), "0.5");
+
+        let param = param.with_precision(2);
+        assert_eq!(param.to_string(), "0.50");
+    }
+
+    #[test]
+    fn custom_formatter_overrides_precision() {
+        let param = FloatParam::new("Gain", 1.0, FloatRange::Linear { min: 0.0, max: 2.0 })
+            .with_precision(4)
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(1));
+        assert_eq!(param.to_string(), "0.0");
+    }
+}
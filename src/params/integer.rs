@@ -8,7 +8,7 @@ use std::sync::Arc;
 use super::internals::ParamPtr;
 use super::range::IntRange;
 use super::smoothing::{Smoother, SmoothingStyle};
-use super::{Param, ParamFlags, ParamMut};
+use super::{Param, ParamAppearance, ParamFlags, ParamMut, UnitClass};
 
 /// A discrete integer parameter that's stored unnormalized. The range is used for the normalization
 /// process.
@@ -52,6 +52,18 @@ pub struct IntParam {
     /// The parameter value's unit, added after `value_to_string` if that is set. NIH-plug will not
     /// automatically add a space before the unit.
     unit: &'static str,
+    /// The kind of unit this parameter's value is expressed in, if it has been set explicitly.
+    /// Otherwise this is inferred from [`unit`][Self::unit].
+    unit_class: Option<UnitClass>,
+    /// How this parameter would like to be displayed by generic editors, if it has been set
+    /// explicitly. Defaults to [`ParamAppearance::Knob`] otherwise.
+    appearance: Option<ParamAppearance>,
+    /// The group of parameters this parameter is linked with, if any. See
+    /// [`Param::link_group()`].
+    link_group: Option<String>,
+    /// The plain `(min, max)` range the plugin's internal modulation matrix may modulate this
+    /// parameter within, if it's a modulation destination. See [`Param::modulation_range()`].
+    modulation_range: Option<(i32, i32)>,
     /// If this parameter has been marked as polyphonically modulatable, then this will be a unique
     /// integer identifying the parameter. Because this value is determined by the plugin itself,
     /// the plugin can easily map
@@ -103,6 +115,26 @@ impl Param for IntParam {
         self.unit
     }
 
+    fn unit_class(&self) -> UnitClass {
+        self.unit_class
+            .unwrap_or_else(|| UnitClass::from_unit(self.unit))
+    }
+
+    fn appearance(&self) -> ParamAppearance {
+        self.appearance.unwrap_or(ParamAppearance::Knob)
+    }
+
+    fn link_group(&self) -> Option<&str> {
+        self.link_group.as_deref()
+    }
+
+    fn modulation_range(&self) -> Option<(f32, f32)> {
+        self.modulation_range.map(|(min, max)| {
+            let (min, max) = (self.preview_normalized(min), self.preview_normalized(max));
+            (min.min(max), min.max(max))
+        })
+    }
+
     fn poly_modulation_id(&self) -> Option<u32> {
         self.poly_modulation_id
     }
@@ -243,6 +275,20 @@ impl ParamMut for IntParam {
                 .set_target(sample_rate, self.modulated_plain_value());
         }
     }
+
+    fn set_smoothing_time_scale(&self, scale: f32) {
+        self.smoothed.set_time_scale(scale);
+    }
+
+    fn assert_validity(&self) {
+        self.range.assert_validity();
+        nih_debug_assert!(
+            self.range.contains(self.default),
+            "The default value ({}) of parameter '{}' lies outside of its range",
+            self.default,
+            self.name
+        );
+    }
 }
 
 impl IntParam {
@@ -266,6 +312,10 @@ impl IntParam {
             range,
             name: name.into(),
             unit: "",
+            unit_class: None,
+            appearance: None,
+            link_group: None,
+            modulation_range: None,
             poly_modulation_id: None,
             value_to_string: None,
             string_to_value: None,
@@ -298,6 +348,8 @@ impl IntParam {
 
     /// Set up a smoother that can gradually interpolate changes made to this parameter, preventing
     /// clicks and zipper noises.
+    /// The smoothing style can later be changed at runtime using
+    /// [`Smoother::set_style()`][crate::prelude::Smoother::set_style()] on the `smoothed` field.
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multiplying by zero
@@ -333,6 +385,39 @@ impl IntParam {
         self
     }
 
+    /// Set the kind of unit this parameter's value is expressed in. By default this is inferred
+    /// from the unit label set with [`with_unit()`][Self::with_unit()], so this only needs to be
+    /// set when the unit is part of the [`value_to_string`][Self::with_value_to_string()]
+    /// function or when the label is not recognized. See [`UnitClass`] for more information.
+    pub fn with_unit_class(mut self, unit_class: UnitClass) -> Self {
+        self.unit_class = Some(unit_class);
+        self
+    }
+
+    /// Set how this parameter would like to be displayed by generic editors. Parameters of this
+    /// type are shown as a [`ParamAppearance::Knob`] by default. This is only a hint, and it does
+    /// not affect how the parameter behaves. See [`ParamAppearance`] for more information.
+    pub fn with_appearance(mut self, appearance: ParamAppearance) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    /// Add this parameter to a named link group. See [`Param::link_group()`].
+    pub fn with_link_group(mut self, link_group: impl Into<String>) -> Self {
+        self.link_group = Some(link_group.into());
+        self
+    }
+
+    /// Mark this parameter as a destination for the plugin's own modulation matrix that may be
+    /// modulated between the plain values `min` and `max`. Like link groups, this is only metadata
+    /// for the plugin and its editor and it does not affect how the parameter behaves. See
+    /// [`Param::modulation_range()`].
+    pub fn with_modulation_range(mut self, min: i32, max: i32) -> Self {
+        nih_debug_assert!(min <= max);
+        self.modulation_range = Some((min, max));
+        self
+    }
+
     /// Use a custom conversion function to convert the plain, unnormalized value to a
     /// string.
     pub fn with_value_to_string(
@@ -381,4 +466,12 @@ impl IntParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Mark the parameter as expensive to change, for parameters that trigger expensive
+    /// recomputations like regenerating a wavetable. Hosts that support it will then not modulate
+    /// this parameter at audio rate. See [`ParamFlags::EXPENSIVE`].
+    pub fn expensive(mut self) -> Self {
+        self.flags.insert(ParamFlags::EXPENSIVE);
+        self
+    }
 }
@@ -1,6 +1,6 @@
 //! Implementation details for the parameter management.
 
-use super::{Param, ParamFlags, ParamMut};
+use super::{Param, ParamAppearance, ParamFlags, ParamMut, UnitClass};
 
 /// Internal pointers to parameters. This is an implementation detail used by the wrappers for type
 /// erasure.
@@ -63,6 +63,10 @@ macro_rules! param_ptr_forward(
 impl ParamPtr {
     param_ptr_forward!(pub unsafe fn name(&self) -> &str);
     param_ptr_forward!(pub unsafe fn unit(&self) -> &'static str);
+    param_ptr_forward!(pub unsafe fn unit_class(&self) -> UnitClass);
+    param_ptr_forward!(pub unsafe fn appearance(&self) -> ParamAppearance);
+    param_ptr_forward!(pub unsafe fn link_group(&self) -> Option<&str>);
+    param_ptr_forward!(pub unsafe fn modulation_range(&self) -> Option<(f32, f32)>);
     param_ptr_forward!(pub unsafe fn poly_modulation_id(&self) -> Option<u32>);
     param_ptr_forward!(pub unsafe fn modulated_normalized_value(&self) -> f32);
     param_ptr_forward!(pub unsafe fn unmodulated_normalized_value(&self) -> f32);
@@ -77,6 +81,9 @@ impl ParamPtr {
     param_ptr_forward!(pub(crate) unsafe fn set_normalized_value(&self, normalized: f32) -> bool);
     param_ptr_forward!(pub(crate) unsafe fn modulate_value(&self, modulation_offset: f32) -> bool);
     param_ptr_forward!(pub(crate) unsafe fn update_smoother(&self, sample_rate: f32, reset: bool));
+    param_ptr_forward!(pub(crate) unsafe fn set_bypassed(&self, bypassed: bool) -> bool);
+    param_ptr_forward!(pub(crate) unsafe fn set_smoothing_time_scale(&self, scale: f32));
+    param_ptr_forward!(pub(crate) unsafe fn assert_validity(&self));
 
     // These functions involve casts since the plugin formats only do floating point types, so we
     // can't generate them with the macro:
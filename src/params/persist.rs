@@ -3,6 +3,8 @@
 
 use std::sync::Arc;
 
+use super::smoothing::{Smoothable, Smoother, SmootherState};
+
 /// Re-export for use in the [`Params`][super::Params] proc-macro.
 pub use serde_json::from_str as deserialize_field;
 /// Re-export for use in the [`Params`][super::Params] proc-macro.
@@ -217,6 +219,36 @@ where
 impl_persistent_arc!(crossbeam::atomic::AtomicCell<T>,
                      T: serde::Serialize + serde::Deserialize<'a> + Copy + Send);
 
+impl<T: Smoothable> PersistentField<'_, SmootherState> for Smoother<T>
+where
+    Self: Send + Sync,
+{
+    fn set(&self, new_value: SmootherState) {
+        self.restore_state(new_value);
+    }
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&SmootherState) -> R,
+    {
+        f(&self.state())
+    }
+}
+
+impl<T: Smoothable> PersistentField<'_, SmootherState> for Arc<Smoother<T>>
+where
+    Smoother<T>: Send + Sync,
+{
+    fn set(&self, new_value: SmootherState) {
+        self.as_ref().set(new_value);
+    }
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&SmootherState) -> R,
+    {
+        self.as_ref().map(f)
+    }
+}
+
 /// Can be used with the `#[serde(with = "nih_plug::params::internals::serialize_atomic_cell")]`
 /// attribute to serialize `AtomicCell<T>`s.
 pub mod serialize_atomic_cell {
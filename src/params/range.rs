@@ -196,6 +196,16 @@ impl FloatRange {
         }
     }
 
+    /// Whether a plain value lies within this range's bounds, inclusive.
+    pub(super) fn contains(&self, plain: f32) -> bool {
+        match self {
+            FloatRange::Linear { min, max }
+            | FloatRange::Skewed { min, max, .. }
+            | FloatRange::SymmetricalSkewed { min, max, .. } => (*min..=*max).contains(&plain),
+            FloatRange::Reversed(range) => range.contains(plain),
+        }
+    }
+
     /// Emits debug assertions to make sure that range minima are always less than the maxima and
     /// that they are not equal.
     pub(super) fn assert_validity(&self) {
@@ -269,6 +279,14 @@ impl IntRange {
         }
     }
 
+    /// Whether a plain value lies within this range's bounds, inclusive.
+    pub(super) fn contains(&self, plain: i32) -> bool {
+        match self {
+            IntRange::Linear { min, max } => (*min..=*max).contains(&plain),
+            IntRange::Reversed(range) => range.contains(plain),
+        }
+    }
+
     /// Emits debug assertions to make sure that range minima are always less than the maxima and
     /// that they are not equal.
     pub(super) fn assert_validity(&self) {
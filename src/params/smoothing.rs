@@ -1,8 +1,11 @@
 //! Utilities to handle smoothing parameter changes over time.
 
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicI32, AtomicU8, Ordering};
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
 // Re-exported here because it's sued in `SmoothingStyle`.
 pub use atomic_float::AtomicF32;
 
@@ -63,8 +66,27 @@ pub struct Smoother<T: Smoothable> {
     current: AtomicF32,
     /// The value we're smoothing towards
     target: T::Atomic,
+    /// The sample rate passed to the last [`set_target()`][Self::set_target()] call. Used to
+    /// recompute the smoothing coefficients when the style is changed with
+    /// [`set_style()`][Self::set_style()] while smoothing.
+    sample_rate: AtomicF32,
+    /// The kind of smoothing set through [`set_style()`][Self::set_style()], encoded as one of the
+    /// `STYLE_OVERRIDE_*` constants. `style` is used as is when there is no override.
+    style_override: AtomicU8,
+    /// The smoothing time in milliseconds for `style_override`.
+    style_override_time: AtomicF32,
+    /// The factor the active style's smoothing time is multiplied by, set through
+    /// [`set_time_scale()`][Self::set_time_scale()].
+    time_scale: AtomicF32,
 }
 
+/// `Smoother::style` is used as is.
+const STYLE_OVERRIDE_DISABLED: u8 = 0;
+const STYLE_OVERRIDE_NONE: u8 = 1;
+const STYLE_OVERRIDE_LINEAR: u8 = 2;
+const STYLE_OVERRIDE_LOGARITHMIC: u8 = 3;
+const STYLE_OVERRIDE_EXPONENTIAL: u8 = 4;
+
 /// An iterator that continuously produces smoothed values. Can be used as an alternative to the
 /// block-based smoothing API. Since the iterator itself is infinite, you can use
 /// [`Smoother::is_smoothing()`] and [`Smoother::steps_left()`] to get information on the current
@@ -73,6 +95,56 @@ pub struct SmootherIter<'a, T: Smoothable> {
     smoother: &'a Smoother<T>,
 }
 
+/// A snapshot of a [`Smoother`]'s progress, including the current value and not just the target
+/// value. Smoothers can be persisted as part of the plugin's state by adding them to the plugin's
+/// [`Params`][crate::prelude::Params] object with a `#[persist = "key"]` attribute, which stores
+/// this snapshot. After the state has been restored, the smoother resumes from the saved value
+/// instead of jumping to it. This is opt-in for every smoother. The smoothing style is not part of
+/// the snapshot, so the restored smoother should use the same style as the saved smoother.
+///
+/// The wrappers call [`Plugin::reset()`][crate::prelude::Plugin::reset()] after loading a state
+/// while the plugin is active, so persisted smoothers should not be reset there.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SmootherState {
+    /// The smoother's current value.
+    pub current: f32,
+    /// The value the smoother is moving towards.
+    pub target: f32,
+    /// The number of steps left until the target has been reached.
+    pub steps_left: i32,
+    /// The amount the current value changes by every step, or the coefficient for exponential
+    /// smoothing. This depends on the sample rate the smoothing was started with.
+    pub step_size: f32,
+}
+
+/// A fixed set of per-voice [`Smoother`]s for polyphonic plugins, for instance for smoothing each
+/// voice's gain and panning. Smoothers are keyed by the voice's ID, and they can also be accessed
+/// by their slot index. All slots are allocated up front, usually with the same capacity as the
+/// plugin's
+/// [`PolyModulationConfig::max_voice_capacity`][crate::prelude::PolyModulationConfig::max_voice_capacity]
+/// or the value passed to
+/// [`ProcessContext::set_current_voice_capacity()`][crate::prelude::ProcessContext::set_current_voice_capacity()].
+/// Allocating a smoother when a voice starts and freeing it when the voice ends only claims and
+/// releases one of those slots, so that can safely be done on the audio thread.
+///
+/// Voices without a voice ID can use a fallback ID computed from the note's channel and note
+/// number.
+#[derive(Clone)]
+pub struct VoiceSmoothers<T: Smoothable> {
+    /// The style used for all of the smoothers.
+    style: SmoothingStyle,
+    /// One slot per voice. The length of this vector is the capacity.
+    slots: Vec<VoiceSmootherSlot<T>>,
+}
+
+/// A slot in [`VoiceSmoothers`].
+#[derive(Clone)]
+struct VoiceSmootherSlot<T: Smoothable> {
+    /// The ID of the voice using this slot, or `None` if the slot is free.
+    voice_id: Option<i32>,
+    smoother: Smoother<T>,
+}
+
 impl SmoothingStyle {
     /// Compute the number of steps to reach the target value based on the sample rate and this
     /// smoothing style's duration.
@@ -186,6 +258,10 @@ impl<T: Smoothable> Default for Smoother<T> {
             step_size: Default::default(),
             current: AtomicF32::new(0.0),
             target: Default::default(),
+            sample_rate: AtomicF32::new(0.0),
+            style_override: AtomicU8::new(STYLE_OVERRIDE_DISABLED),
+            style_override_time: AtomicF32::new(0.0),
+            time_scale: AtomicF32::new(1.0),
         }
     }
 }
@@ -209,6 +285,10 @@ impl<T: Smoothable> Clone for Smoother<T> {
             step_size: AtomicF32::new(self.step_size.load(Ordering::Relaxed)),
             current: AtomicF32::new(self.current.load(Ordering::Relaxed)),
             target: T::atomic_new(T::atomic_load(&self.target)),
+            sample_rate: AtomicF32::new(self.sample_rate.load(Ordering::Relaxed)),
+            style_override: AtomicU8::new(self.style_override.load(Ordering::Relaxed)),
+            style_override_time: AtomicF32::new(self.style_override_time.load(Ordering::Relaxed)),
+            time_scale: AtomicF32::new(self.time_scale.load(Ordering::Relaxed)),
         }
     }
 }
@@ -259,15 +339,150 @@ impl<T: Smoothable> Smoother<T> {
     /// Set the target value.
     pub fn set_target(&self, sample_rate: f32, target: T) {
         T::atomic_store(&self.target, target);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+
+        self.start_smoothing(sample_rate, target.to_f32());
+    }
+
+    /// Set the target value, and start smoothing from `current` instead of from the smoother's
+    /// current value. This can be used to animate a value back to a previous state, for instance
+    /// when scrubbing or undoing a change, regardless of where the smoother currently is or in
+    /// which direction it was moving. The step size is computed from the distance between
+    /// `current` and `target`, and if they're equal then the smoother is simply reset to that
+    /// value.
+    pub fn set_target_with_current(&self, sample_rate: f32, target: T, current: T) {
+        let current_f32 = current.to_f32();
+        let target_f32 = target.to_f32();
+        if current_f32 == target_f32 {
+            self.reset(target);
+            return;
+        }
+
+        T::atomic_store(&self.target, target);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.current.store(current_f32, Ordering::Relaxed);
+
+        self.start_smoothing(sample_rate, target_f32);
+    }
+
+    /// Change the smoothing style at runtime without having to recreate the smoother, for instance
+    /// to switch all of a plugin's smoothers to shorter smoothing times in a 'fast mode'. This
+    /// takes precedence over the [`style`][Self::style] field. If the smoother is currently
+    /// smoothing, then the smoothing coefficients are recomputed so the target value is reached
+    /// after the new style's smoothing time, starting from the current value.
+    ///
+    /// If the smoother was created with [`SmoothingStyle::OversamplingAware`], then the
+    /// oversampling amount from that style still applies to the new style. Passing an
+    /// `OversamplingAware` style to this function uses the style it wraps.
+    pub fn set_style(&self, style: SmoothingStyle) {
+        let (style_override, time) = match style {
+            SmoothingStyle::OversamplingAware(_, style) => return self.set_style(style.clone()),
+            SmoothingStyle::None => (STYLE_OVERRIDE_NONE, 0.0),
+            SmoothingStyle::Linear(time) => (STYLE_OVERRIDE_LINEAR, time),
+            SmoothingStyle::Logarithmic(time) => (STYLE_OVERRIDE_LOGARITHMIC, time),
+            SmoothingStyle::Exponential(time) => (STYLE_OVERRIDE_EXPONENTIAL, time),
+        };
+        self.style_override_time.store(time, Ordering::Relaxed);
+        self.style_override.store(style_override, Ordering::Relaxed);
+
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        if self.is_smoothing() && sample_rate > 0.0 {
+            self.start_smoothing(sample_rate, T::atomic_load(&self.target).to_f32());
+        }
+    }
+
+    /// Undo [`set_style()`][Self::set_style()] and go back to using the [`style`][Self::style]
+    /// field. The smoothing coefficients are recomputed in the same way.
+    pub fn clear_style(&self) {
+        self.style_override
+            .store(STYLE_OVERRIDE_DISABLED, Ordering::Relaxed);
+
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        if self.is_smoothing() && sample_rate > 0.0 {
+            self.start_smoothing(sample_rate, T::atomic_load(&self.target).to_f32());
+        }
+    }
+
+    /// Multiply the smoothing time of the active style by `scale`, for instance to implement a
+    /// global smoothing amount control. This applies on top of both the [`style`][Self::style]
+    /// field and the style set through [`set_style()`][Self::set_style()]. A scale of `1.0` uses
+    /// the style's smoothing time as is, and `0.0` disables smoothing. The new scale is applied
+    /// lazily the next time the target value changes, so any active smoothing finishes with the
+    /// old smoothing time.
+    ///
+    /// [`Params::set_smoothing_time_scale()`][crate::prelude::Params::set_smoothing_time_scale()]
+    /// can be used to change the scale for all of a plugin's parameters at once.
+    pub fn set_time_scale(&self, scale: f32) {
+        nih_debug_assert!(scale >= 0.0);
+
+        self.time_scale.store(scale, Ordering::Relaxed);
+    }
+
+    /// The factor the smoothing time is multiplied by. See
+    /// [`set_time_scale()`][Self::set_time_scale()].
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale.load(Ordering::Relaxed)
+    }
 
-        let steps_left = self.style.num_steps(sample_rate) as i32;
+    /// Take a snapshot of the smoother's progress. See [`SmootherState`].
+    pub fn state(&self) -> SmootherState {
+        SmootherState {
+            current: self.current.load(Ordering::Relaxed),
+            target: T::atomic_load(&self.target).to_f32(),
+            steps_left: self.steps_left.load(Ordering::Relaxed),
+            step_size: self.step_size.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Continue smoothing from a snapshot taken with [`state()`][Self::state()]. The smoothing
+    /// style and time scale are not part of the snapshot and they are left unchanged.
+    pub fn restore_state(&self, state: SmootherState) {
+        T::atomic_store(&self.target, T::from_f32(state.target));
+        self.current.store(state.current, Ordering::Relaxed);
+        self.step_size.store(state.step_size, Ordering::Relaxed);
+        self.steps_left
+            .store(state.steps_left.max(0), Ordering::Relaxed);
+    }
+
+    /// The smoothing style currently in use. This is either the [`style`][Self::style] field or
+    /// the style set through [`set_style()`][Self::set_style()].
+    fn active_style(&self) -> Cow<'_, SmoothingStyle> {
+        let time = self.style_override_time.load(Ordering::Relaxed);
+        let style = match self.style_override.load(Ordering::Relaxed) {
+            STYLE_OVERRIDE_NONE => return Cow::Owned(SmoothingStyle::None),
+            STYLE_OVERRIDE_LINEAR => SmoothingStyle::Linear,
+            STYLE_OVERRIDE_LOGARITHMIC => SmoothingStyle::Logarithmic,
+            STYLE_OVERRIDE_EXPONENTIAL => SmoothingStyle::Exponential,
+            _ => return Cow::Borrowed(&self.style),
+        };
+
+        // Scaling the smoothing time by the oversampling amount results in the same number of
+        // steps as scaling the sample rate
+        match &self.style {
+            SmoothingStyle::OversamplingAware(oversampling_times, _) => {
+                Cow::Owned(style(time * oversampling_times.load(Ordering::Relaxed)))
+            }
+            _ => Cow::Owned(style(time)),
+        }
+    }
+
+    /// Compute the number of steps and the step size needed to get from the current value to
+    /// `target_f32`.
+    fn start_smoothing(&self, sample_rate: f32, target_f32: f32) {
+        let style = self.active_style();
+        // Scaling the sample rate results in the same number of steps as scaling the smoothing time
+        let time_scale = self.time_scale.load(Ordering::Relaxed);
+        let steps_left = if time_scale > 0.0 {
+            style.num_steps(sample_rate * time_scale) as i32
+        } else {
+            0
+        };
         self.steps_left.store(steps_left, Ordering::Relaxed);
 
         let current = self.current.load(Ordering::Relaxed);
-        let target_f32 = target.to_f32();
         self.step_size.store(
             if steps_left > 0 {
-                self.style.step_size(current, target_f32, steps_left as u32)
+                style.step_size(current, target_f32, steps_left as u32)
             } else {
                 0.0
             },
@@ -300,7 +515,7 @@ impl<T: Smoothable> Smoother<T> {
                 self.steps_left.store(0, Ordering::Relaxed);
                 target_f32
             } else {
-                self.style.next(current, target_f32, step_size)
+                self.active_style().next(current, target_f32, step_size)
             };
             self.current.store(new, Ordering::Relaxed);
 
@@ -310,6 +525,27 @@ impl<T: Smoothable> Smoother<T> {
         }
     }
 
+    /// Get the value the next call to [`next()`][Self::next()] will return, without advancing the
+    /// smoother. This is useful for look-ahead computations. The result is only accurate as long as
+    /// the smoother's target or style is not changed in the meantime.
+    #[inline]
+    pub fn peek(&self) -> T {
+        let target = T::atomic_load(&self.target);
+
+        // This needs to mirror `next()` exactly, including snapping to the target on the last step
+        if self.steps_left.load(Ordering::Relaxed) > 1 {
+            let current = self.current.load(Ordering::Relaxed);
+            let step_size = self.step_size.load(Ordering::Relaxed);
+
+            T::from_f32(
+                self.active_style()
+                    .next(current, target.to_f32(), step_size),
+            )
+        } else {
+            target
+        }
+    }
+
     /// [`next()`][Self::next()], but with the ability to skip forward in the smoother.
     /// [`next()`][Self::next()] is equivalent to calling this function with a `steps` value of 1.
     /// Calling this function with a `steps` value of `n` means will cause you to skip the next `n -
@@ -335,7 +571,8 @@ impl<T: Smoothable> Smoother<T> {
                 self.steps_left.store(0, Ordering::Relaxed);
                 target_f32
             } else {
-                self.style.next_step(current, target_f32, step_size, steps)
+                self.active_style()
+                    .next_step(current, target_f32, step_size, steps)
             };
             self.current.store(new, Ordering::Relaxed);
 
@@ -378,6 +615,7 @@ impl<T: Smoothable> Smoother<T> {
         let steps_left = self.steps_left.load(Ordering::Relaxed) as usize;
         let num_smoothed_values = block_values.len().min(steps_left);
         if num_smoothed_values > 0 {
+            let style = self.active_style();
             let mut current = self.current.load(Ordering::Relaxed);
             let target_f32 = target.to_f32();
             let step_size = self.step_size.load(Ordering::Relaxed);
@@ -386,7 +624,7 @@ impl<T: Smoothable> Smoother<T> {
                 // This is the same as calling `next()` `num_smoothed_values` times, but with some
                 // conditionals optimized out
                 block_values[..num_smoothed_values - 1].fill_with(|| {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = style.next(current, target_f32, step_size);
                     T::from_f32(current)
                 });
 
@@ -396,7 +634,7 @@ impl<T: Smoothable> Smoother<T> {
                 block_values[num_smoothed_values - 1] = target;
             } else {
                 block_values[..num_smoothed_values].fill_with(|| {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = style.next(current, target_f32, step_size);
                     T::from_f32(current)
                 });
             }
@@ -439,6 +677,7 @@ impl<T: Smoothable> Smoother<T> {
         let steps_left = self.steps_left.load(Ordering::Relaxed) as usize;
         let num_smoothed_values = block_values.len().min(steps_left);
         if num_smoothed_values > 0 {
+            let style = self.active_style();
             let mut current = self.current.load(Ordering::Relaxed);
             let step_size = self.step_size.load(Ordering::Relaxed);
 
@@ -449,7 +688,7 @@ impl<T: Smoothable> Smoother<T> {
                     .enumerate()
                     .take(num_smoothed_values - 1)
                 {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = style.next(current, target_f32, step_size);
                     *value = f(idx, current);
                 }
 
@@ -461,7 +700,7 @@ impl<T: Smoothable> Smoother<T> {
                     .enumerate()
                     .take(num_smoothed_values)
                 {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = style.next(current, target_f32, step_size);
                     *value = f(idx, current);
                 }
             }
@@ -485,6 +724,110 @@ impl<T: Smoothable> Smoother<T> {
     }
 }
 
+impl<T: Smoothable> VoiceSmoothers<T> {
+    /// Create smoothers for up to `capacity` voices using the specified style. This allocates, so
+    /// it should be done in [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or
+    /// earlier.
+    pub fn new(style: SmoothingStyle, capacity: usize) -> Self {
+        let mut smoothers = Self {
+            style,
+            slots: Vec::new(),
+        };
+        smoothers.set_capacity(capacity);
+
+        smoothers
+    }
+
+    /// The maximum number of voices that can have a smoother at the same time.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Change the maximum number of voices. This frees all voices, and it allocates when the
+    /// capacity grows, so it should only be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()].
+    pub fn set_capacity(&mut self, capacity: usize) {
+        let style = self.style.clone();
+        self.slots.resize_with(capacity, || VoiceSmootherSlot {
+            voice_id: None,
+            smoother: Smoother::new(style.clone()),
+        });
+        self.clear();
+    }
+
+    /// The number of voices that currently have a smoother.
+    pub fn num_active(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.voice_id.is_some())
+            .count()
+    }
+
+    /// Claim a smoother for a new voice, usually in response to a note on event, and reset it to
+    /// `value`. If the voice already has a smoother then that smoother is reset and reused.
+    /// Returns the smoother's slot index, or `None` if all slots are already in use. This does not
+    /// allocate.
+    pub fn allocate(&mut self, voice_id: i32, value: T) -> Option<usize> {
+        let slot_idx = self
+            .slot_index(voice_id)
+            .or_else(|| self.slots.iter().position(|slot| slot.voice_id.is_none()))?;
+
+        let slot = &mut self.slots[slot_idx];
+        slot.voice_id = Some(voice_id);
+        slot.smoother.reset(value);
+
+        Some(slot_idx)
+    }
+
+    /// Release a voice's smoother, usually after the voice has finished playing. Returns `false` if
+    /// the voice did not have a smoother.
+    pub fn free(&mut self, voice_id: i32) -> bool {
+        match self.slot_index(voice_id) {
+            Some(slot_idx) => {
+                self.slots[slot_idx].voice_id = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Release all smoothers. Useful in [`Plugin::reset()`][crate::prelude::Plugin::reset()].
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            slot.voice_id = None;
+        }
+    }
+
+    /// The slot index of a voice's smoother, if it has one.
+    pub fn slot_index(&self, voice_id: i32) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.voice_id == Some(voice_id))
+    }
+
+    /// Get a voice's smoother, if it has one.
+    pub fn get(&self, voice_id: i32) -> Option<&Smoother<T>> {
+        self.slot_index(voice_id)
+            .map(|slot_idx| &self.slots[slot_idx].smoother)
+    }
+
+    /// Get the smoother in a slot returned by [`allocate()`][Self::allocate()]. Returns `None` if
+    /// the slot is out of bounds or if it's not in use.
+    pub fn get_by_index(&self, slot_idx: usize) -> Option<&Smoother<T>> {
+        self.slots
+            .get(slot_idx)
+            .filter(|slot| slot.voice_id.is_some())
+            .map(|slot| &slot.smoother)
+    }
+
+    /// Iterate over the voice IDs and smoothers of all voices that currently have a smoother.
+    pub fn iter_active(&self) -> impl Iterator<Item = (i32, &Smoother<T>)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| Some((slot.voice_id?, &slot.smoother)))
+    }
+}
+
 impl Smoothable for f32 {
     type Atomic = AtomicF32;
 
@@ -713,5 +1056,210 @@ mod tests {
         assert_eq!(smoother.next(), 20);
     }
 
+    #[test]
+    fn peek_matches_next() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(10.0));
+        smoother.reset(10.0);
+        smoother.set_target(1000.0, 20.0);
+
+        // This includes the last step, where `next()` snaps to the target, and the steps after
+        // the smoothing period is over
+        for _ in 0..15 {
+            let peeked = smoother.peek();
+            assert_eq!(peeked, smoother.peek());
+            assert_eq!(peeked, smoother.next());
+        }
+        assert_eq!(smoother.peek(), 20.0);
+    }
+
+    #[test]
+    fn change_style_while_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(50.0));
+        smoother.reset(10.0);
+
+        // At 1 kHz the 50 ms ramp takes 50 steps. Switching to a 5 ms ramp partway through should
+        // reach the target 5 steps later.
+        smoother.set_target(1000.0, 20.0);
+        for _ in 0..10 {
+            smoother.next();
+        }
+        smoother.set_style(SmoothingStyle::Linear(5.0));
+        assert_eq!(smoother.steps_left(), 5);
+        for _ in 0..(5 - 1) {
+            assert_ne!(smoother.next(), 20.0);
+        }
+        assert_eq!(smoother.next(), 20.0);
+
+        // New targets also use the new timing, until the override is cleared again
+        smoother.set_target(1000.0, 30.0);
+        assert_eq!(smoother.steps_left(), 5);
+        smoother.clear_style();
+        assert_eq!(smoother.steps_left(), 50);
+    }
+
+    #[test]
+    fn ramp_down_from_current() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(10.0));
+        smoother.reset(0.25);
+
+        // The ramp starts at the passed value instead of at the smoother's previous value
+        smoother.set_target_with_current(1000.0, 0.0, 1.0);
+        assert_eq!(smoother.previous_value(), 1.0);
+        assert_eq!(smoother.steps_left(), 10);
+        let mut previous = 1.0;
+        for _ in 0..(10 - 1) {
+            let value = smoother.next();
+            approx::assert_relative_eq!(previous - value, 0.1, epsilon = 1e-5);
+            previous = value;
+        }
+        assert_eq!(smoother.next(), 0.0);
+        assert!(!smoother.is_smoothing());
+
+        // Without any distance to cover there's nothing to smooth
+        smoother.set_target_with_current(1000.0, 0.5, 0.5);
+        assert!(!smoother.is_smoothing());
+        assert_eq!(smoother.next(), 0.5);
+    }
+
+    #[test]
+    fn voice_smoothers_lifecycle() {
+        let mut smoothers: VoiceSmoothers<f32> =
+            VoiceSmoothers::new(SmoothingStyle::Linear(10.0), 2);
+        assert_eq!(smoothers.capacity(), 2);
+        assert_eq!(smoothers.num_active(), 0);
+        let slots_ptr = smoothers.slots.as_ptr();
+
+        assert_eq!(smoothers.allocate(10, 0.5), Some(0));
+        assert_eq!(smoothers.allocate(20, 1.0), Some(1));
+        assert_eq!(smoothers.allocate(30, 1.0), None);
+        assert_eq!(smoothers.num_active(), 2);
+
+        // The smoothers are independent
+        smoothers.get(10).unwrap().set_target(1000.0, 1.5);
+        assert!(smoothers.get(10).unwrap().is_smoothing());
+        assert!(!smoothers.get(20).unwrap().is_smoothing());
+        assert_eq!(smoothers.get_by_index(1).unwrap().next(), 1.0);
+
+        // Freeing a voice makes its slot available for the next voice, which starts from its own
+        // value instead of continuing where the old voice left off
+        assert!(smoothers.free(10));
+        assert!(!smoothers.free(10));
+        assert!(smoothers.get(10).is_none());
+        assert!(smoothers.get_by_index(0).is_none());
+        assert_eq!(smoothers.allocate(30, 0.25), Some(0));
+        assert!(!smoothers.get(30).unwrap().is_smoothing());
+        assert_eq!(smoothers.get(30).unwrap().next(), 0.25);
+        assert_eq!(
+            smoothers
+                .iter_active()
+                .map(|(voice_id, _)| voice_id)
+                .collect::<Vec<_>>(),
+            [30, 20]
+        );
+
+        // Allocating and freeing voices reuses the slots allocated up front
+        smoothers.clear();
+        assert_eq!(smoothers.num_active(), 0);
+        for voice_id in 0..100 {
+            smoothers.free(voice_id - 1);
+            assert!(smoothers.allocate(voice_id, 0.0).is_some());
+        }
+        assert_eq!(smoothers.capacity(), 2);
+        assert_eq!(smoothers.slots.as_ptr(), slots_ptr);
+        assert_eq!(smoothers.num_active(), 1);
+    }
+
+    #[test]
+    fn global_time_scale() {
+        use crate::params::range::{FloatRange, IntRange};
+        use crate::params::{FloatParam, IntParam, Param, ParamPtr, Params};
+
+        struct ScaledParams {
+            gain: FloatParam,
+            cutoff: FloatParam,
+            steps: IntParam,
+        }
+
+        unsafe impl Params for ScaledParams {
+            fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+                vec![
+                    (String::from("gain"), self.gain.as_ptr(), String::new()),
+                    (String::from("cutoff"), self.cutoff.as_ptr(), String::new()),
+                    (String::from("steps"), self.steps.as_ptr(), String::new()),
+                ]
+            }
+        }
+
+        let params = ScaledParams {
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(10.0)),
+            cutoff: FloatParam::new("Cutoff", 1.0, FloatRange::Linear { min: 1.0, max: 2.0 })
+                .with_smoother(SmoothingStyle::Logarithmic(40.0)),
+            steps: IntParam::new("Steps", 0, IntRange::Linear { min: 0, max: 10 })
+                .with_smoother(SmoothingStyle::Exponential(20.0)),
+        };
+        params.cutoff.smoothed.reset(1.0);
+        let set_targets = |params: &ScaledParams| {
+            params.gain.smoothed.set_target(1000.0, 1.0);
+            params.cutoff.smoothed.set_target(1000.0, 2.0);
+            params.steps.smoothed.set_target(1000.0, 10);
+        };
+
+        set_targets(&params);
+        assert_eq!(params.gain.smoothed.steps_left(), 10);
+        assert_eq!(params.cutoff.smoothed.steps_left(), 40);
+        assert_eq!(params.steps.smoothed.steps_left(), 20);
+
+        // The active smoothing is not affected, only the next target change uses the new scale
+        params.set_smoothing_time_scale(0.5);
+        assert_eq!(params.gain.smoothed.steps_left(), 10);
+        set_targets(&params);
+        assert_eq!(params.gain.smoothed.steps_left(), 5);
+        assert_eq!(params.cutoff.smoothed.steps_left(), 20);
+        assert_eq!(params.steps.smoothed.steps_left(), 10);
+
+        // This also applies to styles set at runtime
+        params
+            .gain
+            .smoothed
+            .set_style(SmoothingStyle::Linear(100.0));
+        params.set_smoothing_time_scale(2.0);
+        set_targets(&params);
+        assert_eq!(params.gain.smoothed.steps_left(), 200);
+        assert_eq!(params.cutoff.smoothed.steps_left(), 80);
+        assert_eq!(params.steps.smoothed.steps_left(), 40);
+
+        params.set_smoothing_time_scale(0.0);
+        set_targets(&params);
+        assert!(!params.gain.smoothed.is_smoothing());
+        assert_eq!(params.gain.smoothed.next(), 1.0);
+    }
+
+    #[test]
+    fn persisted_state_resumes_mid_ramp() {
+        use crate::params::persist::{deserialize_field, serialize_field, PersistentField};
+
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(0.0);
+        smoother.set_target(100.0, 1.0);
+        for _ in 0..4 {
+            smoother.next();
+        }
+
+        let serialized = PersistentField::map(&smoother, serialize_field).unwrap();
+        let expected: Vec<f32> = (0..7).map(|_| smoother.next()).collect();
+        assert_eq!(expected.last(), Some(&1.0));
+
+        // The restored smoother continues where the saved smoother left off instead of jumping to
+        // the target
+        let restored: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        restored.reset(1.0);
+        PersistentField::set(&restored, deserialize_field(&serialized).unwrap());
+        approx::assert_relative_eq!(restored.previous_value(), 0.4, epsilon = 1e-6);
+        assert_eq!(restored.steps_left(), 6);
+        let resumed: Vec<f32> = (0..7).map(|_| restored.next()).collect();
+        assert_eq!(resumed, expected);
+    }
+
     // TODO: Tests for the exponential smoothing
 }
@@ -0,0 +1,126 @@
+//! Snapshots of all of a plugin's parameter values.
+
+use super::internals::ParamPtr;
+use crate::prelude::ParamSetter;
+
+/// The normalized values of all of a plugin's parameters at a point in time, created using
+/// [`Params::snapshot()`][super::Params::snapshot()]. This can be used to implement A/B
+/// comparisons in a plugin's editor, or to compare parameter values while debugging. The values are
+/// stored before monophonic modulation has been applied, just like in the plugin's state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamSnapshot {
+    /// The `(param_id, normalized_value)` pairs for every parameter, in the same order as
+    /// [`Params::param_map()`][super::Params::param_map()].
+    values: Vec<(String, f32)>,
+}
+
+impl ParamSnapshot {
+    /// Read the current values of the parameters from a `param_map()`. Every parameter's value is
+    /// read atomically, but parameters that are being automated while the snapshot is taken may
+    /// still end up with values from different processing cycles.
+    pub(crate) fn from_param_map(param_map: &[(String, ParamPtr, String)]) -> Self {
+        Self {
+            values: param_map
+                .iter()
+                .map(|(param_id, param_ptr, _)| {
+                    (param_id.clone(), unsafe {
+                        param_ptr.unmodulated_normalized_value()
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the normalized value of the parameter with ID `param_id` at the time the snapshot was
+    /// taken. Returns `None` if the parameter does not exist.
+    pub fn get(&self, param_id: &str) -> Option<f32> {
+        self.values
+            .iter()
+            .find(|(id, _)| id == param_id)
+            .map(|(_, normalized_value)| *normalized_value)
+    }
+
+    /// Iterate over the `(param_id, normalized_value)` pairs of all parameters in the snapshot.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f32)> + '_ {
+        self.values
+            .iter()
+            .map(|(param_id, normalized_value)| (param_id.as_str(), *normalized_value))
+    }
+
+    /// Set the parameters from a `param_map()` back to their values from this snapshot. Only the
+    /// parameters that have changed since the snapshot was taken are set, and they are set through
+    /// the `ParamSetter` so the host is informed about the changes. Parameters that don't exist in
+    /// the snapshot are left alone.
+    pub(crate) fn restore(&self, param_map: &[(String, ParamPtr, String)], setter: &ParamSetter) {
+        for (param_id, param_ptr, _) in param_map {
+            let normalized_value = match self.get(param_id) {
+                Some(normalized_value) => normalized_value,
+                None => continue,
+            };
+
+            unsafe {
+                if param_ptr.unmodulated_normalized_value() == normalized_value {
+                    continue;
+                }
+
+                let context = setter.raw_context;
+                context.raw_begin_set_parameter(*param_ptr);
+                context.raw_set_parameter_normalized(*param_ptr, normalized_value);
+                context.raw_end_set_parameter(*param_ptr);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::context::gui::mock::MockGuiContext;
+    use crate::prelude::{FloatParam, FloatRange, IntParam, IntRange, Param, Params};
+
+    struct TestParams {
+        gain: FloatParam,
+        voices: IntParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("voices"), self.voices.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let params = TestParams {
+            gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            voices: IntParam::new("Voices", 4, IntRange::Linear { min: 1, max: 16 }),
+        };
+        let context = MockGuiContext::default();
+        let setter = ParamSetter::new(&context);
+
+        let snapshot = params.snapshot();
+        assert_eq!(snapshot.get("gain"), Some(0.5));
+        assert_eq!(snapshot.get("voices"), Some(0.2));
+        assert_eq!(snapshot.get("does_not_exist"), None);
+
+        setter.set_parameter(&params.gain, 0.75);
+        assert_eq!(params.gain.value(), 0.75);
+        assert_ne!(params.snapshot(), snapshot);
+        let changes_before_restore = context.changes.load(Ordering::SeqCst);
+
+        // Only the parameter that actually changed is restored
+        params.restore_snapshot(&snapshot, &setter);
+        assert_eq!(params.gain.value(), 0.5);
+        assert_eq!(params.voices.value(), 4);
+        assert_eq!(
+            context.changes.load(Ordering::SeqCst),
+            changes_before_restore + 1
+        );
+        assert_eq!(params.snapshot(), snapshot);
+    }
+}
@@ -0,0 +1,123 @@
+//! Classes of parameter units, so custom editors can format related parameters consistently.
+
+/// The kind of unit a parameter's value is expressed in. Custom editors can use this to format
+/// and lay out related parameters consistently, for instance to always display frequencies in the
+/// same way regardless of the formatter a parameter uses. This is metadata only and does not affect
+/// how the parameter behaves or how it is formatted by NIH-plug itself.
+///
+/// Parameters infer their unit class from their unit label using
+/// [`from_unit()`][Self::from_unit()]. Parameters that include the unit in their
+/// `value_to_string` formatter instead of using a unit label, like the ones using
+/// [`formatters::v2s_f32_hz_then_khz()`][crate::formatters::v2s_f32_hz_then_khz()], should set
+/// their unit class explicitly using `with_unit_class()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitClass {
+    /// The parameter does not have a unit, like a filter's resonance or a toggle.
+    Unitless,
+    /// A frequency in Hertz or kilohertz.
+    Frequency,
+    /// A gain or level in decibels.
+    Decibels,
+    /// A duration in seconds or milliseconds.
+    Time,
+    /// A percentage.
+    Percentage,
+    /// A pitch offset in semitones or cents.
+    Pitch,
+    /// A unit that does not fall into any of the other classes.
+    Other,
+}
+
+impl UnitClass {
+    /// Infer the unit class from a parameter's unit label. Leading and trailing whitespace is
+    /// ignored. Returns [`UnitClass::Unitless`] for an empty label and [`UnitClass::Other`] for
+    /// labels that aren't recognized.
+    pub fn from_unit(unit: &str) -> Self {
+        match unit.trim() {
+            "" => UnitClass::Unitless,
+            "Hz" | "kHz" => UnitClass::Frequency,
+            "dB" | "dBFS" => UnitClass::Decibels,
+            "s" | "ms" | "sec" => UnitClass::Time,
+            "%" => UnitClass::Percentage,
+            "st" | "semitones" | "ct" | "cents" => UnitClass::Pitch,
+            _ => UnitClass::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{
+        formatters, BoolParam, FloatParam, FloatRange, IntParam, IntRange, Param, ParamPtr, Params,
+    };
+
+    struct FilterParams {
+        cutoff: FloatParam,
+        resonance: FloatParam,
+        gain: FloatParam,
+        slope: IntParam,
+        enabled: BoolParam,
+    }
+
+    unsafe impl Params for FilterParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("cutoff"), self.cutoff.as_ptr(), String::new()),
+                (
+                    String::from("resonance"),
+                    self.resonance.as_ptr(),
+                    String::new(),
+                ),
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("slope"), self.slope.as_ptr(), String::new()),
+                (
+                    String::from("enabled"),
+                    self.enabled.as_ptr(),
+                    String::new(),
+                ),
+            ]
+        }
+    }
+
+    #[test]
+    fn params_report_unit_classes() {
+        let params = FilterParams {
+            // The unit is part of the formatter here, so the class needs to be set explicitly
+            cutoff: FloatParam::new(
+                "Cutoff",
+                1000.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 20_000.0,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_unit_class(UnitClass::Frequency),
+            resonance: FloatParam::new("Resonance", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            gain: FloatParam::new(
+                "Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" dB"),
+            slope: IntParam::new("Slope", 12, IntRange::Linear { min: 6, max: 48 })
+                .with_unit(" dB/oct"),
+            enabled: BoolParam::new("Enabled", true),
+        };
+
+        assert_eq!(
+            params.unit_classes(),
+            [
+                (String::from("cutoff"), UnitClass::Frequency),
+                (String::from("resonance"), UnitClass::Unitless),
+                (String::from("gain"), UnitClass::Decibels),
+                (String::from("slope"), UnitClass::Other),
+                (String::from("enabled"), UnitClass::Unitless),
+            ]
+        );
+    }
+}
@@ -2,6 +2,7 @@
 //! that are specific to one or more plugin-APIs.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::prelude::{
     AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, InitContext,
@@ -9,6 +10,8 @@ use crate::prelude::{
 };
 
 pub mod clap;
+#[cfg(test)]
+pub(crate) mod test_plugin;
 #[cfg(feature = "vst3")]
 pub mod vst3;
 
@@ -101,6 +104,14 @@ pub trait Plugin: Default + Send + 'static {
     /// Whether the plugin accepts note events, and what which events it wants to receive. If this
     /// is set to [`MidiConfig::None`], then the plugin won't receive any note events.
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    /// The number of note input ports the plugin exposes when [`MIDI_INPUT`][Self::MIDI_INPUT] is
+    /// not [`MidiConfig::None`]. This must be at least one. Instruments with multiple layers can
+    /// use separate ports to let the user route different tracks or controllers to each layer.
+    /// The port an event arrived on can be retrieved with
+    /// [`ProcessContext::next_event_with_port()`][crate::prelude::ProcessContext::next_event_with_port()].
+    /// With VST3, MIDI CCs that are translated from parameter changes always arrive on the first
+    /// port.
+    const MIDI_INPUT_PORTS: u32 = 1;
     /// Whether the plugin can output note events. If this is set to [`MidiConfig::None`], then the
     /// plugin won't have a note output port. When this is set to another value, then in most hosts
     /// the plugin will consume all note and MIDI CC input. If you don't want that, then you will
@@ -112,11 +123,80 @@ pub trait Plugin: Default + Send + 'static {
     /// every 64 samples.
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
 
+    /// If this is set to a value higher than 1, then the main buffer passed to
+    /// [`process()`][Self::process()] is always padded with silence so its length is a multiple of
+    /// this many samples. This lets plugins process the buffer in SIMD-sized chunks of samples
+    /// without having to handle a shorter tail. The padding samples are zeroed before every
+    /// `process()` call, and whatever the plugin writes to them is discarded. Padding the buffer
+    /// requires copying the main buffer to and from separate storage, and it does not affect the
+    /// auxiliary buffers. Since the padding is processed like any other audio, this is mostly
+    /// useful for stateless processing like gain and waveshaping. Filters and other processors
+    /// with internal state would otherwise see a few samples of silence at the end of every block.
+    /// Smoothers should only be advanced for the first
+    /// [`Buffer::unpadded_samples()`][crate::prelude::Buffer::unpadded_samples()] samples, or they
+    /// will move faster than the host's timeline.
+    const SIMD_BLOCK_MULTIPLE: usize = 1;
+
+    /// Whether the host is allowed to use the same buffers for the main input and the main output.
+    /// This saves the host from having to allocate and clear separate output buffers. The plugin
+    /// always processes the main output buffer in place, and the wrappers only copy the main input
+    /// to it when the host uses separate buffers, so this is safe for every plugin. This is
+    /// reported to the host as an in-place pair between the main input and output ports in CLAP.
+    /// VST3 has no way to communicate this, and VST3 hosts may use in-place buffers either way.
+    /// Disabling this is only useful to work around hosts that mishandle in-place processing.
+    const IN_PLACE_PROCESSING: bool = true;
+
     /// If this is set to true, then the plugin will report itself as having a hard realtime
     /// processing requirement when the host asks for it. Supported hosts will never ask the plugin
-    /// to do offline processing.
+    /// to do offline processing. Set this for DSP that cannot render offline, for instance because
+    /// it depends on the wall clock. This is reported through CLAP's render extension and through
+    /// the `OnlyRT` VST3 subcategory. Hosts that ignore this may still request offline processing,
+    /// in which case a warning is logged and the plugin is run in offline mode anyway.
     const HARD_REALTIME_ONLY: bool = false;
 
+    /// The sample rates the plugin can process audio at, for plugins whose DSP only works at a
+    /// fixed set of sample rates. If this slice is empty, then the plugin supports any sample rate.
+    /// Plugin formats that allow the plugin to decline a sample rate will do so for sample rates
+    /// not in this list. Currently this is the case for VST3 and CLAP, and the standalone wrapper
+    /// will refuse to start with an unsupported sample rate. Sample rates are compared with a
+    /// tolerance of 0.01 Hz.
+    const SUPPORTED_SAMPLE_RATES: &'static [f32] = &[];
+
+    /// If this is set to true, then the plugin promises that it's a pure function of its input: it
+    /// always outputs silence when its inputs are silent and it doesn't receive any note events, it
+    /// has no tail, and it has no other internal state that can produce sound from silence. The
+    /// CLAP and VST3 wrappers will then skip calling [`process()`][Self::process()] for blocks
+    /// where all main and auxiliary inputs are completely silent and there are no incoming note
+    /// events. This is useful for simple effects like gain or waveshaping plugins.
+    ///
+    /// Since `process()` is not called for those blocks, parameter smoothers will not advance
+    /// during silence. Leave this disabled if the plugin has any kind of tail, like reverbs,
+    /// delays, or filters with a long decay, or if it generates sound on its own.
+    const SILENCE_IN_SILENCE_OUT: bool = false;
+
+    /// How long a parameter stays locked after the plugin writes its value, either from the editor
+    /// or with [`ProcessContext::set_parameter_automated()`]. While a parameter is locked, incoming
+    /// host changes for that parameter are ignored unless they match the value the plugin wrote.
+    /// This prevents feedback loops in plugins that both read and write a parameter, for instance
+    /// when syncing with a hardware controller, where the host may send stale values back to the
+    /// plugin. Set to [`Duration::ZERO`] by default, which disables the lock.
+    const PARAM_WRITE_LOCK: Duration = Duration::ZERO;
+
+    /// If enabled, parameters that are currently being changed from the editor, i.e. between
+    /// [`ParamSetter::begin_set_parameter()`][crate::prelude::ParamSetter::begin_set_parameter()]
+    /// and [`ParamSetter::end_set_parameter()`][crate::prelude::ParamSetter::end_set_parameter()]
+    /// calls, keep their current values when the host or the editor loads a new state. All other
+    /// parameters and the plugin's persistent fields are restored as usual. This prevents a state
+    /// reload from clobbering the value a user is dragging. Disabled by default, in which case
+    /// loading a state replaces all parameter values.
+    const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = false;
+
+    /// If enabled, the wrappers snap all parameter smoothers to their current values right before
+    /// calling [`reset()`][Self::reset()]. This prevents audible glides from stale smoother state
+    /// when the host reactivates the plugin. Disable this if the plugin should keep gliding
+    /// through resets.
+    const RESET_SMOOTHERS_ON_RESET: bool = true;
+
     /// The plugin's SysEx message type if it supports sending or receiving MIDI SysEx messages, or
     /// `()` if it does not. This type can be a struct or enum wrapping around one or more message
     /// types, and the [`SysExMessage`] trait is then used to convert between this type and basic
@@ -158,8 +238,11 @@ pub trait Plugin: Default + Send + 'static {
     /// frameworks that may have their own ways of interacting with parameters. See the repo's
     /// readme for more information.
     ///
-    /// Queried only once immediately after the plugin instance is created. This function takes
-    /// `&mut self` to make it easier to move data into the `Editor` implementation.
+    /// Queried only once when the host initializes the plugin instance, before the host checks
+    /// whether the plugin has a GUI. This means that whether the plugin has an editor can be
+    /// decided at runtime, for instance based on the plugin's license. If this returns `None`,
+    /// then the host is told that the plugin does not have a GUI. This function takes `&mut self`
+    /// to make it easier to move data into the `Editor` implementation.
     fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         None
     }
@@ -213,6 +296,29 @@ pub trait Plugin: Default + Send + 'static {
     /// audio thread. You should thus not do any allocations in this function.
     fn reset(&mut self) {}
 
+    /// The plugin's current latency in samples, for plugins whose latency depends on their
+    /// parameters, like a lookahead time or an FFT size. The wrapper queries this after the plugin
+    /// has been initialized, after every processed block, and after the plugin's state has been
+    /// restored. Whenever the returned value changes, the wrapper reports the new latency to the
+    /// host as if [`InitContext::set_latency_samples()`] or
+    /// [`ProcessContext::set_latency_samples()`] had been called. Since this is called from the
+    /// audio thread, it should be cheap and it should not allocate.
+    ///
+    /// The default implementation always returns zero, so the latency is only ever changed through
+    /// the contexts' `set_latency_samples()` functions.
+    fn latency(&self) -> u32 {
+        0
+    }
+
+    /// Called exactly once before the first [`process()`][Self::process()] call after the plugin
+    /// has been activated, with the same `context` that will be passed to that `process()` call.
+    /// This can be used for one-time setup that depends on the first block's transport
+    /// information, which is not yet available in [`initialize()`][Self::initialize()] or
+    /// [`reset()`][Self::reset()]. The hook fires again after the plugin has been deactivated and
+    /// activated again. Like `process()`, this is called from the audio thread so it should not
+    /// allocate.
+    fn first_process(&mut self, context: &mut impl ProcessContext<Self>) {}
+
     /// Process audio. The host's input buffers have already been copied to the output buffers if
     /// they are not processing audio in place (most hosts do however). All channels are also
     /// guaranteed to contain the same number of samples. Lastly, denormals have already been taken
@@ -250,6 +356,77 @@ pub trait Plugin: Default + Send + 'static {
     /// `initialize()` may be called more than once before `deactivate()` is called, for instance
     /// when restoring state while the plugin is still activate.
     fn deactivate(&mut self) {}
+
+    /// An optional longer description of the plugin that hosts can show in their plugin browsers
+    /// or about dialogs, like what the plugin does and who made it. Only some plugin formats can
+    /// pass this on to the host:
+    ///
+    /// - CLAP uses this as the plugin's description if
+    ///   [`ClapPlugin::CLAP_DESCRIPTION`][clap::ClapPlugin::CLAP_DESCRIPTION] is not set.
+    /// - VST3 does not have a field for this in its class information, so this is not exposed to
+    ///   VST3 hosts.
+    /// - The standalone target shows this in its `--help` output.
+    ///
+    /// This is also included in [`plugin_metadata()`]. Queried whenever the plugin is being
+    /// described to the host, so this should be cheap to compute.
+    fn about() -> Option<String> {
+        None
+    }
+
+    /// The presets that ship with the plugin. A custom editor can list these and load one with
+    /// [`GuiContext::set_preset()`][crate::prelude::GuiContext::set_preset()], which goes through
+    /// the same path as [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()].
+    /// Presets are referred to by their index in this list. Queried once when the plugin's wrapper
+    /// is created. Defaults to no presets.
+    ///
+    /// These presets are not exposed to the host, so they only show up in the plugin's own editor.
+    fn factory_presets() -> Vec<FactoryPreset> {
+        Vec::new()
+    }
+}
+
+/// A preset that ships with the plugin. See [`Plugin::factory_presets()`].
+#[derive(Debug, Clone)]
+pub struct FactoryPreset {
+    /// The preset's name, for the editor to display.
+    pub name: String,
+    /// The state that's loaded when the preset gets selected. This can for instance be created by
+    /// parsing a [shared preset string][PluginState::from_shareable_string()] or by building the
+    /// state's parameter map by hand.
+    pub state: PluginState,
+}
+
+/// The descriptive constants from a [`Plugin`], returned by [`plugin_metadata()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginMetadata {
+    /// The plugin's name, from [`Plugin::NAME`].
+    pub name: &'static str,
+    /// The name of the plugin's vendor, from [`Plugin::VENDOR`].
+    pub vendor: &'static str,
+    /// The plugin's web page, from [`Plugin::URL`].
+    pub url: &'static str,
+    /// The vendor's email address, from [`Plugin::EMAIL`].
+    pub email: &'static str,
+    /// The plugin's version string, from [`Plugin::VERSION`].
+    pub version: &'static str,
+    /// The plugin's description, from [`Plugin::about()`].
+    pub about: Option<String>,
+}
+
+/// Collect a plugin's name, vendor, and other descriptive constants in a single [`PluginMetadata`]
+/// struct. This does not require an instance of the plugin or of any of the plugin wrappers, so it
+/// can be used by build scripts and bundlers to generate installers and plugin manifests. See
+/// [`clap_metadata()`][clap::clap_metadata()] and `vst3_metadata()` for the plugin's IDs and
+/// features.
+pub fn plugin_metadata<P: Plugin>() -> PluginMetadata {
+    PluginMetadata {
+        name: P::NAME,
+        vendor: P::VENDOR,
+        url: P::URL,
+        email: P::EMAIL,
+        version: P::VERSION,
+        about: P::about(),
+    }
 }
 
 /// Indicates the current situation after the plugin has processed audio.
@@ -267,3 +444,57 @@ pub enum ProcessStatus {
     /// infinite tail.
     KeepAlive,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::{clap_metadata, ClapFeature, ClapPlugin};
+
+    struct Metadata;
+
+    impl TestPluginConfig for Metadata {
+        const NAME: &'static str = "Metadata";
+        const VERSION: &'static str = "1.2.3";
+        const CLAP_ID: &'static str = "com.moist-plugins-gmbh.metadata";
+        const CLAP_DESCRIPTION: Option<&'static str> = Some("Describes itself");
+        const CLAP_SUPPORT_URL: Option<&'static str> = Some("https://example.com/support");
+        const CLAP_FEATURES: &'static [ClapFeature] =
+            &[ClapFeature::AudioEffect, ClapFeature::Stereo];
+
+        type Params = NoParams;
+
+        fn about() -> Option<String> {
+            Some(format!(
+                "{} {} by {}",
+                Self::NAME,
+                Self::VERSION,
+                MetadataPlugin::VENDOR
+            ))
+        }
+    }
+
+    type MetadataPlugin = TestPlugin<Metadata>;
+
+    #[test]
+    fn metadata_matches_consts() {
+        let metadata = plugin_metadata::<MetadataPlugin>();
+        assert_eq!(metadata.name, MetadataPlugin::NAME);
+        assert_eq!(metadata.vendor, MetadataPlugin::VENDOR);
+        assert_eq!(metadata.url, MetadataPlugin::URL);
+        assert_eq!(metadata.email, MetadataPlugin::EMAIL);
+        assert_eq!(metadata.version, MetadataPlugin::VERSION);
+        assert_eq!(
+            metadata.about.as_deref(),
+            Some("Metadata 1.2.3 by Moist Plugins GmbH")
+        );
+
+        let metadata = clap_metadata::<MetadataPlugin>();
+        assert_eq!(metadata.plugin, plugin_metadata::<MetadataPlugin>());
+        assert_eq!(metadata.id, MetadataPlugin::CLAP_ID);
+        assert_eq!(metadata.description, MetadataPlugin::CLAP_DESCRIPTION);
+        assert_eq!(metadata.manual_url, None);
+        assert_eq!(metadata.support_url, MetadataPlugin::CLAP_SUPPORT_URL);
+        assert_eq!(metadata.features, MetadataPlugin::CLAP_FEATURES);
+    }
+}
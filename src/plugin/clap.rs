@@ -1,13 +1,15 @@
-use super::Plugin;
+use super::{plugin_metadata, Plugin, PluginMetadata};
 use crate::prelude::{ClapFeature, RemoteControlsContext};
 
 /// Provides auxiliary metadata needed for a CLAP plugin.
 #[allow(unused_variables)]
 pub trait ClapPlugin: Plugin {
     /// A unique ID that identifies this particular plugin. This is usually in reverse domain name
-    /// notation, e.g. `com.manufacturer.plugin-name`.
+    /// notation, e.g. `com.manufacturer.plugin-name`. This can be overridden at runtime by
+    /// implementing [`clap_id()`][Self::clap_id()].
     const CLAP_ID: &'static str;
-    /// An optional short description for the plugin.
+    /// An optional short description for the plugin. When this is not set, then
+    /// [`Plugin::about()`] is used instead.
     const CLAP_DESCRIPTION: Option<&'static str>;
     /// The URL to the plugin's manual, if available.
     const CLAP_MANUAL_URL: Option<&'static str>;
@@ -20,6 +22,22 @@ pub trait ClapPlugin: Plugin {
     /// If set, this informs the host about the plugin's capabilities for polyphonic modulation.
     const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = None;
 
+    /// If set, the plugin's note ports tell the host that they prefer MIDI messages over CLAP note
+    /// events. The note ports always accept both dialects. CLAP note events are preferred by
+    /// default since they carry voice IDs and note expressions, which are needed for polyphonic
+    /// modulation. This can be enabled for plugins that mostly deal with raw MIDI, like MIDI
+    /// effects.
+    const CLAP_PREFER_MIDI_NOTE_DIALECT: bool = false;
+
+    /// The plugin ID the CLAP factory registers the plugin with. This defaults to
+    /// [`CLAP_ID`][Self::CLAP_ID], but it can be overridden to compute the ID when the plugin is
+    /// loaded, for instance for white-label builds that share the same plugin type. This is called
+    /// once when the factory gets created. The ID must not be empty, and it may only contain
+    /// printable ASCII characters other than spaces. The factory panics if this is not the case.
+    fn clap_id() -> String {
+        Self::CLAP_ID.to_owned()
+    }
+
     /// This function can be implemented to define plugin-specific [remote control
     /// pages](https://github.com/free-audio/clap/blob/main/include/clap/ext/draft/remote-controls.h)
     /// that the host can use to provide better hardware mapping for a plugin. See the linked
@@ -27,6 +45,37 @@ pub trait ClapPlugin: Plugin {
     fn remote_controls(&self, context: &mut impl RemoteControlsContext) {}
 }
 
+/// A plugin's CLAP metadata, returned by [`clap_metadata()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClapPluginMetadata {
+    /// The plugin's format independent metadata.
+    pub plugin: PluginMetadata,
+    /// The plugin's CLAP ID, from [`ClapPlugin::clap_id()`].
+    pub id: String,
+    /// The plugin's description, from [`ClapPlugin::CLAP_DESCRIPTION`].
+    pub description: Option<&'static str>,
+    /// The URL to the plugin's manual, from [`ClapPlugin::CLAP_MANUAL_URL`].
+    pub manual_url: Option<&'static str>,
+    /// The URL to the plugin's support page, from [`ClapPlugin::CLAP_SUPPORT_URL`].
+    pub support_url: Option<&'static str>,
+    /// The plugin's declared features, from [`ClapPlugin::CLAP_FEATURES`].
+    pub features: &'static [ClapFeature],
+}
+
+/// A plugin's [`plugin_metadata()`] combined with its CLAP ID, description, URLs, and features.
+/// Like [`plugin_metadata()`], this can be used from build scripts without creating the plugin. The
+/// ID is the one returned by [`ClapPlugin::clap_id()`].
+pub fn clap_metadata<P: ClapPlugin>() -> ClapPluginMetadata {
+    ClapPluginMetadata {
+        plugin: plugin_metadata::<P>(),
+        id: P::clap_id(),
+        description: P::CLAP_DESCRIPTION,
+        manual_url: P::CLAP_MANUAL_URL,
+        support_url: P::CLAP_SUPPORT_URL,
+        features: P::CLAP_FEATURES,
+    }
+}
+
 /// Configuration for the plugin's polyphonic modulation options, if it supports .
 pub struct PolyModulationConfig {
     /// The maximum number of voices this plugin will ever use. Call the context's
@@ -0,0 +1,149 @@
+//! A configurable plugin for the crate's own tests, so the tests don't all need to implement the
+//! entire [`Plugin`] trait themselves.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::prelude::{
+    AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, ClapFeature, ClapPlugin, FactoryPreset,
+    InitContext, ParamPtr, Params, Plugin, ProcessContext, ProcessStatus,
+};
+
+/// The parts of a [`TestPlugin`] that differ between tests. Everything has a default except for
+/// the plugin's name. The `CLAP_*` constants are used for the [`ClapPlugin`] implementation.
+pub(crate) trait TestPluginConfig: Sized + Send + 'static {
+    const NAME: &'static str;
+    const VERSION: &'static str = "0.0.1";
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+    const HARD_REALTIME_ONLY: bool = false;
+    const SAMPLE_ACCURATE_AUTOMATION: bool = false;
+    const SUPPORTED_SAMPLE_RATES: &'static [f32] = &[];
+    const PARAM_WRITE_LOCK: Duration = Duration::ZERO;
+    const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = false;
+    const CLAP_ID: &'static str = "com.moist-plugins-gmbh.test-plugin";
+    const CLAP_DESCRIPTION: Option<&'static str> = None;
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[];
+
+    type Params: Params + Default;
+
+    fn about() -> Option<String> {
+        None
+    }
+
+    fn clap_id() -> String {
+        Self::CLAP_ID.to_owned()
+    }
+
+    fn factory_presets() -> Vec<FactoryPreset> {
+        Vec::new()
+    }
+
+    fn latency(_params: &Self::Params) -> u32 {
+        0
+    }
+
+    /// Called from [`Plugin::initialize()`].
+    fn initialize(_context: &mut impl InitContext<TestPlugin<Self>>) -> bool {
+        true
+    }
+
+    /// Called from [`Plugin::process()`]. Leaves the buffer alone by default.
+    fn process(
+        _params: &Self::Params,
+        _buffer: &mut Buffer,
+        _context: &mut impl ProcessContext<TestPlugin<Self>>,
+    ) -> ProcessStatus {
+        ProcessStatus::Normal
+    }
+}
+
+/// A plugin whose metadata and behavior are defined by `C`.
+pub(crate) struct TestPlugin<C: TestPluginConfig> {
+    pub params: Arc<C::Params>,
+    _config: PhantomData<C>,
+}
+
+/// Parameters for test plugins that don't need any.
+#[derive(Default)]
+pub(crate) struct NoParams;
+
+unsafe impl Params for NoParams {
+    fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+        Vec::new()
+    }
+}
+
+impl<C: TestPluginConfig> Default for TestPlugin<C> {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(C::Params::default()),
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<C: TestPluginConfig> Plugin for TestPlugin<C> {
+    const NAME: &'static str = C::NAME;
+    const VENDOR: &'static str = "Moist Plugins GmbH";
+    const URL: &'static str = "https://example.com";
+    const EMAIL: &'static str = "info@example.com";
+    const VERSION: &'static str = C::VERSION;
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = C::AUDIO_IO_LAYOUTS;
+    const HARD_REALTIME_ONLY: bool = C::HARD_REALTIME_ONLY;
+    const SAMPLE_ACCURATE_AUTOMATION: bool = C::SAMPLE_ACCURATE_AUTOMATION;
+    const SUPPORTED_SAMPLE_RATES: &'static [f32] = C::SUPPORTED_SAMPLE_RATES;
+    const PARAM_WRITE_LOCK: Duration = C::PARAM_WRITE_LOCK;
+    const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = C::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn latency(&self) -> u32 {
+        C::latency(&self.params)
+    }
+
+    fn about() -> Option<String> {
+        C::about()
+    }
+
+    fn factory_presets() -> Vec<FactoryPreset> {
+        C::factory_presets()
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        _buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        C::initialize(context)
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        C::process(&self.params, buffer, context)
+    }
+}
+
+impl<C: TestPluginConfig> ClapPlugin for TestPlugin<C> {
+    const CLAP_ID: &'static str = C::CLAP_ID;
+    const CLAP_DESCRIPTION: Option<&'static str> = C::CLAP_DESCRIPTION;
+    const CLAP_MANUAL_URL: Option<&'static str> = None;
+    const CLAP_SUPPORT_URL: Option<&'static str> = C::CLAP_SUPPORT_URL;
+    const CLAP_FEATURES: &'static [ClapFeature] = C::CLAP_FEATURES;
+
+    fn clap_id() -> String {
+        C::clap_id()
+    }
+}
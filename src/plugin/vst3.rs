@@ -1,4 +1,4 @@
-use super::Plugin;
+use super::{plugin_metadata, Plugin, PluginMetadata};
 use crate::prelude::Vst3SubCategory;
 
 /// Provides auxiliary metadata needed for a VST3 plugin.
@@ -6,7 +6,8 @@ pub trait Vst3Plugin: Plugin {
     /// The unique class ID that identifies this particular plugin. You can use the
     /// `*b"fooofooofooofooo"` syntax for this.
     ///
-    /// This will be shuffled into a different byte order on Windows for project-compatibility.
+    /// This will be shuffled into a different byte order on Windows for project-compatibility. This
+    /// can be overridden at runtime by implementing [`vst3_class_id()`][Self::vst3_class_id()].
     const VST3_CLASS_ID: [u8; 16];
     /// One or more subcategories. The host may use these to categorize the plugin. Internally this
     /// slice will be converted to a string where each character is separated by a pipe character
@@ -17,6 +18,43 @@ pub trait Vst3Plugin: Plugin {
     /// [`VST3_CLASS_ID`][Self::VST3_CLASS_ID`] in the correct order for the current platform so
     /// projects and presets can be shared between platforms. This should not be overridden.
     const PLATFORM_VST3_CLASS_ID: [u8; 16] = swap_vst3_uid_byte_order(Self::VST3_CLASS_ID);
+
+    /// The class ID the VST3 factory registers the plugin with. This defaults to
+    /// [`VST3_CLASS_ID`][Self::VST3_CLASS_ID], but it can be overridden to compute the ID when the
+    /// plugin is loaded, for instance for white-label builds that share the same plugin type. The
+    /// ID must not consist of only zeroes.
+    fn vst3_class_id() -> [u8; 16] {
+        Self::VST3_CLASS_ID
+    }
+
+    /// [`vst3_class_id()`][Self::vst3_class_id()] in the correct order for the current platform.
+    /// This should not be overridden.
+    fn platform_vst3_class_id() -> [u8; 16] {
+        swap_vst3_uid_byte_order(Self::vst3_class_id())
+    }
+}
+
+/// A plugin's VST3 metadata, returned by [`vst3_metadata()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vst3PluginMetadata {
+    /// The plugin's format independent metadata.
+    pub plugin: PluginMetadata,
+    /// The plugin's class ID, from [`Vst3Plugin::vst3_class_id()`].
+    pub class_id: [u8; 16],
+    /// The plugin's declared subcategories, from [`Vst3Plugin::VST3_SUBCATEGORIES`].
+    pub subcategories: &'static [Vst3SubCategory],
+}
+
+/// A plugin's [`plugin_metadata()`] combined with its VST3 class ID and subcategories. Like
+/// [`plugin_metadata()`], this can be used from build scripts without creating the plugin. The
+/// class ID is the one returned by [`Vst3Plugin::vst3_class_id()`], in the byte order used on all
+/// platforms other than Windows.
+pub fn vst3_metadata<P: Vst3Plugin>() -> Vst3PluginMetadata {
+    Vst3PluginMetadata {
+        plugin: plugin_metadata::<P>(),
+        class_id: P::vst3_class_id(),
+        subcategories: P::VST3_SUBCATEGORIES,
+    }
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -14,16 +14,22 @@ pub use crate::formatters;
 pub use crate::util;
 
 pub use crate::audio_setup::{
-    new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, PortNames, ProcessMode,
+    new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ChannelCounts, PortNames,
+    ProcessMode,
 };
 pub use crate::buffer::Buffer;
-pub use crate::context::gui::{AsyncExecutor, GuiContext, ParamSetter};
-pub use crate::context::init::InitContext;
-pub use crate::context::process::{ProcessContext, Transport};
+pub use crate::context::gui::{
+    AsyncExecutor, GuiContext, IndicationColor, ParamAutomationState, ParamChangeSource,
+    ParamIndication, ParamMapping, ParamSetter,
+};
+pub use crate::context::init::{
+    ChainHint, HostTimerCallback, InitContext, PosixFdCallback, PosixFdFlags, TrackKind,
+};
+pub use crate::context::process::{EventsIter, ProcessContext, TempoMap, TempoPoint, Transport};
 pub use crate::context::remote_controls::{
     RemoteControlsContext, RemoteControlsPage, RemoteControlsSection,
 };
-pub use crate::context::PluginApi;
+pub use crate::context::{PluginApi, RawHostHandle};
 // This also includes the derive macro
 pub use crate::editor::{Editor, ParentWindowHandle};
 pub use crate::midi::sysex::SysExMessage;
@@ -31,13 +37,21 @@ pub use crate::midi::{control_change, MidiConfig, NoteEvent, PluginNoteEvent};
 pub use crate::params::enums::{Enum, EnumParam};
 pub use crate::params::internals::ParamPtr;
 pub use crate::params::range::{FloatRange, IntRange};
-pub use crate::params::smoothing::{AtomicF32, Smoothable, Smoother, SmoothingStyle};
+pub use crate::params::smoothing::{
+    AtomicF32, Smoothable, Smoother, SmootherState, SmoothingStyle, VoiceSmoothers,
+};
 pub use crate::params::Params;
-pub use crate::params::{BoolParam, FloatParam, IntParam, Param, ParamFlags};
-pub use crate::plugin::clap::{ClapPlugin, PolyModulationConfig};
+pub use crate::params::{
+    BoolParam, FloatParam, IntParam, Param, ParamAppearance, ParamFlags, UnitClass,
+};
+pub use crate::plugin::clap::{
+    clap_metadata, ClapPlugin, ClapPluginMetadata, PolyModulationConfig,
+};
 #[cfg(feature = "vst3")]
-pub use crate::plugin::vst3::Vst3Plugin;
-pub use crate::plugin::{Plugin, ProcessStatus, TaskExecutor};
+pub use crate::plugin::vst3::{vst3_metadata, Vst3Plugin, Vst3PluginMetadata};
+pub use crate::plugin::{
+    plugin_metadata, FactoryPreset, Plugin, PluginMetadata, ProcessStatus, TaskExecutor,
+};
 pub use crate::wrapper::clap::features::ClapFeature;
 pub use crate::wrapper::state::PluginState;
 #[cfg(feature = "vst3")]
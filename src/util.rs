@@ -1,9 +1,73 @@
 //! General conversion functions and utilities.
 
+mod allpass;
+mod chorus;
+mod comb;
+mod compressor;
+mod crossfade;
+mod dc_blocker;
+mod downmix;
+mod enum_crossfade;
+mod fir;
+mod gain_reduction_meter;
+mod glide;
+pub mod interp;
+mod limiter;
+mod mod_delay;
+mod morph_smoother;
+pub mod mute_solo;
+mod panner;
+mod parameter_ramp;
+mod peak_hold;
+mod pitch;
+mod pitch_shifter;
+mod poly_modulation;
+mod rms_buffer;
+mod sinc;
+mod softclip;
+mod stereo_imager;
 mod stft;
+mod svf;
+mod transient_shaper;
+mod transport_phasor;
+mod wavetable;
 pub mod window;
 
+pub use allpass::AllpassFilter;
+pub use chorus::{Chorus, CHORUS_MAX_DELAY_MS};
+pub use comb::CombFilter;
+pub use compressor::{Compressor, CompressorDetection};
+pub use crossfade::Crossfade;
+pub use dc_blocker::DcBlocker;
+pub use downmix::{downmix_to_mono, DownmixCoeffs, MAX_DOWNMIX_CHANNELS};
+pub use enum_crossfade::EnumCrossfade;
+pub use fir::FirFilter;
+pub use gain_reduction_meter::{GainReductionMeter, GainReductionReader};
+pub use glide::{Glide, GlideMode};
+pub use limiter::Limiter;
+pub use mod_delay::{ModDelay, ModDelayInterpolation};
+pub use morph_smoother::{MorphMode, MorphSmoother};
+pub use panner::{PanLaw, Panner};
+pub use parameter_ramp::{Breakpoint, ParameterRamp, RampInterpolation};
+pub use peak_hold::PeakHold;
+pub use pitch::{
+    cents_to_ratio, freq_to_note, note_to_freq, ratio_to_cents, TuningTable, A4_NOTE,
+    DEFAULT_TUNING_HZ,
+};
+pub use pitch_shifter::PitchShifter;
+pub use poly_modulation::PolyModulationOffsets;
+pub use rms_buffer::RmsBuffer;
+pub use sinc::SincTable;
+pub use softclip::{softclip_cubic, softclip_hard, softclip_tanh};
+#[cfg(feature = "simd")]
+pub use softclip::{softclip_cubic_simd, softclip_hard_simd, softclip_tanh_simd};
+pub use stereo_imager::StereoImager;
 pub use stft::StftHelper;
+pub use svf::Svf;
+pub use transient_shaper::TransientShaper;
+pub use transport_phasor::TransportPhasor;
+pub use wavetable::Wavetable;
+pub use window::WindowFunction;
 
 pub const MINUS_INFINITY_DB: f32 = -100.0;
 pub const MINUS_INFINITY_GAIN: f32 = 1e-5; // 10f32.powf(MINUS_INFINITY_DB / 20)
@@ -79,6 +143,49 @@ pub fn gain_to_db_fast_epsilon(gain: f32) -> f32 {
     f32::max(gain, MINUS_INFINITY_GAIN).ln() * CONVERSION_FACTOR
 }
 
+/// Convert a pair of left and right samples to a `(mid, side)` pair. The mid signal is the average
+/// of the two channels, and the side signal is half of their difference.
+#[inline]
+pub fn stereo_to_mid_side(left: f32, right: f32) -> (f32, f32) {
+    ((left + right) * 0.5, (left - right) * 0.5)
+}
+
+/// The inverse of [`stereo_to_mid_side()`]. Converts a pair of mid and side samples back to a
+/// `(left, right)` pair.
+#[inline]
+pub fn mid_side_to_stereo(mid: f32, side: f32) -> (f32, f32) {
+    (mid + side, mid - side)
+}
+
+/// Convert a bipolar modulation signal in `[-1, 1]` to a unipolar signal in `[0, 1]`. The center
+/// of the bipolar range maps to 0.5. Values outside of the range are not clamped, see
+/// [`clamp_bipolar()`] and [`clamp_unipolar()`].
+#[inline]
+pub fn bipolar_to_unipolar(value: f32) -> f32 {
+    (value + 1.0) * 0.5
+}
+
+/// The inverse of [`bipolar_to_unipolar()`]. Converts a unipolar modulation signal in `[0, 1]` to a
+/// bipolar signal in `[-1, 1]`.
+#[inline]
+pub fn unipolar_to_bipolar(value: f32) -> f32 {
+    (value * 2.0) - 1.0
+}
+
+/// Clamp a bipolar modulation signal to `[-1, 1]`. Useful after summing multiple modulation
+/// sources.
+#[inline]
+pub fn clamp_bipolar(value: f32) -> f32 {
+    value.clamp(-1.0, 1.0)
+}
+
+/// Clamp a unipolar modulation signal to `[0, 1]`. Useful after summing multiple modulation
+/// sources.
+#[inline]
+pub fn clamp_unipolar(value: f32) -> f32 {
+    value.clamp(0.0, 1.0)
+}
+
 /// Convert a MIDI note ID to a frequency at A4 = 440 Hz equal temperament and middle C = note 60 =
 /// C4.
 #[inline]
@@ -88,10 +195,10 @@ pub fn midi_note_to_freq(note: u8) -> f32 {
 
 /// The same as [`midi_note_to_freq()`], but for arbitrary note numbers including those outside of
 /// the MIDI range. This also supports fractional note numbers, which is useful when working with
-/// cents.
+/// cents. See [`note_to_freq()`] for other reference tunings.
 #[inline]
 pub fn f32_midi_note_to_freq(note: f32) -> f32 {
-    2.0f32.powf((note - 69.0) / 12.0) * 440.0
+    note_to_freq(note, DEFAULT_TUNING_HZ)
 }
 
 /// The inverse of [`f32_midi_note_to_freq()`]. This returns a fractional note number. Round to a
@@ -99,7 +206,7 @@ pub fn f32_midi_note_to_freq(note: f32) -> f32 {
 /// number of cents.
 #[inline]
 pub fn freq_to_midi_note(freq: f32) -> f32 {
-    ((freq / 440.0).log2() * 12.0) + 69.0
+    freq_to_note(freq, DEFAULT_TUNING_HZ)
 }
 
 #[cfg(test)]
@@ -143,6 +250,44 @@ mod tests {
         }
     }
 
+    mod mid_side {
+        use super::super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let (mid, side) = stereo_to_mid_side(0.75, -0.25);
+            assert_eq!((mid, side), (0.25, 0.5));
+            assert_eq!(mid_side_to_stereo(mid, side), (0.75, -0.25));
+        }
+    }
+
+    mod modulation_polarity {
+        use super::super::*;
+
+        #[test]
+        fn test_bipolar_to_unipolar() {
+            assert_eq!(bipolar_to_unipolar(-1.0), 0.0);
+            assert_eq!(bipolar_to_unipolar(0.0), 0.5);
+            assert_eq!(bipolar_to_unipolar(1.0), 1.0);
+        }
+
+        #[test]
+        fn test_unipolar_to_bipolar() {
+            assert_eq!(unipolar_to_bipolar(0.0), -1.0);
+            assert_eq!(unipolar_to_bipolar(0.5), 0.0);
+            assert_eq!(unipolar_to_bipolar(1.0), 1.0);
+            assert_eq!(unipolar_to_bipolar(bipolar_to_unipolar(-0.25)), -0.25);
+        }
+
+        #[test]
+        fn test_clamp() {
+            assert_eq!(clamp_bipolar(1.5), 1.0);
+            assert_eq!(clamp_bipolar(-1.5), -1.0);
+            assert_eq!(clamp_unipolar(-0.5), 0.0);
+            assert_eq!(clamp_unipolar(0.25), 0.25);
+        }
+    }
+
     mod fast_db_gain_conversion {
         use super::super::*;
 
@@ -0,0 +1,126 @@
+//! A Schroeder allpass filter for building reverbs.
+
+/// A Schroeder allpass filter, computing `y[n] = -g * x[n] + x[n - D] + g * y[n - D]` where `D` is
+/// the delay in samples and `g` is the feedback gain. This has a flat magnitude response, but it
+/// smears an impulse out into a train of echoes spaced `D` samples apart. This increases the echo
+/// density of a reverb without coloring its frequency response. See
+/// [`CombFilter`][super::CombFilter] for the other half of a Schroeder reverb.
+///
+/// This filter processes a single channel. The delay line is allocated when the filter is created,
+/// so the delay length can only be chosen at that point. Processing never allocates.
+#[derive(Debug, Clone)]
+pub struct AllpassFilter {
+    /// The feedback gain `g`. Must be in `(-1, 1)` for the filter to be stable.
+    feedback: f32,
+    /// The delay line containing the filter's internal state `w[n] = x[n] + g * w[n - D]`. Its
+    /// length is the delay in samples.
+    buffer: Vec<f32>,
+    /// The index in `buffer` that will be read from and written to next.
+    pos: usize,
+}
+
+impl AllpassFilter {
+    /// Create an allpass filter with a delay of `delay_samples` samples and feedback gain
+    /// `feedback`. This allocates, so it should be done in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(delay_samples: usize, feedback: f32) -> Self {
+        nih_debug_assert!(delay_samples > 0);
+
+        let mut filter = Self {
+            feedback: 0.0,
+            buffer: vec![0.0; delay_samples.max(1)],
+            pos: 0,
+        };
+        filter.set_feedback(feedback);
+
+        filter
+    }
+
+    /// The delay in samples.
+    pub fn delay_samples(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The feedback gain.
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Change the feedback gain. The filter is only stable for feedback gains with an absolute
+    /// value below 1. This does not reset the filter's state.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        nih_debug_assert!(feedback.abs() < 1.0);
+
+        self.feedback = feedback;
+    }
+
+    /// Clear the delay line.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.pos = 0;
+    }
+
+    /// Filter a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let delayed = self.buffer[self.pos];
+        let state = sample + (self.feedback * delayed);
+        self.buffer[self.pos] = state;
+        self.pos += 1;
+        if self.pos == self.buffer.len() {
+            self.pos = 0;
+        }
+
+        delayed - (self.feedback * state)
+    }
+
+    /// Filter a block of samples in place.
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impulse_response_shape() {
+        let feedback = 0.5;
+        let mut allpass = AllpassFilter::new(3, feedback);
+        let mut samples = vec![0.0; 10];
+        samples[0] = 1.0;
+        allpass.process_block(&mut samples);
+
+        // An immediate inverted impulse, followed by decaying echoes every three samples
+        let first_echo = 1.0 - (feedback * feedback);
+        for (sample_idx, sample) in samples.iter().enumerate() {
+            let expected = match sample_idx {
+                0 => -feedback,
+                3 => first_echo,
+                6 => first_echo * feedback,
+                9 => first_echo * feedback * feedback,
+                _ => 0.0,
+            };
+            approx::assert_relative_eq!(*sample, expected);
+        }
+    }
+
+    #[test]
+    fn stable_and_preserves_energy() {
+        let mut allpass = AllpassFilter::new(23, 0.9);
+        let mut samples = vec![0.0; 23 * 1000];
+        samples[0] = 1.0;
+        allpass.process_block(&mut samples);
+
+        // An allpass filter has unity gain at all frequencies, so the impulse response's energy is
+        // the same as the impulse's
+        assert!(samples
+            .iter()
+            .all(|sample| sample.is_finite() && sample.abs() <= 1.0));
+        let energy: f32 = samples.iter().map(|sample| sample * sample).sum();
+        approx::assert_relative_eq!(energy, 1.0, epsilon = 1e-4);
+    }
+}
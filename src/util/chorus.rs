@@ -0,0 +1,364 @@
+//! A multi-voice stereo chorus that doubles as a flanger.
+
+use std::f32::consts::TAU;
+
+use super::{ModDelay, ModDelayInterpolation};
+use crate::buffer::Buffer;
+
+/// The longest delay time in milliseconds [`Chorus`] supports, including the LFO's modulation
+/// depth. Longer delay times are clamped.
+pub const CHORUS_MAX_DELAY_MS: f32 = 50.0;
+/// The highest amount of feedback [`Chorus`] allows. Feedback amounts closer to 1.0 would take a
+/// very long time to decay.
+const MAX_FEEDBACK: f32 = 0.95;
+
+/// A chorus effect built from several [`ModDelay`] voices. Each voice delays the input by a time
+/// that is swept with a sine LFO, and the voices' LFOs are spread evenly over the LFO cycle. Every
+/// channel gets its own set of voices. With [`set_stereo_spread()`][Self::set_stereo_spread()] the
+/// channels' LFOs are offset from each other, which decorrelates the channels and widens the
+/// stereo image even for mono input.
+///
+/// The same structure acts as a flanger when using a single voice with a short delay time, a
+/// shallow depth, and some [feedback][Self::set_feedback()]:
+///
+/// ```ignore
+/// chorus.set_voices(1);
+/// chorus.set_delay_ms(2.0);
+/// chorus.set_depth_ms(1.5);
+/// chorus.set_feedback(0.7);
+/// ```
+///
+/// The delay lines are allocated in [`set_sample_rate()`][Self::set_sample_rate()], which should
+/// be called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Processing never
+/// allocates. The dry signal is not delayed, so the chorus does not add any latency.
+#[derive(Debug, Clone)]
+pub struct Chorus {
+    /// The number of active voices, in `[1, max_voices]`.
+    num_voices: usize,
+    /// The LFO's frequency in Hertz.
+    rate_hz: f32,
+    /// The amount the LFO sweeps the delay time by in both directions, in milliseconds.
+    depth_ms: f32,
+    /// The delay time at the center of the LFO's sweep, in milliseconds.
+    delay_ms: f32,
+    /// The amount of each voice's output that is fed back into its input.
+    feedback: f32,
+    /// The amount of wet signal in the output, in `[0, 1]`.
+    mix: f32,
+    /// How far the channels' LFOs are offset from each other, in `[0, 1]`.
+    stereo_spread: f32,
+
+    /// The current sample rate, set in [`set_sample_rate()`][Self::set_sample_rate()].
+    sample_rate: f32,
+    /// The LFO's phase in `[0, 1)`. The voices and channels are offset from this phase.
+    lfo_phase: f32,
+
+    /// The delay lines for every channel and voice, indexed by `[channel_idx][voice_idx]`.
+    delays: Vec<Vec<ModDelay>>,
+    /// The last output of every channel and voice, used for the feedback.
+    feedback_samples: Vec<Vec<f32>>,
+}
+
+impl Chorus {
+    /// Create a chorus for up to `num_channels` channels and up to `max_voices` voices per
+    /// channel. The chorus defaults to two voices with a 10 ms delay time modulated by 2 ms at
+    /// 1 Hz, no feedback, an equal mix between the dry and the wet signal, and the full stereo
+    /// spread. Call [`set_sample_rate()`][Self::set_sample_rate()] before processing audio.
+    pub fn new(num_channels: usize, max_voices: usize) -> Self {
+        nih_debug_assert!(max_voices > 0);
+
+        let max_voices = max_voices.max(1);
+        Self {
+            num_voices: 2.min(max_voices),
+            rate_hz: 1.0,
+            depth_ms: 2.0,
+            delay_ms: 10.0,
+            feedback: 0.0,
+            mix: 0.5,
+            stereo_spread: 1.0,
+
+            sample_rate: 0.0,
+            lfo_phase: 0.0,
+
+            delays: vec![
+                vec![ModDelay::new(1, ModDelayInterpolation::CatmullRom); max_voices];
+                num_channels
+            ],
+            feedback_samples: vec![vec![0.0; max_voices]; num_channels],
+        }
+    }
+
+    /// Set the sample rate. This allocates the delay lines and resets the chorus, so it should be
+    /// called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()].
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.sample_rate = sample_rate;
+        let max_delay_samples = (CHORUS_MAX_DELAY_MS / 1000.0 * sample_rate).ceil() as usize;
+        for delays in &mut self.delays {
+            for delay in delays {
+                *delay = ModDelay::new(max_delay_samples.max(1), ModDelayInterpolation::CatmullRom);
+            }
+        }
+
+        self.reset();
+    }
+
+    /// Set the number of voices per channel. This is clamped to the `max_voices` the chorus was
+    /// created with. The voices' LFOs are spread evenly over the LFO cycle.
+    pub fn set_voices(&mut self, num_voices: usize) {
+        let max_voices = self.feedback_samples.first().map_or(1, Vec::len);
+        nih_debug_assert!((1..=max_voices).contains(&num_voices));
+
+        let num_voices = num_voices.clamp(1, max_voices);
+        if num_voices > self.num_voices {
+            // Voices that were inactive may still contain old audio
+            for (delays, feedback_samples) in self.delays.iter_mut().zip(&mut self.feedback_samples)
+            {
+                for voice_idx in self.num_voices..num_voices {
+                    delays[voice_idx].reset();
+                    feedback_samples[voice_idx] = 0.0;
+                }
+            }
+        }
+
+        self.num_voices = num_voices;
+    }
+
+    /// Set the LFO's frequency in Hertz.
+    pub fn set_rate_hz(&mut self, rate_hz: f32) {
+        nih_debug_assert!(rate_hz >= 0.0);
+
+        self.rate_hz = rate_hz.max(0.0);
+    }
+
+    /// Set the amount the LFO sweeps the delay time by in both directions, in milliseconds.
+    pub fn set_depth_ms(&mut self, depth_ms: f32) {
+        nih_debug_assert!(depth_ms >= 0.0);
+
+        self.depth_ms = depth_ms.max(0.0);
+    }
+
+    /// Set the delay time at the center of the LFO's sweep in milliseconds. The delay time
+    /// including the modulation depth is clamped to [`CHORUS_MAX_DELAY_MS`].
+    pub fn set_delay_ms(&mut self, delay_ms: f32) {
+        nih_debug_assert!(delay_ms >= 0.0);
+
+        self.delay_ms = delay_ms.max(0.0);
+    }
+
+    /// Set the amount of each voice's output that is fed back into its input, in `[-0.95, 0.95]`.
+    /// Negative amounts invert the feedback's polarity. Feedback turns the chorus into a flanger.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+
+    /// Set the amount of wet signal in the output. 0.0 only outputs the dry signal, and 1.0 only
+    /// outputs the delayed voices.
+    pub fn set_mix(&mut self, mix: f32) {
+        nih_debug_assert!((0.0..=1.0).contains(&mix));
+
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// Set how far the channels' LFOs are offset from each other. At 0.0 every channel is
+    /// modulated identically, and at 1.0 the channels' voices are interleaved so they sweep
+    /// halfway between each other's voices.
+    pub fn set_stereo_spread(&mut self, stereo_spread: f32) {
+        nih_debug_assert!((0.0..=1.0).contains(&stereo_spread));
+
+        self.stereo_spread = stereo_spread.clamp(0.0, 1.0);
+    }
+
+    /// The number of samples the chorus delays the signal by. The dry signal is passed through
+    /// directly, so this is always zero. This exists for symmetry with the other effects in this
+    /// module.
+    pub fn latency_samples(&self) -> u32 {
+        0
+    }
+
+    /// Clear the delay lines and restart the LFO.
+    pub fn reset(&mut self) {
+        for delays in &mut self.delays {
+            for delay in delays {
+                delay.reset();
+            }
+        }
+        for feedback_samples in &mut self.feedback_samples {
+            feedback_samples.fill(0.0);
+        }
+        self.lfo_phase = 0.0;
+    }
+
+    /// Process `buffer` in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` has more channels than the chorus was created for.
+    pub fn process(&mut self, buffer: &mut Buffer) {
+        assert!(buffer.channels() <= self.delays.len());
+
+        let num_samples = buffer.samples();
+        let num_channels = buffer.channels();
+        let output = buffer.as_slice();
+
+        let samples_per_ms = self.sample_rate / 1000.0;
+        let lfo_increment = if self.sample_rate > 0.0 {
+            self.rate_hz / self.sample_rate
+        } else {
+            0.0
+        };
+        let voice_offset = (self.num_voices as f32).recip();
+        // The last channel's voices end up halfway between the first channel's voices
+        let channel_offset = if num_channels > 1 {
+            self.stereo_spread * voice_offset * 0.5 / (num_channels - 1) as f32
+        } else {
+            0.0
+        };
+        let wet_gain = self.mix * voice_offset;
+        let dry_gain = 1.0 - self.mix;
+
+        for sample_idx in 0..num_samples {
+            for (channel_idx, ((channel, delays), feedback_samples)) in output
+                .iter_mut()
+                .zip(&mut self.delays)
+                .zip(&mut self.feedback_samples)
+                .enumerate()
+            {
+                let dry = channel[sample_idx];
+                let mut wet = 0.0;
+                for (voice_idx, (delay, feedback_sample)) in delays
+                    .iter_mut()
+                    .zip(feedback_samples.iter_mut())
+                    .take(self.num_voices)
+                    .enumerate()
+                {
+                    let phase = self.lfo_phase
+                        + (voice_idx as f32 * voice_offset)
+                        + (channel_idx as f32 * channel_offset);
+                    let lfo = (TAU * phase).sin();
+                    let delay_samples = (self.delay_ms + (lfo * self.depth_ms)) * samples_per_ms;
+
+                    *feedback_sample =
+                        delay.process(dry + (*feedback_sample * self.feedback), delay_samples);
+                    wet += *feedback_sample;
+                }
+
+                channel[sample_idx] = (dry * dry_gain) + (wet * wet_gain);
+            }
+
+            self.lfo_phase += lfo_increment;
+            if self.lfo_phase >= 1.0 {
+                self.lfo_phase -= 1.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::process_test_blocks;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// Run `chorus` over `data` in blocks of `block_size` samples.
+    fn process(chorus: &mut Chorus, data: &mut [Vec<f32>], block_size: usize) {
+        process_test_blocks(data, std::iter::repeat(block_size), |buffer| {
+            chorus.process(buffer)
+        });
+    }
+
+    /// Deterministic white noise in `[-1, 1]`.
+    fn noise(num_samples: usize) -> Vec<f32> {
+        let mut state = 0x1234_5678u32;
+        (0..num_samples)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+            })
+            .collect()
+    }
+
+    /// The normalized correlation between two signals.
+    fn correlation(a: &[f32], b: &[f32]) -> f32 {
+        let dot = |x: &[f32], y: &[f32]| -> f64 {
+            x.iter().zip(y).map(|(x, y)| *x as f64 * *y as f64).sum()
+        };
+
+        (dot(a, b) / (dot(a, a) * dot(b, b)).sqrt()) as f32
+    }
+
+    #[test]
+    fn lfo_modulation_depth() {
+        let mut chorus = Chorus::new(1, 1);
+        chorus.set_sample_rate(SAMPLE_RATE);
+        chorus.set_voices(1);
+        chorus.set_rate_hz(4.0);
+        chorus.set_delay_ms(10.0);
+        chorus.set_depth_ms(3.0);
+        chorus.set_mix(1.0);
+
+        // The interpolation is exact for a ramp, so the output's distance to the input is exactly
+        // the delay time
+        let num_samples = SAMPLE_RATE as usize / 2;
+        let mut data = vec![(0..num_samples).map(|i| i as f32).collect::<Vec<f32>>()];
+        process(&mut chorus, &mut data, 128);
+
+        let delays: Vec<f32> = data[0]
+            .iter()
+            .enumerate()
+            .skip(1000)
+            .map(|(i, sample)| (i as f32 - sample) / (SAMPLE_RATE / 1000.0))
+            .collect();
+        let min_delay = delays.iter().copied().fold(f32::INFINITY, f32::min);
+        let max_delay = delays.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert!((min_delay - 7.0).abs() < 0.01, "{min_delay}");
+        assert!((max_delay - 13.0).abs() < 0.01, "{max_delay}");
+    }
+
+    #[test]
+    fn stereo_decorrelation() {
+        let num_samples = SAMPLE_RATE as usize;
+        let input = noise(num_samples);
+
+        for stereo_spread in [0.0, 1.0] {
+            let mut chorus = Chorus::new(2, 4);
+            chorus.set_sample_rate(SAMPLE_RATE);
+            chorus.set_voices(3);
+            chorus.set_mix(1.0);
+            chorus.set_stereo_spread(stereo_spread);
+
+            let mut data = vec![input.clone(), input.clone()];
+            process(&mut chorus, &mut data, 256);
+
+            let correlation = correlation(&data[0], &data[1]);
+            if stereo_spread == 0.0 {
+                assert_eq!(data[0], data[1]);
+            } else {
+                assert!(correlation < 0.5, "{correlation}");
+            }
+        }
+    }
+
+    #[test]
+    fn flanger_feedback_is_stable() {
+        let mut flanger = Chorus::new(2, 1);
+        flanger.set_sample_rate(SAMPLE_RATE);
+        flanger.set_voices(1);
+        flanger.set_delay_ms(2.0);
+        flanger.set_depth_ms(1.5);
+        flanger.set_feedback(2.0);
+
+        // Even with the feedback clamped to its maximum the output's level stays bounded
+        let input = noise(SAMPLE_RATE as usize);
+        let mut data = vec![input.clone(), input];
+        process(&mut flanger, &mut data, 64);
+
+        let peak = data
+            .iter()
+            .flatten()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak.is_finite() && peak < 20.0, "{peak}");
+    }
+}
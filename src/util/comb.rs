@@ -0,0 +1,119 @@
+//! A feedback comb filter for building reverbs.
+
+/// A Schroeder feedback comb filter, computing `y[n] = x[n - D] + g * y[n - D]` where `D` is the
+/// delay in samples and `g` is the feedback gain. An impulse results in a train of echoes spaced
+/// `D` samples apart that decay by a factor of `g` every echo. Several of these in parallel with
+/// mutually prime delay lengths, followed by a couple of [`AllpassFilter`][super::AllpassFilter]s
+/// in series, form the classic Schroeder reverb.
+///
+/// This filter processes a single channel. The delay line is allocated when the filter is created,
+/// so the delay length can only be chosen at that point. Processing never allocates.
+#[derive(Debug, Clone)]
+pub struct CombFilter {
+    /// The feedback gain `g`. Must be in `(-1, 1)` for the filter to be stable.
+    feedback: f32,
+    /// The delay line. Its length is the delay in samples.
+    buffer: Vec<f32>,
+    /// The index in `buffer` that will be read from and written to next.
+    pos: usize,
+}
+
+impl CombFilter {
+    /// Create a comb filter with a delay of `delay_samples` samples and feedback gain `feedback`.
+    /// This allocates, so it should be done in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(delay_samples: usize, feedback: f32) -> Self {
+        nih_debug_assert!(delay_samples > 0);
+
+        let mut filter = Self {
+            feedback: 0.0,
+            buffer: vec![0.0; delay_samples.max(1)],
+            pos: 0,
+        };
+        filter.set_feedback(feedback);
+
+        filter
+    }
+
+    /// The delay in samples.
+    pub fn delay_samples(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The feedback gain.
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Change the feedback gain. The filter is only stable for feedback gains with an absolute
+    /// value below 1. This does not reset the filter's state.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        nih_debug_assert!(feedback.abs() < 1.0);
+
+        self.feedback = feedback;
+    }
+
+    /// Clear the delay line.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.pos = 0;
+    }
+
+    /// Filter a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let output = self.buffer[self.pos];
+        self.buffer[self.pos] = sample + (self.feedback * output);
+        self.pos += 1;
+        if self.pos == self.buffer.len() {
+            self.pos = 0;
+        }
+
+        output
+    }
+
+    /// Filter a block of samples in place.
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impulse_response_decays() {
+        let mut comb = CombFilter::new(4, 0.5);
+        let mut samples = vec![0.0; 16];
+        samples[0] = 1.0;
+        comb.process_block(&mut samples);
+
+        // Echoes every four samples, each half as loud as the previous one
+        for (sample_idx, sample) in samples.iter().enumerate() {
+            let expected = match sample_idx {
+                4 => 1.0,
+                8 => 0.5,
+                12 => 0.25,
+                _ => 0.0,
+            };
+            assert_eq!(*sample, expected, "sample {sample_idx}");
+        }
+    }
+
+    #[test]
+    fn stable_with_high_feedback() {
+        let mut comb = CombFilter::new(37, 0.98);
+        let mut samples = vec![0.0; 37 * 2000];
+        samples[0] = 1.0;
+        comb.process_block(&mut samples);
+
+        assert!(samples
+            .iter()
+            .all(|sample| sample.is_finite() && sample.abs() <= 1.0));
+        let tail = &samples[samples.len() - 37..];
+        assert!(tail.iter().all(|sample| sample.abs() < 1e-6));
+    }
+}
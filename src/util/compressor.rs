@@ -0,0 +1,377 @@
+//! A feed-forward compressor with an optional sidechain input.
+
+use crate::buffer::Buffer;
+use crate::util::{db_to_gain_fast, gain_to_db_fast};
+
+/// How [`Compressor`] detects the input level when processing more than one channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressorDetection {
+    /// Use the loudest channel to compute a single gain reduction value that's applied to all
+    /// channels. This keeps the stereo image intact.
+    Linked,
+    /// Compute and apply the gain reduction for every channel separately.
+    Independent,
+}
+
+/// A feed-forward compressor with a soft knee, so every dynamics processor doesn't need to
+/// reimplement one. The compressor detects the input's peak level, computes the gain reduction
+/// using a static compression curve, and then smooths that gain reduction in the decibel domain
+/// using separate attack and release times. The level can be detected either from the signal that's
+/// being compressed, or from a separate sidechain buffer.
+///
+/// The static curve uses a quadratic soft knee centered around the threshold. Below
+/// `threshold - knee / 2` the signal is left alone, above `threshold + knee / 2` the level above
+/// the threshold is divided by the ratio, and the knee interpolates between those two regions. A
+/// knee width of 0 dB results in a hard knee.
+///
+/// Gain reduction values are expressed in positive decibels, so 6.0 means that the signal is being
+/// attenuated by 6 dB. [`process()`][Self::process()] returns the maximum gain reduction for the
+/// block, which can be passed directly to
+/// [`GainReductionMeter::next_block()`][super::GainReductionMeter::next_block()] for metering.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.compressor.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`
+/// self.compressor.set_threshold_db(self.params.threshold.value());
+/// let gain_reduction_db = self.compressor.process(buffer, Some(&aux.inputs[0]));
+/// self.gain_reduction_meter.next_block(gain_reduction_db, buffer.samples());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    /// The threshold in decibels.
+    threshold_db: f32,
+    /// The compression ratio. A ratio of 4 means that for every 4 dB the input level goes over the
+    /// threshold, the output level goes 1 dB over the threshold.
+    ratio: f32,
+    /// The width of the soft knee in decibels.
+    knee_db: f32,
+    /// The attack time in milliseconds.
+    attack_ms: f32,
+    /// The release time in milliseconds.
+    release_ms: f32,
+    /// The gain applied after compressing the signal, in decibels.
+    makeup_db: f32,
+    /// How the level is detected when there is more than one channel.
+    detection: CompressorDetection,
+
+    /// The current sample rate, set in [`set_sample_rate()`][Self::set_sample_rate()].
+    sample_rate: f32,
+    /// The per-sample attack coefficient computed from `attack_ms` and the sample rate.
+    attack_coefficient: f32,
+    /// The per-sample release coefficient computed from `release_ms` and the sample rate.
+    release_coefficient: f32,
+    /// The smoothed gain reduction in decibels for every channel. Only the first element is used
+    /// with linked detection.
+    gain_reduction_db: Vec<f32>,
+}
+
+impl Compressor {
+    /// Create a compressor for up to `num_channels` channels. The compressor defaults to a -18 dB
+    /// threshold, a 4:1 ratio, a 6 dB soft knee, 10 ms attack, 100 ms release, no makeup gain, and
+    /// linked detection. The attack and release are instant until
+    /// [`set_sample_rate()`][Self::set_sample_rate()] has been called.
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            threshold_db: -18.0,
+            ratio: 4.0,
+            knee_db: 6.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            makeup_db: 0.0,
+            detection: CompressorDetection::Linked,
+
+            sample_rate: 0.0,
+            attack_coefficient: 0.0,
+            release_coefficient: 0.0,
+            gain_reduction_db: vec![0.0; num_channels],
+        }
+    }
+
+    /// Set the sample rate used to compute the attack and release coefficients. This should be
+    /// called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets
+    /// the compressor.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+        self.reset();
+    }
+
+    /// Set the threshold in decibels.
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db;
+    }
+
+    /// Set the compression ratio. This needs to be at least 1. Use `f32::INFINITY` for limiting.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        nih_debug_assert!(ratio >= 1.0);
+
+        self.ratio = ratio.max(1.0);
+    }
+
+    /// Set the width of the soft knee in decibels. A width of 0 dB results in a hard knee.
+    pub fn set_knee_db(&mut self, knee_db: f32) {
+        nih_debug_assert!(knee_db >= 0.0);
+
+        self.knee_db = knee_db.max(0.0);
+    }
+
+    /// Set the attack time in milliseconds. After this much time the gain reduction has moved 63%
+    /// of the way towards a higher gain reduction.
+    pub fn set_attack_ms(&mut self, attack_ms: f32) {
+        nih_debug_assert!(attack_ms >= 0.0);
+
+        self.attack_ms = attack_ms;
+        self.update_coefficients();
+    }
+
+    /// Set the release time in milliseconds. After this much time the gain reduction has moved 63%
+    /// of the way towards a lower gain reduction.
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        nih_debug_assert!(release_ms >= 0.0);
+
+        self.release_ms = release_ms;
+        self.update_coefficients();
+    }
+
+    /// Set the makeup gain in decibels.
+    pub fn set_makeup_db(&mut self, makeup_db: f32) {
+        self.makeup_db = makeup_db;
+    }
+
+    /// Set how the level is detected when processing more than one channel. This also resets the
+    /// compressor.
+    pub fn set_detection(&mut self, detection: CompressorDetection) {
+        if detection != self.detection {
+            self.detection = detection;
+            self.reset();
+        }
+    }
+
+    /// Clear the gain reduction for all channels.
+    pub fn reset(&mut self) {
+        self.gain_reduction_db.fill(0.0);
+    }
+
+    /// The static compression curve. Returns the gain reduction in decibels for an input level in
+    /// decibels, without any attack or release smoothing.
+    pub fn static_gain_reduction_db(&self, input_db: f32) -> f32 {
+        let overshoot_db = input_db - self.threshold_db;
+        let slope = 1.0 - self.ratio.recip();
+        if 2.0 * overshoot_db <= -self.knee_db {
+            0.0
+        } else if 2.0 * overshoot_db.abs() < self.knee_db {
+            let knee_overshoot_db = overshoot_db + (self.knee_db / 2.0);
+            slope * knee_overshoot_db * knee_overshoot_db / (2.0 * self.knee_db)
+        } else {
+            slope * overshoot_db
+        }
+    }
+
+    /// Compress `buffer` in place and apply the makeup gain. If `sidechain` is set, then the level
+    /// is detected from that buffer instead of from `buffer`. With independent detection, channels
+    /// without a matching sidechain channel use the sidechain's last channel. Returns the highest
+    /// gain reduction in decibels that was applied to any channel during this block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` has more channels than the compressor was created for, or if `sidechain`
+    /// has fewer samples than `buffer`.
+    pub fn process(&mut self, buffer: &mut Buffer, sidechain: Option<&Buffer>) -> f32 {
+        assert!(buffer.channels() <= self.gain_reduction_db.len());
+
+        let num_samples = buffer.samples();
+        let sidechain = sidechain
+            .map(|sidechain| sidechain.as_slice_immutable())
+            .filter(|sidechain| !sidechain.is_empty());
+        let output = buffer.as_slice();
+
+        let mut max_gain_reduction_db = 0.0f32;
+        for sample_idx in 0..num_samples {
+            match self.detection {
+                CompressorDetection::Linked => {
+                    let peak = match sidechain {
+                        Some(sidechain) => peak_at(sidechain, sample_idx),
+                        None => peak_at(output, sample_idx),
+                    };
+                    let gain_reduction_db = self.next_gain_reduction_db(0, peak);
+                    max_gain_reduction_db = max_gain_reduction_db.max(gain_reduction_db);
+
+                    let gain = db_to_gain_fast(self.makeup_db - gain_reduction_db);
+                    for channel in output.iter_mut() {
+                        channel[sample_idx] *= gain;
+                    }
+                }
+                CompressorDetection::Independent => {
+                    for (channel_idx, channel) in output.iter_mut().enumerate() {
+                        let level = match sidechain {
+                            Some(sidechain) => {
+                                sidechain[channel_idx.min(sidechain.len() - 1)][sample_idx]
+                            }
+                            None => channel[sample_idx],
+                        };
+                        let gain_reduction_db =
+                            self.next_gain_reduction_db(channel_idx, level.abs());
+                        max_gain_reduction_db = max_gain_reduction_db.max(gain_reduction_db);
+
+                        channel[sample_idx] *= db_to_gain_fast(self.makeup_db - gain_reduction_db);
+                    }
+                }
+            }
+        }
+
+        max_gain_reduction_db
+    }
+
+    /// Update the smoothed gain reduction for a channel based on the current peak level, and
+    /// return the new gain reduction in decibels.
+    #[inline]
+    fn next_gain_reduction_db(&mut self, channel_idx: usize, peak: f32) -> f32 {
+        let target_db = self.static_gain_reduction_db(gain_to_db_fast(peak));
+        let current_db = &mut self.gain_reduction_db[channel_idx];
+        let coefficient = if target_db > *current_db {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        *current_db = target_db + (coefficient * (*current_db - target_db));
+
+        *current_db
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coefficient = time_constant_coefficient(self.attack_ms, self.sample_rate);
+        self.release_coefficient = time_constant_coefficient(self.release_ms, self.sample_rate);
+    }
+}
+
+/// The highest absolute sample value across all channels at a sample index.
+#[inline]
+fn peak_at(channels: &[&mut [f32]], sample_idx: usize) -> f32 {
+    channels
+        .iter()
+        .map(|channel| channel[sample_idx].abs())
+        .fold(0.0, f32::max)
+}
+
+/// The one-pole coefficient for a time constant in milliseconds. Returns 0 for instant changes.
+pub(super) fn time_constant_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    let time_samples = time_ms / 1000.0 * sample_rate;
+    if time_samples > 0.0 {
+        (-time_samples.recip()).exp()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    /// Run `compressor` over `data`, optionally with a sidechain, and return the gain reduction
+    /// reported by the compressor.
+    fn process(
+        compressor: &mut Compressor,
+        data: &mut [Vec<f32>],
+        sidechain_data: Option<&mut [Vec<f32>]>,
+    ) -> f32 {
+        with_test_buffer(data, |buffer| match sidechain_data {
+            Some(sidechain_data) => with_test_buffer(sidechain_data, |sidechain| {
+                compressor.process(buffer, Some(&*sidechain))
+            }),
+            None => compressor.process(buffer, None),
+        })
+    }
+
+    #[test]
+    fn static_curve() {
+        let mut compressor = Compressor::new(1);
+        compressor.set_threshold_db(-20.0);
+        compressor.set_ratio(4.0);
+
+        // With a hard knee the level above the threshold is divided by the ratio
+        compressor.set_knee_db(0.0);
+        assert_eq!(compressor.static_gain_reduction_db(-30.0), 0.0);
+        assert_eq!(compressor.static_gain_reduction_db(-20.0), 0.0);
+        approx::assert_relative_eq!(compressor.static_gain_reduction_db(-10.0), 7.5);
+
+        // The soft knee starts compressing half the knee width below the threshold, and it joins
+        // the hard knee curve half the knee width above it
+        compressor.set_knee_db(10.0);
+        assert_eq!(compressor.static_gain_reduction_db(-25.0), 0.0);
+        approx::assert_relative_eq!(compressor.static_gain_reduction_db(-20.0), 0.9375);
+        approx::assert_relative_eq!(compressor.static_gain_reduction_db(-15.0), 3.75);
+        approx::assert_relative_eq!(compressor.static_gain_reduction_db(-10.0), 7.5);
+
+        // The makeup gain is applied on top of that. There's no smoothing without a sample rate.
+        compressor.set_makeup_db(6.0);
+        let mut data = vec![vec![db_to_gain_fast(-10.0)]];
+        let gain_reduction_db = process(&mut compressor, &mut data, None);
+        approx::assert_relative_eq!(gain_reduction_db, 7.5, epsilon = 1e-3);
+        approx::assert_relative_eq!(gain_to_db_fast(data[0][0]), -11.5, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn attack_and_release_timing() {
+        let mut compressor = Compressor::new(1);
+        compressor.set_threshold_db(-20.0);
+        compressor.set_ratio(f32::INFINITY);
+        compressor.set_knee_db(0.0);
+        compressor.set_attack_ms(10.0);
+        compressor.set_release_ms(100.0);
+        compressor.set_sample_rate(1000.0);
+
+        // A 0 dB signal should be reduced by 20 dB, and after 10 samples the gain reduction should
+        // have moved 63% of the way there
+        let mut data = vec![vec![1.0; 10]];
+        let gain_reduction_db = process(&mut compressor, &mut data, None);
+        let expected_attack_db = 20.0 * (1.0 - (-1.0f32).exp());
+        approx::assert_relative_eq!(gain_reduction_db, expected_attack_db, epsilon = 1e-3);
+        approx::assert_relative_eq!(
+            gain_to_db_fast(data[0][9]),
+            -expected_attack_db,
+            epsilon = 1e-3
+        );
+
+        // A signal below the threshold releases the gain reduction over 100 samples
+        let mut data = vec![vec![db_to_gain_fast(-40.0); 100]];
+        process(&mut compressor, &mut data, None);
+        let expected_release_db = expected_attack_db * (-1.0f32).exp();
+        approx::assert_relative_eq!(
+            gain_to_db_fast(data[0][99]),
+            -40.0 - expected_release_db,
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn sidechain_linked_and_independent() {
+        let mut compressor = Compressor::new(2);
+        compressor.set_threshold_db(-20.0);
+        compressor.set_ratio(2.0);
+        compressor.set_knee_db(0.0);
+
+        // Only the sidechain's first channel is loud, so linked detection reduces both channels by
+        // 10 dB even though the main input is quiet
+        let quiet = db_to_gain_fast(-30.0);
+        let mut data = vec![vec![quiet], vec![quiet]];
+        let mut sidechain_data = vec![vec![1.0], vec![0.0]];
+        let gain_reduction_db = process(&mut compressor, &mut data, Some(&mut sidechain_data));
+        approx::assert_relative_eq!(gain_reduction_db, 10.0, epsilon = 1e-3);
+        approx::assert_relative_eq!(gain_to_db_fast(data[0][0]), -40.0, epsilon = 1e-3);
+        approx::assert_relative_eq!(gain_to_db_fast(data[1][0]), -40.0, epsilon = 1e-3);
+
+        // With independent detection only the first channel is compressed
+        compressor.set_detection(CompressorDetection::Independent);
+        let mut data = vec![vec![quiet], vec![quiet]];
+        process(&mut compressor, &mut data, Some(&mut sidechain_data));
+        approx::assert_relative_eq!(gain_to_db_fast(data[0][0]), -40.0, epsilon = 1e-3);
+        approx::assert_relative_eq!(gain_to_db_fast(data[1][0]), -30.0, epsilon = 1e-3);
+    }
+}
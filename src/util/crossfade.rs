@@ -0,0 +1,236 @@
+//! Equal-power crossfades between two buffers.
+
+use std::f32;
+
+use crate::buffer::Buffer;
+
+/// Manages an equal-power crossfade from one buffer to another over a fixed duration. This is
+/// useful when a plugin needs to switch between two processed versions of the same signal, for
+/// instance when switching algorithms or when reloading an impulse response. The plugin processes
+/// the signal both ways into two buffers while the crossfade is active, and
+/// [`process()`][Self::process()] then writes the mix of those buffers into the output buffer while
+/// automatically advancing the crossfade.
+///
+/// The crossfade starts fully at A. After calling [`start()`][Self::start()] it moves towards B,
+/// and it stays at B once the crossfade has finished. Call [`reset()`][Self::reset()] to go back
+/// to A, for instance after the plugin has swapped its old algorithm out for the new one.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.crossfade.set_sample_rate(buffer_config.sample_rate);
+///
+/// // When the algorithm changes
+/// self.crossfade.start();
+///
+/// // In `process()`, with `old_buffer` and `new_buffer` containing the two processed signals
+/// if self.crossfade.is_active() {
+///     self.crossfade.process(&old_buffer, &new_buffer, buffer);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Crossfade {
+    /// The crossfade's duration in milliseconds.
+    time_ms: f32,
+    /// The sample rate passed to [`set_sample_rate()`][Self::set_sample_rate()], or 0 if it hasn't
+    /// been called yet.
+    sample_rate: f32,
+    /// The crossfade's duration in samples. Computed from `time_ms` and `sample_rate`.
+    length_samples: u32,
+    /// The number of samples that have been processed since the crossfade started. Equal to
+    /// `length_samples` once the crossfade has finished.
+    position: u32,
+    /// Whether [`start()`][Self::start()] has been called since the last reset. If this is `false`,
+    /// then the output is fully at A.
+    started: bool,
+}
+
+impl Crossfade {
+    /// Create a new crossfade that will take `time_ms` milliseconds once
+    /// [`set_sample_rate()`][Self::set_sample_rate()] has been called. Until then the crossfade
+    /// jumps to B instantly.
+    pub fn new(time_ms: f32) -> Self {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        Self {
+            time_ms,
+            sample_rate: 0.0,
+            length_samples: 0,
+            position: 0,
+            started: false,
+        }
+    }
+
+    /// Set the sample rate used to compute the crossfade's length. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This resets the crossfade
+    /// back to A.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_length();
+        self.reset();
+    }
+
+    /// Change the crossfade's duration. An active crossfade keeps its current position, so it
+    /// finishes right away if it has already progressed past the new duration.
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        self.time_ms = time_ms;
+        self.update_length();
+    }
+
+    fn update_length(&mut self) {
+        self.length_samples = (self.time_ms / 1000.0 * self.sample_rate).round() as u32;
+    }
+
+    /// Start crossfading from A to B. If a crossfade was already active, then it restarts from A.
+    pub fn start(&mut self) {
+        self.position = 0;
+        self.started = true;
+    }
+
+    /// Jump back to A without crossfading.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.started = false;
+    }
+
+    /// Whether a crossfade is currently active. In that case both A and B need to be processed.
+    pub fn is_active(&self) -> bool {
+        self.started && self.position < self.length_samples
+    }
+
+    /// Whether the crossfade has finished and the output is fully at B.
+    pub fn is_finished(&self) -> bool {
+        self.started && self.position >= self.length_samples
+    }
+
+    /// The crossfade's current progress in the range `[0, 1]`, where 0.0 means only A is heard and
+    /// 1.0 means only B is heard.
+    pub fn progress(&self) -> f32 {
+        if !self.started {
+            0.0
+        } else if self.position >= self.length_samples {
+            1.0
+        } else {
+            self.position as f32 / self.length_samples as f32
+        }
+    }
+
+    /// Get the gains for A and B for the next sample and advance the crossfade by one sample. The
+    /// gains follow an equal-power curve, so the squares of the two gains always sum to one. This
+    /// can be used instead of [`process()`][Self::process()] when the signals are not stored in
+    /// [`Buffer`]s.
+    #[inline]
+    pub fn next_gains(&mut self) -> (f32, f32) {
+        if self.started && self.position < self.length_samples {
+            self.position += 1;
+        }
+
+        let angle = self.progress() * f32::consts::FRAC_PI_2;
+        (angle.cos(), angle.sin())
+    }
+
+    /// Write the equal-power mix of `a` and `b` to `output`, advancing the crossfade by
+    /// `output.samples()` samples. All three buffers should have the same number of channels and
+    /// samples. `output` may contain the same data as `a` or `b`, since every sample is read before
+    /// it is written.
+    pub fn process(&mut self, a: &Buffer, b: &Buffer, output: &mut Buffer) {
+        nih_debug_assert_eq!(a.channels(), output.channels());
+        nih_debug_assert_eq!(b.channels(), output.channels());
+        nih_debug_assert_eq!(a.samples(), output.samples());
+        nih_debug_assert_eq!(b.samples(), output.samples());
+
+        let a = a.as_slice_immutable();
+        let b = b.as_slice_immutable();
+        let output = output.as_slice();
+        let num_samples = output
+            .iter()
+            .chain(a.iter())
+            .chain(b.iter())
+            .map(|channel| channel.len())
+            .min()
+            .unwrap_or(0);
+
+        for sample_idx in 0..num_samples {
+            let (a_gain, b_gain) = self.next_gains();
+            for ((output_channel, a_channel), b_channel) in output.iter_mut().zip(a).zip(b) {
+                output_channel[sample_idx] =
+                    (a_channel[sample_idx] * a_gain) + (b_channel[sample_idx] * b_gain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    /// Run `crossfade` over constant A and B buffers and return the output buffer's first channel.
+    fn process_constant(crossfade: &mut Crossfade, a: f32, b: f32, num_samples: usize) -> Vec<f32> {
+        let mut a_data = vec![vec![a; num_samples]; 2];
+        let mut b_data = vec![vec![b; num_samples]; 2];
+        let mut output_data = vec![vec![0.0; num_samples]; 2];
+
+        with_test_buffer(&mut a_data, |a_buffer| {
+            with_test_buffer(&mut b_data, |b_buffer| {
+                with_test_buffer(&mut output_data, |output_buffer| {
+                    crossfade.process(a_buffer, b_buffer, output_buffer)
+                })
+            })
+        });
+
+        output_data.swap_remove(0)
+    }
+
+    #[test]
+    fn stays_at_a_until_started() {
+        let mut crossfade = Crossfade::new(10.0);
+        crossfade.set_sample_rate(1000.0);
+
+        let output = process_constant(&mut crossfade, 1.0, 0.0, 20);
+        assert!(output.iter().all(|sample| *sample == 1.0));
+        assert!(!crossfade.is_active());
+    }
+
+    #[test]
+    fn reaches_b_after_duration() {
+        let mut crossfade = Crossfade::new(10.0);
+        crossfade.set_sample_rate(1000.0);
+        crossfade.start();
+
+        let output = process_constant(&mut crossfade, 0.0, 1.0, 10);
+        assert!(output.windows(2).all(|samples| samples[1] > samples[0]));
+        approx::assert_relative_eq!(output[9], 1.0);
+        assert!(crossfade.is_finished());
+
+        let output = process_constant(&mut crossfade, 0.0, 1.0, 10);
+        assert!(output.iter().all(|sample| *sample == 1.0));
+    }
+
+    #[test]
+    fn time_change_applies_immediately() {
+        let mut crossfade = Crossfade::new(10.0);
+        crossfade.set_sample_rate(1000.0);
+        crossfade.set_time_ms(20.0);
+        crossfade.start();
+
+        process_constant(&mut crossfade, 0.0, 1.0, 10);
+        approx::assert_relative_eq!(crossfade.progress(), 0.5);
+        assert!(crossfade.is_active());
+    }
+
+    #[test]
+    fn equal_power() {
+        let mut crossfade = Crossfade::new(100.0);
+        crossfade.set_sample_rate(1000.0);
+        crossfade.start();
+
+        for _ in 0..100 {
+            let (a_gain, b_gain) = crossfade.next_gains();
+            approx::assert_relative_eq!(a_gain * a_gain + b_gain * b_gain, 1.0, epsilon = 1e-6);
+        }
+    }
+}
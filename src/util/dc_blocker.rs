@@ -0,0 +1,145 @@
+//! A one-pole DC blocking filter.
+
+use std::f32::consts::TAU;
+
+/// The default cutoff frequency used by [`DcBlocker::new()`].
+const DEFAULT_CUTOFF_HZ: f32 = 10.0;
+
+/// A first order DC blocking high-pass filter with a separate filter state for every channel.
+/// Nonlinear processing like saturation and waveshaping often introduces a DC offset, which eats
+/// up headroom and causes clicks when the signal is switched on and off. This filter removes that
+/// offset while leaving the audible frequency range mostly untouched.
+///
+/// The filter computes `y[n] = x[n] - x[n - 1] + r * y[n - 1]`, where `r` is derived from the
+/// cutoff frequency. The cutoff defaults to 10 Hz, and it can be changed using
+/// [`set_cutoff()`][Self::set_cutoff()].
+#[derive(Debug, Clone)]
+pub struct DcBlocker {
+    /// The cutoff frequency in Hertz.
+    cutoff_hz: f32,
+    /// The current sample rate, set in [`set_sample_rate()`][Self::set_sample_rate()].
+    sample_rate: f32,
+    /// The feedback coefficient `r` computed from the cutoff frequency and the sample rate.
+    coefficient: f32,
+    /// The previous input sample for every channel.
+    previous_input: Vec<f32>,
+    /// The previous output sample for every channel.
+    previous_output: Vec<f32>,
+}
+
+impl DcBlocker {
+    /// Create a DC blocker for `num_channels` channels with the default 10 Hz cutoff frequency.
+    /// [`set_sample_rate()`][Self::set_sample_rate()] needs to be called before the filter can be
+    /// used.
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            cutoff_hz: DEFAULT_CUTOFF_HZ,
+            sample_rate: 0.0,
+            coefficient: 1.0,
+            previous_input: vec![0.0; num_channels],
+            previous_output: vec![0.0; num_channels],
+        }
+    }
+
+    /// Set the sample rate used to compute the filter coefficient. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets the
+    /// filter.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.sample_rate = sample_rate;
+        self.update_coefficient();
+        self.reset();
+    }
+
+    /// Change the cutoff frequency in Hertz. Values between 5 and 20 Hz work well for removing DC
+    /// without affecting the low end. This does not reset the filter's state.
+    pub fn set_cutoff(&mut self, cutoff_hz: f32) {
+        nih_debug_assert!(cutoff_hz > 0.0);
+
+        self.cutoff_hz = cutoff_hz;
+        self.update_coefficient();
+    }
+
+    /// The cutoff frequency in Hertz.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff_hz
+    }
+
+    /// Clear the filter state for all channels.
+    pub fn reset(&mut self) {
+        self.previous_input.fill(0.0);
+        self.previous_output.fill(0.0);
+    }
+
+    /// Filter a single sample for a channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel_idx` is out of bounds.
+    #[inline]
+    pub fn process(&mut self, channel_idx: usize, sample: f32) -> f32 {
+        let output = sample - self.previous_input[channel_idx]
+            + self.coefficient * self.previous_output[channel_idx];
+        self.previous_input[channel_idx] = sample;
+        self.previous_output[channel_idx] = output;
+
+        output
+    }
+
+    /// Filter a block of samples for a channel in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel_idx` is out of bounds.
+    pub fn process_block(&mut self, channel_idx: usize, samples: &mut [f32]) {
+        let mut previous_input = self.previous_input[channel_idx];
+        let mut previous_output = self.previous_output[channel_idx];
+        for sample in samples {
+            let input = *sample;
+            previous_output = input - previous_input + self.coefficient * previous_output;
+            previous_input = input;
+            *sample = previous_output;
+        }
+
+        self.previous_input[channel_idx] = previous_input;
+        self.previous_output[channel_idx] = previous_output;
+    }
+
+    fn update_coefficient(&mut self) {
+        if self.sample_rate > 0.0 {
+            self.coefficient = (-TAU * self.cutoff_hz / self.sample_rate).exp();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_dc_offset() {
+        let sample_rate = 44_100.0;
+        let mut dc_blocker = DcBlocker::new(2);
+        dc_blocker.set_sample_rate(sample_rate);
+
+        // A 440 Hz sine wave with a 0.5 DC offset. The per-sample and block variants should behave
+        // identically.
+        let signal: Vec<f32> = (0..sample_rate as usize * 2)
+            .map(|i| 0.5 + 0.25 * (TAU * 440.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let per_sample: Vec<f32> = signal
+            .iter()
+            .map(|&sample| dc_blocker.process(0, sample))
+            .collect();
+        let mut block = signal.clone();
+        dc_blocker.process_block(1, &mut block);
+        assert_eq!(per_sample, block);
+
+        // After a second the filter has settled, and the sine wave's mean is zero over whole
+        // periods
+        let last_second = &block[sample_rate as usize..];
+        let mean = last_second.iter().sum::<f32>() / last_second.len() as f32;
+        approx::assert_relative_eq!(mean, 0.0, epsilon = 1e-3);
+    }
+}
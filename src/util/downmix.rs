@@ -0,0 +1,153 @@
+//! Downmixing multichannel audio to mono for analysis.
+
+use crate::buffer::Buffer;
+
+/// The maximum number of channels [`DownmixCoeffs`] can hold coefficients for. This is enough for
+/// a 7.1 layout.
+pub const MAX_DOWNMIX_CHANNELS: usize = 8;
+
+/// The -3 dB gain used for the center and surround channels in the ITU-R BS.775 downmix.
+const MINUS_3_DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Per-channel gains for [`downmix_to_mono()`]. Use
+/// [`for_channel_count()`][Self::for_channel_count()] to get sensible defaults for a channel
+/// layout, or [`new()`][Self::new()] to use custom gains. This is a fixed size `Copy` type so it
+/// can be created on the audio thread without allocating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownmixCoeffs {
+    gains: [f32; MAX_DOWNMIX_CHANNELS],
+    num_channels: usize,
+}
+
+impl DownmixCoeffs {
+    /// Use custom per-channel gains. Only the first [`MAX_DOWNMIX_CHANNELS`] gains are used.
+    pub fn new(gains: &[f32]) -> Self {
+        nih_debug_assert!(gains.len() <= MAX_DOWNMIX_CHANNELS);
+
+        let num_channels = gains.len().min(MAX_DOWNMIX_CHANNELS);
+        let mut coeffs = Self {
+            gains: [0.0; MAX_DOWNMIX_CHANNELS],
+            num_channels,
+        };
+        coeffs.gains[..num_channels].copy_from_slice(&gains[..num_channels]);
+
+        coeffs
+    }
+
+    /// The default gains for a channel count:
+    ///
+    /// - Mono signals are passed through as is.
+    /// - Stereo signals are averaged.
+    /// - 5.1 signals in the `L, R, C, LFE, Ls, Rs` order use the ITU-R BS.775 stereo downmix, which
+    ///   adds the center and surround channels at -3 dB and drops the LFE channel, and the two
+    ///   resulting channels are then averaged like a stereo signal.
+    /// - All other channel counts are averaged.
+    pub fn for_channel_count(num_channels: usize) -> Self {
+        match num_channels {
+            1 => Self::new(&[1.0]),
+            2 => Self::new(&[0.5, 0.5]),
+            6 => Self::new(&[
+                0.5,
+                0.5,
+                MINUS_3_DB,
+                0.0,
+                0.5 * MINUS_3_DB,
+                0.5 * MINUS_3_DB,
+            ]),
+            n => {
+                let num_channels = n.min(MAX_DOWNMIX_CHANNELS);
+                let mut coeffs = Self {
+                    gains: [0.0; MAX_DOWNMIX_CHANNELS],
+                    num_channels,
+                };
+                coeffs.gains[..num_channels].fill((num_channels as f32).recip());
+
+                coeffs
+            }
+        }
+    }
+
+    /// The gains for every channel.
+    pub fn gains(&self) -> &[f32] {
+        &self.gains[..self.num_channels]
+    }
+}
+
+/// Downmix `buffer` to a single mono channel in `out` using the per-channel gains from `coeffs`.
+/// This is intended for metering and analysis, and it works the same regardless of the host.
+/// Channels without a coefficient are ignored.
+///
+/// # Panics
+///
+/// Panics if `out` is shorter than the buffer.
+pub fn downmix_to_mono(buffer: &Buffer, out: &mut [f32], coeffs: DownmixCoeffs) {
+    nih_debug_assert_eq!(buffer.channels(), coeffs.num_channels);
+
+    let out = &mut out[..buffer.samples()];
+    out.fill(0.0);
+    for (channel, gain) in buffer.as_slice_immutable().iter().zip(coeffs.gains()) {
+        for (out_sample, sample) in out.iter_mut().zip(channel.iter()) {
+            *out_sample += sample * gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    /// Downmix `data` using `coeffs` and return the result.
+    fn downmix(data: &mut [Vec<f32>], coeffs: DownmixCoeffs) -> Vec<f32> {
+        let mut out = vec![0.0; data[0].len()];
+        with_test_buffer(data, |buffer| downmix_to_mono(buffer, &mut out, coeffs));
+
+        out
+    }
+
+    #[test]
+    fn stereo_average() {
+        let coeffs = DownmixCoeffs::for_channel_count(2);
+        assert_eq!(coeffs.gains(), [0.5, 0.5]);
+
+        let mut data = [vec![1.0, 0.5, -0.25], vec![0.0, 0.5, 0.75]];
+        assert_eq!(downmix(&mut data, coeffs), [0.5, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn surround_51_coefficients() {
+        let coeffs = DownmixCoeffs::for_channel_count(6);
+        let gains = coeffs.gains();
+        assert_eq!(gains.len(), 6);
+        assert_eq!(gains[0], 0.5);
+        assert_eq!(gains[1], 0.5);
+        approx::assert_relative_eq!(gains[2], FRAC_1_SQRT_2);
+        assert_eq!(gains[3], 0.0);
+        approx::assert_relative_eq!(gains[4], FRAC_1_SQRT_2 / 2.0);
+        approx::assert_relative_eq!(gains[5], FRAC_1_SQRT_2 / 2.0);
+
+        // The left channel is halved like in the stereo downmix, the LFE channel is always
+        // dropped, and the center and surround channels end up at -3 dB
+        let mut data = [
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0],
+            vec![0.0, 0.0, 1.0],
+        ];
+        let out = downmix(&mut data, coeffs);
+        assert_eq!(out[0], 0.5);
+        approx::assert_relative_eq!(out[1], FRAC_1_SQRT_2);
+        approx::assert_relative_eq!(out[2], FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn other_channel_counts() {
+        assert_eq!(DownmixCoeffs::for_channel_count(1).gains(), [1.0]);
+        assert_eq!(DownmixCoeffs::for_channel_count(4).gains(), [0.25; 4]);
+        assert_eq!(DownmixCoeffs::new(&[1.0, 0.0]).gains(), [1.0, 0.0]);
+    }
+}
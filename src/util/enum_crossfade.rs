@@ -0,0 +1,212 @@
+//! A helper for crossfading between the old and new values of discrete parameters.
+
+/// Keeps track of the previous value of a discrete parameter, like an
+/// [`EnumParam`][crate::prelude::EnumParam] used to select a waveform or an algorithm, and produces
+/// a crossfade coefficient after that value changes. Switching between two algorithms instantly
+/// will usually cause a click, so instead the plugin can process both the previous and the current
+/// selection for a short while and mix them using the coefficient returned by
+/// [`next()`][Self::next()].
+///
+/// The coefficient is linear, goes from 0.0 (only the previous value) to 1.0 (only the current
+/// value), and it stays at 1.0 when no crossfade is active. If the value changes again while a
+/// crossfade is still active, then the crossfade restarts from the value that was being faded to at
+/// that point. The value that was being faded out is dropped entirely, so only two values ever need
+/// to be processed at the same time.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.waveform_crossfade.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`, for every sample
+/// let t = self.waveform_crossfade.next(self.params.waveform.value());
+/// let sample = if self.waveform_crossfade.is_crossfading() {
+///     let old = self.oscillator.render(self.waveform_crossfade.previous());
+///     let new = self.oscillator.render(self.waveform_crossfade.current());
+///     old + (new - old) * t
+/// } else {
+///     self.oscillator.render(self.waveform_crossfade.current())
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnumCrossfade<T> {
+    /// The value that's being faded out.
+    previous: T,
+    /// The value that's being faded in, or the current value if no crossfade is active.
+    current: T,
+
+    /// The crossfade's duration in milliseconds.
+    time_ms: f32,
+    /// The last sample rate passed to [`set_sample_rate()`][Self::set_sample_rate()], or 0 before
+    /// that.
+    sample_rate: f32,
+    /// The crossfade's duration in samples. Computed from `time_ms` and `sample_rate`.
+    length_samples: u32,
+    /// The number of samples left in the current crossfade. 0 if no crossfade is active.
+    samples_left: u32,
+}
+
+impl<T: Copy + PartialEq> EnumCrossfade<T> {
+    /// Create a new crossfade helper starting at `value`. The crossfade will take `time_ms`
+    /// milliseconds once [`set_sample_rate()`][Self::set_sample_rate()] has been called. Until
+    /// then value changes happen instantly.
+    pub fn new(value: T, time_ms: f32) -> Self {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        Self {
+            previous: value,
+            current: value,
+
+            time_ms,
+            sample_rate: 0.0,
+            length_samples: 0,
+            samples_left: 0,
+        }
+    }
+
+    /// Set the sample rate used to compute the crossfade's length. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Any active crossfade is
+    /// cancelled.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.length_samples = (self.time_ms / 1000.0 * sample_rate).round() as u32;
+        self.samples_left = 0;
+    }
+
+    /// Change the crossfade's duration. The next value change uses the new duration. An active
+    /// crossfade is shortened if fewer samples than its remaining length fit in the new duration.
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        self.time_ms = time_ms;
+        self.length_samples = (time_ms / 1000.0 * self.sample_rate).round() as u32;
+        self.samples_left = self.samples_left.min(self.length_samples);
+    }
+
+    /// Immediately jump to `value` without crossfading. Useful in
+    /// [`Plugin::reset()`][crate::prelude::Plugin::reset()].
+    pub fn reset(&mut self, value: T) {
+        self.previous = value;
+        self.current = value;
+        self.samples_left = 0;
+    }
+
+    /// Get the crossfade coefficient for the next sample, given the parameter's current value. If
+    /// `value` differs from the last value passed to this function, then a new crossfade is
+    /// started. The returned value is in the range `[0, 1]`, where 0.0 means that only
+    /// [`previous()`][Self::previous()] should be heard and 1.0 means that only
+    /// [`current()`][Self::current()] should be heard.
+    #[inline]
+    pub fn next(&mut self, value: T) -> f32 {
+        if value != self.current {
+            // Rapid successive changes restart the crossfade from whatever was being faded in
+            self.previous = self.current;
+            self.current = value;
+            self.samples_left = self.length_samples;
+        }
+
+        if self.samples_left > 0 {
+            self.samples_left -= 1;
+            1.0 - (self.samples_left as f32 / self.length_samples as f32)
+        } else {
+            self.previous = self.current;
+            1.0
+        }
+    }
+
+    /// The value that's being faded out. Equal to [`current()`][Self::current()] when no crossfade
+    /// is active.
+    #[inline]
+    pub fn previous(&self) -> T {
+        self.previous
+    }
+
+    /// The value that's being faded in, or the current value if no crossfade is active.
+    #[inline]
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// Whether a crossfade is currently active. In that case both [`previous()`][Self::previous()]
+    /// and [`current()`][Self::current()] should be processed.
+    #[inline]
+    pub fn is_crossfading(&self) -> bool {
+        self.samples_left > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Waveform {
+        Sine,
+        Saw,
+        Square,
+    }
+
+    #[test]
+    fn no_change_no_crossfade() {
+        let mut crossfade = EnumCrossfade::new(Waveform::Sine, 10.0);
+        crossfade.set_sample_rate(1000.0);
+
+        for _ in 0..20 {
+            assert_eq!(crossfade.next(Waveform::Sine), 1.0);
+            assert!(!crossfade.is_crossfading());
+        }
+    }
+
+    #[test]
+    fn smooth_crossfade() {
+        let mut crossfade = EnumCrossfade::new(Waveform::Sine, 10.0);
+        crossfade.set_sample_rate(1000.0);
+
+        let mut last_t = 0.0;
+        for _ in 0..10 {
+            let t = crossfade.next(Waveform::Saw);
+            assert!(t > last_t);
+            assert!(t - last_t <= 0.1 + 1e-6);
+            assert_eq!(crossfade.previous(), Waveform::Sine);
+            assert_eq!(crossfade.current(), Waveform::Saw);
+            last_t = t;
+        }
+
+        approx::assert_relative_eq!(last_t, 1.0);
+        assert!(!crossfade.is_crossfading());
+        assert_eq!(crossfade.next(Waveform::Saw), 1.0);
+        assert_eq!(crossfade.previous(), Waveform::Saw);
+    }
+
+    #[test]
+    fn time_change_applies_to_next_crossfade() {
+        let mut crossfade = EnumCrossfade::new(Waveform::Sine, 10.0);
+        crossfade.set_sample_rate(1000.0);
+        crossfade.set_time_ms(20.0);
+
+        let t = crossfade.next(Waveform::Saw);
+        approx::assert_relative_eq!(t, 0.05, epsilon = 1e-6);
+
+        // Shortening the crossfade while it's active must not push the coefficient below zero
+        crossfade.set_time_ms(5.0);
+        let t = crossfade.next(Waveform::Saw);
+        assert!((0.0..=1.0).contains(&t));
+    }
+
+    #[test]
+    fn restart_on_successive_changes() {
+        let mut crossfade = EnumCrossfade::new(Waveform::Sine, 10.0);
+        crossfade.set_sample_rate(1000.0);
+
+        for _ in 0..5 {
+            crossfade.next(Waveform::Saw);
+        }
+        assert!(crossfade.is_crossfading());
+
+        let t = crossfade.next(Waveform::Square);
+        approx::assert_relative_eq!(t, 0.1, epsilon = 1e-6);
+        assert_eq!(crossfade.previous(), Waveform::Saw);
+        assert_eq!(crossfade.current(), Waveform::Square);
+    }
+}
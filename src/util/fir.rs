@@ -0,0 +1,168 @@
+//! A direct form FIR filter.
+
+use crate::buffer::Buffer;
+
+/// A finite impulse response filter that convolves every channel with the same set of taps using
+/// direct form convolution, with a separate filter state for every channel. This is meant for
+/// short kernels with a handful of taps, like simple smoothing, interpolation, or correction
+/// filters. Longer kernels are much more efficiently applied using FFT convolution. The state is
+/// carried across blocks, so processing a signal in blocks produces the same output as
+/// processing it all at once.
+///
+/// The filter computes `y[n] = taps[0] * x[n] + taps[1] * x[n - 1] + ...`. It starts out with a
+/// single unity tap, which passes the signal through unchanged.
+///
+/// Changing the number of taps reallocates the filter's state, so
+/// [`set_taps()`][Self::set_taps()] should be called from
+/// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] when the kernel's length
+/// changes. Replacing the taps with a kernel of the same length never allocates and can be done
+/// while processing.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.fir.set_taps(&[0.25, 0.5, 0.25]);
+///
+/// // In `process()`
+/// self.fir.process(buffer);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FirFilter {
+    /// The filter's impulse response.
+    taps: Vec<f32>,
+    /// The last `taps.len()` input samples for every channel. Every sample is stored twice, at
+    /// `pos` and at `pos + taps.len()`, so the last `taps.len()` samples can always be read as a
+    /// single contiguous slice in chronological order.
+    histories: Vec<Vec<f32>>,
+    /// The index in the histories that will be written to next, in `[0, taps.len())`.
+    pos: usize,
+}
+
+impl FirFilter {
+    /// Create a filter for `num_channels` channels with a single unity tap.
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            taps: vec![1.0],
+            histories: vec![vec![0.0; 2]; num_channels],
+            pos: 0,
+        }
+    }
+
+    /// Replace the filter's taps. If the number of taps changes then the filter's state is
+    /// reallocated and cleared, otherwise the state is kept as is. An empty kernel is treated as a
+    /// single zero tap.
+    pub fn set_taps(&mut self, taps: &[f32]) {
+        nih_debug_assert!(!taps.is_empty());
+        let taps = if taps.is_empty() { &[0.0] } else { taps };
+
+        if taps.len() == self.taps.len() {
+            self.taps.copy_from_slice(taps);
+        } else {
+            self.taps = taps.to_vec();
+            for history in &mut self.histories {
+                *history = vec![0.0; taps.len() * 2];
+            }
+            self.pos = 0;
+        }
+    }
+
+    /// The filter's current taps.
+    pub fn taps(&self) -> &[f32] {
+        &self.taps
+    }
+
+    /// Clear the filter state for all channels.
+    pub fn reset(&mut self) {
+        for history in &mut self.histories {
+            history.fill(0.0);
+        }
+        self.pos = 0;
+    }
+
+    /// Filter `buffer` in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` has more channels than the filter was created for.
+    pub fn process(&mut self, buffer: &mut Buffer) {
+        assert!(buffer.channels() <= self.histories.len());
+
+        let num_taps = self.taps.len();
+        let num_samples = buffer.samples();
+        for (channel, history) in buffer.as_slice().iter_mut().zip(self.histories.iter_mut()) {
+            let mut pos = self.pos;
+            for sample in channel.iter_mut() {
+                history[pos] = *sample;
+                history[pos + num_taps] = *sample;
+                pos = (pos + 1) % num_taps;
+
+                // The window starts with the oldest sample, so it lines up with the reversed taps
+                let window = &history[pos..pos + num_taps];
+                *sample = window
+                    .iter()
+                    .zip(self.taps.iter().rev())
+                    .map(|(x, tap)| x * tap)
+                    .sum();
+            }
+        }
+
+        self.pos = (self.pos + num_samples) % num_taps;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{process_test_blocks, with_test_buffer};
+
+    /// Convolve `signal` with `taps` the textbook way.
+    fn reference_convolution(signal: &[f32], taps: &[f32]) -> Vec<f32> {
+        (0..signal.len())
+            .map(|n| {
+                taps.iter()
+                    .enumerate()
+                    .filter(|(k, _)| *k <= n)
+                    .map(|(k, tap)| tap * signal[n - k])
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_reference_convolution() {
+        let taps = [0.5, 0.3, 0.2, -0.1, 0.05];
+        let mut fir = FirFilter::new(2);
+        fir.set_taps(&taps);
+
+        let signals: Vec<Vec<f32>> = (0..2)
+            .map(|channel| {
+                (0..100)
+                    .map(|i| ((i * 7 + channel * 13) % 11) as f32 / 5.0 - 1.0)
+                    .collect()
+            })
+            .collect();
+
+        // Blocks shorter and longer than the kernel should both carry the state over correctly
+        let mut data = signals.clone();
+        let mut num_processed = 0;
+        process_test_blocks(&mut data, [3, 1, 17, 6, 2, 40, 31], |buffer| {
+            fir.process(buffer);
+            num_processed += buffer.samples();
+        });
+        assert_eq!(num_processed, 100);
+
+        for (output, signal) in data.iter().zip(&signals) {
+            let expected = reference_convolution(signal, &taps);
+            for (actual, expected) in output.iter().zip(expected) {
+                approx::assert_relative_eq!(*actual, expected, epsilon = 1e-6);
+            }
+        }
+
+        // Changing the number of taps starts over with a clean state
+        fir.set_taps(&[1.0, 1.0]);
+        let mut data = [vec![1.0, 2.0, 3.0]];
+        with_test_buffer(&mut data, |buffer| fir.process(buffer));
+        assert_eq!(data[0], [1.0, 3.0, 5.0]);
+    }
+}
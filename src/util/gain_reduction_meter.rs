@@ -0,0 +1,231 @@
+//! A gain reduction meter with release ballistics for dynamics processors.
+
+use atomic_float::AtomicF32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Smooths the gain reduction computed by a compressor or limiter in
+/// [`Plugin::process()`][crate::prelude::Plugin::process()] and publishes the result so the editor
+/// can display it. Increases in gain reduction are shown immediately, while decreases follow an
+/// exponential release curve. This mimics how the gain reduction meters on hardware compressors
+/// behave, and it keeps the meter readable even when the gain reduction changes every sample.
+///
+/// Gain reduction values are expressed in positive decibels, so 6.0 means that the signal is being
+/// attenuated by 6 dB. The audio thread updates the meter using [`next()`][Self::next()] or
+/// [`next_block()`][Self::next_block()], and the editor reads the smoothed value through a
+/// [`GainReductionReader`] obtained from [`reader()`][Self::reader()].
+///
+/// # Example
+///
+/// ```ignore
+/// // In `Plugin::editor()`
+/// let gain_reduction = self.gain_reduction_meter.reader();
+///
+/// // In `initialize()`
+/// self.gain_reduction_meter.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`, for every sample
+/// let gain_reduction_db = self.compressor.process(channel_samples);
+/// self.gain_reduction_meter.next(gain_reduction_db);
+///
+/// // In the editor
+/// let gain_reduction_db = gain_reduction.gain_reduction_db();
+/// ```
+#[derive(Debug)]
+pub struct GainReductionMeter {
+    /// The release time constant in milliseconds. After this much time the displayed gain reduction
+    /// has released 63% of the way towards the current gain reduction.
+    release_ms: f32,
+    /// The sample rate set through [`set_sample_rate()`][Self::set_sample_rate()]. 0 until then.
+    sample_rate: f32,
+    /// The per-sample release coefficient computed from `release_ms` and `sample_rate`.
+    release_coefficient: f32,
+    /// The smoothed gain reduction in decibels. This is kept on the audio thread so the atomic
+    /// value never needs to be read back.
+    current_db: f32,
+    /// The smoothed gain reduction shared with the editor.
+    shared_db: Arc<AtomicF32>,
+}
+
+/// Reads the smoothed gain reduction published by a [`GainReductionMeter`]. This can be cloned
+/// and moved into the editor.
+#[derive(Debug, Clone)]
+pub struct GainReductionReader {
+    shared_db: Arc<AtomicF32>,
+}
+
+impl GainReductionMeter {
+    /// Create a new gain reduction meter with a release time constant of `release_ms`
+    /// milliseconds. The release is instant until
+    /// [`set_sample_rate()`][Self::set_sample_rate()] has been called.
+    pub fn new(release_ms: f32) -> Self {
+        nih_debug_assert!(release_ms >= 0.0);
+
+        Self {
+            release_ms,
+            sample_rate: 0.0,
+            release_coefficient: 0.0,
+            current_db: 0.0,
+            shared_db: Arc::new(AtomicF32::new(0.0)),
+        }
+    }
+
+    /// Set the sample rate used to compute the release ballistic. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets the meter.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_release_coefficient();
+        self.reset();
+    }
+
+    /// Change the release time constant. Unlike [`set_sample_rate()`][Self::set_sample_rate()]
+    /// this does not reset the meter, so this can be called from
+    /// [`Plugin::process()`][crate::prelude::Plugin::process()] when a release parameter changes.
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        nih_debug_assert!(release_ms >= 0.0);
+
+        self.release_ms = release_ms;
+        self.update_release_coefficient();
+    }
+
+    fn update_release_coefficient(&mut self) {
+        let release_samples = self.release_ms / 1000.0 * self.sample_rate;
+        self.release_coefficient = if release_samples > 0.0 {
+            (-release_samples.recip()).exp()
+        } else {
+            0.0
+        };
+    }
+
+    /// Reset the meter back to 0 dB of gain reduction.
+    pub fn reset(&mut self) {
+        self.current_db = 0.0;
+        self.shared_db.store(0.0, Ordering::Relaxed);
+    }
+
+    /// Get a reader the editor can use to display the smoothed gain reduction.
+    pub fn reader(&self) -> GainReductionReader {
+        GainReductionReader {
+            shared_db: self.shared_db.clone(),
+        }
+    }
+
+    /// Update the meter with the gain reduction for the next sample, in positive decibels. Returns
+    /// the smoothed gain reduction that's shown on the meter.
+    #[inline]
+    pub fn next(&mut self, gain_reduction_db: f32) -> f32 {
+        self.advance(gain_reduction_db, self.release_coefficient)
+    }
+
+    /// Update the meter with the highest gain reduction for a block of `block_len` samples, in
+    /// positive decibels. This applies the release for the entire block at once, which is cheaper
+    /// than calling [`next()`][Self::next()] for every sample when the gain reduction is only
+    /// computed once per block. Returns the smoothed gain reduction that's shown on the meter.
+    pub fn next_block(&mut self, gain_reduction_db: f32, block_len: usize) -> f32 {
+        if block_len == 0 {
+            return self.current_db;
+        }
+
+        self.advance(
+            gain_reduction_db,
+            self.release_coefficient.powi(block_len as i32),
+        )
+    }
+
+    /// Apply the ballistic with an explicit release coefficient and publish the result.
+    #[inline]
+    fn advance(&mut self, gain_reduction_db: f32, release_coefficient: f32) -> f32 {
+        let gain_reduction_db = gain_reduction_db.max(0.0);
+        self.current_db = if gain_reduction_db >= self.current_db {
+            gain_reduction_db
+        } else {
+            gain_reduction_db + (self.current_db - gain_reduction_db) * release_coefficient
+        };
+
+        self.shared_db.store(self.current_db, Ordering::Relaxed);
+        self.current_db
+    }
+}
+
+impl GainReductionReader {
+    /// The smoothed gain reduction in positive decibels.
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.shared_db.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_release_ballistic() {
+        let mut meter = GainReductionMeter::new(10.0);
+        meter.set_sample_rate(1000.0);
+        let reader = meter.reader();
+
+        // Gain reduction is shown immediately
+        meter.next(3.0);
+        assert_eq!(reader.gain_reduction_db(), 3.0);
+        meter.next(12.0);
+        assert_eq!(reader.gain_reduction_db(), 12.0);
+
+        // After the release time constant, 63% of the difference has been released
+        for _ in 0..10 {
+            meter.next(0.0);
+        }
+        approx::assert_relative_eq!(
+            reader.gain_reduction_db(),
+            12.0 * (-1.0f32).exp(),
+            epsilon = 1e-4
+        );
+
+        // The meter releases towards the current gain reduction, and it jumps back up as soon as
+        // the gain reduction exceeds the displayed value again
+        for _ in 0..1000 {
+            meter.next(2.0);
+        }
+        approx::assert_relative_eq!(reader.gain_reduction_db(), 2.0, epsilon = 1e-4);
+        meter.next(6.0);
+        assert_eq!(reader.gain_reduction_db(), 6.0);
+    }
+
+    #[test]
+    fn release_change_keeps_meter_value() {
+        let mut meter = GainReductionMeter::new(10.0);
+        meter.set_sample_rate(1000.0);
+        meter.next(12.0);
+
+        meter.set_release_ms(20.0);
+        assert_eq!(meter.reader().gain_reduction_db(), 12.0);
+        for _ in 0..20 {
+            meter.next(0.0);
+        }
+        approx::assert_relative_eq!(
+            meter.reader().gain_reduction_db(),
+            12.0 * (-1.0f32).exp(),
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn block_release_matches_per_sample() {
+        let mut per_sample = GainReductionMeter::new(50.0);
+        let mut per_block = GainReductionMeter::new(50.0);
+        per_sample.set_sample_rate(44_100.0);
+        per_block.set_sample_rate(44_100.0);
+
+        per_sample.next(9.0);
+        per_block.next_block(9.0, 1);
+        for _ in 0..64 {
+            per_sample.next(1.0);
+        }
+        per_block.next_block(1.0, 64);
+
+        approx::assert_relative_eq!(
+            per_sample.reader().gain_reduction_db(),
+            per_block.reader().gain_reduction_db(),
+            epsilon = 1e-4
+        );
+    }
+}
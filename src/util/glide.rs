@@ -0,0 +1,319 @@
+//! Portamento for monophonic instruments.
+
+use crate::midi::sysex::SysExMessage;
+use crate::midi::NoteEvent;
+
+/// When [`Glide`] glides to a new note and when it retriggers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlideMode {
+    /// Glide to every new note, even when all previous notes have already been released. Every
+    /// note on event retriggers the instrument.
+    Always,
+    /// Only glide when a new note is played while another note is still held. Notes played after
+    /// all notes have been released jump to their pitch immediately and retrigger the instrument,
+    /// while overlapping notes glide without retriggering.
+    Legato,
+}
+
+/// Portamento for monophonic instruments. Pass all incoming note events to
+/// [`handle_event()`][Self::handle_event()], and call [`next()`][Self::next()] once per sample to
+/// get the current pitch as a fractional note number. This can be converted to a frequency using
+/// [`note_to_freq()`][super::note_to_freq()]. The pitch moves linearly towards the new note over
+/// the configured glide time, regardless of the distance between the two notes.
+///
+/// Held notes use last note priority. Releasing the most recent note while older notes are still
+/// held glides back to the most recent of those notes. Releasing the last held note keeps the pitch
+/// at that note so the instrument's release stage sounds at the right pitch.
+///
+/// The held notes are stored in a fixed capacity list so this never allocates after it has been
+/// created.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.glide.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`
+/// while let Some(event) = context.next_event() {
+///     if self.glide.handle_event(&event) {
+///         self.envelope.retrigger();
+///     } else if !self.glide.is_gate_open() {
+///         self.envelope.release();
+///     }
+/// }
+/// for sample in channel_samples {
+///     let freq = util::note_to_freq(self.glide.next(), util::DEFAULT_TUNING_HZ);
+///     *sample = self.oscillator.next(freq);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Glide {
+    mode: GlideMode,
+
+    /// The time it takes to glide to a new note in milliseconds.
+    time_ms: f32,
+    /// The sample rate passed to [`set_sample_rate()`][Self::set_sample_rate()]. 0 until then.
+    sample_rate: f32,
+    /// The time it takes to glide to a new note in samples. Computed from `time_ms` and
+    /// `sample_rate`.
+    length_samples: u32,
+
+    /// The notes that are currently held, in the order they were played. The last note is the
+    /// note the pitch is moving towards.
+    held_notes: Vec<u8>,
+    /// Whether a note has been played since the last reset. Until then there's no pitch to glide
+    /// from.
+    has_pitch: bool,
+
+    /// The current pitch as a fractional note number.
+    pitch: f32,
+    /// The note number the pitch is moving towards.
+    target: f32,
+    /// The amount the pitch changes by every sample while gliding.
+    step_size: f32,
+    /// The number of samples left until the target has been reached.
+    steps_left: u32,
+}
+
+impl Glide {
+    /// Create a glide helper that takes `time_ms` milliseconds to glide between notes once
+    /// [`set_sample_rate()`][Self::set_sample_rate()] has been called. Until then the pitch jumps
+    /// to new notes instantly. This allocates, so it should be done in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(mode: GlideMode, time_ms: f32) -> Self {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        Self {
+            mode,
+
+            time_ms,
+            sample_rate: 0.0,
+            length_samples: 0,
+
+            // There are only 128 MIDI notes
+            held_notes: Vec::with_capacity(128),
+            has_pitch: false,
+
+            pitch: 0.0,
+            target: 0.0,
+            step_size: 0.0,
+            steps_left: 0,
+        }
+    }
+
+    /// Set the sample rate used to compute the glide time. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Any active glide is
+    /// finished immediately.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.length_samples = (self.time_ms / 1000.0 * sample_rate).round() as u32;
+        self.pitch = self.target;
+        self.steps_left = 0;
+    }
+
+    /// Change the glide time. Like [`set_mode()`][Self::set_mode()] this takes effect for the next
+    /// note, so a glide that's already in progress keeps its speed.
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        self.time_ms = time_ms;
+        self.length_samples = (time_ms / 1000.0 * self.sample_rate).round() as u32;
+    }
+
+    /// Change when the pitch glides to new notes. Takes effect for the next note.
+    pub fn set_mode(&mut self, mode: GlideMode) {
+        self.mode = mode;
+    }
+
+    /// Forget all held notes and stop gliding. The next note will jump to its pitch. Useful in
+    /// [`Plugin::reset()`][crate::prelude::Plugin::reset()].
+    pub fn reset(&mut self) {
+        self.held_notes.clear();
+        self.has_pitch = false;
+        self.pitch = self.target;
+        self.steps_left = 0;
+    }
+
+    /// Update the held notes and the target pitch based on a note event. Only
+    /// [`NoteEvent::NoteOn`] and [`NoteEvent::NoteOff`] events are used, other events are ignored.
+    /// Returns `true` if the event started a new note that should retrigger the instrument's
+    /// envelopes. See [`GlideMode`] for when that happens.
+    pub fn handle_event<S: SysExMessage>(&mut self, event: &NoteEvent<S>) -> bool {
+        match *event {
+            NoteEvent::NoteOn { note, .. } => self.note_on(note),
+            NoteEvent::NoteOff { note, .. } => {
+                self.note_off(note);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Start playing a note. Returns `true` if the instrument should be retriggered.
+    pub fn note_on(&mut self, note: u8) -> bool {
+        let is_legato = !self.held_notes.is_empty();
+        self.held_notes.retain(|held_note| *held_note != note);
+        if self.held_notes.len() < self.held_notes.capacity() {
+            self.held_notes.push(note);
+        }
+
+        let should_glide = match self.mode {
+            GlideMode::Always => self.has_pitch,
+            GlideMode::Legato => is_legato,
+        };
+        self.set_target(note, should_glide);
+
+        match self.mode {
+            GlideMode::Always => true,
+            GlideMode::Legato => !is_legato,
+        }
+    }
+
+    /// Stop playing a note. If this was the most recent note and other notes are still held, then
+    /// the pitch glides back to the most recent of those notes.
+    pub fn note_off(&mut self, note: u8) {
+        let was_current = self.held_notes.last() == Some(&note);
+        self.held_notes.retain(|held_note| *held_note != note);
+
+        if was_current {
+            if let Some(&previous_note) = self.held_notes.last() {
+                self.set_target(previous_note, true);
+            }
+        }
+    }
+
+    /// Advance the glide by one sample and return the current pitch as a fractional note number.
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        if self.steps_left > 1 {
+            self.steps_left -= 1;
+            self.pitch += self.step_size;
+        } else {
+            // Snapping to the target avoids accumulating rounding errors
+            self.steps_left = 0;
+            self.pitch = self.target;
+        }
+
+        self.pitch
+    }
+
+    /// The current pitch as a fractional note number without advancing the glide.
+    #[inline]
+    pub fn current(&self) -> f32 {
+        self.pitch
+    }
+
+    /// The most recently played note that's still held, if any.
+    #[inline]
+    pub fn current_note(&self) -> Option<u8> {
+        self.held_notes.last().copied()
+    }
+
+    /// Whether any notes are currently held.
+    #[inline]
+    pub fn is_gate_open(&self) -> bool {
+        !self.held_notes.is_empty()
+    }
+
+    /// Whether the pitch is still moving towards the current note.
+    #[inline]
+    pub fn is_gliding(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    /// Move towards `note`, either by gliding or by jumping to it immediately.
+    fn set_target(&mut self, note: u8, glide: bool) {
+        self.target = note as f32;
+        self.has_pitch = true;
+        if glide && self.length_samples > 0 {
+            self.step_size = (self.target - self.pitch) / self.length_samples as f32;
+            self.steps_left = self.length_samples;
+        } else {
+            self.pitch = self.target;
+            self.steps_left = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_on(note: u8) -> NoteEvent<()> {
+        NoteEvent::NoteOn {
+            timing: 0,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 1.0,
+        }
+    }
+
+    fn note_off(note: u8) -> NoteEvent<()> {
+        NoteEvent::NoteOff {
+            timing: 0,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 0.0,
+        }
+    }
+
+    #[test]
+    fn legato_notes_glide() {
+        let mut glide = Glide::new(GlideMode::Legato, 4.0);
+        glide.set_sample_rate(1000.0);
+
+        assert!(glide.handle_event(&note_on(60)));
+        assert_eq!(glide.next(), 60.0);
+
+        // The second note overlaps the first note, so it glides without retriggering
+        assert!(!glide.handle_event(&note_on(64)));
+        assert_eq!(glide.next(), 61.0);
+        assert_eq!(glide.next(), 62.0);
+        assert_eq!(glide.next(), 63.0);
+        assert_eq!(glide.next(), 64.0);
+        assert!(!glide.is_gliding());
+
+        // Releasing the second note glides back to the first note
+        assert!(!glide.handle_event(&note_off(64)));
+        assert_eq!(glide.current_note(), Some(60));
+        assert_eq!(glide.next(), 63.0);
+    }
+
+    #[test]
+    fn separate_notes_jump() {
+        let mut glide = Glide::new(GlideMode::Legato, 4.0);
+        glide.set_sample_rate(1000.0);
+
+        assert!(glide.handle_event(&note_on(60)));
+        glide.handle_event(&note_off(60));
+        assert!(!glide.is_gate_open());
+        assert_eq!(glide.next(), 60.0);
+
+        assert!(glide.handle_event(&note_on(67)));
+        assert_eq!(glide.next(), 67.0);
+        assert!(!glide.is_gliding());
+
+        // In the other mode the same notes glide and still retrigger
+        glide.handle_event(&note_off(67));
+        glide.set_mode(GlideMode::Always);
+        assert!(glide.handle_event(&note_on(63)));
+        assert_eq!(glide.next(), 66.0);
+        assert!(glide.is_gliding());
+    }
+
+    #[test]
+    fn time_change_applies_to_next_note() {
+        let mut glide = Glide::new(GlideMode::Legato, 4.0);
+        glide.set_sample_rate(1000.0);
+        glide.handle_event(&note_on(60));
+        glide.next();
+
+        glide.set_time_ms(2.0);
+        glide.handle_event(&note_on(64));
+        assert_eq!(glide.next(), 62.0);
+        assert_eq!(glide.next(), 64.0);
+    }
+}
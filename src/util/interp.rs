@@ -0,0 +1,81 @@
+//! Interpolation functions for things like fractional delay lines, wavetables, and envelopes. These
+//! functions don't branch, so they can be auto-vectorized when used in a loop.
+
+use std::f32::consts::PI;
+
+/// Linearly interpolate between `a` and `b`. `t = 0.0` returns `a`, and `t = 1.0` returns `b`.
+#[inline]
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Interpolate between `a` and `b` using a half cosine period. This is smoother than
+/// [`lerp()`] at the endpoints, since the slope is zero at `t = 0.0` and at `t = 1.0`.
+#[inline]
+pub fn cosine(a: f32, b: f32, t: f32) -> f32 {
+    let t = (1.0 - (t * PI).cos()) * 0.5;
+    lerp(a, b, t)
+}
+
+/// Cubic interpolation between `y1` and `y2`, using the surrounding points `y0` and `y3` to
+/// compute the curve's slope. `t = 0.0` returns `y1`, and `t = 1.0` returns `y2`.
+///
+/// <http://paulbourke.net/miscellaneous/interpolation/>
+#[inline]
+pub fn cubic(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let a0 = y3 - y2 - y0 + y1;
+    let a1 = y0 - y1 - a0;
+    let a2 = y2 - y0;
+    let a3 = y1;
+
+    ((a0 * t + a1) * t + a2) * t + a3
+}
+
+/// Catmull-Rom spline interpolation between `y1` and `y2`, using the surrounding points `y0` and
+/// `y3` to compute the tangents. Unlike [`cubic()`] the curve's slope is continuous across
+/// segments, which makes this a good default for interpolating sampled audio. `t = 0.0` returns
+/// `y1`, and `t = 1.0` returns `y2`.
+///
+/// <https://en.wikipedia.org/wiki/Cubic_Hermite_spline#Catmull%E2%80%93Rom_spline>
+#[inline]
+pub fn catmull_rom(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let a0 = -0.5 * y0 + 1.5 * y1 - 1.5 * y2 + 0.5 * y3;
+    let a1 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+    let a2 = -0.5 * y0 + 0.5 * y2;
+    let a3 = y1;
+
+    ((a0 * t + a1) * t + a2) * t + a3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [f32; 4] = [0.3, -0.5, 0.8, 0.1];
+
+    #[test]
+    fn endpoints() {
+        let [y0, y1, y2, y3] = POINTS;
+
+        assert_eq!(lerp(y1, y2, 0.0), y1);
+        approx::assert_relative_eq!(lerp(y1, y2, 1.0), y2);
+        assert_eq!(cosine(y1, y2, 0.0), y1);
+        approx::assert_relative_eq!(cosine(y1, y2, 1.0), y2);
+        assert_eq!(cubic(y0, y1, y2, y3, 0.0), y1);
+        approx::assert_relative_eq!(cubic(y0, y1, y2, y3, 1.0), y2);
+        assert_eq!(catmull_rom(y0, y1, y2, y3, 0.0), y1);
+        approx::assert_relative_eq!(catmull_rom(y0, y1, y2, y3, 1.0), y2);
+    }
+
+    #[test]
+    fn lerp_is_monotonic() {
+        let [_, y1, y2, _] = POINTS;
+
+        let mut previous = lerp(y1, y2, 0.0);
+        for i in 1..=100 {
+            let value = lerp(y1, y2, i as f32 / 100.0);
+            assert!(value > previous);
+            previous = value;
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! A brick-wall lookahead limiter.
+
+use std::collections::VecDeque;
+
+use super::compressor::time_constant_coefficient;
+use super::{db_to_gain, gain_to_db_fast, interp};
+use crate::buffer::Buffer;
+
+/// The positions between two samples where [`Limiter`] estimates the signal's true peak level.
+const TRUE_PEAK_POSITIONS: [f32; 3] = [0.25, 0.5, 0.75];
+
+/// A brick-wall limiter with lookahead. The input is delayed by the lookahead time so the limiter
+/// can start reducing the gain before a peak arrives. Unlike a [`Compressor`][super::Compressor]
+/// with an infinite ratio, the output's sample peaks never exceed the threshold, regardless of how
+/// loud the input is. The gain reduction ramps in linearly over the lookahead time and it recovers
+/// over the release time. All channels share the same gain reduction to keep the stereo image
+/// intact.
+///
+/// With true peak detection enabled, the limiter also estimates the peaks between samples that
+/// would appear after reconstructing the analog signal, and it reduces the gain for those as well.
+/// This adds one sample of latency.
+///
+/// The delay line is allocated when the sample rate or the lookahead time changes, so
+/// [`set_sample_rate()`][Self::set_sample_rate()] and
+/// [`set_lookahead_ms()`][Self::set_lookahead_ms()] should be called from
+/// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Processing never allocates.
+/// The limiter delays the signal by [`latency_samples()`][Self::latency_samples()] samples, which
+/// should be reported to the host using [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()]
+/// or [`Plugin::latency()`][crate::prelude::Plugin::latency()].
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.limiter.set_sample_rate(buffer_config.sample_rate);
+/// context.set_latency_samples(self.limiter.latency_samples());
+///
+/// // In `process()`
+/// self.limiter.set_threshold_db(self.params.ceiling.value());
+/// let gain_reduction_db = self.limiter.process(buffer);
+/// self.gain_reduction_meter.next_block(gain_reduction_db, buffer.samples());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    /// The threshold in decibels.
+    threshold_db: f32,
+    /// The release time in milliseconds.
+    release_ms: f32,
+    /// The lookahead time in milliseconds.
+    lookahead_ms: f32,
+    /// Whether peaks between samples are taken into account.
+    true_peak: bool,
+
+    /// The current sample rate, set in [`set_sample_rate()`][Self::set_sample_rate()].
+    sample_rate: f32,
+    /// `threshold_db` as a linear gain.
+    threshold_gain: f32,
+    /// The per-sample release coefficient computed from `release_ms` and the sample rate.
+    release_coefficient: f32,
+    /// The number of samples the input is delayed by. This is the lookahead time in samples, plus
+    /// one sample when true peak detection is enabled.
+    delay_samples: usize,
+    /// The number of samples the gain reduction needs to be held for to cover the delay.
+    hold_samples: usize,
+
+    /// A ring buffer for every channel containing the last `delay_samples + 1` input samples.
+    delay_lines: Vec<Vec<f32>>,
+    /// The index in the delay lines that will be written to next.
+    delay_pos: usize,
+    /// The last three input samples for every channel, used to estimate the true peak level.
+    true_peak_history: Vec<[f32; 3]>,
+    /// The gains needed to keep the last `hold_samples` samples below the threshold, stored as
+    /// `(sample_counter, gain)` pairs with increasing gains. The front contains the lowest gain
+    /// within the hold window.
+    hold_window: VecDeque<(u64, f32)>,
+    /// The number of samples processed since the last reset.
+    sample_counter: u64,
+    /// The held gain after applying the release.
+    release_gain: f32,
+    /// The last couple of released gains, which are averaged to ramp in the gain reduction over the
+    /// lookahead time.
+    ramp_window: Vec<f32>,
+    /// The index in `ramp_window` that will be overwritten next.
+    ramp_pos: usize,
+    /// The sum of all values in `ramp_window`.
+    ramp_sum: f64,
+}
+
+impl Limiter {
+    /// Create a limiter for up to `num_channels` channels. The limiter defaults to a 0 dB
+    /// threshold, 100 ms release, 5 ms lookahead, and true peak detection is disabled. The
+    /// lookahead and the release are instant until [`set_sample_rate()`][Self::set_sample_rate()]
+    /// has been called.
+    pub fn new(num_channels: usize) -> Self {
+        let mut limiter = Self {
+            threshold_db: 0.0,
+            release_ms: 100.0,
+            lookahead_ms: 5.0,
+            true_peak: false,
+
+            sample_rate: 0.0,
+            threshold_gain: 1.0,
+            release_coefficient: 0.0,
+            delay_samples: 0,
+            hold_samples: 1,
+
+            delay_lines: vec![Vec::new(); num_channels],
+            delay_pos: 0,
+            true_peak_history: vec![[0.0; 3]; num_channels],
+            hold_window: VecDeque::new(),
+            sample_counter: 0,
+            release_gain: 1.0,
+            ramp_window: Vec::new(),
+            ramp_pos: 0,
+            ramp_sum: 0.0,
+        };
+        limiter.update_lookahead();
+
+        limiter
+    }
+
+    /// Set the sample rate used to compute the lookahead and release times. This should be called
+    /// from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This allocates, it
+    /// resets the limiter, and it may change the latency.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.sample_rate = sample_rate;
+        self.release_coefficient = time_constant_coefficient(self.release_ms, self.sample_rate);
+        self.update_lookahead();
+    }
+
+    /// Set the threshold in decibels. The output never exceeds this level.
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db;
+        self.threshold_gain = db_to_gain(threshold_db);
+    }
+
+    /// Set the release time in milliseconds. After this much time the gain reduction has recovered
+    /// 63% of the way after a peak has passed.
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        nih_debug_assert!(release_ms >= 0.0);
+
+        self.release_ms = release_ms;
+        self.release_coefficient = time_constant_coefficient(self.release_ms, self.sample_rate);
+    }
+
+    /// Set the lookahead time in milliseconds. This changes the latency, and it allocates when the
+    /// lookahead time grows, so it should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets the
+    /// limiter.
+    pub fn set_lookahead_ms(&mut self, lookahead_ms: f32) {
+        nih_debug_assert!(lookahead_ms >= 0.0);
+
+        self.lookahead_ms = lookahead_ms.max(0.0);
+        self.update_lookahead();
+    }
+
+    /// Enable or disable true peak detection. This changes the latency by one sample, so it should
+    /// be called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also
+    /// resets the limiter.
+    pub fn set_true_peak(&mut self, true_peak: bool) {
+        if true_peak != self.true_peak {
+            self.true_peak = true_peak;
+            self.update_lookahead();
+        }
+    }
+
+    /// The number of samples the limiter delays the signal by. Report this to the host as the
+    /// plugin's latency.
+    pub fn latency_samples(&self) -> u32 {
+        self.delay_samples as u32
+    }
+
+    /// Clear the delay line and the gain reduction.
+    pub fn reset(&mut self) {
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.delay_pos = 0;
+        self.true_peak_history.fill([0.0; 3]);
+
+        self.hold_window.clear();
+        self.sample_counter = 0;
+        self.release_gain = 1.0;
+        self.ramp_window.fill(1.0);
+        self.ramp_pos = 0;
+        self.ramp_sum = self.ramp_window.len() as f64;
+    }
+
+    /// Limit `buffer` in place. Returns the highest gain reduction in decibels that was applied
+    /// during this block as a positive value, which can be passed directly to
+    /// [`GainReductionMeter::next_block()`][super::GainReductionMeter::next_block()].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` has more channels than the limiter was created for.
+    pub fn process(&mut self, buffer: &mut Buffer) -> f32 {
+        assert!(buffer.channels() <= self.delay_lines.len());
+
+        let num_samples = buffer.samples();
+        let output = buffer.as_slice();
+
+        let mut max_gain_reduction_db = 0.0f32;
+        for sample_idx in 0..num_samples {
+            let mut peak = 0.0f32;
+            for (channel, history) in output.iter().zip(self.true_peak_history.iter_mut()) {
+                peak = peak.max(detect_level(channel[sample_idx], history, self.true_peak));
+            }
+
+            let gain = self.next_gain(peak);
+            if gain < 1.0 {
+                max_gain_reduction_db = max_gain_reduction_db.max(-gain_to_db_fast(gain));
+            }
+
+            // The delay lines contain `delay_samples + 1` samples, so the oldest sample is
+            // `delay_samples` samples behind the one that was just written
+            let read_pos = (self.delay_pos + 1) % (self.delay_samples + 1);
+            for (channel, delay_line) in output.iter_mut().zip(self.delay_lines.iter_mut()) {
+                delay_line[self.delay_pos] = channel[sample_idx];
+
+                // The clamp only guards against rounding errors in the gain computation
+                channel[sample_idx] =
+                    (delay_line[read_pos] * gain).clamp(-self.threshold_gain, self.threshold_gain);
+            }
+            self.delay_pos = read_pos;
+        }
+
+        max_gain_reduction_db
+    }
+
+    /// Compute the gain for the delayed sample based on the detected level of the newest sample.
+    #[inline]
+    fn next_gain(&mut self, peak: f32) -> f32 {
+        let target_gain = if peak > self.threshold_gain {
+            self.threshold_gain / peak
+        } else {
+            1.0
+        };
+
+        // The lowest gain needed within the hold window. Gains that can never be the lowest gain
+        // again are removed so finding the minimum doesn't require a linear search.
+        while matches!(self.hold_window.back(), Some((_, gain)) if *gain >= target_gain) {
+            self.hold_window.pop_back();
+        }
+        self.hold_window
+            .push_back((self.sample_counter, target_gain));
+        let oldest_counter = (self.sample_counter + 1).saturating_sub(self.hold_samples as u64);
+        while matches!(self.hold_window.front(), Some((counter, _)) if *counter < oldest_counter) {
+            self.hold_window.pop_front();
+        }
+        self.sample_counter += 1;
+        let held_gain = self.hold_window.front().map_or(1.0, |(_, gain)| *gain);
+
+        // Gain reduction is applied instantly and released gradually. The released gain never
+        // exceeds the held gain.
+        self.release_gain = if held_gain < self.release_gain {
+            held_gain
+        } else {
+            held_gain + (self.release_coefficient * (self.release_gain - held_gain))
+        };
+
+        // Averaging the released gain over the ramp window turns the instant gain reduction into a
+        // linear ramp. Since every gain in the window is held for long enough, the average never
+        // exceeds the gain needed for the delayed sample.
+        self.ramp_sum += (self.release_gain - self.ramp_window[self.ramp_pos]) as f64;
+        self.ramp_window[self.ramp_pos] = self.release_gain;
+        self.ramp_pos = (self.ramp_pos + 1) % self.ramp_window.len();
+
+        (self.ramp_sum / self.ramp_window.len() as f64).min(1.0) as f32
+    }
+
+    /// Recompute the delay, hold, and ramp lengths after the sample rate, the lookahead time, or
+    /// the true peak setting changed, and reset the limiter.
+    fn update_lookahead(&mut self) {
+        let lookahead_samples = (self.lookahead_ms / 1000.0 * self.sample_rate).round() as usize;
+
+        // The gain for a sample needs to be low enough for the newest sample `delay_samples`
+        // samples later. With true peak detection the level for a sample is only known one sample
+        // later since it depends on the next sample, and the gain reduction for a peak between two
+        // samples needs to be applied to both of those samples.
+        let ramp_samples;
+        if self.true_peak {
+            let lookahead_samples = lookahead_samples.max(1);
+            self.delay_samples = lookahead_samples + 1;
+            self.hold_samples = lookahead_samples + 1;
+            ramp_samples = lookahead_samples;
+        } else {
+            self.delay_samples = lookahead_samples;
+            self.hold_samples = lookahead_samples + 1;
+            ramp_samples = lookahead_samples + 1;
+        }
+
+        for delay_line in &mut self.delay_lines {
+            delay_line.resize(self.delay_samples + 1, 0.0);
+        }
+        self.hold_window.reserve(self.hold_samples + 1);
+        self.ramp_window.resize(ramp_samples, 1.0);
+
+        self.reset();
+    }
+}
+
+/// The level of the newest sample `x` for the limiter's detector. With true peak detection this
+/// is instead the level of the sample before it, including the estimated peaks between that sample
+/// and its neighbors. `history` contains the last three samples before `x`, and it is updated to
+/// include `x`.
+#[inline]
+fn detect_level(x: f32, history: &mut [f32; 3], true_peak: bool) -> f32 {
+    if !true_peak {
+        return x.abs();
+    }
+
+    let [y0, y1, y2] = *history;
+    *history = [y1, y2, x];
+
+    // The peaks between `y1` and `y2` are assigned to `y2`. The peaks between `y2` and `x` are
+    // assigned to `x` in the next call, and the limiter's ramp covers both samples.
+    TRUE_PEAK_POSITIONS
+        .iter()
+        .map(|t| interp::catmull_rom(y0, y1, y2, x, *t).abs())
+        .fold(y2.abs(), f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_4, TAU};
+
+    use super::*;
+    use crate::buffer::process_test_blocks;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// Run `limiter` over `data` in blocks of `block_size` samples and return the highest gain
+    /// reduction reported by the limiter.
+    fn process(limiter: &mut Limiter, data: &mut [Vec<f32>], block_size: usize) -> f32 {
+        let mut max_gain_reduction_db = 0.0f32;
+        process_test_blocks(data, std::iter::repeat(block_size), |buffer| {
+            max_gain_reduction_db = max_gain_reduction_db.max(limiter.process(buffer));
+        });
+
+        max_gain_reduction_db
+    }
+
+    #[test]
+    fn output_never_exceeds_threshold() {
+        for true_peak in [false, true] {
+            let mut limiter = Limiter::new(2);
+            limiter.set_threshold_db(-6.0);
+            limiter.set_release_ms(20.0);
+            limiter.set_lookahead_ms(1.0);
+            limiter.set_true_peak(true_peak);
+            limiter.set_sample_rate(SAMPLE_RATE);
+
+            // Bursts of increasingly loud sines with sudden jumps in level, and a single sample
+            // spike on one channel
+            let mut data: Vec<Vec<f32>> = (0..2)
+                .map(|channel_idx| {
+                    (0..(SAMPLE_RATE as usize / 2))
+                        .map(|i| {
+                            let level = ((i / 1000) % 8) as f32 * 0.75;
+                            let frequency = 100.0 + channel_idx as f32 * 1234.0;
+                            let spike = if channel_idx == 1 && i == 12_345 {
+                                20.0
+                            } else {
+                                0.0
+                            };
+                            (TAU * frequency * i as f32 / SAMPLE_RATE).sin() * level + spike
+                        })
+                        .collect()
+                })
+                .collect();
+            let gain_reduction_db = process(&mut limiter, &mut data, 256);
+            assert!(gain_reduction_db > 20.0, "{gain_reduction_db}");
+
+            let threshold_gain = db_to_gain(-6.0);
+            for channel in &data {
+                for (sample_idx, sample) in channel.iter().enumerate() {
+                    assert!(
+                        sample.abs() <= threshold_gain,
+                        "True peak {true_peak}: {sample} at sample {sample_idx} exceeds the \
+                         threshold"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reported_latency() {
+        for (true_peak, expected_latency) in [(false, 48), (true, 49)] {
+            let mut limiter = Limiter::new(1);
+            limiter.set_lookahead_ms(1.0);
+            limiter.set_true_peak(true_peak);
+            limiter.set_sample_rate(SAMPLE_RATE);
+            assert_eq!(limiter.latency_samples(), expected_latency);
+
+            // A quiet impulse passes through the limiter untouched, but delayed by the latency
+            let mut data = vec![vec![0.0; 256]];
+            data[0][10] = 0.5;
+            assert_eq!(process(&mut limiter, &mut data, 64), 0.0);
+            for (sample_idx, sample) in data[0].iter().enumerate() {
+                let expected = if sample_idx == 10 + expected_latency as usize {
+                    0.5
+                } else {
+                    0.0
+                };
+                assert_eq!(
+                    *sample, expected,
+                    "True peak {true_peak} at sample {sample_idx}"
+                );
+            }
+        }
+
+        // Without lookahead, the limiter has no latency
+        let mut limiter = Limiter::new(1);
+        limiter.set_lookahead_ms(0.0);
+        limiter.set_sample_rate(SAMPLE_RATE);
+        assert_eq!(limiter.latency_samples(), 0);
+    }
+
+    #[test]
+    fn true_peak_detection() {
+        // A sine at a quarter of the sample rate with a 45 degree phase offset only hits 71% of its
+        // peak level at the sample positions
+        let make_data = || {
+            vec![(0..1024)
+                .map(|i| (FRAC_PI_4 + i as f32 * TAU / 4.0).sin())
+                .collect()]
+        };
+        let threshold_db = -2.0;
+
+        let mut limiter = Limiter::new(1);
+        limiter.set_threshold_db(threshold_db);
+        limiter.set_sample_rate(SAMPLE_RATE);
+        let mut data = make_data();
+        assert_eq!(process(&mut limiter, &mut data, 128), 0.0);
+
+        // With true peak detection the estimated peaks between the samples are brought down to the
+        // threshold, so the sample peaks end up well below it
+        limiter.set_true_peak(true);
+        let mut data = make_data();
+        let gain_reduction_db = process(&mut limiter, &mut data, 128);
+        assert!(gain_reduction_db > 0.5, "{gain_reduction_db}");
+        let settled_peak = data[0][512..]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(
+            settled_peak < db_to_gain(threshold_db) * 0.85,
+            "{settled_peak}"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! A modulatable fractional delay line for chorus and flanger effects.
+
+use super::interp;
+
+/// How [`ModDelay`] interpolates between samples when the delay time is not a whole number of
+/// samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModDelayInterpolation {
+    /// Linear interpolation using [`interp::lerp()`]. This is cheap, but it slightly dampens high
+    /// frequencies when the delay time falls between two samples.
+    Linear,
+    /// Catmull-Rom spline interpolation using [`interp::catmull_rom()`]. This preserves high
+    /// frequencies better, but it needs one more sample of headroom so the minimum delay is one
+    /// sample.
+    CatmullRom,
+}
+
+/// A delay line with a fractional delay time that can be changed every sample, as used in chorus,
+/// flanger, and vibrato effects. The delay time is usually modulated with an LFO, and because the
+/// read position is interpolated between samples, sweeping the delay time does not cause the
+/// clicks and zipper noise an integer delay line would.
+///
+/// This processes a single channel. The buffer is allocated when the delay line is created, so the
+/// maximum delay time can only be chosen at that point. Processing never allocates. Delay times
+/// outside of the supported range are clamped, so the read position never passes the write
+/// position or reads samples that have already been overwritten.
+#[derive(Debug, Clone)]
+pub struct ModDelay {
+    interpolation: ModDelayInterpolation,
+    /// The maximum delay time in samples.
+    max_delay_samples: usize,
+    /// The ring buffer. This has a couple extra samples of room for the interpolation.
+    buffer: Vec<f32>,
+    /// The index in `buffer` containing the most recently written sample.
+    write_pos: usize,
+}
+
+impl ModDelay {
+    /// Create a delay line that supports delay times of up to `max_delay_samples` samples. This
+    /// allocates, so it should be done in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(max_delay_samples: usize, interpolation: ModDelayInterpolation) -> Self {
+        nih_debug_assert!(max_delay_samples > 0);
+
+        let max_delay_samples = max_delay_samples.max(1);
+        Self {
+            interpolation,
+            max_delay_samples,
+            // The interpolation reads up to two samples past the maximum delay
+            buffer: vec![0.0; max_delay_samples + 3],
+            write_pos: 0,
+        }
+    }
+
+    /// The shortest supported delay time in samples. This depends on the interpolation method.
+    pub fn min_delay_samples(&self) -> f32 {
+        match self.interpolation {
+            ModDelayInterpolation::Linear => 0.0,
+            ModDelayInterpolation::CatmullRom => 1.0,
+        }
+    }
+
+    /// The longest supported delay time in samples.
+    pub fn max_delay_samples(&self) -> f32 {
+        self.max_delay_samples as f32
+    }
+
+    /// Clear the delay line.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
+
+    /// Write `sample` to the delay line and read the output `delay_samples` samples in the past.
+    /// The delay time may be fractional, and it is clamped to
+    /// `[min_delay_samples(), max_delay_samples()]`. With a delay of zero samples the input is
+    /// passed through as is.
+    #[inline]
+    pub fn process(&mut self, sample: f32, delay_samples: f32) -> f32 {
+        self.write_pos += 1;
+        if self.write_pos == self.buffer.len() {
+            self.write_pos = 0;
+        }
+        self.buffer[self.write_pos] = sample;
+
+        let delay_samples = delay_samples.clamp(self.min_delay_samples(), self.max_delay_samples());
+        let whole_delay = delay_samples as usize;
+        let t = delay_samples - whole_delay as f32;
+
+        // `y1` is the sample `whole_delay` samples in the past, and `y2` is the one before that
+        match self.interpolation {
+            ModDelayInterpolation::Linear => {
+                let y1 = self.read(whole_delay);
+                let y2 = self.read(whole_delay + 1);
+
+                interp::lerp(y1, y2, t)
+            }
+            ModDelayInterpolation::CatmullRom => {
+                let y0 = self.read(whole_delay - 1);
+                let y1 = self.read(whole_delay);
+                let y2 = self.read(whole_delay + 1);
+                let y3 = self.read(whole_delay + 2);
+
+                interp::catmull_rom(y0, y1, y2, y3, t)
+            }
+        }
+    }
+
+    /// Read the sample written `delay` samples before the most recent sample.
+    #[inline]
+    fn read(&self, delay: usize) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[(self.write_pos + len - delay) % len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn whole_sample_delays() {
+        for interpolation in [
+            ModDelayInterpolation::Linear,
+            ModDelayInterpolation::CatmullRom,
+        ] {
+            let mut delay = ModDelay::new(16, interpolation);
+            let output: Vec<f32> = (0..20)
+                .map(|i| delay.process(if i == 0 { 1.0 } else { 0.0 }, 10.0))
+                .collect();
+
+            for (sample_idx, sample) in output.iter().enumerate() {
+                let expected = if sample_idx == 10 { 1.0 } else { 0.0 };
+                assert_eq!(
+                    *sample, expected,
+                    "{interpolation:?} at sample {sample_idx}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lfo_modulation_is_continuous() {
+        for interpolation in [
+            ModDelayInterpolation::Linear,
+            ModDelayInterpolation::CatmullRom,
+        ] {
+            // A typical chorus sweeping a 200 Hz sine between 5 and 15 ms at 2 Hz
+            let mut delay = ModDelay::new((SAMPLE_RATE * 0.02) as usize, interpolation);
+            let input_increment = 200.0 / SAMPLE_RATE;
+            let lfo_increment = 2.0 / SAMPLE_RATE;
+
+            // The input's largest change between two samples, with some leeway for the change in
+            // pitch caused by the modulation
+            let max_step = TAU * input_increment * 1.1;
+            let mut previous_output = 0.0;
+            for i in 0..(SAMPLE_RATE as usize) {
+                let input = (TAU * input_increment * i as f32).sin();
+                let lfo = (TAU * lfo_increment * i as f32).sin();
+                let delay_samples = (0.01 + lfo * 0.005) * SAMPLE_RATE;
+
+                let output = delay.process(input, delay_samples);
+                assert!(
+                    (output - previous_output).abs() <= max_step,
+                    "{interpolation:?} jumped from {previous_output} to {output} at sample {i}"
+                );
+                previous_output = output;
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_clamped() {
+        let mut delay = ModDelay::new(4, ModDelayInterpolation::CatmullRom);
+        for i in 1..=10 {
+            // Delays past the maximum would read samples that have already been overwritten, and
+            // negative delays would read samples that have not been written yet
+            let longest = delay.process(i as f32, 100.0);
+            assert_eq!(longest, (i - 4).max(0) as f32);
+        }
+
+        assert_eq!(delay.process(11.0, -5.0), 10.0);
+    }
+}
@@ -0,0 +1,289 @@
+//! A smoother for morphing between a fixed number of discrete states.
+
+/// What [`MorphSmoother`] does at the ends of its range of states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MorphMode {
+    /// The position stays within `[0, num_states - 1]`. Morphing from the first to the last state
+    /// passes through every state in between.
+    Clamp,
+    /// The states form a loop where the last state is followed by the first state again, like the
+    /// frames of a looping wavetable. The position stays within `[0, num_states)`, and the smoother
+    /// always takes the shortest way around the loop.
+    Wrap,
+}
+
+/// Smoothly moves a position between `num_states` discrete states, for instance to morph between
+/// the frames of a wavetable or between a set of filter types. Every sample,
+/// [`next()`][Self::next()] returns the two neighboring states `(index_a, index_b)` around the
+/// current position and the fractional amount `frac` of `index_b` that should be mixed into
+/// `index_a`. The position moves linearly towards the target over the configured time, and the
+/// fraction can optionally be shaped with a [curve][Self::set_curve()], for instance to hold on to
+/// each state a bit longer before moving on to the next one.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.morph.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`
+/// self.morph.set_target(self.params.position.value());
+/// for sample in channel_samples {
+///     let (index_a, index_b, frac) = self.morph.next();
+///     let a = self.wavetable.render(index_a);
+///     let b = self.wavetable.render(index_b);
+///     *sample = a + (b - a) * frac;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MorphSmoother {
+    /// The number of states, at least one.
+    num_states: usize,
+    mode: MorphMode,
+    /// Shapes the fractional part of the position before it's returned from
+    /// [`next()`][Self::next()]. Should map 0.0 to 0.0 and 1.0 to 1.0.
+    curve: fn(f32) -> f32,
+
+    /// The time it takes to reach a new target in milliseconds.
+    time_ms: f32,
+    /// The sample rate passed to [`set_sample_rate()`][Self::set_sample_rate()]. 0 until then.
+    sample_rate: f32,
+    /// The time it takes to reach a new target in samples. Computed from `time_ms` and
+    /// `sample_rate`.
+    length_samples: u32,
+
+    /// The current position in `[0, num_states - 1]`, or in `[0, num_states)` when wrapping.
+    position: f32,
+    /// The position the smoother is moving towards.
+    target: f32,
+    /// The amount the position changes by every sample while smoothing.
+    step_size: f32,
+    /// The number of samples left until the target has been reached.
+    steps_left: u32,
+}
+
+impl MorphSmoother {
+    /// Create a smoother for `num_states` states starting at the first state. Moving to a new
+    /// target takes `time_ms` milliseconds once [`set_sample_rate()`][Self::set_sample_rate()] has
+    /// been called. Until then the position jumps to the target instantly.
+    pub fn new(num_states: usize, mode: MorphMode, time_ms: f32) -> Self {
+        nih_debug_assert!(num_states > 0);
+        nih_debug_assert!(time_ms >= 0.0);
+
+        Self {
+            num_states: num_states.max(1),
+            mode,
+            curve: |frac| frac,
+
+            time_ms,
+            sample_rate: 0.0,
+            length_samples: 0,
+
+            position: 0.0,
+            target: 0.0,
+            step_size: 0.0,
+            steps_left: 0,
+        }
+    }
+
+    /// Set the sample rate used to compute the smoothing time. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Any active smoothing is
+    /// finished immediately.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.length_samples = (self.time_ms / 1000.0 * sample_rate).round() as u32;
+        self.reset(self.target);
+    }
+
+    /// Change the smoothing time. The new time is used starting from the next target change, and a
+    /// morph that's already in progress finishes at its original speed.
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        nih_debug_assert!(time_ms >= 0.0);
+
+        self.time_ms = time_ms;
+        self.length_samples = (time_ms / 1000.0 * self.sample_rate).round() as u32;
+    }
+
+    /// Shape the fraction returned from [`next()`][Self::next()] with `curve`. The curve receives
+    /// the linear fraction between two neighboring states in `[0, 1)`, and it should map 0.0 to
+    /// 0.0 and 1.0 to 1.0 so the morph stays continuous when crossing a state. Defaults to a
+    /// linear curve.
+    pub fn set_curve(&mut self, curve: fn(f32) -> f32) {
+        self.curve = curve;
+    }
+
+    /// Immediately jump to `position` without smoothing. Useful in
+    /// [`Plugin::reset()`][crate::prelude::Plugin::reset()].
+    pub fn reset(&mut self, position: f32) {
+        self.position = self.limit(position);
+        self.target = self.position;
+        self.steps_left = 0;
+    }
+
+    /// Start moving towards `position`, which may be fractional. The position is clamped to
+    /// `[0, num_states - 1]`, or wrapped around to `[0, num_states)` when using
+    /// [`MorphMode::Wrap`]. This restarts the smoothing from the current position if the target
+    /// changed.
+    pub fn set_target(&mut self, position: f32) {
+        let target = self.limit(position);
+        if target == self.target {
+            return;
+        }
+
+        let mut distance = target - self.position;
+        if self.mode == MorphMode::Wrap {
+            let num_states = self.num_states as f32;
+            if distance > num_states / 2.0 {
+                distance -= num_states;
+            } else if distance < -num_states / 2.0 {
+                distance += num_states;
+            }
+        }
+
+        self.target = target;
+        if self.length_samples == 0 {
+            self.position = target;
+            self.steps_left = 0;
+        } else {
+            self.step_size = distance / self.length_samples as f32;
+            self.steps_left = self.length_samples;
+        }
+    }
+
+    /// Advance the position by one sample and return `(index_a, index_b, frac)`, where `index_a`
+    /// and `index_b` are the two neighboring states around the position and `frac` is the
+    /// amount of `index_b` that should be mixed in after applying the curve. `index_b` is
+    /// `index_a + 1`, except at the last state where it either stays at the last state or wraps
+    /// around to the first state depending on the [`MorphMode`].
+    #[inline]
+    pub fn next(&mut self) -> (usize, usize, f32) {
+        if self.steps_left > 1 {
+            self.steps_left -= 1;
+            self.position += self.step_size;
+            if self.mode == MorphMode::Wrap {
+                self.position = self.position.rem_euclid(self.num_states as f32);
+            }
+        } else {
+            // Snapping to the target avoids accumulating rounding errors
+            self.steps_left = 0;
+            self.position = self.target;
+        }
+
+        self.current()
+    }
+
+    /// Get the current neighboring states and fraction without advancing the smoother. See
+    /// [`next()`][Self::next()].
+    #[inline]
+    pub fn current(&self) -> (usize, usize, f32) {
+        let index_a = (self.position as usize).min(self.num_states - 1);
+        let index_b = match self.mode {
+            MorphMode::Clamp => (index_a + 1).min(self.num_states - 1),
+            MorphMode::Wrap => (index_a + 1) % self.num_states,
+        };
+        let frac = (self.position - index_a as f32).clamp(0.0, 1.0);
+
+        (index_a, index_b, (self.curve)(frac))
+    }
+
+    /// The current position without applying the curve.
+    #[inline]
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Whether the position is still moving towards the target.
+    #[inline]
+    pub fn is_smoothing(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    /// Clamp or wrap `position` to the range of states depending on the mode.
+    fn limit(&self, position: f32) -> f32 {
+        let num_states = self.num_states as f32;
+        match self.mode {
+            MorphMode::Clamp => position.clamp(0.0, num_states - 1.0),
+            MorphMode::Wrap => {
+                // `rem_euclid()` can round up to `num_states` for tiny negative values
+                let position = position.rem_euclid(num_states);
+                if position >= num_states {
+                    0.0
+                } else {
+                    position
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn morph_from_first_to_last_state() {
+        let mut morph = MorphSmoother::new(4, MorphMode::Clamp, 6.0);
+        morph.set_sample_rate(1000.0);
+        assert_eq!(morph.current(), (0, 1, 0.0));
+
+        morph.set_target(3.0);
+        let expected = [
+            (0, 1, 0.5),
+            (1, 2, 0.0),
+            (1, 2, 0.5),
+            (2, 3, 0.0),
+            (2, 3, 0.5),
+            (3, 3, 0.0),
+            (3, 3, 0.0),
+        ];
+        for (step, expected) in expected.into_iter().enumerate() {
+            assert_eq!(morph.next(), expected, "step {step}");
+        }
+        assert!(!morph.is_smoothing());
+
+        // Targets outside of the range are clamped
+        morph.set_target(10.0);
+        assert!(!morph.is_smoothing());
+        assert_eq!(morph.position(), 3.0);
+    }
+
+    #[test]
+    fn wrap_takes_shortest_path() {
+        let mut morph = MorphSmoother::new(4, MorphMode::Wrap, 2.0);
+        morph.set_sample_rate(1000.0);
+        morph.reset(3.0);
+
+        // Going from the last state to the first state wraps around instead of passing through
+        // the states in between
+        morph.set_target(4.0);
+        assert_eq!(morph.next(), (3, 0, 0.5));
+        assert_eq!(morph.next(), (0, 1, 0.0));
+
+        morph.set_target(-0.5);
+        assert_eq!(morph.next(), (3, 0, 0.75));
+        assert_eq!(morph.next(), (3, 0, 0.5));
+    }
+
+    #[test]
+    fn time_change_applies_to_next_target() {
+        let mut morph = MorphSmoother::new(2, MorphMode::Clamp, 2.0);
+        morph.set_sample_rate(1000.0);
+        morph.set_time_ms(4.0);
+
+        morph.set_target(1.0);
+        assert_eq!(morph.next(), (0, 1, 0.25));
+    }
+
+    #[test]
+    fn curve_shapes_fraction() {
+        let mut morph = MorphSmoother::new(2, MorphMode::Clamp, 4.0);
+        morph.set_sample_rate(1000.0);
+        morph.set_curve(|frac| frac * frac);
+
+        morph.set_target(1.0);
+        assert_eq!(morph.next(), (0, 1, 0.0625));
+        assert_eq!(morph.next(), (0, 1, 0.25));
+        assert_eq!(morph.next(), (0, 1, 0.5625));
+        assert_eq!(morph.next(), (1, 1, 0.0));
+    }
+}
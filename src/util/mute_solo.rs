@@ -0,0 +1,80 @@
+//! Mute and solo handling for mixer-style and multiband plugins.
+
+/// Whether a single band should be audible. Soloing a band overrides its mute button, and if any
+/// band is soloed then all bands that are not soloed are silenced.
+#[inline]
+pub fn is_enabled(muted: bool, soloed: bool, any_soloed: bool) -> bool {
+    if any_soloed {
+        soloed
+    } else {
+        !muted
+    }
+}
+
+/// Compute which bands should be audible from every band's `(muted, soloed)` state, and write the
+/// results to `enabled`. This uses the usual mixer semantics described in [`is_enabled()`]. If
+/// multiple bands are soloed, then all of those bands are audible. The iterator is traversed twice
+/// and it does not allocate, so this can be called from the audio thread.
+///
+/// ```
+/// # use nih_plug::prelude::*;
+/// # use nih_plug::util::mute_solo;
+/// # struct BandParams { mute: BoolParam, solo: BoolParam }
+/// # let bands: [BandParams; 0] = [];
+/// let mut enabled = [false; 0];
+/// mute_solo::enabled_bands(
+///     bands.iter().map(|band| (band.mute.value(), band.solo.value())),
+///     &mut enabled,
+/// );
+/// ```
+///
+/// If the number of bands doesn't match the length of `enabled`, then only the shortest of the
+/// two is processed and a debug assertion is triggered.
+pub fn enabled_bands(bands: impl Iterator<Item = (bool, bool)> + Clone, enabled: &mut [bool]) {
+    let any_soloed = bands.clone().any(|(_, soloed)| soloed);
+
+    let mut num_bands = 0;
+    for ((muted, soloed), enabled) in bands.zip(enabled.iter_mut()) {
+        *enabled = is_enabled(muted, soloed, any_soloed);
+        num_bands += 1;
+    }
+    nih_debug_assert_eq!(num_bands, enabled.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute(bands: &[(bool, bool)]) -> Vec<bool> {
+        let mut enabled = vec![false; bands.len()];
+        enabled_bands(bands.iter().copied(), &mut enabled);
+
+        enabled
+    }
+
+    #[test]
+    fn mute_only() {
+        assert_eq!(
+            compute(&[(false, false), (true, false), (false, false)]),
+            [true, false, true]
+        );
+        assert_eq!(compute(&[(true, false), (true, false)]), [false, false]);
+    }
+
+    #[test]
+    fn solo_overrides_mute() {
+        // The soloed band plays even though it's also muted
+        assert_eq!(
+            compute(&[(false, false), (true, true), (false, false)]),
+            [false, true, false]
+        );
+    }
+
+    #[test]
+    fn multiple_solos() {
+        assert_eq!(
+            compute(&[(false, true), (false, false), (true, true), (true, false)]),
+            [true, false, true, false]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Pan laws and a panner for per-sample pan automation.
+
+use std::f32::consts::FRAC_PI_4;
+
+use crate::buffer::Buffer;
+
+/// How the left and right channel gains change as a signal is panned. The pan laws differ in how
+/// much a centered signal is attenuated compared to a signal that's panned hard left or right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanLaw {
+    /// The gains change linearly, and they always add up to one. A centered signal is attenuated
+    /// by 6 dB, which causes a noticeable dip in loudness in the center for uncorrelated signals.
+    Linear,
+    /// The gains follow a quarter sine and cosine curve, so the squared gains always add up to
+    /// one. A centered signal is attenuated by 3 dB and the perceived loudness stays constant
+    /// while panning.
+    ConstantPower,
+    /// A compromise between [`Linear`][Self::Linear] and
+    /// [`ConstantPower`][Self::ConstantPower] that attenuates a centered signal by 4.5 dB.
+    Compromise,
+}
+
+impl PanLaw {
+    /// Compute the `(left, right)` gains for a pan position in `[-1, 1]`, where -1 is hard left, 0
+    /// is the center, and 1 is hard right. The position is clamped to that range.
+    #[inline]
+    pub fn gains(self, pan: f32) -> (f32, f32) {
+        let pan = pan.clamp(-1.0, 1.0);
+        let linear = || ((1.0 - pan) / 2.0, (1.0 + pan) / 2.0);
+        let constant_power = || {
+            let (sin, cos) = ((pan + 1.0) * FRAC_PI_4).sin_cos();
+            (cos, sin)
+        };
+
+        match self {
+            PanLaw::Linear => linear(),
+            PanLaw::ConstantPower => constant_power(),
+            PanLaw::Compromise => {
+                let (linear_left, linear_right) = linear();
+                let (power_left, power_right) = constant_power();
+                (
+                    (linear_left * power_left).sqrt(),
+                    (linear_right * power_right).sqrt(),
+                )
+            }
+        }
+    }
+}
+
+/// Pans a stereo signal using per-sample pan positions, for instance from an LFO for an auto-pan
+/// effect or from a smoothed parameter using
+/// [`Smoother::next_block_exact()`][crate::prelude::Smoother::next_block_exact()]. The left
+/// channel is multiplied by the pan law's left gain and the right channel by its right gain, so a
+/// mono source should be copied to both channels before panning it.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `process()`
+/// let pan = &mut self.pan_scratch[..buffer.samples()];
+/// for pan in pan.iter_mut() {
+///     *pan = self.lfo.next();
+/// }
+/// self.panner.process(buffer, pan);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Panner {
+    law: PanLaw,
+}
+
+impl Default for Panner {
+    fn default() -> Self {
+        Self::new(PanLaw::ConstantPower)
+    }
+}
+
+impl Panner {
+    /// Create a panner that uses `law` to compute the channel gains.
+    pub fn new(law: PanLaw) -> Self {
+        Self { law }
+    }
+
+    /// Change the pan law.
+    pub fn set_law(&mut self, law: PanLaw) {
+        self.law = law;
+    }
+
+    /// The pan law used to compute the channel gains.
+    pub fn law(&self) -> PanLaw {
+        self.law
+    }
+
+    /// Pan a single pair of left and right samples to `pan` in `[-1, 1]`, returning the new
+    /// `(left, right)` pair.
+    #[inline]
+    pub fn process_sample(&self, left: f32, right: f32, pan: f32) -> (f32, f32) {
+        let (left_gain, right_gain) = self.law.gains(pan);
+
+        (left * left_gain, right * right_gain)
+    }
+
+    /// Pan a stereo buffer in place. The sample at index `n` in both channels is panned to
+    /// `pan[n]`, which should lie in `[-1, 1]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` does not have exactly two channels, or if the length of `pan` does not
+    /// match the number of samples in the buffer.
+    pub fn process(&self, buffer: &mut Buffer, pan: &[f32]) {
+        assert_eq!(buffer.channels(), 2);
+        assert_eq!(
+            pan.len(),
+            buffer.samples(),
+            "The pan slice needs to contain a pan position for every sample in the buffer"
+        );
+
+        let (left, right) = buffer.as_slice().split_at_mut(1);
+        for ((left, right), &pan) in left[0].iter_mut().zip(right[0].iter_mut()).zip(pan) {
+            let (new_left, new_right) = self.process_sample(*left, *right, pan);
+            *left = new_left;
+            *right = new_right;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    /// Pan a constant signal of ones in both channels and return the processed channels, which
+    /// then contain the channel gains.
+    fn pan_ones(panner: &Panner, pan: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let mut data = [vec![1.0; pan.len()], vec![1.0; pan.len()]];
+        with_test_buffer(&mut data, |buffer| panner.process(buffer, pan));
+
+        let [left, right] = data;
+        (left, right)
+    }
+
+    #[test]
+    fn auto_pan_tracks_pan_law() {
+        // A single period of a triangle LFO going from hard left to hard right and back
+        let pan: Vec<f32> = (0..64)
+            .map(|i| 1.0 - (i as f32 / 16.0 - 2.0).abs())
+            .collect();
+        assert_eq!((pan[0], pan[16], pan[32], pan[48]), (-1.0, 0.0, 1.0, 0.0));
+
+        let (left, right) = pan_ones(&Panner::new(PanLaw::ConstantPower), &pan);
+        for ((left, right), pan) in left.iter().zip(&right).zip(&pan) {
+            approx::assert_relative_eq!(left * left + right * right, 1.0, epsilon = 1e-6);
+            assert_eq!((*left, *right), PanLaw::ConstantPower.gains(*pan));
+        }
+        approx::assert_relative_eq!(left[0], 1.0, epsilon = 1e-6);
+        approx::assert_relative_eq!(right[0], 0.0, epsilon = 1e-6);
+        approx::assert_relative_eq!(left[16], std::f32::consts::FRAC_1_SQRT_2, epsilon = 1e-6);
+        approx::assert_relative_eq!(right[32], 1.0, epsilon = 1e-6);
+
+        let (left, right) = pan_ones(&Panner::new(PanLaw::Linear), &pan);
+        for (left, right) in left.iter().zip(&right) {
+            approx::assert_relative_eq!(left + right, 1.0, epsilon = 1e-6);
+        }
+        assert_eq!((left[16], right[16]), (0.5, 0.5));
+
+        // The compromise law attenuates the center by 4.5 dB
+        let (left, right) = PanLaw::Compromise.gains(0.0);
+        approx::assert_relative_eq!(left, right, epsilon = 1e-6);
+        approx::assert_relative_eq!(crate::util::gain_to_db(left), -4.5, epsilon = 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "pan position for every sample")]
+    fn pan_slice_length_mismatch() {
+        let mut data = [vec![0.0; 4], vec![0.0; 4]];
+        with_test_buffer(&mut data, |buffer| {
+            Panner::default().process(buffer, &[0.0; 3])
+        });
+    }
+}
@@ -0,0 +1,194 @@
+//! Scripted parameter trajectories for tests and demo automation.
+
+/// How a [`ParameterRamp`] moves from the previous breakpoint's value to a breakpoint's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RampInterpolation {
+    /// Hold the previous breakpoint's value, and jump to the new value at the breakpoint's time.
+    Step,
+    /// Interpolate linearly between the two values.
+    Linear,
+    /// Interpolate exponentially between the two values, which sounds more natural for
+    /// frequencies and gain ratios. This falls back to linear interpolation if the two values
+    /// don't have the same sign or if either of them is zero.
+    Exponential,
+}
+
+/// A single point on a [`ParameterRamp`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+    /// The time at which the ramp reaches `value`, in samples.
+    pub time_samples: u32,
+    /// The ramp's value at `time_samples`.
+    pub value: f32,
+    /// How the ramp moves from the previous breakpoint's value to this value.
+    pub interpolation: RampInterpolation,
+}
+
+/// A scripted parameter trajectory consisting of a starting value and a list of breakpoints. This
+/// yields the interpolated value for every sample, which makes it useful for driving parameters in
+/// a deterministic way in tests, or for demo automation. The ramp holds the last breakpoint's value
+/// after it has passed all breakpoints.
+///
+/// # Example
+///
+/// ```ignore
+/// // Sweep a cutoff frequency up over one second and then drop it back down after two seconds
+/// let mut ramp = ParameterRamp::new(200.0)
+///     .with_breakpoint(48_000, 10_000.0, RampInterpolation::Exponential)
+///     .with_breakpoint(96_000, 200.0, RampInterpolation::Step);
+///
+/// for cutoff in ramp.by_ref().take(buffer.samples()) {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParameterRamp {
+    /// The ramp's value at sample 0, used until the first breakpoint.
+    initial_value: f32,
+    /// The breakpoints, sorted by time. Breakpoints with the same time are stored in the order
+    /// they were added in.
+    breakpoints: Vec<Breakpoint>,
+    /// The sample index of the next value yielded by the iterator.
+    position: u32,
+}
+
+impl ParameterRamp {
+    /// Create a new ramp that starts at `initial_value`.
+    pub fn new(initial_value: f32) -> Self {
+        Self {
+            initial_value,
+            breakpoints: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Add a breakpoint so the ramp reaches `value` at `time_samples`, using `interpolation` to get
+    /// there from the previous breakpoint. Breakpoints can be added in any order. If multiple
+    /// breakpoints have the same time, then the last one added determines the value at that time.
+    pub fn with_breakpoint(
+        mut self,
+        time_samples: u32,
+        value: f32,
+        interpolation: RampInterpolation,
+    ) -> Self {
+        let insert_idx = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.time_samples <= time_samples);
+        self.breakpoints.insert(
+            insert_idx,
+            Breakpoint {
+                time_samples,
+                value,
+                interpolation,
+            },
+        );
+
+        self
+    }
+
+    /// The ramp's breakpoints, sorted by time.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Get the ramp's value at a sample index. This does not affect the iterator.
+    pub fn value_at(&self, sample_idx: u32) -> f32 {
+        let next_idx = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.time_samples <= sample_idx);
+        let (previous_time, previous_value) = match next_idx.checked_sub(1) {
+            Some(previous_idx) => (
+                self.breakpoints[previous_idx].time_samples,
+                self.breakpoints[previous_idx].value,
+            ),
+            None => (0, self.initial_value),
+        };
+        let next = match self.breakpoints.get(next_idx) {
+            Some(next) => next,
+            None => return previous_value,
+        };
+
+        let t = (sample_idx - previous_time) as f32 / (next.time_samples - previous_time) as f32;
+        match next.interpolation {
+            RampInterpolation::Step => previous_value,
+            RampInterpolation::Exponential if previous_value * next.value > 0.0 => {
+                previous_value * (next.value / previous_value).powf(t)
+            }
+            RampInterpolation::Linear | RampInterpolation::Exponential => {
+                previous_value + (next.value - previous_value) * t
+            }
+        }
+    }
+
+    /// The sample index of the next value the iterator will yield.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Move the iterator back to the start of the ramp.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+
+    /// Fill `block` with the ramp's next values and advance the iterator by `block.len()`
+    /// samples.
+    pub fn next_block(&mut self, block: &mut [f32]) {
+        for value in block {
+            *value = self.value_at(self.position);
+            self.position = self.position.saturating_add(1);
+        }
+    }
+}
+
+impl Iterator for ParameterRamp {
+    type Item = f32;
+
+    /// Yield the ramp's value for the next sample. This never returns `None`, so use
+    /// [`Iterator::take()`] to limit the number of values.
+    fn next(&mut self) -> Option<f32> {
+        let value = self.value_at(self.position);
+        self.position = self.position.saturating_add(1);
+
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_breakpoints() {
+        let ramp = ParameterRamp::new(0.0)
+            .with_breakpoint(10, 1.0, RampInterpolation::Linear)
+            .with_breakpoint(30, 0.5, RampInterpolation::Step)
+            .with_breakpoint(40, 8.0, RampInterpolation::Exponential);
+
+        let values: Vec<f32> = ramp.clone().take(50).collect();
+        for breakpoint in ramp.breakpoints() {
+            assert_eq!(
+                values[breakpoint.time_samples as usize], breakpoint.value,
+                "{breakpoint:?}"
+            );
+        }
+
+        // Linear ramps pass through the midpoint, steps hold the previous value until the
+        // breakpoint, and exponential ramps pass through the geometric mean
+        assert_eq!(values[5], 0.5);
+        assert_eq!(values[29], 1.0);
+        approx::assert_relative_eq!(values[35], 2.0, epsilon = 1e-6);
+        assert_eq!(values[49], 8.0);
+    }
+
+    #[test]
+    fn breakpoints_out_of_order() {
+        let mut ramp = ParameterRamp::new(0.0)
+            .with_breakpoint(8, 0.0, RampInterpolation::Linear)
+            .with_breakpoint(4, 1.0, RampInterpolation::Linear);
+
+        let mut block = [0.0; 9];
+        ramp.next_block(&mut block);
+        assert_eq!(block, [0.0, 0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25, 0.0]);
+        assert_eq!(ramp.position(), 9);
+    }
+}
@@ -0,0 +1,209 @@
+//! A peak hold detector for level meters.
+
+use super::{db_to_gain, MINUS_INFINITY_GAIN};
+
+/// Tracks the peak level of a signal for the peak hold indicator on a level meter. When a sample
+/// exceeds the held value, that sample's absolute value is held for the configured hold time.
+/// After that, the held value decays at a constant rate in decibels per second until a new peak
+/// comes along. The hold time and the decay rate are configured independently, so a decay rate of
+/// 0 holds peaks forever and a hold time of 0 makes the indicator start decaying immediately.
+///
+/// This processes a single channel and it never allocates. The held value is a linear gain, so
+/// use [`gain_to_db()`][super::gain_to_db()] to display it in decibels.
+#[derive(Debug, Clone)]
+pub struct PeakHold {
+    /// How long a peak is held before it starts decaying, in milliseconds.
+    hold_ms: f32,
+    /// How fast the held value decays after the hold time has passed, in decibels per second.
+    decay_db_per_second: f32,
+    /// The sample rate passed to [`set_sample_rate()`][Self::set_sample_rate()], if it has been
+    /// called. Peaks are held forever until then.
+    sample_rate: Option<f32>,
+
+    /// The hold time in samples, computed from `hold_ms` and the sample rate.
+    hold_samples: usize,
+    /// The gain the held value is multiplied by for every sample while it is decaying, computed
+    /// from `decay_db_per_second` and the sample rate.
+    decay_coefficient: f32,
+
+    /// The current held value, as a linear gain.
+    held: f32,
+    /// The number of samples left before the held value starts decaying.
+    hold_remaining: usize,
+}
+
+impl PeakHold {
+    /// Create a peak hold detector that holds peaks for `hold_ms` milliseconds before decaying at
+    /// `decay_db_per_second` decibels per second. Peaks are held forever until
+    /// [`set_sample_rate()`][Self::set_sample_rate()] has been called.
+    pub fn new(hold_ms: f32, decay_db_per_second: f32) -> Self {
+        nih_debug_assert!(hold_ms >= 0.0);
+        nih_debug_assert!(decay_db_per_second >= 0.0);
+
+        Self {
+            hold_ms,
+            decay_db_per_second,
+            sample_rate: None,
+
+            hold_samples: usize::MAX,
+            decay_coefficient: 1.0,
+
+            held: 0.0,
+            hold_remaining: 0,
+        }
+    }
+
+    /// Set the sample rate used to compute the hold time and the decay rate. This should be called
+    /// from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets the
+    /// held value.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = Some(sample_rate);
+        self.update_hold_samples();
+        self.update_decay_coefficient();
+
+        self.reset();
+    }
+
+    /// Change the hold time without resetting the held value. A peak that is currently being held
+    /// starts decaying early if it has already been held for longer than the new hold time.
+    pub fn set_hold_ms(&mut self, hold_ms: f32) {
+        nih_debug_assert!(hold_ms >= 0.0);
+
+        let held_samples = self.hold_samples - self.hold_remaining;
+        self.hold_ms = hold_ms;
+        self.update_hold_samples();
+        self.hold_remaining = self.hold_samples.saturating_sub(held_samples);
+    }
+
+    /// Change the decay rate without resetting the held value. A value that is already decaying
+    /// continues from where it is at the new rate.
+    pub fn set_decay_db_per_second(&mut self, decay_db_per_second: f32) {
+        nih_debug_assert!(decay_db_per_second >= 0.0);
+
+        self.decay_db_per_second = decay_db_per_second;
+        self.update_decay_coefficient();
+    }
+
+    fn update_hold_samples(&mut self) {
+        if let Some(sample_rate) = self.sample_rate {
+            self.hold_samples = (self.hold_ms / 1000.0 * sample_rate).round() as usize;
+        }
+    }
+
+    fn update_decay_coefficient(&mut self) {
+        if let Some(sample_rate) = self.sample_rate {
+            self.decay_coefficient = db_to_gain(-self.decay_db_per_second / sample_rate);
+        }
+    }
+
+    /// Reset the held value back to silence.
+    pub fn reset(&mut self) {
+        self.held = 0.0;
+        self.hold_remaining = 0;
+    }
+
+    /// The current held value, as a linear gain.
+    pub fn value(&self) -> f32 {
+        self.held
+    }
+
+    /// Update the detector with the next sample and return the held value, as a linear gain.
+    #[inline]
+    pub fn push(&mut self, sample: f32) -> f32 {
+        let amplitude = sample.abs();
+        if amplitude >= self.held {
+            self.held = amplitude;
+            self.hold_remaining = self.hold_samples;
+        } else if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+        } else {
+            self.held *= self.decay_coefficient;
+            // This prevents the value from decaying into denormals
+            if self.held < MINUS_INFINITY_GAIN {
+                self.held = 0.0;
+            }
+        }
+
+        self.held
+    }
+
+    /// Update the detector with a block of samples and return the held value after the last
+    /// sample, as a linear gain.
+    pub fn push_block(&mut self, samples: &[f32]) -> f32 {
+        for sample in samples {
+            self.push(*sample);
+        }
+
+        self.held
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::gain_to_db;
+
+    #[test]
+    fn holds_then_decays() {
+        // 10 ms of hold time and a decay of 20 dB per second at a sample rate of 1 kHz
+        let mut peak_hold = PeakHold::new(10.0, 20.0);
+        peak_hold.set_sample_rate(1000.0);
+
+        peak_hold.push(0.5);
+        assert_eq!(peak_hold.push(-1.0), 1.0);
+
+        // Quieter samples don't affect the held value during the hold time
+        for _ in 0..10 {
+            assert_eq!(peak_hold.push(0.25), 1.0);
+        }
+
+        // After that it decays by 20 dB every second, or by 1 dB every 50 samples
+        let mut previous_db = 0.0;
+        for i in 1..=1000 {
+            let held_db = gain_to_db(peak_hold.push(0.0));
+            assert!(held_db < previous_db);
+            if i % 50 == 0 {
+                approx::assert_relative_eq!(held_db, -(i / 50) as f32, epsilon = 1e-3);
+            }
+            previous_db = held_db;
+        }
+
+        // A new peak restarts the hold time
+        assert_eq!(peak_hold.push(0.5), 0.5);
+        assert_eq!(peak_hold.push(0.0), 0.5);
+    }
+
+    #[test]
+    fn hold_and_decay_are_independent() {
+        let mut no_hold = PeakHold::new(0.0, 20.0);
+        no_hold.set_sample_rate(1000.0);
+        no_hold.push(1.0);
+        assert!(no_hold.push(0.0) < 1.0);
+
+        let mut no_decay = PeakHold::new(5.0, 0.0);
+        no_decay.set_sample_rate(1000.0);
+        no_decay.push(1.0);
+        assert_eq!(no_decay.push_block(&[0.0; 1000]), 1.0);
+
+        // The decay reaches silence eventually instead of producing denormals
+        let mut fast_decay = PeakHold::new(0.0, 1000.0);
+        fast_decay.set_sample_rate(1000.0);
+        fast_decay.push(1.0);
+        assert_eq!(fast_decay.push_block(&[0.0; 200]), 0.0);
+    }
+
+    #[test]
+    fn settings_apply_while_holding() {
+        let mut peak_hold = PeakHold::new(100.0, 0.0);
+        peak_hold.set_sample_rate(1000.0);
+        peak_hold.push(1.0);
+        peak_hold.push_block(&[0.0; 10]);
+
+        // The peak has already been held for longer than the new hold time, so it starts decaying
+        // right away at the new rate
+        peak_hold.set_hold_ms(5.0);
+        peak_hold.set_decay_db_per_second(1000.0);
+        peak_hold.push(0.0);
+        approx::assert_relative_eq!(gain_to_db(peak_hold.value()), -1.0, epsilon = 1e-3);
+    }
+}
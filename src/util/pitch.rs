@@ -0,0 +1,147 @@
+//! Conversions between note numbers and frequencies with a configurable reference tuning and
+//! optional microtuning.
+
+/// The MIDI note number for A4, the reference note for the tuning frequency.
+pub const A4_NOTE: f32 = 69.0;
+/// The standard reference tuning frequency for A4 in Hertz.
+pub const DEFAULT_TUNING_HZ: f32 = 440.0;
+
+/// Convert a note number to a frequency in twelve-tone equal temperament, where A4 (note 69) maps
+/// to `tuning_hz`. Middle C is note 60 = C4. Fractional note numbers are supported, so a cents
+/// offset can be applied by adding `cents / 100.0` to the note number.
+#[inline]
+pub fn note_to_freq(note: f32, tuning_hz: f32) -> f32 {
+    2.0f32.powf((note - A4_NOTE) / 12.0) * tuning_hz
+}
+
+/// The inverse of [`note_to_freq()`]. This returns a fractional note number. Round to a whole
+/// number, subtract that from the result, and multiply the fractional part by 100 to get the
+/// number of cents.
+#[inline]
+pub fn freq_to_note(freq: f32, tuning_hz: f32) -> f32 {
+    ((freq / tuning_hz).log2() * 12.0) + A4_NOTE
+}
+
+/// Convert an offset in cents to a frequency ratio. Multiply a frequency by this ratio to detune
+/// it.
+#[inline]
+pub fn cents_to_ratio(cents: f32) -> f32 {
+    2.0f32.powf(cents / 1200.0)
+}
+
+/// The inverse of [`cents_to_ratio()`].
+#[inline]
+pub fn ratio_to_cents(ratio: f32) -> f32 {
+    ratio.log2() * 1200.0
+}
+
+/// A frequency for each of the 128 MIDI notes, for microtuning. Starts out as twelve-tone equal
+/// temperament, and individual notes can be retuned afterwards, for instance when receiving MIDI
+/// Tuning Standard messages. Looking up a note's frequency is a simple table lookup, so this can be
+/// used on the audio thread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningTable {
+    frequencies: [f32; 128],
+}
+
+impl Default for TuningTable {
+    fn default() -> Self {
+        Self::equal_temperament(DEFAULT_TUNING_HZ)
+    }
+}
+
+impl TuningTable {
+    /// Create a tuning table for twelve-tone equal temperament with A4 tuned to `tuning_hz`.
+    pub fn equal_temperament(tuning_hz: f32) -> Self {
+        Self::from_octave_offsets(tuning_hz, [0.0; 12])
+    }
+
+    /// Create a tuning table from a cents offset for each of the twelve pitch classes, starting at
+    /// C. The same offsets are applied in every octave. This is how many hardware synthesizers
+    /// implement scale tunings like just intonation. A4 is tuned to `tuning_hz` plus A's offset.
+    pub fn from_octave_offsets(tuning_hz: f32, offsets_cents: [f32; 12]) -> Self {
+        let mut frequencies = [0.0; 128];
+        for (note, frequency) in frequencies.iter_mut().enumerate() {
+            let cents = offsets_cents[note % 12];
+            *frequency = note_to_freq(note as f32 + cents / 100.0, tuning_hz);
+        }
+
+        Self { frequencies }
+    }
+
+    /// Get a note's frequency in Hertz. Notes above 127 are clamped to 127.
+    #[inline]
+    pub fn freq(&self, note: u8) -> f32 {
+        self.frequencies[note.min(127) as usize]
+    }
+
+    /// Get a note's frequency in Hertz with a cents offset applied, for instance for pitch bend.
+    #[inline]
+    pub fn freq_with_cents(&self, note: u8, cents: f32) -> f32 {
+        self.freq(note) * cents_to_ratio(cents)
+    }
+
+    /// Retune a single note. Notes above 127 are ignored.
+    pub fn set_freq(&mut self, note: u8, freq: f32) {
+        nih_debug_assert!(freq > 0.0);
+
+        if let Some(frequency) = self.frequencies.get_mut(note as usize) {
+            *frequency = freq;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a4_is_reference() {
+        approx::assert_relative_eq!(note_to_freq(A4_NOTE, 440.0), 440.0);
+        approx::assert_relative_eq!(note_to_freq(A4_NOTE, 432.0), 432.0);
+        approx::assert_relative_eq!(freq_to_note(432.0, 432.0), A4_NOTE);
+        approx::assert_relative_eq!(TuningTable::equal_temperament(432.0).freq(69), 432.0);
+    }
+
+    #[test]
+    fn octaves_double() {
+        for tuning_hz in [432.0, 440.0, 442.0] {
+            for note in [21.0, 60.0, 69.0, 96.5] {
+                approx::assert_relative_eq!(
+                    note_to_freq(note + 12.0, tuning_hz),
+                    note_to_freq(note, tuning_hz) * 2.0,
+                    max_relative = 1e-5
+                );
+            }
+        }
+
+        let table = TuningTable::default();
+        approx::assert_relative_eq!(table.freq(81), table.freq(69) * 2.0, max_relative = 1e-5);
+        approx::assert_relative_eq!(table.freq(57), table.freq(69) / 2.0, max_relative = 1e-5);
+    }
+
+    #[test]
+    fn cents() {
+        approx::assert_relative_eq!(cents_to_ratio(1200.0), 2.0);
+        approx::assert_relative_eq!(ratio_to_cents(1.5), 701.955, epsilon = 1e-3);
+        approx::assert_relative_eq!(
+            TuningTable::default().freq_with_cents(69, 100.0),
+            note_to_freq(70.0, 440.0),
+            max_relative = 1e-5
+        );
+    }
+
+    #[test]
+    fn microtuning() {
+        // E is tuned 14 cents flat for a just major third above C
+        let mut offsets = [0.0; 12];
+        offsets[4] = -14.0;
+        let mut table = TuningTable::from_octave_offsets(440.0, offsets);
+        approx::assert_relative_eq!(table.freq(60), note_to_freq(60.0, 440.0));
+        approx::assert_relative_eq!(table.freq(64), note_to_freq(63.86, 440.0), epsilon = 1e-3);
+        approx::assert_relative_eq!(table.freq(76), table.freq(64) * 2.0, max_relative = 1e-5);
+
+        table.set_freq(60, 250.0);
+        assert_eq!(table.freq(60), 250.0);
+    }
+}
@@ -0,0 +1,420 @@
+//! A phase vocoder based pitch shifter.
+
+use std::f32::consts::{PI, TAU};
+
+use super::stft::StftHelper;
+use super::window;
+use crate::buffer::Buffer;
+
+/// The number of overlapping windows used by [`PitchShifter`]. Four times overlap with a Hann
+/// window is the minimum for a phase vocoder to not produce audible artifacts on most material.
+const OVERLAP_TIMES: usize = 4;
+
+/// The spectral envelope used for formant preservation is the magnitude spectrum smoothed over this
+/// many bins in each direction, for a 2048 sample window. This is scaled with the window size so
+/// the envelope's resolution in Hertz stays the same.
+const FORMANT_SMOOTHING_BINS_2048: usize = 16;
+
+/// Shifts the pitch of a signal without changing its duration using a phase vocoder. This is built
+/// on top of [`StftHelper`], and it introduces latency equal to the window size. Report
+/// [`latency_samples()`][Self::latency_samples()] to the host using
+/// [`InitContext::set_latency_samples()`][crate::prelude::InitContext::set_latency_samples()].
+///
+/// Every window's frequency bins are moved to the bins for their shifted frequencies, and the
+/// phases are advanced to match the new frequencies so the overlapping windows add up coherently.
+/// Larger windows result in better frequency resolution and fewer artifacts for tonal material at
+/// the cost of more latency and more smearing of transients. 2048 or 4096 samples works well for
+/// most material at 44.1 or 48 kHz.
+///
+/// Shifting the pitch also shifts the signal's formants, which makes voices sound unnatural. With
+/// [`set_preserve_formants()`][Self::set_preserve_formants()] the signal's spectral envelope is
+/// kept in place while the harmonics are shifted.
+pub struct PitchShifter {
+    stft: StftHelper,
+    fft: Fft,
+    /// A Hann window, applied both before the forward FFT and after the inverse FFT.
+    window_function: Vec<f32>,
+    /// Compensates for the FFT's scaling and the gain added by the overlapping windows.
+    gain_compensation: f32,
+    preserve_formants: bool,
+
+    /// The phase vocoder's per-channel phase state.
+    channel_states: Vec<ChannelState>,
+
+    /// Scratch buffers for the current window. These are preallocated so processing doesn't
+    /// allocate.
+    spectrum: Vec<Complex>,
+    magnitudes: Vec<f32>,
+    /// The analyzed frequency for every bin, in fractional bins.
+    frequencies: Vec<f32>,
+    shifted_magnitudes: Vec<f32>,
+    shifted_frequencies: Vec<f32>,
+    envelope: Vec<f32>,
+}
+
+/// The phase state for a single channel.
+#[derive(Debug, Clone)]
+struct ChannelState {
+    /// The phase for each bin in the previous analysis window.
+    last_phases: Vec<f32>,
+    /// The accumulated phase for each bin in the synthesized output.
+    phase_sums: Vec<f32>,
+}
+
+/// A complex number for the FFT.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+/// A simple in-place radix-2 complex FFT. This is not the fastest FFT out there, but it keeps
+/// NIH-plug free of FFT dependencies. Plugins doing heavier spectral processing should use a
+/// dedicated FFT library with [`StftHelper`] instead.
+struct Fft {
+    /// `exp(-2 * pi * i * k / size)` for `k` in `0..size / 2`.
+    twiddles: Vec<Complex>,
+    /// The bit reversed index for every index.
+    bit_reversed: Vec<usize>,
+}
+
+impl PitchShifter {
+    /// Create a pitch shifter for `num_channels` channels using windows of `window_size` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is zero or if `window_size` is not a power of two of at least 16
+    /// samples.
+    pub fn new(num_channels: usize, window_size: usize) -> Self {
+        assert!(window_size >= 16 && window_size.is_power_of_two());
+
+        let window_function = window::hann(window_size);
+        let hop_size = window_size / OVERLAP_TIMES;
+        let window_power: f32 = window_function.iter().map(|x| x * x).sum();
+        let num_bins = window_size / 2 + 1;
+
+        Self {
+            stft: StftHelper::new(num_channels, window_size, 0),
+            fft: Fft::new(window_size),
+            gain_compensation: (window_power / hop_size as f32 * window_size as f32).recip(),
+            window_function,
+            preserve_formants: false,
+
+            channel_states: vec![
+                ChannelState {
+                    last_phases: vec![0.0; num_bins],
+                    phase_sums: vec![0.0; num_bins],
+                };
+                num_channels
+            ],
+
+            spectrum: vec![Complex::default(); window_size],
+            magnitudes: vec![0.0; num_bins],
+            frequencies: vec![0.0; num_bins],
+            shifted_magnitudes: vec![0.0; num_bins],
+            shifted_frequencies: vec![0.0; num_bins],
+            envelope: vec![0.0; num_bins],
+        }
+    }
+
+    /// Keep the signal's spectral envelope in place when shifting the pitch, so voices and
+    /// instruments keep their character. Disabled by default.
+    pub fn set_preserve_formants(&mut self, preserve_formants: bool) {
+        self.preserve_formants = preserve_formants;
+    }
+
+    /// Whether formant preservation is enabled.
+    pub fn preserve_formants(&self) -> bool {
+        self.preserve_formants
+    }
+
+    /// The latency introduced by the pitch shifter, in samples.
+    pub fn latency_samples(&self) -> u32 {
+        self.stft.latency_samples()
+    }
+
+    /// Clear the pitch shifter's buffers and phase state. The next
+    /// [`latency_samples()`][Self::latency_samples()] samples will be silent after this.
+    pub fn reset(&mut self) {
+        self.stft.set_block_size(self.window_function.len());
+        for state in &mut self.channel_states {
+            state.last_phases.fill(0.0);
+            state.phase_sums.fill(0.0);
+        }
+    }
+
+    /// Shift the pitch of the audio in `buffer` by `semitones` semitones, in place. The pitch can
+    /// be changed from block to block. The ratio between the new and old frequencies is limited to
+    /// two octaves in either direction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` does not have the same number of channels as the pitch shifter was
+    /// created with.
+    pub fn process(&mut self, buffer: &mut Buffer, semitones: f32) {
+        let ratio = 2.0f32.powf(semitones / 12.0).clamp(0.25, 4.0);
+        let window_size = self.window_function.len();
+        let num_bins = window_size / 2 + 1;
+        // The phase a bin's center frequency advances by between two windows
+        let bin_phase_advance = TAU * (window_size / OVERLAP_TIMES) as f32 / window_size as f32;
+
+        self.stft
+            .process_overlap_add(buffer, OVERLAP_TIMES, |channel_idx, real_buffer| {
+                let state = &mut self.channel_states[channel_idx];
+
+                for ((bin, sample), window) in self
+                    .spectrum
+                    .iter_mut()
+                    .zip(real_buffer.iter())
+                    .zip(&self.window_function)
+                {
+                    *bin = Complex {
+                        re: sample * window,
+                        im: 0.0,
+                    };
+                }
+                self.fft.process(&mut self.spectrum, false);
+
+                // Analysis: compute each bin's magnitude and its true frequency from the phase
+                // difference with the previous window
+                for (k, bin) in self.spectrum[..num_bins].iter().enumerate() {
+                    let phase = bin.im.atan2(bin.re);
+                    let phase_delta =
+                        wrap_phase(phase - state.last_phases[k] - k as f32 * bin_phase_advance);
+                    state.last_phases[k] = phase;
+
+                    self.magnitudes[k] = (bin.re * bin.re + bin.im * bin.im).sqrt();
+                    self.frequencies[k] = k as f32 + phase_delta / bin_phase_advance;
+                }
+
+                if self.preserve_formants {
+                    let smoothing_bins = (FORMANT_SMOOTHING_BINS_2048 * window_size / 2048).max(1);
+                    compute_envelope(&self.magnitudes, &mut self.envelope, smoothing_bins);
+                    for (magnitude, envelope) in self.magnitudes.iter_mut().zip(&self.envelope) {
+                        *magnitude /= envelope.max(f32::EPSILON);
+                    }
+                }
+
+                // Move every bin to the bin for its shifted frequency
+                self.shifted_magnitudes.fill(0.0);
+                self.shifted_frequencies.fill(0.0);
+                for k in 0..num_bins {
+                    let target_bin = (k as f32 * ratio).round() as usize;
+                    if target_bin < num_bins {
+                        self.shifted_magnitudes[target_bin] += self.magnitudes[k];
+                        self.shifted_frequencies[target_bin] = self.frequencies[k] * ratio;
+                    }
+                }
+
+                if self.preserve_formants {
+                    for (magnitude, envelope) in
+                        self.shifted_magnitudes.iter_mut().zip(&self.envelope)
+                    {
+                        *magnitude *= envelope;
+                    }
+                }
+
+                // Synthesis: advance the phases according to the shifted frequencies and rebuild
+                // the full Hermitian symmetric spectrum for the inverse FFT
+                for k in 0..num_bins {
+                    state.phase_sums[k] = (state.phase_sums[k]
+                        + self.shifted_frequencies[k] * bin_phase_advance)
+                        .rem_euclid(TAU);
+
+                    let (sin, cos) = state.phase_sums[k].sin_cos();
+                    self.spectrum[k] = Complex {
+                        re: self.shifted_magnitudes[k] * cos,
+                        im: self.shifted_magnitudes[k] * sin,
+                    };
+                }
+                for k in 1..window_size / 2 {
+                    let bin = self.spectrum[k];
+                    self.spectrum[window_size - k] = Complex {
+                        re: bin.re,
+                        im: -bin.im,
+                    };
+                }
+                self.fft.process(&mut self.spectrum, true);
+
+                for ((sample, bin), window) in real_buffer
+                    .iter_mut()
+                    .zip(&self.spectrum)
+                    .zip(&self.window_function)
+                {
+                    *sample = bin.re * window * self.gain_compensation;
+                }
+            });
+    }
+}
+
+impl Fft {
+    /// Prepare an FFT for `size` samples. `size` must be a power of two.
+    fn new(size: usize) -> Self {
+        nih_debug_assert!(size.is_power_of_two());
+
+        let bits = size.trailing_zeros();
+        Self {
+            twiddles: (0..size / 2)
+                .map(|k| {
+                    let (sin, cos) = (-TAU * k as f32 / size as f32).sin_cos();
+                    Complex { re: cos, im: sin }
+                })
+                .collect(),
+            bit_reversed: (0..size)
+                .map(|i| {
+                    if bits == 0 {
+                        0
+                    } else {
+                        i.reverse_bits() >> (usize::BITS - bits)
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Compute the forward or inverse FFT of `data` in place. The inverse FFT is not normalized.
+    fn process(&self, data: &mut [Complex], inverse: bool) {
+        let size = data.len();
+        nih_debug_assert_eq!(size, self.bit_reversed.len());
+
+        for (i, &j) in self.bit_reversed.iter().enumerate() {
+            if j > i {
+                data.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= size {
+            let half_len = len / 2;
+            let twiddle_step = size / len;
+            for start in (0..size).step_by(len) {
+                for k in 0..half_len {
+                    let mut twiddle = self.twiddles[k * twiddle_step];
+                    if inverse {
+                        twiddle.im = -twiddle.im;
+                    }
+
+                    let a = data[start + k];
+                    let b = data[start + k + half_len];
+                    let b = Complex {
+                        re: b.re * twiddle.re - b.im * twiddle.im,
+                        im: b.re * twiddle.im + b.im * twiddle.re,
+                    };
+                    data[start + k] = Complex {
+                        re: a.re + b.re,
+                        im: a.im + b.im,
+                    };
+                    data[start + k + half_len] = Complex {
+                        re: a.re - b.re,
+                        im: a.im - b.im,
+                    };
+                }
+            }
+
+            len *= 2;
+        }
+    }
+}
+
+/// Wrap a phase to `[-pi, pi]`.
+fn wrap_phase(phase: f32) -> f32 {
+    (phase + PI).rem_euclid(TAU) - PI
+}
+
+/// Compute the spectral envelope by averaging the magnitudes over `smoothing_bins` bins in each
+/// direction.
+fn compute_envelope(magnitudes: &[f32], envelope: &mut [f32], smoothing_bins: usize) {
+    let num_bins = magnitudes.len();
+    let mut sum: f32 = magnitudes[..smoothing_bins.min(num_bins)].iter().sum();
+    for (k, envelope) in envelope.iter_mut().enumerate() {
+        // The window covers `k - smoothing_bins..=k + smoothing_bins`, clamped to the spectrum
+        if k + smoothing_bins < num_bins {
+            sum += magnitudes[k + smoothing_bins];
+        }
+        if k > smoothing_bins {
+            sum -= magnitudes[k - smoothing_bins - 1];
+        }
+
+        let window_start = k.saturating_sub(smoothing_bins);
+        let window_end = (k + smoothing_bins + 1).min(num_bins);
+        *envelope = sum.max(0.0) / (window_end - window_start) as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::process_test_blocks;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const WINDOW_SIZE: usize = 2048;
+
+    /// Run a sine wave at `frequency` through the pitch shifter and return the output.
+    fn shift_sine(frequency: f32, semitones: f32, preserve_formants: bool) -> Vec<f32> {
+        let mut data = [(0..SAMPLE_RATE as usize / 2)
+            .map(|i| (TAU * frequency * i as f32 / SAMPLE_RATE).sin() * 0.5)
+            .collect::<Vec<f32>>()];
+
+        let mut pitch_shifter = PitchShifter::new(1, WINDOW_SIZE);
+        pitch_shifter.set_preserve_formants(preserve_formants);
+        assert_eq!(pitch_shifter.latency_samples(), WINDOW_SIZE as u32);
+
+        // Process the signal in blocks like a host would
+        process_test_blocks(&mut data, std::iter::repeat(512), |buffer| {
+            pitch_shifter.process(buffer, semitones)
+        });
+
+        let [samples] = data;
+        samples
+    }
+
+    /// Estimate the frequency of a sine wave by counting its rising zero crossings.
+    fn estimate_frequency(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+
+        crossings as f32 / (samples.len() as f32 / SAMPLE_RATE)
+    }
+
+    #[test]
+    fn fft_roundtrip() {
+        let fft = Fft::new(16);
+        let input: Vec<Complex> = (0..16)
+            .map(|i| Complex {
+                re: (i as f32 * 0.7).sin(),
+                im: 0.0,
+            })
+            .collect();
+
+        let mut data = input.clone();
+        fft.process(&mut data, false);
+        fft.process(&mut data, true);
+        for (output, input) in data.iter().zip(&input) {
+            approx::assert_relative_eq!(output.re / 16.0, input.re, epsilon = 1e-5);
+            approx::assert_relative_eq!(output.im / 16.0, 0.0, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn octave_up_doubles_frequency() {
+        // This frequency lies exactly on a bin center
+        let frequency = 20.0 * SAMPLE_RATE / WINDOW_SIZE as f32;
+        let output = shift_sine(frequency, 12.0, false);
+
+        // Skip the latency and the first couple of windows while the phase vocoder settles
+        let steady_state = &output[WINDOW_SIZE * 3..];
+        let estimated_frequency = estimate_frequency(steady_state);
+        approx::assert_relative_eq!(
+            estimated_frequency,
+            frequency * 2.0,
+            epsilon = frequency * 0.02
+        );
+
+        // The level stays roughly the same
+        let rms =
+            (steady_state.iter().map(|x| x * x).sum::<f32>() / steady_state.len() as f32).sqrt();
+        assert!((0.2..0.5).contains(&rms), "RMS was {rms}");
+    }
+}
@@ -0,0 +1,215 @@
+//! Per-voice polyphonic modulation bookkeeping.
+
+use crate::midi::sysex::SysExMessage;
+use crate::midi::NoteEvent;
+use crate::params::{FloatParam, Param};
+
+/// Keeps track of the polyphonic modulation offsets the host sent for every voice. NIH-plug's
+/// wrappers pass [`NoteEvent::PolyModulation`] events straight to the plugin and don't store any
+/// per-voice state themselves, so a plugin that stores these offsets also needs to clear them when
+/// a voice ends. Otherwise a voice ID that's later reused by the host for a new note would inherit
+/// the old voice's modulation. Passing every incoming note event and every
+/// [`NoteEvent::VoiceTerminated`] event the plugin outputs to
+/// [`handle_event()`][Self::handle_event()] takes care of this:
+///
+/// - A `PolyModulation` event sets the offset for that voice and parameter.
+/// - A `NoteOn` event with a voice ID clears all offsets for that voice ID, since it always starts
+///   a new voice.
+/// - A `VoiceTerminated` event with a voice ID clears all offsets for that voice ID.
+///
+/// `NoteOff` events do not clear the offsets since the voice may still be releasing.
+///
+/// The offsets are stored in a fixed capacity list so this never allocates after it has been
+/// created. The capacity should be at least the plugin's maximum number of voices times the number
+/// of polyphonically modulatable parameters. Offsets that don't fit are dropped, and a debug
+/// assertion failure is triggered in that case.
+#[derive(Debug, Clone)]
+pub struct PolyModulationOffsets {
+    /// The `(voice_id, poly_modulation_id, normalized_offset)` triples for all modulated voices.
+    offsets: Vec<(i32, u32, f32)>,
+}
+
+impl PolyModulationOffsets {
+    /// Create an empty offset tracker that can store up to `capacity` voice and parameter
+    /// combinations. This allocates, so it should be done in
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            offsets: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Update the offsets based on a note event. See the struct's documentation for the events
+    /// that affect the stored offsets. Other events are ignored.
+    pub fn handle_event<S: SysExMessage>(&mut self, event: &NoteEvent<S>) {
+        match *event {
+            NoteEvent::PolyModulation {
+                voice_id,
+                poly_modulation_id,
+                normalized_offset,
+                ..
+            } => self.set_offset(voice_id, poly_modulation_id, normalized_offset),
+            NoteEvent::NoteOn {
+                voice_id: Some(voice_id),
+                ..
+            }
+            | NoteEvent::VoiceTerminated {
+                voice_id: Some(voice_id),
+                ..
+            } => self.clear_voice(voice_id),
+            _ => (),
+        }
+    }
+
+    /// Get the normalized modulation offset for a voice and a parameter's polyphonic modulation
+    /// ID. Returns 0.0 if the parameter is not modulated for that voice.
+    pub fn offset(&self, voice_id: i32, poly_modulation_id: u32) -> f32 {
+        self.offsets
+            .iter()
+            .find(|(id, mod_id, _)| *id == voice_id && *mod_id == poly_modulation_id)
+            .map(|(_, _, offset)| *offset)
+            .unwrap_or(0.0)
+    }
+
+    /// Get a voice's value for a polyphonically modulated parameter. `base` is the parameter's
+    /// smoothed plain value for the current sample, usually obtained through
+    /// `param.smoothed.next()`. The voice's modulation offset is applied on top of that without any
+    /// smoothing, since the host already sends smooth modulation. This keeps the base value's
+    /// smoothing separate from the per-voice offsets. Parameters without a polyphonic modulation ID
+    /// return `base` unchanged.
+    pub fn modulated_value(&self, param: &FloatParam, voice_id: i32, base: f32) -> f32 {
+        match param.poly_modulation_id() {
+            Some(poly_modulation_id) => {
+                param.apply_poly_modulation(base, self.offset(voice_id, poly_modulation_id))
+            }
+            None => base,
+        }
+    }
+
+    /// Set the normalized modulation offset for a voice and a parameter's polyphonic modulation ID.
+    pub fn set_offset(&mut self, voice_id: i32, poly_modulation_id: u32, normalized_offset: f32) {
+        if let Some((_, _, offset)) = self
+            .offsets
+            .iter_mut()
+            .find(|(id, mod_id, _)| *id == voice_id && *mod_id == poly_modulation_id)
+        {
+            *offset = normalized_offset;
+        } else if self.offsets.len() < self.offsets.capacity() {
+            self.offsets
+                .push((voice_id, poly_modulation_id, normalized_offset));
+        } else {
+            nih_debug_assert_failure!(
+                "Dropping polyphonic modulation for voice {}, the tracker is full",
+                voice_id
+            );
+        }
+    }
+
+    /// Remove all modulation offsets for a voice.
+    pub fn clear_voice(&mut self, voice_id: i32) {
+        self.offsets.retain(|(id, _, _)| *id != voice_id);
+    }
+
+    /// Remove all modulation offsets, for instance when the plugin gets reset.
+    pub fn reset(&mut self) {
+        self.offsets.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::range::FloatRange;
+    use crate::params::smoothing::SmoothingStyle;
+    use crate::params::ParamMut;
+
+    const GAIN_POLY_MOD_ID: u32 = 0;
+    const CUTOFF_POLY_MOD_ID: u32 = 1;
+
+    #[test]
+    fn reused_voice_id_has_no_stale_offset() {
+        let mut offsets = PolyModulationOffsets::new(16);
+        let events: [NoteEvent<()>; 3] = [
+            NoteEvent::NoteOn {
+                timing: 0,
+                voice_id: Some(7),
+                channel: 0,
+                note: 60,
+                velocity: 1.0,
+            },
+            NoteEvent::PolyModulation {
+                timing: 0,
+                voice_id: 7,
+                poly_modulation_id: GAIN_POLY_MOD_ID,
+                normalized_offset: 0.25,
+            },
+            NoteEvent::PolyModulation {
+                timing: 0,
+                voice_id: 8,
+                poly_modulation_id: CUTOFF_POLY_MOD_ID,
+                normalized_offset: -0.5,
+            },
+        ];
+        for event in &events {
+            offsets.handle_event(event);
+        }
+        assert_eq!(offsets.offset(7, GAIN_POLY_MOD_ID), 0.25);
+        assert_eq!(offsets.offset(7, CUTOFF_POLY_MOD_ID), 0.0);
+
+        // The plugin outputs this event when the voice has finished releasing
+        offsets.handle_event(&NoteEvent::<()>::VoiceTerminated {
+            timing: 0,
+            voice_id: Some(7),
+            channel: 0,
+            note: 60,
+        });
+        assert_eq!(offsets.offset(7, GAIN_POLY_MOD_ID), 0.0);
+
+        // Modulation sent before the voice ID was reused should also not leak into the new voice
+        offsets.set_offset(7, GAIN_POLY_MOD_ID, 0.75);
+        offsets.handle_event(&NoteEvent::<()>::NoteOn {
+            timing: 0,
+            voice_id: Some(7),
+            channel: 0,
+            note: 64,
+            velocity: 1.0,
+        });
+        assert_eq!(offsets.offset(7, GAIN_POLY_MOD_ID), 0.0);
+
+        // Other voices are not affected
+        assert_eq!(offsets.offset(8, CUTOFF_POLY_MOD_ID), -0.5);
+    }
+
+    #[test]
+    fn base_smooths_while_offset_is_instant() {
+        let param = FloatParam::new("Cutoff", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+            .with_smoother(SmoothingStyle::Linear(10.0))
+            .with_poly_modulation_id(CUTOFF_POLY_MOD_ID);
+        param.update_smoother(1000.0, true);
+
+        let mut offsets = PolyModulationOffsets::new(16);
+        offsets.set_offset(3, CUTOFF_POLY_MOD_ID, 0.25);
+
+        // The base value takes ten samples to reach its new value
+        param.set_plain_value(0.5);
+        param.update_smoother(1000.0, false);
+        let base = param.smoothed.next();
+        approx::assert_relative_eq!(base, 0.05);
+        approx::assert_relative_eq!(offsets.modulated_value(&param, 3, base), 0.3);
+        approx::assert_relative_eq!(offsets.modulated_value(&param, 4, base), base);
+
+        // The offset on the other hand is applied immediately without resetting the smoother
+        offsets.set_offset(3, CUTOFF_POLY_MOD_ID, 0.5);
+        let base = param.smoothed.next();
+        approx::assert_relative_eq!(base, 0.1);
+        approx::assert_relative_eq!(offsets.modulated_value(&param, 3, base), 0.6);
+
+        // Modulation is clamped to the parameter's range
+        offsets.set_offset(3, CUTOFF_POLY_MOD_ID, 2.0);
+        approx::assert_relative_eq!(
+            offsets.modulated_value(&param, 3, param.smoothed.next()),
+            1.0
+        );
+        assert_eq!(param.value(), 0.5);
+    }
+}
@@ -0,0 +1,139 @@
+//! A sliding window RMS level detector.
+
+/// Computes the RMS level over a sliding window of the last `window_len` samples, for instance for
+/// the detector in a gate or an expander. A running sum of squares is kept so every sample only
+/// needs a constant amount of work regardless of the window's length. The sum is accumulated in
+/// double precision to prevent rounding errors from building up over time.
+///
+/// This processes a single channel. The window is allocated when the buffer is created or when its
+/// length changes, so [`set_window_len()`][Self::set_window_len()] should only be called from
+/// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. Processing never allocates.
+#[derive(Debug, Clone)]
+pub struct RmsBuffer {
+    /// The squared samples in the window. Its length is the window length.
+    squares: Vec<f32>,
+    /// The index in `squares` that will be overwritten next.
+    pos: usize,
+    /// The sum of all values in `squares`.
+    sum: f64,
+}
+
+impl RmsBuffer {
+    /// Create an RMS buffer with a window of `window_len` samples. This allocates, so it should be
+    /// done in [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] or earlier.
+    pub fn new(window_len: usize) -> Self {
+        let mut buffer = Self {
+            squares: Vec::new(),
+            pos: 0,
+            sum: 0.0,
+        };
+        buffer.set_window_len(window_len);
+
+        buffer
+    }
+
+    /// The number of samples in a window with a duration of `window_ms` milliseconds at
+    /// `sample_rate`. This is always at least one sample.
+    pub fn window_len_for(sample_rate: f32, window_ms: f32) -> usize {
+        ((sample_rate * window_ms / 1000.0).round() as usize).max(1)
+    }
+
+    /// The window length in samples.
+    pub fn window_len(&self) -> usize {
+        self.squares.len()
+    }
+
+    /// Change the window length. This clears the window and it allocates when the window grows, so
+    /// it should be called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()].
+    pub fn set_window_len(&mut self, window_len: usize) {
+        nih_debug_assert!(window_len > 0);
+
+        self.squares.resize(window_len.max(1), 0.0);
+        self.reset();
+    }
+
+    /// Clear the window.
+    pub fn reset(&mut self) {
+        self.squares.fill(0.0);
+        self.pos = 0;
+        self.sum = 0.0;
+    }
+
+    /// The RMS level over the current window. Before the window has been filled, the missing
+    /// samples count as silence.
+    pub fn rms(&self) -> f32 {
+        (self.sum / self.squares.len() as f64).sqrt() as f32
+    }
+
+    /// Add a sample to the window, dropping the oldest sample, and return the new RMS level.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let square = sample * sample;
+        self.sum += square as f64 - self.squares[self.pos] as f64;
+        // Rounding errors could otherwise cause a tiny negative sum after a loud passage
+        self.sum = self.sum.max(0.0);
+        self.squares[self.pos] = square;
+        self.pos += 1;
+        if self.pos == self.squares.len() {
+            self.pos = 0;
+        }
+
+        self.rms()
+    }
+
+    /// Add a block of samples to the window and return the RMS level after the last sample.
+    pub fn process_block(&mut self, samples: &[f32]) -> f32 {
+        for sample in samples {
+            self.process(*sample);
+        }
+
+        self.rms()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compute the RMS over the last `window_len` samples of `samples`, with samples before the
+    /// start counting as silence.
+    fn brute_force_rms(samples: &[f32], window_len: usize) -> f32 {
+        let window = &samples[samples.len().saturating_sub(window_len)..];
+        let sum: f64 = window.iter().map(|sample| (sample * sample) as f64).sum();
+
+        (sum / window_len as f64).sqrt() as f32
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let window_len = 37;
+        let mut rms_buffer = RmsBuffer::new(window_len);
+
+        // A decaying sine with a couple of loud spikes so the window sees large changes
+        let samples: Vec<f32> = (0..2000)
+            .map(|i| {
+                let spike = if i % 500 == 250 { 20.0 } else { 0.0 };
+                (i as f32 * 0.1).sin() * (-(i as f32) / 1000.0).exp() + spike
+            })
+            .collect();
+        for (sample_idx, sample) in samples.iter().enumerate() {
+            let rms = rms_buffer.process(*sample);
+            let expected = brute_force_rms(&samples[..sample_idx + 1], window_len);
+            approx::assert_relative_eq!(rms, expected, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn window_len_changes() {
+        let mut rms_buffer = RmsBuffer::new(RmsBuffer::window_len_for(1000.0, 4.0));
+        assert_eq!(rms_buffer.window_len(), 4);
+        assert_eq!(rms_buffer.process_block(&[1.0; 8]), 1.0);
+
+        // Changing the window length clears the window
+        rms_buffer.set_window_len(16);
+        assert_eq!(rms_buffer.window_len(), 16);
+        assert_eq!(rms_buffer.rms(), 0.0);
+        assert_eq!(rms_buffer.process_block(&[2.0; 4]), 1.0);
+        assert_eq!(rms_buffer.process_block(&[2.0; 12]), 2.0);
+    }
+}
@@ -0,0 +1,216 @@
+//! Windowed sinc interpolation for high quality fractional delay reads and resampling.
+
+use std::f64;
+
+/// A precomputed table of Blackman windowed sinc interpolation kernels. The table contains
+/// `oversampling + 1` kernel phases of `num_taps` coefficients each, and
+/// [`interpolate()`][Self::interpolate()] linearly interpolates between the two nearest phases.
+/// This allows reading samples at fractional positions with very little aliasing, for instance
+/// when resampling or when implementing modulated delay lines.
+///
+/// Creating this table allocates, so it should be created ahead of time in
+/// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] and not on the audio thread.
+/// Reading from the table is realtime-safe.
+#[derive(Debug, Clone)]
+pub struct SincTable {
+    /// The number of input samples used for every interpolated output sample. Always even.
+    num_taps: usize,
+    /// The number of kernel phases per sample.
+    oversampling: usize,
+    /// The kernel coefficients, stored phase by phase. Phase `p` starts at index `p * num_taps`.
+    /// There are `oversampling + 1` phases so the last phase can be used for interpolation.
+    coefficients: Vec<f32>,
+}
+
+impl SincTable {
+    /// Create a new windowed sinc table. `num_taps` is the number of input samples that contribute
+    /// to each output sample and needs to be even. Higher values result in a steeper transition
+    /// band at the cost of more CPU usage. `oversampling` is the number of kernel phases computed
+    /// per sample. `cutoff` is the filter's cutoff frequency relative to the Nyquist frequency, so
+    /// it should be in the range `(0, 1]`. A value slightly below 1.0 (e.g. 0.9) leaves room for
+    /// the transition band so frequencies near the Nyquist frequency don't alias.
+    ///
+    /// Every kernel phase is normalized to have unity gain at DC.
+    pub fn new(num_taps: usize, oversampling: usize, cutoff: f32) -> Self {
+        nih_debug_assert!(num_taps >= 2 && num_taps % 2 == 0);
+        nih_debug_assert!(oversampling >= 1);
+        nih_debug_assert!(cutoff > 0.0 && cutoff <= 1.0);
+        let num_taps = (num_taps.max(2) + 1) & !1;
+        let oversampling = oversampling.max(1);
+        let cutoff = cutoff.clamp(f32::EPSILON, 1.0) as f64;
+
+        let half_width = (num_taps / 2) as f64;
+        let kernel = |t: f64| -> f64 {
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                let x = f64::consts::PI * cutoff * t;
+                x.sin() / x
+            };
+
+            // A Blackman window centered around `t = 0`, spanning the kernel's entire support
+            let window = if t.abs() >= half_width {
+                0.0
+            } else {
+                let phase = f64::consts::PI * t / half_width;
+                0.42 + (0.5 * phase.cos()) + (0.08 * (2.0 * phase).cos())
+            };
+
+            cutoff * sinc * window
+        };
+
+        // The kernels are computed at double precision before being normalized
+        let mut coefficients = vec![0.0; num_taps * (oversampling + 1)];
+        let mut kernel_phase = vec![0.0f64; num_taps];
+        for (phase, phase_coefficients) in coefficients.chunks_exact_mut(num_taps).enumerate() {
+            let fraction = phase as f64 / oversampling as f64;
+            for (tap, value) in kernel_phase.iter_mut().enumerate() {
+                let offset = tap as f64 - half_width + 1.0;
+                *value = kernel(offset - fraction);
+            }
+
+            let gain: f64 = kernel_phase.iter().sum();
+            for (coefficient, value) in phase_coefficients.iter_mut().zip(&kernel_phase) {
+                *coefficient = (value / gain) as f32;
+            }
+        }
+
+        Self {
+            num_taps,
+            oversampling,
+            coefficients,
+        }
+    }
+
+    /// The number of input samples used for every interpolated output sample.
+    pub fn num_taps(&self) -> usize {
+        self.num_taps
+    }
+
+    /// The number of kernel phases per sample.
+    pub fn oversampling(&self) -> usize {
+        self.oversampling
+    }
+
+    /// The number of samples the interpolation looks ahead. Reading at `position` uses the samples
+    /// from `position.floor() - latency + 1` up to and including `position.floor() + latency`.
+    pub fn latency(&self) -> usize {
+        self.num_taps / 2
+    }
+
+    /// Get the coefficients for a kernel phase. `phase` must be in `[0, oversampling]`. The
+    /// coefficient at index `i` gets multiplied with the sample `i - num_taps / 2 + 1` samples
+    /// after the integer read position.
+    pub fn phase(&self, phase: usize) -> &[f32] {
+        &self.coefficients[phase * self.num_taps..(phase + 1) * self.num_taps]
+    }
+
+    /// Read a sample from `samples` at a fractional `position`. Samples outside of `samples` are
+    /// treated as silence. See [`latency()`][Self::latency()] for the samples that are used.
+    pub fn interpolate(&self, samples: &[f32], position: f64) -> f32 {
+        let index = position.floor();
+        let fraction = (position - index) * self.oversampling as f64;
+        let phase_idx = (fraction as usize).min(self.oversampling - 1);
+        let phase_t = (fraction - phase_idx as f64) as f32;
+
+        let first_sample_idx = index as isize - self.latency() as isize + 1;
+        let phase_1 = self.phase(phase_idx);
+        let phase_2 = self.phase(phase_idx + 1);
+
+        let mut result = 0.0;
+        if first_sample_idx >= 0 && first_sample_idx as usize + self.num_taps <= samples.len() {
+            // This is the fast path where the entire kernel fits within the slice
+            let first_sample_idx = first_sample_idx as usize;
+            let samples = &samples[first_sample_idx..first_sample_idx + self.num_taps];
+            for ((sample, c1), c2) in samples.iter().zip(phase_1).zip(phase_2) {
+                result += sample * (c1 + (c2 - c1) * phase_t);
+            }
+        } else {
+            for (tap, (c1, c2)) in phase_1.iter().zip(phase_2).enumerate() {
+                let sample_idx = first_sample_idx + tap as isize;
+                if sample_idx >= 0 && (sample_idx as usize) < samples.len() {
+                    result += samples[sample_idx as usize] * (c1 + (c2 - c1) * phase_t);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compute the magnitude response in decibels of the table's prototype filter at a frequency
+    /// relative to the sample rate, so 0.5 is the Nyquist frequency.
+    fn magnitude_db(table: &SincTable, frequency: f64) -> f64 {
+        let oversampling = table.oversampling() as isize;
+        let mut re = 0.0;
+        let mut im = 0.0;
+        for phase in 0..table.oversampling() {
+            for (tap, coefficient) in table.phase(phase).iter().enumerate() {
+                let offset = tap as isize - table.latency() as isize + 1;
+                let n = (offset * oversampling - phase as isize) as f64;
+                let omega = 2.0 * f64::consts::PI * frequency * n / oversampling as f64;
+                re += *coefficient as f64 * omega.cos();
+                im -= *coefficient as f64 * omega.sin();
+            }
+        }
+
+        let magnitude = (re * re + im * im).sqrt() / oversampling as f64;
+        20.0 * magnitude.max(1e-12).log10()
+    }
+
+    #[test]
+    fn passband_flatness() {
+        let table = SincTable::new(32, 64, 0.9);
+        for i in 0..=35 {
+            let frequency = i as f64 / 100.0;
+            let db = magnitude_db(&table, frequency);
+            assert!(db.abs() < 0.01, "{db} dB at {frequency}");
+        }
+    }
+
+    #[test]
+    fn stopband_attenuation() {
+        let table = SincTable::new(32, 64, 0.9);
+        for i in 55..=500 {
+            let frequency = i as f64 / 100.0;
+            let db = magnitude_db(&table, frequency);
+            assert!(db < -70.0, "{db} dB at {frequency}");
+        }
+    }
+
+    #[test]
+    fn integer_positions() {
+        let table = SincTable::new(16, 32, 1.0);
+        let samples: Vec<f32> = (0..64).map(|i| (i as f32 * 0.1).sin()).collect();
+        for (i, sample) in samples.iter().enumerate() {
+            approx::assert_relative_eq!(
+                table.interpolate(&samples, i as f64),
+                *sample,
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn fractional_positions() {
+        let table = SincTable::new(32, 64, 0.9);
+        let frequency = 0.05;
+        let samples: Vec<f32> = (0..256)
+            .map(|i| (2.0 * f64::consts::PI * frequency * i as f64).sin() as f32)
+            .collect();
+
+        for i in 0..100 {
+            let position = 64.0 + i as f64 * 0.37;
+            let expected = (2.0 * f64::consts::PI * frequency * position).sin() as f32;
+            approx::assert_relative_eq!(
+                table.interpolate(&samples, position),
+                expected,
+                epsilon = 1e-3
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Saturation curves for distortion and limiting.
+//!
+//! All of these functions take a `drive` argument that's used as a gain before the signal goes
+//! through the curve. The output is divided by the drive again afterwards so that signals at low
+//! amplitudes pass through at unity gain regardless of the drive. Increasing the drive thus only
+//! lowers the point where the curve starts to saturate, with the output being bounded to
+//! `[-1 / drive, 1 / drive]`. Without any drive, i.e. with a drive of 1.0, the curves approximate
+//! the identity function for small values and they never exceed an absolute value of 1.0. Follow
+//! up with a makeup gain if the output should stay at the same level as the drive changes.
+
+#[cfg(feature = "simd")]
+use std::simd::num::SimdFloat;
+#[cfg(feature = "simd")]
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
+
+/// The input value where [`softclip_cubic()`] reaches its maximum of 1.0 without drive.
+const CUBIC_KNEE: f32 = 1.5;
+
+/// A hyperbolic tangent soft clipper. This is the smoothest of the three curves and it starts
+/// saturating gradually well before reaching the ceiling. See the [module
+/// documentation][self] for how `drive` is applied.
+#[inline]
+pub fn softclip_tanh(sample: f32, drive: f32) -> f32 {
+    nih_debug_assert!(drive > 0.0);
+
+    (sample * drive).tanh() / drive
+}
+
+/// A cubic polynomial soft clipper, `x - 4x³ / 27`, that's cheaper to compute than
+/// [`softclip_tanh()`]. The curve reaches the ceiling at an input of 1.5 without drive and it's
+/// flat after that. See the [module documentation][self] for how `drive` is applied.
+#[inline]
+pub fn softclip_cubic(sample: f32, drive: f32) -> f32 {
+    nih_debug_assert!(drive > 0.0);
+
+    let x = (sample * drive).clamp(-CUBIC_KNEE, CUBIC_KNEE);
+    (x - (4.0 / 27.0) * x * x * x) / drive
+}
+
+/// A hard clipper. This passes the signal through untouched until it reaches the ceiling. See the
+/// [module documentation][self] for how `drive` is applied.
+#[inline]
+pub fn softclip_hard(sample: f32, drive: f32) -> f32 {
+    nih_debug_assert!(drive > 0.0);
+
+    (sample * drive).clamp(-1.0, 1.0) / drive
+}
+
+/// The same as [`softclip_tanh()`], but for SIMD vectors. There's no vectorized `tanh()`, so this
+/// applies the curve to every lane individually.
+#[cfg(feature = "simd")]
+#[inline]
+pub fn softclip_tanh_simd<const LANES: usize>(
+    samples: Simd<f32, LANES>,
+    drive: f32,
+) -> Simd<f32, LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    Simd::from_array(
+        samples
+            .to_array()
+            .map(|sample| softclip_tanh(sample, drive)),
+    )
+}
+
+/// The same as [`softclip_cubic()`], but for SIMD vectors.
+#[cfg(feature = "simd")]
+#[inline]
+pub fn softclip_cubic_simd<const LANES: usize>(
+    samples: Simd<f32, LANES>,
+    drive: f32,
+) -> Simd<f32, LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    nih_debug_assert!(drive > 0.0);
+
+    let x = (samples * Simd::splat(drive))
+        .simd_clamp(Simd::splat(-CUBIC_KNEE), Simd::splat(CUBIC_KNEE));
+    (x - Simd::splat(4.0 / 27.0) * x * x * x) / Simd::splat(drive)
+}
+
+/// The same as [`softclip_hard()`], but for SIMD vectors.
+#[cfg(feature = "simd")]
+#[inline]
+pub fn softclip_hard_simd<const LANES: usize>(
+    samples: Simd<f32, LANES>,
+    drive: f32,
+) -> Simd<f32, LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    nih_debug_assert!(drive > 0.0);
+
+    (samples * Simd::splat(drive)).simd_clamp(Simd::splat(-1.0), Simd::splat(1.0))
+        / Simd::splat(drive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [(&str, fn(f32, f32) -> f32); 3] = [
+        ("tanh", softclip_tanh),
+        ("cubic", softclip_cubic),
+        ("hard", softclip_hard),
+    ];
+    const DRIVES: [f32; 3] = [1.0, 2.5, 10.0];
+
+    #[test]
+    fn odd_symmetric() {
+        for (name, curve) in CURVES {
+            for drive in DRIVES {
+                for sample in [0.0, 0.01, 0.3, 0.9, 1.2, 5.0] {
+                    assert_eq!(
+                        curve(-sample, drive),
+                        -curve(sample, drive),
+                        "{name} at {sample} with drive {drive}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounded() {
+        for (name, curve) in CURVES {
+            for drive in DRIVES {
+                let ceiling = drive.recip();
+                for sample in [0.5, 1.0, 2.0, 100.0, f32::MAX] {
+                    let output = curve(sample, drive);
+                    assert!(
+                        output > 0.0 && output <= ceiling,
+                        "{name} at {sample} with drive {drive} returned {output}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn identity_at_low_amplitudes() {
+        for (name, curve) in CURVES {
+            for drive in DRIVES {
+                for sample in [1e-4, -1e-3, 5e-3] {
+                    let output = curve(sample, drive);
+                    assert!(
+                        (output - sample).abs() <= sample.abs() * 1e-3,
+                        "{name} at {sample} with drive {drive} returned {output}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! A combined stereo width, rotation, and balance processor.
+
+use super::{mid_side_to_stereo, stereo_to_mid_side};
+use crate::buffer::Buffer;
+
+/// Adjusts the stereo image of a stereo signal by changing its width, rotating it, and changing its
+/// balance, in that order. These operations are commonly combined in stereo processing plugins,
+/// and getting the gain staging right for all three at once is easy to get subtly wrong.
+///
+/// - The width scales the side signal using [`stereo_to_mid_side()`]. A width of 0 results in a
+///   mono signal, 1 leaves the signal unchanged, and 2 doubles the side signal. When widening,
+///   both the mid and the side signals are attenuated so that uncorrelated stereo material does
+///   not get louder. Narrowing is not compensated so a centered source keeps its level, and a
+///   width of 0 outputs the mid signal, which is the average of the two channels.
+/// - The rotation rotates the left and right channels around the center in degrees. Positive
+///   angles move the stereo image to the right, and a rotation of 45 degrees moves a centered
+///   source to the right channel. This is a power preserving operation.
+/// - The balance attenuates one of the two channels. A balance of -1 mutes the right channel, 0
+///   leaves both channels unchanged, and 1 mutes the left channel. The louder channel is never
+///   boosted.
+///
+/// All settings are neutral by default, in which case the signal passes through unchanged. The
+/// imager does not smooth parameter changes. To use smoothed parameters, either update the
+/// settings every block, or use [`process_sample()`][Self::process_sample()] and update them
+/// every sample.
+#[derive(Debug, Clone)]
+pub struct StereoImager {
+    /// The width in `[0, 2]`, where 1 is neutral.
+    width: f32,
+    /// The rotation in degrees, where 0 is neutral.
+    rotation_degrees: f32,
+    /// The balance in `[-1, 1]`, where 0 is neutral.
+    balance: f32,
+
+    /// The gain applied to the mid and side signals after applying the width to compensate for
+    /// the added side signal.
+    width_normalization: f32,
+    /// The sine and cosine of the rotation angle.
+    rotation_sin: f32,
+    rotation_cos: f32,
+    /// The gains applied to the left and right channels for the balance.
+    left_gain: f32,
+    right_gain: f32,
+}
+
+impl Default for StereoImager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StereoImager {
+    /// Create a stereo imager with neutral settings.
+    pub fn new() -> Self {
+        Self {
+            width: 1.0,
+            rotation_degrees: 0.0,
+            balance: 0.0,
+
+            width_normalization: 1.0,
+            rotation_sin: 0.0,
+            rotation_cos: 1.0,
+            left_gain: 1.0,
+            right_gain: 1.0,
+        }
+    }
+
+    /// Set the stereo width. The value is clamped to `[0, 2]`.
+    pub fn set_width(&mut self, width: f32) {
+        nih_debug_assert!((0.0..=2.0).contains(&width));
+
+        self.width = width.clamp(0.0, 2.0);
+        // The side signal's power increases with `width^2`. For uncorrelated channels with equal
+        // power the mid and side signals also have equal power, so dividing by the square root of
+        // the average power keeps the total power constant.
+        self.width_normalization = if self.width > 1.0 {
+            (2.0 / (1.0 + self.width * self.width)).sqrt()
+        } else {
+            1.0
+        };
+    }
+
+    /// Set the rotation in degrees. Values between -45 and 45 degrees are the most useful.
+    pub fn set_rotation_degrees(&mut self, rotation_degrees: f32) {
+        self.rotation_degrees = rotation_degrees;
+
+        let (sin, cos) = rotation_degrees.to_radians().sin_cos();
+        self.rotation_sin = sin;
+        self.rotation_cos = cos;
+    }
+
+    /// Set the balance. The value is clamped to `[-1, 1]`.
+    pub fn set_balance(&mut self, balance: f32) {
+        nih_debug_assert!((-1.0..=1.0).contains(&balance));
+
+        self.balance = balance.clamp(-1.0, 1.0);
+        self.left_gain = (1.0 - self.balance).min(1.0);
+        self.right_gain = (1.0 + self.balance).min(1.0);
+    }
+
+    /// The stereo width.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// The rotation in degrees.
+    pub fn rotation_degrees(&self) -> f32 {
+        self.rotation_degrees
+    }
+
+    /// The balance.
+    pub fn balance(&self) -> f32 {
+        self.balance
+    }
+
+    /// Process a single pair of left and right samples, returning the new `(left, right)` pair.
+    #[inline]
+    pub fn process_sample(&self, left: f32, right: f32) -> (f32, f32) {
+        let (mid, side) = stereo_to_mid_side(left, right);
+        let (left, right) = mid_side_to_stereo(
+            mid * self.width_normalization,
+            side * self.width * self.width_normalization,
+        );
+
+        let rotated_left = left * self.rotation_cos - right * self.rotation_sin;
+        let rotated_right = left * self.rotation_sin + right * self.rotation_cos;
+
+        (
+            rotated_left * self.left_gain,
+            rotated_right * self.right_gain,
+        )
+    }
+
+    /// Process a stereo buffer in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` does not have exactly two channels.
+    pub fn process(&self, buffer: &mut Buffer) {
+        assert_eq!(buffer.channels(), 2);
+
+        let (left, right) = buffer.as_slice().split_at_mut(1);
+        for (left, right) in left[0].iter_mut().zip(right[0].iter_mut()) {
+            let (new_left, new_right) = self.process_sample(*left, *right);
+            *left = new_left;
+            *right = new_right;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    /// Run `imager` over a copy of `left` and `right` and return the processed channels.
+    fn process(imager: &StereoImager, left: &[f32], right: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let mut data = [left.to_vec(), right.to_vec()];
+        with_test_buffer(&mut data, |buffer| imager.process(buffer));
+
+        let [left, right] = data;
+        (left, right)
+    }
+
+    const LEFT: [f32; 4] = [0.5, -0.25, 0.8, 0.0];
+    const RIGHT: [f32; 4] = [0.1, 0.3, -0.6, 0.9];
+
+    #[test]
+    fn neutral_is_identity() {
+        let imager = StereoImager::default();
+        let (left, right) = process(&imager, &LEFT, &RIGHT);
+        for (actual, expected) in left.iter().zip(LEFT).chain(right.iter().zip(RIGHT)) {
+            approx::assert_relative_eq!(*actual, expected, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn zero_width_is_mono() {
+        let mut imager = StereoImager::new();
+        imager.set_width(0.0);
+        let (left, right) = process(&imager, &LEFT, &RIGHT);
+        for ((left, right), (input_left, input_right)) in
+            left.iter().zip(&right).zip(LEFT.iter().zip(RIGHT))
+        {
+            assert_eq!(left, right);
+            approx::assert_relative_eq!(*left, (input_left + input_right) / 2.0, epsilon = 1e-6);
+        }
+
+        // A centered source keeps its level, and out of phase material cancels out entirely
+        let (left, right) = process(&imager, &[0.5, 0.5], &[0.5, -0.5]);
+        assert_eq!(left, [0.5, 0.0]);
+        assert_eq!(right, [0.5, 0.0]);
+    }
+
+    #[test]
+    fn widening_is_normalized() {
+        let mut imager = StereoImager::new();
+        imager.set_width(2.0);
+
+        // Uncorrelated channels with equal power, in this case a signal that's only in one channel
+        // and then only in the other, keep their total power
+        let (left, right) = process(&imager, &[1.0, 0.0], &[0.0, 1.0]);
+        for (left, right) in left.iter().zip(&right) {
+            approx::assert_relative_eq!(left * left + right * right, 1.0, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn rotation_and_balance() {
+        let mut imager = StereoImager::new();
+        imager.set_rotation_degrees(45.0);
+        let (left, right) = imager.process_sample(0.5, 0.5);
+        approx::assert_relative_eq!(left, 0.0, epsilon = 1e-6);
+        approx::assert_relative_eq!(right, 0.5 * std::f32::consts::SQRT_2, epsilon = 1e-6);
+
+        imager.set_rotation_degrees(0.0);
+        imager.set_balance(-0.5);
+        assert_eq!(imager.process_sample(0.5, 0.5), (0.5, 0.25));
+        imager.set_balance(1.0);
+        assert_eq!(imager.process_sample(0.5, 0.5), (0.0, 0.5));
+    }
+}
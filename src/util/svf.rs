@@ -0,0 +1,247 @@
+//! A zero-delay feedback state-variable filter.
+
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// The default cutoff frequency used by [`Svf::new()`].
+const DEFAULT_CUTOFF_HZ: f32 = 1000.0;
+/// The highest cutoff frequency as a fraction of the sample rate. The filter's prewarping maps
+/// the Nyquist frequency to infinity, so the cutoff is clamped to stay just below it.
+const MAX_CUTOFF_RATIO: f32 = 0.49;
+
+/// A state-variable filter using the topology-preserving transform (TPT), also known as a
+/// zero-delay feedback filter. Every call to [`process()`][Self::process()] computes the
+/// low-pass, band-pass, and high-pass outputs at the same time. Unlike biquads, the filter's
+/// state doesn't depend on its coefficients, so the cutoff frequency and resonance can be
+/// modulated at audio rate without instability or zipper noise. This filter processes a single
+/// channel, so use one instance per channel.
+///
+/// This is based on Andrew Simper's derivation:
+/// <https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf>
+#[derive(Debug, Clone)]
+pub struct Svf {
+    /// The cutoff frequency in Hertz.
+    cutoff_hz: f32,
+    /// The filter's resonance, expressed as its Q factor.
+    q: f32,
+    /// The current sample rate, set in [`set_sample_rate()`][Self::set_sample_rate()].
+    sample_rate: f32,
+
+    /// The prewarped cutoff frequency `g = tan(pi * cutoff / sample_rate)`.
+    g: f32,
+    /// The damping factor `k = 1 / q`.
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+
+    /// The states of the filter's two integrators.
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl Default for Svf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svf {
+    /// Create a filter with a 1 kHz cutoff frequency and a Butterworth response.
+    /// [`set_sample_rate()`][Self::set_sample_rate()] needs to be called before the filter can be
+    /// used.
+    pub fn new() -> Self {
+        let mut filter = Self {
+            cutoff_hz: DEFAULT_CUTOFF_HZ,
+            q: FRAC_1_SQRT_2,
+            sample_rate: 0.0,
+
+            g: 0.0,
+            k: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        };
+        filter.update_coefficients();
+
+        filter
+    }
+
+    /// Set the sample rate used to compute the filter coefficients. This should be called from
+    /// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also resets the
+    /// filter.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+        self.reset();
+    }
+
+    /// Change the cutoff frequency in Hertz. The frequency is clamped to just below the Nyquist
+    /// frequency. This can safely be called for every sample and does not reset the filter's
+    /// state.
+    pub fn set_cutoff(&mut self, cutoff_hz: f32) {
+        nih_debug_assert!(cutoff_hz > 0.0);
+
+        self.cutoff_hz = cutoff_hz;
+        self.update_coefficients();
+    }
+
+    /// Change the filter's resonance, expressed as its Q factor. A Q of `1 / sqrt(2)` results in
+    /// a Butterworth response without any peaking, and higher values cause the filter to resonate
+    /// around the cutoff frequency. All three outputs are amplified by a factor of `q` at the
+    /// cutoff frequency. This can safely be called for every sample.
+    pub fn set_resonance(&mut self, q: f32) {
+        nih_debug_assert!(q > 0.0);
+
+        self.q = q;
+        self.update_coefficients();
+    }
+
+    /// The cutoff frequency in Hertz.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff_hz
+    }
+
+    /// The filter's resonance, expressed as its Q factor.
+    pub fn resonance(&self) -> f32 {
+        self.q
+    }
+
+    /// Clear the filter's state.
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    /// Filter a single sample. Returns the low-pass, band-pass, and high-pass outputs, in that
+    /// order.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> (f32, f32, f32) {
+        let v3 = sample - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        let low_pass = v2;
+        let band_pass = v1;
+        let high_pass = sample - self.k * v1 - v2;
+
+        (low_pass, band_pass, high_pass)
+    }
+
+    fn update_coefficients(&mut self) {
+        if self.sample_rate > 0.0 {
+            let cutoff_hz = self.cutoff_hz.min(self.sample_rate * MAX_CUTOFF_RATIO);
+            self.g = (PI * cutoff_hz / self.sample_rate).tan();
+        }
+
+        self.k = 1.0 / self.q;
+        self.a1 = 1.0 / (1.0 + self.g * (self.g + self.k));
+        self.a2 = self.g * self.a1;
+        self.a3 = self.g * self.a2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    /// The peak output amplitude for each of the filter's outputs after feeding it a sine wave for
+    /// a second.
+    fn sine_response(filter: &mut Svf, frequency: f32) -> (f32, f32, f32) {
+        let num_samples = SAMPLE_RATE as usize;
+        let mut peaks = (0.0f32, 0.0f32, 0.0f32);
+        for i in 0..num_samples {
+            let (low_pass, band_pass, high_pass) =
+                filter.process((TAU * frequency * i as f32 / SAMPLE_RATE).sin());
+
+            // Skip the filter's transient response
+            if i >= num_samples / 2 {
+                peaks.0 = peaks.0.max(low_pass.abs());
+                peaks.1 = peaks.1.max(band_pass.abs());
+                peaks.2 = peaks.2.max(high_pass.abs());
+            }
+        }
+
+        peaks
+    }
+
+    #[test]
+    fn resonance_peaking() {
+        let mut filter = Svf::new();
+        filter.set_sample_rate(SAMPLE_RATE);
+        filter.set_cutoff(1000.0);
+
+        // With a Butterworth response all outputs are 3 dB down at the cutoff frequency
+        let (low_pass, band_pass, high_pass) = sine_response(&mut filter, 1000.0);
+        approx::assert_relative_eq!(low_pass, FRAC_1_SQRT_2, max_relative = 0.01);
+        approx::assert_relative_eq!(band_pass, FRAC_1_SQRT_2, max_relative = 0.01);
+        approx::assert_relative_eq!(high_pass, FRAC_1_SQRT_2, max_relative = 0.01);
+
+        filter.reset();
+        filter.set_resonance(8.0);
+        let (low_pass, band_pass, high_pass) = sine_response(&mut filter, 1000.0);
+        approx::assert_relative_eq!(low_pass, 8.0, max_relative = 0.01);
+        approx::assert_relative_eq!(band_pass, 8.0, max_relative = 0.01);
+        approx::assert_relative_eq!(high_pass, 8.0, max_relative = 0.01);
+
+        // Away from the cutoff frequency the resonance should not affect the passband
+        filter.reset();
+        let (low_pass, _, _) = sine_response(&mut filter, 50.0);
+        approx::assert_relative_eq!(low_pass, 1.0, max_relative = 0.01);
+    }
+
+    #[test]
+    fn stable_under_fast_cutoff_sweeps() {
+        let mut filter = Svf::new();
+        filter.set_sample_rate(SAMPLE_RATE);
+        filter.set_resonance(20.0);
+
+        // Deterministic white noise from a linear congruential generator
+        let mut rng_state = 1u32;
+        let mut noise = move || {
+            rng_state = rng_state
+                .wrapping_mul(1_664_525)
+                .wrapping_add(1_013_904_223);
+            (rng_state >> 8) as f32 / (1 << 23) as f32 - 1.0
+        };
+
+        // The cutoff is swept across the entire audible range at audio rate, and then jumps
+        // between extremes on every sample
+        for i in 0..SAMPLE_RATE as usize {
+            let sweep = (TAU * 500.0 * i as f32 / SAMPLE_RATE).sin() * 0.5 + 0.5;
+            filter.set_cutoff(20.0 * 1000.0f32.powf(sweep));
+
+            let (low_pass, band_pass, high_pass) = filter.process(noise());
+            for output in [low_pass, band_pass, high_pass] {
+                assert!(output.is_finite());
+                assert!(output.abs() < 100.0, "{output}");
+            }
+        }
+        for i in 0..SAMPLE_RATE as usize {
+            filter.set_cutoff(if i % 2 == 0 { 20.0 } else { 30_000.0 });
+
+            let (low_pass, band_pass, high_pass) = filter.process(noise());
+            for output in [low_pass, band_pass, high_pass] {
+                assert!(output.is_finite());
+                assert!(output.abs() < 100.0, "{output}");
+            }
+        }
+
+        // The filter should settle back down once the input stops
+        for _ in 0..SAMPLE_RATE as usize {
+            filter.process(0.0);
+        }
+        let (low_pass, band_pass, high_pass) = filter.process(0.0);
+        assert!(low_pass.abs() < 1e-3 && band_pass.abs() < 1e-3 && high_pass.abs() < 1e-3);
+    }
+}
@@ -0,0 +1,282 @@
+//! A transient shaper for changing the level of a signal's attacks and sustain.
+
+use super::compressor::time_constant_coefficient;
+use crate::buffer::Buffer;
+use crate::util::db_to_gain_fast;
+
+/// The attack time of the fast envelope follower, in milliseconds.
+const FAST_ATTACK_MS: f32 = 1.0;
+/// The release time of the fast envelope follower, in milliseconds.
+const FAST_RELEASE_MS: f32 = 20.0;
+/// The attack time of the envelope follower that lags behind the fast envelope during attacks.
+const SLOW_ATTACK_MS: f32 = 20.0;
+/// The release time of the envelope follower that lags behind the fast envelope during decays.
+const SLOW_RELEASE_MS: f32 = 300.0;
+/// The gain in decibels applied at the very start of an attack with an attack amount of 1, and
+/// after the signal has fully decayed with a sustain amount of 1.
+const MAX_GAIN_DB: f32 = 12.0;
+
+/// A transient designer that emphasizes or softens a signal's attacks and sustain without having
+/// to set a threshold. The shaper uses differential envelope detection with three peak envelope
+/// followers. A fast follower tracks the signal's level closely. During an attack, a follower with
+/// a slower attack time lags behind it, and the relative difference between the two is the attack
+/// portion of the signal. During a decay, a follower with a slower release time stays above the
+/// fast follower, and that relative difference is the sustain portion of the signal. Because both
+/// are relative measures, the shaper responds the same way to quiet and loud signals.
+///
+/// The [attack][Self::set_attack()] and [sustain][Self::set_sustain()] amounts scale the gain
+/// that's applied to those portions of the signal. With an amount of 1 the very start of an attack
+/// is boosted by 12 dB, an amount of 0 leaves that portion alone, and negative amounts attenuate
+/// it instead. All channels share the same gain to keep the stereo image intact.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.transient_shaper.set_sample_rate(buffer_config.sample_rate);
+///
+/// // In `process()`
+/// self.transient_shaper.set_attack(self.params.attack.value());
+/// self.transient_shaper.set_sustain(self.params.sustain.value());
+/// self.transient_shaper.process(buffer);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransientShaper {
+    /// The amount the attack portion of the signal is emphasized by, usually in `[-1, 1]`.
+    attack: f32,
+    /// The amount the sustain portion of the signal is emphasized by, usually in `[-1, 1]`.
+    sustain: f32,
+
+    /// The per-sample coefficients for the envelope followers, computed in
+    /// [`set_sample_rate()`][Self::set_sample_rate()].
+    fast_attack_coefficient: f32,
+    fast_release_coefficient: f32,
+    slow_attack_coefficient: f32,
+    slow_release_coefficient: f32,
+
+    /// Follows the signal's peak level with a fast attack and release.
+    fast_envelope: f32,
+    /// Follows the signal's peak level with a slow attack and a fast release.
+    slow_attack_envelope: f32,
+    /// Follows the signal's peak level with a fast attack and a slow release.
+    slow_release_envelope: f32,
+}
+
+impl Default for TransientShaper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransientShaper {
+    /// Create a transient shaper that leaves the signal unchanged. The envelope followers are
+    /// instant until [`set_sample_rate()`][Self::set_sample_rate()] has been called.
+    pub fn new() -> Self {
+        Self {
+            attack: 0.0,
+            sustain: 0.0,
+
+            fast_attack_coefficient: 0.0,
+            fast_release_coefficient: 0.0,
+            slow_attack_coefficient: 0.0,
+            slow_release_coefficient: 0.0,
+
+            fast_envelope: 0.0,
+            slow_attack_envelope: 0.0,
+            slow_release_envelope: 0.0,
+        }
+    }
+
+    /// Set the sample rate used to compute the envelope followers' coefficients. This should be
+    /// called from [`Plugin::initialize()`][crate::prelude::Plugin::initialize()]. This also
+    /// resets the shaper.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        self.fast_attack_coefficient = time_constant_coefficient(FAST_ATTACK_MS, sample_rate);
+        self.fast_release_coefficient = time_constant_coefficient(FAST_RELEASE_MS, sample_rate);
+        self.slow_attack_coefficient = time_constant_coefficient(SLOW_ATTACK_MS, sample_rate);
+        self.slow_release_coefficient = time_constant_coefficient(SLOW_RELEASE_MS, sample_rate);
+        self.reset();
+    }
+
+    /// Set the attack amount. Positive values make attacks punchier, negative values soften them.
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack;
+    }
+
+    /// Set the sustain amount. Positive values bring out the tails of notes and room sound,
+    /// negative values make the signal tighter.
+    pub fn set_sustain(&mut self, sustain: f32) {
+        self.sustain = sustain;
+    }
+
+    /// The attack amount.
+    pub fn attack(&self) -> f32 {
+        self.attack
+    }
+
+    /// The sustain amount.
+    pub fn sustain(&self) -> f32 {
+        self.sustain
+    }
+
+    /// Clear the envelope followers.
+    pub fn reset(&mut self) {
+        self.fast_envelope = 0.0;
+        self.slow_attack_envelope = 0.0;
+        self.slow_release_envelope = 0.0;
+    }
+
+    /// Shape the transients of `buffer` in place.
+    pub fn process(&mut self, buffer: &mut Buffer) {
+        let num_samples = buffer.samples();
+        let output = buffer.as_slice();
+        for sample_idx in 0..num_samples {
+            let peak = output
+                .iter()
+                .map(|channel| channel[sample_idx].abs())
+                .fold(0.0, f32::max);
+
+            let gain = db_to_gain_fast(self.next_gain_db(peak));
+            for channel in output.iter_mut() {
+                channel[sample_idx] *= gain;
+            }
+        }
+    }
+
+    /// Update the envelope followers based on the current peak level, and return the gain in
+    /// decibels that should be applied to the current sample.
+    #[inline]
+    fn next_gain_db(&mut self, peak: f32) -> f32 {
+        follow_envelope(
+            &mut self.fast_envelope,
+            peak,
+            self.fast_attack_coefficient,
+            self.fast_release_coefficient,
+        );
+        follow_envelope(
+            &mut self.slow_attack_envelope,
+            peak,
+            self.slow_attack_coefficient,
+            self.fast_release_coefficient,
+        );
+        follow_envelope(
+            &mut self.slow_release_envelope,
+            peak,
+            self.fast_attack_coefficient,
+            self.slow_release_coefficient,
+        );
+
+        // These are the fractions of the fast envelope that the slow attack envelope hasn't caught
+        // up with yet, and of the slow release envelope that the fast envelope has already decayed
+        // by
+        let attack = relative_difference(self.fast_envelope, self.slow_attack_envelope);
+        let sustain = relative_difference(self.slow_release_envelope, self.fast_envelope);
+
+        ((self.attack * attack) + (self.sustain * sustain)) * MAX_GAIN_DB
+    }
+}
+
+/// How much smaller `lower` is than `upper` as a fraction of `upper`, in `[0, 1]`.
+#[inline]
+fn relative_difference(upper: f32, lower: f32) -> f32 {
+    if upper > f32::EPSILON {
+        ((upper - lower) / upper).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Move a peak envelope follower towards `peak` using the attack coefficient when the level rises
+/// and the release coefficient when it falls.
+#[inline]
+fn follow_envelope(
+    envelope: &mut f32,
+    peak: f32,
+    attack_coefficient: f32,
+    release_coefficient: f32,
+) {
+    let coefficient = if peak > *envelope {
+        attack_coefficient
+    } else {
+        release_coefficient
+    };
+    *envelope = peak + (coefficient * (*envelope - peak));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_test_buffer;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// 10 ms of silence followed by a drum-like hit, a 200 Hz sine wave with an instant attack that
+    /// decays by 60 dB over half a second.
+    fn percussive_signal() -> Vec<f32> {
+        let silence = (SAMPLE_RATE * 0.01) as usize;
+        (0..(SAMPLE_RATE * 0.5) as usize + silence)
+            .map(|i| match i.checked_sub(silence) {
+                Some(i) => {
+                    let t = i as f32 / SAMPLE_RATE;
+                    (std::f32::consts::TAU * 200.0 * t).sin() * db_to_gain_fast(-120.0 * t)
+                }
+                None => 0.0,
+            })
+            .collect()
+    }
+
+    /// Process `signal` as a single channel and return the result.
+    fn shape(attack: f32, sustain: f32, signal: &[f32]) -> Vec<f32> {
+        let mut shaper = TransientShaper::new();
+        shaper.set_sample_rate(SAMPLE_RATE);
+        shaper.set_attack(attack);
+        shaper.set_sustain(sustain);
+
+        let mut data = [signal.to_vec()];
+        with_test_buffer(&mut data, |buffer| shaper.process(buffer));
+
+        let [samples] = data;
+        samples
+    }
+
+    /// The peak level of `signal` between `start_ms` and `end_ms`.
+    fn peak(signal: &[f32], start_ms: f32, end_ms: f32) -> f32 {
+        let start = (start_ms / 1000.0 * SAMPLE_RATE) as usize;
+        let end = (end_ms / 1000.0 * SAMPLE_RATE) as usize;
+        signal[start..end]
+            .iter()
+            .fold(0.0, |peak, x| x.abs().max(peak))
+    }
+
+    #[test]
+    fn attack_amplifies_transients() {
+        let signal = percussive_signal();
+        assert_eq!(shape(0.0, 0.0, &signal), signal);
+
+        // The hit starts at 10 ms, so its first couple of milliseconds are the attack
+        let attack_peak = peak(&signal, 10.0, 15.0);
+        let louder = shape(1.0, 0.0, &signal);
+        let softer = shape(-1.0, 0.0, &signal);
+        assert!(peak(&louder, 10.0, 15.0) > attack_peak * 1.5);
+        assert!(peak(&softer, 10.0, 15.0) < attack_peak / 1.5);
+
+        // The tail of the hit is left mostly alone
+        let tail_peak = peak(&signal, 200.0, 250.0);
+        approx::assert_relative_eq!(
+            peak(&louder, 200.0, 250.0),
+            tail_peak,
+            epsilon = 0.2 * tail_peak
+        );
+
+        // The sustain amount instead changes the tail
+        let sustained = shape(0.0, 1.0, &signal);
+        assert!(peak(&sustained, 200.0, 250.0) > tail_peak * 1.5);
+        approx::assert_relative_eq!(
+            peak(&sustained, 10.0, 15.0),
+            attack_peak,
+            epsilon = 0.1 * attack_peak
+        );
+    }
+}
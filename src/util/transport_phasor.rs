@@ -0,0 +1,209 @@
+//! A tempo synced phase accumulator that follows the host's transport.
+
+use crate::context::process::Transport;
+
+/// The tempo used when the host has never reported a tempo.
+const DEFAULT_TEMPO: f64 = 120.0;
+
+/// How far the host's reported position may deviate from the accumulated position before it's
+/// treated as a jump in the transport, in beats. This leaves some room for rounding in the host's
+/// position reporting and for tempo changes within a block.
+const DISCONTINUITY_TOLERANCE_BEATS: f64 = 1e-3;
+
+/// A phase accumulator for tempo synced effects and LFOs. The phase wraps around in `[0, 1)` every
+/// [`cycle_beats()`][Self::cycle_beats()] quarter notes.
+///
+/// The phase is stored in the phasor itself and it's advanced one sample at a time, so it stays
+/// continuous when the host changes the buffer size between blocks or when the block gets split up
+/// for sample accurate automation. While the transport is playing, the host's position is compared
+/// to the accumulated position at the start of every block. If the host jumped to another position,
+/// for instance because it looped around or because the user moved the playhead, then the phase
+/// snaps to match the new position. While the transport is stopped the phase keeps running at the
+/// last known tempo.
+///
+/// ```ignore
+/// self.phasor.start_block(context.transport());
+/// for channel_samples in buffer.iter_samples() {
+///     let lfo = (self.phasor.next() * std::f32::consts::TAU).sin();
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransportPhasor {
+    /// The length of a single cycle in quarter notes.
+    cycle_beats: f64,
+    /// The phase for the next sample, in `[0, 1)`.
+    phase: f64,
+    /// The amount added to `phase` every sample, computed at the start of the block.
+    phase_increment: f64,
+    /// The number of quarter notes per sample at the current tempo.
+    beats_per_sample: f64,
+    /// The tempo from the last block, used when the host does not report a tempo.
+    tempo: f64,
+    /// The position in beats the host should report at the start of the next block if the
+    /// transport keeps playing without jumping. `None` when the transport is stopped or if the host
+    /// does not report its position.
+    expected_pos_beats: Option<f64>,
+}
+
+impl TransportPhasor {
+    /// Create a phasor that completes a cycle every `cycle_beats` quarter notes. For instance, a
+    /// value of 4.0 results in one cycle per bar in 4/4 time, and 0.5 results in one cycle per
+    /// eighth note.
+    pub fn new(cycle_beats: f64) -> Self {
+        nih_debug_assert!(cycle_beats > 0.0);
+
+        Self {
+            cycle_beats,
+            phase: 0.0,
+            phase_increment: 0.0,
+            beats_per_sample: 0.0,
+            tempo: DEFAULT_TEMPO,
+            expected_pos_beats: None,
+        }
+    }
+
+    /// Change the cycle length in quarter notes. If the transport is playing, then the phase will
+    /// snap to the transport's position at the start of the next block.
+    pub fn set_cycle_beats(&mut self, cycle_beats: f64) {
+        nih_debug_assert!(cycle_beats > 0.0);
+
+        self.cycle_beats = cycle_beats;
+        self.expected_pos_beats = None;
+    }
+
+    /// The length of a single cycle in quarter notes.
+    pub fn cycle_beats(&self) -> f64 {
+        self.cycle_beats
+    }
+
+    /// The phase the next call to [`next()`][Self::next()] will return.
+    pub fn phase(&self) -> f32 {
+        self.phase as f32
+    }
+
+    /// Reset the phase to zero. If the transport is playing, then the phase will snap to the
+    /// transport's position at the start of the next block. This should be called from
+    /// [`Plugin::reset()`][crate::prelude::Plugin::reset()].
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.expected_pos_beats = None;
+    }
+
+    /// Synchronize the phasor with the host's transport. This needs to be called once at the start
+    /// of every block, before calling [`next()`][Self::next()] for every sample in the block.
+    /// Returns `true` if the phase snapped to a new position because the transport started playing
+    /// or jumped to another position.
+    pub fn start_block(&mut self, transport: &Transport) -> bool {
+        if let Some(tempo) = transport.tempo {
+            self.tempo = tempo;
+        }
+        self.beats_per_sample = self.tempo / 60.0 / transport.sample_rate as f64;
+        self.phase_increment = self.beats_per_sample / self.cycle_beats;
+
+        match (transport.playing, transport.pos_beats()) {
+            (true, Some(pos_beats)) => {
+                let is_continuous = matches!(
+                    self.expected_pos_beats,
+                    Some(expected_pos_beats)
+                        if (pos_beats - expected_pos_beats).abs() <= DISCONTINUITY_TOLERANCE_BEATS
+                );
+                self.expected_pos_beats = Some(pos_beats);
+
+                if is_continuous {
+                    false
+                } else {
+                    self.phase = (pos_beats / self.cycle_beats).rem_euclid(1.0);
+                    true
+                }
+            }
+            _ => {
+                self.expected_pos_beats = None;
+                false
+            }
+        }
+    }
+
+    /// Get the phase for the current sample and advance the phasor by one sample. This should be
+    /// called exactly once per sample.
+    // This mirrors `Smoother::next()`
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        let phase = self.phase;
+        self.phase = (self.phase + self.phase_increment).fract();
+        if let Some(expected_pos_beats) = &mut self.expected_pos_beats {
+            *expected_pos_beats += self.beats_per_sample;
+        }
+
+        phase as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const TEMPO: f64 = 120.0;
+
+    /// A playing transport at `TEMPO` at `pos_samples` into the song.
+    fn transport_at(pos_samples: usize) -> Transport {
+        let mut transport = Transport::new(SAMPLE_RATE);
+        transport.playing = true;
+        transport.tempo = Some(TEMPO);
+        transport.pos_beats = Some(pos_samples as f64 / SAMPLE_RATE as f64 / 60.0 * TEMPO);
+
+        transport
+    }
+
+    #[test]
+    fn continuous_with_changing_block_sizes() {
+        let mut phasor = TransportPhasor::new(1.0);
+        let beats_per_sample = TEMPO / 60.0 / SAMPLE_RATE as f64;
+
+        let mut pos_samples = 0;
+        for (block_idx, block_len) in [64, 17, 256, 1, 100, 512, 3]
+            .iter()
+            .cycle()
+            .take(70)
+            .enumerate()
+        {
+            // Only the first block should snap to the transport
+            assert_eq!(
+                phasor.start_block(&transport_at(pos_samples)),
+                block_idx == 0
+            );
+            for _ in 0..*block_len {
+                let expected_phase = (pos_samples as f64 * beats_per_sample).fract();
+                approx::assert_relative_eq!(phasor.next() as f64, expected_phase, epsilon = 1e-5);
+                pos_samples += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn snaps_on_transport_jumps() {
+        let mut phasor = TransportPhasor::new(4.0);
+        phasor.start_block(&transport_at(0));
+        for _ in 0..128 {
+            phasor.next();
+        }
+
+        // The host looped back to the start of the second bar
+        let mut transport = transport_at(128);
+        transport.pos_beats = Some(5.0);
+        assert!(phasor.start_block(&transport));
+        approx::assert_relative_eq!(phasor.next(), 0.25);
+
+        // While stopped the phase keeps running, and it snaps again when playback starts
+        transport.playing = false;
+        assert!(!phasor.start_block(&transport));
+        let free_running = phasor.next();
+        assert!(free_running > 0.25);
+        transport.playing = true;
+        transport.pos_beats = Some(2.0);
+        assert!(phasor.start_block(&transport));
+        approx::assert_relative_eq!(phasor.next(), 0.5);
+    }
+}
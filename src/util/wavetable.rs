@@ -0,0 +1,252 @@
+//! Band-limited wavetables for wavetable oscillators.
+
+use std::f64;
+
+use super::interp::lerp;
+
+/// A single-cycle waveform with band-limited mipmaps, one per octave, for aliasing free wavetable
+/// playback. The waveform's spectrum is computed once when the wavetable is created. Level 0
+/// contains all harmonics that fit in the table, and every following level contains half as many
+/// harmonics as the level before it, down to a pure sine wave in the last level.
+/// [`sample()`][Self::sample()] picks the level with the most harmonics that still all lie below
+/// the Nyquist frequency for the current playback frequency.
+///
+/// The wavetable does not store a phase, so a single wavetable can be shared between all of a
+/// synth's voices. Every voice keeps track of its own phase in `[0, 1)`.
+///
+/// Creating a wavetable allocates and computes a discrete Fourier transform of the waveform, so it
+/// should be created ahead of time in
+/// [`Plugin::initialize()`][crate::prelude::Plugin::initialize()] and not on the audio thread.
+/// Reading from the wavetable is realtime-safe.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`
+/// self.wavetable = Wavetable::new(&saw_cycle);
+///
+/// // In `process()`
+/// for sample in channel_samples {
+///     *sample = self.wavetable.sample(self.phase, frequency, sample_rate);
+///     self.phase = (self.phase + frequency / sample_rate).fract();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Wavetable {
+    /// The number of samples in a single cycle.
+    table_size: usize,
+    /// The maximum harmonic number contained in each level, halving with every level.
+    max_harmonics: Vec<usize>,
+    /// The band-limited versions of the waveform. Each level contains `table_size + 1` samples,
+    /// where the last sample is a copy of the first sample so reads can interpolate across the
+    /// end of the cycle without wrapping.
+    levels: Vec<Vec<f32>>,
+}
+
+impl Wavetable {
+    /// Create a wavetable from a single cycle of a waveform. The table size is the waveform's
+    /// length, which should be at least a couple hundred samples so the lower levels contain
+    /// enough harmonics for low notes. 2048 samples is a common choice.
+    pub fn new(waveform: &[f32]) -> Self {
+        nih_debug_assert!(waveform.len() >= 4);
+        let table_size = waveform.len().max(1);
+
+        // A single period of a cosine and a sine, indexed by `(harmonic * n) % table_size`
+        let (cosines, sines): (Vec<f64>, Vec<f64>) = (0..table_size)
+            .map(|n| (f64::consts::TAU * n as f64 / table_size as f64).sin_cos())
+            .map(|(sin, cos)| (cos, sin))
+            .unzip();
+        let cosine = |index: usize| cosines[index % table_size];
+        let sine = |index: usize| sines[index % table_size];
+
+        // Harmonics at or above the table's own Nyquist frequency cannot be represented
+        let max_harmonic = ((table_size - 1) / 2).max(1);
+        let dc = waveform.iter().map(|&x| x as f64).sum::<f64>() / table_size as f64;
+        let spectrum: Vec<(f64, f64)> = (1..=max_harmonic)
+            .map(|harmonic| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (n, &x) in waveform.iter().enumerate() {
+                    re += x as f64 * cosine(harmonic * n);
+                    im -= x as f64 * sine(harmonic * n);
+                }
+
+                (re * 2.0 / table_size as f64, im * 2.0 / table_size as f64)
+            })
+            .collect();
+
+        let mut max_harmonics = Vec::new();
+        let mut levels = Vec::new();
+        let mut level_harmonics = max_harmonic;
+        loop {
+            let mut level: Vec<f32> = (0..table_size)
+                .map(|n| {
+                    let mut sample = dc;
+                    for (harmonic, &(re, im)) in (1..=level_harmonics).zip(&spectrum) {
+                        sample += re * cosine(harmonic * n) - im * sine(harmonic * n);
+                    }
+
+                    sample as f32
+                })
+                .collect();
+            level.push(level[0]);
+
+            max_harmonics.push(level_harmonics);
+            levels.push(level);
+            if level_harmonics <= 1 {
+                break;
+            }
+            level_harmonics /= 2;
+        }
+
+        Self {
+            table_size,
+            max_harmonics,
+            levels,
+        }
+    }
+
+    /// The number of samples in a single cycle.
+    pub fn table_size(&self) -> usize {
+        self.table_size
+    }
+
+    /// The number of band-limited levels.
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The highest harmonic contained in a level.
+    pub fn max_harmonic(&self, level: usize) -> usize {
+        self.max_harmonics[level]
+    }
+
+    /// A single cycle of one of the band-limited levels, with the first sample repeated at the end.
+    pub fn level(&self, level: usize) -> &[f32] {
+        &self.levels[level]
+    }
+
+    /// Get the level with the most harmonics that can be played back at `frequency` Hz without any
+    /// of its harmonics exceeding the Nyquist frequency. Frequencies above the Nyquist frequency
+    /// use the last level, which only contains the fundamental.
+    pub fn level_for_frequency(&self, frequency: f32, sample_rate: f32) -> usize {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        let harmonics_below_nyquist = (sample_rate / 2.0 / frequency.abs()).floor();
+        self.max_harmonics
+            .iter()
+            .position(|&max_harmonic| max_harmonic as f32 <= harmonics_below_nyquist)
+            .unwrap_or(self.levels.len() - 1)
+    }
+
+    /// Read the wavetable at `phase` in `[0, 1)` for a waveform played back at `frequency` Hz,
+    /// using the level picked by [`level_for_frequency()`][Self::level_for_frequency()]. The
+    /// samples are linearly interpolated.
+    #[inline]
+    pub fn sample(&self, phase: f32, frequency: f32, sample_rate: f32) -> f32 {
+        self.sample_level(self.level_for_frequency(frequency, sample_rate), phase)
+    }
+
+    /// Read a specific level of the wavetable at `phase` in `[0, 1)`. Phases outside of that
+    /// range wrap around. The samples are linearly interpolated.
+    #[inline]
+    pub fn sample_level(&self, level: usize, phase: f32) -> f32 {
+        let level = &self.levels[level];
+        let position = (phase - phase.floor()) * self.table_size as f32;
+        let index = (position as usize).min(self.table_size - 1);
+        let t = position - index as f32;
+
+        lerp(level[index], level[index + 1], t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    fn sawtooth(table_size: usize) -> Vec<f32> {
+        (0..table_size)
+            .map(|n| (2.0 * n as f32 / table_size as f32) - 1.0)
+            .collect()
+    }
+
+    /// The fraction of `signal`'s energy contained in the bins for the first harmonics of
+    /// `frequency`. The signal should contain a whole number of periods.
+    fn harmonic_energy_fraction(signal: &[f32], frequency: f32) -> f32 {
+        let num_samples = signal.len() as f64;
+        let total_energy: f64 = signal.iter().map(|&x| (x as f64).powi(2)).sum();
+
+        let mut harmonic_energy = 0.0;
+        let mut harmonic_freq = frequency;
+        while harmonic_freq < SAMPLE_RATE / 2.0 {
+            let bin = (harmonic_freq / SAMPLE_RATE) as f64 * num_samples;
+            let (mut re, mut im) = (0.0, 0.0);
+            for (n, &x) in signal.iter().enumerate() {
+                let phase = f64::consts::TAU * bin * n as f64 / num_samples;
+                re += x as f64 * phase.cos();
+                im -= x as f64 * phase.sin();
+            }
+            // Both the positive and the negative frequency bins
+            harmonic_energy += 2.0 * (re * re + im * im) / num_samples;
+
+            harmonic_freq += frequency;
+        }
+
+        (harmonic_energy / total_energy) as f32
+    }
+
+    #[test]
+    fn levels_halve_harmonics() {
+        let wavetable = Wavetable::new(&sawtooth(2048));
+        assert_eq!(wavetable.num_levels(), 10);
+        assert_eq!(wavetable.max_harmonic(0), 1023);
+        assert_eq!(wavetable.max_harmonic(9), 1);
+        assert_eq!(wavetable.level(0).len(), 2049);
+
+        // Low notes use the full spectrum, higher notes use fewer harmonics
+        assert_eq!(wavetable.level_for_frequency(20.0, SAMPLE_RATE), 0);
+        assert_eq!(wavetable.level_for_frequency(5000.0, SAMPLE_RATE), 8);
+        assert_eq!(wavetable.level_for_frequency(30_000.0, SAMPLE_RATE), 9);
+
+        // The last level is a pure sine wave with the sawtooth's fundamental's amplitude. The
+        // sampled sawtooth has a tiny DC offset and cosine component, hence the tolerance.
+        let sine = wavetable.level(9);
+        for (n, &sample) in sine.iter().enumerate() {
+            let phase = std::f32::consts::TAU * n as f32 / 2048.0;
+            approx::assert_relative_eq!(
+                sample,
+                -2.0 / std::f32::consts::PI * phase.sin(),
+                epsilon = 5e-3
+            );
+        }
+    }
+
+    #[test]
+    fn high_frequency_playback_is_band_limited() {
+        let wavetable = Wavetable::new(&sawtooth(2048));
+
+        // 0.1 seconds at 5 kHz is exactly 500 periods
+        let frequency = 5000.0;
+        let level = wavetable.level_for_frequency(frequency, SAMPLE_RATE);
+        let render = |level: usize| -> Vec<f32> {
+            let mut phase = 0.0f32;
+            (0..4410)
+                .map(|_| {
+                    let sample = wavetable.sample_level(level, phase);
+                    phase = (phase + frequency / SAMPLE_RATE).fract();
+                    sample
+                })
+                .collect()
+        };
+
+        let band_limited = harmonic_energy_fraction(&render(level), frequency);
+        let naive = harmonic_energy_fraction(&render(0), frequency);
+        assert!(band_limited > 0.99, "{band_limited}");
+        assert!(naive < 0.95, "{naive}");
+        assert_eq!(
+            wavetable.sample(0.3, frequency, SAMPLE_RATE),
+            wavetable.sample_level(level, 0.3)
+        );
+    }
+}
@@ -48,6 +48,110 @@ pub fn hann_in_place(window: &mut [f32]) {
     }
 }
 
+/// A window function that can generate windows of any size. This is useful when the window
+/// function is user configurable. The [`fill()`][Self::fill()] method generates symmetric windows,
+/// just like [`hann()`] and [`blackman()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunction {
+    /// A rectangular window, which is the same as not applying any windowing at all.
+    Rectangular,
+    /// A Hann window. See [`hann()`].
+    Hann,
+    /// A Hamming window. This is similar to the Hann window, but it doesn't go all the way down to
+    /// zero at the edges.
+    ///
+    /// <https://en.wikipedia.org/wiki/Window_function#Hann_and_Hamming_windows>
+    Hamming,
+    /// A Blackman window with the 'standard' coefficients. See [`blackman()`].
+    Blackman,
+    /// A four term Blackman-Harris window. This has much lower sidelobes than the Blackman window
+    /// at the cost of a wider main lobe.
+    ///
+    /// <https://en.wikipedia.org/wiki/Window_function#Blackman%E2%80%93Harris_window>
+    BlackmanHarris,
+    /// A Kaiser window with the specified beta parameter. Higher beta values result in lower
+    /// sidelobes and a wider main lobe. A beta of 0 results in a rectangular window.
+    ///
+    /// <https://en.wikipedia.org/wiki/Kaiser_window>
+    Kaiser(f32),
+}
+
+impl WindowFunction {
+    /// Fill a slice with this window function. The window's size is the length of the slice. This
+    /// does not allocate.
+    pub fn fill(&self, window: &mut [f32]) {
+        let size = window.len();
+        for (i, sample) in window.iter_mut().enumerate() {
+            *sample = self.sample(i, size);
+        }
+    }
+
+    /// The same as [`fill()`][Self::fill()], but allocating a new vector instead.
+    pub fn generate(&self, size: usize) -> Vec<f32> {
+        let mut window = vec![0.0; size];
+        self.fill(&mut window);
+
+        window
+    }
+
+    /// The window's coherent gain for a window of `size` samples. This is the window's average
+    /// value, or the amount a sine wave's amplitude gets scaled by after windowing. Divide the
+    /// magnitudes of an FFT's bins by this value times the window's size to get the original
+    /// amplitudes back.
+    pub fn coherent_gain(&self, size: usize) -> f32 {
+        if size == 0 {
+            return 0.0;
+        }
+
+        let sum: f32 = (0..size).map(|i| self.sample(i, size)).sum();
+        sum / size as f32
+    }
+
+    /// Compute the window's value for sample `i` of a window of `size` samples.
+    fn sample(&self, i: usize, size: usize) -> f32 {
+        // A window of a single sample would otherwise divide by zero
+        if size <= 1 {
+            return 1.0;
+        }
+
+        // This goes from 0 to 1 over the length of the window
+        let t = i as f32 / (size - 1) as f32;
+        let cos_1 = (f32::consts::TAU * t).cos();
+        let cos_2 = (2.0 * f32::consts::TAU * t).cos();
+        let cos_3 = (3.0 * f32::consts::TAU * t).cos();
+        match *self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => 0.5 - (0.5 * cos_1),
+            WindowFunction::Hamming => 0.54 - (0.46 * cos_1),
+            WindowFunction::Blackman => 0.42 - (0.5 * cos_1) + (0.08 * cos_2),
+            WindowFunction::BlackmanHarris => {
+                0.35875 - (0.48829 * cos_1) + (0.14128 * cos_2) - (0.01168 * cos_3)
+            }
+            WindowFunction::Kaiser(beta) => {
+                let x = 2.0 * t - 1.0;
+                bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+        }
+    }
+}
+
+/// The zeroth order modified Bessel function of the first kind, needed for the Kaiser window. This
+/// uses the function's power series, which converges quickly for the beta values used in practice.
+fn bessel_i0(x: f32) -> f32 {
+    let half_x = x / 2.0;
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..50 {
+        term *= (half_x / k as f32).powi(2);
+        sum += term;
+        if term < sum * 1e-9 {
+            break;
+        }
+    }
+
+    sum
+}
+
 /// Multiply a buffer with a window function.
 #[inline]
 pub fn multiply_with_window(buffer: &mut [f32], window_function: &[f32]) {
@@ -56,3 +160,89 @@ pub fn multiply_with_window(buffer: &mut [f32], window_function: &[f32]) {
         *sample *= window_sample;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_FUNCTIONS: [WindowFunction; 6] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::Kaiser(8.6),
+    ];
+
+    #[test]
+    fn symmetric() {
+        for window_function in WINDOW_FUNCTIONS {
+            for size in [16, 17] {
+                let window = window_function.generate(size);
+                for (a, b) in window.iter().zip(window.iter().rev()) {
+                    approx::assert_relative_eq!(*a, *b, epsilon = 1e-6);
+                }
+
+                // Every window peaks at the center
+                if size % 2 == 1 {
+                    approx::assert_relative_eq!(window[size / 2], 1.0, epsilon = 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn endpoints() {
+        let expected = [1.0, 0.0, 0.08, 0.0, 0.00006, 1.0 / bessel_i0(8.6)];
+        for (window_function, expected) in WINDOW_FUNCTIONS.into_iter().zip(expected) {
+            let window = window_function.generate(65);
+            approx::assert_relative_eq!(window[0], expected, epsilon = 1e-6);
+            approx::assert_relative_eq!(window[64], expected, epsilon = 1e-6);
+        }
+
+        // A Kaiser window with a beta of 0 is rectangular
+        assert!(WindowFunction::Kaiser(0.0)
+            .generate(8)
+            .iter()
+            .all(|&sample| sample == 1.0));
+    }
+
+    #[test]
+    fn matches_existing_windows() {
+        let mut window = [0.0; 32];
+        WindowFunction::Hann.fill(&mut window);
+        for (a, b) in window.iter().zip(hann(32)) {
+            approx::assert_relative_eq!(*a, b, epsilon = 1e-6);
+        }
+
+        WindowFunction::Blackman.fill(&mut window);
+        for (a, b) in window.iter().zip(blackman(32)) {
+            approx::assert_relative_eq!(*a, b, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn coherent_gain() {
+        approx::assert_relative_eq!(WindowFunction::Rectangular.coherent_gain(1024), 1.0);
+        approx::assert_relative_eq!(
+            WindowFunction::Hann.coherent_gain(1024),
+            0.5,
+            epsilon = 1e-3
+        );
+        approx::assert_relative_eq!(
+            WindowFunction::Hamming.coherent_gain(1024),
+            0.54,
+            epsilon = 1e-3
+        );
+        approx::assert_relative_eq!(
+            WindowFunction::Blackman.coherent_gain(1024),
+            0.42,
+            epsilon = 1e-3
+        );
+        approx::assert_relative_eq!(
+            WindowFunction::BlackmanHarris.coherent_gain(1024),
+            0.35875,
+            epsilon = 1e-3
+        );
+    }
+}
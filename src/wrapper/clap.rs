@@ -14,20 +14,28 @@ pub use clap_sys::factory::plugin_factory::CLAP_PLUGIN_FACTORY_ID;
 pub use clap_sys::version::CLAP_VERSION;
 pub use lazy_static::lazy_static;
 
-/// Export a CLAP plugin from this library using the provided plugin type.
+/// Export one or more CLAP plugins from this library using the provided plugin types. Every type
+/// is exposed to the host as a separate plugin, so alternate variants of a plugin, like a mono and
+/// a stereo version, can be exported from the same library by passing multiple types. These
+/// plugins need to have unique CLAP IDs.
+///
+/// ```ignore
+/// nih_export_clap!(MyPluginMono, MyPluginStereo);
+/// ```
 #[macro_export]
 macro_rules! nih_export_clap {
-    ($plugin_ty:ty) => {
-        // We need a function pointer to a [wrapper::get_factory()] that creates a factory for `$plugin_ty`, so we need to generate the function inside of this macro
+    ($($plugin_ty:ty),+ $(,)?) => {
+        // We need a function pointer to a [wrapper::get_factory()] that creates a factory for the plugin types, so we need to generate the function inside of this macro
         #[doc(hidden)]
         mod clap {
-            // Because `$plugin_ty` is likely defined in the enclosing scope
+            // Because the plugin types are likely defined in the enclosing scope
             use super::*;
 
             // We don't use generics inside of statics, so this lazy_static is used as kind of an
             // escape hatch
             ::nih_plug::wrapper::clap::lazy_static! {
-                static ref FACTORY: ::nih_plug::wrapper::clap::Factory<$plugin_ty> = ::nih_plug::wrapper::clap::Factory::default();
+                static ref FACTORY: ::nih_plug::wrapper::clap::Factory = ::nih_plug::wrapper::clap::Factory::default()
+                    $(.with_plugin::<$plugin_ty>())+;
             }
 
             pub extern "C" fn init(_plugin_path: *const ::std::os::raw::c_char) -> bool {
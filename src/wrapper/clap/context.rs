@@ -11,10 +11,13 @@ use std::sync::Arc;
 use super::wrapper::{OutputParamEvent, Task, Wrapper};
 use crate::event_loop::EventLoop;
 use crate::prelude::{
-    ClapPlugin, GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, ProcessContext,
-    RemoteControlsContext, RemoteControlsPage, RemoteControlsSection, Transport,
+    ChainHint, ChannelCounts, ClapPlugin, EventsIter, GuiContext, HostTimerCallback, InitContext,
+    ParamChangeSource, ParamIndication, ParamPtr, PluginApi, PluginNoteEvent, PosixFdCallback,
+    PosixFdFlags, ProcessContext, RawHostHandle, RemoteControlsContext, RemoteControlsPage,
+    RemoteControlsSection, Transport,
 };
-use crate::wrapper::util::strlcpy;
+use crate::wrapper::util::buffer_management::ScratchBuffers;
+use crate::wrapper::util::{strlcpy, InputEventPorts};
 
 /// An [`InitContext`] implementation for the wrapper.
 ///
@@ -41,8 +44,11 @@ pub(crate) struct PendingInitContextRequests {
 pub(crate) struct WrapperProcessContext<'a, P: ClapPlugin> {
     pub(super) wrapper: &'a Wrapper<P>,
     pub(super) input_events_guard: AtomicRefMut<'a, VecDeque<PluginNoteEvent<P>>>,
+    pub(super) input_event_ports_guard: AtomicRefMut<'a, InputEventPorts>,
     pub(super) output_events_guard: AtomicRefMut<'a, VecDeque<PluginNoteEvent<P>>>,
+    pub(super) scratch_buffers_guard: AtomicRefMut<'a, ScratchBuffers>,
     pub(super) transport: Transport,
+    pub(super) block_start_samples: i64,
 }
 
 /// A [`GuiContext`] implementation for the wrapper. This is passed to the plugin in
@@ -90,6 +96,26 @@ impl<P: ClapPlugin> InitContext<P> for WrapperInitContext<'_, P> {
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
+
+    fn chain_hint(&self) -> ChainHint {
+        self.wrapper.chain_hint()
+    }
+
+    fn request_scratch_buffers(&self, count: usize) {
+        self.wrapper.request_scratch_buffers(count)
+    }
+
+    fn register_host_timer(&self, period_ms: u32, callback: HostTimerCallback) -> bool {
+        self.wrapper.register_host_timer(period_ms, callback)
+    }
+
+    fn register_posix_fd(&self, fd: i32, flags: PosixFdFlags, callback: PosixFdCallback) -> bool {
+        self.wrapper.register_posix_fd(fd, flags, callback)
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.wrapper.raw_host_handle()
+    }
 }
 
 impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
@@ -112,8 +138,31 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         &self.transport
     }
 
+    #[inline]
+    fn block_start_samples(&self) -> i64 {
+        self.block_start_samples
+    }
+
+    fn channel_counts(&self) -> ChannelCounts {
+        self.wrapper.channel_counts()
+    }
+
+    fn scratch_buffers(&mut self) -> &mut [Box<[f32]>] {
+        self.scratch_buffers_guard.as_mut_slice()
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
-        self.input_events_guard.pop_front()
+        self.next_event_with_port().map(|(event, _)| event)
+    }
+
+    fn next_event_with_port(&mut self) -> Option<(PluginNoteEvent<P>, u32)> {
+        let event = self.input_events_guard.pop_front()?;
+        Some((event, self.input_event_ports_guard.pop_front()))
+    }
+
+    fn events(&self) -> EventsIter<'_, PluginNoteEvent<P>> {
+        let (first, second) = self.input_events_guard.as_slices();
+        EventsIter::new(first, second)
     }
 
     fn send_event(&mut self, event: PluginNoteEvent<P>) {
@@ -127,6 +176,46 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
+
+    fn request_restart(&self) {
+        self.wrapper.request_restart()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.wrapper.raw_host_handle()
+    }
+
+    unsafe fn raw_set_parameter_automated(
+        &mut self,
+        param: ParamPtr,
+        sample_offset: u32,
+        normalized: f32,
+    ) {
+        match self.wrapper.param_ptr_to_hash.get(&param) {
+            Some(hash) => {
+                // Like with changes made from the editor, the parameter's value is only updated
+                // when this event is written to the host's output queue at the end of the block
+                let clap_plain_value = normalized as f64 * param.step_count().unwrap_or(1) as f64;
+                let success =
+                    self.wrapper
+                        .queue_process_parameter_event(OutputParamEvent::SetValue {
+                            param_hash: *hash,
+                            clap_plain_value,
+                            timing: sample_offset,
+                        });
+                self.wrapper
+                    .param_write_locks
+                    .plugin_wrote(hash, normalized);
+
+                nih_debug_assert!(
+                    success,
+                    "Parameter output event queue was full, parameter change will not be sent to \
+                     the host"
+                );
+            }
+            None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+        }
+    }
 }
 
 impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
@@ -146,6 +235,7 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::BeginGesture { param_hash: *hash });
+                self.wrapper.param_gestures.begin(hash);
 
                 nih_debug_assert!(
                     success,
@@ -182,7 +272,14 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                     .queue_parameter_event(OutputParamEvent::SetValue {
                         param_hash: *hash,
                         clap_plain_value,
+                        timing: 0,
                     });
+                self.wrapper
+                    .param_change_sources
+                    .editor_changed(hash, normalized);
+                self.wrapper
+                    .param_write_locks
+                    .plugin_wrote(hash, normalized);
 
                 nih_debug_assert!(
                     success,
@@ -211,6 +308,7 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::EndGesture { param_hash: *hash });
+                self.wrapper.param_gestures.end(hash);
 
                 nih_debug_assert!(
                     success,
@@ -233,6 +331,26 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
         }
     }
 
+    unsafe fn raw_take_param_change_source(&self, param: ParamPtr) -> Option<ParamChangeSource> {
+        match self.wrapper.param_ptr_to_hash.get(&param) {
+            Some(hash) => self.wrapper.param_change_sources.take(hash),
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                None
+            }
+        }
+    }
+
+    unsafe fn raw_param_indication(&self, param: ParamPtr) -> Option<ParamIndication> {
+        match self.wrapper.param_ptr_to_hash.get(&param) {
+            Some(hash) => self.wrapper.param_indications.get(*hash),
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                None
+            }
+        }
+    }
+
     fn get_state(&self) -> crate::wrapper::state::PluginState {
         self.wrapper.get_state_object()
     }
@@ -240,6 +358,44 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
         self.wrapper.set_state_object_from_gui(state)
     }
+
+    fn current_preset(&self) -> Option<usize> {
+        self.wrapper.factory_presets.current()
+    }
+
+    fn set_preset(&self, index: usize) -> bool {
+        self.wrapper
+            .factory_presets
+            .load(index, |state| self.wrapper.set_state_object_from_gui(state))
+    }
+
+    fn editor_state(&self, key: &str) -> Option<String> {
+        self.wrapper.editor_state.get(key)
+    }
+
+    fn set_editor_state(&self, key: &str, value: Option<String>) {
+        self.wrapper.editor_state.set(key, value)
+    }
+
+    fn transport(&self) -> Option<Transport> {
+        self.wrapper.transport_snapshot.load()
+    }
+
+    fn mark_state_dirty(&self) {
+        self.wrapper.mark_state_dirty()
+    }
+
+    fn request_param_rescan(&self) {
+        self.wrapper.request_param_rescan()
+    }
+
+    fn chain_hint(&self) -> ChainHint {
+        self.wrapper.chain_hint()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.wrapper.raw_host_handle()
+    }
 }
 
 /// A remote control section. The plugin can fill this with information for one or more pages.
@@ -4,7 +4,8 @@ use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::os::raw::c_char;
 
-use crate::prelude::ClapPlugin;
+use crate::prelude::{ClapFeature, ClapPlugin};
+use crate::wrapper::util::is_analyzer_plugin;
 
 /// A static descriptor for a plugin. This is used in both the descriptor and on the plugin object
 /// itself.
@@ -36,8 +37,14 @@ pub struct PluginDescriptor<P: ClapPlugin> {
 
 impl<P: ClapPlugin> Default for PluginDescriptor<P> {
     fn default() -> Self {
+        let clap_id = P::clap_id();
+        assert!(
+            is_valid_clap_id(&clap_id),
+            "{clap_id:?} is not a valid CLAP plugin ID"
+        );
+
         let mut descriptor = Self {
-            clap_id: CString::new(P::CLAP_ID).expect("`CLAP_ID` contained null bytes"),
+            clap_id: CString::new(clap_id).expect("`CLAP_ID` contained null bytes"),
             name: CString::new(P::NAME).expect("`NAME` contained null bytes"),
             vendor: CString::new(P::VENDOR).expect("`VENDOR` contained null bytes"),
             url: CString::new(P::URL).expect("`URL` contained null bytes"),
@@ -46,12 +53,14 @@ impl<P: ClapPlugin> Default for PluginDescriptor<P> {
                 .map(|url| CString::new(url).expect("`CLAP_MANUAL_URL` contained null bytes")),
             clap_support_url: P::CLAP_SUPPORT_URL
                 .map(|url| CString::new(url).expect("`CLAP_SUPPORT_URL` contained null bytes")),
-            clap_description: P::CLAP_DESCRIPTION.map(|description| {
-                CString::new(description).expect("`CLAP_DESCRIPTION` contained null bytes")
-            }),
-            clap_features: P::CLAP_FEATURES
-                .iter()
-                .map(|feat| feat.as_str())
+            // The plugin's about string is used as a fallback for the CLAP-specific description
+            clap_description: P::CLAP_DESCRIPTION.map(String::from).or_else(P::about).map(
+                |description| {
+                    CString::new(description).expect("The description contained null bytes")
+                },
+            ),
+            clap_features: clap_features::<P>()
+                .into_iter()
                 .map(|s| CString::new(s).expect("`CLAP_FEATURES` contained null bytes"))
                 .collect(),
 
@@ -115,3 +124,127 @@ impl<P: ClapPlugin> PluginDescriptor<P> {
         self.clap_id.as_c_str()
     }
 }
+
+/// The plugin's CLAP features. This adds the `analyzer` feature if all of the plugin's audio IO
+/// layouts are analyzer layouts.
+fn clap_features<P: ClapPlugin>() -> Vec<&'static str> {
+    let mut features: Vec<&'static str> =
+        P::CLAP_FEATURES.iter().map(ClapFeature::as_str).collect();
+    if is_analyzer_plugin(P::AUDIO_IO_LAYOUTS) && !P::CLAP_FEATURES.contains(&ClapFeature::Analyzer)
+    {
+        features.push(ClapFeature::Analyzer.as_str());
+    }
+
+    features
+}
+
+/// Check whether a plugin ID is non-empty and only consists of printable ASCII characters without
+/// any spaces.
+fn is_valid_clap_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|c| c.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::*;
+
+    struct WhiteLabel;
+
+    impl TestPluginConfig for WhiteLabel {
+        const NAME: &'static str = "White Label";
+        const CLAP_ID: &'static str = "com.moist-plugins-gmbh.white-label";
+
+        type Params = NoParams;
+
+        fn clap_id() -> String {
+            format!("{}.customer-a", Self::CLAP_ID)
+        }
+    }
+
+    type WhiteLabelPlugin = TestPlugin<WhiteLabel>;
+
+    #[test]
+    fn dynamic_clap_id() {
+        let descriptor = PluginDescriptor::<WhiteLabelPlugin>::default();
+        let expected = "com.moist-plugins-gmbh.white-label.customer-a";
+
+        assert_eq!(descriptor.clap_id().to_str(), Ok(expected));
+        let registered_id =
+            unsafe { CStr::from_ptr(descriptor.clap_plugin_descriptor().id) }.to_str();
+        assert_eq!(registered_id, Ok(expected));
+    }
+
+    #[test]
+    fn analyzer_feature() {
+        struct Analyzer;
+
+        impl TestPluginConfig for Analyzer {
+            const NAME: &'static str = "Analyzer";
+            const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect];
+            const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+                main_input_channels: Some(new_nonzero_u32(2)),
+                ..AudioIOLayout::const_default()
+            }];
+
+            type Params = NoParams;
+        }
+
+        type AnalyzerPlugin = TestPlugin<Analyzer>;
+
+        assert_eq!(
+            clap_features::<AnalyzerPlugin>(),
+            ["audio-effect", "analyzer"]
+        );
+        assert_eq!(clap_features::<WhiteLabelPlugin>(), [] as [&str; 0]);
+
+        // The feature also ends up in the descriptor passed to the host
+        let descriptor = PluginDescriptor::<AnalyzerPlugin>::default();
+        let features = unsafe { descriptor.clap_plugin_descriptor().features };
+        let registered_features: Vec<&str> = (0..)
+            .map(|i| unsafe { *features.add(i) })
+            .take_while(|feature| !feature.is_null())
+            .map(|feature| unsafe { CStr::from_ptr(feature) }.to_str().unwrap())
+            .collect();
+        assert_eq!(registered_features, ["audio-effect", "analyzer"]);
+    }
+
+    #[test]
+    fn about_string_as_description() {
+        struct About;
+
+        impl TestPluginConfig for About {
+            const NAME: &'static str = "About";
+
+            type Params = NoParams;
+
+            fn about() -> Option<String> {
+                Some(String::from("Makes things sound about right"))
+            }
+        }
+
+        type AboutPlugin = TestPlugin<About>;
+
+        let descriptor = PluginDescriptor::<AboutPlugin>::default();
+        let description =
+            unsafe { CStr::from_ptr(descriptor.clap_plugin_descriptor().description) }.to_str();
+        assert_eq!(description, Ok("Makes things sound about right"));
+        assert_eq!(
+            plugin_metadata::<AboutPlugin>().about.as_deref(),
+            Some("Makes things sound about right")
+        );
+
+        // Without an about string the description stays empty
+        let descriptor = PluginDescriptor::<WhiteLabelPlugin>::default();
+        assert!(descriptor.clap_plugin_descriptor().description.is_null());
+    }
+
+    #[test]
+    fn clap_id_validation() {
+        assert!(is_valid_clap_id("com.moist-plugins-gmbh.gain"));
+        assert!(!is_valid_clap_id(""));
+        assert!(!is_valid_clap_id("com.moist plugins.gain"));
+        assert!(!is_valid_clap_id("com.moist-plugins-gmbh.gäin"));
+    }
+}
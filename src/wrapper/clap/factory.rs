@@ -10,19 +10,49 @@ use super::descriptor::PluginDescriptor;
 use super::wrapper::Wrapper;
 use crate::prelude::ClapPlugin;
 
-/// The plugin's factory. Initialized using a lazy_static from the entry point's `get_factory()`
-/// function. From this point onwards we don't need to generate code with macros anymore.
+/// The plugin factory. Initialized using a lazy_static from the entry point's `get_factory()`
+/// function. From this point onwards we don't need to generate code with macros anymore. A single
+/// factory can contain multiple plugins, for instance mono and stereo variants of the same plugin.
+/// These are added using [`with_plugin()`][Self::with_plugin()].
 #[doc(hidden)]
 #[repr(C)]
-pub struct Factory<P: ClapPlugin> {
+pub struct Factory {
     // Keep the vtable as the first field so we can do a simple pointer cast. There's no data
     // pointer as the API expects this thing to be entirely static, which in our case it isn't.
     pub clap_plugin_factory: clap_plugin_factory,
 
-    plugin_descriptor: PluginDescriptor<P>,
+    /// The plugins exposed by this factory, in the order they were added.
+    plugins: Vec<Box<dyn FactoryPlugin>>,
 }
 
-impl<P: ClapPlugin> Default for Factory<P> {
+/// A type erased plugin that can be listed and created by a [`Factory`].
+trait FactoryPlugin: Send + Sync {
+    fn clap_plugin_descriptor(&self) -> &clap_plugin_descriptor;
+    fn clap_id(&self) -> &CStr;
+    /// Create a new instance of the plugin. The returned pointer is owned by the host.
+    unsafe fn create_plugin(&self, host: *const clap_host) -> *const clap_plugin;
+}
+
+impl<P: ClapPlugin> FactoryPlugin for PluginDescriptor<P> {
+    fn clap_plugin_descriptor(&self) -> &clap_plugin_descriptor {
+        PluginDescriptor::clap_plugin_descriptor(self)
+    }
+
+    fn clap_id(&self) -> &CStr {
+        PluginDescriptor::clap_id(self)
+    }
+
+    unsafe fn create_plugin(&self, host: *const clap_host) -> *const clap_plugin {
+        // Arc does not have a convenient leak function like Box, so this gets a bit awkward
+        // This pointer gets turned into an Arc and its reference count decremented in
+        // [Wrapper::destroy()]
+        (*Arc::into_raw(Wrapper::<P>::new(host)))
+            .clap_plugin
+            .as_ptr()
+    }
+}
+
+impl Default for Factory {
     fn default() -> Self {
         Self {
             clap_plugin_factory: clap_plugin_factory {
@@ -30,14 +60,31 @@ impl<P: ClapPlugin> Default for Factory<P> {
                 get_plugin_descriptor: Some(Self::get_plugin_descriptor),
                 create_plugin: Some(Self::create_plugin),
             },
-            plugin_descriptor: PluginDescriptor::default(),
+            plugins: Vec::new(),
         }
     }
 }
 
-impl<P: ClapPlugin> Factory<P> {
-    unsafe extern "C" fn get_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
-        1
+impl Factory {
+    /// Add a plugin to the factory. Every plugin needs to have a unique CLAP ID.
+    pub fn with_plugin<P: ClapPlugin>(mut self) -> Self {
+        let descriptor = PluginDescriptor::<P>::default();
+        assert!(
+            self.plugins
+                .iter()
+                .all(|plugin| plugin.clap_id() != descriptor.clap_id()),
+            "Multiple plugins use the CLAP ID {:?}",
+            descriptor.clap_id()
+        );
+
+        self.plugins.push(Box::new(descriptor));
+        self
+    }
+
+    unsafe extern "C" fn get_plugin_count(factory: *const clap_plugin_factory) -> u32 {
+        let factory = &*(factory as *const Self);
+
+        factory.plugins.len() as u32
     }
 
     unsafe extern "C" fn get_plugin_descriptor(
@@ -46,10 +93,9 @@ impl<P: ClapPlugin> Factory<P> {
     ) -> *const clap_plugin_descriptor {
         let factory = &*(factory as *const Self);
 
-        if index == 0 {
-            factory.plugin_descriptor.clap_plugin_descriptor()
-        } else {
-            ptr::null()
+        match factory.plugins.get(index as usize) {
+            Some(plugin) => plugin.clap_plugin_descriptor(),
+            None => ptr::null(),
         }
     }
 
@@ -60,16 +106,106 @@ impl<P: ClapPlugin> Factory<P> {
     ) -> *const clap_plugin {
         let factory = &*(factory as *const Self);
 
-        if !plugin_id.is_null() && CStr::from_ptr(plugin_id) == factory.plugin_descriptor.clap_id()
+        if plugin_id.is_null() {
+            return ptr::null();
+        }
+
+        let plugin_id = CStr::from_ptr(plugin_id);
+        match factory
+            .plugins
+            .iter()
+            .find(|plugin| plugin.clap_id() == plugin_id)
         {
-            // Arc does not have a convenient leak function like Box, so this gets a bit awkward
-            // This pointer gets turned into an Arc and its reference count decremented in
-            // [Wrapper::destroy()]
-            (*Arc::into_raw(Wrapper::<P>::new(host)))
-                .clap_plugin
-                .as_ptr()
+            Some(plugin) => plugin.create_plugin(host),
+            None => ptr::null(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::*;
+
+    /// A plugin with separate mono and stereo variants.
+    struct Variant<const CHANNELS: u32>;
+
+    type VariantPlugin<const CHANNELS: u32> = TestPlugin<Variant<CHANNELS>>;
+
+    const MONO_LAYOUTS: &[AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: Some(new_nonzero_u32(1)),
+        main_output_channels: Some(new_nonzero_u32(1)),
+        ..AudioIOLayout::const_default()
+    }];
+    const STEREO_LAYOUTS: &[AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: Some(new_nonzero_u32(2)),
+        main_output_channels: Some(new_nonzero_u32(2)),
+        ..AudioIOLayout::const_default()
+    }];
+
+    impl<const CHANNELS: u32> TestPluginConfig for Variant<CHANNELS> {
+        const NAME: &'static str = if CHANNELS == 1 {
+            "Gain (Mono)"
+        } else {
+            "Gain (Stereo)"
+        };
+        const CLAP_ID: &'static str = if CHANNELS == 1 {
+            "com.moist-plugins-gmbh.gain-mono"
+        } else {
+            "com.moist-plugins-gmbh.gain-stereo"
+        };
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = if CHANNELS == 1 {
+            MONO_LAYOUTS
         } else {
-            ptr::null()
+            STEREO_LAYOUTS
+        };
+
+        type Params = NoParams;
+    }
+
+    #[test]
+    fn multiple_plugins() {
+        let factory = Factory::default()
+            .with_plugin::<VariantPlugin<1>>()
+            .with_plugin::<VariantPlugin<2>>();
+        let clap_plugin_factory = &factory.clap_plugin_factory;
+
+        unsafe {
+            assert_eq!(Factory::get_plugin_count(clap_plugin_factory), 2);
+
+            let registered: Vec<(&str, &str)> = (0..2)
+                .map(|index| {
+                    let descriptor = &*Factory::get_plugin_descriptor(clap_plugin_factory, index);
+                    (
+                        CStr::from_ptr(descriptor.id).to_str().unwrap(),
+                        CStr::from_ptr(descriptor.name).to_str().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                registered,
+                [
+                    ("com.moist-plugins-gmbh.gain-mono", "Gain (Mono)"),
+                    ("com.moist-plugins-gmbh.gain-stereo", "Gain (Stereo)")
+                ]
+            );
+
+            assert!(Factory::get_plugin_descriptor(clap_plugin_factory, 2).is_null());
+            assert!(Factory::create_plugin(
+                clap_plugin_factory,
+                ptr::null(),
+                b"com.moist-plugins-gmbh.gain-surround\0".as_ptr() as *const c_char
+            )
+            .is_null());
         }
     }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_clap_ids() {
+        let _ = Factory::default()
+            .with_plugin::<VariantPlugin<1>>()
+            .with_plugin::<VariantPlugin<1>>();
+    }
 }
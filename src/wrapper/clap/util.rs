@@ -1,7 +1,53 @@
+use clap_sys::color::clap_color;
+use clap_sys::events::{
+    clap_event_header, clap_event_midi, clap_event_midi_sysex, clap_event_note,
+    clap_event_note_expression, clap_event_param_value, CLAP_CORE_EVENT_SPACE_ID,
+    CLAP_EVENT_IS_LIVE, CLAP_EVENT_MIDI, CLAP_EVENT_MIDI_SYSEX, CLAP_EVENT_NOTE_CHOKE,
+    CLAP_EVENT_NOTE_END, CLAP_EVENT_NOTE_EXPRESSION, CLAP_EVENT_NOTE_OFF, CLAP_EVENT_NOTE_ON,
+    CLAP_EVENT_PARAM_VALUE, CLAP_NOTE_EXPRESSION_BRIGHTNESS, CLAP_NOTE_EXPRESSION_EXPRESSION,
+    CLAP_NOTE_EXPRESSION_PAN, CLAP_NOTE_EXPRESSION_PRESSURE, CLAP_NOTE_EXPRESSION_TUNING,
+    CLAP_NOTE_EXPRESSION_VIBRATO, CLAP_NOTE_EXPRESSION_VOLUME,
+};
+use clap_sys::ext::draft::param_indication::{
+    CLAP_PARAM_INDICATION_AUTOMATION_NONE, CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING,
+    CLAP_PARAM_INDICATION_AUTOMATION_PLAYING, CLAP_PARAM_INDICATION_AUTOMATION_PRESENT,
+    CLAP_PARAM_INDICATION_AUTOMATION_RECORDING,
+};
+use clap_sys::ext::draft::track_info::{
+    clap_track_info, CLAP_TRACK_INFO_HAS_AUDIO_CHANNEL, CLAP_TRACK_INFO_HAS_TRACK_COLOR,
+    CLAP_TRACK_INFO_HAS_TRACK_NAME, CLAP_TRACK_INFO_IS_FOR_BUS, CLAP_TRACK_INFO_IS_FOR_MASTER,
+    CLAP_TRACK_INFO_IS_FOR_RETURN_TRACK,
+};
+use clap_sys::ext::note_ports::{CLAP_NOTE_DIALECT_CLAP, CLAP_NOTE_DIALECT_MIDI};
+use clap_sys::ext::params::{
+    clap_host_params, clap_param_info_flags, clap_param_rescan_flags, CLAP_PARAM_IS_AUTOMATABLE,
+    CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_MODULATABLE,
+    CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY, CLAP_PARAM_IS_STEPPED,
+    CLAP_PARAM_RESCAN_ALL, CLAP_PARAM_RESCAN_INFO, CLAP_PARAM_RESCAN_TEXT,
+    CLAP_PARAM_RESCAN_VALUES,
+};
+use clap_sys::ext::posix_fd_support::{clap_host_posix_fd_support, clap_posix_fd_flags};
+use clap_sys::ext::state::clap_host_state;
+use clap_sys::ext::timer_support::clap_host_timer_support;
+use clap_sys::host::clap_host;
+use clap_sys::id::{clap_id, CLAP_INVALID_ID};
+use clap_sys::plugin::clap_plugin;
 use clap_sys::stream::{clap_istream, clap_ostream};
-use std::mem::MaybeUninit;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io;
+use std::mem::{self, MaybeUninit};
 use std::ops::Deref;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use super::wrapper::OutputParamEvent;
+use crate::prelude::{
+    AudioIOLayout, BufferConfig, ChainHint, Editor, HostTimerCallback, IndicationColor, MidiConfig,
+    NoteEvent, ParamAutomationState, ParamFlags, ParamIndication, ParamMapping, PosixFdCallback,
+    PosixFdFlags, ProcessMode, SysExMessage, TrackKind,
+};
 
 /// Early exit out of a function with the specified return value when one of the passed pointers is
 /// null.
@@ -165,3 +211,1551 @@ pub fn write_stream(stream: &clap_ostream, slice: &[u8]) -> bool {
 
     true
 }
+
+/// Written in place of the state's length when the state is streamed to the host in chunks using
+/// [`ChunkedWriter`][crate::wrapper::state::ChunkedWriter]. Older versions of NIH-plug prefixed the
+/// state with its length instead, and those states can still be loaded.
+pub const CHUNKED_STATE_MARKER: u64 = u64::MAX;
+
+/// Adapts a `clap_ostream` to [`io::Write`]. Use [`write_stream()`] for writing entire slices
+/// directly.
+pub struct ClapOStream<'a>(pub &'a clap_ostream);
+
+impl io::Write for ClapOStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stream = self.0;
+        let bytes_written = unsafe_clap_call! {
+            stream=>write(stream, buf.as_ptr() as *const c_void, buf.len() as u64)
+        };
+        if bytes_written <= 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "Error or end of stream while writing to the stream",
+            ));
+        }
+
+        Ok(bytes_written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adapts a `clap_istream` to [`io::Read`]. Use [`read_stream()`] for filling entire slices
+/// directly.
+pub struct ClapIStream<'a>(pub &'a clap_istream);
+
+impl io::Read for ClapIStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = self.0;
+        let bytes_read = unsafe_clap_call! {
+            stream=>read(stream, buf.as_mut_ptr() as *mut c_void, buf.len() as u64)
+        };
+        if bytes_read < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Error while reading from the stream",
+            ));
+        }
+
+        Ok(bytes_read as usize)
+    }
+}
+
+/// Keeps track of the host timer used to drive an editor's redraws through the `timer-support`
+/// extension. See [`Editor::host_timer_interval_ms()`].
+#[derive(Debug)]
+pub struct EditorTimer {
+    /// The ID of the registered timer, or `CLAP_INVALID_ID` if no timer is registered.
+    timer_id: AtomicU32,
+}
+
+impl Default for EditorTimer {
+    fn default() -> Self {
+        Self {
+            timer_id: AtomicU32::new(CLAP_INVALID_ID),
+        }
+    }
+}
+
+impl EditorTimer {
+    /// Register a timer with the host for an editor that wants to be driven by the host's timers.
+    /// Returns `false` if the host does not support timers or if it refused to register the timer,
+    /// in which case the editor needs to drive its own redraws.
+    ///
+    /// # Safety
+    ///
+    /// `host` and `host_timer_support` must be the host's valid `clap_host` and
+    /// `clap_host_timer_support` objects, and this must be called from the main thread.
+    pub unsafe fn register(
+        &self,
+        host: *const clap_host,
+        host_timer_support: Option<&clap_host_timer_support>,
+        period_ms: u32,
+    ) -> bool {
+        let host_timer_support = match host_timer_support {
+            Some(host_timer_support) => host_timer_support,
+            None => return false,
+        };
+
+        // There should only ever be a single editor
+        self.unregister(host, Some(host_timer_support));
+
+        let mut timer_id = CLAP_INVALID_ID;
+        let success =
+            clap_call! { host_timer_support=>register_timer(host, period_ms, &mut timer_id) };
+        if success && timer_id != CLAP_INVALID_ID {
+            self.timer_id.store(timer_id, Ordering::SeqCst);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Unregister the editor's timer if one was registered.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as [`register()`][Self::register()] apply.
+    pub unsafe fn unregister(
+        &self,
+        host: *const clap_host,
+        host_timer_support: Option<&clap_host_timer_support>,
+    ) {
+        let timer_id = self.timer_id.swap(CLAP_INVALID_ID, Ordering::SeqCst);
+        match host_timer_support {
+            Some(host_timer_support) if timer_id != CLAP_INVALID_ID => {
+                clap_call! { host_timer_support=>unregister_timer(host, timer_id) };
+            }
+            _ => (),
+        }
+    }
+
+    /// Handle a timer callback from the host. If `timer_id` belongs to the editor's timer, then
+    /// [`Editor::on_host_timer()`] is called and this returns `true`.
+    pub fn on_timer(&self, timer_id: clap_id, editor: &dyn Editor) -> bool {
+        if timer_id != CLAP_INVALID_ID && timer_id == self.timer_id.load(Ordering::SeqCst) {
+            editor.on_host_timer();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Keeps track of the timers and file descriptors the plugin registered with the host's main thread
+/// event loop through [`InitContext::register_host_timer()`][crate::prelude::InitContext::register_host_timer()]
+/// and [`InitContext::register_posix_fd()`][crate::prelude::InitContext::register_posix_fd()]. The
+/// host calls back into the plugin through the `timer-support` and `posix-fd-support` extensions,
+/// and those calls are forwarded to the registered callbacks.
+#[derive(Default)]
+pub struct HostIo {
+    /// Registrations are only accepted while this is set. The wrapper sets this while the plugin
+    /// gets initialized during activation.
+    accepting_registrations: AtomicBool,
+    /// The plugin's timer callbacks, indexed by the host's timer IDs.
+    timers: Mutex<HashMap<clap_id, HostTimerCallback>>,
+    /// The plugin's file descriptor callbacks, indexed by their file descriptors.
+    fds: Mutex<HashMap<i32, PosixFdCallback>>,
+}
+
+impl HostIo {
+    /// Allow or disallow new registrations. See the
+    /// [`accepting_registrations`][Self::accepting_registrations] field.
+    pub fn set_accepting_registrations(&self, accepting: bool) {
+        self.accepting_registrations
+            .store(accepting, Ordering::SeqCst);
+    }
+
+    /// Register a timer with the host that calls `callback` every `period_ms` milliseconds.
+    /// Returns `false` if registrations are not currently accepted, if the host does not support
+    /// timers, or if it refused to register the timer.
+    ///
+    /// # Safety
+    ///
+    /// `host` and `host_timer_support` must be the host's valid `clap_host` and
+    /// `clap_host_timer_support` objects, and this must be called from the main thread.
+    pub unsafe fn register_timer(
+        &self,
+        host: *const clap_host,
+        host_timer_support: Option<&clap_host_timer_support>,
+        period_ms: u32,
+        callback: HostTimerCallback,
+    ) -> bool {
+        let host_timer_support = match host_timer_support {
+            Some(host_timer_support) if self.accepting_registrations.load(Ordering::SeqCst) => {
+                host_timer_support
+            }
+            _ => return false,
+        };
+
+        let mut timer_id = CLAP_INVALID_ID;
+        let success =
+            clap_call! { host_timer_support=>register_timer(host, period_ms, &mut timer_id) };
+        if success && timer_id != CLAP_INVALID_ID {
+            self.timers.lock().insert(timer_id, callback);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Register a file descriptor with the host so `callback` is called whenever one of the
+    /// events in `flags` occurs. Returns `false` if registrations are not currently accepted, if
+    /// the file descriptor was already registered, if the host does not support the
+    /// `posix-fd-support` extension, or if it refused to register the file descriptor.
+    ///
+    /// # Safety
+    ///
+    /// `host` and `host_posix_fd_support` must be the host's valid `clap_host` and
+    /// `clap_host_posix_fd_support` objects, and this must be called from the main thread.
+    pub unsafe fn register_fd(
+        &self,
+        host: *const clap_host,
+        host_posix_fd_support: Option<&clap_host_posix_fd_support>,
+        fd: i32,
+        flags: PosixFdFlags,
+        callback: PosixFdCallback,
+    ) -> bool {
+        let host_posix_fd_support = match host_posix_fd_support {
+            Some(host_posix_fd_support) if self.accepting_registrations.load(Ordering::SeqCst) => {
+                host_posix_fd_support
+            }
+            _ => return false,
+        };
+
+        let mut fds = self.fds.lock();
+        if fds.contains_key(&fd) {
+            nih_debug_assert_failure!("File descriptor {} was registered twice", fd);
+            return false;
+        }
+
+        let success = clap_call! { host_posix_fd_support=>register_fd(host, fd, flags.bits()) };
+        if success {
+            fds.insert(fd, callback);
+        }
+
+        success
+    }
+
+    /// Unregister all of the plugin's timers and file descriptors.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for [`register_timer()`][Self::register_timer()] and
+    /// [`register_fd()`][Self::register_fd()] apply.
+    pub unsafe fn unregister_all(
+        &self,
+        host: *const clap_host,
+        host_timer_support: Option<&clap_host_timer_support>,
+        host_posix_fd_support: Option<&clap_host_posix_fd_support>,
+    ) {
+        for (timer_id, _) in self.timers.lock().drain() {
+            if let Some(host_timer_support) = host_timer_support {
+                clap_call! { host_timer_support=>unregister_timer(host, timer_id) };
+            }
+        }
+        for (fd, _) in self.fds.lock().drain() {
+            if let Some(host_posix_fd_support) = host_posix_fd_support {
+                clap_call! { host_posix_fd_support=>unregister_fd(host, fd) };
+            }
+        }
+    }
+
+    /// Handle a timer callback from the host. If `timer_id` belongs to one of the plugin's timers,
+    /// then its callback is called and this returns `true`.
+    pub fn on_timer(&self, timer_id: clap_id) -> bool {
+        match self.timers.lock().get_mut(&timer_id) {
+            Some(callback) => {
+                callback();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handle a file descriptor callback from the host. If `fd` has been registered by the plugin,
+    /// then its callback is called with the events in `flags` and this returns `true`.
+    pub fn on_fd(&self, fd: i32, flags: clap_posix_fd_flags) -> bool {
+        match self.fds.lock().get_mut(&fd) {
+            Some(callback) => {
+                callback(PosixFdFlags::from_bits_truncate(flags));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Whether the host can embed the plugin's editor using the windowing API `api`. This is always
+/// `false` when the plugin does not have an editor. The wrapper only exposes the GUI extension when
+/// [`Plugin::editor()`][crate::prelude::Plugin::editor()] returned an editor, but some hosts query
+/// the extension's functions regardless, so the functions need to report that there's no GUI as
+/// well.
+pub fn is_gui_api_supported(editor: Option<&dyn Editor>, api: &CStr, is_floating: bool) -> bool {
+    // We don't do standalone floating windows
+    if editor.is_none() || is_floating {
+        return false;
+    }
+
+    #[cfg(all(target_family = "unix", not(target_os = "macos")))]
+    if api == clap_sys::ext::gui::CLAP_WINDOW_API_X11 {
+        return true;
+    }
+    #[cfg(target_os = "macos")]
+    if api == clap_sys::ext::gui::CLAP_WINDOW_API_COCOA {
+        return true;
+    }
+    #[cfg(target_os = "windows")]
+    if api == clap_sys::ext::gui::CLAP_WINDOW_API_WIN32 {
+        return true;
+    }
+
+    false
+}
+
+/// Forward the host's `clap_plugin_gui::show()` and `clap_plugin_gui::hide()` calls to an open
+/// editor using [`Editor::set_visible()`]. `editor` should be `None` if the editor is not currently
+/// open, in which case this returns `false`.
+pub fn set_editor_visible(editor: Option<&dyn Editor>, visible: bool) -> bool {
+    match editor {
+        Some(editor) => {
+            editor.set_visible(visible);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Tell the host that the plugin's state has changed through the `state` extension. Returns `false`
+/// if the host does not support the `state` extension.
+///
+/// # Safety
+///
+/// `host` and `host_state` must be the host's valid `clap_host` and `clap_host_state` objects, and
+/// this must be called from the main thread.
+pub unsafe fn mark_state_dirty(
+    host: *const clap_host,
+    host_state: Option<&clap_host_state>,
+) -> bool {
+    match host_state {
+        Some(host_state) => {
+            clap_call! { host_state=>mark_dirty(host) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Ask the host to rescan all of the plugin's parameters through the `params` extension. The CLAP
+/// specification only allows `CLAP_PARAM_RESCAN_ALL` while the plugin is deactivated, so when
+/// `is_activated` is set this rescans everything that can change while the plugin is activated
+/// instead. Returns `false` if the host does not support the `params` extension.
+///
+/// # Safety
+///
+/// `host` and `host_params` must be the host's valid `clap_host` and `clap_host_params` objects,
+/// and this must be called from the main thread.
+pub unsafe fn rescan_all_params(
+    host: *const clap_host,
+    host_params: Option<&clap_host_params>,
+    is_activated: bool,
+) -> bool {
+    let flags: clap_param_rescan_flags = if is_activated {
+        CLAP_PARAM_RESCAN_VALUES | CLAP_PARAM_RESCAN_TEXT | CLAP_PARAM_RESCAN_INFO
+    } else {
+        CLAP_PARAM_RESCAN_ALL
+    };
+
+    match host_params {
+        Some(host_params) => {
+            clap_call! { host_params=>rescan(host, flags) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// The parameter indications sent by the host through the `param-indication` extension, indexed by
+/// parameter hash. The editor reads these through
+/// [`ParamSetter::indication()`][crate::prelude::ParamSetter::indication()].
+#[derive(Debug, Default)]
+pub struct ParamIndications {
+    indications: Mutex<HashMap<clap_id, ParamIndication>>,
+}
+
+impl ParamIndications {
+    /// Handle a `clap_plugin_param_indication::set_mapping()` call from the host.
+    ///
+    /// # Safety
+    ///
+    /// `color`, `label`, and `description` must either be null or point to a valid color and valid
+    /// null terminated strings.
+    pub unsafe fn set_mapping(
+        &self,
+        param_hash: clap_id,
+        has_mapping: bool,
+        color: *const clap_color,
+        label: *const c_char,
+        description: *const c_char,
+    ) {
+        let mapping = if has_mapping {
+            Some(ParamMapping {
+                color: color_from_ptr(color),
+                label: string_from_ptr(label),
+                description: string_from_ptr(description),
+            })
+        } else {
+            None
+        };
+
+        self.update(param_hash, |indication| indication.mapping = mapping);
+    }
+
+    /// Handle a `clap_plugin_param_indication::set_automation()` call from the host.
+    ///
+    /// # Safety
+    ///
+    /// `color` must either be null or point to a valid color.
+    pub unsafe fn set_automation(
+        &self,
+        param_hash: clap_id,
+        automation_state: u32,
+        color: *const clap_color,
+    ) {
+        let automation = match automation_state {
+            CLAP_PARAM_INDICATION_AUTOMATION_NONE => ParamAutomationState::None,
+            CLAP_PARAM_INDICATION_AUTOMATION_PRESENT => ParamAutomationState::Present,
+            CLAP_PARAM_INDICATION_AUTOMATION_PLAYING => ParamAutomationState::Playing,
+            CLAP_PARAM_INDICATION_AUTOMATION_RECORDING => ParamAutomationState::Recording,
+            CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING => ParamAutomationState::Overriding,
+            n => {
+                nih_debug_assert_failure!("Unknown automation state {}", n);
+                ParamAutomationState::None
+            }
+        };
+        let automation_color = color_from_ptr(color);
+
+        self.update(param_hash, |indication| {
+            indication.automation = automation;
+            indication.automation_color = automation_color;
+        });
+    }
+
+    /// Get the current indication for a parameter, or `None` if the host has not sent any or if
+    /// the parameter no longer has a mapping or automation.
+    pub fn get(&self, param_hash: clap_id) -> Option<ParamIndication> {
+        self.indications.lock().get(&param_hash).cloned()
+    }
+
+    /// Modify a parameter's indication. Indications without any information are removed again.
+    fn update(&self, param_hash: clap_id, f: impl FnOnce(&mut ParamIndication)) {
+        let mut indications = self.indications.lock();
+        let indication = indications.entry(param_hash).or_default();
+        f(indication);
+
+        if indication.mapping.is_none() && indication.automation == ParamAutomationState::None {
+            indications.remove(&param_hash);
+        }
+    }
+}
+
+/// Convert a color from the `param-indication` or `track-info` extensions. Null pointers are
+/// converted to `None`.
+unsafe fn color_from_ptr(color: *const clap_color) -> Option<IndicationColor> {
+    color.as_ref().map(|color| IndicationColor {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha: color.alpha,
+    })
+}
+
+/// Convert a string from the `param-indication` extension. Null pointers are converted to empty
+/// strings.
+unsafe fn string_from_ptr(string: *const c_char) -> String {
+    if string.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(string).to_string_lossy().into_owned()
+    }
+}
+
+/// Convert the information from the host's `track-info` extension to a [`ChainHint`]. Fields the
+/// host did not set in `info.flags` are left unknown.
+pub fn chain_hint_from_track_info(info: &clap_track_info) -> ChainHint {
+    let track_kind = if info.flags & CLAP_TRACK_INFO_IS_FOR_MASTER != 0 {
+        TrackKind::Master
+    } else if info.flags & CLAP_TRACK_INFO_IS_FOR_RETURN_TRACK != 0 {
+        TrackKind::Return
+    } else if info.flags & CLAP_TRACK_INFO_IS_FOR_BUS != 0 {
+        TrackKind::Bus
+    } else {
+        TrackKind::Track
+    };
+
+    let track_name = if info.flags & CLAP_TRACK_INFO_HAS_TRACK_NAME != 0 {
+        // The name may not be null terminated if the host filled the entire buffer
+        let name_len = info
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(info.name.len());
+        let name_bytes: Vec<u8> = info.name[..name_len].iter().map(|&c| c as u8).collect();
+        Some(String::from_utf8_lossy(&name_bytes).into_owned())
+    } else {
+        None
+    };
+
+    let track_channel_count = if info.flags & CLAP_TRACK_INFO_HAS_AUDIO_CHANNEL != 0 {
+        u32::try_from(info.audio_channel_count).ok()
+    } else {
+        None
+    };
+
+    let track_color = if info.flags & CLAP_TRACK_INFO_HAS_TRACK_COLOR != 0 {
+        unsafe { color_from_ptr(&info.color) }
+    } else {
+        None
+    };
+
+    ChainHint {
+        track_kind,
+        track_name,
+        track_channel_count,
+        track_color,
+    }
+}
+
+/// Sort the parameter events output by the plugin by their timing. Like
+/// [`sort_output_events()`][crate::wrapper::util::sort_output_events()] this is a stable insertion
+/// sort, so gestures and value changes with the same timing stay in the order they were queued in.
+pub fn sort_output_param_events(events: &mut [OutputParamEvent]) {
+    for i in 1..events.len() {
+        let mut j = i;
+        while j > 0 && events[j - 1].timing() > events[j].timing() {
+            events.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+/// The last value the editor queued for each parameter that has not yet been sent to the host.
+/// This is used to apply the editor's parameter changes to the plugin before the host gets a chance
+/// to flush them, for instance when the host saves the plugin's state without flushing the
+/// parameters first. The host-bound event queue itself is left alone. The map is created up front,
+/// so recording and clearing values is realtime-safe.
+#[derive(Debug)]
+pub struct QueuedEditorValues {
+    /// The queued plain values' bits, or [`NO_QUEUED_VALUE`] if there is no queued value.
+    values: HashMap<clap_id, AtomicU64>,
+}
+
+/// Stored in [`QueuedEditorValues`] when a parameter has no queued value. This is a NaN, so it
+/// cannot be a valid parameter value.
+const NO_QUEUED_VALUE: u64 = u64::MAX;
+
+impl QueuedEditorValues {
+    /// Create the map for all of the plugin's parameters.
+    pub fn new(param_hashes: impl IntoIterator<Item = clap_id>) -> Self {
+        Self {
+            values: param_hashes
+                .into_iter()
+                .map(|param_hash| (param_hash, AtomicU64::new(NO_QUEUED_VALUE)))
+                .collect(),
+        }
+    }
+
+    /// Record that the editor queued a new value for a parameter. This should be called before the
+    /// event is pushed to the output queue so it can never be cleared before it has been set.
+    pub fn queued(&self, param_hash: clap_id, clap_plain_value: f64) {
+        if let Some(value) = self.values.get(&param_hash) {
+            value.store(clap_plain_value.to_bits(), Ordering::SeqCst);
+        }
+    }
+
+    /// Record that a queued value has been applied to the plugin and sent to the host, or that it
+    /// could not be queued. The value is only cleared if the editor has not queued a different
+    /// value for the parameter in the meantime.
+    pub fn dequeued(&self, param_hash: clap_id, clap_plain_value: f64) {
+        if let Some(value) = self.values.get(&param_hash) {
+            let _ = value.compare_exchange(
+                clap_plain_value.to_bits(),
+                NO_QUEUED_VALUE,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    /// Call `apply(param_hash, clap_plain_value)` for every parameter with a queued value. The
+    /// values stay queued until the corresponding events have been sent to the host.
+    pub fn apply(&self, mut apply: impl FnMut(clap_id, f64)) {
+        for (param_hash, value) in &self.values {
+            let value = value.load(Ordering::SeqCst);
+            if value != NO_QUEUED_VALUE {
+                apply(*param_hash, f64::from_bits(value));
+            }
+        }
+    }
+}
+
+/// Create the `CLAP_EVENT_PARAM_VALUE` event for a parameter value change output by the plugin.
+/// `time` is the event's timing within the host's buffer.
+pub fn output_param_value_event(
+    param_hash: clap_id,
+    clap_plain_value: f64,
+    time: u32,
+) -> clap_event_param_value {
+    clap_event_param_value {
+        header: clap_event_header {
+            size: mem::size_of::<clap_event_param_value>() as u32,
+            time,
+            space_id: CLAP_CORE_EVENT_SPACE_ID,
+            type_: CLAP_EVENT_PARAM_VALUE,
+            flags: CLAP_EVENT_IS_LIVE,
+        },
+        param_id: param_hash,
+        cookie: std::ptr::null_mut(),
+        port_index: -1,
+        note_id: -1,
+        channel: -1,
+        key: -1,
+        value: clap_plain_value,
+    }
+}
+
+/// The draft `configurable-audio-ports` extension's ID. Our `clap-sys` version doesn't include this
+/// extension yet, so the definitions are mirrored from `ext/draft/configurable-audio-ports.h`.
+pub const CLAP_EXT_CONFIGURABLE_AUDIO_PORTS: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"clap.configurable-audio-ports.draft1\0") };
+
+/// A request from the host to change a single audio port's channel count.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct clap_audio_port_configuration_request {
+    pub is_input: bool,
+    pub port_index: u32,
+    pub channel_count: u32,
+    pub port_type: *const c_char,
+    pub port_details: *const c_void,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct clap_plugin_configurable_audio_ports {
+    pub can_apply_configuration: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            requests: *const clap_audio_port_configuration_request,
+            request_count: u32,
+        ) -> bool,
+    >,
+    pub apply_configuration: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            requests: *const clap_audio_port_configuration_request,
+            request_count: u32,
+        ) -> bool,
+    >,
+}
+
+/// Find the audio IO layout from `layouts` that results from applying the host's port
+/// configuration `requests` to `current_layout`. Ports that are not part of the request keep their
+/// current channel counts. Returns `None` if a request refers to a port that doesn't exist, or if
+/// none of the plugin's layouts match the requested configuration.
+pub fn resolve_audio_port_configuration(
+    layouts: &[AudioIOLayout],
+    current_layout: &AudioIOLayout,
+    requests: &[clap_audio_port_configuration_request],
+) -> Option<AudioIOLayout> {
+    let mut input_channels = port_channel_counts(current_layout, true);
+    let mut output_channels = port_channel_counts(current_layout, false);
+    for request in requests {
+        let channels = if request.is_input {
+            &mut input_channels
+        } else {
+            &mut output_channels
+        };
+
+        *channels.get_mut(request.port_index as usize)? = request.channel_count;
+    }
+
+    layouts
+        .iter()
+        .find(|layout| {
+            port_channel_counts(layout, true) == input_channels
+                && port_channel_counts(layout, false) == output_channels
+        })
+        .copied()
+}
+
+/// The channel counts for all of a layout's input or output ports, using the same port order as
+/// the `audio-ports` extension. The main port, if it exists, always comes first.
+fn port_channel_counts(layout: &AudioIOLayout, is_input: bool) -> Vec<u32> {
+    let (main_channels, aux_ports) = if is_input {
+        (layout.main_input_channels, layout.aux_input_ports)
+    } else {
+        (layout.main_output_channels, layout.aux_output_ports)
+    };
+
+    main_channels
+        .iter()
+        .chain(aux_ports)
+        .map(|channels| channels.get())
+        .collect()
+}
+
+/// The index of the note port an incoming CLAP event arrived on. Events that are not tied to a note
+/// port, and note events sent to all ports with a port index of -1, are assigned to the first
+/// port.
+///
+/// # Safety
+///
+/// `event` must point to a valid event of the type indicated by its header.
+pub unsafe fn input_event_port(event: *const clap_event_header) -> u32 {
+    let port_index = match ((*event).space_id, (*event).type_) {
+        (
+            CLAP_CORE_EVENT_SPACE_ID,
+            CLAP_EVENT_NOTE_ON | CLAP_EVENT_NOTE_OFF | CLAP_EVENT_NOTE_CHOKE | CLAP_EVENT_NOTE_END,
+        ) => (*(event as *const clap_event_note)).port_index as i32,
+        (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_EXPRESSION) => {
+            (*(event as *const clap_event_note_expression)).port_index as i32
+        }
+        (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI) => {
+            (*(event as *const clap_event_midi)).port_index as i32
+        }
+        (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI_SYSEX) => {
+            (*(event as *const clap_event_midi_sysex)).port_index as i32
+        }
+        _ => 0,
+    };
+
+    port_index.max(0) as u32
+}
+
+/// The `(supported_dialects, preferred_dialect)` flags for a note port with the given
+/// [`MidiConfig`], or `None` if the config doesn't need a note port. Ports always support both
+/// CLAP note events and MIDI. Even plugins that only use [`MidiConfig::Basic`] need MIDI, since
+/// some hosts like REAPER only send SysEx messages to ports that support the MIDI dialect.
+/// `prefer_midi` comes from [`ClapPlugin::CLAP_PREFER_MIDI_NOTE_DIALECT`][crate::prelude::ClapPlugin::CLAP_PREFER_MIDI_NOTE_DIALECT].
+//
+// TODO: Implement MPE (would just be a toggle for the plugin to expose it) and MIDI2
+pub fn note_port_dialects(config: MidiConfig, prefer_midi: bool) -> Option<(u32, u32)> {
+    if config < MidiConfig::Basic {
+        return None;
+    }
+
+    let preferred_dialect = if prefer_midi {
+        CLAP_NOTE_DIALECT_MIDI
+    } else {
+        CLAP_NOTE_DIALECT_CLAP
+    };
+
+    Some((
+        CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI,
+        preferred_dialect,
+    ))
+}
+
+/// Translate a CLAP note expression event to a polyphonic expression event with the same timing.
+/// Every expression event from the host results in its own event, so continuous changes like a
+/// tuning glide reach the plugin as a series of sample accurate events. Returns `None` for unknown
+/// expression types.
+pub fn translate_note_expression<S: SysExMessage>(
+    timing: u32,
+    event: &clap_event_note_expression,
+) -> Option<NoteEvent<S>> {
+    let voice_id = if event.note_id != -1 {
+        Some(event.note_id)
+    } else {
+        None
+    };
+    let channel = event.channel as u8;
+    let note = event.key as u8;
+
+    match event.expression_id {
+        CLAP_NOTE_EXPRESSION_PRESSURE => Some(NoteEvent::PolyPressure {
+            timing,
+            voice_id,
+            channel,
+            note,
+            pressure: event.value as f32,
+        }),
+        CLAP_NOTE_EXPRESSION_VOLUME => Some(NoteEvent::PolyVolume {
+            timing,
+            voice_id,
+            channel,
+            note,
+            gain: event.value as f32,
+        }),
+        CLAP_NOTE_EXPRESSION_PAN => Some(NoteEvent::PolyPan {
+            timing,
+            voice_id,
+            channel,
+            note,
+            // In CLAP this value goes from [0, 1] instead of [-1, 1]
+            pan: (event.value as f32 * 2.0) - 1.0,
+        }),
+        CLAP_NOTE_EXPRESSION_TUNING => Some(NoteEvent::PolyTuning {
+            timing,
+            voice_id,
+            channel,
+            note,
+            tuning: event.value as f32,
+        }),
+        CLAP_NOTE_EXPRESSION_VIBRATO => Some(NoteEvent::PolyVibrato {
+            timing,
+            voice_id,
+            channel,
+            note,
+            vibrato: event.value as f32,
+        }),
+        CLAP_NOTE_EXPRESSION_EXPRESSION => Some(NoteEvent::PolyExpression {
+            timing,
+            voice_id,
+            channel,
+            note,
+            expression: event.value as f32,
+        }),
+        CLAP_NOTE_EXPRESSION_BRIGHTNESS => Some(NoteEvent::PolyBrightness {
+            timing,
+            voice_id,
+            channel,
+            note,
+            brightness: event.value as f32,
+        }),
+        n => {
+            nih_debug_assert_failure!("Unhandled note expression ID {}", n);
+            None
+        }
+    }
+}
+
+/// Create the buffer config from the arguments passed to `clap_plugin::activate()`. CLAP hosts
+/// don't have a separate preferred block size, but hosts that always process fixed size blocks
+/// pass the same minimum and maximum size, so that size is used as the preferred buffer size.
+pub fn buffer_config_from_activate(
+    sample_rate: f64,
+    min_frames_count: u32,
+    max_frames_count: u32,
+    process_mode: ProcessMode,
+) -> BufferConfig {
+    BufferConfig {
+        sample_rate: sample_rate as f32,
+        min_buffer_size: Some(min_frames_count),
+        max_buffer_size: max_frames_count,
+        preferred_buffer_size: (min_frames_count == max_frames_count).then_some(max_frames_count),
+        process_mode,
+    }
+}
+
+/// `CLAP_PARAM_IS_ENUM` from CLAP 1.2, which is not yet part of the bindings. Hosts that don't know
+/// about this flag simply ignore it.
+const CLAP_PARAM_IS_ENUM: clap_param_info_flags = 1 << 16;
+
+/// Compute the flags for a parameter's `clap_param_info`. `is_poly_modulatable` should be set when
+/// the plugin assigned a polyphonic modulation ID to the parameter. `is_enum` should be set for
+/// parameters that would like to be displayed as a dropdown, and it only has an effect for stepped
+/// parameters.
+pub fn param_info_flags(
+    flags: ParamFlags,
+    is_stepped: bool,
+    is_enum: bool,
+    is_poly_modulatable: bool,
+) -> clap_param_info_flags {
+    let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
+    let hidden = flags.contains(ParamFlags::HIDDEN);
+    let is_bypass = flags.contains(ParamFlags::BYPASS);
+    // Expensive parameters can still be automated, but hosts may modulate parameters at audio rate
+    let modulatable = !flags.contains(ParamFlags::EXPENSIVE);
+
+    // TODO: Somehow expose per note/channel/port modulation
+    let mut info_flags = 0;
+    if automatable && !hidden {
+        info_flags |= CLAP_PARAM_IS_AUTOMATABLE;
+        if modulatable {
+            info_flags |= CLAP_PARAM_IS_MODULATABLE;
+            if is_poly_modulatable {
+                info_flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
+            }
+        }
+    }
+    if hidden {
+        info_flags |= CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY;
+    }
+    if is_bypass {
+        info_flags |= CLAP_PARAM_IS_BYPASS
+    }
+    if is_stepped {
+        info_flags |= CLAP_PARAM_IS_STEPPED;
+        // Hosts can show enumerated parameters as a dropdown in their generic UIs
+        if is_enum {
+            info_flags |= CLAP_PARAM_IS_ENUM;
+        }
+    }
+
+    info_flags
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use std::num::NonZeroU32;
+
+    use clap_sys::events::CLAP_EVENT_NOTE_EXPRESSION;
+
+    use super::*;
+    use crate::prelude::{new_nonzero_u32, GuiContext, ParentWindowHandle};
+
+    const MOCK_TIMER_ID: clap_id = 42;
+
+    unsafe extern "C" fn mock_register_timer(
+        _host: *const clap_host,
+        _period_ms: u32,
+        timer_id: *mut clap_id,
+    ) -> bool {
+        *timer_id = MOCK_TIMER_ID;
+        true
+    }
+
+    unsafe extern "C" fn mock_unregister_timer(
+        _host: *const clap_host,
+        _timer_id: clap_id,
+    ) -> bool {
+        true
+    }
+
+    #[derive(Default)]
+    struct MockEditor {
+        redraws: AtomicUsize,
+        visibility_changes: Mutex<Vec<bool>>,
+    }
+
+    impl Editor for MockEditor {
+        fn spawn(
+            &self,
+            _parent: ParentWindowHandle,
+            _context: Arc<dyn GuiContext>,
+        ) -> Box<dyn Any + Send> {
+            Box::new(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            (100, 100)
+        }
+
+        fn set_scale_factor(&self, _factor: f32) -> bool {
+            false
+        }
+
+        fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+
+        fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+
+        fn param_values_changed(&self) {}
+
+        fn host_timer_interval_ms(&self) -> Option<u32> {
+            Some(16)
+        }
+
+        fn on_host_timer(&self) {
+            self.redraws.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn set_visible(&self, visible: bool) {
+            self.visibility_changes.lock().push(visible);
+        }
+    }
+
+    #[test]
+    fn editor_timer_drives_redraws() {
+        let host_timer_support = clap_host_timer_support {
+            register_timer: Some(mock_register_timer),
+            unregister_timer: Some(mock_unregister_timer),
+        };
+        let editor = MockEditor::default();
+        let timer = EditorTimer::default();
+
+        // Timer callbacks before the timer has been registered should be ignored
+        assert!(!timer.on_timer(MOCK_TIMER_ID, &editor));
+
+        assert!(unsafe { timer.register(std::ptr::null(), Some(&host_timer_support), 16) });
+        assert!(timer.on_timer(MOCK_TIMER_ID, &editor));
+        assert!(!timer.on_timer(MOCK_TIMER_ID + 1, &editor));
+        assert_eq!(editor.redraws.load(Ordering::SeqCst), 1);
+
+        unsafe { timer.unregister(std::ptr::null(), Some(&host_timer_support)) };
+        assert!(!timer.on_timer(MOCK_TIMER_ID, &editor));
+        assert_eq!(editor.redraws.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn editor_timer_unsupported_host() {
+        let timer = EditorTimer::default();
+        assert!(!unsafe { timer.register(std::ptr::null(), None, 16) });
+    }
+
+    unsafe extern "C" fn mock_register_fd(
+        _host: *const clap_host,
+        _fd: i32,
+        _flags: clap_posix_fd_flags,
+    ) -> bool {
+        true
+    }
+
+    unsafe extern "C" fn mock_modify_fd(
+        _host: *const clap_host,
+        _fd: i32,
+        _flags: clap_posix_fd_flags,
+    ) -> bool {
+        true
+    }
+
+    unsafe extern "C" fn mock_unregister_fd(_host: *const clap_host, _fd: i32) -> bool {
+        true
+    }
+
+    #[test]
+    fn host_io_timer_callback_fires() {
+        let host_timer_support = clap_host_timer_support {
+            register_timer: Some(mock_register_timer),
+            unregister_timer: Some(mock_unregister_timer),
+        };
+        let host_io = HostIo::default();
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let make_callback = || -> HostTimerCallback {
+            let ticks = ticks.clone();
+            Box::new(move || {
+                ticks.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        // Registrations are only accepted while the plugin is being activated
+        assert!(!unsafe {
+            host_io.register_timer(
+                std::ptr::null(),
+                Some(&host_timer_support),
+                100,
+                make_callback(),
+            )
+        });
+
+        host_io.set_accepting_registrations(true);
+        assert!(unsafe { host_io.register_timer(std::ptr::null(), None, 100, make_callback()) });
+        assert!(unsafe {
+            host_io.register_timer(
+                std::ptr::null(),
+                Some(&host_timer_support),
+                100,
+                make_callback(),
+            )
+        });
+        host_io.set_accepting_registrations(false);
+
+        assert!(host_io.on_timer(MOCK_TIMER_ID));
+        assert!(host_io.on_timer(MOCK_TIMER_ID));
+        assert!(!host_io.on_timer(MOCK_TIMER_ID + 1));
+        assert_eq!(ticks.load(Ordering::SeqCst), 2);
+
+        unsafe { host_io.unregister_all(std::ptr::null(), Some(&host_timer_support), None) };
+        assert!(!host_io.on_timer(MOCK_TIMER_ID));
+        assert_eq!(ticks.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn host_io_fd_callback_receives_flags() {
+        let host_posix_fd_support = clap_host_posix_fd_support {
+            register_fd: Some(mock_register_fd),
+            modify_fd: Some(mock_modify_fd),
+            unregister_fd: Some(mock_unregister_fd),
+        };
+        let host_io = HostIo::default();
+        let received_flags = Arc::new(Mutex::new(Vec::new()));
+
+        host_io.set_accepting_registrations(true);
+        let callback_flags = received_flags.clone();
+        assert!(unsafe {
+            host_io.register_fd(
+                std::ptr::null(),
+                Some(&host_posix_fd_support),
+                3,
+                PosixFdFlags::READ | PosixFdFlags::ERROR,
+                Box::new(move |flags| callback_flags.lock().push(flags)),
+            )
+        });
+        host_io.set_accepting_registrations(false);
+
+        assert!(host_io.on_fd(3, PosixFdFlags::READ.bits()));
+        assert!(!host_io.on_fd(4, PosixFdFlags::READ.bits()));
+        assert_eq!(*received_flags.lock(), [PosixFdFlags::READ]);
+    }
+
+    #[test]
+    fn editor_visibility_follows_show_and_hide() {
+        let editor = MockEditor::default();
+
+        assert!(set_editor_visible(Some(&editor), false));
+        assert!(set_editor_visible(Some(&editor), true));
+        assert_eq!(*editor.visibility_changes.lock(), [false, true]);
+
+        // Show and hide calls without an open editor are rejected
+        assert!(!set_editor_visible(None, false));
+        assert_eq!(*editor.visibility_changes.lock(), [false, true]);
+    }
+
+    #[test]
+    fn no_gui_without_editor() {
+        #[cfg(all(target_family = "unix", not(target_os = "macos")))]
+        let native_api = clap_sys::ext::gui::CLAP_WINDOW_API_X11;
+        #[cfg(target_os = "macos")]
+        let native_api = clap_sys::ext::gui::CLAP_WINDOW_API_COCOA;
+        #[cfg(target_os = "windows")]
+        let native_api = clap_sys::ext::gui::CLAP_WINDOW_API_WIN32;
+
+        let editor = MockEditor::default();
+        assert!(is_gui_api_supported(Some(&editor), native_api, false));
+        assert!(!is_gui_api_supported(Some(&editor), native_api, true));
+
+        // A plugin whose `editor()` returned `None` must not report support for any API
+        assert!(!is_gui_api_supported(None, native_api, false));
+        assert!(!is_gui_api_supported(None, native_api, true));
+    }
+
+    static MOCK_DIRTY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn mock_mark_dirty(_host: *const clap_host) {
+        MOCK_DIRTY_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn state_dirty_reaches_host() {
+        let host_state = clap_host_state {
+            mark_dirty: Some(mock_mark_dirty),
+        };
+
+        assert!(unsafe { mark_state_dirty(std::ptr::null(), Some(&host_state)) });
+        assert_eq!(MOCK_DIRTY_COUNT.load(Ordering::SeqCst), 1);
+
+        // Hosts without the state extension are not notified
+        assert!(!unsafe { mark_state_dirty(std::ptr::null(), None) });
+        assert_eq!(MOCK_DIRTY_COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    static MOCK_RESCAN_FLAGS: AtomicU32 = AtomicU32::new(0);
+
+    unsafe extern "C" fn mock_rescan(_host: *const clap_host, flags: clap_param_rescan_flags) {
+        MOCK_RESCAN_FLAGS.store(flags, Ordering::SeqCst);
+    }
+
+    unsafe extern "C" fn mock_clear(
+        _host: *const clap_host,
+        _param_id: clap_id,
+        _flags: clap_sys::ext::params::clap_param_clear_flags,
+    ) {
+    }
+
+    unsafe extern "C" fn mock_request_flush(_host: *const clap_host) {}
+
+    #[test]
+    fn param_rescan_reaches_host() {
+        let host_params = clap_host_params {
+            rescan: Some(mock_rescan),
+            clear: Some(mock_clear),
+            request_flush: Some(mock_request_flush),
+        };
+
+        assert!(unsafe { rescan_all_params(std::ptr::null(), Some(&host_params), false) });
+        assert_eq!(
+            MOCK_RESCAN_FLAGS.load(Ordering::SeqCst),
+            CLAP_PARAM_RESCAN_ALL
+        );
+
+        // A full rescan is not allowed while the plugin is activated
+        assert!(unsafe { rescan_all_params(std::ptr::null(), Some(&host_params), true) });
+        assert_eq!(
+            MOCK_RESCAN_FLAGS.load(Ordering::SeqCst),
+            CLAP_PARAM_RESCAN_VALUES | CLAP_PARAM_RESCAN_TEXT | CLAP_PARAM_RESCAN_INFO
+        );
+
+        MOCK_RESCAN_FLAGS.store(0, Ordering::SeqCst);
+        assert!(!unsafe { rescan_all_params(std::ptr::null(), None, false) });
+        assert_eq!(MOCK_RESCAN_FLAGS.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn param_indication_mapping() {
+        let indications = ParamIndications::default();
+        let color = clap_color {
+            alpha: 255,
+            red: 255,
+            green: 128,
+            blue: 0,
+        };
+        let label = b"CC 74\0";
+        let description = b"Filter knob on the controller\0";
+
+        assert_eq!(indications.get(1), None);
+        unsafe {
+            indications.set_mapping(
+                1,
+                true,
+                &color,
+                label.as_ptr() as *const c_char,
+                description.as_ptr() as *const c_char,
+            )
+        };
+
+        // This is what the editor receives through `ParamSetter::indication()`
+        assert_eq!(
+            indications.get(1),
+            Some(ParamIndication {
+                mapping: Some(ParamMapping {
+                    color: Some(IndicationColor {
+                        red: 255,
+                        green: 128,
+                        blue: 0,
+                        alpha: 255,
+                    }),
+                    label: String::from("CC 74"),
+                    description: String::from("Filter knob on the controller"),
+                }),
+                automation: ParamAutomationState::None,
+                automation_color: None,
+            })
+        );
+        assert_eq!(indications.get(2), None);
+
+        unsafe {
+            indications.set_mapping(
+                1,
+                false,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(indications.get(1), None);
+    }
+
+    #[test]
+    fn param_indication_automation() {
+        let indications = ParamIndications::default();
+
+        unsafe {
+            indications.set_automation(
+                1,
+                CLAP_PARAM_INDICATION_AUTOMATION_PLAYING,
+                std::ptr::null(),
+            )
+        };
+        let indication = indications.get(1).unwrap();
+        assert_eq!(indication.mapping, None);
+        assert_eq!(indication.automation, ParamAutomationState::Playing);
+
+        unsafe {
+            indications.set_automation(1, CLAP_PARAM_INDICATION_AUTOMATION_NONE, std::ptr::null())
+        };
+        assert_eq!(indications.get(1), None);
+    }
+
+    #[test]
+    fn chain_hint_from_master_track() {
+        let mut info: clap_track_info = unsafe { std::mem::zeroed() };
+        info.flags = CLAP_TRACK_INFO_IS_FOR_MASTER
+            | CLAP_TRACK_INFO_HAS_TRACK_NAME
+            | CLAP_TRACK_INFO_HAS_AUDIO_CHANNEL
+            | CLAP_TRACK_INFO_HAS_TRACK_COLOR;
+        for (c, byte) in info.name.iter_mut().zip(b"Master") {
+            *c = *byte as c_char;
+        }
+        info.audio_channel_count = 2;
+        info.color = clap_color {
+            alpha: 255,
+            red: 200,
+            green: 100,
+            blue: 50,
+        };
+
+        assert_eq!(
+            chain_hint_from_track_info(&info),
+            ChainHint {
+                track_kind: TrackKind::Master,
+                track_name: Some(String::from("Master")),
+                track_channel_count: Some(2),
+                track_color: Some(IndicationColor {
+                    red: 200,
+                    green: 100,
+                    blue: 50,
+                    alpha: 255,
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn chain_hint_ignores_unset_fields() {
+        let mut info: clap_track_info = unsafe { std::mem::zeroed() };
+        info.flags = CLAP_TRACK_INFO_IS_FOR_BUS;
+        info.audio_channel_count = 2;
+
+        assert_eq!(
+            chain_hint_from_track_info(&info),
+            ChainHint {
+                track_kind: TrackKind::Bus,
+                track_name: None,
+                track_channel_count: None,
+                track_color: None,
+            }
+        );
+    }
+
+    #[test]
+    fn buffer_size_hints_from_activate() {
+        // A host that processes variable sized blocks
+        let buffer_config = buffer_config_from_activate(48_000.0, 32, 1024, ProcessMode::Realtime);
+        assert_eq!(buffer_config.sample_rate, 48_000.0);
+        assert_eq!(buffer_config.min_buffer_size, Some(32));
+        assert_eq!(buffer_config.max_buffer_size, 1024);
+        assert_eq!(buffer_config.preferred_buffer_size, None);
+
+        // A host that always processes 256 sample blocks
+        let buffer_config = buffer_config_from_activate(44_100.0, 256, 256, ProcessMode::Offline);
+        assert_eq!(buffer_config.min_buffer_size, Some(256));
+        assert_eq!(buffer_config.max_buffer_size, 256);
+        assert_eq!(buffer_config.preferred_buffer_size, Some(256));
+        assert_eq!(buffer_config.process_mode, ProcessMode::Offline);
+    }
+
+    #[test]
+    fn tuning_ramp_is_sample_accurate() {
+        // The host glides the note up by two semitones over the block, sending an update every 64
+        // samples
+        let tuning_events: Vec<NoteEvent<()>> = (0..8)
+            .filter_map(|idx| {
+                let timing = idx * 64;
+                let event = clap_event_note_expression {
+                    header: clap_event_header {
+                        size: mem::size_of::<clap_event_note_expression>() as u32,
+                        time: timing,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_: CLAP_EVENT_NOTE_EXPRESSION,
+                        flags: 0,
+                    },
+                    expression_id: CLAP_NOTE_EXPRESSION_TUNING,
+                    note_id: 7,
+                    port_index: 0,
+                    channel: 0,
+                    key: 60,
+                    value: idx as f64 * 2.0 / 7.0,
+                };
+
+                translate_note_expression(timing, &event)
+            })
+            .collect();
+        assert_eq!(tuning_events.len(), 8);
+
+        for (idx, event) in tuning_events.iter().enumerate() {
+            match *event {
+                NoteEvent::PolyTuning {
+                    timing,
+                    voice_id,
+                    channel,
+                    note,
+                    tuning,
+                } => {
+                    assert_eq!(timing, idx as u32 * 64);
+                    assert_eq!(voice_id, Some(7));
+                    assert_eq!((channel, note), (0, 60));
+                    assert_eq!(tuning, (idx as f64 * 2.0 / 7.0) as f32);
+                }
+                ref event => panic!("Unexpected event: {event:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn param_info_flags_default() {
+        let info_flags = param_info_flags(ParamFlags::empty(), false, false, true);
+        assert_eq!(
+            info_flags,
+            CLAP_PARAM_IS_AUTOMATABLE
+                | CLAP_PARAM_IS_MODULATABLE
+                | CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID
+        );
+    }
+
+    #[test]
+    fn param_info_flags_expensive() {
+        let info_flags = param_info_flags(ParamFlags::EXPENSIVE, true, false, true);
+        assert_eq!(
+            info_flags,
+            CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_STEPPED
+        );
+    }
+
+    #[test]
+    fn param_info_flags_hidden() {
+        let info_flags = param_info_flags(
+            ParamFlags::HIDDEN | ParamFlags::EXPENSIVE,
+            false,
+            false,
+            false,
+        );
+        assert_eq!(info_flags, CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY);
+    }
+
+    #[test]
+    fn param_info_flags_enum() {
+        let info_flags = param_info_flags(ParamFlags::empty(), true, true, false);
+        assert_eq!(
+            info_flags,
+            CLAP_PARAM_IS_AUTOMATABLE
+                | CLAP_PARAM_IS_MODULATABLE
+                | CLAP_PARAM_IS_STEPPED
+                | CLAP_PARAM_IS_ENUM
+        );
+
+        // Continuous parameters cannot be enumerated
+        let info_flags = param_info_flags(ParamFlags::empty(), false, true, false);
+        assert_eq!(
+            info_flags,
+            CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE
+        );
+    }
+
+    #[test]
+    fn automated_param_event_keeps_offset() {
+        let block_start = 64;
+        let mut events = [
+            OutputParamEvent::SetValue {
+                param_hash: 1,
+                clap_plain_value: 0.75,
+                timing: 17,
+            },
+            // A change made from the editor while the plugin was processing audio
+            OutputParamEvent::SetValue {
+                param_hash: 2,
+                clap_plain_value: 0.5,
+                timing: 0,
+            },
+            OutputParamEvent::SetValue {
+                param_hash: 1,
+                clap_plain_value: 0.25,
+                timing: 3,
+            },
+        ];
+        sort_output_param_events(&mut events);
+
+        let emitted: Vec<(clap_id, u32, f64)> = events
+            .iter()
+            .map(|event| match *event {
+                OutputParamEvent::SetValue {
+                    param_hash,
+                    clap_plain_value,
+                    timing,
+                } => {
+                    let event = output_param_value_event(
+                        param_hash,
+                        clap_plain_value,
+                        block_start + timing,
+                    );
+                    (event.param_id, event.header.time, event.value)
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(emitted, [(2, 64, 0.5), (1, 67, 0.25), (1, 81, 0.75)]);
+    }
+
+    #[test]
+    fn queued_editor_values_keep_latest_value() {
+        let queued_values = QueuedEditorValues::new([1, 3]);
+        queued_values.queued(3, 0.4);
+        queued_values.queued(3, 0.6);
+
+        // Only the latest value is applied
+        let mut applied = Vec::new();
+        queued_values
+            .apply(|param_hash, clap_plain_value| applied.push((param_hash, clap_plain_value)));
+        assert_eq!(applied, [(3, 0.6)]);
+
+        // The value stays queued until the event with the latest value has been sent to the host
+        queued_values.dequeued(3, 0.4);
+        let mut applied = Vec::new();
+        queued_values
+            .apply(|param_hash, clap_plain_value| applied.push((param_hash, clap_plain_value)));
+        assert_eq!(applied, [(3, 0.6)]);
+
+        queued_values.dequeued(3, 0.6);
+        queued_values.apply(|_, _| panic!("There should not be any queued values left"));
+    }
+
+    #[test]
+    fn audio_port_reconfiguration() {
+        const LAYOUTS: [AudioIOLayout; 2] = [
+            AudioIOLayout {
+                main_input_channels: NonZeroU32::new(2),
+                main_output_channels: NonZeroU32::new(2),
+                aux_input_ports: &[new_nonzero_u32(1)],
+                ..AudioIOLayout::const_default()
+            },
+            AudioIOLayout {
+                main_input_channels: NonZeroU32::new(2),
+                main_output_channels: NonZeroU32::new(2),
+                aux_input_ports: &[new_nonzero_u32(2)],
+                ..AudioIOLayout::const_default()
+            },
+        ];
+        let request = |is_input, port_index, channel_count| clap_audio_port_configuration_request {
+            is_input,
+            port_index,
+            channel_count,
+            port_type: std::ptr::null(),
+            port_details: std::ptr::null(),
+        };
+
+        // The host asks for a stereo sidechain input instead of a mono one
+        let new_layout =
+            resolve_audio_port_configuration(&LAYOUTS, &LAYOUTS[0], &[request(true, 1, 2)])
+                .expect("The requested configuration should be supported");
+        assert_eq!(new_layout.aux_input_ports, [new_nonzero_u32(2)]);
+        assert_eq!(port_channel_counts(&new_layout, true), [2, 2]);
+        assert_eq!(port_channel_counts(&new_layout, false), [2]);
+
+        // There is no layout with a mono main output, and the plugin doesn't have a second
+        // sidechain input
+        assert!(
+            resolve_audio_port_configuration(&LAYOUTS, &new_layout, &[request(false, 0, 1)])
+                .is_none()
+        );
+        assert!(
+            resolve_audio_port_configuration(&LAYOUTS, &new_layout, &[request(true, 2, 2)])
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn note_port_dialects_match_midi_config() {
+        assert_eq!(note_port_dialects(MidiConfig::None, false), None);
+        assert_eq!(note_port_dialects(MidiConfig::None, true), None);
+
+        let both_dialects = CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI;
+        for config in [MidiConfig::Basic, MidiConfig::MidiCCs] {
+            assert_eq!(
+                note_port_dialects(config, false),
+                Some((both_dialects, CLAP_NOTE_DIALECT_CLAP))
+            );
+            assert_eq!(
+                note_port_dialects(config, true),
+                Some((both_dialects, CLAP_NOTE_DIALECT_MIDI))
+            );
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use atomic_float::AtomicF32;
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
+use clap_sys::color::clap_color;
 use clap_sys::events::{
     clap_event_header, clap_event_midi, clap_event_midi_sysex, clap_event_note,
     clap_event_note_expression, clap_event_param_gesture, clap_event_param_mod,
@@ -22,31 +23,39 @@ use clap_sys::ext::audio_ports::{
 use clap_sys::ext::audio_ports_config::{
     clap_audio_ports_config, clap_plugin_audio_ports_config, CLAP_EXT_AUDIO_PORTS_CONFIG,
 };
+use clap_sys::ext::draft::param_indication::{
+    clap_plugin_param_indication, CLAP_EXT_PARAM_INDICATION,
+};
 use clap_sys::ext::draft::remote_controls::{
     clap_plugin_remote_controls, clap_remote_controls_page, CLAP_EXT_REMOTE_CONTROLS,
 };
+use clap_sys::ext::draft::track_info::{
+    clap_host_track_info, clap_track_info, CLAP_EXT_TRACK_INFO,
+};
 use clap_sys::ext::gui::{
     clap_gui_resize_hints, clap_host_gui, clap_plugin_gui, clap_window, CLAP_EXT_GUI,
     CLAP_WINDOW_API_COCOA, CLAP_WINDOW_API_WIN32, CLAP_WINDOW_API_X11,
 };
 use clap_sys::ext::latency::{clap_host_latency, clap_plugin_latency, CLAP_EXT_LATENCY};
-use clap_sys::ext::note_ports::{
-    clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_CLAP,
-    CLAP_NOTE_DIALECT_MIDI,
-};
+use clap_sys::ext::note_ports::{clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS};
 use clap_sys::ext::params::{
     clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
-    CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
-    CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
-    CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
+    CLAP_PARAM_RESCAN_VALUES,
+};
+use clap_sys::ext::posix_fd_support::{
+    clap_host_posix_fd_support, clap_plugin_posix_fd_support, clap_posix_fd_flags,
+    CLAP_EXT_POSIX_FD_SUPPORT,
 };
 use clap_sys::ext::render::{
     clap_plugin_render, clap_plugin_render_mode, CLAP_EXT_RENDER, CLAP_RENDER_OFFLINE,
     CLAP_RENDER_REALTIME,
 };
-use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::ext::state::{clap_host_state, clap_plugin_state, CLAP_EXT_STATE};
 use clap_sys::ext::tail::{clap_plugin_tail, CLAP_EXT_TAIL};
 use clap_sys::ext::thread_check::{clap_host_thread_check, CLAP_EXT_THREAD_CHECK};
+use clap_sys::ext::timer_support::{
+    clap_host_timer_support, clap_plugin_timer_support, CLAP_EXT_TIMER_SUPPORT,
+};
 use clap_sys::ext::voice_info::{
     clap_host_voice_info, clap_plugin_voice_info, clap_voice_info, CLAP_EXT_VOICE_INFO,
     CLAP_VOICE_INFO_SUPPORTS_OVERLAPPING_NOTES,
@@ -73,28 +82,45 @@ use std::mem;
 use std::num::NonZeroU32;
 use std::os::raw::c_char;
 use std::ptr::NonNull;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use std::thread::{self, ThreadId};
 use std::time::Duration;
 
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
 use super::descriptor::PluginDescriptor;
-use super::util::ClapPtr;
+use super::util::{
+    output_param_value_event, sort_output_param_events, ClapPtr, QueuedEditorValues,
+};
 use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ClapPlugin, Editor, MidiConfig,
-    NoteEvent, ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent,
-    ProcessMode, ProcessStatus, SysExMessage, TaskExecutor, Transport,
+    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ChainHint, ChannelCounts,
+    ClapPlugin, Editor, HostTimerCallback, MidiConfig, NoteEvent, ParamAppearance, ParamFlags,
+    ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent, PosixFdCallback, PosixFdFlags,
+    ProcessMode, ProcessStatus, RawHostHandle, SysExMessage, TaskExecutor, TempoMap, Transport,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::clap::context::RemoteControlPages;
-use crate::wrapper::clap::util::{read_stream, write_stream};
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::clap::util::{
+    buffer_config_from_activate, chain_hint_from_track_info, clap_audio_port_configuration_request,
+    clap_plugin_configurable_audio_ports, input_event_port, is_gui_api_supported, mark_state_dirty,
+    note_port_dialects, param_info_flags, read_stream, rescan_all_params,
+    resolve_audio_port_configuration, set_editor_visible, translate_note_expression, write_stream,
+    ClapIStream, ClapOStream, EditorTimer, HostIo, ParamIndications, CHUNKED_STATE_MARKER,
+    CLAP_EXT_CONFIGURABLE_AUDIO_PORTS,
+};
+use crate::wrapper::state::{self, ChunkedReader, ChunkedWriter, EditorStateStore, PluginState};
+use crate::wrapper::util::buffer_management::{
+    BufferManager, ChannelPointers, PaddedBuffer, ScratchBuffers,
+};
 use crate::wrapper::util::{
-    clamp_input_event_timing, clamp_output_event_timing, hash_param_id, process_wrapper, strlcpy,
+    adjust_editor_size, check_process_mode, clamp_input_event_timing, clamp_output_event_timing,
+    hash_param_id, is_sample_rate_supported, lone_param_link_groups, note_input_port_name,
+    process_wrapper, reset_param_smoothers, skip_silent_block, sort_output_events, strlcpy,
+    BlockPositionCounter, BypassState, CarriedInputEvents, FactoryPresets, FirstProcess,
+    InputEventPorts, ParamChangeSources, ParamGestures, ParamWriteLocks, PendingRequest,
+    PluginLatency, SeekDetector, TransportSnapshot,
 };
 
 /// How many output parameter changes we can store in our output parameter change queue. Storing
@@ -127,8 +153,12 @@ pub struct Wrapper<P: ClapPlugin> {
     editor_scaling_factor: AtomicF32,
 
     is_processing: AtomicBool,
-    /// The current IO configuration, modified through the `clap_plugin_audio_ports_config`
-    /// extension. Initialized to the plugin's first audio IO configuration.
+    /// Whether the plugin is currently activated. A full parameter rescan is only allowed while
+    /// the plugin is deactivated.
+    is_activated: AtomicBool,
+    /// The current IO configuration, modified through the `clap_plugin_audio_ports_config` and
+    /// `clap_plugin_configurable_audio_ports` extensions. Initialized to the plugin's first audio
+    /// IO configuration.
     current_audio_io_layout: AtomicCell<AudioIOLayout>,
     /// The current buffer configuration, containing the sample rate and the maximum block size.
     /// Will be set in `clap_plugin::activate()`.
@@ -141,17 +171,43 @@ pub struct Wrapper<P: ClapPlugin> {
     /// TODO: Maybe load these lazily at some point instead of needing to spool them all to this
     ///       queue first
     input_events: AtomicRefCell<VecDeque<PluginNoteEvent<P>>>,
+    /// The note port every event in `input_events` arrived on.
+    input_event_ports: AtomicRefCell<InputEventPorts>,
     /// Stores any events the plugin has output during the current processing cycle, analogous to
     /// `input_events`.
     output_events: AtomicRefCell<VecDeque<PluginNoteEvent<P>>>,
     /// The last process status returned by the plugin. This is used for tail handling.
     last_process_status: AtomicCell<ProcessStatus>,
+    /// Keeps track of the absolute position of each block for
+    /// [`ProcessContext::block_start_samples()`]. Reset whenever the plugin gets reset.
+    block_position: BlockPositionCounter,
+    /// Detects seeks for [`Transport::just_seeked()`]. Reset whenever the plugin gets reset.
+    seek_detector: SeekDetector,
+    /// Keeps the note events from process calls without any samples for the next block. Reset
+    /// whenever the plugin gets reset.
+    carried_input_events: CarriedInputEvents,
     /// The current latency in samples, as set by the plugin through the [`ProcessContext`]. Uses
     /// the latency extension.
     pub current_latency: AtomicU32,
+    /// Reports changes in [`Plugin::latency()`] to the host through
+    /// [`set_latency_samples()`][Self::set_latency_samples()].
+    plugin_latency: PluginLatency,
+    /// Set when the plugin requested a restart through [`ProcessContext::request_restart()`] until
+    /// the host has been notified on the main thread.
+    restart_request: PendingRequest,
+    /// Calls [`Plugin::first_process()`] before the first block after the plugin gets activated.
+    first_process: FirstProcess,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     buffer_manager: AtomicRefCell<BufferManager>,
+    /// Pads the main buffer to a multiple of [`Plugin::SIMD_BLOCK_MULTIPLE`] samples.
+    padded_buffer: AtomicRefCell<PaddedBuffer>,
+    /// The number of scratch buffers the plugin requested with
+    /// `InitContext::request_scratch_buffers()` during the last initialization.
+    scratch_buffer_count: AtomicUsize,
+    /// The scratch buffers exposed through `ProcessContext::scratch_buffers()`. These are allocated
+    /// when the plugin gets activated.
+    scratch_buffers: AtomicRefCell<ScratchBuffers>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -169,6 +225,8 @@ pub struct Wrapper<P: ClapPlugin> {
 
     clap_plugin_audio_ports_config: clap_plugin_audio_ports_config,
 
+    clap_plugin_configurable_audio_ports: clap_plugin_configurable_audio_ports,
+
     // The main `clap_plugin` vtable. A pointer to this `Wrapper<P>` instance is stored in the
     // `plugin_data` field. This pointer is set after creating the `Arc<Wrapper<P>>`.
     pub clap_plugin: AtomicRefCell<clap_plugin>,
@@ -212,6 +270,26 @@ pub struct Wrapper<P: ClapPlugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// Whether parameters were last changed by the host or by the editor, indexed by parameter
+    /// hash. Exposed to the editor through the `GuiContext`.
+    pub param_change_sources: ParamChangeSources<u32>,
+    /// Locks parameters for `P::PARAM_WRITE_LOCK` after the plugin writes them, indexed by
+    /// parameter hash.
+    pub param_write_locks: ParamWriteLocks<u32>,
+    /// Parameters that are currently being changed from the editor, indexed by parameter hash.
+    /// Used for `P::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD`.
+    pub param_gestures: ParamGestures<u32>,
+    /// Lets parameters with a neutral bypass value react to changes to the bypass parameter.
+    bypass_state: BypassState,
+    /// The editor's own state that's saved alongside the parameters. Exposed to the editor through
+    /// the `GuiContext`.
+    pub editor_state: EditorStateStore,
+    /// The transport information from the last processing cycle. Exposed to the editor through the
+    /// `GuiContext`.
+    pub transport_snapshot: TransportSnapshot,
+    /// The plugin's factory presets and the preset that was loaded last. Exposed to the editor
+    /// through the `GuiContext`.
+    pub factory_presets: FactoryPresets,
     /// For all polyphonically modulatable parameters, mappings from the parameter hash's hash to
     /// the parameter's poly modulation ID. These IDs are then passed to the plugin, so it can
     /// quickly refer to parameter by matching on constant IDs.
@@ -223,19 +301,56 @@ pub struct Wrapper<P: ClapPlugin> {
     ///      even if it does then that should still not be a problem because the host also reads it
     ///      in the same order, right?
     output_parameter_events: ArrayQueue<OutputParamEvent>,
+    /// The last value queued by the editor for each parameter that has not yet been written to the
+    /// host, indexed by parameter hash. Used to apply pending editor changes when saving the state.
+    queued_editor_values: QueuedEditorValues,
+    /// The events from `output_parameter_events` are moved here and sorted by their timing before
+    /// they're written to the host's output queue. This has the same capacity as that queue, so
+    /// this never allocates.
+    sorted_output_parameter_events: AtomicRefCell<Vec<OutputParamEvent>>,
 
     host_thread_check: AtomicRefCell<Option<ClapPtr<clap_host_thread_check>>>,
 
+    clap_plugin_param_indication: clap_plugin_param_indication,
+    /// The mapping and automation indications sent by the host, indexed by parameter hash.
+    /// Exposed to the editor through the `GuiContext`.
+    pub param_indications: ParamIndications,
+
     clap_plugin_remote_controls: clap_plugin_remote_controls,
     /// The plugin's remote control pages, if it defines any. Filled when initializing the plugin.
     remote_control_pages: Vec<clap_remote_controls_page>,
 
+    clap_plugin_posix_fd_support: clap_plugin_posix_fd_support,
+    host_posix_fd_support: AtomicRefCell<Option<ClapPtr<clap_host_posix_fd_support>>>,
+
     clap_plugin_render: clap_plugin_render,
 
     clap_plugin_state: clap_plugin_state,
+    host_state: AtomicRefCell<Option<ClapPtr<clap_host_state>>>,
+    /// Set when the editor marked the plugin's state as dirty through
+    /// [`GuiContext::mark_state_dirty()`][crate::prelude::GuiContext::mark_state_dirty()] until the
+    /// host has been notified on the main thread.
+    state_dirty_request: PendingRequest,
+    /// Set when the editor requested a parameter rescan through
+    /// [`GuiContext::request_param_rescan()`][crate::prelude::GuiContext::request_param_rescan()]
+    /// until the host has been notified on the main thread.
+    param_rescan_request: PendingRequest,
 
     clap_plugin_tail: clap_plugin_tail,
 
+    clap_plugin_timer_support: clap_plugin_timer_support,
+    host_timer_support: AtomicRefCell<Option<ClapPtr<clap_host_timer_support>>>,
+    /// The host timer driving the editor's redraws, if the editor opted into that through
+    /// [`Editor::host_timer_interval_ms()`][crate::prelude::Editor::host_timer_interval_ms()].
+    editor_timer: EditorTimer,
+    /// The timers and file descriptors the plugin registered for its own background IO through
+    /// [`InitContext::register_host_timer()`][crate::prelude::InitContext::register_host_timer()]
+    /// and [`InitContext::register_posix_fd()`][crate::prelude::InitContext::register_posix_fd()].
+    host_io: HostIo,
+
+    /// Used to fill in the [`ChainHint`] returned from the init context.
+    host_track_info: AtomicRefCell<Option<ClapPtr<clap_host_track_info>>>,
+
     clap_plugin_voice_info: clap_plugin_voice_info,
     host_voice_info: AtomicRefCell<Option<ClapPtr<clap_host_voice_info>>>,
     /// If `P::CLAP_POLY_MODULATION_CONFIG` is set, then the plugin can configure the current number
@@ -280,6 +395,12 @@ pub enum Task<P: Plugin> {
     VoiceInfoChanged,
     /// Tell the host that it should rescan the current parameter values.
     RescanParamValues,
+    /// Ask the host to restart the plugin.
+    RestartRequested,
+    /// Tell the host that the plugin's state has changed.
+    StateDirty,
+    /// Ask the host to rescan all of the plugin's parameters.
+    ParamRescanRequested,
 }
 
 /// The types of CLAP parameter updates for events.
@@ -307,12 +428,28 @@ pub enum OutputParamEvent {
         /// The 'plain' value as reported to CLAP. This is the normalized value multiplied by
         /// [`params::step_size()`][crate::params::step_size()].
         clap_plain_value: f64,
+        /// The event's timing relative to the start of the block it was sent in. Changes made from
+        /// the editor are always sent at the start of the block and use 0, while automation points
+        /// written through [`ProcessContext::set_parameter_automated()`] use the plugin's sample
+        /// offset.
+        timing: u32,
     },
     /// Begin an automation gesture. This must always be sent after sending one or more [`SetValue`]
     /// events.
     EndGesture { param_hash: u32 },
 }
 
+impl OutputParamEvent {
+    /// The event's timing relative to the start of the block. Gestures are always sent at the start
+    /// of the block.
+    pub fn timing(&self) -> u32 {
+        match self {
+            OutputParamEvent::SetValue { timing, .. } => *timing,
+            OutputParamEvent::BeginGesture { .. } | OutputParamEvent::EndGesture { .. } => 0,
+        }
+    }
+}
+
 /// Because CLAP has this [`clap_host::request_host_callback()`] function, we don't need to use
 /// `OsEventLoop` and can instead just request a main thread callback directly.
 impl<P: ClapPlugin> EventLoop<Task<P>, Wrapper<P>> for Wrapper<P> {
@@ -420,6 +557,36 @@ impl<P: ClapPlugin> MainThreadExecutor<Task<P>> for Wrapper<P> {
                 }
                 None => nih_debug_assert_failure!("The host does not support parameters? What?"),
             },
+            Task::RestartRequested => {
+                // Further requests made from now on will trigger another restart
+                if self.restart_request.take() {
+                    unsafe_clap_call! { &*self.host_callback=>request_restart(&*self.host_callback) };
+                }
+            }
+            Task::StateDirty => {
+                if self.state_dirty_request.take() {
+                    nih_debug_assert!(is_gui_thread);
+
+                    // Hosts that don't support the state extension simply won't be notified
+                    unsafe {
+                        mark_state_dirty(&*self.host_callback, self.host_state.borrow().as_deref())
+                    };
+                }
+            }
+            Task::ParamRescanRequested => {
+                if self.param_rescan_request.take() {
+                    nih_debug_assert!(is_gui_thread);
+
+                    let rescanned = unsafe {
+                        rescan_all_params(
+                            &*self.host_callback,
+                            self.host_params.borrow().as_deref(),
+                            self.is_activated.load(Ordering::SeqCst),
+                        )
+                    };
+                    nih_debug_assert!(rescanned, "The host does not support parameters? What?");
+                }
+            }
         };
     }
 }
@@ -454,10 +621,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                 (id, hash, ptr, group)
             })
             .collect();
-        let param_hashes = param_id_hashes_ptrs_groups
+        let param_hashes: Vec<u32> = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, _, _)| *hash)
             .collect();
+        let param_change_sources = ParamChangeSources::new(param_hashes.iter().copied());
+        let param_write_locks =
+            ParamWriteLocks::new(P::PARAM_WRITE_LOCK, param_hashes.iter().copied());
+        let param_gestures = ParamGestures::new(param_hashes.iter().copied());
+        let bypass_state = unsafe {
+            BypassState::new(
+                param_id_hashes_ptrs_groups
+                    .iter()
+                    .map(|(_, _, ptr, _)| *ptr),
+            )
+        };
         let param_by_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, ptr, _)| (*hash, *ptr))
@@ -518,7 +696,25 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
 
                 bypass_param_exists |= is_bypass;
+
+                unsafe { ptr.assert_validity() };
             }
+
+            let lone_link_groups = unsafe {
+                lone_param_link_groups(param_id_hashes_ptrs_groups.iter().map(|(_, _, ptr, _)| ptr))
+            };
+            for link_group in lone_link_groups {
+                nih_debug_assert_failure!(
+                    "The parameter link group '{}' only contains a single parameter",
+                    link_group
+                );
+            }
+
+            nih_debug_assert!(
+                P::MIDI_INPUT < MidiConfig::Basic || P::MIDI_INPUT_PORTS >= 1,
+                "The plugin accepts note events but has no note input ports, set \
+                 'Plugin::MIDI_INPUT_PORTS' to at least one"
+            );
         }
 
         // Support for the remote controls extension
@@ -541,21 +737,36 @@ impl<P: ClapPlugin> Wrapper<P> {
             editor_scaling_factor: AtomicF32::new(1.0),
 
             is_processing: AtomicBool::new(false),
+            is_activated: AtomicBool::new(false),
             current_audio_io_layout: AtomicCell::new(
                 P::AUDIO_IO_LAYOUTS.first().copied().unwrap_or_default(),
             ),
             current_buffer_config: AtomicCell::new(None),
             current_process_mode: AtomicCell::new(ProcessMode::Realtime),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
+            input_event_ports: AtomicRefCell::new(InputEventPorts::with_capacity(512)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
+            block_position: BlockPositionCounter::default(),
+            seek_detector: SeekDetector::default(),
+            carried_input_events: CarriedInputEvents::default(),
             current_latency: AtomicU32::new(0),
+            plugin_latency: PluginLatency::default(),
+            restart_request: PendingRequest::default(),
+            first_process: FirstProcess::default(),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            padded_buffer: AtomicRefCell::new(PaddedBuffer::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+                P::SIMD_BLOCK_MULTIPLE,
+            )),
+            scratch_buffer_count: AtomicUsize::new(0),
+            scratch_buffers: AtomicRefCell::new(ScratchBuffers::default()),
             updated_state_sender,
             updated_state_receiver,
 
@@ -588,6 +799,11 @@ impl<P: ClapPlugin> Wrapper<P> {
                 select: Some(Self::ext_audio_ports_config_select),
             },
 
+            clap_plugin_configurable_audio_ports: clap_plugin_configurable_audio_ports {
+                can_apply_configuration: Some(Self::ext_configurable_audio_ports_can_apply),
+                apply_configuration: Some(Self::ext_configurable_audio_ports_apply),
+            },
+
             clap_plugin_audio_ports: clap_plugin_audio_ports {
                 count: Some(Self::ext_audio_ports_count),
                 get: Some(Self::ext_audio_ports_get),
@@ -637,11 +853,28 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_group_by_hash,
             param_id_to_hash,
             param_ptr_to_hash,
+            param_change_sources,
+            param_write_locks,
+            param_gestures,
+            bypass_state,
+            editor_state: EditorStateStore::default(),
+            transport_snapshot: TransportSnapshot::default(),
+            factory_presets: FactoryPresets::new::<P>(),
             poly_mod_ids_by_hash,
             output_parameter_events: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            queued_editor_values: QueuedEditorValues::new(param_hashes.iter().copied()),
+            sorted_output_parameter_events: AtomicRefCell::new(Vec::with_capacity(
+                OUTPUT_EVENT_QUEUE_CAPACITY,
+            )),
 
             host_thread_check: AtomicRefCell::new(None),
 
+            clap_plugin_param_indication: clap_plugin_param_indication {
+                set_mapping: Some(Self::ext_param_indication_set_mapping),
+                set_automation: Some(Self::ext_param_indication_set_automation),
+            },
+            param_indications: ParamIndications::default(),
+
             clap_plugin_remote_controls: clap_plugin_remote_controls {
                 count: Some(Self::ext_remote_controls_count),
                 get: Some(Self::ext_remote_controls_get),
@@ -653,15 +886,32 @@ impl<P: ClapPlugin> Wrapper<P> {
                 set: Some(Self::ext_render_set),
             },
 
+            clap_plugin_posix_fd_support: clap_plugin_posix_fd_support {
+                on_fd: Some(Self::ext_posix_fd_support_on_fd),
+            },
+            host_posix_fd_support: AtomicRefCell::new(None),
+
             clap_plugin_state: clap_plugin_state {
                 save: Some(Self::ext_state_save),
                 load: Some(Self::ext_state_load),
             },
+            host_state: AtomicRefCell::new(None),
+            state_dirty_request: PendingRequest::default(),
+            param_rescan_request: PendingRequest::default(),
 
             clap_plugin_tail: clap_plugin_tail {
                 get: Some(Self::ext_tail_get),
             },
 
+            clap_plugin_timer_support: clap_plugin_timer_support {
+                on_timer: Some(Self::ext_timer_support_on_timer),
+            },
+            host_timer_support: AtomicRefCell::new(None),
+            editor_timer: EditorTimer::default(),
+            host_io: HostIo::default(),
+
+            host_track_info: AtomicRefCell::new(None),
+
             clap_plugin_voice_info: clap_plugin_voice_info {
                 get: Some(Self::ext_voice_info_get),
             },
@@ -693,30 +943,6 @@ impl<P: ClapPlugin> Wrapper<P> {
         // from the vtable functions
         wrapper.clap_plugin.borrow_mut().plugin_data = Arc::as_ptr(&wrapper) as *mut _;
 
-        // The editor also needs to be initialized later so the Async executor can work.
-        *wrapper.editor.borrow_mut() = wrapper
-            .plugin
-            .lock()
-            .editor(AsyncExecutor {
-                execute_background: Arc::new({
-                    let wrapper = wrapper.clone();
-
-                    move |task| {
-                        let task_posted = wrapper.schedule_background(Task::PluginTask(task));
-                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
-                    }
-                }),
-                execute_gui: Arc::new({
-                    let wrapper = wrapper.clone();
-
-                    move |task| {
-                        let task_posted = wrapper.schedule_gui(Task::PluginTask(task));
-                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
-                    }
-                }),
-            })
-            .map(Mutex::new);
-
         // Same with the background thread
         *wrapper.background_thread.borrow_mut() =
             Some(BackgroundThread::get_or_create(Arc::downgrade(&wrapper)));
@@ -743,12 +969,25 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
-    fn make_process_context(&self, transport: Transport) -> WrapperProcessContext<'_, P> {
+    /// Create a process context for a block of `block_len` samples. This also advances the block
+    /// position counter, so this should be called exactly once per (split) block.
+    fn make_process_context(
+        &self,
+        mut transport: Transport,
+        block_len: usize,
+    ) -> WrapperProcessContext<'_, P> {
+        let block_start_samples = self.block_position.advance(&transport, block_len);
+        transport.just_seeked = self.seek_detector.advance(&transport, block_len);
+        self.transport_snapshot.store(&transport);
+
         WrapperProcessContext {
             wrapper: self,
             input_events_guard: self.input_events.borrow_mut(),
+            input_event_ports_guard: self.input_event_ports.borrow_mut(),
             output_events_guard: self.output_events.borrow_mut(),
+            scratch_buffers_guard: self.scratch_buffers.borrow_mut(),
             transport,
+            block_start_samples,
         }
     }
 
@@ -769,8 +1008,29 @@ impl<P: ClapPlugin> Wrapper<P> {
     ///
     /// Returns `false` if the parameter value queue was full and the update will not be sent to the
     /// host (it will still be set on the plugin either way).
+    ///
+    /// This is meant for changes made from the GUI. Requesting a flush is not allowed from the
+    /// audio thread, so changes made from `process()` should use
+    /// [`queue_process_parameter_event()`][Self::queue_process_parameter_event()] instead.
     pub fn queue_parameter_event(&self, event: OutputParamEvent) -> bool {
+        let queued_value = match event {
+            OutputParamEvent::SetValue {
+                param_hash,
+                clap_plain_value,
+                ..
+            } => Some((param_hash, clap_plain_value)),
+            _ => None,
+        };
+        if let Some((param_hash, clap_plain_value)) = queued_value {
+            self.queued_editor_values
+                .queued(param_hash, clap_plain_value);
+        }
+
         let result = self.output_parameter_events.push(event).is_ok();
+        if let (false, Some((param_hash, clap_plain_value))) = (result, queued_value) {
+            self.queued_editor_values
+                .dequeued(param_hash, clap_plain_value);
+        }
 
         // Requesting a flush is fine even during audio processing. This avoids a race condition.
         match &*self.host_params.borrow() {
@@ -783,6 +1043,86 @@ impl<P: ClapPlugin> Wrapper<P> {
         result
     }
 
+    /// Queue a parameter output event from the audio thread while the plugin is processing audio.
+    /// Unlike [`queue_parameter_event()`][Self::queue_parameter_event()] this does not request a
+    /// parameter flush, since the queued events are written to the host's output queue at the end
+    /// of the current process call anyways.
+    ///
+    /// Returns `false` if the parameter value queue was full and the update will not be sent to the
+    /// host.
+    pub fn queue_process_parameter_event(&self, event: OutputParamEvent) -> bool {
+        self.output_parameter_events.push(event).is_ok()
+    }
+
+    /// Apply the parameter values from the editor's queued parameter changes to the plugin without
+    /// waiting for the host to flush them. The changes stay queued so they're still sent to the
+    /// host during the next parameter flush or process call. Used when saving the plugin's state,
+    /// since hosts may do that without flushing the parameters first, for instance when closing a
+    /// project right after a parameter was changed. This does nothing while the plugin is
+    /// processing audio, since then the changes are applied at the end of the current block
+    /// instead.
+    pub fn apply_queued_parameter_values(&self) {
+        if self.is_processing.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let sample_rate = self.current_buffer_config.load().map(|c| c.sample_rate);
+        self.queued_editor_values
+            .apply(|param_hash, clap_plain_value| {
+                self.update_plain_value_by_hash(
+                    param_hash,
+                    ClapParamUpdate::PlainValueSet(clap_plain_value),
+                    sample_rate,
+                );
+            });
+    }
+
+    /// Reset the plugin's state and its block position. If `P::RESET_SMOOTHERS_ON_RESET` is set,
+    /// then the parameter smoothers are snapped to their current values first.
+    fn reset_plugin(&self) {
+        if P::RESET_SMOOTHERS_ON_RESET {
+            if let Some(buffer_config) = self.current_buffer_config.load() {
+                unsafe {
+                    reset_param_smoothers(self.param_by_hash.values(), buffer_config.sample_rate)
+                };
+            }
+        }
+
+        self.block_position.reset();
+        self.seek_detector.reset();
+        self.carried_input_events.reset();
+        process_wrapper(|| self.plugin.lock().reset());
+    }
+
+    /// Query the plugin's editor. This is done when the host initializes the plugin rather than in
+    /// the constructor. The host is not allowed to query the GUI extension before that point, and
+    /// this lets [`Plugin::editor()`] depend on the host context.
+    fn init_editor(&self) {
+        let wrapper = self.this.borrow().upgrade().unwrap();
+        *self.editor.borrow_mut() = self
+            .plugin
+            .lock()
+            .editor(AsyncExecutor {
+                execute_background: Arc::new({
+                    let wrapper = wrapper.clone();
+
+                    move |task| {
+                        let task_posted = wrapper.schedule_background(Task::PluginTask(task));
+                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+                    }
+                }),
+                execute_gui: Arc::new({
+                    let wrapper = wrapper.clone();
+
+                    move |task| {
+                        let task_posted = wrapper.schedule_gui(Task::PluginTask(task));
+                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+                    }
+                }),
+            })
+            .map(Mutex::new);
+    }
+
     /// Request a resize based on the editor's current reported size. As of CLAP 0.24 this can
     /// safely be called from any thread. If this returns `false`, then the plugin should reset its
     /// size back to the previous value.
@@ -807,6 +1147,19 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    /// Show or hide the editor in response to the host's `show()` and `hide()` calls without
+    /// closing it. Returns `false` if the editor is not currently open.
+    fn set_editor_visible(&self, visible: bool) -> bool {
+        let editor_handle = self.editor_handle.lock();
+        let editor = self.editor.borrow();
+        let editor = match (editor_handle.as_ref(), editor.as_ref()) {
+            (Some(_), Some(editor)) => Some(editor.lock()),
+            _ => None,
+        };
+
+        set_editor_visible(editor.as_deref().map(|editor| &**editor), visible)
+    }
+
     /// Convenience function for setting a value for a parameter as triggered by a VST3 parameter
     /// update. The same rate is for updating parameter smoothing.
     ///
@@ -884,7 +1237,9 @@ impl<P: ClapPlugin> Wrapper<P> {
         total_buffer_len: usize,
     ) {
         let mut input_events = self.input_events.borrow_mut();
+        let mut input_event_ports = self.input_event_ports.borrow_mut();
         input_events.clear();
+        input_event_ports.sync(&input_events, 0);
 
         let num_events = clap_call! { in_=>size(in_) };
         for event_idx in 0..num_events {
@@ -896,7 +1251,41 @@ impl<P: ClapPlugin> Wrapper<P> {
                 current_sample_idx,
                 total_buffer_len,
             );
+            input_event_ports.sync(&input_events, input_event_port(event));
+        }
+    }
+
+    /// Collect the tempo from the process call's transport information and all tempo changes in
+    /// the event queue into a tempo map for the entire buffer. The wrapper splits the buffer on
+    /// transport events, so the map for each block is created from this using
+    /// `TempoMap::starting_at()`.
+    pub unsafe fn collect_tempo_map(
+        in_: Option<&clap_input_events>,
+        transport_info: *const clap_event_transport,
+    ) -> TempoMap {
+        let mut tempo_map = TempoMap::default();
+        if !transport_info.is_null() && (*transport_info).flags & CLAP_TRANSPORT_HAS_TEMPO != 0 {
+            tempo_map.push(0, (*transport_info).tempo);
+        }
+
+        if let Some(in_) = in_ {
+            let num_events = clap_call! { in_=>size(in_) };
+            for event_idx in 0..num_events {
+                let event = clap_call! { in_=>get(in_, event_idx) };
+                if ((*event).space_id, (*event).type_)
+                    != (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_TRANSPORT)
+                {
+                    continue;
+                }
+
+                let event = &*(event as *const clap_event_transport);
+                if event.flags & CLAP_TRANSPORT_HAS_TEMPO != 0 {
+                    tempo_map.push(event.header.time, event.tempo);
+                }
+            }
         }
+
+        tempo_map
     }
 
     /// Similar to [`handle_in_events()`][Self::handle_in_events()], but will stop just before an
@@ -918,7 +1307,9 @@ impl<P: ClapPlugin> Wrapper<P> {
         stop_predicate: impl Fn(*const clap_event_header) -> bool,
     ) -> Option<(usize, usize)> {
         let mut input_events = self.input_events.borrow_mut();
-        input_events.clear();
+        let mut input_event_ports = self.input_event_ports.borrow_mut();
+        self.carried_input_events.begin_block(&mut input_events);
+        input_event_ports.sync(&input_events, 0);
 
         // To achieve this, we'll always read one event ahead
         let num_events = clap_call! { in_=>size(in_) };
@@ -936,6 +1327,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 current_sample_idx,
                 total_buffer_len,
             );
+            input_event_ports.sync(&input_events, input_event_port(event));
 
             // Stop just before the next parameter change or transport information event at a sample
             // after the current sample
@@ -955,6 +1347,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             current_sample_idx,
             total_buffer_len,
         );
+        input_event_ports.sync(&input_events, input_event_port(event));
 
         None
     }
@@ -971,75 +1364,22 @@ impl<P: ClapPlugin> Wrapper<P> {
         current_sample_idx: usize,
         total_buffer_len: usize,
     ) {
-        // We'll always write these events to the first sample, so even when we add note output we
-        // shouldn't have to think about interleaving events here
+        // Parameter changes from the editor and gestures are written to the start of the block, but
+        // automation points written by the plugin during processing can have any timing within the
+        // block. Hosts expect all output events to be sorted, so these parameter events are
+        // interleaved with the plugin's note events below.
         let sample_rate = self.current_buffer_config.load().map(|c| c.sample_rate);
-        while let Some(change) = self.output_parameter_events.pop() {
-            let push_successful = match change {
-                OutputParamEvent::BeginGesture { param_hash } => {
-                    let event = clap_event_param_gesture {
-                        header: clap_event_header {
-                            size: mem::size_of::<clap_event_param_gesture>() as u32,
-                            time: current_sample_idx as u32,
-                            space_id: CLAP_CORE_EVENT_SPACE_ID,
-                            type_: CLAP_EVENT_PARAM_GESTURE_BEGIN,
-                            flags: CLAP_EVENT_IS_LIVE,
-                        },
-                        param_id: param_hash,
-                    };
-
-                    clap_call! { out=>try_push(out, &event.header) }
-                }
-                OutputParamEvent::SetValue {
-                    param_hash,
-                    clap_plain_value,
-                } => {
-                    self.update_plain_value_by_hash(
-                        param_hash,
-                        ClapParamUpdate::PlainValueSet(clap_plain_value),
-                        sample_rate,
-                    );
-
-                    let event = clap_event_param_value {
-                        header: clap_event_header {
-                            size: mem::size_of::<clap_event_param_value>() as u32,
-                            time: current_sample_idx as u32,
-                            space_id: CLAP_CORE_EVENT_SPACE_ID,
-                            type_: CLAP_EVENT_PARAM_VALUE,
-                            flags: CLAP_EVENT_IS_LIVE,
-                        },
-                        param_id: param_hash,
-                        cookie: std::ptr::null_mut(),
-                        port_index: -1,
-                        note_id: -1,
-                        channel: -1,
-                        key: -1,
-                        value: clap_plain_value,
-                    };
-
-                    clap_call! { out=>try_push(out, &event.header) }
-                }
-                OutputParamEvent::EndGesture { param_hash } => {
-                    let event = clap_event_param_gesture {
-                        header: clap_event_header {
-                            size: mem::size_of::<clap_event_param_gesture>() as u32,
-                            time: current_sample_idx as u32,
-                            space_id: CLAP_CORE_EVENT_SPACE_ID,
-                            type_: CLAP_EVENT_PARAM_GESTURE_END,
-                            flags: CLAP_EVENT_IS_LIVE,
-                        },
-                        param_id: param_hash,
-                    };
-
-                    clap_call! { out=>try_push(out, &event.header) }
-                }
-            };
-
-            nih_debug_assert!(push_successful);
+        let mut param_events = self.sorted_output_parameter_events.borrow_mut();
+        param_events.clear();
+        while let Some(event) = self.output_parameter_events.pop() {
+            param_events.push(event);
         }
+        sort_output_param_events(&mut param_events);
+        let mut param_events = param_events.drain(..).peekable();
 
-        // Also send all note events generated by the plugin
+        // Also send all note events generated by the plugin. Hosts expect these to be sorted.
         let mut output_events = self.output_events.borrow_mut();
+        sort_output_events(&mut output_events);
         while let Some(event) = output_events.pop_front() {
             // Out of bounds events are clamped to the buffer's size
             let time = clamp_output_event_timing(
@@ -1047,6 +1387,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                 total_buffer_len as u32,
             );
 
+            while let Some(param_event) = param_events.next_if(|param_event| {
+                clamp_output_event_timing(
+                    param_event.timing() + current_sample_idx as u32,
+                    total_buffer_len as u32,
+                ) <= time
+            }) {
+                self.write_out_param_event(
+                    out,
+                    param_event,
+                    current_sample_idx,
+                    total_buffer_len,
+                    sample_rate,
+                );
+            }
+
             let push_successful = match event {
                 NoteEvent::NoteOn {
                     timing: _,
@@ -1362,6 +1717,82 @@ impl<P: ClapPlugin> Wrapper<P> {
 
             nih_debug_assert!(push_successful, "Could not send note event");
         }
+
+        // Any remaining parameter events come after the last note event
+        for param_event in param_events {
+            self.write_out_param_event(
+                out,
+                param_event,
+                current_sample_idx,
+                total_buffer_len,
+                sample_rate,
+            );
+        }
+    }
+
+    /// Write a single parameter event from `output_parameter_events` to the host's output event
+    /// queue, and update the parameter's value if it's a value change. Used in
+    /// [`handle_out_events()`][Self::handle_out_events()].
+    unsafe fn write_out_param_event(
+        &self,
+        out: &clap_output_events,
+        change: OutputParamEvent,
+        current_sample_idx: usize,
+        total_buffer_len: usize,
+        sample_rate: Option<f32>,
+    ) {
+        let time = clamp_output_event_timing(
+            change.timing() + current_sample_idx as u32,
+            total_buffer_len as u32,
+        );
+        let push_successful = match change {
+            OutputParamEvent::BeginGesture { param_hash } => {
+                let event = clap_event_param_gesture {
+                    header: clap_event_header {
+                        size: mem::size_of::<clap_event_param_gesture>() as u32,
+                        time,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_: CLAP_EVENT_PARAM_GESTURE_BEGIN,
+                        flags: CLAP_EVENT_IS_LIVE,
+                    },
+                    param_id: param_hash,
+                };
+
+                clap_call! { out=>try_push(out, &event.header) }
+            }
+            OutputParamEvent::SetValue {
+                param_hash,
+                clap_plain_value,
+                timing: _,
+            } => {
+                self.update_plain_value_by_hash(
+                    param_hash,
+                    ClapParamUpdate::PlainValueSet(clap_plain_value),
+                    sample_rate,
+                );
+                self.queued_editor_values
+                    .dequeued(param_hash, clap_plain_value);
+
+                let event = output_param_value_event(param_hash, clap_plain_value, time);
+                clap_call! { out=>try_push(out, &event.header) }
+            }
+            OutputParamEvent::EndGesture { param_hash } => {
+                let event = clap_event_param_gesture {
+                    header: clap_event_header {
+                        size: mem::size_of::<clap_event_param_gesture>() as u32,
+                        time,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_: CLAP_EVENT_PARAM_GESTURE_END,
+                        flags: CLAP_EVENT_IS_LIVE,
+                    },
+                    param_id: param_hash,
+                };
+
+                clap_call! { out=>try_push(out, &event.header) }
+            }
+        };
+
+        nih_debug_assert!(push_successful);
     }
 
     /// Handle an incoming CLAP event. The sample index is provided to support block splitting for
@@ -1392,6 +1823,22 @@ impl<P: ClapPlugin> Wrapper<P> {
         match (raw_event.space_id, raw_event.type_) {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE) => {
                 let event = &*(event as *const clap_event_param_value);
+                if let Some(param_ptr) = self.param_by_hash.get(&event.param_id) {
+                    let normalized_value =
+                        event.value as f32 / param_ptr.step_count().unwrap_or(1) as f32;
+
+                    // Changes that would overwrite the plugin's own recent writes are ignored, see
+                    // `Plugin::PARAM_WRITE_LOCK`
+                    if !self
+                        .param_write_locks
+                        .allows_host_change(&event.param_id, normalized_value)
+                    {
+                        return;
+                    }
+
+                    self.param_change_sources
+                        .host_changed(&event.param_id, normalized_value);
+                }
                 self.update_plain_value_by_hash(
                     event.param_id,
                     ClapParamUpdate::PlainValueSet(event.value),
@@ -1431,6 +1878,9 @@ impl<P: ClapPlugin> Wrapper<P> {
                             // The host may also add key and channel information here, but it may
                             // also pass -1. So not having that information here at all seems like
                             // the safest choice.
+                            // The offset is passed to the plugin as is and it never touches the
+                            // parameter's own value or smoother. That way the global smoother
+                            // doesn't end up fighting the per-voice modulation.
                             input_events.push_back(NoteEvent::PolyModulation {
                                 timing,
                                 voice_id: event.note_id,
@@ -1462,35 +1912,35 @@ impl<P: ClapPlugin> Wrapper<P> {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_ON) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
-                    input_events.push_back(NoteEvent::NoteOn {
+                    input_events.push_back(NoteEvent::note_on_or_off(
                         // When splitting up the buffer for sample accurate automation all events
                         // should be relative to the block
                         timing,
-                        voice_id: if event.note_id != -1 {
+                        if event.note_id != -1 {
                             Some(event.note_id)
                         } else {
                             None
                         },
-                        channel: event.channel as u8,
-                        note: event.key as u8,
-                        velocity: event.velocity as f32,
-                    });
+                        event.channel as u8,
+                        event.key as u8,
+                        event.velocity as f32,
+                    ));
                 }
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_OFF) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
-                    input_events.push_back(NoteEvent::NoteOff {
+                    input_events.push_back(NoteEvent::note_off(
                         timing,
-                        voice_id: if event.note_id != -1 {
+                        if event.note_id != -1 {
                             Some(event.note_id)
                         } else {
                             None
                         },
-                        channel: event.channel as u8,
-                        note: event.key as u8,
-                        velocity: event.velocity as f32,
-                    });
+                        event.channel as u8,
+                        event.key as u8,
+                        event.velocity as f32,
+                    ));
                 }
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_CHOKE) => {
@@ -1511,102 +1961,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_EXPRESSION) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
-                    // TODO: Add support for the other expression types
                     let event = &*(event as *const clap_event_note_expression);
-                    match event.expression_id {
-                        CLAP_NOTE_EXPRESSION_PRESSURE => {
-                            input_events.push_back(NoteEvent::PolyPressure {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                pressure: event.value as f32,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_VOLUME => {
-                            input_events.push_back(NoteEvent::PolyVolume {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                gain: event.value as f32,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_PAN => {
-                            input_events.push_back(NoteEvent::PolyPan {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                // In CLAP this value goes from [0, 1] instead of [-1, 1]
-                                pan: (event.value as f32 * 2.0) - 1.0,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_TUNING => {
-                            input_events.push_back(NoteEvent::PolyTuning {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                tuning: event.value as f32,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_VIBRATO => {
-                            input_events.push_back(NoteEvent::PolyVibrato {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                vibrato: event.value as f32,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_EXPRESSION => {
-                            input_events.push_back(NoteEvent::PolyExpression {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                expression: event.value as f32,
-                            });
-                        }
-                        CLAP_NOTE_EXPRESSION_BRIGHTNESS => {
-                            input_events.push_back(NoteEvent::PolyBrightness {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                brightness: event.value as f32,
-                            });
-                        }
-                        n => nih_debug_assert_failure!("Unhandled note expression ID {}", n),
+                    if let Some(event) = translate_note_expression(timing, event) {
+                        input_events.push_back(event);
                     }
                 }
             }
@@ -1662,6 +2019,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.editor_state,
             )
         }
     }
@@ -1712,6 +2070,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
     }
 
+    /// The channel counts for the currently active audio IO layout.
+    pub fn channel_counts(&self) -> ChannelCounts {
+        self.current_audio_io_layout.load().channel_counts()
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // Only make a callback if it's actually needed
         // XXX: For CLAP we could move this handling to the Plugin struct, but it may be worthwhile
@@ -1723,21 +2086,115 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
-    pub fn set_current_voice_capacity(&self, capacity: u32) {
-        match P::CLAP_POLY_MODULATION_CONFIG {
-            Some(config) => {
-                let clamped_capacity = capacity.clamp(1, config.max_voice_capacity);
-                nih_debug_assert_eq!(
-                    capacity,
-                    clamped_capacity,
-                    "The current voice capacity must be between 1 and the maximum capacity"
-                );
+    pub fn request_restart(&self) {
+        if self.restart_request.request() {
+            let task_posted = self.schedule_gui(Task::RestartRequested);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
 
-                if clamped_capacity != self.current_voice_capacity.load(Ordering::Relaxed) {
-                    self.current_voice_capacity
-                        .store(clamped_capacity, Ordering::Relaxed);
-                    let task_posted = self.schedule_gui(Task::VoiceInfoChanged);
-                    nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    pub fn mark_state_dirty(&self) {
+        if self.state_dirty_request.request() {
+            let task_posted = self.schedule_gui(Task::StateDirty);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
+    pub fn request_param_rescan(&self) {
+        if self.param_rescan_request.request() {
+            let task_posted = self.schedule_gui(Task::ParamRescanRequested);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
+    /// The raw `clap_host` pointer for
+    /// [`GuiContext::raw_host_handle()`][crate::prelude::GuiContext::raw_host_handle()] and friends.
+    pub fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Clap(&*self.host_callback as *const clap_host as *const c_void)
+    }
+
+    /// Ask the host for information about the track the plugin has been inserted on. Returns an
+    /// unknown hint if the host does not support the `track-info` extension.
+    pub fn chain_hint(&self) -> ChainHint {
+        match &*self.host_track_info.borrow() {
+            Some(host_track_info) => {
+                let mut info: clap_track_info = unsafe { mem::zeroed() };
+                let success =
+                    unsafe_clap_call! { host_track_info=>get(&*self.host_callback, &mut info) };
+                if success {
+                    chain_hint_from_track_info(&info)
+                } else {
+                    ChainHint::default()
+                }
+            }
+            None => ChainHint::default(),
+        }
+    }
+
+    /// Record the number of scratch buffers the plugin needs. These are allocated after the plugin
+    /// has been initialized.
+    pub fn request_scratch_buffers(&self, count: usize) {
+        self.scratch_buffer_count.store(count, Ordering::Relaxed);
+    }
+
+    /// Register a timer for the plugin's background IO. Only possible while the plugin is being
+    /// activated, see [`HostIo`].
+    pub fn register_host_timer(&self, period_ms: u32, callback: HostTimerCallback) -> bool {
+        unsafe {
+            self.host_io.register_timer(
+                &*self.host_callback,
+                self.host_timer_support.borrow().as_deref(),
+                period_ms,
+                callback,
+            )
+        }
+    }
+
+    /// Register a file descriptor for the plugin's background IO. Only possible while the plugin
+    /// is being activated, see [`HostIo`].
+    pub fn register_posix_fd(
+        &self,
+        fd: i32,
+        flags: PosixFdFlags,
+        callback: PosixFdCallback,
+    ) -> bool {
+        unsafe {
+            self.host_io.register_fd(
+                &*self.host_callback,
+                self.host_posix_fd_support.borrow().as_deref(),
+                fd,
+                flags,
+                callback,
+            )
+        }
+    }
+
+    /// Unregister all timers and file descriptors the plugin registered for its background IO.
+    fn unregister_host_io(&self) {
+        unsafe {
+            self.host_io.unregister_all(
+                &*self.host_callback,
+                self.host_timer_support.borrow().as_deref(),
+                self.host_posix_fd_support.borrow().as_deref(),
+            )
+        };
+    }
+
+    pub fn set_current_voice_capacity(&self, capacity: u32) {
+        match P::CLAP_POLY_MODULATION_CONFIG {
+            Some(config) => {
+                let clamped_capacity = capacity.clamp(1, config.max_voice_capacity);
+                nih_debug_assert_eq!(
+                    capacity,
+                    clamped_capacity,
+                    "The current voice capacity must be between 1 and the maximum capacity"
+                );
+
+                if clamped_capacity != self.current_voice_capacity.load(Ordering::Relaxed) {
+                    self.current_voice_capacity
+                        .store(clamped_capacity, Ordering::Relaxed);
+                    let task_posted = self.schedule_gui(Task::VoiceInfoChanged);
+                    nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
                 }
             }
             None => nih_debug_assert_failure!(
@@ -1772,6 +2229,13 @@ impl<P: ClapPlugin> Wrapper<P> {
                 state,
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                |param_ptr| {
+                    self.param_ptr_to_hash
+                        .get(&param_ptr)
+                        .map(|hash| self.param_gestures.preserves_on_state_load::<P>(hash))
+                        .unwrap_or(false)
+                },
+                &self.editor_state,
                 self.current_buffer_config.load().as_ref(),
             )
         });
@@ -1780,6 +2244,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             return false;
         }
 
+        // The plugin's state no longer matches the factory preset that was loaded last, if any
+        self.factory_presets.state_loaded();
+
         // If the plugin was already initialized then it needs to be reinitialized
         if let Some(buffer_config) = buffer_config {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -1791,7 +2258,12 @@ impl<P: ClapPlugin> Wrapper<P> {
                 plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context)
             });
             if success {
+                self.block_position.reset();
+                self.seek_detector.reset();
+                self.carried_input_events.reset();
                 process_wrapper(|| plugin.reset());
+                self.plugin_latency
+                    .update(&*plugin, |samples| self.set_latency_samples(samples));
             }
         }
 
@@ -1825,6 +2297,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             query_host_extension::<clap_host_latency>(&wrapper.host_callback, CLAP_EXT_LATENCY);
         *wrapper.host_params.borrow_mut() =
             query_host_extension::<clap_host_params>(&wrapper.host_callback, CLAP_EXT_PARAMS);
+        *wrapper.host_state.borrow_mut() =
+            query_host_extension::<clap_host_state>(&wrapper.host_callback, CLAP_EXT_STATE);
         *wrapper.host_voice_info.borrow_mut() = query_host_extension::<clap_host_voice_info>(
             &wrapper.host_callback,
             CLAP_EXT_VOICE_INFO,
@@ -1833,6 +2307,22 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.host_callback,
             CLAP_EXT_THREAD_CHECK,
         );
+        *wrapper.host_timer_support.borrow_mut() = query_host_extension::<clap_host_timer_support>(
+            &wrapper.host_callback,
+            CLAP_EXT_TIMER_SUPPORT,
+        );
+        *wrapper.host_posix_fd_support.borrow_mut() =
+            query_host_extension::<clap_host_posix_fd_support>(
+                &wrapper.host_callback,
+                CLAP_EXT_POSIX_FD_SUPPORT,
+            );
+        *wrapper.host_track_info.borrow_mut() = query_host_extension::<clap_host_track_info>(
+            &wrapper.host_callback,
+            CLAP_EXT_TRACK_INFO,
+        );
+
+        // If the plugin does not return an editor here, then the GUI extension won't be exposed
+        wrapper.init_editor();
 
         true
     }
@@ -1854,13 +2344,21 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
+        if !is_sample_rate_supported(P::SUPPORTED_SAMPLE_RATES, sample_rate as f32) {
+            nih_log!(
+                "The host tried to activate the plugin with an unsupported sample rate of {} Hz",
+                sample_rate
+            );
+            return false;
+        }
+
         let audio_io_layout = wrapper.current_audio_io_layout.load();
-        let buffer_config = BufferConfig {
-            sample_rate: sample_rate as f32,
-            min_buffer_size: Some(min_frames_count),
-            max_buffer_size: max_frames_count,
-            process_mode: wrapper.current_process_mode.load(),
-        };
+        let buffer_config = buffer_config_from_activate(
+            sample_rate,
+            min_frames_count,
+            max_frames_count,
+            wrapper.current_process_mode.load(),
+        );
 
         // Before initializing the plugin, make sure all smoothers are set the the default values
         for param in wrapper.param_by_hash.values() {
@@ -1870,7 +2368,14 @@ impl<P: ClapPlugin> Wrapper<P> {
         // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
         let mut init_context = wrapper.make_init_context();
         let mut plugin = wrapper.plugin.lock();
-        if plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context) {
+        wrapper.scratch_buffer_count.store(0, Ordering::Relaxed);
+        // The plugin can only register timers and file descriptors for its background IO during
+        // activation, and it does so again every time it gets activated
+        wrapper.unregister_host_io();
+        wrapper.host_io.set_accepting_registrations(true);
+        let initialized = plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context);
+        wrapper.host_io.set_accepting_registrations(false);
+        if initialized {
             // NOTE: `Plugin::reset()` is called in `clap_plugin::start_processing()` instead of in
             //       this function
 
@@ -1878,12 +2383,28 @@ impl<P: ClapPlugin> Wrapper<P> {
             // pointers into a set of `Buffer` objects for the plugin's main and auxiliary IO
             *wrapper.buffer_manager.borrow_mut() =
                 BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            *wrapper.padded_buffer.borrow_mut() = PaddedBuffer::for_audio_io_layout(
+                max_frames_count as usize,
+                audio_io_layout,
+                P::SIMD_BLOCK_MULTIPLE,
+            );
+            *wrapper.scratch_buffers.borrow_mut() = ScratchBuffers::new(
+                wrapper.scratch_buffer_count.load(Ordering::Relaxed),
+                max_frames_count as usize,
+            );
 
             // Also store this for later, so we can reinitialize the plugin after restoring state
             wrapper.current_buffer_config.store(Some(buffer_config));
+            wrapper.is_activated.store(true, Ordering::SeqCst);
+            wrapper.first_process.activated();
+            wrapper
+                .plugin_latency
+                .update(&*plugin, |samples| wrapper.set_latency_samples(samples));
 
             true
         } else {
+            wrapper.unregister_host_io();
+
             false
         }
     }
@@ -1892,7 +2413,10 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!((), plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
+        wrapper.is_activated.store(false, Ordering::SeqCst);
+        wrapper.first_process.deactivated();
         wrapper.plugin.lock().deactivate();
+        wrapper.unregister_host_io();
     }
 
     unsafe extern "C" fn start_processing(plugin: *const clap_plugin) -> bool {
@@ -1907,7 +2431,7 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         // To be consistent with the VST3 wrapper, we'll also reset the buffers here in addition to
         // the dedicated `reset()` function.
-        process_wrapper(|| wrapper.plugin.lock().reset());
+        wrapper.reset_plugin();
 
         true
     }
@@ -1923,7 +2447,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!((), plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        process_wrapper(|| wrapper.plugin.lock().reset());
+        wrapper.reset_plugin();
     }
 
     unsafe extern "C" fn process(
@@ -1957,6 +2481,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             // The host may send new transport information as an event. In that case we'll also
             // split the buffer.
             let mut transport_info = process.transport;
+            let tempo_map = Self::collect_tempo_map(process.in_events.as_ref(), transport_info);
 
             let result = loop {
                 if !process.in_events.is_null() {
@@ -2010,6 +2535,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
+                // Hosts may call the process function without any samples to flush events. The
+                // parameter changes have already been applied at this point, and the note events
+                // are delivered at the start of the next block since the plugin's process function
+                // is not called for empty buffers.
+                if total_buffer_len == 0 {
+                    wrapper
+                        .carried_input_events
+                        .carry_over(&mut wrapper.input_events.borrow_mut());
+                    if !process.out_events.is_null() {
+                        wrapper.handle_out_events(&*process.out_events, 0, 0);
+                    }
+
+                    break CLAP_PROCESS_CONTINUE;
+                }
+
                 // After processing the events we now know where/if the block should be split, and
                 // we can start preparing audio processing
                 let block_len = block_end - block_start;
@@ -2129,6 +2669,9 @@ impl<P: ClapPlugin> Wrapper<P> {
                     .load()
                     .expect("Process call without prior initialization call")
                     .sample_rate;
+                // The bypass parameter's value for this block is now known
+                wrapper.bypass_state.update(sample_rate);
+
                 let mut transport = Transport::new(sample_rate);
                 if !transport_info.is_null() {
                     let context = &*transport_info;
@@ -2139,6 +2682,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                         Some(context.flags & CLAP_TRANSPORT_IS_WITHIN_PRE_ROLL != 0);
                     if context.flags & CLAP_TRANSPORT_HAS_TEMPO != 0 {
                         transport.tempo = Some(context.tempo);
+                        transport.tempo_map = tempo_map.starting_at(block_start as u32);
                     }
                     if context.flags & CLAP_TRANSPORT_HAS_TIME_SIGNATURE != 0 {
                         transport.time_sig_numerator = Some(context.tsig_num as i32);
@@ -2213,7 +2757,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
-                let result = if buffer_is_valid {
+                let skip_process = skip_silent_block(
+                    P::SILENCE_IN_SILENCE_OUT,
+                    !wrapper.input_events.borrow().is_empty(),
+                    buffers.main_buffer,
+                    buffers.aux_inputs,
+                );
+                let result = if buffer_is_valid && skip_process {
+                    // The plugin promised to output silence when its input is silent. The main
+                    // buffer is processed in place and the auxiliary outputs have already been
+                    // cleared, so the outputs are already silent.
+                    wrapper.block_position.advance(&transport, block_len);
+                    wrapper.seek_detector.skip(&transport, block_len);
+                    wrapper.last_process_status.store(ProcessStatus::Normal);
+                    ProcessStatus::Normal
+                } else if buffer_is_valid {
                     let mut plugin = wrapper.plugin.lock();
                     // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
                     //         slices (which it cannot do without using unsafe code), then they
@@ -2222,9 +2780,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                         inputs: buffers.aux_inputs,
                         outputs: buffers.aux_outputs,
                     };
-                    let mut context = wrapper.make_process_context(transport);
-                    let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
+                    let mut context = wrapper.make_process_context(transport, block_len);
+                    wrapper
+                        .first_process
+                        .before_process(|| plugin.first_process(&mut context));
+                    let result = wrapper
+                        .padded_buffer
+                        .borrow_mut()
+                        .process(buffers.main_buffer, |buffer| {
+                            plugin.process(buffer, &mut aux, &mut context)
+                        });
                     wrapper.last_process_status.store(result);
+                    // Parameter changes from this block may have changed the plugin's latency
+                    wrapper
+                        .plugin_latency
+                        .update(&*plugin, |samples| wrapper.set_latency_samples(samples));
                     result
                 } else {
                     ProcessStatus::Normal
@@ -2292,6 +2862,8 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         if id == CLAP_EXT_AUDIO_PORTS_CONFIG {
             &wrapper.clap_plugin_audio_ports_config as *const _ as *const c_void
+        } else if id == CLAP_EXT_CONFIGURABLE_AUDIO_PORTS {
+            &wrapper.clap_plugin_configurable_audio_ports as *const _ as *const c_void
         } else if id == CLAP_EXT_AUDIO_PORTS {
             &wrapper.clap_plugin_audio_ports as *const _ as *const c_void
         } else if id == CLAP_EXT_GUI && wrapper.editor.borrow().is_some() {
@@ -2305,14 +2877,21 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.clap_plugin_note_ports as *const _ as *const c_void
         } else if id == CLAP_EXT_PARAMS {
             &wrapper.clap_plugin_params as *const _ as *const c_void
+        } else if id == CLAP_EXT_PARAM_INDICATION {
+            &wrapper.clap_plugin_param_indication as *const _ as *const c_void
         } else if id == CLAP_EXT_REMOTE_CONTROLS {
             &wrapper.clap_plugin_remote_controls as *const _ as *const c_void
+        } else if id == CLAP_EXT_POSIX_FD_SUPPORT {
+            &wrapper.clap_plugin_posix_fd_support as *const _ as *const c_void
         } else if id == CLAP_EXT_RENDER {
             &wrapper.clap_plugin_render as *const _ as *const c_void
         } else if id == CLAP_EXT_STATE {
             &wrapper.clap_plugin_state as *const _ as *const c_void
         } else if id == CLAP_EXT_TAIL {
             &wrapper.clap_plugin_tail as *const _ as *const c_void
+        } else if id == CLAP_EXT_TIMER_SUPPORT {
+            // The timers are used to drive the editor and the plugin's own background IO
+            &wrapper.clap_plugin_timer_support as *const _ as *const c_void
         } else if id == CLAP_EXT_VOICE_INFO && P::CLAP_POLY_MODULATION_CONFIG.is_some() {
             &wrapper.clap_plugin_voice_info as *const _ as *const c_void
         } else {
@@ -2421,6 +3000,66 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_configurable_audio_ports_can_apply(
+        plugin: *const clap_plugin,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper
+            .resolve_audio_port_configuration_requests(requests, request_count)
+            .is_some()
+    }
+
+    unsafe extern "C" fn ext_configurable_audio_ports_apply(
+        plugin: *const clap_plugin,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // The host will rescan the audio ports and reactivate the plugin after this, at which
+        // point the plugin is initialized with the new layout
+        match wrapper.resolve_audio_port_configuration_requests(requests, request_count) {
+            Some(audio_io_layout) => {
+                wrapper.current_audio_io_layout.store(audio_io_layout);
+
+                true
+            }
+            None => {
+                nih_debug_assert_failure!(
+                    "Host tried to apply an unsupported audio port configuration"
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Find the audio IO layout matching the host's `configurable-audio-ports` requests, relative
+    /// to the currently active layout.
+    unsafe fn resolve_audio_port_configuration_requests(
+        &self,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> Option<AudioIOLayout> {
+        let requests = if request_count == 0 {
+            &[]
+        } else {
+            check_null_ptr!(None, requests);
+            std::slice::from_raw_parts(requests, request_count as usize)
+        };
+
+        resolve_audio_port_configuration(
+            P::AUDIO_IO_LAYOUTS,
+            &self.current_audio_io_layout.load(),
+            requests,
+        )
+    }
+
     unsafe extern "C" fn ext_audio_ports_count(plugin: *const clap_plugin, is_input: bool) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -2484,6 +3123,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             index + num_input_ports
         };
         let pair_stable_id = match (is_input, is_main_port) {
+            _ if !P::IN_PLACE_PROCESSING => CLAP_INVALID_ID,
             // Ports are named linearly with inputs coming before outputs, so this is the index of
             // the first output port
             (true, true) if has_main_output => num_input_ports,
@@ -2554,37 +3194,33 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 
     unsafe extern "C" fn ext_gui_is_api_supported(
-        _plugin: *const clap_plugin,
+        plugin: *const clap_plugin,
         api: *const c_char,
         is_floating: bool,
     ) -> bool {
-        // We don't do standalone floating windows
-        if is_floating {
-            return false;
-        }
-
-        #[cfg(all(target_family = "unix", not(target_os = "macos")))]
-        if CStr::from_ptr(api) == CLAP_WINDOW_API_X11 {
-            return true;
-        }
-        #[cfg(target_os = "macos")]
-        if CStr::from_ptr(api) == CLAP_WINDOW_API_COCOA {
-            return true;
-        }
-        #[cfg(target_os = "windows")]
-        if CStr::from_ptr(api) == CLAP_WINDOW_API_WIN32 {
-            return true;
-        }
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, api);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        false
+        let editor = wrapper.editor.borrow();
+        let editor = editor.as_ref().map(|editor| editor.lock());
+        is_gui_api_supported(
+            editor.as_deref().map(|editor| &**editor),
+            CStr::from_ptr(api),
+            is_floating,
+        )
     }
 
     unsafe extern "C" fn ext_gui_get_preferred_api(
-        _plugin: *const clap_plugin,
+        plugin: *const clap_plugin,
         api: *mut *const c_char,
         is_floating: *mut bool,
     ) -> bool {
-        check_null_ptr!(false, api, is_floating);
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, api, is_floating);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if wrapper.editor.borrow().is_none() {
+            return false;
+        }
 
         #[cfg(all(target_family = "unix", not(target_os = "macos")))]
         {
@@ -2636,6 +3272,10 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         let mut editor_handle = wrapper.editor_handle.lock();
         if editor_handle.is_some() {
+            wrapper.editor_timer.unregister(
+                &*wrapper.host_callback,
+                wrapper.host_timer_support.borrow().as_deref(),
+            );
             *editor_handle = None;
         } else {
             nih_debug_assert_failure!("Tried destroying editor while the editor was not active");
@@ -2652,14 +3292,12 @@ impl<P: ClapPlugin> Wrapper<P> {
             return false;
         }
 
-        if wrapper
-            .editor
-            .borrow()
-            .as_ref()
-            .unwrap()
-            .lock()
-            .set_scale_factor(scale as f32)
-        {
+        let editor = wrapper.editor.borrow();
+        let editor = match editor.as_ref() {
+            Some(editor) => editor,
+            None => return false,
+        };
+        if editor.lock().set_scale_factor(scale as f32) {
             wrapper
                 .editor_scaling_factor
                 .store(scale as f32, std::sync::atomic::Ordering::Relaxed);
@@ -2678,8 +3316,10 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         // For macOS the scaling factor is always 1
-        let (unscaled_width, unscaled_height) =
-            wrapper.editor.borrow().as_ref().unwrap().lock().size();
+        let (unscaled_width, unscaled_height) = match wrapper.editor.borrow().as_ref() {
+            Some(editor) => editor.lock().size(),
+            None => return false,
+        };
         let scaling_factor = wrapper.editor_scaling_factor.load(Ordering::Relaxed);
         (*width, *height) = (
             (unscaled_width as f32 * scaling_factor).round() as u32,
@@ -2703,12 +3343,21 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 
     unsafe extern "C" fn ext_gui_adjust_size(
-        _plugin: *const clap_plugin,
-        _width: *mut u32,
-        _height: *mut u32,
+        plugin: *const clap_plugin,
+        width: *mut u32,
+        height: *mut u32,
     ) -> bool {
-        // TODO: Implement Host->Plugin GUI resizing
-        false
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, width, height);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // The editor gets to snap the host's proposed size to a size it can actually take on
+        let scaling_factor = wrapper.editor_scaling_factor.load(Ordering::Relaxed);
+        (*width, *height) = match wrapper.editor.borrow().as_ref() {
+            Some(editor) => adjust_editor_size(&**editor.lock(), (*width, *height), scaling_factor),
+            None => return false,
+        };
+
+        true
     }
 
     unsafe extern "C" fn ext_gui_set_size(
@@ -2721,8 +3370,10 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        let (unscaled_width, unscaled_height) =
-            wrapper.editor.borrow().as_ref().unwrap().lock().size();
+        let (unscaled_width, unscaled_height) = match wrapper.editor.borrow().as_ref() {
+            Some(editor) => editor.lock().size(),
+            None => return false,
+        };
         let scaling_factor = wrapper.editor_scaling_factor.load(Ordering::Relaxed);
         let (editor_width, editor_height) = (
             (unscaled_width as f32 * scaling_factor).round() as u32,
@@ -2763,13 +3414,30 @@ impl<P: ClapPlugin> Wrapper<P> {
                     return false;
                 };
 
-                // This extension is only exposed when we have an editor
-                *editor_handle = Some(wrapper.editor.borrow().as_ref().unwrap().lock().spawn(
-                    ParentWindowHandle { handle },
-                    wrapper.clone().make_gui_context(),
-                ));
+                // This extension is only exposed when we have an editor, but the host may still
+                // call this function if it ignored that
+                let editor = wrapper.editor.borrow();
+                match editor.as_ref() {
+                    Some(editor) => {
+                        let editor = editor.lock();
+                        *editor_handle = Some(editor.spawn(
+                            ParentWindowHandle { handle },
+                            wrapper.clone().make_gui_context(),
+                        ));
 
-                true
+                        // If the host doesn't support timers, then the editor keeps driving itself
+                        if let Some(period_ms) = editor.host_timer_interval_ms() {
+                            wrapper.editor_timer.register(
+                                &*wrapper.host_callback,
+                                wrapper.host_timer_support.borrow().as_deref(),
+                                period_ms,
+                            );
+                        }
+
+                        true
+                    }
+                    None => false,
+                }
             } else {
                 nih_debug_assert_failure!(
                     "Host tried to attach editor while the editor is already attached"
@@ -2797,15 +3465,18 @@ impl<P: ClapPlugin> Wrapper<P> {
         // This is only relevant for floating windows
     }
 
-    unsafe extern "C" fn ext_gui_show(_plugin: *const clap_plugin) -> bool {
-        // TODO: Does this get used? Is this only for the free-standing window extension? (which we
-        //       don't implement) This wouldn't make any sense for embedded editors.
-        false
+    unsafe extern "C" fn ext_gui_show(plugin: *const clap_plugin) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper.set_editor_visible(true)
     }
 
-    unsafe extern "C" fn ext_gui_hide(_plugin: *const clap_plugin) -> bool {
-        // TODO: Same as the above
-        false
+    unsafe extern "C" fn ext_gui_hide(plugin: *const clap_plugin) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper.set_editor_visible(false)
     }
 
     unsafe extern "C" fn ext_latency_get(plugin: *const clap_plugin) -> u32 {
@@ -2817,7 +3488,7 @@ impl<P: ClapPlugin> Wrapper<P> {
 
     unsafe extern "C" fn ext_note_ports_count(_plugin: *const clap_plugin, is_input: bool) -> u32 {
         match is_input {
-            true if P::MIDI_INPUT >= MidiConfig::Basic => 1,
+            true if P::MIDI_INPUT >= MidiConfig::Basic => P::MIDI_INPUT_PORTS,
             false if P::MIDI_OUTPUT >= MidiConfig::Basic => 1,
             _ => 0,
         }
@@ -2830,29 +3501,38 @@ impl<P: ClapPlugin> Wrapper<P> {
         info: *mut clap_note_port_info,
     ) -> bool {
         match (index, is_input) {
-            (0, true) if P::MIDI_INPUT >= MidiConfig::Basic => {
+            (index, true) if P::MIDI_INPUT >= MidiConfig::Basic && index < P::MIDI_INPUT_PORTS => {
                 *info = std::mem::zeroed();
 
+                let (supported_dialects, preferred_dialect) =
+                    note_port_dialects(P::MIDI_INPUT, P::CLAP_PREFER_MIDI_NOTE_DIALECT)
+                        .expect("No note dialects for a note input port");
+
                 let info = &mut *info;
-                info.id = 0;
-                // NOTE: REAPER won't send us SysEx if we don't support the MIDI dialect
-                // TODO: Implement MPE (would just be a toggle for the plugin to expose it) and MIDI2
-                info.supported_dialects = CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI;
-                info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
-                strlcpy(&mut info.name, "Note Input");
+                info.id = index;
+                info.supported_dialects = supported_dialects;
+                info.preferred_dialect = preferred_dialect;
+                strlcpy(
+                    &mut info.name,
+                    &note_input_port_name(index, P::MIDI_INPUT_PORTS),
+                );
 
                 true
             }
             (0, false) if P::MIDI_OUTPUT >= MidiConfig::Basic => {
                 *info = std::mem::zeroed();
 
+                let (supported_dialects, preferred_dialect) =
+                    note_port_dialects(P::MIDI_OUTPUT, P::CLAP_PREFER_MIDI_NOTE_DIALECT)
+                        .expect("No note dialects for a note output port");
+
                 let info = &mut *info;
                 info.id = 0;
                 // If `P::MIDI_OUTPUT < MidiConfig::MidiCCs` we'll throw away MIDI CCs, pitch bend
                 // messages, and other messages that are not basic note on, off and polyphonic
                 // pressure messages. This way the behavior is the same as the VST3 wrapper.
-                info.supported_dialects = CLAP_NOTE_DIALECT_CLAP | CLAP_NOTE_DIALECT_MIDI;
-                info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
+                info.supported_dialects = supported_dialects;
+                info.preferred_dialect = preferred_dialect;
                 strlcpy(&mut info.name, "Note Output");
 
                 true
@@ -2885,10 +3565,6 @@ impl<P: ClapPlugin> Wrapper<P> {
         let param_ptr = &wrapper.param_by_hash[param_hash];
         let default_value = param_ptr.default_normalized_value();
         let step_count = param_ptr.step_count();
-        let flags = param_ptr.flags();
-        let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
-        let hidden = flags.contains(ParamFlags::HIDDEN);
-        let is_bypass = flags.contains(ParamFlags::BYPASS);
 
         *param_info = std::mem::zeroed();
 
@@ -2896,23 +3572,12 @@ impl<P: ClapPlugin> Wrapper<P> {
         //       hashmap lookup, but for now we'll stay consistent with the VST3 implementation.
         let param_info = &mut *param_info;
         param_info.id = *param_hash;
-        // TODO: Somehow expose per note/channel/port modulation
-        param_info.flags = 0;
-        if automatable && !hidden {
-            param_info.flags |= CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE;
-            if wrapper.poly_mod_ids_by_hash.contains_key(param_hash) {
-                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
-            }
-        }
-        if hidden {
-            param_info.flags |= CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY;
-        }
-        if is_bypass {
-            param_info.flags |= CLAP_PARAM_IS_BYPASS
-        }
-        if step_count.is_some() {
-            param_info.flags |= CLAP_PARAM_IS_STEPPED
-        }
+        param_info.flags = param_info_flags(
+            param_ptr.flags(),
+            step_count.is_some(),
+            param_ptr.appearance() == ParamAppearance::Dropdown,
+            wrapper.poly_mod_ids_by_hash.contains_key(param_hash),
+        );
         param_info.cookie = std::ptr::null_mut();
         strlcpy(&mut param_info.name, param_ptr.name());
         strlcpy(&mut param_info.module, param_group);
@@ -3022,6 +3687,46 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_param_indication_set_mapping(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        has_mapping: bool,
+        color: *const clap_color,
+        label: *const c_char,
+        description: *const c_char,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if !wrapper.param_by_hash.contains_key(&param_id) {
+            nih_debug_assert_failure!("Unknown parameter hash: {}", param_id);
+            return;
+        }
+
+        wrapper
+            .param_indications
+            .set_mapping(param_id, has_mapping, color, label, description);
+    }
+
+    unsafe extern "C" fn ext_param_indication_set_automation(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        automation_state: u32,
+        color: *const clap_color,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if !wrapper.param_by_hash.contains_key(&param_id) {
+            nih_debug_assert_failure!("Unknown parameter hash: {}", param_id);
+            return;
+        }
+
+        wrapper
+            .param_indications
+            .set_automation(param_id, automation_state, color);
+    }
+
     unsafe extern "C" fn ext_remote_controls_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -3062,13 +3767,14 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         let mode = match mode {
             CLAP_RENDER_REALTIME => ProcessMode::Realtime,
-            // Even if the plugin has a hard realtime requirement, we'll still honor this
             CLAP_RENDER_OFFLINE => ProcessMode::Offline,
             n => {
                 nih_debug_assert_failure!("Unknown rendering mode '{}', defaulting to realtime", n);
                 ProcessMode::Realtime
             }
         };
+        // Even if the plugin has a hard realtime requirement, we'll still honor this
+        check_process_mode(P::HARD_REALTIME_ONLY, mode);
         wrapper.current_process_mode.store(mode);
 
         true
@@ -3081,37 +3787,41 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        let serialized = state::serialize_json::<P>(
+        // The host may not have flushed the parameter changes made in the editor yet
+        wrapper.apply_queued_parameter_values();
+
+        // CLAP does not provide a way to tell how much data there is left in a stream. Instead of
+        // prepending the state's length, which would require serializing the entire state upfront,
+        // the state is streamed to the host in length-prefixed chunks.
+        if !write_stream(&*stream, &CHUNKED_STATE_MARKER.to_le_bytes()) {
+            nih_debug_assert_failure!(
+                "Error or end of stream while writing the state header to the stream."
+            );
+            return false;
+        }
+
+        let mut writer = ChunkedWriter::new(ClapOStream(&*stream));
+        let serialized = state::serialize_json_to_writer::<P>(
             wrapper.params.clone(),
             state::make_params_iter(&wrapper.param_by_hash, &wrapper.param_id_to_hash),
+            &wrapper.editor_state,
+            &mut writer,
         );
-        match serialized {
-            Ok(serialized) => {
-                // CLAP does not provide a way to tell how much data there is left in a stream, so
-                // we need to prepend it to our actual state data.
-                let length_bytes = (serialized.len() as u64).to_le_bytes();
-                if !write_stream(&*stream, &length_bytes) {
-                    nih_debug_assert_failure!(
-                        "Error or end of stream while writing the state length to the stream."
-                    );
-                    return false;
-                }
-                if !write_stream(&*stream, &serialized) {
-                    nih_debug_assert_failure!(
-                        "Error or end of stream while writing the state buffer to the stream."
-                    );
-                    return false;
-                }
+        if let Err(err) = serialized {
+            nih_debug_assert_failure!("Could not save state: {:#}", err);
+            return false;
+        }
+        if let Err(err) = writer.finish() {
+            nih_debug_assert_failure!(
+                "Error or end of stream while writing the state to the stream: {}",
+                err
+            );
+            return false;
+        }
 
-                nih_trace!("Saved state ({} bytes)", serialized.len());
+        nih_trace!("Saved state");
 
-                true
-            }
-            Err(err) => {
-                nih_debug_assert_failure!("Could not save state: {:#}", err);
-                false
-            }
-        }
+        true
     }
 
     unsafe extern "C" fn ext_state_load(
@@ -3122,7 +3832,8 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         // CLAP does not have a way to tell how much data there is left in a stream, so we've
-        // prepended the size in front of our JSON state
+        // either prepended the size in front of our JSON state, or the state was streamed in
+        // length-prefixed chunks
         let mut length_bytes = [0u8; 8];
         if !read_stream(&*stream, length_bytes.as_mut_slice()) {
             nih_debug_assert_failure!(
@@ -3132,20 +3843,26 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
         let length = u64::from_le_bytes(length_bytes);
 
-        let mut read_buffer: Vec<u8> = Vec::with_capacity(length as usize);
-        if !read_stream(&*stream, read_buffer.spare_capacity_mut()) {
-            nih_debug_assert_failure!(
-                "Error or end of stream while reading the state buffer from the stream."
-            );
-            return false;
-        }
-        read_buffer.set_len(length as usize);
+        let state = if length == CHUNKED_STATE_MARKER {
+            state::deserialize_json_from_reader(ChunkedReader::new(ClapIStream(&*stream)))
+        } else {
+            let mut read_buffer: Vec<u8> = Vec::with_capacity(length as usize);
+            if !read_stream(&*stream, read_buffer.spare_capacity_mut()) {
+                nih_debug_assert_failure!(
+                    "Error or end of stream while reading the state buffer from the stream."
+                );
+                return false;
+            }
+            read_buffer.set_len(length as usize);
+
+            state::deserialize_json(&read_buffer)
+        };
 
-        match state::deserialize_json(&read_buffer) {
+        match state {
             Some(mut state) => {
                 let success = wrapper.set_state_inner(&mut state);
                 if success {
-                    nih_trace!("Loaded state ({} bytes)", read_buffer.len());
+                    nih_trace!("Loaded state");
                 }
 
                 success
@@ -3165,6 +3882,33 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_timer_support_on_timer(plugin: *const clap_plugin, timer_id: clap_id) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if wrapper.editor_handle.lock().is_some() {
+            if let Some(editor) = wrapper.editor.borrow().as_ref() {
+                if wrapper.editor_timer.on_timer(timer_id, &**editor.lock()) {
+                    return;
+                }
+            }
+        }
+
+        wrapper.host_io.on_timer(timer_id);
+    }
+
+    unsafe extern "C" fn ext_posix_fd_support_on_fd(
+        plugin: *const clap_plugin,
+        fd: i32,
+        flags: clap_posix_fd_flags,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let handled = wrapper.host_io.on_fd(fd, flags);
+        nih_debug_assert!(handled, "Unknown file descriptor {}", fd);
+    }
+
     unsafe extern "C" fn ext_voice_info_get(
         plugin: *const clap_plugin,
         info: *mut clap_voice_info,
@@ -3208,3 +3952,527 @@ unsafe fn query_host_extension<T>(
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap_sys::audio_buffer::clap_audio_buffer;
+    use clap_sys::ext::params::clap_param_rescan_flags;
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::{
+        new_nonzero_u32, BoolParam, Buffer, FactoryPreset, FloatParam, FloatRange, GuiContext,
+        InitContext, Param, ProcessContext, SmoothingStyle,
+    };
+    use crate::wrapper::clap::util::output_param_value_event;
+    use crate::wrapper::state::ParamValue;
+
+    fn mock_host() -> clap_host {
+        // The wrapper doesn't call into the host until the plugin is initialized, so none of these
+        // callbacks need to be implemented
+        clap_host {
+            clap_version: clap_sys::version::CLAP_VERSION,
+            host_data: std::ptr::null_mut(),
+            name: b"Mock Host\0".as_ptr() as *const c_char,
+            vendor: b"Moist Plugins GmbH\0".as_ptr() as *const c_char,
+            url: b"https://example.com\0".as_ptr() as *const c_char,
+            version: b"0.0.1\0".as_ptr() as *const c_char,
+            get_extension: None,
+            request_restart: None,
+            request_process: None,
+            request_callback: None,
+        }
+    }
+
+    struct HostHandle;
+
+    impl TestPluginConfig for HostHandle {
+        const NAME: &'static str = "Host Handle";
+
+        type Params = NoParams;
+    }
+
+    #[test]
+    fn raw_host_handle_points_to_host() {
+        let host = mock_host();
+        let expected = RawHostHandle::Clap(&host as *const clap_host as *const c_void);
+
+        let wrapper = Wrapper::<TestPlugin<HostHandle>>::new(&host);
+        let init_handle = unsafe { wrapper.make_init_context().raw_host_handle() };
+        let gui_handle = unsafe { wrapper.clone().make_gui_context().raw_host_handle() };
+        assert_eq!(init_handle, expected);
+        assert_eq!(gui_handle, expected);
+        match init_handle {
+            RawHostHandle::Clap(ptr) => assert!(!ptr.is_null()),
+            _ => panic!("Expected a CLAP host handle"),
+        }
+    }
+
+    struct SampleAccurateBypass;
+
+    struct BypassParams {
+        bypass: BoolParam,
+        wet: FloatParam,
+    }
+
+    impl Default for BypassParams {
+        fn default() -> Self {
+            Self {
+                bypass: BoolParam::new("Bypass", false).make_bypass(),
+                wet: FloatParam::new("Wet", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                    .with_smoother(SmoothingStyle::Linear(10.0))
+                    .with_neutral_on_bypass(0.0),
+            }
+        }
+    }
+
+    unsafe impl Params for BypassParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("bypass"), self.bypass.as_ptr(), String::new()),
+                (String::from("wet"), self.wet.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    impl TestPluginConfig for SampleAccurateBypass {
+        const NAME: &'static str = "Sample Accurate Bypass";
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+            main_output_channels: Some(new_nonzero_u32(1)),
+            ..AudioIOLayout::const_default()
+        }];
+        const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+        type Params = BypassParams;
+
+        /// Writes the smoothed wet amount to the output so the test can see when the smoother
+        /// started moving.
+        fn process(
+            params: &Self::Params,
+            buffer: &mut Buffer,
+            _context: &mut impl ProcessContext<TestPlugin<Self>>,
+        ) -> ProcessStatus {
+            for channel_samples in buffer.iter_samples() {
+                let wet = params.wet.smoothed.next();
+                for sample in channel_samples {
+                    *sample = wet;
+                }
+            }
+
+            ProcessStatus::Normal
+        }
+    }
+
+    unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+        (*((*list).ctx as *const Vec<clap_event_param_value>)).len() as u32
+    }
+
+    unsafe extern "C" fn events_get(
+        list: *const clap_input_events,
+        index: u32,
+    ) -> *const clap_event_header {
+        let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+        // The wrapper casts this back to the full event, so the pointer needs to cover all of it
+        &events[index as usize] as *const clap_event_param_value as *const clap_event_header
+    }
+
+    #[test]
+    fn bypass_ramp_starts_at_automated_sample() {
+        const BLOCK_SIZE: usize = 256;
+        const BYPASS_SAMPLE: usize = 100;
+
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<SampleAccurateBypass>>::new(&host);
+        let plugin = &*wrapper.clap_plugin.borrow() as *const clap_plugin;
+        let bypass_hash = wrapper.param_id_to_hash["bypass"];
+
+        // The wrapper only splits the buffer before events that come after the first one, so the
+        // automation starts with a redundant event at the start of the buffer like in most hosts
+        let events = vec![
+            output_param_value_event(bypass_hash, 0.0, 0),
+            output_param_value_event(bypass_hash, 1.0, BYPASS_SAMPLE as u32),
+        ];
+        let in_events = clap_input_events {
+            ctx: &events as *const Vec<clap_event_param_value> as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+
+        let mut output = vec![0.0f32; BLOCK_SIZE];
+        let mut channel_pointers = [output.as_mut_ptr()];
+        let mut audio_output = clap_audio_buffer {
+            data32: channel_pointers.as_mut_ptr(),
+            data64: std::ptr::null_mut(),
+            channel_count: 1,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let process = clap_process {
+            steady_time: -1,
+            frames_count: BLOCK_SIZE as u32,
+            transport: std::ptr::null(),
+            audio_inputs: std::ptr::null(),
+            audio_outputs: &mut audio_output,
+            audio_inputs_count: 0,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: std::ptr::null(),
+        };
+
+        unsafe {
+            assert!(Wrapper::<TestPlugin<SampleAccurateBypass>>::activate(
+                plugin,
+                48000.0,
+                1,
+                BLOCK_SIZE as u32
+            ));
+            assert!(Wrapper::<TestPlugin<SampleAccurateBypass>>::start_processing(plugin));
+            Wrapper::<TestPlugin<SampleAccurateBypass>>::process(plugin, &process);
+        }
+
+        // The smoother should only start ramping towards the neutral value once the bypass
+        // parameter has actually been automated
+        assert!(output[..BYPASS_SAMPLE].iter().all(|&sample| sample == 1.0));
+        assert!(output[BYPASS_SAMPLE] < 1.0);
+        assert!(output[BYPASS_SAMPLE..]
+            .windows(2)
+            .all(|samples| samples[1] < samples[0]));
+    }
+
+    struct Scratch;
+
+    const SCRATCH_BUFFER_COUNT: usize = 3;
+
+    impl TestPluginConfig for Scratch {
+        const NAME: &'static str = "Scratch";
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+            main_output_channels: Some(new_nonzero_u32(1)),
+            ..AudioIOLayout::const_default()
+        }];
+
+        type Params = NoParams;
+
+        fn initialize(context: &mut impl InitContext<TestPlugin<Self>>) -> bool {
+            context.request_scratch_buffers(SCRATCH_BUFFER_COUNT);
+            true
+        }
+
+        /// Writes the number of scratch buffers followed by their lengths to the output, since
+        /// panicking inside of the wrapper's process function would abort the test.
+        fn process(
+            _params: &Self::Params,
+            buffer: &mut Buffer,
+            context: &mut impl ProcessContext<TestPlugin<Self>>,
+        ) -> ProcessStatus {
+            let scratch_buffers = context.scratch_buffers();
+            let output = &mut buffer.as_slice()[0];
+            output[0] = scratch_buffers.len() as f32;
+            for (sample, scratch_buffer) in output[1..].iter_mut().zip(scratch_buffers.iter()) {
+                *sample = scratch_buffer.len() as f32;
+            }
+
+            ProcessStatus::Normal
+        }
+    }
+
+    #[test]
+    fn scratch_buffers_requested_during_initialization() {
+        const MAX_BLOCK_SIZE: usize = 512;
+        const BLOCK_SIZE: usize = 16;
+
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<Scratch>>::new(&host);
+        let plugin = &*wrapper.clap_plugin.borrow() as *const clap_plugin;
+
+        let mut output = vec![0.0f32; BLOCK_SIZE];
+        let mut channel_pointers = [output.as_mut_ptr()];
+        let mut audio_output = clap_audio_buffer {
+            data32: channel_pointers.as_mut_ptr(),
+            data64: std::ptr::null_mut(),
+            channel_count: 1,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let process = clap_process {
+            steady_time: -1,
+            frames_count: BLOCK_SIZE as u32,
+            transport: std::ptr::null(),
+            audio_inputs: std::ptr::null(),
+            audio_outputs: &mut audio_output,
+            audio_inputs_count: 0,
+            audio_outputs_count: 1,
+            in_events: std::ptr::null(),
+            out_events: std::ptr::null(),
+        };
+
+        unsafe {
+            assert!(Wrapper::<TestPlugin<Scratch>>::activate(
+                plugin,
+                48000.0,
+                1,
+                MAX_BLOCK_SIZE as u32
+            ));
+            assert!(Wrapper::<TestPlugin<Scratch>>::start_processing(plugin));
+            Wrapper::<TestPlugin<Scratch>>::process(plugin, &process);
+        }
+
+        // The buffers cover the maximum block size even though the block is shorter
+        assert_eq!(output[0], SCRATCH_BUFFER_COUNT as f32);
+        assert_eq!(
+            output[1..=SCRATCH_BUFFER_COUNT],
+            [MAX_BLOCK_SIZE as f32; SCRATCH_BUFFER_COUNT]
+        );
+    }
+
+    struct WriteLock;
+
+    struct WriteLockParams {
+        value: FloatParam,
+    }
+
+    impl Default for WriteLockParams {
+        fn default() -> Self {
+            Self {
+                value: FloatParam::new("Value", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            }
+        }
+    }
+
+    unsafe impl Params for WriteLockParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(String::from("value"), self.value.as_ptr(), String::new())]
+        }
+    }
+
+    impl TestPluginConfig for WriteLock {
+        const NAME: &'static str = "Write Lock";
+        const PARAM_WRITE_LOCK: Duration = Duration::from_secs(60);
+
+        type Params = WriteLockParams;
+    }
+
+    #[test]
+    fn param_write_lock_ignores_host_echo() {
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<WriteLock>>::new(&host);
+        let params = wrapper.plugin.lock().params.clone();
+        let param_hash = wrapper.param_id_to_hash["value"];
+        let host_changed = |normalized_value: f64| unsafe {
+            let event = output_param_value_event(param_hash, normalized_value, 0);
+            wrapper.handle_in_event(
+                &event as *const clap_event_param_value as *const clap_event_header,
+                &mut wrapper.input_events.borrow_mut(),
+                None,
+                0,
+                0,
+            );
+        };
+
+        // The plugin writes two values in quick succession from the audio thread. These are only
+        // applied once they're sent to the host at the end of the block.
+        {
+            let mut context = wrapper.make_process_context(Transport::new(48000.0), 0);
+            context.set_parameter_automated(&params.value, 0, 0.25);
+            context.set_parameter_automated(&params.value, 0, 0.5);
+        }
+
+        // The host then sends the first value back to the plugin, which should be ignored
+        host_changed(0.25);
+        assert_eq!(params.value.unmodulated_normalized_value(), 0.0);
+
+        // The value the plugin last wrote can still be applied
+        host_changed(0.5);
+        assert_eq!(params.value.unmodulated_normalized_value(), 0.5);
+    }
+
+    struct Gesture;
+
+    struct GestureParams {
+        gain: FloatParam,
+        frequency: FloatParam,
+    }
+
+    impl Default for GestureParams {
+        fn default() -> Self {
+            let range = FloatRange::Linear { min: 0.0, max: 1.0 };
+            Self {
+                gain: FloatParam::new("Gain", 0.5, range),
+                frequency: FloatParam::new("Frequency", 0.5, range),
+            }
+        }
+    }
+
+    unsafe impl Params for GestureParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (
+                    String::from("frequency"),
+                    self.frequency.as_ptr(),
+                    String::new(),
+                ),
+            ]
+        }
+    }
+
+    impl TestPluginConfig for Gesture {
+        const NAME: &'static str = "Gesture";
+        const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = true;
+
+        type Params = GestureParams;
+    }
+
+    #[test]
+    fn state_load_preserves_gestured_params() {
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<Gesture>>::new(&host);
+        let params = wrapper.plugin.lock().params.clone();
+        let gain_hash = wrapper.param_id_to_hash["gain"];
+        let mut state = PluginState {
+            version: String::from("0.0.1"),
+            params: BTreeMap::from([
+                (String::from("gain"), ParamValue::F32(0.25)),
+                (String::from("frequency"), ParamValue::F32(0.75)),
+            ]),
+            fields: BTreeMap::new(),
+            gui: BTreeMap::new(),
+        };
+
+        // The user is dragging the gain knob in the editor while the host reloads the state
+        wrapper.param_gestures.begin(&gain_hash);
+        unsafe { params.gain.as_ptr().set_normalized_value(0.9) };
+        assert!(wrapper.set_state_inner(&mut state));
+        assert_eq!(params.gain.value(), 0.9);
+        assert_eq!(params.frequency.value(), 0.75);
+
+        // Once the gesture has ended the state is applied as usual
+        wrapper.param_gestures.end(&gain_hash);
+        assert!(wrapper.set_state_inner(&mut state));
+        assert_eq!(params.gain.value(), 0.25);
+    }
+
+    unsafe extern "C" fn rescan(_host: *const clap_host, _flags: clap_param_rescan_flags) {}
+
+    unsafe extern "C" fn request_flush(_host: *const clap_host) {}
+
+    /// Supports the parameter flushes and rescans requested when the editor changes parameters or
+    /// loads a state.
+    static HOST_PARAMS: clap_host_params = clap_host_params {
+        rescan: Some(rescan),
+        clear: None,
+        request_flush: Some(request_flush),
+    };
+
+    unsafe extern "C" fn write_to_vec(
+        stream: *const clap_ostream,
+        buffer: *const c_void,
+        size: u64,
+    ) -> i64 {
+        let data = &mut *((*stream).ctx as *mut Vec<u8>);
+        data.extend_from_slice(std::slice::from_raw_parts(
+            buffer as *const u8,
+            size as usize,
+        ));
+
+        size as i64
+    }
+
+    #[test]
+    fn queued_gui_change_applied_on_save() {
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<Gesture>>::new(&host);
+        *wrapper.host_params.borrow_mut() = Some(unsafe { ClapPtr::new(&HOST_PARAMS) });
+        let plugin = &*wrapper.clap_plugin.borrow() as *const clap_plugin;
+        let params = wrapper.plugin.lock().params.clone();
+        let gui_context = wrapper.clone().make_gui_context();
+
+        // The user changes the gain in the editor, and the host saves the state before it has
+        // flushed the parameters
+        unsafe {
+            let param = params.gain.as_ptr();
+            gui_context.raw_begin_set_parameter(param);
+            gui_context.raw_set_parameter_normalized(param, 0.75);
+            gui_context.raw_end_set_parameter(param);
+        }
+        assert_eq!(params.gain.value(), 0.5);
+
+        let mut saved = Vec::new();
+        let stream = clap_ostream {
+            ctx: &mut saved as *mut Vec<u8> as *mut c_void,
+            write: Some(write_to_vec),
+        };
+        assert!(unsafe { Wrapper::<TestPlugin<Gesture>>::ext_state_save(plugin, &stream) });
+
+        assert_eq!(saved[..8], CHUNKED_STATE_MARKER.to_le_bytes());
+        let saved_state = state::deserialize_json_from_reader(ChunkedReader::new(&saved[8..]))
+            .expect("The saved state should be valid");
+        assert!(matches!(saved_state.params["gain"], ParamValue::F32(value) if value == 0.75));
+        assert!(matches!(saved_state.params["frequency"], ParamValue::F32(value) if value == 0.5));
+    }
+
+    struct Presets;
+
+    struct PresetParams {
+        gain: FloatParam,
+    }
+
+    impl Default for PresetParams {
+        fn default() -> Self {
+            Self {
+                gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            }
+        }
+    }
+
+    unsafe impl Params for PresetParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(String::from("gain"), self.gain.as_ptr(), String::new())]
+        }
+    }
+
+    impl TestPluginConfig for Presets {
+        const NAME: &'static str = "Presets";
+
+        type Params = PresetParams;
+
+        fn factory_presets() -> Vec<FactoryPreset> {
+            [("Quiet", 0.1), ("Loud", 0.9)]
+                .into_iter()
+                .map(|(name, gain)| FactoryPreset {
+                    name: String::from(name),
+                    state: PluginState {
+                        version: String::from("0.0.1"),
+                        params: BTreeMap::from([(String::from("gain"), ParamValue::F32(gain))]),
+                        fields: BTreeMap::new(),
+                        gui: BTreeMap::new(),
+                    },
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn set_preset_from_gui() {
+        let host = mock_host();
+        let wrapper = Wrapper::<TestPlugin<Presets>>::new(&host);
+        *wrapper.host_params.borrow_mut() = Some(unsafe { ClapPtr::new(&HOST_PARAMS) });
+        let params = wrapper.plugin.lock().params.clone();
+        let gui_context = wrapper.clone().make_gui_context();
+        assert_eq!(gui_context.current_preset(), None);
+
+        assert!(gui_context.set_preset(1));
+        assert_eq!(gui_context.current_preset(), Some(1));
+        assert_eq!(params.gain.value(), 0.9);
+
+        // Out of range indices are ignored
+        assert!(!gui_context.set_preset(2));
+        assert_eq!(gui_context.current_preset(), Some(1));
+        assert_eq!(params.gain.value(), 0.9);
+
+        // Any other state replaces the preset, even if it's the same state
+        gui_context.set_state(gui_context.get_state());
+        assert_eq!(gui_context.current_preset(), None);
+        assert_eq!(params.gain.value(), 0.9);
+    }
+}
@@ -2,9 +2,11 @@
 //! of relying on a plugin host. This is mostly useful for quickly testing GUI changes.
 
 use clap::{CommandFactory, FromArgMatches};
+use std::path::Path;
 
 use self::backend::Backend;
 use self::config::WrapperConfig;
+use self::wav::WavFile;
 use self::wrapper::{Wrapper, WrapperError};
 use super::util::setup_logger;
 use crate::prelude::Plugin;
@@ -12,6 +14,13 @@ use crate::prelude::Plugin;
 mod backend;
 mod config;
 mod context;
+#[cfg(feature = "standalone_control_socket")]
+mod control_socket;
+mod midi;
+#[cfg(feature = "standalone_osc")]
+mod osc;
+mod param_overlay;
+mod wav;
 mod wrapper;
 
 /// Open an NIH-plug plugin as a standalone application. If the plugin has an editor, this will open
@@ -43,7 +52,9 @@ mod wrapper;
 /// ```
 ///
 /// By default this will connect to the 'default' audio and MIDI ports. Use the command line options
-/// to change this. `--help` lists all available options.
+/// to change this. `--help` lists all available options. Passing `--input <wav> --output <wav>`
+/// instead processes a WAV file offline without opening the editor, which is useful for batch
+/// processing and for testing plugins in CI.
 ///
 /// If the wrapped plugin fails to initialize or throws an error during audio processing, then this
 /// function will return `false`.
@@ -67,10 +78,15 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
         &WrapperConfig::command()
             .name(P::NAME)
             .author(P::VENDOR)
+            .about(P::about())
             .get_matches_from(args),
     )
     .unwrap_or_else(|err| err.exit());
 
+    if let (Some(input), Some(output)) = (config.input.clone(), config.output.clone()) {
+        return run_offline::<P>(config, &input, &output);
+    }
+
     match config.backend {
         config::BackendType::Auto => {
             let result = backend::Jack::new::<P>(config.clone()).map(|backend| {
@@ -175,6 +191,47 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
     }
 }
 
+/// Process the `input` WAV file offline and write the result to `output`.
+fn run_offline<P: Plugin>(mut config: WrapperConfig, input: &Path, output: &Path) -> bool {
+    let input_file = match WavFile::read(input) {
+        Ok(input_file) => input_file,
+        Err(err) => {
+            nih_error!("Could not read '{}': {:#}", input.display(), err);
+            return false;
+        }
+    };
+
+    // The file is processed at its own sample rate
+    config.sample_rate = input_file.sample_rate as f32;
+    let backend = match backend::Offline::new::<P>(config.clone(), input_file) {
+        Ok(backend) => backend,
+        Err(err) => {
+            nih_error!("Could not process '{}': {:#}", input.display(), err);
+            return false;
+        }
+    };
+
+    let rendered = backend.rendered();
+    if !run_wrapper::<P, _>(backend, config) {
+        return false;
+    }
+
+    let output_file = rendered.lock().take();
+    match output_file {
+        Some(output_file) => match output_file.write(output) {
+            Ok(()) => true,
+            Err(err) => {
+                nih_error!("Could not write '{}': {:#}", output.display(), err);
+                false
+            }
+        },
+        None => {
+            nih_error!("The plugin stopped processing before the end of the input file");
+            false
+        }
+    }
+}
+
 fn run_wrapper<P: Plugin, B: Backend<P>>(backend: B, config: WrapperConfig) -> bool {
     let wrapper = match Wrapper::<P, _>::new(backend, config) {
         Ok(wrapper) => wrapper,
@@ -199,5 +256,8 @@ fn print_error(error: WrapperError) {
         WrapperError::InitializationFailed => {
             nih_error!("The plugin failed to initialize");
         }
+        WrapperError::UnsupportedSampleRate(sample_rate) => {
+            nih_error!("The plugin does not support a sample rate of {sample_rate} Hz");
+        }
     }
 }
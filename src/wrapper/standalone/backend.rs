@@ -3,10 +3,12 @@ use crate::prelude::{AuxiliaryBuffers, PluginNoteEvent, Transport};
 mod cpal;
 mod dummy;
 mod jack;
+mod offline;
 
 pub use self::cpal::CpalMidir;
 pub use self::dummy::Dummy;
 pub use self::jack::Jack;
+pub use self::offline::Offline;
 pub use crate::buffer::Buffer;
 pub use crate::plugin::Plugin;
 
@@ -28,4 +30,9 @@ pub trait Backend<P: Plugin>: 'static + Send + Sync {
             + 'static
             + Send,
     );
+
+    /// Called with the plugin's current latency in samples right before [`run()`][Self::run()] is
+    /// called when processing offline. Backends that can compensate for the latency can store it
+    /// here, the other backends ignore it.
+    fn set_latency_samples(&mut self, _samples: u32) {}
 }
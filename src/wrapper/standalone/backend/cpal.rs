@@ -15,11 +15,11 @@ use std::ptr::NonNull;
 use std::thread::ScopedJoinHandle;
 
 use super::super::config::WrapperConfig;
+use super::super::midi::MidiInputParser;
 use super::Backend;
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, NoteEvent, Plugin, PluginNoteEvent,
-    Transport,
+    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, Plugin, PluginNoteEvent, Transport,
 };
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
 
@@ -658,14 +658,15 @@ impl CpalMidir {
         mut midi_input_rb_producer: rtrb::Producer<PluginNoteEvent<P>>,
     ) -> impl FnMut(u64, &[u8], &mut ()) + Send + 'static {
         // This callback parses the received MIDI bytes and sends them to a ring buffer
+        let mut midi_input_parser = MidiInputParser::default();
         move |_timing, midi_data, _data| {
             // Since this is system MIDI there's no real useful timing information and we'll set all
             // the timings to the first sample in the buffer
-            if let Ok(event) = NoteEvent::from_midi(0, midi_data) {
+            midi_input_parser.parse(0, midi_data, |event| {
                 if midi_input_rb_producer.push(event).is_err() {
                     nih_error!("The MIDI input event queue was full, dropping event");
                 }
-            }
+            });
         }
     }
 
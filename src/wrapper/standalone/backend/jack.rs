@@ -12,11 +12,11 @@ use jack::{
 use parking_lot::Mutex;
 
 use super::super::config::WrapperConfig;
+use super::super::midi::MidiInputParser;
 use super::Backend;
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, NoteEvent, Plugin, PluginNoteEvent,
-    Transport,
+    AudioIOLayout, AuxiliaryBuffers, Buffer, MidiConfig, Plugin, PluginNoteEvent, Transport,
 };
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
 use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing};
@@ -115,6 +115,7 @@ impl<P: Plugin> Backend<P> for Jack {
         let aux_output_ports = self.aux_output_ports.clone();
         let midi_input = self.midi_input.clone();
         let midi_output = self.midi_output.clone();
+        let mut midi_input_parser = MidiInputParser::default();
         let process_handler = ClosureProcessHandler::new(move |client, ps| {
             // In theory we could handle `num_frames <= buffer_size`, but JACK will never chop up
             // buffers like that so we'll just make it easier for ourselves by not supporting that
@@ -241,11 +242,10 @@ impl<P: Plugin> Backend<P> for Jack {
 
             input_events.clear();
             if let Some(midi_input) = &midi_input {
-                input_events.extend(midi_input.iter(ps).filter_map(|midi| {
+                for midi in midi_input.iter(ps) {
                     let timing = clamp_input_event_timing(midi.time, num_frames);
-
-                    NoteEvent::from_midi(timing, midi.bytes).ok()
-                }));
+                    midi_input_parser.parse(timing, midi.bytes, |event| input_events.push(event));
+                }
             }
 
             output_events.clear();
@@ -0,0 +1,410 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::num::NonZeroU32;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use super::super::config::WrapperConfig;
+use super::super::wav::WavFile;
+use super::Backend;
+use crate::prelude::{AudioIOLayout, AuxiliaryBuffers, Buffer, Plugin, PluginNoteEvent, Transport};
+use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::util::is_sample_rate_supported;
+
+/// This backend processes a WAV file as fast as possible instead of connecting to an audio device.
+/// The input file is fed to the plugin's main input in blocks of the configured period size, and
+/// the plugin's main output is collected in a WAV file with the same sample rate and length. That
+/// file can be obtained through [`rendered()`][Self::rendered()] once the wrapper has finished
+/// running. There is no MIDI input or output, and there is no parameter automation.
+///
+/// The plugin's latency at the start of processing is compensated for by feeding the plugin that
+/// many samples of silence after the end of the input file and then removing the same number of
+/// samples from the start of the output. Latency changes during processing are not compensated.
+pub struct Offline {
+    config: WrapperConfig,
+    audio_io_layout: AudioIOLayout,
+    /// The plugin's latency in samples, set by the wrapper before processing starts.
+    latency: u32,
+
+    input: WavFile,
+    /// The processed audio. This is only set when the entire input file has been processed.
+    rendered: Arc<Mutex<Option<WavFile>>>,
+}
+
+impl<P: Plugin> Backend<P> for Offline {
+    fn run(
+        &mut self,
+        mut cb: impl FnMut(
+                &mut Buffer,
+                &mut AuxiliaryBuffers,
+                Transport,
+                &[PluginNoteEvent<P>],
+                &mut Vec<PluginNoteEvent<P>>,
+            ) -> bool
+            + 'static
+            + Send,
+    ) {
+        let total_samples = self.input.channels[0].len();
+        let latency = self.latency as usize;
+        let period_size = self.config.period_size as usize;
+        let num_output_channels = self
+            .audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or_default() as usize;
+        let num_input_channels = self.input.channels.len();
+
+        // The main input and output share the same storage, just like in the dummy backend. The
+        // auxiliary inputs are always silent.
+        let mut main_io_storage = vec![vec![0.0f32; period_size]; num_output_channels];
+        let mut aux_input_storage: Vec<Vec<Vec<f32>>> = self
+            .audio_io_layout
+            .aux_input_ports
+            .iter()
+            .map(|channel_count| vec![vec![0.0f32; period_size]; channel_count.get() as usize])
+            .collect();
+        let mut aux_output_storage: Vec<Vec<Vec<f32>>> = self
+            .audio_io_layout
+            .aux_output_ports
+            .iter()
+            .map(|channel_count| vec![vec![0.0f32; period_size]; channel_count.get() as usize])
+            .collect();
+
+        let mut main_io_channel_pointers: Vec<*mut f32> = main_io_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+        let mut aux_input_channel_pointers: Vec<Vec<*mut f32>> = aux_input_storage
+            .iter_mut()
+            .map(|aux_input_storage| {
+                aux_input_storage
+                    .iter_mut()
+                    .map(|channel_slice| channel_slice.as_mut_ptr())
+                    .collect()
+            })
+            .collect();
+        let mut aux_output_channel_pointers: Vec<Vec<*mut f32>> = aux_output_storage
+            .iter_mut()
+            .map(|aux_output_storage| {
+                aux_output_storage
+                    .iter_mut()
+                    .map(|channel_slice| channel_slice.as_mut_ptr())
+                    .collect()
+            })
+            .collect();
+
+        let mut buffer_manager =
+            BufferManager::for_audio_io_layout(period_size, self.audio_io_layout);
+
+        let mut output_channels = vec![Vec::with_capacity(total_samples); num_output_channels];
+        let mut midi_output_events = Vec::with_capacity(1024);
+        let mut num_processed_samples = 0usize;
+        // The input is padded with silence to flush the plugin's latency out of the output
+        while num_processed_samples < total_samples + latency {
+            let num_samples = period_size.min(total_samples + latency - num_processed_samples);
+            let block = num_processed_samples..num_processed_samples + num_samples;
+            let input_block = block.start.min(total_samples)..block.end.min(total_samples);
+
+            let mut transport = Transport::new(self.input.sample_rate as f32);
+            transport.pos_samples = Some(num_processed_samples as i64);
+            transport.tempo = Some(self.config.tempo as f64);
+            transport.time_sig_numerator = Some(self.config.timesig_num as i32);
+            transport.time_sig_denominator = Some(self.config.timesig_denom as i32);
+            transport.playing = true;
+
+            for (channel_idx, channel) in main_io_storage.iter_mut().enumerate() {
+                channel.fill(0.0);
+                if let Some(input_channel) = self.input.channels.get(channel_idx) {
+                    channel[..input_block.len()]
+                        .copy_from_slice(&input_channel[input_block.clone()]);
+                }
+            }
+            for aux_buffer in &mut aux_input_storage {
+                for channel in aux_buffer {
+                    channel.fill(0.0);
+                }
+            }
+            for aux_buffer in &mut aux_output_storage {
+                for channel in aux_buffer {
+                    channel.fill(0.0);
+                }
+            }
+
+            let buffers = unsafe {
+                buffer_manager.create_buffers(0, num_samples, |buffer_sources| {
+                    *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
+                        ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
+                        num_channels: main_io_channel_pointers.len(),
+                    });
+                    *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                        ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
+                        num_channels: num_input_channels.min(main_io_channel_pointers.len()),
+                    });
+
+                    for (input_source_channel_pointers, input_channel_pointers) in buffer_sources
+                        .aux_input_channel_pointers
+                        .iter_mut()
+                        .zip(aux_input_channel_pointers.iter_mut())
+                    {
+                        *input_source_channel_pointers = Some(ChannelPointers {
+                            ptrs: NonNull::new(input_channel_pointers.as_mut_ptr()).unwrap(),
+                            num_channels: input_channel_pointers.len(),
+                        });
+                    }
+
+                    for (output_source_channel_pointers, output_channel_pointers) in buffer_sources
+                        .aux_output_channel_pointers
+                        .iter_mut()
+                        .zip(aux_output_channel_pointers.iter_mut())
+                    {
+                        *output_source_channel_pointers = Some(ChannelPointers {
+                            ptrs: NonNull::new(output_channel_pointers.as_mut_ptr()).unwrap(),
+                            num_channels: output_channel_pointers.len(),
+                        });
+                    }
+                })
+            };
+
+            midi_output_events.clear();
+            let mut aux = AuxiliaryBuffers {
+                inputs: buffers.aux_inputs,
+                outputs: buffers.aux_outputs,
+            };
+            if !cb(
+                buffers.main_buffer,
+                &mut aux,
+                transport,
+                &[],
+                &mut midi_output_events,
+            ) {
+                // The plugin either returned an error or the wrapper is shutting down, so the
+                // output would be incomplete
+                return;
+            }
+
+            // The first `latency` samples of the output only contain the plugin's delay
+            let num_delayed_samples = latency.saturating_sub(block.start).min(num_samples);
+            for (output_channel, channel) in output_channels.iter_mut().zip(&main_io_storage) {
+                output_channel.extend_from_slice(&channel[num_delayed_samples..num_samples]);
+            }
+            num_processed_samples += num_samples;
+        }
+
+        *self.rendered.lock() = Some(WavFile {
+            sample_rate: self.input.sample_rate,
+            channels: output_channels,
+        });
+    }
+
+    fn set_latency_samples(&mut self, samples: u32) {
+        self.latency = samples;
+    }
+}
+
+impl Offline {
+    /// Prepare to process `input`. Returns an error if the plugin does not support the file's
+    /// sample rate, or if the number of channels in the file does not match the selected audio
+    /// layout's main input.
+    pub fn new<P: Plugin>(config: WrapperConfig, input: WavFile) -> Result<Self> {
+        let audio_io_layout = config.audio_io_layout_or_exit::<P>();
+        let num_input_channels = audio_io_layout
+            .main_input_channels
+            .map(NonZeroU32::get)
+            .unwrap_or_default() as usize;
+        let num_output_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or_default() as usize;
+
+        if !is_sample_rate_supported(P::SUPPORTED_SAMPLE_RATES, input.sample_rate as f32) {
+            anyhow::bail!(
+                "The input file's sample rate of {} Hz is not supported by the plugin",
+                input.sample_rate
+            );
+        }
+        if input.channels.len() != num_input_channels {
+            anyhow::bail!(
+                "The input file has {} channel(s), but the '{}' audio layout has {} main input \
+                 channel(s). Use '--audio-layout' to select a different layout.",
+                input.channels.len(),
+                audio_io_layout.name(),
+                num_input_channels
+            );
+        }
+        if num_output_channels == 0 {
+            anyhow::bail!(
+                "The '{}' audio layout does not have a main output",
+                audio_io_layout.name()
+            );
+        }
+
+        Ok(Self {
+            config,
+            audio_io_layout,
+            latency: 0,
+
+            input,
+            rendered: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Get a handle to the processed audio. This will contain the output file after the entire
+    /// input file has been processed.
+    pub fn rendered(&self) -> Arc<Mutex<Option<WavFile>>> {
+        self.rendered.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::plugin::test_plugin::{TestPlugin, TestPluginConfig};
+    use crate::prelude::{
+        new_nonzero_u32, nih_export_standalone_with_args, FloatParam, FloatRange, Param, ParamPtr,
+        Params, ProcessContext, ProcessStatus,
+    };
+
+    struct Gain;
+
+    type GainPlugin = TestPlugin<Gain>;
+
+    struct GainParams {
+        gain: FloatParam,
+    }
+
+    unsafe impl Params for GainParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(String::from("gain"), self.gain.as_ptr(), String::new())]
+        }
+    }
+
+    impl Default for GainParams {
+        fn default() -> Self {
+            Self {
+                gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            }
+        }
+    }
+
+    impl TestPluginConfig for Gain {
+        const NAME: &'static str = "Gain";
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            ..AudioIOLayout::const_default()
+        }];
+        const SUPPORTED_SAMPLE_RATES: &'static [f32] = &[44_100.0, 48_000.0];
+
+        type Params = GainParams;
+
+        fn process(
+            params: &Self::Params,
+            buffer: &mut Buffer,
+            _context: &mut impl ProcessContext<TestPlugin<Self>>,
+        ) -> ProcessStatus {
+            let gain = params.gain.value();
+            buffer.map_samples(|sample| sample * gain);
+
+            ProcessStatus::Normal
+        }
+    }
+
+    #[test]
+    fn process_wav_file() {
+        let dir = std::env::temp_dir().join(format!("nih-plug-offline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input_path = dir.join("input.wav");
+        let output_path = dir.join("output.wav");
+        let process = || {
+            nih_export_standalone_with_args::<GainPlugin, _>(
+                [
+                    "gain",
+                    "--period-size",
+                    "64",
+                    "--input",
+                    input_path.to_str().unwrap(),
+                    "--output",
+                    output_path.to_str().unwrap(),
+                ]
+                .map(String::from),
+            )
+        };
+
+        // The file's length is not a multiple of the period size
+        let input = WavFile {
+            sample_rate: 44_100,
+            channels: (0..2)
+                .map(|channel| {
+                    (0..1000)
+                        .map(|i| ((i * 7 + channel * 3) % 17) as f32 / 8.0 - 1.0)
+                        .collect()
+                })
+                .collect(),
+        };
+        input.write(&input_path).unwrap();
+        assert!(process());
+
+        let output = WavFile::read(&output_path).unwrap();
+        assert_eq!(output.sample_rate, 44_100);
+        assert_eq!(output.channels.len(), 2);
+        for (output_channel, input_channel) in output.channels.iter().zip(&input.channels) {
+            assert_eq!(output_channel.len(), input_channel.len());
+            for (output_sample, input_sample) in output_channel.iter().zip(input_channel) {
+                assert_eq!(*output_sample, input_sample * 0.5);
+            }
+        }
+
+        // Mono files and unsupported sample rates are rejected before processing anything
+        std::fs::remove_file(&output_path).unwrap();
+        WavFile {
+            sample_rate: 44_100,
+            channels: vec![vec![0.0; 100]],
+        }
+        .write(&input_path)
+        .unwrap();
+        assert!(!process());
+        WavFile {
+            sample_rate: 22_050,
+            channels: vec![vec![0.0; 100]; 2],
+        }
+        .write(&input_path)
+        .unwrap();
+        assert!(!process());
+        assert!(!output_path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compensate_latency() {
+        const LATENCY: usize = 100;
+
+        let input = WavFile {
+            sample_rate: 44_100,
+            channels: vec![(0..1000).map(|i| i as f32).collect(); 2],
+        };
+        let config = WrapperConfig::parse_from(["gain", "--period-size", "64"]);
+        let mut backend = Offline::new::<GainPlugin>(config, input.clone()).unwrap();
+        let rendered = backend.rendered();
+
+        // This simulates a plugin that delays its input by `LATENCY` samples
+        let mut delay_lines = vec![VecDeque::from(vec![0.0; LATENCY]); 2];
+        Backend::<GainPlugin>::set_latency_samples(&mut backend, LATENCY as u32);
+        Backend::<GainPlugin>::run(&mut backend, move |buffer, _, _, _, _| {
+            for (channel, delay_line) in buffer.as_slice().iter_mut().zip(&mut delay_lines) {
+                for sample in channel.iter_mut() {
+                    delay_line.push_back(*sample);
+                    *sample = delay_line.pop_front().unwrap();
+                }
+            }
+
+            true
+        });
+
+        let output = rendered.lock().take().unwrap();
+        assert_eq!(output, input);
+    }
+}
@@ -1,5 +1,6 @@
 use clap::{Parser, ValueEnum};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 
 use crate::prelude::{AudioIOLayout, Plugin};
 
@@ -32,6 +33,18 @@ pub struct WrapperConfig {
     #[clap(value_parser, short = 'p', long, default_value = "512")]
     pub period_size: u32,
 
+    /// If set, then this WAV file is processed offline without opening the editor or connecting
+    /// to an audio backend, and the result is written to the '--output' file.
+    ///
+    /// The file is processed at its own sample rate, and its channel count needs to match the main
+    /// input of the selected audio layout. Parameter automation is not supported, so all
+    /// parameters keep their default values.
+    #[clap(value_parser, long, requires = "output")]
+    pub input: Option<PathBuf>,
+    /// The WAV file the processed '--input' file is written to as 32-bit floating point samples.
+    #[clap(value_parser, long, requires = "input")]
+    pub output: Option<PathBuf>,
+
     /// The input device for the ALSA, CoreAudio, and WASAPI backends. No input will be connected if
     /// this is not specified.
     ///
@@ -93,6 +106,25 @@ pub struct WrapperConfig {
     /// The time signature's denominator.
     #[clap(value_parser, long, default_value = "4")]
     pub timesig_denom: u32,
+
+    /// If set, then all of the plugin's parameter values are printed to the terminal whenever they
+    /// change, including their smoothed values. This works with and without an editor.
+    #[clap(long)]
+    pub param_overlay: bool,
+
+    /// If set, then the plugin's parameters can be listed, read, and changed over a TCP socket
+    /// listening on this address ('127.0.0.1:9000'). Requests and responses are JSON objects
+    /// separated by newlines.
+    #[cfg(feature = "standalone_control_socket")]
+    #[clap(value_parser, long)]
+    pub control_socket: Option<std::net::SocketAddr>,
+
+    /// If set, then the plugin's parameters can be set and queried with OSC messages sent over UDP
+    /// to this address ('0.0.0.0:9001'). '/param/<id> <float>' sets a parameter's normalized value,
+    /// and the new value is sent back to the sender.
+    #[cfg(feature = "standalone_osc")]
+    #[clap(value_parser, long)]
+    pub osc: Option<std::net::SocketAddr>,
 }
 
 /// Determines which audio and MIDI backend should be used.
@@ -1,11 +1,14 @@
+use atomic_refcell::AtomicRefMut;
 use std::sync::Arc;
 
 use super::backend::Backend;
 use super::wrapper::{Task, Wrapper};
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, Plugin, PluginApi, PluginNoteEvent, ProcessContext,
-    Transport,
+    ChainHint, ChannelCounts, EventsIter, GuiContext, HostTimerCallback, InitContext,
+    ParamChangeSource, ParamIndication, ParamPtr, Plugin, PluginApi, PluginNoteEvent,
+    PosixFdCallback, PosixFdFlags, ProcessContext, RawHostHandle, Transport,
 };
+use crate::wrapper::util::buffer_management::ScratchBuffers;
 
 /// An [`InitContext`] implementation for the standalone wrapper.
 pub(crate) struct WrapperInitContext<'a, P: Plugin, B: Backend<P>> {
@@ -23,7 +26,9 @@ pub(crate) struct WrapperProcessContext<'a, P: Plugin, B: Backend<P>> {
     // here to keep the standalone backend implementation a bit more flexible
     pub(super) input_events_idx: usize,
     pub(super) output_events: &'a mut Vec<PluginNoteEvent<P>>,
+    pub(super) scratch_buffers_guard: AtomicRefMut<'a, ScratchBuffers>,
     pub(super) transport: Transport,
+    pub(super) block_start_samples: i64,
 }
 
 /// A [`GuiContext`] implementation for the wrapper. This is passed to the plugin in
@@ -52,6 +57,34 @@ impl<P: Plugin, B: Backend<P>> InitContext<P> for WrapperInitContext<'_, P, B> {
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn request_scratch_buffers(&self, count: usize) {
+        self.wrapper.request_scratch_buffers(count);
+    }
+
+    fn register_host_timer(&self, _period_ms: u32, _callback: HostTimerCallback) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn register_posix_fd(
+        &self,
+        _fd: i32,
+        _flags: PosixFdFlags,
+        _callback: PosixFdCallback,
+    ) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn chain_hint(&self) -> ChainHint {
+        // There is no host to ask
+        ChainHint::default()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Standalone
+    }
 }
 
 impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P, B> {
@@ -74,6 +107,19 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
         &self.transport
     }
 
+    #[inline]
+    fn block_start_samples(&self) -> i64 {
+        self.block_start_samples
+    }
+
+    fn channel_counts(&self) -> ChannelCounts {
+        self.wrapper.channel_counts()
+    }
+
+    fn scratch_buffers(&mut self) -> &mut [Box<[f32]>] {
+        self.scratch_buffers_guard.as_mut_slice()
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
         // We'll pretend we're a queue, choo choo
         if self.input_events_idx < self.input_events.len() {
@@ -86,6 +132,10 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
         }
     }
 
+    fn events(&self) -> EventsIter<'_, PluginNoteEvent<P>> {
+        EventsIter::new(&self.input_events[self.input_events_idx..], &[])
+    }
+
     fn send_event(&mut self, event: PluginNoteEvent<P>) {
         self.output_events.push(event);
     }
@@ -97,6 +147,25 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn request_restart(&self) {
+        // The standalone target can't be restarted by a host
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Standalone
+    }
+
+    unsafe fn raw_set_parameter_automated(
+        &mut self,
+        param: ParamPtr,
+        _sample_offset: u32,
+        normalized: f32,
+    ) {
+        // There's no host to record the automation, so this only changes the parameter's value.
+        // The new value is applied at the start of the next block.
+        self.wrapper.set_parameter_automated(param, normalized);
+    }
 }
 
 impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
@@ -109,11 +178,13 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         true
     }
 
-    unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
-        // Since there's no automation being recorded here, gestures don't mean anything
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        // Since there's no automation being recorded here, gestures are only used to preserve
+        // parameters that are being changed when loading a state
+        self.wrapper.set_param_gesture(param, true);
 
         #[cfg(debug_assertions)]
-        match self.wrapper.param_id_from_ptr(_param) {
+        match self.wrapper.param_id_from_ptr(param) {
             Some(param_id) => self
                 .param_gesture_checker
                 .borrow_mut()
@@ -139,9 +210,11 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         }
     }
 
-    unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.wrapper.set_param_gesture(param, false);
+
         #[cfg(debug_assertions)]
-        match self.wrapper.param_id_from_ptr(_param) {
+        match self.wrapper.param_id_from_ptr(param) {
             Some(param_id) => self
                 .param_gesture_checker
                 .borrow_mut()
@@ -152,6 +225,15 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         }
     }
 
+    unsafe fn raw_take_param_change_source(&self, param: ParamPtr) -> Option<ParamChangeSource> {
+        self.wrapper.take_param_change_source(param)
+    }
+
+    unsafe fn raw_param_indication(&self, _param: ParamPtr) -> Option<ParamIndication> {
+        // This is only supported by CLAP
+        None
+    }
+
     fn get_state(&self) -> crate::wrapper::state::PluginState {
         self.wrapper.get_state_object()
     }
@@ -159,4 +241,41 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
         self.wrapper.set_state_object_from_gui(state)
     }
+
+    fn current_preset(&self) -> Option<usize> {
+        self.wrapper.current_preset()
+    }
+
+    fn set_preset(&self, index: usize) -> bool {
+        self.wrapper.set_preset(index)
+    }
+
+    fn editor_state(&self, key: &str) -> Option<String> {
+        self.wrapper.editor_state(key)
+    }
+
+    fn set_editor_state(&self, key: &str, value: Option<String>) {
+        self.wrapper.set_editor_state(key, value)
+    }
+
+    fn transport(&self) -> Option<Transport> {
+        self.wrapper.transport()
+    }
+
+    fn mark_state_dirty(&self) {
+        // There's no host to notify, and the standalone target doesn't save any state
+    }
+
+    fn request_param_rescan(&self) {
+        // There's no host that could have stale information about the parameters
+    }
+
+    fn chain_hint(&self) -> ChainHint {
+        // There is no host to ask
+        ChainHint::default()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Standalone
+    }
 }
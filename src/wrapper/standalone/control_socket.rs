@@ -0,0 +1,236 @@
+//! A control socket for headless standalones. This exposes the plugin's parameters over TCP using
+//! a simple line based JSON protocol, so the standalone can for instance be controlled from a
+//! hardware front panel.
+//!
+//! Every request is a single line containing a JSON object, and every request gets a single line
+//! JSON response:
+//!
+//! - `{"command": "list"}` returns `{"params": [...]}` with an entry for every parameter.
+//! - `{"command": "get", "id": "gain"}` returns `{"param": {...}}`.
+//! - `{"command": "set", "id": "gain", "normalized_value": 0.5}` returns `{"ok": true}`.
+//!
+//! Parameter entries look like
+//! `{"id": "gain", "name": "Gain", "normalized_value": 0.5, "value": "-6.02 dB"}`. Failed requests
+//! return `{"error": "..."}`.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use crate::prelude::{ParamPtr, Params};
+
+/// A request sent over the control socket.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Request {
+    List,
+    Get { id: String },
+    Set { id: String, normalized_value: f32 },
+}
+
+/// The data shared between all of the control socket's connections.
+struct ControlSocket<F> {
+    /// Keeps the parameters alive for as long as the `ParamPtr`s in `param_map` are used.
+    _params: Arc<dyn Params>,
+    /// The parameter IDs and pointers, in the same order as the plugin's `param_map()`.
+    param_map: Vec<(String, ParamPtr)>,
+    /// Sets a parameter's normalized value. This should go through the wrapper so the change is
+    /// handled the same way as a change made from the editor.
+    set_parameter: F,
+}
+
+/// Start listening for connections on `addr` on a background thread. Every connection is handled
+/// on its own thread. `set_parameter` is called for every `set` request, and it should return
+/// `false` if the parameter could not be set. Returns the address the socket is bound to, which is
+/// useful when binding to port 0.
+pub fn spawn<F>(
+    addr: SocketAddr,
+    params: Arc<dyn Params>,
+    set_parameter: F,
+) -> io::Result<SocketAddr>
+where
+    F: Fn(ParamPtr, f32) -> bool + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+
+    let control_socket = Arc::new(ControlSocket {
+        param_map: params
+            .param_map()
+            .into_iter()
+            .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
+            .collect(),
+        _params: params,
+        set_parameter,
+    });
+
+    thread::Builder::new()
+        .name(String::from("control-socket"))
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let control_socket = control_socket.clone();
+                        thread::spawn(move || {
+                            if let Err(err) = control_socket.handle_connection(stream) {
+                                nih_trace!("Control socket connection closed: {err}");
+                            }
+                        });
+                    }
+                    Err(err) => nih_error!("Could not accept a control socket connection: {err}"),
+                }
+            }
+        })?;
+
+    Ok(local_addr)
+}
+
+impl<F> ControlSocket<F>
+where
+    F: Fn(ParamPtr, f32) -> bool,
+{
+    /// Handle requests from a single client until it disconnects.
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = self.handle_request(&line);
+            writeln!(writer, "{response}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle a single JSON request and return the JSON response.
+    fn handle_request(&self, request: &str) -> serde_json::Value {
+        let request: Request = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(err) => return json!({ "error": format!("Invalid request: {err}") }),
+        };
+
+        match request {
+            Request::List => json!({
+                "params": self
+                    .param_map
+                    .iter()
+                    .map(|(param_id, param_ptr)| param_json(param_id, *param_ptr))
+                    .collect::<Vec<_>>()
+            }),
+            Request::Get { id } => match self.param_ptr(&id) {
+                Some(param_ptr) => json!({ "param": param_json(&id, param_ptr) }),
+                None => json!({ "error": format!("Unknown parameter '{id}'") }),
+            },
+            Request::Set {
+                id,
+                normalized_value,
+            } => match self.param_ptr(&id) {
+                Some(_) if !(0.0..=1.0).contains(&normalized_value) => {
+                    json!({ "error": "The normalized value must be between 0 and 1" })
+                }
+                Some(param_ptr) => {
+                    if (self.set_parameter)(param_ptr, normalized_value) {
+                        json!({ "ok": true })
+                    } else {
+                        json!({ "error": format!("Could not set parameter '{id}'") })
+                    }
+                }
+                None => json!({ "error": format!("Unknown parameter '{id}'") }),
+            },
+        }
+    }
+
+    fn param_ptr(&self, param_id: &str) -> Option<ParamPtr> {
+        self.param_map
+            .iter()
+            .find(|(id, _)| id == param_id)
+            .map(|(_, param_ptr)| *param_ptr)
+    }
+}
+
+/// The JSON representation of a parameter and its current value.
+fn param_json(param_id: &str, param_ptr: ParamPtr) -> serde_json::Value {
+    // SAFETY: The `ControlSocket` keeps the `Params` object these pointers point to alive
+    let (name, normalized_value, value) = unsafe {
+        let normalized_value = param_ptr.unmodulated_normalized_value();
+        (
+            param_ptr.name().to_owned(),
+            normalized_value,
+            param_ptr.normalized_value_to_string(normalized_value, true),
+        )
+    };
+
+    json!({
+        "id": param_id,
+        "name": name,
+        "normalized_value": normalized_value,
+        "value": value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{FloatParam, FloatRange, Param};
+
+    struct TestParams {
+        gain: FloatParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(String::from("gain"), self.gain.as_ptr(), String::new())]
+        }
+    }
+
+    fn request(stream: &mut BufReader<TcpStream>, request: &str) -> serde_json::Value {
+        writeln!(stream.get_mut(), "{request}").unwrap();
+
+        let mut response = String::new();
+        stream.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn set_over_socket() {
+        let params = Arc::new(TestParams {
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+        });
+
+        // The wrapper queues the change for the audio thread, but there's no audio thread here
+        let addr = spawn(
+            "127.0.0.1:0".parse().unwrap(),
+            params.clone(),
+            |param_ptr, normalized_value| unsafe {
+                param_ptr.set_normalized_value(normalized_value)
+            },
+        )
+        .unwrap();
+        let mut stream = BufReader::new(TcpStream::connect(addr).unwrap());
+
+        assert_eq!(
+            request(
+                &mut stream,
+                r#"{"command": "set", "id": "gain", "normalized_value": 0.25}"#
+            ),
+            json!({ "ok": true })
+        );
+        assert_eq!(params.gain.value(), 0.25);
+
+        let response = request(&mut stream, r#"{"command": "get", "id": "gain"}"#);
+        assert_eq!(response["param"]["normalized_value"], 0.25);
+
+        let response = request(&mut stream, r#"{"command": "list"}"#);
+        assert_eq!(response["params"][0]["id"], "gain");
+        assert_eq!(response["params"][0]["name"], "Gain");
+
+        let response = request(&mut stream, r#"{"command": "get", "id": "cutoff"}"#);
+        assert!(response["error"].is_string());
+    }
+}
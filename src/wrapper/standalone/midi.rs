@@ -0,0 +1,272 @@
+//! Parsing for the raw MIDI input received by the standalone backends.
+
+use midi_consts::channel_event as midi;
+
+use crate::midi::sysex::SysExMessage;
+use crate::midi::NoteEvent;
+
+/// The number of CCs that have a 14-bit LSB counterpart. CCs `0..32` are the MSBs, and CCs `32..64`
+/// are the matching LSBs.
+const NUM_14_BIT_CCS: usize = 32;
+
+/// Converts raw MIDI input to [`NoteEvent`]s while keeping track of the state needed to handle
+/// running status and 14-bit CCs. A single parser should be used for each MIDI input.
+///
+/// # Running status
+///
+/// Channel messages may omit their status byte if it's the same as the previous message's status
+/// byte. The last channel message status byte is remembered between calls to
+/// [`parse()`][Self::parse()]. System common messages and SysEx clear the running status, and
+/// realtime messages are ignored and leave it intact.
+///
+/// # 14-bit CCs
+///
+/// Following the MIDI 1.0 specification, CCs 0 through 31 are treated as the most significant
+/// bytes of 14-bit controllers, and CCs 32 through 63 as the least significant bytes of those same
+/// controllers. An MSB is output as a regular 7-bit `NoteEvent::MidiCC` for the MSB's CC number so
+/// controllers that only send MSBs keep working. When it is followed by an LSB on the same channel,
+/// then the LSB is combined with the last MSB and output as another `NoteEvent::MidiCC` for the
+/// MSB's CC number with the full 14-bit resolution. LSBs that are received before any MSB for that
+/// controller are passed through as is.
+#[derive(Debug)]
+pub struct MidiInputParser {
+    /// The status byte of the last channel message, used when a message omits its status byte.
+    running_status: Option<u8>,
+    /// The last received MSB for every 14-bit CC, indexed by `[channel][cc]`.
+    cc_msbs: [[Option<u8>; NUM_14_BIT_CCS]; 16],
+}
+
+impl Default for MidiInputParser {
+    fn default() -> Self {
+        Self {
+            running_status: None,
+            cc_msbs: [[None; NUM_14_BIT_CCS]; 16],
+        }
+    }
+}
+
+impl MidiInputParser {
+    /// Parse a buffer of raw MIDI data containing one or more messages, and call `on_event` for
+    /// every resulting note event. All events will have the same `timing`. Unknown or incomplete
+    /// messages are skipped.
+    pub fn parse<S: SysExMessage>(
+        &mut self,
+        timing: u32,
+        midi_data: &[u8],
+        mut on_event: impl FnMut(NoteEvent<S>),
+    ) {
+        let mut remaining = midi_data;
+        while let Some(&first_byte) = remaining.first() {
+            // SysEx and system common messages are handled as a whole and they clear the running
+            // status
+            if first_byte == 0xf0 {
+                self.running_status = None;
+                let sysex_len = remaining
+                    .iter()
+                    .position(|&byte| byte == 0xf7)
+                    .map(|end_idx| end_idx + 1)
+                    .unwrap_or(remaining.len());
+                if let Ok(event) = NoteEvent::from_midi(timing, &remaining[..sysex_len]) {
+                    on_event(event);
+                }
+
+                remaining = &remaining[sysex_len..];
+                continue;
+            }
+            if first_byte >= 0xf8 {
+                // Realtime messages can occur anywhere and don't affect running status
+                remaining = &remaining[1..];
+                continue;
+            }
+            if first_byte >= 0xf1 {
+                self.running_status = None;
+                let message_len = match first_byte {
+                    0xf1 | 0xf3 => 2,
+                    0xf2 => 3,
+                    _ => 1,
+                };
+                remaining = &remaining[message_len.min(remaining.len())..];
+                continue;
+            }
+
+            let (status_byte, data) = if first_byte & 0x80 != 0 {
+                self.running_status = Some(first_byte);
+                (first_byte, &remaining[1..])
+            } else {
+                match self.running_status {
+                    Some(status_byte) => (status_byte, remaining),
+                    None => {
+                        nih_trace!("Received MIDI data byte {first_byte:#x} without a status byte");
+                        remaining = &remaining[1..];
+                        continue;
+                    }
+                }
+            };
+
+            let num_data_bytes = match status_byte & midi::EVENT_TYPE_MASK {
+                midi::PROGRAM_CHANGE | midi::CHANNEL_KEY_PRESSURE => 1,
+                _ => 2,
+            };
+            if data.len() < num_data_bytes {
+                nih_trace!("Received an incomplete MIDI message: {midi_data:02x?}");
+                return;
+            }
+
+            let mut message = [status_byte, 0, 0];
+            message[1..1 + num_data_bytes].copy_from_slice(&data[..num_data_bytes]);
+            remaining = &data[num_data_bytes..];
+
+            self.parse_channel_message(timing, message, &mut on_event);
+        }
+    }
+
+    /// Parse a single three byte channel message, reassembling 14-bit CCs along the way. Messages
+    /// with a single data byte should be padded with a zero.
+    fn parse_channel_message<S: SysExMessage>(
+        &mut self,
+        timing: u32,
+        message: [u8; 3],
+        on_event: &mut impl FnMut(NoteEvent<S>),
+    ) {
+        if message[0] & midi::EVENT_TYPE_MASK == midi::CONTROL_CHANGE {
+            let channel = message[0] & midi::MIDI_CHANNEL_MASK;
+            let cc = message[1] as usize;
+            let value = message[2];
+
+            if cc < NUM_14_BIT_CCS {
+                self.cc_msbs[channel as usize][cc] = Some(value);
+            } else if cc < NUM_14_BIT_CCS * 2 {
+                let msb_cc = cc - NUM_14_BIT_CCS;
+                if let Some(msb) = self.cc_msbs[channel as usize][msb_cc] {
+                    on_event(NoteEvent::MidiCC {
+                        timing,
+                        channel,
+                        cc: msb_cc as u8,
+                        value: (((msb as u16) << 7) | value as u16) as f32 / ((1 << 14) - 1) as f32,
+                    });
+
+                    return;
+                }
+            }
+        }
+
+        if let Ok(event) = NoteEvent::from_midi(timing, &message) {
+            on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(parser: &mut MidiInputParser, midi_data: &[u8]) -> Vec<NoteEvent<()>> {
+        let mut events = Vec::new();
+        parser.parse(0, midi_data, |event| events.push(event));
+
+        events
+    }
+
+    #[test]
+    fn reassemble_14_bit_cc() {
+        let mut parser = MidiInputParser::default();
+
+        // CC 1 (mod wheel) MSB followed by CC 33, its LSB
+        let events = parse_all(&mut parser, &[0xb2, 1, 0x40]);
+        assert_eq!(
+            events,
+            [NoteEvent::MidiCC {
+                timing: 0,
+                channel: 2,
+                cc: 1,
+                value: 64.0 / 127.0
+            }]
+        );
+
+        let events = parse_all(&mut parser, &[0xb2, 33, 0x20]);
+        assert_eq!(
+            events,
+            [NoteEvent::MidiCC {
+                timing: 0,
+                channel: 2,
+                cc: 1,
+                value: ((0x40 << 7) | 0x20) as f32 / 16383.0
+            }]
+        );
+    }
+
+    #[test]
+    fn lsb_without_msb() {
+        let mut parser = MidiInputParser::default();
+
+        let events = parse_all(&mut parser, &[0xb0, 33, 0x20]);
+        assert_eq!(
+            events,
+            [NoteEvent::MidiCC {
+                timing: 0,
+                channel: 0,
+                cc: 33,
+                value: 0x20 as f32 / 127.0
+            }]
+        );
+    }
+
+    #[test]
+    fn running_status() {
+        let mut parser = MidiInputParser::default();
+
+        // Two note ons in a single buffer, and a third one in the next buffer
+        let mut events = parse_all(&mut parser, &[0x90, 60, 100, 64, 100]);
+        events.extend(parse_all(&mut parser, &[67, 0]));
+        assert_eq!(
+            events,
+            [
+                NoteEvent::NoteOn {
+                    timing: 0,
+                    voice_id: None,
+                    channel: 0,
+                    note: 60,
+                    velocity: 100.0 / 127.0
+                },
+                NoteEvent::NoteOn {
+                    timing: 0,
+                    voice_id: None,
+                    channel: 0,
+                    note: 64,
+                    velocity: 100.0 / 127.0
+                },
+                NoteEvent::NoteOff {
+                    timing: 0,
+                    voice_id: None,
+                    channel: 0,
+                    note: 67,
+                    velocity: 0.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn running_status_two_byte_messages() {
+        let mut parser = MidiInputParser::default();
+
+        // Program changes only have a single data byte, and a realtime clock message in between
+        // should not affect the running status
+        let events = parse_all(&mut parser, &[0xc1, 5, 0xf8, 6]);
+        assert_eq!(
+            events,
+            [
+                NoteEvent::MidiProgramChange {
+                    timing: 0,
+                    channel: 1,
+                    program: 5
+                },
+                NoteEvent::MidiProgramChange {
+                    timing: 0,
+                    channel: 1,
+                    program: 6
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! An OSC server for controlling standalones from live performance rigs. This exposes the plugin's
+//! parameters over UDP using [OSC 1.0](https://opensoundcontrol.stanford.edu/spec-1_0.html)
+//! messages. Parameters are addressed by their parameter IDs, and all values are normalized values
+//! in the `[0, 1]` range:
+//!
+//! - `/param/<id> <float>` sets the parameter's normalized value. The new value is sent back to the
+//!   sender as a `/param/<id> <float>` message.
+//! - `/param/<id>` without any arguments sends the parameter's current normalized value back to
+//!   the sender in the same format.
+//! - `/params` without any arguments sends a `/param/<id> <float>` message for every parameter back
+//!   to the sender.
+//!
+//! Values may be sent as 32-bit floats, 64-bit doubles, or 32-bit integers. Messages contained in
+//! OSC bundles are handled immediately, ignoring the bundle's time tag. Invalid messages and
+//! messages for unknown parameters are ignored.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::thread;
+
+use crate::prelude::{ParamPtr, Params};
+
+/// The largest OSC packet that can be received. This is larger than anything that fits in a single
+/// UDP datagram on most networks.
+const MAX_PACKET_SIZE: usize = 65536;
+
+/// An argument in an OSC message. Only the types that can be used as parameter values are
+/// supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OscArg {
+    Float(f32),
+    Double(f64),
+    Int(i32),
+}
+
+impl OscArg {
+    fn as_f32(self) -> f32 {
+        match self {
+            OscArg::Float(value) => value,
+            OscArg::Double(value) => value as f32,
+            OscArg::Int(value) => value as f32,
+        }
+    }
+}
+
+/// A decoded OSC message.
+#[derive(Debug, Clone, PartialEq)]
+struct OscMessage<'a> {
+    address: &'a str,
+    args: Vec<OscArg>,
+}
+
+/// The data used by the OSC server's thread.
+struct OscServer<F> {
+    /// Keeps the parameters alive for as long as the `ParamPtr`s in `param_map` are used.
+    _params: Arc<dyn Params>,
+    /// The parameter IDs and pointers, in the same order as the plugin's `param_map()`.
+    param_map: Vec<(String, ParamPtr)>,
+    /// Sets a parameter's normalized value. This should go through the wrapper so the change is
+    /// handled the same way as a change made from the editor.
+    set_parameter: F,
+}
+
+/// Start listening for OSC messages on `addr` on a background thread. `set_parameter` is called
+/// for every set message, and it should return `false` if the parameter could not be set. Returns
+/// the address the socket is bound to, which is useful when binding to port 0.
+pub fn spawn<F>(
+    addr: SocketAddr,
+    params: Arc<dyn Params>,
+    set_parameter: F,
+) -> io::Result<SocketAddr>
+where
+    F: Fn(ParamPtr, f32) -> bool + Send + Sync + 'static,
+{
+    let socket = UdpSocket::bind(addr)?;
+    let local_addr = socket.local_addr()?;
+
+    let server = OscServer {
+        param_map: params
+            .param_map()
+            .into_iter()
+            .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
+            .collect(),
+        _params: params,
+        set_parameter,
+    };
+
+    thread::Builder::new()
+        .name(String::from("osc-server"))
+        .spawn(move || {
+            let mut packet = vec![0u8; MAX_PACKET_SIZE];
+            loop {
+                match socket.recv_from(&mut packet) {
+                    Ok((packet_len, sender)) => {
+                        for reply in server.handle_packet(&packet[..packet_len]) {
+                            if let Err(err) = socket.send_to(&reply, sender) {
+                                nih_trace!("Could not send an OSC reply to {sender}: {err}");
+                            }
+                        }
+                    }
+                    Err(err) => nih_error!("Could not receive an OSC packet: {err}"),
+                }
+            }
+        })?;
+
+    Ok(local_addr)
+}
+
+impl<F> OscServer<F>
+where
+    F: Fn(ParamPtr, f32) -> bool,
+{
+    /// Handle an OSC packet containing either a message or a bundle, returning the encoded reply
+    /// messages that should be sent back to the sender.
+    fn handle_packet(&self, packet: &[u8]) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        if !decode_packet(packet, &mut messages) {
+            nih_trace!("Ignoring an invalid OSC packet");
+        }
+
+        let mut replies = Vec::new();
+        for message in messages {
+            self.handle_message(&message, &mut replies);
+        }
+
+        replies
+    }
+
+    fn handle_message(&self, message: &OscMessage, replies: &mut Vec<Vec<u8>>) {
+        if message.address == "/params" {
+            for (param_id, param_ptr) in &self.param_map {
+                replies.push(param_value_message(param_id, *param_ptr));
+            }
+
+            return;
+        }
+
+        let (param_id, param_ptr) = match message
+            .address
+            .strip_prefix("/param/")
+            .and_then(|param_id| self.param_ptr(param_id))
+        {
+            Some(param) => param,
+            None => {
+                nih_trace!(
+                    "Ignoring OSC message for unknown address {}",
+                    message.address
+                );
+                return;
+            }
+        };
+
+        if let Some(value) = message.args.first() {
+            let normalized_value = value.as_f32();
+            if !(0.0..=1.0).contains(&normalized_value)
+                || !(self.set_parameter)(param_ptr, normalized_value)
+            {
+                nih_trace!("Could not set parameter '{param_id}' to {normalized_value} over OSC");
+            }
+        }
+
+        replies.push(param_value_message(param_id, param_ptr));
+    }
+
+    fn param_ptr(&self, param_id: &str) -> Option<(&str, ParamPtr)> {
+        self.param_map
+            .iter()
+            .find(|(id, _)| id == param_id)
+            .map(|(id, param_ptr)| (id.as_str(), *param_ptr))
+    }
+}
+
+/// Encode a `/param/<id> <float>` message with the parameter's current normalized value.
+fn param_value_message(param_id: &str, param_ptr: ParamPtr) -> Vec<u8> {
+    // SAFETY: The `OscServer` keeps the `Params` object these pointers point to alive
+    let normalized_value = unsafe { param_ptr.unmodulated_normalized_value() };
+
+    encode_message(
+        &format!("/param/{param_id}"),
+        &[OscArg::Float(normalized_value)],
+    )
+}
+
+/// Decode an OSC packet and add all messages it contains to `messages`. Returns `false` if the
+/// packet is malformed. Messages decoded before the error are still added.
+fn decode_packet<'a>(packet: &'a [u8], messages: &mut Vec<OscMessage<'a>>) -> bool {
+    if let Some(elements) = packet.strip_prefix(b"#bundle\0") {
+        // The bundle's elements follow after the eight byte time tag, with every element being
+        // prefixed by its size
+        let mut elements = match elements.get(8..) {
+            Some(elements) => elements,
+            None => return false,
+        };
+        while !elements.is_empty() {
+            let element_len = match read_i32(elements) {
+                Some(len) if len >= 0 && len as usize + 4 <= elements.len() => len as usize,
+                _ => return false,
+            };
+            if !decode_packet(&elements[4..4 + element_len], messages) {
+                return false;
+            }
+
+            elements = &elements[4 + element_len..];
+        }
+
+        true
+    } else {
+        match decode_message(packet) {
+            Some(message) => {
+                messages.push(message);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Decode a single OSC message. Returns `None` if the message is malformed or if it contains
+/// arguments of an unsupported type.
+fn decode_message(packet: &[u8]) -> Option<OscMessage<'_>> {
+    let (address, rest) = read_string(packet)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+
+    // Very old implementations may omit the type tag string for messages without arguments
+    if rest.is_empty() {
+        return Some(OscMessage {
+            address,
+            args: Vec::new(),
+        });
+    }
+
+    let (type_tags, mut rest) = read_string(rest)?;
+    let type_tags = type_tags.strip_prefix(',')?;
+    let mut args = Vec::with_capacity(type_tags.len());
+    for type_tag in type_tags.chars() {
+        match type_tag {
+            'f' => {
+                args.push(OscArg::Float(f32::from_bits(read_i32(rest)? as u32)));
+                rest = &rest[4..];
+            }
+            'i' => {
+                args.push(OscArg::Int(read_i32(rest)?));
+                rest = &rest[4..];
+            }
+            'd' => {
+                let bytes: [u8; 8] = rest.get(..8)?.try_into().ok()?;
+                args.push(OscArg::Double(f64::from_be_bytes(bytes)));
+                rest = &rest[8..];
+            }
+            _ => return None,
+        }
+    }
+
+    Some(OscMessage { address, args })
+}
+
+/// Encode an OSC message.
+fn encode_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut message = Vec::new();
+    write_string(&mut message, address);
+
+    let type_tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Float(_) => 'f',
+            OscArg::Double(_) => 'd',
+            OscArg::Int(_) => 'i',
+        }))
+        .collect();
+    write_string(&mut message, &type_tags);
+
+    for arg in args {
+        match arg {
+            OscArg::Float(value) => message.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Double(value) => message.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Int(value) => message.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    message
+}
+
+/// Read a null terminated OSC string that's padded to a multiple of four bytes. Returns the string
+/// and the remaining data.
+fn read_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let string_len = data.iter().position(|&byte| byte == 0)?;
+    let padded_len = (string_len + 4) & !3;
+    if padded_len > data.len() {
+        return None;
+    }
+
+    let string = std::str::from_utf8(&data[..string_len]).ok()?;
+    Some((string, &data[padded_len..]))
+}
+
+/// Write a null terminated OSC string padded to a multiple of four bytes.
+fn write_string(data: &mut Vec<u8>, string: &str) {
+    data.extend_from_slice(string.as_bytes());
+    let padding = 4 - (string.len() % 4);
+    data.extend(std::iter::repeat(0).take(padding));
+}
+
+/// Read a big-endian 32-bit integer from the start of `data`.
+fn read_i32(data: &[u8]) -> Option<i32> {
+    Some(i32::from_be_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::prelude::{FloatParam, FloatRange, Param};
+
+    struct TestParams {
+        gain: FloatParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(String::from("gain"), self.gain.as_ptr(), String::new())]
+        }
+    }
+
+    /// Receive a single OSC message and return its address and arguments.
+    fn receive(socket: &UdpSocket) -> (String, Vec<OscArg>) {
+        let mut packet = vec![0u8; MAX_PACKET_SIZE];
+        let packet_len = socket.recv(&mut packet).unwrap();
+        let message = decode_message(&packet[..packet_len]).unwrap();
+
+        (message.address.to_owned(), message.args)
+    }
+
+    #[test]
+    fn set_over_osc() {
+        let params = Arc::new(TestParams {
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+        });
+
+        // The wrapper queues the change for the audio thread, but there's no audio thread here
+        let addr = spawn(
+            "127.0.0.1:0".parse().unwrap(),
+            params.clone(),
+            |param_ptr, normalized_value| unsafe {
+                param_ptr.set_normalized_value(normalized_value)
+            },
+        )
+        .unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        socket.connect(addr).unwrap();
+
+        socket
+            .send(&encode_message("/param/gain", &[OscArg::Float(0.25)]))
+            .unwrap();
+        assert_eq!(
+            receive(&socket),
+            (String::from("/param/gain"), vec![OscArg::Float(0.25)])
+        );
+        assert_eq!(params.gain.value(), 0.25);
+
+        // Values can also be sent as doubles, and messages can be wrapped in bundles
+        let message = encode_message("/param/gain", &[OscArg::Double(0.5)]);
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bundle.extend_from_slice(&(message.len() as i32).to_be_bytes());
+        bundle.extend_from_slice(&message);
+        socket.send(&bundle).unwrap();
+        assert_eq!(receive(&socket).1, [OscArg::Float(0.5)]);
+        assert_eq!(params.gain.value(), 0.5);
+
+        // Querying a parameter or all parameters emits their current values
+        socket.send(&encode_message("/param/gain", &[])).unwrap();
+        assert_eq!(receive(&socket).1, [OscArg::Float(0.5)]);
+        socket.send(&encode_message("/params", &[])).unwrap();
+        assert_eq!(receive(&socket).0, "/param/gain");
+    }
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let args = [OscArg::Float(0.75), OscArg::Int(-3), OscArg::Double(0.1)];
+        let message = encode_message("/param/cutoff", &args);
+        assert_eq!(message.len() % 4, 0);
+        assert_eq!(
+            decode_message(&message),
+            Some(OscMessage {
+                address: "/param/cutoff",
+                args: args.to_vec()
+            })
+        );
+
+        // Truncated messages are rejected
+        assert_eq!(decode_message(&message[..message.len() - 4]), None);
+    }
+}
@@ -0,0 +1,181 @@
+//! A live listing of all of the plugin's parameter values for debugging plugins in the standalone.
+//! This works the same with and without a custom editor. Baseview does not provide any way to draw
+//! text into the editor's window, so the listing is printed to the terminal every frame the values
+//! change instead of being drawn on top of the editor.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::prelude::{Param, ParamPtr, Params};
+
+/// Reads the current values of all parameters from the plugin's `param_map()`.
+pub struct ParamOverlay {
+    /// Keeps the parameters alive for as long as the `ParamPtr`s in `param_map` are used.
+    _params: Arc<dyn Params>,
+    /// The parameter IDs and pointers, in the same order as the plugin's `param_map()`.
+    param_map: Vec<(String, ParamPtr)>,
+    /// The values read during the last [`update()`][Self::update()].
+    rows: Vec<ParamOverlayRow>,
+}
+
+/// A parameter's values at the time of the last [`ParamOverlay::update()`].
+#[derive(Debug, Clone, PartialEq)]
+struct ParamOverlayRow {
+    id: String,
+    name: String,
+    /// The normalized value before monophonic modulation has been applied.
+    normalized_value: f32,
+    /// The normalized value after monophonic modulation has been applied.
+    modulated_normalized_value: f32,
+    /// The modulated value formatted with the parameter's own formatter, including the unit.
+    value: String,
+    /// The smoother's current value for float and integer parameters, formatted the same way as
+    /// `value`. This lags behind `value` while the parameter is being smoothed.
+    smoothed_value: Option<String>,
+}
+
+impl ParamOverlay {
+    pub fn new(params: Arc<dyn Params>) -> Self {
+        Self {
+            param_map: params
+                .param_map()
+                .into_iter()
+                .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
+                .collect(),
+            _params: params,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Read the current parameter values. Returns `true` if any of the values changed since the
+    /// last update.
+    pub fn update(&mut self) -> bool {
+        let rows: Vec<ParamOverlayRow> = self
+            .param_map
+            .iter()
+            .map(|(param_id, param_ptr)| unsafe { read_row(param_id, *param_ptr) })
+            .collect();
+
+        if rows != self.rows {
+            self.rows = rows;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Format the values read during the last [`update()`][Self::update()] as a table with one line
+    /// per parameter.
+    pub fn render(&self) -> String {
+        let name_width = self
+            .rows
+            .iter()
+            .map(|row| row.name.len())
+            .max()
+            .unwrap_or(0);
+        let id_width = self.rows.iter().map(|row| row.id.len()).max().unwrap_or(0);
+
+        let mut table = String::new();
+        for row in &self.rows {
+            let _ = write!(
+                table,
+                "{:name_width$}  {:id_width$}  {:.4} (modulated {:.4})  {}",
+                row.name, row.id, row.normalized_value, row.modulated_normalized_value, row.value,
+            );
+            if let Some(smoothed_value) = &row.smoothed_value {
+                let _ = write!(table, " (smoothed {smoothed_value})");
+            }
+            table.push('\n');
+        }
+
+        table
+    }
+}
+
+/// Read a single parameter's values.
+///
+/// # Safety
+///
+/// `param_ptr` needs to point to a live parameter.
+unsafe fn read_row(param_id: &str, param_ptr: ParamPtr) -> ParamOverlayRow {
+    let modulated_normalized_value = param_ptr.modulated_normalized_value();
+    let smoothed_value = match param_ptr {
+        ParamPtr::FloatParam(p) => {
+            let p = &*p;
+            Some(p.normalized_value_to_string(
+                p.preview_normalized(p.smoothed.previous_value()),
+                true,
+            ))
+        }
+        ParamPtr::IntParam(p) => {
+            let p = &*p;
+            Some(p.normalized_value_to_string(
+                p.preview_normalized(p.smoothed.previous_value()),
+                true,
+            ))
+        }
+        ParamPtr::BoolParam(_) | ParamPtr::EnumParam(_) => None,
+    };
+
+    ParamOverlayRow {
+        id: param_id.to_owned(),
+        name: param_ptr.name().to_owned(),
+        normalized_value: param_ptr.unmodulated_normalized_value(),
+        modulated_normalized_value,
+        value: param_ptr.normalized_value_to_string(modulated_normalized_value, true),
+        smoothed_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{BoolParam, FloatParam, FloatRange};
+
+    struct TestParams {
+        gain: FloatParam,
+        enabled: BoolParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (
+                    String::from("enabled"),
+                    self.enabled.as_ptr(),
+                    String::new(),
+                ),
+            ]
+        }
+    }
+
+    #[test]
+    fn reads_values_from_param_map() {
+        let params = Arc::new(TestParams {
+            gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit(" x"),
+            enabled: BoolParam::new("Enabled", true),
+        });
+        let mut overlay = ParamOverlay::new(params.clone());
+
+        assert!(overlay.update());
+        assert!(!overlay.update());
+        let rows = &overlay.rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].id, "gain");
+        assert_eq!(rows[0].name, "Gain");
+        assert_eq!(rows[0].normalized_value, 0.5);
+        assert_eq!(rows[0].value, params.gain.to_string());
+        assert!(rows[0].smoothed_value.is_some());
+        assert_eq!(rows[1].normalized_value, 1.0);
+        assert_eq!(rows[1].smoothed_value, None);
+
+        // Changes made elsewhere, like from the audio thread, show up in the next update
+        unsafe { params.gain.as_ptr().set_normalized_value(0.25) };
+        assert!(overlay.update());
+        assert_eq!(overlay.rows[0].normalized_value, 0.25);
+        assert_eq!(overlay.rows[0].value, params.gain.to_string());
+        assert!(overlay.render().starts_with("Gain     gain     0.2500"));
+    }
+}
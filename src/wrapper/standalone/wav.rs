@@ -0,0 +1,221 @@
+//! Minimal WAV file reading and writing for offline processing.
+
+use anyhow::{Context, Result};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+/// `WAVE_FORMAT_PCM`, integer samples.
+const FORMAT_PCM: u16 = 0x0001;
+/// `WAVE_FORMAT_IEEE_FLOAT`, floating point samples.
+const FORMAT_IEEE_FLOAT: u16 = 0x0003;
+/// `WAVE_FORMAT_EXTENSIBLE`, the actual format is stored in the first two bytes of the sub format
+/// GUID.
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// An entire WAV file's audio, decoded to `f32` samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavFile {
+    pub sample_rate: u32,
+    /// The samples for each channel. All channels have the same length.
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl WavFile {
+    /// Read and decode a WAV file. 8, 16, 24, and 32-bit integer PCM files and 32 and 64-bit
+    /// floating point files are supported.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).context("Could not open the file")?;
+
+        Self::from_reader(file)
+    }
+
+    /// The same as [`read()`][Self::read()], but reading from an arbitrary reader.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            anyhow::bail!("Not a RIFF WAVE file");
+        }
+
+        // The format is stored as `(format, num_channels, sample_rate, block_align, bit_depth)`
+        let mut format = None;
+        let mut data = None;
+        let mut chunks = &bytes[12..];
+        while chunks.len() >= 8 {
+            let chunk_id = &chunks[0..4];
+            let chunk_size = read_u32(&chunks[4..8]) as usize;
+            let chunk = chunks
+                .get(8..8 + chunk_size)
+                .context("The file is truncated")?;
+
+            match chunk_id {
+                b"fmt " => {
+                    if chunk.len() < 16 {
+                        anyhow::bail!("Malformed format chunk");
+                    }
+
+                    let mut format_tag = read_u16(&chunk[0..2]);
+                    if format_tag == FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+                        format_tag = read_u16(&chunk[24..26]);
+                    }
+                    format = Some((
+                        format_tag,
+                        read_u16(&chunk[2..4]) as usize,
+                        read_u32(&chunk[4..8]),
+                        read_u16(&chunk[12..14]) as usize,
+                        read_u16(&chunk[14..16]),
+                    ));
+                }
+                b"data" => data = Some(chunk),
+                _ => (),
+            }
+
+            // Chunks are padded to an even number of bytes
+            chunks = chunks
+                .get(8 + chunk_size + (chunk_size % 2)..)
+                .unwrap_or(&[]);
+        }
+
+        let (format_tag, num_channels, sample_rate, block_align, bit_depth) =
+            format.context("The file does not contain a format chunk")?;
+        let data = data.context("The file does not contain a data chunk")?;
+        let bytes_per_sample = bit_depth as usize / 8;
+        if num_channels == 0 || block_align != num_channels * bytes_per_sample {
+            anyhow::bail!("Malformed format chunk");
+        }
+
+        let decode: fn(&[u8]) -> f32 = match (format_tag, bit_depth) {
+            (FORMAT_PCM, 8) => |bytes| (bytes[0] as f32 - 128.0) / 128.0,
+            (FORMAT_PCM, 16) => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            (FORMAT_PCM, 24) => {
+                |bytes| i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2147483648.0
+            }
+            (FORMAT_PCM, 32) => |bytes| read_u32(bytes) as i32 as f32 / 2147483648.0,
+            (FORMAT_IEEE_FLOAT, 32) => |bytes| f32::from_bits(read_u32(bytes)),
+            (FORMAT_IEEE_FLOAT, 64) => {
+                |bytes| f64::from_le_bytes(bytes[0..8].try_into().unwrap()) as f32
+            }
+            (format_tag, bit_depth) => anyhow::bail!(
+                "Unsupported sample format {format_tag:#06x} with a bit depth of {bit_depth}"
+            ),
+        };
+
+        let mut channels = vec![Vec::with_capacity(data.len() / block_align); num_channels];
+        for frame in data.chunks_exact(block_align) {
+            for (channel, sample) in channels
+                .iter_mut()
+                .zip(frame.chunks_exact(bytes_per_sample))
+            {
+                channel.push(decode(sample));
+            }
+        }
+
+        Ok(Self {
+            sample_rate,
+            channels,
+        })
+    }
+
+    /// Write the audio to a 32-bit floating point WAV file.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).context("Could not create the file")?;
+        let mut writer = BufWriter::new(file);
+        self.to_writer(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// The same as [`write()`][Self::write()], but writing to an arbitrary writer.
+    pub fn to_writer(&self, mut writer: impl Write) -> Result<()> {
+        let num_channels = self.channels.len();
+        let num_samples = self.channels.first().map(Vec::len).unwrap_or_default();
+        let block_align = num_channels * 4;
+        let data_size = u32::try_from(num_samples * block_align)
+            .ok()
+            .filter(|size| *size <= u32::MAX - 36)
+            .context("The audio is too long to be stored in a WAV file")?;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_size).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&FORMAT_IEEE_FLOAT.to_le_bytes())?;
+        writer.write_all(&(num_channels as u16).to_le_bytes())?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&(self.sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&(block_align as u16).to_le_bytes())?;
+        writer.write_all(&32u16.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&data_size.to_le_bytes())?;
+        for sample_idx in 0..num_samples {
+            for channel in &self.channels {
+                writer.write_all(&channel[sample_idx].to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let wav = WavFile {
+            sample_rate: 44_100,
+            channels: vec![vec![0.0, 0.5, -1.0, 0.25], vec![1.0, -0.5, 0.125, 0.0]],
+        };
+
+        let mut bytes = Vec::new();
+        wav.to_writer(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 44 + 4 * 2 * 4);
+        assert_eq!(WavFile::from_reader(bytes.as_slice()).unwrap(), wav);
+    }
+
+    #[test]
+    fn read_pcm() {
+        // A mono 16-bit file with an unrelated chunk with an odd size before the data chunk
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&50u32.to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        for field in [FORMAT_PCM, 1] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(&48_000u32.to_le_bytes());
+        bytes.extend_from_slice(&96_000u32.to_le_bytes());
+        for field in [2u16, 16] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"LIST");
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&6u32.to_le_bytes());
+        for sample in [0i16, 16384, -32768] {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let wav = WavFile::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(wav.sample_rate, 48_000);
+        assert_eq!(wav.channels, [[0.0, 0.5, -1.0]]);
+
+        assert!(WavFile::from_reader(&b"RIFF\0\0\0\0AIFF"[..]).is_err());
+    }
+}
@@ -6,22 +6,29 @@ use parking_lot::Mutex;
 use raw_window_handle::HasRawWindowHandle;
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 use super::backend::Backend;
 use super::config::WrapperConfig;
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
+use super::param_overlay::ParamOverlay;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, ParamFlags, ParamPtr, Params,
-    ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor,
-    Transport,
+    AsyncExecutor, AudioIOLayout, BufferConfig, ChannelCounts, Editor, ParamChangeSource,
+    ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode,
+    ProcessStatus, TaskExecutor, Transport,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::process_wrapper;
+use crate::wrapper::state::{self, EditorStateStore, PluginState};
+use crate::wrapper::util::buffer_management::{PaddedBuffer, ScratchBuffers};
+use crate::wrapper::util::{
+    is_sample_rate_supported, lone_param_link_groups, process_wrapper, BlockPositionCounter,
+    BypassState, FactoryPresets, FirstProcess, ParamChangeSources, ParamGestures, SeekDetector,
+    TransportSnapshot,
+};
 
 /// How many parameter changes we can store in our unprocessed parameter change queue. Storing more
 /// than this many parameters at a time will cause changes to get lost.
@@ -63,6 +70,25 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// A mapping from parameter string IDs to parameter pointers. Used for serialization and
     /// deserialization.
     param_id_to_ptr: HashMap<String, ParamPtr>,
+    /// Keeps track of which parameters have been changed by the editor. There is no host here, so
+    /// the source will always be the editor.
+    param_change_sources: ParamChangeSources<ParamPtr>,
+    /// Parameters that are currently being changed from the editor. Used for
+    /// `P::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD`.
+    param_gestures: ParamGestures<ParamPtr>,
+    /// Lets parameters with a neutral bypass value react to changes to the bypass parameter.
+    bypass_state: BypassState,
+    /// The editor's own state that's saved alongside the parameters. There's no host to save the
+    /// state for the standalone target, but this keeps the values around while the editor is
+    /// being recreated.
+    editor_state: EditorStateStore,
+    /// The transport information from the last processing cycle, exposed to the editor.
+    transport_snapshot: TransportSnapshot,
+    /// The plugin's factory presets and the preset that was loaded last, exposed to the editor.
+    factory_presets: FactoryPresets,
+    /// Calls [`Plugin::first_process()`] before the first block after the plugin has been
+    /// initialized.
+    first_process: FirstProcess,
 
     /// The bus and buffer configurations are static for the standalone target.
     audio_io_layout: AudioIOLayout,
@@ -88,6 +114,17 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// still kept track of to avoid firing debug assertions multiple times for the same latency
     /// value.
     current_latency: AtomicU32,
+    /// The number of scratch buffers the plugin requested with
+    /// `InitContext::request_scratch_buffers()` during initialization.
+    scratch_buffer_count: AtomicUsize,
+    /// The scratch buffers exposed through `ProcessContext::scratch_buffers()`. These are allocated
+    /// after the plugin has been initialized.
+    scratch_buffers: AtomicRefCell<ScratchBuffers>,
+    /// Keeps track of the absolute position of each block for
+    /// [`ProcessContext::block_start_samples()`]. Reset whenever the plugin gets reset.
+    block_position: BlockPositionCounter,
+    /// Detects seeks for [`Transport::just_seeked()`]. Reset whenever the plugin gets reset.
+    seek_detector: SeekDetector,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -111,6 +148,9 @@ pub enum Task<P: Plugin> {
 pub enum WrapperError {
     /// The plugin returned `false` during initialization.
     InitializationFailed,
+    /// The plugin does not support the configured sample rate. See
+    /// [`Plugin::SUPPORTED_SAMPLE_RATES`].
+    UnsupportedSampleRate(f32),
 }
 
 struct WrapperWindowHandler {
@@ -121,6 +161,9 @@ struct WrapperWindowHandler {
     /// This is used to communicate with the wrapper from the audio thread and from within the
     /// baseview window handler on the GUI thread.
     gui_task_receiver: channel::Receiver<GuiTask>,
+    /// Lists the parameter values every frame they change if the `--param-overlay` option was
+    /// passed.
+    param_overlay: Option<ParamOverlay>,
 }
 
 /// A message sent to the GUI thread.
@@ -144,6 +187,10 @@ impl WindowHandler for WrapperWindowHandler {
                 GuiTask::Close => window.close(),
             }
         }
+
+        if let Some(param_overlay) = &mut self.param_overlay {
+            print_param_overlay(param_overlay);
+        }
     }
 
     fn on_event(&mut self, _window: &mut Window, _event: baseview::Event) -> EventStatus {
@@ -151,6 +198,13 @@ impl WindowHandler for WrapperWindowHandler {
     }
 }
 
+/// Print the parameter overlay if any of the parameter values have changed since the last call.
+fn print_param_overlay(param_overlay: &mut ParamOverlay) {
+    if param_overlay.update() {
+        nih_log!("Parameter values:\n{}", param_overlay.render());
+    }
+}
+
 impl<P: Plugin, B: Backend<P>> MainThreadExecutor<Task<P>> for Wrapper<P, B> {
     fn execute(&self, task: Task<P>, _is_gui_thread: bool) {
         match task {
@@ -213,6 +267,17 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 }
 
                 bypass_param_exists |= is_bypass;
+
+                unsafe { ptr.assert_validity() };
+            }
+
+            let lone_link_groups =
+                unsafe { lone_param_link_groups(param_map.iter().map(|(_, ptr, _)| ptr)) };
+            for link_group in lone_link_groups {
+                nih_debug_assert_failure!(
+                    "The parameter link group '{}' only contains a single parameter",
+                    link_group
+                );
             }
         }
 
@@ -234,18 +299,36 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 .iter()
                 .map(|(param_id, param_ptr, _)| (*param_ptr, param_id.clone()))
                 .collect(),
+            param_change_sources: ParamChangeSources::new(
+                param_map.iter().map(|(_, param_ptr, _)| *param_ptr),
+            ),
+            param_gestures: ParamGestures::new(
+                param_map.iter().map(|(_, param_ptr, _)| *param_ptr),
+            ),
+            bypass_state: unsafe {
+                BypassState::new(param_map.iter().map(|(_, param_ptr, _)| *param_ptr))
+            },
             param_id_to_ptr: param_map
                 .into_iter()
                 .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
                 .collect(),
+            editor_state: EditorStateStore::default(),
+            transport_snapshot: TransportSnapshot::default(),
+            factory_presets: FactoryPresets::new::<P>(),
+            first_process: FirstProcess::default(),
 
             audio_io_layout,
             buffer_config: BufferConfig {
                 sample_rate: config.sample_rate,
                 min_buffer_size: None,
                 max_buffer_size: config.period_size,
+                preferred_buffer_size: Some(config.period_size),
                 // TODO: Detect JACK freewheeling and report it here
-                process_mode: ProcessMode::Realtime,
+                process_mode: if config.input.is_some() {
+                    ProcessMode::Offline
+                } else {
+                    ProcessMode::Realtime
+                },
             },
             config,
 
@@ -253,6 +336,10 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             updated_state_sender,
             updated_state_receiver,
             current_latency: AtomicU32::new(0),
+            scratch_buffer_count: AtomicUsize::new(0),
+            scratch_buffers: AtomicRefCell::new(ScratchBuffers::default()),
+            block_position: BlockPositionCounter::default(),
+            seek_detector: SeekDetector::default(),
         });
 
         *wrapper.event_loop.borrow_mut() =
@@ -282,6 +369,12 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             })
             .map(|editor| Arc::new(Mutex::new(editor)));
 
+        if !is_sample_rate_supported(P::SUPPORTED_SAMPLE_RATES, wrapper.buffer_config.sample_rate) {
+            return Err(WrapperError::UnsupportedSampleRate(
+                wrapper.buffer_config.sample_rate,
+            ));
+        }
+
         // Before initializing the plugin, make sure all smoothers are set the the default values
         for param in wrapper.param_id_to_ptr.values() {
             unsafe { param.update_smoother(wrapper.buffer_config.sample_rate, true) };
@@ -296,21 +389,70 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             ) {
                 return Err(WrapperError::InitializationFailed);
             }
+            *wrapper.scratch_buffers.borrow_mut() = ScratchBuffers::new(
+                wrapper.scratch_buffer_count.load(Ordering::Relaxed),
+                wrapper.buffer_config.max_buffer_size as usize,
+            );
+            wrapper.block_position.reset();
+            wrapper.seek_detector.reset();
             process_wrapper(|| plugin.reset());
+            wrapper.first_process.activated();
         }
 
         Ok(wrapper)
     }
 
     /// Open the editor, start processing audio, and block this thread until the editor is closed.
-    /// If the plugin does not have an editor, then this will block until SIGINT is received.
+    /// If the plugin does not have an editor, then this will block until SIGINT is received. When
+    /// processing a file offline, this instead processes the entire file on this thread without
+    /// opening the editor.
     ///
     /// Will return an error if the plugin threw an error during audio processing or if the editor
     /// could not be opened.
     pub fn run(self: Arc<Self>) -> Result<(), WrapperError> {
         let (gui_task_sender, gui_task_receiver) = channel::bounded(512);
+        if self.buffer_config.process_mode == ProcessMode::Offline {
+            // The offline backend returns as soon as it reaches the end of its input file. The GUI
+            // task receiver only needs to stay alive until then.
+            self.backend
+                .borrow_mut()
+                .set_latency_samples(self.current_latency.load(Ordering::SeqCst));
+            self.clone()
+                .run_audio_thread(Arc::new(AtomicBool::new(false)), gui_task_sender);
+            drop(gui_task_receiver);
+            self.plugin.lock().deactivate();
+
+            return Ok(());
+        }
+
         *self.gui_tasks_sender.borrow_mut() = Some(gui_task_sender.clone());
 
+        #[cfg(feature = "standalone_control_socket")]
+        if let Some(addr) = self.config.control_socket {
+            // Parameter changes go through the same path as changes made from the editor
+            let this = self.clone();
+            match super::control_socket::spawn(
+                addr,
+                self.params.clone(),
+                move |param_ptr, value| this.set_parameter(param_ptr, value),
+            ) {
+                Ok(addr) => nih_log!("Listening for control socket connections on {addr}"),
+                Err(err) => nih_error!("Could not open the control socket on {addr}: {err}"),
+            }
+        }
+
+        #[cfg(feature = "standalone_osc")]
+        if let Some(addr) = self.config.osc {
+            // Parameter changes go through the same path as changes made from the editor
+            let this = self.clone();
+            match super::osc::spawn(addr, self.params.clone(), move |param_ptr, value| {
+                this.set_parameter(param_ptr, value)
+            }) {
+                Ok(addr) => nih_log!("Listening for OSC messages on {addr}"),
+                Err(err) => nih_error!("Could not open the OSC socket on {addr}: {err}"),
+            }
+        }
+
         // We'll spawn a separate thread to handle IO and to process audio. This audio thread should
         // terminate together with this function.
         let terminate_audio_thread = Arc::new(AtomicBool::new(false));
@@ -320,6 +462,12 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             thread::spawn(move || this.run_audio_thread(terminate_audio_thread, gui_task_sender))
         };
 
+        let param_overlay = if self.config.param_overlay {
+            Some(ParamOverlay::new(self.params.clone()))
+        } else {
+            None
+        };
+
         match self.editor.borrow().clone() {
             Some(editor) => {
                 let context = self.clone().make_gui_context();
@@ -359,6 +507,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                         WrapperWindowHandler {
                             _editor_handle: editor_handle,
                             gui_task_receiver,
+                            param_overlay,
                         }
                     },
                 )
@@ -367,7 +516,15 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 // TODO: Properly block until SIGINT is received if the plugin does not have an editor
                 // TODO: Make sure to handle `GuiTask::Close` here as well
                 nih_log!("{} does not have a GUI, blocking indefinitely...", P::NAME);
-                std::thread::park();
+                match param_overlay {
+                    // Without a window there are no frames, so the overlay is updated at roughly
+                    // the same rate from here instead
+                    Some(mut param_overlay) => loop {
+                        print_param_overlay(&mut param_overlay);
+                        thread::sleep(Duration::from_millis(16));
+                    },
+                    None => std::thread::park(),
+                }
             }
         }
 
@@ -395,6 +552,22 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
     /// This returns false if the parameter was not set because the `ParamPtr` was either unknown or
     /// the queue is full.
     pub fn set_parameter(&self, param: ParamPtr, normalized: f32) -> bool {
+        let push_successful = self.queue_parameter_change(param, normalized);
+        if push_successful {
+            self.param_change_sources.editor_changed(&param, normalized);
+        }
+
+        push_successful
+    }
+
+    /// The same as [`set_parameter()`][Self::set_parameter()], but for changes made by the plugin
+    /// itself through `ProcessContext::set_parameter_automated()`. These are not attributed to the
+    /// editor.
+    pub fn set_parameter_automated(&self, param: ParamPtr, normalized: f32) -> bool {
+        self.queue_parameter_change(param, normalized)
+    }
+
+    fn queue_parameter_change(&self, param: ParamPtr, normalized: f32) -> bool {
         if !self.param_ptr_to_id.contains_key(&param) {
             return false;
         }
@@ -408,6 +581,48 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         push_successful
     }
 
+    /// Record that the editor started or ended a gesture for a parameter. See
+    /// [`ParamGestures`].
+    pub fn set_param_gesture(&self, param: ParamPtr, active: bool) {
+        if active {
+            self.param_gestures.begin(&param);
+        } else {
+            self.param_gestures.end(&param);
+        }
+    }
+
+    /// Get and reset the source of the last change to a parameter. See
+    /// [`GuiContext::raw_take_param_change_source()`][crate::prelude::GuiContext::raw_take_param_change_source()].
+    pub fn take_param_change_source(&self, param: ParamPtr) -> Option<ParamChangeSource> {
+        self.param_change_sources.take(&param)
+    }
+
+    /// Get the transport information from the last processing cycle. See [`TransportSnapshot`].
+    pub fn transport(&self) -> Option<Transport> {
+        self.transport_snapshot.load()
+    }
+
+    /// The factory preset that was loaded last. See [`FactoryPresets`].
+    pub fn current_preset(&self) -> Option<usize> {
+        self.factory_presets.current()
+    }
+
+    /// Load a factory preset from the GUI. Returns `false` if the index is out of range.
+    pub fn set_preset(&self, index: usize) -> bool {
+        self.factory_presets
+            .load(index, |state| self.set_state_object_from_gui(state))
+    }
+
+    /// Get a value from the editor's own state. See [`EditorStateStore`].
+    pub fn editor_state(&self, key: &str) -> Option<String> {
+        self.editor_state.get(key)
+    }
+
+    /// Store a value in the editor's own state. See [`EditorStateStore`].
+    pub fn set_editor_state(&self, key: &str, value: Option<String>) {
+        self.editor_state.set(key, value)
+    }
+
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
     /// the JSON in the relevant plugin API methods instead.
@@ -418,6 +633,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 self.param_id_to_ptr
                     .iter()
                     .map(|(param_id, param_ptr)| (param_id, *param_ptr)),
+                &self.editor_state,
             )
         }
     }
@@ -478,11 +694,26 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         }
     }
 
+    /// The channel counts for the audio IO layout, which is fixed for the standalone target.
+    pub fn channel_counts(&self) -> ChannelCounts {
+        self.audio_io_layout.channel_counts()
+    }
+
+    /// Record the number of scratch buffers the plugin needs. These are allocated after the plugin
+    /// has been initialized.
+    pub fn request_scratch_buffers(&self, count: usize) {
+        self.scratch_buffer_count.store(count, Ordering::Relaxed);
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // This should only change the value if it's actually needed
         let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
-        if old_latency != samples {
-            // None of the backends actually support this at the moment
+        // The offline backend compensates for the latency that was set before processing started.
+        // The backend is borrowed for as long as it's processing.
+        let compensated = self.buffer_config.process_mode == ProcessMode::Offline
+            && self.backend.try_borrow_mut().is_ok();
+        if old_latency != samples && !compensated {
+            // None of the realtime backends actually support this at the moment
             nih_debug_assert_failure!("Standalones currently don't support latency reporting");
         }
     }
@@ -494,6 +725,11 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         should_terminate: Arc<AtomicBool>,
         gui_task_sender: channel::Sender<GuiTask>,
     ) {
+        let mut padded_buffer = PaddedBuffer::for_audio_io_layout(
+            self.buffer_config.max_buffer_size as usize,
+            self.audio_io_layout,
+            P::SIMD_BLOCK_MULTIPLE,
+        );
         self.clone().backend.borrow_mut().run(
             move |buffer, aux, transport, input_events, output_events| {
                 // TODO: This process wrapper should actually be in the backends (since the backends
@@ -505,13 +741,21 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                     }
 
                     let sample_rate = self.buffer_config.sample_rate;
+                    unsafe { self.bypass_state.update(sample_rate) };
                     {
                         let mut plugin = self.plugin.lock();
-                        if let ProcessStatus::Error(err) = plugin.process(
-                            buffer,
-                            aux,
-                            &mut self.make_process_context(transport, input_events, output_events),
-                        ) {
+                        let block_len = buffer.samples();
+                        let mut context = self.make_process_context(
+                            transport,
+                            block_len,
+                            input_events,
+                            output_events,
+                        );
+                        self.first_process
+                            .before_process(|| plugin.first_process(&mut context));
+                        let result = padded_buffer
+                            .process(buffer, |buffer| plugin.process(buffer, aux, &mut context));
+                        if let ProcessStatus::Error(err) = result {
                             nih_error!("The plugin returned an error while processing:");
                             nih_error!("{}", err);
 
@@ -586,16 +830,23 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
 
     fn make_process_context<'a>(
         &'a self,
-        transport: Transport,
+        mut transport: Transport,
+        block_len: usize,
         input_events: &'a [PluginNoteEvent<P>],
         output_events: &'a mut Vec<PluginNoteEvent<P>>,
     ) -> WrapperProcessContext<'a, P, B> {
+        let block_start_samples = self.block_position.advance(&transport, block_len);
+        transport.just_seeked = self.seek_detector.advance(&transport, block_len);
+        self.transport_snapshot.store(&transport);
+
         WrapperProcessContext {
             wrapper: self,
             input_events,
             input_events_idx: 0,
             output_events,
+            scratch_buffers_guard: self.scratch_buffers.borrow_mut(),
             transport,
+            block_start_samples,
         }
     }
 
@@ -622,6 +873,8 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 state,
                 self.params.clone(),
                 |param_id| self.param_id_to_ptr.get(param_id).copied(),
+                |param_ptr| self.param_gestures.preserves_on_state_load::<P>(&param_ptr),
+                &self.editor_state,
                 Some(&self.buffer_config),
             )
         });
@@ -630,6 +883,9 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             return false;
         }
 
+        // The plugin's state no longer matches the factory preset that was loaded last, if any
+        self.factory_presets.state_loaded();
+
         // If the plugin was already initialized then it needs to be reinitialized
         {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -645,6 +901,8 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 )
             });
             if success {
+                self.block_position.reset();
+                self.seek_detector.reset();
                 process_wrapper(|| plugin.reset());
             }
         }
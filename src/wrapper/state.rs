@@ -2,13 +2,30 @@
 //! to plugins through the [`GuiContext`][crate::prelude::GuiContext].
 
 use anyhow::{Context, Result};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufReader, Read, Write};
 use std::sync::Arc;
 
 use crate::params::ParamMut;
 use crate::prelude::{BufferConfig, Param, ParamPtr, Params, Plugin};
 
+/// The maximum size of a single chunk written by [`ChunkedWriter`].
+pub(crate) const STATE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The magic number at the start of every Zstandard frame. Used to detect compressed states when
+/// reading them from a stream.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The format version stored in the first byte of the strings produced by
+/// [`PluginState::to_shareable_string()`]. This needs to be bumped whenever the format changes.
+const SHAREABLE_STRING_VERSION: u8 = 1;
+
+/// The alphabet for the URL-safe base64 variant used by shareable strings.
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
 // These state objects are also exposed directly to the plugin so it can do its own internal preset
 // management
 
@@ -50,6 +67,128 @@ pub struct PluginState {
     /// The individual fields are also serialized as JSON so they can safely be restored
     /// independently of the other fields.
     pub fields: BTreeMap<String, String>,
+    /// State belonging to the plugin's editor, like the currently selected tab or the GUI's theme.
+    /// These values are set through
+    /// [`GuiContext::set_editor_state()`][crate::prelude::GuiContext::set_editor_state()], and
+    /// unlike parameters they are not exposed to the host.
+    #[serde(default)]
+    pub gui: BTreeMap<String, String>,
+}
+
+impl PluginState {
+    /// Encode this state as a compact, URL-safe string so users can share presets as text, for
+    /// instance in a forum post or a chat message. This includes the parameter values and the
+    /// persisted fields, but not the editor state in [`gui`][Self::gui]. The string starts with a
+    /// format version so strings created by future versions of NIH-plug can be detected.
+    ///
+    /// Parse the string again with [`from_shareable_string()`][Self::from_shareable_string()],
+    /// and apply the result with [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()].
+    pub fn to_shareable_string(&self) -> String {
+        let state = PluginState {
+            version: self.version.clone(),
+            params: self.params.clone(),
+            fields: self.fields.clone(),
+            gui: BTreeMap::new(),
+        };
+
+        let mut data = vec![SHAREABLE_STRING_VERSION];
+        serde_json::to_writer(&mut data, &state).expect("Could not format the state as JSON");
+
+        base64_url_encode(&data)
+    }
+
+    /// Parse a string created by [`to_shareable_string()`][Self::to_shareable_string()]. Leading
+    /// and trailing whitespace is ignored. Returns an error if the string is malformed or if it was
+    /// created with an unknown format version.
+    pub fn from_shareable_string(string: &str) -> Result<PluginState> {
+        let data = base64_url_decode(string.trim()).context("The string is not valid base64")?;
+        match data.split_first() {
+            Some((&SHAREABLE_STRING_VERSION, json)) => {
+                serde_json::from_slice(json).context("Could not parse JSON")
+            }
+            Some((version, _)) => anyhow::bail!("Unknown shareable string version {version}"),
+            None => anyhow::bail!("The string is empty"),
+        }
+    }
+}
+
+/// Encode `data` using the URL-safe base64 alphabet without padding.
+fn base64_url_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 4 + 2) / 3);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, byte)| {
+            bits | ((*byte as u32) << (16 - idx * 8))
+        });
+
+        // Every byte is spread out over at most two characters
+        for char_idx in 0..=chunk.len() {
+            let value = (bits >> (18 - char_idx * 6)) & 0b11_1111;
+            encoded.push(BASE64_URL_ALPHABET[value as usize] as char);
+        }
+    }
+
+    encoded
+}
+
+/// Decode a string encoded with [`base64_url_encode()`]. Returns `None` if the string contains
+/// characters outside of the alphabet or if its length is not possible for unpadded base64.
+fn base64_url_decode(string: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(string.len() * 3 / 4);
+    for chunk in string.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let mut bits = 0u32;
+        for (char_idx, char) in chunk.iter().enumerate() {
+            let value = BASE64_URL_ALPHABET.iter().position(|c| c == char)? as u32;
+            bits |= value << (18 - char_idx * 6);
+        }
+        for byte_idx in 0..chunk.len() - 1 {
+            decoded.push((bits >> (16 - byte_idx * 8)) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Storage for the editor's own state, see
+/// [`GuiContext::set_editor_state()`][crate::prelude::GuiContext::set_editor_state()]. This lives
+/// in the wrapper so the values outlive the editor, and it is saved to and restored from
+/// [`PluginState::gui`] together with the rest of the plugin's state.
+#[derive(Debug, Default)]
+pub(crate) struct EditorStateStore {
+    values: Mutex<BTreeMap<String, String>>,
+}
+
+impl EditorStateStore {
+    /// Get the value stored under `key`, if it exists.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.values.lock().get(key).cloned()
+    }
+
+    /// Store `value` under `key`. `None` removes the key.
+    pub fn set(&self, key: &str, value: Option<String>) {
+        let mut values = self.values.lock();
+        match value {
+            Some(value) => {
+                values.insert(key.to_owned(), value);
+            }
+            None => {
+                values.remove(key);
+            }
+        }
+    }
+
+    /// Get a copy of all stored values for inclusion in a [`PluginState`].
+    pub fn snapshot(&self) -> BTreeMap<String, String> {
+        self.values.lock().clone()
+    }
+
+    /// Replace all stored values with the values from a [`PluginState`].
+    pub fn restore(&self, values: &BTreeMap<String, String>) {
+        *self.values.lock() = values.clone();
+    }
 }
 
 /// Create a parameters iterator from the hashtables stored in the plugin wrappers. This avoids
@@ -85,6 +224,7 @@ pub(crate) fn make_params_getter<'a>(
 pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    editor_state: &EditorStateStore,
 ) -> PluginState {
     // We'll serialize parameter values as a simple `string_param_id: display_value` map.
     // NOTE: If the plugin is being modulated (and the plugin is a CLAP plugin in Bitwig Studio),
@@ -125,6 +265,7 @@ pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
         version: String::from(P::VERSION),
         params,
         fields,
+        gui: editor_state.snapshot(),
     }
 }
 
@@ -134,8 +275,9 @@ pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
 pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    editor_state: &EditorStateStore,
 ) -> Result<Vec<u8>> {
-    let plugin_state = serialize_object::<P>(plugin_params, params_iter);
+    let plugin_state = serialize_object::<P>(plugin_params, params_iter, editor_state);
     let json = serde_json::to_vec(&plugin_state).context("Could not format as JSON")?;
 
     #[cfg(feature = "zstd")]
@@ -159,6 +301,39 @@ pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
     }
 }
 
+/// The same as [`serialize_json()`], but the (compressed) JSON data is written to `writer` as it is
+/// being generated instead of being collected in a vector first. This is used together with
+/// [`ChunkedWriter`] to stream large states to the host.
+pub(crate) unsafe fn serialize_json_to_writer<'a, P: Plugin>(
+    plugin_params: Arc<dyn Params>,
+    params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    editor_state: &EditorStateStore,
+    writer: impl Write,
+) -> Result<()> {
+    let plugin_state = serialize_object::<P>(plugin_params, params_iter, editor_state);
+
+    write_json(&plugin_state, writer)
+}
+
+/// Write a [`PluginState`] object to `writer` as JSON. If the `zstd` feature is enabled, then the
+/// state will be compressed using Zstandard.
+pub(crate) fn write_json(plugin_state: &PluginState, writer: impl Write) -> Result<()> {
+    #[cfg(feature = "zstd")]
+    {
+        let mut encoder =
+            zstd::stream::write::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .context("Could not compress state")?;
+        serde_json::to_writer(&mut encoder, plugin_state).context("Could not format as JSON")?;
+        encoder.finish().context("Could not compress state")?;
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        serde_json::to_writer(writer, plugin_state).context("Could not format as JSON")?;
+    }
+
+    Ok(())
+}
+
 /// Deserialize a plugin's state from a [`PluginState`] object. This is used to allow the plugin to
 /// do its own internal preset management. Returns `false` and logs an error if the state could not
 /// be deserialized.
@@ -170,11 +345,14 @@ pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
 /// parameter values. The smoothers have already been reset by this function.
 ///
 /// The [`Plugin`] argument is used to call [`Plugin::filter_state()`] just before loading the
-/// state.
+/// state. Parameters for which `is_param_preserved` returns `true` keep their current values. The
+/// wrappers use this to implement [`Plugin::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD`].
 pub(crate) unsafe fn deserialize_object<P: Plugin>(
     state: &mut PluginState,
     plugin_params: Arc<dyn Params>,
     params_getter: impl Fn(&str) -> Option<ParamPtr>,
+    is_param_preserved: impl Fn(ParamPtr) -> bool,
+    editor_state: &EditorStateStore,
     current_buffer_config: Option<&BufferConfig>,
 ) -> bool {
     // This lets the plugin perform migrations on old state if needed
@@ -189,6 +367,9 @@ pub(crate) unsafe fn deserialize_object<P: Plugin>(
                 continue;
             }
         };
+        if is_param_preserved(param_ptr) {
+            continue;
+        }
 
         match (param_ptr, param_value) {
             (ParamPtr::FloatParam(p), ParamValue::F32(v)) => {
@@ -235,6 +416,9 @@ pub(crate) unsafe fn deserialize_object<P: Plugin>(
     // storing things like sample data.
     plugin_params.deserialize_fields(&state.fields);
 
+    // The editor's state is restored separately so it's available the next time the editor opens
+    editor_state.restore(&state.gui);
+
     true
 }
 
@@ -292,3 +476,423 @@ pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
 
     result
 }
+
+/// The same as [`deserialize_json()`], but the (compressed) JSON data is read from `reader` while
+/// it is being parsed instead of being read into a vector first. This is used together with
+/// [`ChunkedReader`] to stream large states from the host. Compressed and uncompressed states are
+/// told apart by looking at the first couple bytes.
+pub(crate) fn deserialize_json_from_reader(mut reader: impl Read) -> Option<PluginState> {
+    let mut prefix = [0u8; ZSTD_MAGIC.len()];
+    if let Err(err) = reader.read_exact(&mut prefix) {
+        nih_debug_assert_failure!("Error while reading state: {}", err);
+        return None;
+    }
+    let reader = BufReader::new(prefix.as_slice().chain(reader));
+
+    let result: Result<PluginState> = if prefix == ZSTD_MAGIC {
+        #[cfg(feature = "zstd")]
+        {
+            zstd::stream::read::Decoder::with_buffer(reader)
+                .context("Could not decompress state")
+                .and_then(|decoder| {
+                    serde_json::from_reader(BufReader::new(decoder)).context("Could not parse JSON")
+                })
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            Err(anyhow::anyhow!(
+                "The state is compressed, but the `zstd` feature is not enabled"
+            ))
+        }
+    } else {
+        serde_json::from_reader(reader).context("Could not parse JSON")
+    };
+
+    match result {
+        Ok(state) => Some(state),
+        Err(err) => {
+            nih_debug_assert_failure!("Error while deserializing state: {:#}", err);
+            None
+        }
+    }
+}
+
+/// Splits a stream of unknown length into chunks of up to [`STATE_CHUNK_SIZE`] bytes, each prefixed
+/// by its length as a little endian `u32`. [`finish()`][Self::finish()] ends the stream with an
+/// empty chunk. This allows states to be written to streams that need to know how much data to
+/// expect, like CLAP's state streams, without having to serialize the entire state upfront. Read
+/// the data back with a [`ChunkedReader`].
+pub(crate) struct ChunkedWriter<W: Write> {
+    writer: W,
+    /// The data for the next chunk. This is written to `writer` once it's full.
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::with_capacity(STATE_CHUNK_SIZE),
+        }
+    }
+
+    /// Write any remaining data followed by the empty chunk that marks the end of the stream, and
+    /// return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk()?;
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    /// Write the buffered data as a chunk, if there is any.
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.writer
+            .write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&self.buffer)?;
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = buf.len().min(STATE_CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..num_bytes]);
+        if self.buffer.len() == STATE_CHUNK_SIZE {
+            self.write_chunk()?;
+        }
+
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.writer.flush()
+    }
+}
+
+/// Reads the data written by a [`ChunkedWriter`]. This reports the end of the data after reading
+/// the empty chunk that ends the stream, without reading any further from the underlying reader.
+pub(crate) struct ChunkedReader<R: Read> {
+    reader: R,
+    /// The number of bytes left in the current chunk.
+    chunk_remaining: usize,
+    /// Whether the empty chunk at the end of the stream has been read.
+    finished: bool,
+}
+
+impl<R: Read> ChunkedReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            chunk_remaining: 0,
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.chunk_remaining == 0 {
+            let mut length_bytes = [0u8; 4];
+            self.reader.read_exact(&mut length_bytes)?;
+            self.chunk_remaining = u32::from_le_bytes(length_bytes) as usize;
+            if self.chunk_remaining == 0 {
+                self.finished = true;
+                return Ok(0);
+            }
+        }
+
+        let num_bytes = buf.len().min(self.chunk_remaining);
+        let bytes_read = self.reader.read(&mut buf[..num_bytes])?;
+        if bytes_read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.chunk_remaining -= bytes_read;
+
+        Ok(bytes_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::test_plugin::{TestPlugin, TestPluginConfig};
+    use crate::prelude::*;
+    use crate::wrapper::util::ParamGestures;
+
+    struct Gesture;
+
+    type GesturePlugin = TestPlugin<Gesture>;
+
+    struct GestureParams {
+        gain: FloatParam,
+        frequency: FloatParam,
+    }
+
+    unsafe impl Params for GestureParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (
+                    String::from("frequency"),
+                    self.frequency.as_ptr(),
+                    String::new(),
+                ),
+            ]
+        }
+    }
+
+    impl Default for GestureParams {
+        fn default() -> Self {
+            let range = FloatRange::Linear { min: 0.0, max: 1.0 };
+            Self {
+                gain: FloatParam::new("Gain", 0.5, range),
+                frequency: FloatParam::new("Frequency", 0.5, range),
+            }
+        }
+    }
+
+    impl TestPluginConfig for Gesture {
+        const NAME: &'static str = "Gesture";
+        const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = true;
+
+        type Params = GestureParams;
+    }
+
+    #[test]
+    fn state_load_preserves_gestured_params() {
+        let plugin = GesturePlugin::default();
+        let params = plugin.params.clone();
+        let param_map = params.param_map();
+        let gestures = ParamGestures::new(param_map.iter().map(|(_, ptr, _)| *ptr));
+        let editor_state = EditorStateStore::default();
+
+        let mut state = PluginState {
+            version: String::from("0.0.1"),
+            params: BTreeMap::from([
+                (String::from("gain"), ParamValue::F32(0.25)),
+                (String::from("frequency"), ParamValue::F32(0.75)),
+            ]),
+            fields: BTreeMap::new(),
+            gui: BTreeMap::new(),
+        };
+        let load_state = |state: &mut PluginState| unsafe {
+            deserialize_object::<GesturePlugin>(
+                state,
+                params.clone(),
+                |param_id| {
+                    param_map
+                        .iter()
+                        .find(|(id, _, _)| id == param_id)
+                        .map(|(_, ptr, _)| *ptr)
+                },
+                |param_ptr| gestures.preserves_on_state_load::<GesturePlugin>(&param_ptr),
+                &editor_state,
+                None,
+            )
+        };
+
+        // The user is dragging the gain knob in the editor while the host reloads the state
+        let gain_ptr = params.gain.as_ptr();
+        gestures.begin(&gain_ptr);
+        unsafe { gain_ptr.set_normalized_value(0.9) };
+        assert!(load_state(&mut state));
+        assert_eq!(params.gain.value(), 0.9);
+        assert_eq!(params.frequency.value(), 0.75);
+
+        // Once the gesture has ended the state is applied as usual
+        gestures.end(&gain_ptr);
+        assert!(load_state(&mut state));
+        assert_eq!(params.gain.value(), 0.25);
+    }
+
+    #[test]
+    fn editor_state_roundtrip() {
+        let editor_state = EditorStateStore::default();
+        editor_state.set("tab", Some(String::from("envelope")));
+        editor_state.set("scroll", Some(String::from("120")));
+        editor_state.set("scroll", None);
+
+        let state = PluginState {
+            version: String::from("0.1.0"),
+            params: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            gui: editor_state.snapshot(),
+        };
+        let json = serde_json::to_vec(&state).unwrap();
+
+        // This simulates the plugin being reopened with a fresh wrapper and editor
+        let restored_state: PluginState = serde_json::from_slice(&json).unwrap();
+        assert!(restored_state.params.is_empty());
+        let restored_editor_state = EditorStateStore::default();
+        restored_editor_state.restore(&restored_state.gui);
+
+        assert_eq!(
+            restored_editor_state.get("tab").as_deref(),
+            Some("envelope")
+        );
+        assert_eq!(restored_editor_state.get("scroll"), None);
+    }
+
+    #[test]
+    fn shareable_string_roundtrip() {
+        let state = PluginState {
+            version: String::from("1.2.3"),
+            params: BTreeMap::from([
+                (String::from("gain"), ParamValue::F32(-12.5)),
+                (String::from("voices"), ParamValue::I32(7)),
+                (String::from("bypass"), ParamValue::Bool(true)),
+                (
+                    String::from("mode"),
+                    ParamValue::String(String::from("düsseldorf")),
+                ),
+            ]),
+            fields: BTreeMap::from([(String::from("wavetable"), String::from("[1,2,3]"))]),
+            gui: BTreeMap::from([(String::from("tab"), String::from("envelope"))]),
+        };
+
+        let string = state.to_shareable_string();
+        assert!(string
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let restored_state = PluginState::from_shareable_string(&format!(" {string}\n")).unwrap();
+        assert_eq!(restored_state.version, state.version);
+        assert_eq!(
+            serde_json::to_string(&restored_state.params).unwrap(),
+            serde_json::to_string(&state.params).unwrap()
+        );
+        assert_eq!(restored_state.fields, state.fields);
+        // Editor state is not part of a preset
+        assert!(restored_state.gui.is_empty());
+
+        // All possible lengths of the last base64 group need to survive the roundtrip
+        for len in 0..8 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 97 + 200) as u8).collect();
+            assert_eq!(base64_url_decode(&base64_url_encode(&data)), Some(data));
+        }
+    }
+
+    #[test]
+    fn shareable_string_unknown_version() {
+        let state = PluginState {
+            version: String::from("1.2.3"),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.5))]),
+            fields: BTreeMap::new(),
+            gui: BTreeMap::new(),
+        };
+
+        let mut data = base64_url_decode(&state.to_shareable_string()).unwrap();
+        assert_eq!(data[0], SHAREABLE_STRING_VERSION);
+        data[0] = SHAREABLE_STRING_VERSION + 1;
+        let err = PluginState::from_shareable_string(&base64_url_encode(&data)).unwrap_err();
+        assert!(err.to_string().contains("version"), "{err}");
+
+        assert!(PluginState::from_shareable_string("").is_err());
+        assert!(PluginState::from_shareable_string("not/base64+").is_err());
+    }
+
+    /// Only writes up to 1000 bytes at a time, like some host streams.
+    struct TrickleWriter(Vec<u8>);
+
+    impl Write for TrickleWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let num_bytes = buf.len().min(1000);
+            self.0.extend_from_slice(&buf[..num_bytes]);
+            Ok(num_bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Only reads up to 777 bytes at a time, like some host streams.
+    struct TrickleReader<'a>(&'a [u8]);
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let num_bytes = buf.len().min(777);
+            self.0.read(&mut buf[..num_bytes])
+        }
+    }
+
+    /// Pseudo-random hexadecimal data that doesn't compress too well.
+    fn noise_string(len: usize) -> String {
+        let mut seed: u32 = 1;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                char::from_digit(seed >> 28, 16).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunked_stream_roundtrip() {
+        // This covers both partially filled and completely filled chunks
+        let data = noise_string(STATE_CHUNK_SIZE * 3 + 17).into_bytes();
+        let mut writer = ChunkedWriter::new(TrickleWriter(Vec::new()));
+        writer.write_all(&data).unwrap();
+        let stream = writer.finish().unwrap().0;
+        assert_eq!(stream.len(), data.len() + 5 * 4);
+
+        // There's some trailing data after the stream that should not be touched
+        let stream_with_trailer = [stream.as_slice(), b"trailer"].concat();
+        let mut trickle_reader = TrickleReader(&stream_with_trailer);
+        let mut reader = ChunkedReader::new(&mut trickle_reader);
+        let mut read_data = Vec::new();
+        reader.read_to_end(&mut read_data).unwrap();
+        assert!(read_data == data);
+        assert_eq!(trickle_reader.0, b"trailer");
+
+        // Truncated streams are an error rather than silently returning partial data
+        let mut reader = ChunkedReader::new(TrickleReader(&stream[..stream.len() / 2]));
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn large_state_streaming() {
+        let state = PluginState {
+            version: String::from("0.1.0"),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.5))]),
+            fields: BTreeMap::from([(String::from("sample_data"), noise_string(1 << 20))]),
+            gui: BTreeMap::from([(String::from("tab"), String::from("samples"))]),
+        };
+
+        let mut writer = ChunkedWriter::new(TrickleWriter(Vec::new()));
+        write_json(&state, &mut writer).unwrap();
+        let stream = writer.finish().unwrap().0;
+        // The state should span multiple chunks, even when compressed
+        assert!(stream.len() > STATE_CHUNK_SIZE * 2);
+
+        let restored_state =
+            deserialize_json_from_reader(ChunkedReader::new(TrickleReader(&stream))).unwrap();
+        assert_eq!(restored_state.version, state.version);
+        assert!(matches!(
+            restored_state.params.get("gain"),
+            Some(ParamValue::F32(value)) if *value == 0.5
+        ));
+        assert!(restored_state.fields == state.fields);
+        assert_eq!(restored_state.gui, state.gui);
+    }
+
+    #[test]
+    fn old_state_without_editor_state() {
+        let state: PluginState =
+            serde_json::from_str(r#"{"version":"0.1.0","params":{},"fields":{}}"#).unwrap();
+        assert!(state.gui.is_empty());
+    }
+}
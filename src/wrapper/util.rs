@@ -1,8 +1,22 @@
 use backtrace::Backtrace;
+use crossbeam::atomic::AtomicCell;
+use parking_lot::Mutex;
 use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
 use std::marker::PhantomData;
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
 
+use crate::buffer::Buffer;
+use crate::editor::Editor;
+use crate::midi::sysex::SysExMessage;
+use crate::midi::NoteEvent;
+use crate::prelude::{
+    AtomicF32, AudioIOLayout, FactoryPreset, ParamChangeSource, ParamFlags, ParamPtr, Plugin,
+    PluginState, ProcessMode, Transport,
+};
 use crate::util::permit_alloc;
 
 pub(crate) mod buffer_management;
@@ -89,6 +103,763 @@ pub fn clamp_output_event_timing(timing: u32, total_buffer_len: u32) -> u32 {
     timing.min(last_valid_index)
 }
 
+/// Sort the plugin's output events by their timing before they're sent to the host, since hosts
+/// may drop events that are not sorted. The sort is stable so events with the same timing keep
+/// the order the plugin output them in. Output events are usually already sorted or almost sorted,
+/// so this uses an insertion sort. Unlike the standard library's stable sort this also doesn't
+/// allocate.
+pub fn sort_output_events<S: SysExMessage>(events: &mut VecDeque<NoteEvent<S>>) {
+    let events = events.make_contiguous();
+    for i in 1..events.len() {
+        let mut j = i;
+        while j > 0 && events[j - 1].timing() > events[j].timing() {
+            events.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+/// Check whether the wrappers can skip calling the plugin's process function for a block. This is
+/// only the case for plugins that set
+/// [`Plugin::SILENCE_IN_SILENCE_OUT`][crate::prelude::Plugin::SILENCE_IN_SILENCE_OUT] when the
+/// block doesn't contain any input events, and when the main buffer and all auxiliary input buffers
+/// only contain silence. The main buffer is processed in place, so it contains the block's main
+/// input at this point.
+pub fn skip_silent_block(
+    silence_in_silence_out: bool,
+    has_input_events: bool,
+    main_buffer: &Buffer,
+    aux_inputs: &[Buffer],
+) -> bool {
+    silence_in_silence_out
+        && !has_input_events
+        && main_buffer.is_silent(0.0)
+        && aux_inputs.iter().all(|buffer| buffer.is_silent(0.0))
+}
+
+/// Check whether `sample_rate` is one of the sample rates in `supported_sample_rates`, which should
+/// be [`Plugin::SUPPORTED_SAMPLE_RATES`][crate::prelude::Plugin::SUPPORTED_SAMPLE_RATES]. An empty
+/// slice means that all sample rates are supported.
+pub fn is_sample_rate_supported(supported_sample_rates: &[f32], sample_rate: f32) -> bool {
+    supported_sample_rates.is_empty()
+        || supported_sample_rates
+            .iter()
+            .any(|supported| (supported - sample_rate).abs() < 0.01)
+}
+
+/// Check whether a plugin can handle the processing mode requested by the host. Plugins that set
+/// [`Plugin::HARD_REALTIME_ONLY`][crate::prelude::Plugin::HARD_REALTIME_ONLY] cannot render
+/// offline, so this logs a warning and returns `false` when such a plugin is asked to process
+/// offline anyway. The wrappers still honor the requested mode since the host decides how the
+/// plugin gets run.
+pub fn check_process_mode(hard_realtime_only: bool, process_mode: ProcessMode) -> bool {
+    if hard_realtime_only && process_mode == ProcessMode::Offline {
+        nih_warn!(
+            "The host requested offline processing, but the plugin can only process audio in \
+             realtime. The rendered output may not match realtime playback."
+        );
+        false
+    } else {
+        true
+    }
+}
+
+/// Whether a plugin should be categorized as an analyzer because all of its audio IO layouts are
+/// analyzer layouts. See [`AudioIOLayout::is_analyzer()`].
+pub fn is_analyzer_plugin(audio_io_layouts: &[AudioIOLayout]) -> bool {
+    !audio_io_layouts.is_empty() && audio_io_layouts.iter().all(AudioIOLayout::is_analyzer)
+}
+
+/// Let the editor adjust a `(width, height)` size proposed by the host. The host's size is in
+/// physical pixels, so it's converted to logical pixels using `scaling_factor` before calling
+/// [`Editor::adjust_size()`], and the result is converted back again.
+pub fn adjust_editor_size(
+    editor: &dyn Editor,
+    proposed: (u32, u32),
+    scaling_factor: f32,
+) -> (u32, u32) {
+    let to_logical = |size: u32| (size as f32 / scaling_factor).round() as u32;
+    let to_physical = |size: u32| (size as f32 * scaling_factor).round() as u32;
+
+    let (width, height) = editor.adjust_size((to_logical(proposed.0), to_logical(proposed.1)));
+    (to_physical(width), to_physical(height))
+}
+
+/// Decides which key events forwarded by the host should be claimed by the editor based on
+/// [`Editor::wants_exclusive_keyboard_focus()`]. A key release is claimed only if the matching key
+/// press was claimed, even if the editor's focus changed in between, so the host never sees a
+/// release without the press that came before it. `K` identifies a key.
+#[derive(Debug, Default)]
+pub struct KeyboardFocusClaims<K> {
+    /// The keys whose presses were claimed by the editor and that have not been released yet.
+    held_keys: Mutex<Vec<K>>,
+}
+
+impl<K: PartialEq> KeyboardFocusClaims<K> {
+    /// Called when the host forwards a key press. Returns `true` if the editor claims the key
+    /// press, and `false` if it should be passed back to the host.
+    pub fn key_down(&self, editor: &dyn Editor, key: K) -> bool {
+        if !editor.wants_exclusive_keyboard_focus() {
+            return false;
+        }
+
+        let mut held_keys = self.held_keys.lock();
+        if !held_keys.contains(&key) {
+            held_keys.push(key);
+        }
+
+        true
+    }
+
+    /// Called when the host forwards a key release. Returns `true` if the press for this key was
+    /// claimed by the editor.
+    pub fn key_up(&self, key: K) -> bool {
+        let mut held_keys = self.held_keys.lock();
+        match held_keys.iter().position(|held_key| *held_key == key) {
+            Some(idx) => {
+                held_keys.swap_remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget about all held keys. Should be called when the editor gets closed or loses focus.
+    pub fn reset(&self) {
+        self.held_keys.lock().clear();
+    }
+}
+
+/// Coalesces repeated requests made from the audio thread into a single task for the main thread.
+/// [`request()`][Self::request()] returns `true` only when no request was pending yet, in which
+/// case the caller should schedule the task. The task then calls [`take()`][Self::take()] before
+/// notifying the host.
+#[derive(Debug, Default)]
+pub struct PendingRequest {
+    pending: AtomicBool,
+}
+
+impl PendingRequest {
+    /// Mark the request as pending. Returns `true` if the request was not yet pending.
+    pub fn request(&self) -> bool {
+        !self.pending.swap(true, Ordering::SeqCst)
+    }
+
+    /// Clear the pending request. Returns `true` if a request was pending.
+    pub fn take(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Makes sure [`Plugin::first_process()`][crate::prelude::Plugin::first_process()] is called
+/// exactly once after every activation, right before the plugin processes its first block. The
+/// wrappers call [`activated()`][Self::activated()] and [`deactivated()`][Self::deactivated()]
+/// from their (de)activation functions, and [`before_process()`][Self::before_process()] before
+/// every call to `Plugin::process()`.
+#[derive(Debug, Default)]
+pub struct FirstProcess {
+    pending: PendingRequest,
+}
+
+impl FirstProcess {
+    /// The plugin has been activated, so the next block it processes is its first one.
+    pub fn activated(&self) {
+        self.pending.request();
+    }
+
+    /// The plugin has been deactivated. If it did not process any audio since it was activated,
+    /// then the hook won't be called until the plugin is activated again.
+    pub fn deactivated(&self) {
+        self.pending.take();
+    }
+
+    /// Run `first_process` if this is the first block since the plugin was activated.
+    pub fn before_process(&self, first_process: impl FnOnce()) {
+        if self.pending.take() {
+            first_process();
+        }
+    }
+}
+
+/// Keeps track of the absolute position of each processed block on the host's timeline. While the
+/// transport is playing and the host provides a sample position, that position is used as is.
+/// Otherwise the position keeps advancing from where the last block ended so blocks processed
+/// while the transport is stopped still get unique, monotonically increasing positions. This
+/// backs [`ProcessContext::block_start_samples()`][crate::prelude::ProcessContext::block_start_samples()].
+#[derive(Debug, Default)]
+pub struct BlockPositionCounter {
+    /// The expected start position of the next block, in samples.
+    next_block_start: AtomicI64,
+}
+
+impl BlockPositionCounter {
+    /// Reset the counter back to the start of the timeline. Should be called whenever the plugin
+    /// gets reset.
+    pub fn reset(&self) {
+        self.next_block_start.store(0, Ordering::Relaxed);
+    }
+
+    /// Compute the absolute start position of a block of `block_len` samples with the given
+    /// transport information, and advance the counter past that block. `transport` should already
+    /// be compensated for block splitting.
+    pub fn advance(&self, transport: &Transport, block_len: usize) -> i64 {
+        let block_start = match transport.pos_samples() {
+            Some(pos_samples) if transport.playing => pos_samples,
+            _ => self.next_block_start.load(Ordering::Relaxed),
+        };
+        self.next_block_start
+            .store(block_start + block_len as i64, Ordering::Relaxed);
+
+        block_start
+    }
+}
+
+/// The number of samples the playhead may deviate from its expected position before
+/// [`SeekDetector`] treats it as a seek. This leaves some room for rounding errors when the host
+/// only reports the position in seconds or beats.
+const SEEK_TOLERANCE_SAMPLES: i64 = 1;
+
+/// Detects when the host's playhead jumped to another position. While the transport is playing
+/// the playhead should advance by exactly one block every block, and while it is stopped it should
+/// stay where it is. Any other position change is a seek. This backs
+/// [`Transport::just_seeked()`][crate::prelude::Transport::just_seeked()].
+#[derive(Debug, Default)]
+pub struct SeekDetector {
+    /// The position the playhead should be at at the start of the next block if the host doesn't
+    /// seek, in samples. `None` before the first block and when the host doesn't report a
+    /// position.
+    expected_pos_samples: AtomicCell<Option<i64>>,
+    /// Set when a seek happened during a block the plugin didn't process, so the flag can be
+    /// passed to the next block that does get processed.
+    pending_seek: AtomicBool,
+}
+
+impl SeekDetector {
+    /// Forget the previous playhead position. The first block after this is never treated as a
+    /// seek. Should be called whenever the plugin gets reset.
+    pub fn reset(&self) {
+        self.expected_pos_samples.store(None);
+        self.pending_seek.store(false, Ordering::Relaxed);
+    }
+
+    /// Check whether a block of `block_len` samples with the given transport information starts
+    /// right after a seek. `transport` should already be compensated for block splitting. This
+    /// should be called exactly once for every (split) block the plugin processes.
+    pub fn advance(&self, transport: &Transport, block_len: usize) -> bool {
+        let seeked = self.update(transport, block_len);
+
+        self.pending_seek.swap(false, Ordering::Relaxed) || seeked
+    }
+
+    /// The same as [`advance()`][Self::advance()], but for blocks the plugin doesn't get to
+    /// process. A seek detected here is reported for the next processed block instead.
+    pub fn skip(&self, transport: &Transport, block_len: usize) {
+        if self.update(transport, block_len) {
+            self.pending_seek.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn update(&self, transport: &Transport, block_len: usize) -> bool {
+        let pos_samples = transport.pos_samples();
+        let next_pos_samples = pos_samples.map(|pos_samples| {
+            if transport.playing {
+                pos_samples + block_len as i64
+            } else {
+                pos_samples
+            }
+        });
+
+        match (
+            pos_samples,
+            self.expected_pos_samples.swap(next_pos_samples),
+        ) {
+            (Some(pos_samples), Some(expected_pos_samples)) => {
+                (pos_samples - expected_pos_samples).abs() > SEEK_TOLERANCE_SAMPLES
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Keeps track of the latency returned by [`Plugin::latency()`] so the wrappers only inform the
+/// host when it actually changes.
+#[derive(Debug, Default)]
+pub struct PluginLatency {
+    /// The value returned by `Plugin::latency()` the last time it was queried.
+    last_latency: AtomicU32,
+}
+
+impl PluginLatency {
+    /// Query the plugin's latency and call `set_latency_samples` with the new value if it differs
+    /// from the value returned the last time this function was called. Should be called after the
+    /// plugin has been initialized, after every processed block, and after restoring the plugin's
+    /// state.
+    pub fn update<P: Plugin>(&self, plugin: &P, set_latency_samples: impl FnOnce(u32)) {
+        let latency = plugin.latency();
+        if self.last_latency.swap(latency, Ordering::Relaxed) != latency {
+            set_latency_samples(latency);
+        }
+    }
+}
+
+/// Keeps the note events from process calls without any samples. Some hosts send those calls to
+/// flush events, but the plugin's process function is not called for them since a lot of DSP code
+/// assumes there's at least one sample to process. The events are instead delivered at the start
+/// of the next block that does contain samples.
+#[derive(Debug, Default)]
+pub struct CarriedInputEvents {
+    /// Set when the wrapper's input event queue contains events from a zero-length block.
+    pending: AtomicBool,
+}
+
+impl CarriedInputEvents {
+    /// Prepare the wrapper's input event queue for a new block. This clears the queue, unless it
+    /// contains events carried over from a zero-length block. Should be called in place of clearing
+    /// the queue before adding the new block's events to it.
+    pub fn begin_block<S: SysExMessage>(&self, input_events: &mut VecDeque<NoteEvent<S>>) {
+        if !self.pending.swap(false, Ordering::Relaxed) {
+            input_events.clear();
+        }
+    }
+
+    /// Keep the events in the queue for the next block, instead of processing a block without any
+    /// samples. The events are moved to the start of the next block.
+    pub fn carry_over<S: SysExMessage>(&self, input_events: &mut VecDeque<NoteEvent<S>>) {
+        for event in input_events.iter_mut() {
+            event.set_timing(0);
+        }
+
+        self.pending
+            .store(!input_events.is_empty(), Ordering::Relaxed);
+    }
+
+    /// Forget about any carried over events. Should be called whenever the plugin gets reset.
+    pub fn reset(&self) {
+        self.pending.store(false, Ordering::Relaxed);
+    }
+}
+
+/// The note input port every event in a wrapper's input event queue arrived on, for plugins with
+/// multiple [`Plugin::MIDI_INPUT_PORTS`]. The port indices are stored in a queue that runs parallel
+/// to the input events, so the event type doesn't need to carry them.
+#[derive(Debug, Default)]
+pub struct InputEventPorts {
+    /// The port index for every event in the input event queue, in the same order.
+    ports: VecDeque<u32>,
+}
+
+impl InputEventPorts {
+    /// Create the port queue with room for `capacity` events. This should match the capacity of
+    /// the input event queue.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ports: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Assign `port` to all events that have been added to the back of `input_events` since the
+    /// last call, and forget the ports of events that have been removed from it through anything
+    /// other than [`pop_front()`][Self::pop_front()]. Should be called whenever the input event
+    /// queue has been cleared, and after adding the events for every incoming host event.
+    pub fn sync<T>(&mut self, input_events: &VecDeque<T>, port: u32) {
+        self.ports.resize(input_events.len(), port);
+    }
+
+    /// The port for the event that's being popped from the front of the input event queue.
+    pub fn pop_front(&mut self) -> u32 {
+        self.ports.pop_front().unwrap_or(0)
+    }
+}
+
+/// The name for the note input port with index `port` out of `num_ports` ports. Plugins with a
+/// single note input port keep the unnumbered name.
+pub fn note_input_port_name(port: u32, num_ports: u32) -> String {
+    if num_ports <= 1 {
+        String::from("Note Input")
+    } else {
+        format!("Note Input {}", port + 1)
+    }
+}
+
+/// Keeps track of whether parameters were last changed by the host or by the plugin's editor. Used
+/// to implement
+/// [`GuiContext::raw_take_param_change_source()`][crate::prelude::GuiContext::raw_take_param_change_source()].
+/// The map is created up front, so recording changes is realtime-safe.
+#[derive(Debug)]
+pub struct ParamChangeSources<K> {
+    sources: HashMap<K, ParamChangeSourceState>,
+}
+
+#[derive(Debug, Default)]
+struct ParamChangeSourceState {
+    /// One of the `SOURCE_*` constants.
+    source: AtomicU8,
+    /// The last normalized value set by the editor. If the host sends the same value back while
+    /// the source is still set to the editor, then the change is treated as an echo of the
+    /// editor's change.
+    last_editor_value: AtomicF32,
+}
+
+const SOURCE_NONE: u8 = 0;
+const SOURCE_HOST: u8 = 1;
+const SOURCE_EDITOR: u8 = 2;
+
+impl<K: Eq + Hash> ParamChangeSources<K> {
+    /// Create the source tracking map for all of the plugin's parameters.
+    pub fn new(keys: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            sources: keys
+                .into_iter()
+                .map(|key| (key, ParamChangeSourceState::default()))
+                .collect(),
+        }
+    }
+
+    /// Record that the host changed a parameter's value.
+    pub fn host_changed(&self, key: &K, normalized_value: f32) {
+        if let Some(state) = self.sources.get(key) {
+            let is_editor_echo = state.source.load(Ordering::Relaxed) == SOURCE_EDITOR
+                && state.last_editor_value.load(Ordering::Relaxed) == normalized_value;
+            if !is_editor_echo {
+                state.source.store(SOURCE_HOST, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Record that the plugin's editor changed a parameter's value.
+    pub fn editor_changed(&self, key: &K, normalized_value: f32) {
+        if let Some(state) = self.sources.get(key) {
+            state
+                .last_editor_value
+                .store(normalized_value, Ordering::Relaxed);
+            state.source.store(SOURCE_EDITOR, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the source of the last change to the parameter since the last time this was called, and
+    /// reset it.
+    pub fn take(&self, key: &K) -> Option<ParamChangeSource> {
+        match self
+            .sources
+            .get(key)?
+            .source
+            .swap(SOURCE_NONE, Ordering::Relaxed)
+        {
+            SOURCE_HOST => Some(ParamChangeSource::Host),
+            SOURCE_EDITOR => Some(ParamChangeSource::Editor),
+            _ => None,
+        }
+    }
+}
+
+/// Implements [`Plugin::PARAM_WRITE_LOCK`][crate::prelude::Plugin::PARAM_WRITE_LOCK]. After the
+/// plugin writes a parameter value, incoming host changes for that parameter that don't match the
+/// written value are ignored until the lock expires. Like [`ParamChangeSources`], the map is
+/// created up front so recording writes is realtime-safe.
+#[derive(Debug)]
+pub struct ParamWriteLocks<K> {
+    /// How long parameters stay locked after the plugin writes them. Locking is disabled when this
+    /// is zero.
+    window: Duration,
+    /// The lock expiration times are stored relative to this instant.
+    epoch: Instant,
+    locks: HashMap<K, ParamWriteLockState>,
+}
+
+#[derive(Debug, Default)]
+struct ParamWriteLockState {
+    /// The time the lock expires at, in nanoseconds since `epoch`. Zero if the parameter has never
+    /// been written by the plugin.
+    locked_until: AtomicU64,
+    /// The last normalized value written by the plugin. The host sending this value back is still
+    /// allowed, since the VST3 wrapper relies on that to apply the editor's changes while
+    /// processing audio.
+    last_plugin_value: AtomicF32,
+}
+
+impl<K: Eq + Hash> ParamWriteLocks<K> {
+    /// Create the write locks for all of the plugin's parameters.
+    pub fn new(window: Duration, keys: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            window,
+            epoch: Instant::now(),
+            locks: keys
+                .into_iter()
+                .map(|key| (key, ParamWriteLockState::default()))
+                .collect(),
+        }
+    }
+
+    /// Record that the plugin wrote a parameter value, either from the editor or from the audio
+    /// thread. This locks the parameter for the configured window.
+    pub fn plugin_wrote(&self, key: &K, normalized_value: f32) {
+        if !self.window.is_zero() {
+            self.plugin_wrote_at(key, normalized_value, Instant::now());
+        }
+    }
+
+    /// Whether a host change for this parameter should be applied. Returns `false` if the plugin
+    /// recently wrote a different value to the parameter.
+    pub fn allows_host_change(&self, key: &K, normalized_value: f32) -> bool {
+        self.window.is_zero() || self.allows_host_change_at(key, normalized_value, Instant::now())
+    }
+
+    fn plugin_wrote_at(&self, key: &K, normalized_value: f32, now: Instant) {
+        if let Some(state) = self.locks.get(key) {
+            let locked_until = (now + self.window).duration_since(self.epoch).as_nanos() as u64;
+            state
+                .last_plugin_value
+                .store(normalized_value, Ordering::Relaxed);
+            state.locked_until.store(locked_until, Ordering::Relaxed);
+        }
+    }
+
+    fn allows_host_change_at(&self, key: &K, normalized_value: f32, now: Instant) -> bool {
+        match self.locks.get(key) {
+            Some(state) => {
+                let now = now.duration_since(self.epoch).as_nanos() as u64;
+                now >= state.locked_until.load(Ordering::Relaxed)
+                    || state.last_plugin_value.load(Ordering::Relaxed) == normalized_value
+            }
+            None => true,
+        }
+    }
+}
+
+/// Keeps track of which parameters are currently being changed from the editor, i.e. between a
+/// `begin_set_parameter()` and an `end_set_parameter()` call. Used to implement
+/// [`Plugin::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD`][crate::prelude::Plugin::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD].
+/// Like [`ParamChangeSources`], the map is created up front so gestures can be tracked without
+/// allocating.
+#[derive(Debug)]
+pub struct ParamGestures<K> {
+    active: HashMap<K, AtomicBool>,
+}
+
+impl<K: Eq + Hash> ParamGestures<K> {
+    /// Create the gesture trackers for all of the plugin's parameters.
+    pub fn new(keys: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            active: keys
+                .into_iter()
+                .map(|key| (key, AtomicBool::new(false)))
+                .collect(),
+        }
+    }
+
+    /// Record that the editor started a gesture for a parameter.
+    pub fn begin(&self, key: &K) {
+        if let Some(active) = self.active.get(key) {
+            active.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Record that the editor ended its gesture for a parameter.
+    pub fn end(&self, key: &K) {
+        if let Some(active) = self.active.get(key) {
+            active.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the editor is currently in the middle of a gesture for this parameter.
+    pub fn is_active(&self, key: &K) -> bool {
+        self.active
+            .get(key)
+            .map(|active| active.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+
+    /// Whether this parameter should keep its current value when loading a new state. This is only
+    /// the case if the plugin enabled `P::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD` and the editor
+    /// is in the middle of a gesture for the parameter.
+    pub fn preserves_on_state_load<P: Plugin>(&self, key: &K) -> bool {
+        P::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD && self.is_active(key)
+    }
+}
+
+/// The plugin's [`Plugin::factory_presets()`] and the index of the preset that was loaded last.
+/// Used to implement `GuiContext::current_preset()` and `GuiContext::set_preset()`. Loading any
+/// other state clears the current preset, see [`state_loaded()`][Self::state_loaded()].
+#[derive(Debug)]
+pub struct FactoryPresets {
+    presets: Vec<FactoryPreset>,
+    current: AtomicCell<Option<usize>>,
+}
+
+impl FactoryPresets {
+    /// Query the plugin's factory presets. No preset is selected at first.
+    pub fn new<P: Plugin>() -> Self {
+        Self {
+            presets: P::factory_presets(),
+            current: AtomicCell::new(None),
+        }
+    }
+
+    /// The index of the preset that was loaded last, if the state hasn't been replaced since.
+    pub fn current(&self) -> Option<usize> {
+        self.current.load()
+    }
+
+    /// Load the preset at `index` using `set_state`, which should be the wrapper's
+    /// `set_state_object_from_gui()` function. Returns `false` and leaves the current preset alone
+    /// if there is no preset with that index.
+    pub fn load(&self, index: usize, set_state: impl FnOnce(PluginState)) -> bool {
+        match self.presets.get(index) {
+            Some(preset) => {
+                set_state(preset.state.clone());
+                // `set_state` only returns after the state has been loaded, so this overrides the
+                // `state_loaded()` call made while loading the preset
+                self.current.store(Some(index));
+
+                true
+            }
+            None => {
+                nih_log!(
+                    "Tried to load factory preset {index}, but the plugin only has {} presets",
+                    self.presets.len()
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Called whenever a state has been loaded, since the plugin's state then no longer matches the
+    /// current preset.
+    pub fn state_loaded(&self) {
+        self.current.store(None);
+    }
+}
+
+/// The latest transport information from the audio thread, exposed to the editor through
+/// [`GuiContext::transport()`][crate::prelude::GuiContext::transport()]. The audio thread stores a
+/// copy for every block, and the GUI thread can read it at any time without blocking the audio
+/// thread.
+#[derive(Debug, Default)]
+pub struct TransportSnapshot {
+    transport: AtomicCell<Option<Transport>>,
+}
+
+impl TransportSnapshot {
+    /// Store the transport information for the block that's about to be processed.
+    pub fn store(&self, transport: &Transport) {
+        self.transport.store(Some(*transport));
+    }
+
+    /// Get the most recently stored transport information, if the plugin has processed any audio.
+    pub fn load(&self) -> Option<Transport> {
+        self.transport.load()
+    }
+}
+
+/// Snap all of the parameters' smoothers to their current values. Called right before
+/// [`Plugin::reset()`][crate::prelude::Plugin::reset()] if
+/// [`Plugin::RESET_SMOOTHERS_ON_RESET`][crate::prelude::Plugin::RESET_SMOOTHERS_ON_RESET] is
+/// enabled.
+///
+/// # Safety
+///
+/// The parameter pointers need to point to valid parameters.
+pub unsafe fn reset_param_smoothers<'a>(
+    params: impl IntoIterator<Item = &'a ParamPtr>,
+    sample_rate: f32,
+) {
+    for param in params {
+        param.update_smoother(sample_rate, true);
+    }
+}
+
+/// Find the parameter link groups that only contain a single parameter, sorted by name. Linking a
+/// parameter to nothing is most likely a typo in the link group's name, so the wrappers emit a
+/// debug assertion failure for these. See
+/// [`Param::link_group()`][crate::prelude::Param::link_group()].
+///
+/// # Safety
+///
+/// The parameter pointers need to point to valid parameters.
+pub unsafe fn lone_param_link_groups<'a>(
+    params: impl IntoIterator<Item = &'a ParamPtr>,
+) -> Vec<String> {
+    let mut link_group_sizes: HashMap<&str, usize> = HashMap::new();
+    for param in params {
+        if let Some(link_group) = param.link_group() {
+            *link_group_sizes.entry(link_group).or_default() += 1;
+        }
+    }
+
+    let mut lone_link_groups: Vec<String> = link_group_sizes
+        .into_iter()
+        .filter(|(_, size)| *size == 1)
+        .map(|(link_group, _)| link_group.to_owned())
+        .collect();
+    lone_link_groups.sort();
+
+    lone_link_groups
+}
+
+/// Keeps track of the plugin's bypass parameter so parameters with a neutral bypass value (see
+/// [`FloatParam::with_neutral_on_bypass()`][crate::prelude::FloatParam::with_neutral_on_bypass()])
+/// can smooth towards that value while the plugin is bypassed. The wrappers call
+/// [`update()`][Self::update()] before processing every block.
+#[derive(Debug)]
+pub struct BypassState {
+    /// The plugin's bypass parameter, if it has one. If there are multiple bypass parameters, then
+    /// this is the first one, since that is also the one the host will use.
+    bypass_param: Option<ParamPtr>,
+    /// All of the plugin's parameters that need to be informed about changes to the bypass state.
+    params: Vec<ParamPtr>,
+    /// Whether the plugin was bypassed during the last [`update()`][Self::update()].
+    bypassed: AtomicBool,
+}
+
+impl BypassState {
+    /// Find the bypass parameter among the plugin's parameters.
+    ///
+    /// # Safety
+    ///
+    /// The parameter pointers need to point to valid parameters, and they need to stay valid for
+    /// as long as this object is used.
+    pub unsafe fn new(params: impl IntoIterator<Item = ParamPtr>) -> Self {
+        let params: Vec<ParamPtr> = params.into_iter().collect();
+        let bypass_param = params
+            .iter()
+            .find(|param| param.flags().contains(ParamFlags::BYPASS))
+            .copied();
+
+        Self {
+            bypass_param,
+            params,
+            bypassed: AtomicBool::new(false),
+        }
+    }
+
+    /// Check whether the bypass parameter's value has changed since the last call, and update the
+    /// parameters' smoothers if it has. Returns `true` if the bypass state has changed.
+    ///
+    /// # Safety
+    ///
+    /// The parameter pointers passed to [`new()`][Self::new()] need to still be valid.
+    pub unsafe fn update(&self, sample_rate: f32) -> bool {
+        let bypass_param = match self.bypass_param {
+            Some(bypass_param) => bypass_param,
+            None => return false,
+        };
+
+        let bypassed = bypass_param.modulated_normalized_value() >= 0.5;
+        if self.bypassed.swap(bypassed, Ordering::Relaxed) == bypassed {
+            return false;
+        }
+
+        for param in &self.params {
+            if param.set_bypassed(bypassed) {
+                param.update_smoother(sample_rate, false);
+            }
+        }
+
+        true
+    }
+}
+
 /// Set up the logger so that the `nih_*!()` logging and assertion macros log output to a
 /// centralized location and panics also get written there. By default this logs to STDERR. If a
 /// Windows debugger is attached, then messages will be sent there instead. This uses
@@ -259,9 +1030,16 @@ impl Drop for ScopedFtz {
 
 #[cfg(test)]
 mod miri {
+    use std::any::Any;
     use std::ffi::CStr;
+    use std::sync::Arc;
 
     use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::{
+        BoolParam, FloatParam, FloatRange, GuiContext, IntParam, IntRange, Param, Params,
+        ParentWindowHandle, SmoothingStyle,
+    };
 
     #[test]
     fn strlcpy_normal() {
@@ -284,4 +1062,758 @@ mod miri {
             Ok("Hello")
         );
     }
+
+    #[test]
+    fn output_events_sorted() {
+        let note_on = |timing, note| NoteEvent::<()>::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 1.0,
+        };
+
+        // This is what an arpeggiator emitting a chord followed by an earlier note would produce
+        let mut events: VecDeque<_> = [
+            note_on(10, 60),
+            note_on(10, 64),
+            note_on(3, 48),
+            note_on(10, 67),
+            note_on(0, 36),
+        ]
+        .into_iter()
+        .collect();
+        sort_output_events(&mut events);
+
+        // Events with the same timing should stay in the same order
+        assert_eq!(
+            events.into_iter().collect::<Vec<_>>(),
+            [
+                note_on(0, 36),
+                note_on(3, 48),
+                note_on(10, 60),
+                note_on(10, 64),
+                note_on(10, 67),
+            ]
+        );
+    }
+
+    #[test]
+    fn silent_block_detection() {
+        let mut main_data = vec![vec![0.0; 16]; 2];
+        let mut aux_data = vec![vec![0.0; 16]; 1];
+        let mut main_buffer = Buffer::default();
+        let mut aux_buffer = Buffer::default();
+        unsafe {
+            main_buffer.set_slices(16, |slices| {
+                *slices = main_data.iter_mut().map(|c| c.as_mut_slice()).collect()
+            });
+            aux_buffer.set_slices(16, |slices| {
+                *slices = aux_data.iter_mut().map(|c| c.as_mut_slice()).collect()
+            });
+        }
+
+        // A pure gain plugin fed with silence does not need to be processed
+        let mut aux_inputs = [aux_buffer];
+        assert!(skip_silent_block(true, false, &main_buffer, &aux_inputs));
+
+        // Unless the plugin didn't opt in, or there are note events that could produce sound
+        assert!(!skip_silent_block(false, false, &main_buffer, &aux_inputs));
+        assert!(!skip_silent_block(true, true, &main_buffer, &aux_inputs));
+
+        main_buffer.as_slice()[1][15] = 1e-9;
+        assert!(!skip_silent_block(true, false, &main_buffer, &aux_inputs));
+
+        main_buffer.as_slice()[1][15] = 0.0;
+        aux_inputs[0].as_slice()[0][0] = -0.5;
+        assert!(!skip_silent_block(true, false, &main_buffer, &aux_inputs));
+    }
+
+    #[test]
+    fn sample_rates_all_supported() {
+        assert!(is_sample_rate_supported(&[], 44_100.0));
+        assert!(is_sample_rate_supported(&[], 12_345.0));
+    }
+
+    #[test]
+    fn sample_rates_declined() {
+        let supported = [44_100.0, 48_000.0];
+        assert!(is_sample_rate_supported(&supported, 48_000.0));
+        assert!(is_sample_rate_supported(&supported, 44_100.001));
+        assert!(!is_sample_rate_supported(&supported, 96_000.0));
+        assert!(!is_sample_rate_supported(&supported, 44_000.0));
+    }
+
+    #[test]
+    fn offline_processing_on_realtime_only_plugin() {
+        assert!(check_process_mode(false, ProcessMode::Offline));
+        assert!(check_process_mode(true, ProcessMode::Realtime));
+        assert!(check_process_mode(true, ProcessMode::Buffered));
+        assert!(!check_process_mode(true, ProcessMode::Offline));
+    }
+
+    #[test]
+    fn pending_request_coalesced() {
+        let request = PendingRequest::default();
+        assert!(!request.take());
+
+        // Only the first request should cause a task to be queued
+        assert!(request.request());
+        assert!(!request.request());
+        assert!(request.take());
+        assert!(!request.take());
+
+        assert!(request.request());
+    }
+
+    #[test]
+    fn first_process_fires_once() {
+        let first_process = FirstProcess::default();
+        let process_blocks = |num_blocks: usize| {
+            let mut calls = 0;
+            for _ in 0..num_blocks {
+                first_process.before_process(|| calls += 1);
+            }
+
+            calls
+        };
+
+        // Processing audio without activating the plugin first
+        assert_eq!(process_blocks(4), 0);
+
+        // Activating the plugin
+        first_process.activated();
+        assert_eq!(process_blocks(4), 1);
+
+        // Deactivating the plugin before it processed any audio should not leave the flag set
+        first_process.activated();
+        first_process.deactivated();
+        assert_eq!(process_blocks(4), 0);
+
+        // Reactivating the plugin
+        first_process.activated();
+        assert_eq!(process_blocks(4), 1);
+    }
+
+    #[test]
+    fn block_position_stopped_transport() {
+        let counter = BlockPositionCounter::default();
+        let mut transport = Transport::new(44_100.0);
+        transport.pos_samples = Some(1000);
+
+        assert_eq!(counter.advance(&transport, 512), 0);
+        assert_eq!(counter.advance(&transport, 512), 512);
+        assert_eq!(counter.advance(&transport, 128), 1024);
+
+        counter.reset();
+        assert_eq!(counter.advance(&transport, 512), 0);
+    }
+
+    #[test]
+    fn block_position_playing_transport() {
+        let counter = BlockPositionCounter::default();
+        let mut transport = Transport::new(44_100.0);
+        transport.playing = true;
+
+        transport.pos_samples = Some(1000);
+        assert_eq!(counter.advance(&transport, 512), 1000);
+        transport.pos_samples = Some(1512);
+        assert_eq!(counter.advance(&transport, 512), 1512);
+
+        // When the transport stops the position keeps advancing from the last block
+        transport.playing = false;
+        assert_eq!(counter.advance(&transport, 512), 2024);
+        assert_eq!(counter.advance(&transport, 512), 2536);
+    }
+
+    #[test]
+    fn seek_while_playing() {
+        let detector = SeekDetector::default();
+        let mut transport = Transport::new(44_100.0);
+        transport.playing = true;
+
+        // The first block is not a seek, even though the position is unexpected
+        transport.pos_samples = Some(1000);
+        assert!(!detector.advance(&transport, 512));
+        transport.pos_samples = Some(1512);
+        assert!(!detector.advance(&transport, 512));
+
+        // The host jumps back to the start, which should only be reported for the first block
+        transport.pos_samples = Some(0);
+        assert!(detector.advance(&transport, 512));
+        transport.pos_samples = Some(512);
+        assert!(!detector.advance(&transport, 512));
+        transport.pos_samples = Some(1024);
+        assert!(!detector.advance(&transport, 512));
+
+        // A seek during a block the plugin didn't process is reported for the next processed block
+        transport.pos_samples = Some(44_100);
+        detector.skip(&transport, 512);
+        transport.pos_samples = Some(44_612);
+        assert!(detector.advance(&transport, 512));
+        transport.pos_samples = Some(45_124);
+        assert!(!detector.advance(&transport, 512));
+    }
+
+    #[test]
+    fn seek_while_stopped() {
+        let detector = SeekDetector::default();
+        let mut transport = Transport::new(44_100.0);
+
+        // The playhead doesn't move while the transport is stopped
+        transport.pos_samples = Some(1000);
+        assert!(!detector.advance(&transport, 512));
+        assert!(!detector.advance(&transport, 512));
+
+        transport.pos_samples = Some(8000);
+        assert!(detector.advance(&transport, 512));
+        assert!(!detector.advance(&transport, 512));
+
+        // Starting playback from the stopped position is not a seek
+        transport.playing = true;
+        assert!(!detector.advance(&transport, 512));
+        transport.pos_samples = Some(8512);
+        assert!(!detector.advance(&transport, 512));
+
+        // Without any position information there's nothing to compare against
+        transport.pos_samples = None;
+        assert!(!detector.advance(&transport, 512));
+        transport.pos_samples = Some(0);
+        assert!(!detector.advance(&transport, 512));
+
+        detector.reset();
+        transport.pos_samples = Some(20_000);
+        assert!(!detector.advance(&transport, 512));
+    }
+
+    /// A plugin with a lookahead parameter that determines its latency.
+    struct Lookahead;
+
+    type LookaheadPlugin = TestPlugin<Lookahead>;
+
+    struct LookaheadParams {
+        lookahead_ms: FloatParam,
+    }
+
+    impl Default for LookaheadParams {
+        fn default() -> Self {
+            Self {
+                lookahead_ms: FloatParam::new(
+                    "Lookahead",
+                    0.0,
+                    FloatRange::Linear {
+                        min: 0.0,
+                        max: 10.0,
+                    },
+                ),
+            }
+        }
+    }
+
+    unsafe impl Params for LookaheadParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![(
+                String::from("lookahead"),
+                self.lookahead_ms.as_ptr(),
+                String::new(),
+            )]
+        }
+    }
+
+    impl TestPluginConfig for Lookahead {
+        const NAME: &'static str = "Lookahead";
+
+        type Params = LookaheadParams;
+
+        fn latency(params: &Self::Params) -> u32 {
+            // The lookahead in milliseconds at 48 kHz
+            (params.lookahead_ms.value() * 48.0).round() as u32
+        }
+    }
+
+    #[test]
+    fn latency_follows_params() {
+        let plugin = LookaheadPlugin::default();
+        let latency = PluginLatency::default();
+        let mut reported_latencies = Vec::new();
+
+        // The initial latency of zero is not reported since nothing changed
+        latency.update(&plugin, |samples| reported_latencies.push(samples));
+        assert!(reported_latencies.is_empty());
+
+        let lookahead_ptr = plugin.params.lookahead_ms.as_ptr();
+        unsafe { lookahead_ptr.set_normalized_value(0.5) };
+        latency.update(&plugin, |samples| reported_latencies.push(samples));
+        assert_eq!(reported_latencies, [240]);
+
+        // Querying the latency again without changing the parameter doesn't report anything
+        latency.update(&plugin, |samples| reported_latencies.push(samples));
+        assert_eq!(reported_latencies, [240]);
+
+        unsafe { lookahead_ptr.set_normalized_value(0.0) };
+        latency.update(&plugin, |samples| reported_latencies.push(samples));
+        assert_eq!(reported_latencies, [240, 0]);
+    }
+
+    #[test]
+    fn zero_frame_events_carried_over() {
+        let note_on = |timing, note| NoteEvent::<()>::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 1.0,
+        };
+        let carried_events = CarriedInputEvents::default();
+        let mut input_events = VecDeque::new();
+
+        // A zero-frame call without events doesn't keep anything around
+        carried_events.begin_block(&mut input_events);
+        carried_events.carry_over(&mut input_events);
+        input_events.push_back(note_on(0, 36));
+        carried_events.begin_block(&mut input_events);
+        assert!(input_events.is_empty());
+
+        // The host flushes a note with a zero-frame process call, and the next block adds another
+        // note to the queue
+        input_events.push_back(note_on(0, 60));
+        carried_events.carry_over(&mut input_events);
+        carried_events.begin_block(&mut input_events);
+        input_events.push_back(note_on(10, 64));
+        assert_eq!(
+            input_events.iter().cloned().collect::<Vec<_>>(),
+            [note_on(0, 60), note_on(10, 64)]
+        );
+
+        // The events are only delivered once
+        carried_events.begin_block(&mut input_events);
+        assert!(input_events.is_empty());
+
+        // Resetting the plugin drops the carried over events
+        input_events.push_back(note_on(0, 67));
+        carried_events.carry_over(&mut input_events);
+        carried_events.reset();
+        carried_events.begin_block(&mut input_events);
+        assert!(input_events.is_empty());
+    }
+
+    #[test]
+    fn input_event_ports_preserved() {
+        let note_on = |timing, note| NoteEvent::<()>::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 1.0,
+        };
+        let mut input_events = VecDeque::new();
+        let mut ports = InputEventPorts::with_capacity(8);
+
+        // Events arriving on two ports, where a single host event may produce multiple events
+        input_events.push_back(note_on(0, 60));
+        ports.sync(&input_events, 0);
+        input_events.push_back(note_on(0, 36));
+        input_events.push_back(note_on(2, 38));
+        ports.sync(&input_events, 1);
+        input_events.push_back(note_on(5, 64));
+        ports.sync(&input_events, 0);
+
+        let mut delivered = Vec::new();
+        while let Some(event) = input_events.pop_front() {
+            delivered.push((event.timing(), ports.pop_front()));
+        }
+        assert_eq!(delivered, [(0, 0), (0, 1), (2, 1), (5, 0)]);
+
+        // Clearing the queue also clears the ports
+        input_events.push_back(note_on(0, 60));
+        ports.sync(&input_events, 1);
+        input_events.clear();
+        ports.sync(&input_events, 0);
+        input_events.push_back(note_on(0, 62));
+        ports.sync(&input_events, 0);
+        input_events.pop_front();
+        assert_eq!(ports.pop_front(), 0);
+    }
+
+    #[test]
+    fn param_change_sources() {
+        let sources = ParamChangeSources::new([1u32, 2u32]);
+        assert_eq!(sources.take(&1), None);
+
+        sources.host_changed(&1, 0.25);
+        sources.editor_changed(&2, 0.5);
+        assert_eq!(sources.take(&1), Some(ParamChangeSource::Host));
+        assert_eq!(sources.take(&2), Some(ParamChangeSource::Editor));
+
+        // Taking the source resets it
+        assert_eq!(sources.take(&1), None);
+        assert_eq!(sources.take(&2), None);
+
+        // Unknown parameters are ignored
+        sources.host_changed(&3, 0.0);
+        assert_eq!(sources.take(&3), None);
+    }
+
+    #[test]
+    fn param_change_sources_editor_echo() {
+        let sources = ParamChangeSources::new([1u32]);
+
+        // The host echoing the editor's change back should not count as a host change
+        sources.editor_changed(&1, 0.5);
+        sources.host_changed(&1, 0.5);
+        assert_eq!(sources.take(&1), Some(ParamChangeSource::Editor));
+
+        sources.editor_changed(&1, 0.5);
+        sources.host_changed(&1, 0.75);
+        assert_eq!(sources.take(&1), Some(ParamChangeSource::Host));
+    }
+
+    #[test]
+    fn param_write_lock_expires() {
+        let locks = ParamWriteLocks::new(Duration::from_millis(100), [1u32, 2u32]);
+
+        // The plugin writes two values in quick succession, and the host then sends the first one
+        // back to the plugin
+        let start = Instant::now();
+        locks.plugin_wrote_at(&1, 0.25, start);
+        locks.plugin_wrote_at(&1, 0.5, start);
+        assert!(!locks.allows_host_change_at(&1, 0.25, start + Duration::from_millis(10)));
+        assert!(locks.allows_host_change_at(&1, 0.5, start + Duration::from_millis(20)));
+
+        // Other parameters are not affected
+        assert!(locks.allows_host_change_at(&2, 0.25, start + Duration::from_millis(10)));
+
+        // Once the lock has expired the host can change the parameter again
+        assert!(locks.allows_host_change_at(&1, 0.25, start + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn param_write_lock_disabled() {
+        let locks = ParamWriteLocks::new(Duration::ZERO, [1u32]);
+
+        locks.plugin_wrote(&1, 0.5);
+        assert!(locks.allows_host_change(&1, 0.25));
+    }
+
+    #[test]
+    fn gestured_params_preserved_on_state_load() {
+        struct Preserving;
+
+        impl TestPluginConfig for Preserving {
+            const NAME: &'static str = "Preserving";
+            const PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD: bool = true;
+
+            type Params = NoParams;
+        }
+
+        struct Discarding;
+
+        impl TestPluginConfig for Discarding {
+            const NAME: &'static str = "Discarding";
+
+            type Params = NoParams;
+        }
+
+        let gestures = ParamGestures::new([1u32, 2u32]);
+        gestures.begin(&1);
+        assert!(gestures.preserves_on_state_load::<TestPlugin<Preserving>>(&1));
+        assert!(!gestures.preserves_on_state_load::<TestPlugin<Preserving>>(&2));
+        assert!(!gestures.preserves_on_state_load::<TestPlugin<Discarding>>(&1));
+
+        gestures.end(&1);
+        assert!(!gestures.preserves_on_state_load::<TestPlugin<Preserving>>(&1));
+    }
+
+    #[test]
+    fn linked_params_report_link_group() {
+        struct StereoParams {
+            left_gain: FloatParam,
+            right_gain: FloatParam,
+            mix: FloatParam,
+            mute: BoolParam,
+        }
+
+        unsafe impl Params for StereoParams {
+            fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+                vec![
+                    (
+                        String::from("lgain"),
+                        self.left_gain.as_ptr(),
+                        String::new(),
+                    ),
+                    (
+                        String::from("rgain"),
+                        self.right_gain.as_ptr(),
+                        String::new(),
+                    ),
+                    (String::from("mix"), self.mix.as_ptr(), String::new()),
+                    (String::from("mute"), self.mute.as_ptr(), String::new()),
+                ]
+            }
+        }
+
+        let range = FloatRange::Linear { min: 0.0, max: 1.0 };
+        let params = StereoParams {
+            left_gain: FloatParam::new("Left Gain", 1.0, range).with_link_group("gain"),
+            right_gain: FloatParam::new("Right Gain", 1.0, range).with_link_group("gain"),
+            mix: FloatParam::new("Mix", 1.0, range),
+            mute: BoolParam::new("Mute", false).with_link_group("mutes"),
+        };
+
+        assert_eq!(params.left_gain.link_group(), Some("gain"));
+        assert_eq!(params.mix.link_group(), None);
+        assert_eq!(
+            params.link_groups(),
+            [
+                (String::from("lgain"), String::from("gain")),
+                (String::from("rgain"), String::from("gain")),
+                (String::from("mute"), String::from("mutes")),
+            ]
+        );
+
+        // The mute parameter is not linked to anything, so that's probably a mistake
+        let param_map = params.param_map();
+        let lone_link_groups =
+            unsafe { lone_param_link_groups(param_map.iter().map(|(_, ptr, _)| ptr)) };
+        assert_eq!(lone_link_groups, ["mutes"]);
+    }
+
+    #[test]
+    fn params_report_modulation_destinations() {
+        struct SynthParams {
+            cutoff: FloatParam,
+            resonance: FloatParam,
+            coarse: IntParam,
+            gain: FloatParam,
+        }
+
+        unsafe impl Params for SynthParams {
+            fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+                vec![
+                    (String::from("cutoff"), self.cutoff.as_ptr(), String::new()),
+                    (String::from("res"), self.resonance.as_ptr(), String::new()),
+                    (String::from("coarse"), self.coarse.as_ptr(), String::new()),
+                    (String::from("gain"), self.gain.as_ptr(), String::new()),
+                ]
+            }
+        }
+
+        let params = SynthParams {
+            cutoff: FloatParam::new(
+                "Cutoff",
+                1000.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 2000.0,
+                },
+            )
+            .with_modulation_range(500.0, 1500.0),
+            resonance: FloatParam::new("Resonance", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_modulation_range(0.0, 1.0),
+            coarse: IntParam::new("Coarse", 0, IntRange::Linear { min: -24, max: 24 })
+                .with_modulation_range(-12, 0),
+            gain: FloatParam::new("Gain", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+        };
+
+        // The ranges are reported as normalized values, and the gain is not a destination
+        assert_eq!(params.cutoff.modulation_range(), Some((0.25, 0.75)));
+        assert_eq!(params.gain.modulation_range(), None);
+        assert_eq!(
+            params.modulation_destinations(),
+            [
+                (String::from("cutoff"), (0.25, 0.75)),
+                (String::from("res"), (0.0, 1.0)),
+                (String::from("coarse"), (0.25, 0.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn smoothers_snap_after_reset() {
+        let sample_rate = 44_100.0;
+        let param = FloatParam::new("Param", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+            .with_smoother(SmoothingStyle::Linear(100.0));
+        let param_ptr = param.as_ptr();
+
+        // The parameter changed right before the host deactivated the plugin
+        unsafe {
+            param_ptr.set_normalized_value(1.0);
+            param_ptr.update_smoother(sample_rate, false);
+        }
+        param.smoothed.next();
+        assert!(param.smoothed.is_smoothing());
+
+        unsafe { reset_param_smoothers([&param_ptr], sample_rate) };
+        assert!(!param.smoothed.is_smoothing());
+        assert_eq!(param.smoothed.next(), 1.0);
+    }
+
+    #[test]
+    fn param_ramps_to_neutral_on_bypass() {
+        let sample_rate = 1000.0;
+        let bypass = BoolParam::new("Bypass", false).make_bypass();
+        let gain = FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+            .with_smoother(SmoothingStyle::Linear(10.0))
+            .with_neutral_on_bypass(1.0);
+        let bypass_ptr = bypass.as_ptr();
+        let gain_ptr = gain.as_ptr();
+        let bypass_state = unsafe { BypassState::new([bypass_ptr, gain_ptr]) };
+
+        unsafe { reset_param_smoothers([&gain_ptr], sample_rate) };
+        assert!(!unsafe { bypass_state.update(sample_rate) });
+        assert_eq!(gain.smoothed.next(), 0.5);
+
+        // The smoother ramps to the neutral value over ten samples, without changing the parameter
+        unsafe { bypass_ptr.set_normalized_value(1.0) };
+        assert!(unsafe { bypass_state.update(sample_rate) });
+        let first = gain.smoothed.next();
+        assert!(first > 0.5 && first < 1.0);
+        for _ in 0..9 {
+            gain.smoothed.next();
+        }
+        assert_eq!(gain.smoothed.next(), 1.0);
+        assert_eq!(gain.value(), 0.5);
+
+        // Automation while bypassed does not move the smoother away from the neutral value
+        unsafe {
+            gain_ptr.set_normalized_value(0.25);
+            gain_ptr.update_smoother(sample_rate, false);
+        }
+        assert!(!unsafe { bypass_state.update(sample_rate) });
+        assert_eq!(gain.smoothed.next(), 1.0);
+
+        // And after un-bypassing it ramps back to the parameter's current value
+        unsafe { bypass_ptr.set_normalized_value(0.0) };
+        assert!(unsafe { bypass_state.update(sample_rate) });
+        let first = gain.smoothed.next();
+        assert!(first > 0.25 && first < 1.0);
+        for _ in 0..9 {
+            gain.smoothed.next();
+        }
+        assert_eq!(gain.smoothed.next(), 0.25);
+    }
+
+    #[test]
+    fn transport_snapshot() {
+        let snapshot = TransportSnapshot::default();
+        assert!(snapshot.load().is_none());
+
+        let mut transport = Transport::new(44_100.0);
+        transport.playing = true;
+        transport.tempo = Some(120.0);
+        transport.pos_beats = Some(4.0);
+        snapshot.store(&transport);
+
+        // The GUI sees the latest block's transport information
+        transport.tempo = Some(140.0);
+        transport.pos_beats = Some(8.0);
+        snapshot.store(&transport);
+
+        let gui_transport = snapshot
+            .load()
+            .expect("The transport should have been stored");
+        assert!(gui_transport.playing);
+        assert_eq!(gui_transport.tempo, Some(140.0));
+        assert_eq!(gui_transport.pos_beats(), Some(8.0));
+    }
+
+    /// An editor that snaps its size to a 50 pixel grid.
+    struct GridEditor;
+
+    impl Editor for GridEditor {
+        fn spawn(
+            &self,
+            _parent: ParentWindowHandle,
+            _context: Arc<dyn GuiContext>,
+        ) -> Box<dyn Any + Send> {
+            Box::new(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            (400, 300)
+        }
+
+        fn set_scale_factor(&self, _factor: f32) -> bool {
+            false
+        }
+
+        fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+
+        fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+
+        fn param_values_changed(&self) {}
+
+        fn adjust_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+            let snap = |size: u32| ((size + 25) / 50).max(1) * 50;
+            (snap(width), snap(height))
+        }
+    }
+
+    #[test]
+    fn editor_size_snaps_to_grid() {
+        assert_eq!(adjust_editor_size(&GridEditor, (230, 149), 1.0), (250, 150));
+        assert_eq!(adjust_editor_size(&GridEditor, (400, 300), 1.0), (400, 300));
+
+        // The host's sizes are in physical pixels while the editor works in logical pixels
+        assert_eq!(adjust_editor_size(&GridEditor, (460, 298), 2.0), (500, 300));
+    }
+
+    /// An editor with a text field that wants exclusive keyboard focus while it's being edited.
+    #[derive(Default)]
+    struct TextFieldEditor {
+        editing: AtomicBool,
+    }
+
+    impl Editor for TextFieldEditor {
+        fn spawn(
+            &self,
+            _parent: ParentWindowHandle,
+            _context: Arc<dyn GuiContext>,
+        ) -> Box<dyn Any + Send> {
+            Box::new(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            (400, 300)
+        }
+
+        fn set_scale_factor(&self, _factor: f32) -> bool {
+            false
+        }
+
+        fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+
+        fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+
+        fn param_values_changed(&self) {}
+
+        fn wants_exclusive_keyboard_focus(&self) -> bool {
+            self.editing.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn keyboard_focus_claims_follow_editor() {
+        let editor = TextFieldEditor::default();
+        let claims = KeyboardFocusClaims::default();
+
+        // Without an active text field the host keeps its keyboard shortcuts
+        assert!(!claims.key_down(&editor, 'a'));
+        assert!(!claims.key_up('a'));
+
+        editor.editing.store(true, Ordering::Relaxed);
+        assert!(claims.key_down(&editor, 'b'));
+        assert!(claims.key_down(&editor, 'b'));
+        assert!(claims.key_down(&editor, 'c'));
+
+        // Releases match up with their presses even after the text field loses focus
+        editor.editing.store(false, Ordering::Relaxed);
+        assert!(!claims.key_down(&editor, 'd'));
+        assert!(claims.key_up('b'));
+        assert!(!claims.key_up('b'));
+        assert!(!claims.key_up('d'));
+
+        claims.reset();
+        assert!(!claims.key_up('c'));
+    }
 }
@@ -34,6 +34,10 @@ pub struct BufferManager {
     /// will be shortened when returning a reference to these buffers in `create_buffers` to match
     /// the function's lifetime.
     main_buffer: Buffer<'static>,
+    /// Stores the data to back `main_buffer` for layouts with a main input but without a main
+    /// output, like the ones used by analyzers. There's no output buffer to copy the main input to
+    /// in that case, so it's copied to our own buffers instead. Empty for all other layouts.
+    main_input_storage: Vec<Vec<f32>>,
 
     aux_input_buffers: Vec<Buffer<'static>>,
     /// Stores the data to back `aux_input_buffers`. We need to copy the host's auxiliary input
@@ -73,29 +77,38 @@ impl BufferManager {
     /// Initialize managed buffers for a specific audio IO layout. The actual buffers can be set up
     /// using channel pointer data using [`create_buffers()`][Self::create_buffers()].
     pub fn for_audio_io_layout(max_buffer_size: usize, audio_io_layout: AudioIOLayout) -> Self {
+        let num_main_input_channels = audio_io_layout
+            .main_input_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
         nih_debug_assert!(
-            audio_io_layout
-                .main_input_channels
-                .map(NonZeroU32::get)
-                .unwrap_or(0)
-                <= audio_io_layout
-                    .main_output_channels
-                    .map(NonZeroU32::get)
-                    .unwrap_or(0),
+            audio_io_layout.main_output_channels.is_none()
+                || num_main_input_channels
+                    <= audio_io_layout
+                        .main_output_channels
+                        .map(NonZeroU32::get)
+                        .unwrap_or(0) as usize,
             "Stereo-to-mono and other many-to-few audio channel configurations are currently not \
              supported"
         );
 
+        // Layouts without a main output pass the main input to the plugin through our own buffers
+        let main_input_storage = if audio_io_layout.main_output_channels.is_none() {
+            vec![vec![0.0; max_buffer_size]; num_main_input_channels]
+        } else {
+            Vec::new()
+        };
+
         // The buffers are preallocated so that `create_buffers()` can be called without having to
         // allocate
         let mut main_buffer = Buffer::default();
         unsafe {
             main_buffer.set_slices(0, |output_slices| {
                 output_slices.resize_with(
-                    audio_io_layout
-                        .main_output_channels
-                        .map(NonZeroU32::get)
-                        .unwrap_or(0) as usize,
+                    match audio_io_layout.main_output_channels {
+                        Some(num_channels) => num_channels.get() as usize,
+                        None => main_input_storage.len(),
+                    },
                     || &mut [],
                 );
             })
@@ -137,6 +150,7 @@ impl BufferManager {
             aux_output_channel_pointers: vec![None; audio_io_layout.aux_output_ports.len()],
 
             main_buffer,
+            main_input_storage,
 
             aux_input_buffers,
             aux_input_storage,
@@ -206,7 +220,7 @@ impl BufferManager {
                     output_slices[output_channel_pointers.num_channels..].fill_with(|| &mut [])
                 }
                 None => {
-                    nih_debug_assert_eq!(output_slices.len(), 0);
+                    nih_debug_assert_eq!(output_slices.len(), self.main_input_storage.len());
 
                     // Same as above
                     output_slices.fill_with(|| &mut [])
@@ -214,6 +228,43 @@ impl BufferManager {
             }
         });
 
+        // Layouts without a main output, like the ones used by analyzers, get the main input
+        // through our own buffers instead. Missing input channels are filled with zeroes.
+        if !self.main_input_storage.is_empty() {
+            let num_input_channels = self
+                .main_input_channel_pointers
+                .map(|input_channel_pointers| input_channel_pointers.num_channels)
+                .unwrap_or(0);
+            for (channel_idx, channel) in self.main_input_storage.iter_mut().enumerate() {
+                nih_debug_assert!(num_samples <= channel.capacity());
+                channel.resize(num_samples, 0.0);
+
+                match self.main_input_channel_pointers {
+                    Some(input_channel_pointers) if channel_idx < num_input_channels => {
+                        let input_channel_pointer =
+                            input_channel_pointers.ptrs.as_ptr().add(channel_idx);
+                        assert!(!input_channel_pointer.is_null());
+
+                        channel.copy_from_slice(std::slice::from_raw_parts_mut(
+                            (*input_channel_pointer).add(sample_offset),
+                            num_samples,
+                        ))
+                    }
+                    _ => channel.fill(0.0),
+                }
+            }
+
+            let main_input_storage = &mut self.main_input_storage;
+            self.main_buffer.set_slices(num_samples, |output_slices| {
+                for (channel_slice, channel_storage) in
+                    output_slices.iter_mut().zip(main_input_storage.iter_mut())
+                {
+                    // SAFETY: `channel_storage` is no longer used accessed directly after this
+                    *channel_slice = &mut *(channel_storage.as_mut_slice() as *mut [f32]);
+                }
+            });
+        }
+
         // Since NIH-plug processes audio in-place, main input data needs to be copied to the main
         // output buffers
         if let (Some(input_channel_pointers), Some(output_channel_pointers)) = (
@@ -221,7 +272,10 @@ impl BufferManager {
             self.main_output_channel_pointers,
         ) {
             // If the host processes the main IO out of place then the inputs need to be copied to
-            // the output buffers. Otherwise the input should already be there.
+            // the output buffers. Otherwise the input should already be there. Hosts that process
+            // in place may still use separate channel pointer arrays for the inputs and the
+            // outputs, as is always the case in CLAP, so this needs to be checked per channel.
+            // Copying a channel onto itself would create two aliasing mutable slices.
             if input_channel_pointers.ptrs != output_channel_pointers.ptrs {
                 self.main_buffer.set_slices(num_samples, |output_slices| {
                     for (channel_idx, output_slice) in output_slices
@@ -233,10 +287,11 @@ impl BufferManager {
                             input_channel_pointers.ptrs.as_ptr().add(channel_idx);
                         assert!(!input_channel_pointer.is_null());
 
-                        output_slice.copy_from_slice(std::slice::from_raw_parts_mut(
-                            (*input_channel_pointer).add(sample_offset),
-                            num_samples,
-                        ))
+                        let input_ptr = (*input_channel_pointer).add(sample_offset);
+                        if input_ptr != output_slice.as_mut_ptr() {
+                            output_slice
+                                .copy_from_slice(std::slice::from_raw_parts(input_ptr, num_samples))
+                        }
                     }
                 });
             }
@@ -373,6 +428,136 @@ impl BufferManager {
     }
 }
 
+/// Pads the main buffer passed to [`Plugin::process()`][crate::prelude::Plugin::process()] with
+/// silence so its length is a multiple of
+/// [`Plugin::SIMD_BLOCK_MULTIPLE`][crate::prelude::Plugin::SIMD_BLOCK_MULTIPLE]. The host's
+/// samples are copied to preallocated storage, the plugin processes that storage, and then only the
+/// non-padding samples are copied back. If the multiple is 1 or lower, then the host's buffer is
+/// passed through as is.
+pub struct PaddedBuffer {
+    /// The block length is rounded up to a multiple of this.
+    multiple: usize,
+    /// The buffer passed to the plugin. This `'static` lifetime is shortened again in
+    /// [`process()`][Self::process()]. Its slices point to `storage`.
+    buffer: Buffer<'static>,
+    /// Stores the padded samples for every channel. Empty if `multiple` is 1 or lower.
+    storage: Vec<Vec<f32>>,
+}
+
+impl PaddedBuffer {
+    /// Preallocate storage for the main buffer of an audio IO layout with up to `max_buffer_size`
+    /// samples, rounded up to a multiple of `multiple`.
+    pub fn for_audio_io_layout(
+        max_buffer_size: usize,
+        audio_io_layout: AudioIOLayout,
+        multiple: usize,
+    ) -> Self {
+        // This matches the main buffer created by `BufferManager`
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .or(audio_io_layout.main_input_channels)
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
+        let (storage, buffer) = if multiple > 1 {
+            let mut buffer = Buffer::default();
+            unsafe {
+                buffer.set_slices(0, |slices| {
+                    slices.resize_with(num_channels, || &mut []);
+                })
+            };
+
+            (
+                vec![vec![0.0; round_up(max_buffer_size, multiple)]; num_channels],
+                buffer,
+            )
+        } else {
+            (Vec::new(), Buffer::default())
+        };
+
+        Self {
+            multiple,
+            buffer,
+            storage,
+        }
+    }
+
+    /// Call `process` with a padded version of `buffer`, and copy the result back to `buffer`. The
+    /// padding samples are always zeroed before calling `process`, and they are discarded
+    /// afterwards.
+    pub fn process<R>(&mut self, buffer: &mut Buffer, process: impl FnOnce(&mut Buffer) -> R) -> R {
+        let num_samples = buffer.samples();
+        let padded_num_samples = round_up(num_samples, self.multiple);
+        if self.multiple <= 1 || self.storage.len() != buffer.channels() {
+            nih_debug_assert!(
+                self.multiple <= 1,
+                "The padded buffer's channel count does not match the main buffer's"
+            );
+            return process(buffer);
+        }
+
+        for (storage, channel) in self.storage.iter_mut().zip(buffer.as_slice_immutable()) {
+            nih_debug_assert!(padded_num_samples <= storage.len());
+            storage[..num_samples].copy_from_slice(channel);
+            storage[num_samples..padded_num_samples].fill(0.0);
+        }
+
+        let storage = &mut self.storage;
+        unsafe {
+            self.buffer.set_slices(padded_num_samples, |slices| {
+                for (slice, storage) in slices.iter_mut().zip(storage.iter_mut()) {
+                    // SAFETY: These slices are only used while `process` is being called, and
+                    //         `storage` is never resized
+                    *slice = &mut *(&mut storage[..padded_num_samples] as *mut [f32]);
+                }
+            })
+        };
+        self.buffer
+            .set_padding_samples(padded_num_samples - num_samples);
+        let result = process(&mut self.buffer);
+
+        for (channel, storage) in buffer.as_slice().iter_mut().zip(&self.storage) {
+            channel.copy_from_slice(&storage[..num_samples]);
+        }
+
+        result
+    }
+}
+
+/// Scratch buffers requested by the plugin with
+/// [`InitContext::request_scratch_buffers()`][crate::prelude::InitContext::request_scratch_buffers()].
+/// These are allocated once when the plugin is activated, and they're exposed to the plugin
+/// through [`ProcessContext::scratch_buffers()`][crate::prelude::ProcessContext::scratch_buffers()].
+/// The buffers are boxed slices so the plugin cannot accidentally resize them on the audio thread.
+#[derive(Debug, Default)]
+pub struct ScratchBuffers {
+    buffers: Vec<Box<[f32]>>,
+}
+
+impl ScratchBuffers {
+    /// Allocate `count` zeroed buffers with `max_buffer_size` samples each.
+    pub fn new(count: usize, max_buffer_size: usize) -> Self {
+        Self {
+            buffers: (0..count)
+                .map(|_| vec![0.0; max_buffer_size].into_boxed_slice())
+                .collect(),
+        }
+    }
+
+    /// Get mutable access to the scratch buffers.
+    pub fn as_mut_slice(&mut self) -> &mut [Box<[f32]>] {
+        &mut self.buffers
+    }
+}
+
+/// Round `num_samples` up to the next multiple of `multiple`.
+fn round_up(num_samples: usize, multiple: usize) -> usize {
+    if multiple > 1 {
+        ((num_samples + multiple - 1) / multiple) * multiple
+    } else {
+        num_samples
+    }
+}
+
 #[cfg(any(miri, test))]
 mod miri {
     use super::*;
@@ -501,4 +686,146 @@ mod miri {
             }
         }
     }
+
+    #[test]
+    fn in_place_buffer_io() {
+        const STEREO_LAYOUT: AudioIOLayout = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            ..AudioIOLayout::const_default()
+        };
+
+        // Like CLAP hosts, the inputs and outputs use separate channel pointer arrays that point
+        // to the same channels
+        let mut main_io_storage = vec![vec![0.5f32; BUFFER_SIZE], vec![0.25f32; BUFFER_SIZE]];
+        let mut main_input_channel_pointers: Vec<*mut f32> = main_io_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+        let mut main_output_channel_pointers = main_input_channel_pointers.clone();
+
+        let mut buffer_manager = BufferManager::for_audio_io_layout(BUFFER_SIZE, STEREO_LAYOUT);
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_input_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_input_channel_pointers.len(),
+                });
+                *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_output_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_output_channel_pointers.len(),
+                });
+            })
+        };
+
+        // The plugin sees the unmodified input, and its output ends up in the host's buffers
+        for channel_samples in buffers.main_buffer.iter_samples() {
+            for (sample, input) in channel_samples.into_iter().zip([0.5, 0.25]) {
+                assert!(*sample == input);
+                *sample *= 2.0;
+            }
+        }
+
+        assert!(main_io_storage[0].iter().all(|sample| *sample == 1.0));
+        assert!(main_io_storage[1].iter().all(|sample| *sample == 0.5));
+    }
+
+    #[test]
+    fn input_only_buffer_io() {
+        const ANALYZER_LAYOUT: AudioIOLayout = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            ..AudioIOLayout::const_default()
+        };
+
+        let mut main_input_storage = vec![vec![0.5f32; BUFFER_SIZE]; 2];
+        let mut main_input_channel_pointers: Vec<*mut f32> = main_input_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+
+        let mut buffer_manager = BufferManager::for_audio_io_layout(BUFFER_SIZE, ANALYZER_LAYOUT);
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_input_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_input_channel_pointers.len(),
+                });
+            })
+        };
+
+        // The plugin sees the main input, but since there are no outputs its changes are discarded
+        assert_eq!(buffers.main_buffer.channels(), 2);
+        for channel_samples in buffers.main_buffer.iter_samples() {
+            for sample in channel_samples {
+                assert!(*sample == 0.5);
+                *sample = 1.0;
+            }
+        }
+
+        for channel in &main_input_storage {
+            for sample in channel {
+                assert!(*sample == 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn padded_buffer_discards_padding() {
+        // The host's buffer is larger than the block, and the samples after the block must not be
+        // touched
+        const NUM_SAMPLES: usize = 13;
+        let mut host_storage = vec![vec![0.25f32; 16]; NUM_MAIN_OUTPUT_CHANNELS];
+
+        let mut padded_buffer = PaddedBuffer::for_audio_io_layout(BUFFER_SIZE, AUDIO_IO_LAYOUT, 4);
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(NUM_SAMPLES, |slices| {
+                *slices = host_storage
+                    .iter_mut()
+                    .map(|channel| &mut channel[..NUM_SAMPLES])
+                    .collect();
+            })
+        };
+
+        let padded_samples = padded_buffer.process(&mut buffer, |buffer| {
+            for channel in buffer.as_slice() {
+                assert_eq!(channel[..NUM_SAMPLES], [0.25; NUM_SAMPLES]);
+                assert_eq!(channel[NUM_SAMPLES..], [0.0; 3]);
+            }
+
+            for channel in buffer.as_slice() {
+                for sample in channel.iter_mut() {
+                    *sample += 1.0;
+                }
+            }
+
+            (buffer.samples(), buffer.unpadded_samples())
+        });
+        drop(buffer);
+
+        assert_eq!(padded_samples, (16, NUM_SAMPLES));
+        for channel in &host_storage {
+            assert_eq!(channel[..NUM_SAMPLES], [1.25; NUM_SAMPLES]);
+            assert_eq!(channel[NUM_SAMPLES..], [0.25; 3]);
+        }
+
+        // Without padding the host's buffer is passed through
+        let mut padded_buffer = PaddedBuffer::for_audio_io_layout(BUFFER_SIZE, AUDIO_IO_LAYOUT, 1);
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(NUM_SAMPLES, |slices| {
+                *slices = host_storage
+                    .iter_mut()
+                    .map(|channel| &mut channel[..NUM_SAMPLES])
+                    .collect();
+            })
+        };
+        assert_eq!(
+            padded_buffer.process(&mut buffer, |buffer| (
+                buffer.samples(),
+                buffer.unpadded_samples()
+            )),
+            (NUM_SAMPLES, NUM_SAMPLES)
+        );
+    }
 }
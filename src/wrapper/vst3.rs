@@ -13,14 +13,22 @@ mod wrapper;
 /// Re-export for the wrapper.
 pub use factory::Factory;
 
-/// Export a VST3 plugin from this library using the provided plugin type.
+/// Export one or more VST3 plugins from this library using the provided plugin types. Every type is
+/// exposed to the host as a separate plugin, so alternate variants of a plugin, like a mono and a
+/// stereo version, can be exported from the same library by passing multiple types. These plugins
+/// need to have unique class IDs.
+///
+/// ```ignore
+/// nih_export_vst3!(MyPluginMono, MyPluginStereo);
+/// ```
 #[macro_export]
 macro_rules! nih_export_vst3 {
-    ($plugin_ty:ty) => {
+    ($($plugin_ty:ty),+ $(,)?) => {
         /// The VST3 plugin factory entry point.
         #[no_mangle]
         pub extern "system" fn GetPluginFactory() -> *mut ::std::ffi::c_void {
-            let factory = ::nih_plug::wrapper::vst3::Factory::<$plugin_ty>::new();
+            let factory = ::nih_plug::wrapper::vst3::Factory::new()
+                $(.with_plugin::<$plugin_ty>())+;
 
             Box::into_raw(factory) as *mut ::std::ffi::c_void
         }
@@ -6,9 +6,12 @@ use std::sync::Arc;
 use vst3_sys::vst::IComponentHandler;
 
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, PluginState, ProcessContext,
-    Transport, Vst3Plugin,
+    ChainHint, ChannelCounts, EventsIter, GuiContext, HostTimerCallback, InitContext,
+    ParamChangeSource, ParamIndication, ParamPtr, PluginApi, PluginNoteEvent, PluginState,
+    PosixFdCallback, PosixFdFlags, ProcessContext, RawHostHandle, Transport, Vst3Plugin,
 };
+use crate::wrapper::util::buffer_management::ScratchBuffers;
+use crate::wrapper::util::InputEventPorts;
 
 use super::inner::{Task, WrapperInner};
 
@@ -40,8 +43,11 @@ pub(crate) struct PendingInitContextRequests {
 pub(crate) struct WrapperProcessContext<'a, P: Vst3Plugin> {
     pub(super) inner: &'a WrapperInner<P>,
     pub(super) input_events_guard: AtomicRefMut<'a, VecDeque<PluginNoteEvent<P>>>,
+    pub(super) input_event_ports_guard: AtomicRefMut<'a, InputEventPorts>,
     pub(super) output_events_guard: AtomicRefMut<'a, VecDeque<PluginNoteEvent<P>>>,
+    pub(super) scratch_buffers_guard: AtomicRefMut<'a, ScratchBuffers>,
     pub(super) transport: Transport,
+    pub(super) block_start_samples: i64,
 }
 
 /// A [`GuiContext`] implementation for the wrapper. This is passed to the plugin in
@@ -79,6 +85,33 @@ impl<P: Vst3Plugin> InitContext<P> for WrapperInitContext<'_, P> {
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn request_scratch_buffers(&self, count: usize) {
+        self.inner.request_scratch_buffers(count);
+    }
+
+    fn register_host_timer(&self, _period_ms: u32, _callback: HostTimerCallback) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn register_posix_fd(
+        &self,
+        _fd: i32,
+        _flags: PosixFdFlags,
+        _callback: PosixFdCallback,
+    ) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn chain_hint(&self) -> ChainHint {
+        self.inner.chain_hint.lock().clone()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.inner.raw_host_handle()
+    }
 }
 
 impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
@@ -101,8 +134,31 @@ impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         &self.transport
     }
 
+    #[inline]
+    fn block_start_samples(&self) -> i64 {
+        self.block_start_samples
+    }
+
+    fn channel_counts(&self) -> ChannelCounts {
+        self.inner.current_audio_io_layout.load().channel_counts()
+    }
+
+    fn scratch_buffers(&mut self) -> &mut [Box<[f32]>] {
+        self.scratch_buffers_guard.as_mut_slice()
+    }
+
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>> {
-        self.input_events_guard.pop_front()
+        self.next_event_with_port().map(|(event, _)| event)
+    }
+
+    fn next_event_with_port(&mut self) -> Option<(PluginNoteEvent<P>, u32)> {
+        let event = self.input_events_guard.pop_front()?;
+        Some((event, self.input_event_ports_guard.pop_front()))
+    }
+
+    fn events(&self) -> EventsIter<'_, PluginNoteEvent<P>> {
+        let (first, second) = self.input_events_guard.as_slices();
+        EventsIter::new(first, second)
     }
 
     fn send_event(&mut self, event: PluginNoteEvent<P>) {
@@ -116,6 +172,44 @@ impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn request_restart(&self) {
+        self.inner.request_restart()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.inner.raw_host_handle()
+    }
+
+    unsafe fn raw_set_parameter_automated(
+        &mut self,
+        param: ParamPtr,
+        _sample_offset: u32,
+        normalized: f32,
+    ) {
+        // VST3 doesn't get the sample offset, the last value is written to the end of the block
+        match self.inner.param_ptr_to_hash.get(&param) {
+            Some(hash) => {
+                self.inner.param_write_locks.plugin_wrote(hash, normalized);
+
+                let mut output_param_changes = self.inner.output_param_changes.borrow_mut();
+                if let Some((_, value)) = output_param_changes
+                    .iter_mut()
+                    .find(|(param_hash, _)| param_hash == hash)
+                {
+                    *value = normalized;
+                } else if output_param_changes.len() < output_param_changes.capacity() {
+                    output_param_changes.push((*hash, normalized));
+                } else {
+                    nih_debug_assert_failure!(
+                        "Parameter output queue was full, parameter change will not be sent to the \
+                         host"
+                    );
+                }
+            }
+            None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+        }
+    }
 }
 
 impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
@@ -138,6 +232,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner.param_gestures.begin(hash);
                     handler.begin_edit(*hash);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -161,6 +256,13 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    // This needs to happen before the host gets a chance to send the change back
+                    // to the plugin
+                    self.inner
+                        .param_change_sources
+                        .editor_changed(hash, normalized);
+                    self.inner.param_write_locks.plugin_wrote(hash, normalized);
+
                     // Only update the parameters manually if the host is not processing audio. If
                     // the plugin is currently processing audio, the host will pass this change back
                     // to the plugin in the audio callback. This also prevents the values from
@@ -202,6 +304,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner.param_gestures.end(hash);
                     handler.end_edit(*hash);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -221,6 +324,21 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         }
     }
 
+    unsafe fn raw_take_param_change_source(&self, param: ParamPtr) -> Option<ParamChangeSource> {
+        match self.inner.param_ptr_to_hash.get(&param) {
+            Some(hash) => self.inner.param_change_sources.take(hash),
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                None
+            }
+        }
+    }
+
+    unsafe fn raw_param_indication(&self, _param: ParamPtr) -> Option<ParamIndication> {
+        // This is only supported by CLAP
+        None
+    }
+
     fn get_state(&self) -> PluginState {
         self.inner.get_state_object()
     }
@@ -228,4 +346,42 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     fn set_state(&self, state: PluginState) {
         self.inner.set_state_object_from_gui(state)
     }
+
+    fn current_preset(&self) -> Option<usize> {
+        self.inner.factory_presets.current()
+    }
+
+    fn set_preset(&self, index: usize) -> bool {
+        self.inner
+            .factory_presets
+            .load(index, |state| self.inner.set_state_object_from_gui(state))
+    }
+
+    fn editor_state(&self, key: &str) -> Option<String> {
+        self.inner.editor_state.get(key)
+    }
+
+    fn set_editor_state(&self, key: &str, value: Option<String>) {
+        self.inner.editor_state.set(key, value)
+    }
+
+    fn transport(&self) -> Option<Transport> {
+        self.inner.transport_snapshot.load()
+    }
+
+    fn mark_state_dirty(&self) {
+        self.inner.mark_state_dirty()
+    }
+
+    fn request_param_rescan(&self) {
+        self.inner.request_param_rescan()
+    }
+
+    fn chain_hint(&self) -> ChainHint {
+        self.inner.chain_hint.lock().clone()
+    }
+
+    unsafe fn raw_host_handle(&self) -> RawHostHandle {
+        self.inner.raw_host_handle()
+    }
 }
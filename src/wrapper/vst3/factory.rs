@@ -12,73 +12,109 @@ use super::subcategories::Vst3SubCategory;
 use super::util::u16strlcpy;
 use super::wrapper::Wrapper;
 use crate::prelude::Vst3Plugin;
-use crate::wrapper::util::strlcpy;
+use crate::wrapper::util::{is_analyzer_plugin, strlcpy};
 
 /// The VST3 SDK version this is roughly based on. The bindings include some VST 3.7 things but not
 /// everything, so we'll play it safe.
 const VST3_SDK_VERSION: &str = "VST 3.6.14";
 
+/// The plugin factory. A single factory can contain multiple plugins, for instance mono and stereo
+/// variants of the same plugin. These are added using [`with_plugin()`][Self::with_plugin()].
 #[doc(hidden)]
 #[VST3(implements(IPluginFactory, IPluginFactory2, IPluginFactory3))]
-pub struct Factory<P: Vst3Plugin> {
-    /// The type will be used for constructing plugin instances later.
-    _phantom: PhantomData<P>,
+pub struct Factory {
+    /// The plugins exposed by this factory, in the order they were added. The factory's vendor
+    /// information is taken from the first plugin.
+    plugins: Vec<Box<dyn FactoryPlugin>>,
 }
 
-impl<P: Vst3Plugin> Factory<P> {
+/// A type erased plugin that can be listed and created by a [`Factory`].
+trait FactoryPlugin {
+    /// The plugin's class ID in the platform's byte order.
+    fn class_id(&self) -> [u8; 16];
+    fn name(&self) -> &'static str;
+    fn vendor(&self) -> &'static str;
+    fn url(&self) -> &'static str;
+    fn email(&self) -> &'static str;
+    fn version(&self) -> &'static str;
+    fn subcategories(&self) -> String;
+    /// Create a new instance of the plugin and query `iid` on it.
+    unsafe fn create_instance(
+        &self,
+        iid: *const vst3_sys::IID,
+        obj: *mut *mut vst3_sys::c_void,
+    ) -> tresult;
+}
+
+/// The [`FactoryPlugin`] implementation for a plugin type.
+struct PluginClass<P: Vst3Plugin>(PhantomData<P>);
+
+impl Factory {
     pub fn new() -> Box<Self> {
-        Self::allocate(PhantomData::default())
+        Self::allocate(Vec::new())
     }
-}
 
-impl<P: Vst3Plugin> IPluginFactory for Factory<P> {
-    unsafe fn get_factory_info(&self, info: *mut vst3_sys::base::PFactoryInfo) -> tresult {
-        *info = mem::zeroed();
+    /// Add a plugin to the factory. Every plugin needs to have a unique class ID.
+    pub fn with_plugin<P: Vst3Plugin>(mut self: Box<Self>) -> Box<Self> {
+        nih_debug_assert_ne!(
+            P::vst3_class_id(),
+            [0; 16],
+            "The VST3 class ID should not consist of only zeroes"
+        );
+        assert!(
+            self.plugins
+                .iter()
+                .all(|plugin| plugin.class_id() != P::platform_vst3_class_id()),
+            "Multiple plugins use the VST3 class ID {:?}",
+            P::vst3_class_id()
+        );
 
-        let info = &mut *info;
-        strlcpy(&mut info.vendor, P::VENDOR);
-        strlcpy(&mut info.url, P::URL);
-        strlcpy(&mut info.email, P::EMAIL);
-        info.flags = vst3_sys::base::FactoryFlags::kUnicode as i32;
+        self.plugins.push(Box::new(PluginClass::<P>(PhantomData)));
+        self
+    }
 
-        kResultOk
+    fn plugin(&self, index: i32) -> Option<&dyn FactoryPlugin> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.plugins.get(index))
+            .map(|plugin| plugin.as_ref())
     }
+}
 
-    unsafe fn count_classes(&self) -> i32 {
-        // We don't do shell plugins, and good of an idea having separated components and edit
-        // controllers in theory is, few software can use it, and doing that would make our simple
-        // microframework a lot less simple
-        1
+impl<P: Vst3Plugin> FactoryPlugin for PluginClass<P> {
+    fn class_id(&self) -> [u8; 16] {
+        P::platform_vst3_class_id()
     }
 
-    unsafe fn get_class_info(&self, index: i32, info: *mut vst3_sys::base::PClassInfo) -> tresult {
-        if index != 0 {
-            return kInvalidArgument;
-        }
+    fn name(&self) -> &'static str {
+        P::NAME
+    }
 
-        *info = mem::zeroed();
+    fn vendor(&self) -> &'static str {
+        P::VENDOR
+    }
 
-        let info = &mut *info;
-        info.cid.data = P::PLATFORM_VST3_CLASS_ID;
-        info.cardinality = vst3_sys::base::ClassCardinality::kManyInstances as i32;
-        strlcpy(&mut info.category, "Audio Module Class");
-        strlcpy(&mut info.name, P::NAME);
+    fn url(&self) -> &'static str {
+        P::URL
+    }
 
-        kResultOk
+    fn email(&self) -> &'static str {
+        P::EMAIL
+    }
+
+    fn version(&self) -> &'static str {
+        P::VERSION
+    }
+
+    fn subcategories(&self) -> String {
+        make_subcategories_string::<P>()
     }
 
     unsafe fn create_instance(
         &self,
-        cid: *const vst3_sys::IID,
         iid: *const vst3_sys::IID,
         obj: *mut *mut vst3_sys::c_void,
     ) -> tresult {
-        check_null_ptr!(cid, obj);
-
-        if (*cid).data != P::PLATFORM_VST3_CLASS_ID {
-            return kInvalidArgument;
-        }
-
         let wrapper = Wrapper::<P>::new();
 
         // 99.999% of the times `iid` will be that of `IComponent`, but the caller is technically
@@ -102,54 +138,117 @@ impl<P: Vst3Plugin> IPluginFactory for Factory<P> {
     }
 }
 
-impl<P: Vst3Plugin> IPluginFactory2 for Factory<P> {
+impl IPluginFactory for Factory {
+    unsafe fn get_factory_info(&self, info: *mut vst3_sys::base::PFactoryInfo) -> tresult {
+        let plugin = match self.plugins.first() {
+            Some(plugin) => plugin,
+            None => return kInvalidArgument,
+        };
+
+        *info = mem::zeroed();
+
+        let info = &mut *info;
+        strlcpy(&mut info.vendor, plugin.vendor());
+        strlcpy(&mut info.url, plugin.url());
+        strlcpy(&mut info.email, plugin.email());
+        info.flags = vst3_sys::base::FactoryFlags::kUnicode as i32;
+
+        kResultOk
+    }
+
+    unsafe fn count_classes(&self) -> i32 {
+        // We don't do shell plugins, and good of an idea having separated components and edit
+        // controllers in theory is, few software can use it, and doing that would make our simple
+        // microframework a lot less simple. Every plugin is thus a single class.
+        self.plugins.len() as i32
+    }
+
+    unsafe fn get_class_info(&self, index: i32, info: *mut vst3_sys::base::PClassInfo) -> tresult {
+        let plugin = match self.plugin(index) {
+            Some(plugin) => plugin,
+            None => return kInvalidArgument,
+        };
+
+        *info = mem::zeroed();
+
+        let info = &mut *info;
+        info.cid.data = plugin.class_id();
+        info.cardinality = vst3_sys::base::ClassCardinality::kManyInstances as i32;
+        strlcpy(&mut info.category, "Audio Module Class");
+        strlcpy(&mut info.name, plugin.name());
+
+        kResultOk
+    }
+
+    unsafe fn create_instance(
+        &self,
+        cid: *const vst3_sys::IID,
+        iid: *const vst3_sys::IID,
+        obj: *mut *mut vst3_sys::c_void,
+    ) -> tresult {
+        check_null_ptr!(cid, obj);
+
+        match self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.class_id() == (*cid).data)
+        {
+            Some(plugin) => plugin.create_instance(iid, obj),
+            None => kInvalidArgument,
+        }
+    }
+}
+
+impl IPluginFactory2 for Factory {
     unsafe fn get_class_info2(
         &self,
         index: i32,
         info: *mut vst3_sys::base::PClassInfo2,
     ) -> tresult {
-        if index != 0 {
-            return kInvalidArgument;
-        }
+        let plugin = match self.plugin(index) {
+            Some(plugin) => plugin,
+            None => return kInvalidArgument,
+        };
 
         *info = mem::zeroed();
 
         let info = &mut *info;
-        info.cid.data = P::PLATFORM_VST3_CLASS_ID;
+        info.cid.data = plugin.class_id();
         info.cardinality = vst3_sys::base::ClassCardinality::kManyInstances as i32;
         strlcpy(&mut info.category, "Audio Module Class");
-        strlcpy(&mut info.name, P::NAME);
+        strlcpy(&mut info.name, plugin.name());
         info.class_flags = 1 << 1; // kSimpleModeSupported
-        strlcpy(&mut info.subcategories, &make_subcategories_string::<P>());
-        strlcpy(&mut info.vendor, P::VENDOR);
-        strlcpy(&mut info.version, P::VERSION);
+        strlcpy(&mut info.subcategories, &plugin.subcategories());
+        strlcpy(&mut info.vendor, plugin.vendor());
+        strlcpy(&mut info.version, plugin.version());
         strlcpy(&mut info.sdk_version, VST3_SDK_VERSION);
 
         kResultOk
     }
 }
 
-impl<P: Vst3Plugin> IPluginFactory3 for Factory<P> {
+impl IPluginFactory3 for Factory {
     unsafe fn get_class_info_unicode(
         &self,
         index: i32,
         info: *mut vst3_sys::base::PClassInfoW,
     ) -> tresult {
-        if index != 0 {
-            return kInvalidArgument;
-        }
+        let plugin = match self.plugin(index) {
+            Some(plugin) => plugin,
+            None => return kInvalidArgument,
+        };
 
         *info = mem::zeroed();
 
         let info = &mut *info;
-        info.cid.data = P::PLATFORM_VST3_CLASS_ID;
+        info.cid.data = plugin.class_id();
         info.cardinality = vst3_sys::base::ClassCardinality::kManyInstances as i32;
         strlcpy(&mut info.category, "Audio Module Class");
-        u16strlcpy(&mut info.name, P::NAME);
+        u16strlcpy(&mut info.name, plugin.name());
         info.class_flags = 1 << 1; // kSimpleModeSupported
-        strlcpy(&mut info.subcategories, &make_subcategories_string::<P>());
-        u16strlcpy(&mut info.vendor, P::VENDOR);
-        u16strlcpy(&mut info.version, P::VERSION);
+        strlcpy(&mut info.subcategories, &plugin.subcategories());
+        u16strlcpy(&mut info.vendor, plugin.vendor());
+        u16strlcpy(&mut info.version, plugin.version());
         u16strlcpy(&mut info.sdk_version, VST3_SDK_VERSION);
 
         kResultOk
@@ -165,11 +264,17 @@ fn make_subcategories_string<P: Vst3Plugin>() -> String {
     // No idea if any hosts do something with OnlyRT, but it's part of VST3's example categories
     // list. Plugins should not be adding this feature manually
     nih_debug_assert!(!P::VST3_SUBCATEGORIES.contains(&Vst3SubCategory::Custom("OnlyRT")));
-    let subcategory_string = P::VST3_SUBCATEGORIES
+    let mut subcategories: Vec<&str> = P::VST3_SUBCATEGORIES
         .iter()
         .map(Vst3SubCategory::as_str)
-        .collect::<Vec<&str>>()
-        .join("|");
+        .collect();
+    // Plugins that only have analyzer layouts are always categorized as analyzers
+    if is_analyzer_plugin(P::AUDIO_IO_LAYOUTS)
+        && !P::VST3_SUBCATEGORIES.contains(&Vst3SubCategory::Analyzer)
+    {
+        subcategories.push(Vst3SubCategory::Analyzer.as_str());
+    }
+    let subcategory_string = subcategories.join("|");
 
     let subcategory_string = if P::HARD_REALTIME_ONLY {
         format!("{subcategory_string}|OnlyRT")
@@ -180,3 +285,45 @@ fn make_subcategories_string<P: Vst3Plugin>() -> String {
 
     subcategory_string
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::test_plugin::{NoParams, TestPlugin, TestPluginConfig};
+    use crate::prelude::*;
+
+    /// A plugin that can optionally only process audio in realtime.
+    struct Realtime<const HARD_REALTIME_ONLY: bool>;
+
+    type RealtimePlugin<const HARD_REALTIME_ONLY: bool> = TestPlugin<Realtime<HARD_REALTIME_ONLY>>;
+
+    impl<const HARD_REALTIME_ONLY: bool> TestPluginConfig for Realtime<HARD_REALTIME_ONLY> {
+        const NAME: &'static str = "Wall Clock";
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            ..AudioIOLayout::const_default()
+        }];
+        const HARD_REALTIME_ONLY: bool = HARD_REALTIME_ONLY;
+
+        type Params = NoParams;
+    }
+
+    impl<const HARD_REALTIME_ONLY: bool> Vst3Plugin for RealtimePlugin<HARD_REALTIME_ONLY> {
+        const VST3_CLASS_ID: [u8; 16] = *b"WallClockPlugin!";
+        const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+            &[Vst3SubCategory::Fx, Vst3SubCategory::Delay];
+    }
+
+    #[test]
+    fn hard_realtime_only_subcategory() {
+        assert_eq!(
+            make_subcategories_string::<RealtimePlugin<false>>(),
+            "Fx|Delay"
+        );
+        assert_eq!(
+            make_subcategories_string::<RealtimePlugin<true>>(),
+            "Fx|Delay|OnlyRT"
+        );
+    }
+}
@@ -3,11 +3,13 @@ use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, SendTimeoutError};
 use parking_lot::{Mutex, RwLock};
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use vst3_sys::base::{kInvalidArgument, kResultOk, tresult};
-use vst3_sys::vst::{IComponentHandler, RestartFlags};
+use vst3_sys::vst::{IComponentHandler, IComponentHandler2, RestartFlags};
 
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
 use super::note_expressions::NoteExpressionController;
@@ -16,13 +18,18 @@ use super::util::{ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_STAR
 use super::view::WrapperView;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, MidiConfig, ParamFlags, ParamPtr, Params,
-    Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport, Vst3Plugin,
+    AsyncExecutor, AudioIOLayout, BufferConfig, ChainHint, Editor, MidiConfig, ParamFlags,
+    ParamPtr, Params, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, RawHostHandle,
+    TaskExecutor, Transport, Vst3Plugin,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::buffer_management::BufferManager;
-use crate::wrapper::util::{hash_param_id, process_wrapper};
+use crate::wrapper::state::{self, EditorStateStore, PluginState};
+use crate::wrapper::util::buffer_management::{BufferManager, PaddedBuffer, ScratchBuffers};
+use crate::wrapper::util::{
+    hash_param_id, lone_param_link_groups, process_wrapper, BlockPositionCounter, BypassState,
+    CarriedInputEvents, FactoryPresets, FirstProcess, InputEventPorts, ParamChangeSources,
+    ParamGestures, ParamWriteLocks, PendingRequest, PluginLatency, SeekDetector, TransportSnapshot,
+};
 
 /// The actual wrapper bits. We need this as an `Arc<T>` so we can safely use our event loop API.
 /// Since we can't combine that with VST3's interior reference counting this just has to be moved to
@@ -44,6 +51,11 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// The host's [`IComponentHandler`] instance, if passed through
     /// [`IEditController::set_component_handler`].
     pub component_handler: AtomicRefCell<Option<VstPtr<dyn IComponentHandler>>>,
+    /// The raw `FUnknown*` host context passed to `IPluginBase::initialize()`. This is only exposed
+    /// to the plugin through
+    /// [`GuiContext::raw_host_handle()`][crate::prelude::GuiContext::raw_host_handle()] and
+    /// friends, and it's reset to a null pointer again when the plugin gets terminated.
+    pub host_context: AtomicPtr<c_void>,
 
     /// Our own [`IPlugView`] instance. This is set while the editor is actually visible (which is
     /// different form the lifetime of [`WrapperView`][super::WrapperView] itself).
@@ -75,12 +87,44 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     pub current_process_mode: AtomicCell<ProcessMode>,
     /// The last process status returned by the plugin. This is used for tail handling.
     pub last_process_status: AtomicCell<ProcessStatus>,
+    /// Keeps track of the absolute position of each block for
+    /// [`ProcessContext::block_start_samples()`]. Reset whenever the plugin gets reset.
+    pub block_position: BlockPositionCounter,
+    /// Detects seeks for [`Transport::just_seeked()`]. Reset whenever the plugin gets reset.
+    pub seek_detector: SeekDetector,
+    /// Keeps the note events from parameter flushes without any samples for the next block. Reset
+    /// whenever the plugin gets reset.
+    pub carried_input_events: CarriedInputEvents,
     /// The current latency in samples, as set by the plugin through the [`InitContext`] and the
     /// [`ProcessContext`].
     pub current_latency: AtomicU32,
+    /// Reports changes in [`Plugin::latency()`] to the host through
+    /// [`set_latency_samples()`][Self::set_latency_samples()].
+    pub plugin_latency: PluginLatency,
+    /// Set when the plugin requested a restart through [`ProcessContext::request_restart()`] until
+    /// the host has been notified on the main thread.
+    pub restart_request: PendingRequest,
+    /// Calls [`Plugin::first_process()`] before the first block after the plugin gets activated.
+    pub first_process: FirstProcess,
+    /// Set when the editor marked the plugin's state as dirty through
+    /// [`GuiContext::mark_state_dirty()`][crate::prelude::GuiContext::mark_state_dirty()] until the
+    /// host has been notified on the main thread.
+    pub state_dirty_request: PendingRequest,
+    /// Set when the editor requested a parameter rescan through
+    /// [`GuiContext::request_param_rescan()`][crate::prelude::GuiContext::request_param_rescan()]
+    /// until the host has been notified on the main thread.
+    pub param_rescan_request: PendingRequest,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     pub buffer_manager: AtomicRefCell<BufferManager>,
+    /// Pads the main buffer to a multiple of [`Plugin::SIMD_BLOCK_MULTIPLE`] samples.
+    pub padded_buffer: AtomicRefCell<PaddedBuffer>,
+    /// The number of scratch buffers the plugin requested with
+    /// `InitContext::request_scratch_buffers()` during the last initialization.
+    pub scratch_buffer_count: AtomicUsize,
+    /// The scratch buffers exposed through `ProcessContext::scratch_buffers()`. These are allocated
+    /// when the plugin gets activated.
+    pub scratch_buffers: AtomicRefCell<ScratchBuffers>,
     /// The incoming events for the plugin, if `P::ACCEPTS_MIDI` is set. If
     /// `P::SAMPLE_ACCURATE_AUTOMATION`, this is also read in lockstep with the parameter change
     /// block splitting.
@@ -89,9 +133,16 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     ///       interleave parameter changes and note events, this queue has to be sorted when
     ///       creating the process context
     pub input_events: AtomicRefCell<VecDeque<PluginNoteEvent<P>>>,
+    /// The note port every event in `input_events` arrived on.
+    pub input_event_ports: AtomicRefCell<InputEventPorts>,
     /// Stores any events the plugin has output during the current processing cycle, analogous to
     /// `input_events`.
     pub output_events: AtomicRefCell<VecDeque<PluginNoteEvent<P>>>,
+    /// The parameter hashes and normalized values of the automation points written by the plugin
+    /// through [`ProcessContext::set_parameter_automated()`] during the current processing cycle.
+    /// VST3 writes these to the end of the block, so only the last value for each parameter is
+    /// kept.
+    pub output_param_changes: AtomicRefCell<Vec<(u32, f32)>>,
     /// VST3 has several useful predefined note expressions, but for some reason they are the only
     /// note event type that don't have MIDI note ID and channel fields. So we need to keep track of
     /// the most recent VST3 note IDs we've seen, and then map those back to MIDI note IDs and
@@ -137,6 +188,30 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// Whether parameters were last changed by the host or by the editor, indexed by parameter
+    /// hash. Exposed to the editor through the `GuiContext`.
+    pub param_change_sources: ParamChangeSources<u32>,
+    /// Locks parameters for `P::PARAM_WRITE_LOCK` after the plugin writes them, indexed by
+    /// parameter hash.
+    pub param_write_locks: ParamWriteLocks<u32>,
+    /// Parameters that are currently being changed from the editor, indexed by parameter hash.
+    /// Used for `P::PRESERVE_GESTURED_PARAMS_ON_STATE_LOAD`.
+    pub param_gestures: ParamGestures<u32>,
+    /// Lets parameters with a neutral bypass value react to changes to the bypass parameter.
+    pub bypass_state: BypassState,
+    /// The editor's own state that's saved alongside the parameters. Exposed to the editor through
+    /// the `GuiContext`.
+    pub editor_state: EditorStateStore,
+    /// The transport information from the last processing cycle. Exposed to the editor through the
+    /// `GuiContext`.
+    pub transport_snapshot: TransportSnapshot,
+    /// The plugin's factory presets and the preset that was loaded last. Exposed to the editor
+    /// through the `GuiContext`.
+    pub factory_presets: FactoryPresets,
+    /// Information about the track the plugin has been inserted on, set by the host through
+    /// `IInfoListener`. Hosts usually call this after the plugin has been initialized, so this is
+    /// exposed to the plugin through both the `InitContext` and the `GuiContext`.
+    pub chain_hint: Mutex<ChainHint>,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -157,6 +232,14 @@ pub enum Task<P: Plugin> {
     /// Request the editor to be resized according to its current size. Right now there is no way to
     /// handle "denied resize" requests yet.
     RequestResize,
+    /// Ask the host to reload the plugin. This is a separate task from `TriggerRestart` so repeated
+    /// requests can be coalesced.
+    RestartRequested,
+    /// Tell the host that the plugin's state has changed.
+    StateDirty,
+    /// Ask the host to rescan all of the plugin's parameters. This is a separate task from
+    /// `TriggerRestart` so repeated requests can be coalesced.
+    ParamRescanRequested,
 }
 
 /// VST3 makes audio processing pretty complicated. In order to support both block splitting for
@@ -181,8 +264,9 @@ pub enum ProcessEvent<P: Plugin> {
     ///
     /// The timing stored within the note event needs to have the block start index subtraced from
     /// it. make sure to subtract the block start index with [`NoteEvent::subtract_timing()`] before
-    /// putting this into the input event queue.
-    NoteEvent(PluginNoteEvent<P>),
+    /// putting this into the input event queue. The second field is the index of the event bus
+    /// the event arrived on.
+    NoteEvent(PluginNoteEvent<P>, u32),
 }
 
 impl<P: Vst3Plugin> WrapperInner<P> {
@@ -235,6 +319,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
 
                 bypass_param_exists |= is_bypass;
 
+                unsafe { ptr.assert_validity() };
+
                 if P::MIDI_INPUT >= MidiConfig::MidiCCs
                     && (VST3_MIDI_PARAMS_START..VST3_MIDI_PARAMS_END).contains(hash)
                 {
@@ -245,12 +331,39 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                     );
                 }
             }
+
+            let lone_link_groups = unsafe {
+                lone_param_link_groups(param_id_hashes_ptrs_groups.iter().map(|(_, _, ptr, _)| ptr))
+            };
+            for link_group in lone_link_groups {
+                nih_debug_assert_failure!(
+                    "The parameter link group '{}' only contains a single parameter",
+                    link_group
+                );
+            }
+
+            nih_debug_assert!(
+                P::MIDI_INPUT < MidiConfig::Basic || P::MIDI_INPUT_PORTS >= 1,
+                "The plugin accepts note events but has no note input ports, set \
+                 'Plugin::MIDI_INPUT_PORTS' to at least one"
+            );
         }
 
-        let param_hashes = param_id_hashes_ptrs_groups
+        let param_hashes: Vec<u32> = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, _, _)| *hash)
             .collect();
+        let param_change_sources = ParamChangeSources::new(param_hashes.iter().copied());
+        let param_write_locks =
+            ParamWriteLocks::new(P::PARAM_WRITE_LOCK, param_hashes.iter().copied());
+        let param_gestures = ParamGestures::new(param_hashes.iter().copied());
+        let bypass_state = unsafe {
+            BypassState::new(
+                param_id_hashes_ptrs_groups
+                    .iter()
+                    .map(|(_, _, ptr, _)| *ptr),
+            )
+        };
         let param_by_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(_, hash, ptr, _)| (*hash, *ptr))
@@ -282,6 +395,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             editor: AtomicRefCell::new(None),
 
             component_handler: AtomicRefCell::new(None),
+            host_context: AtomicPtr::new(ptr::null_mut()),
 
             plug_view: RwLock::new(None),
 
@@ -298,15 +412,32 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             current_buffer_config: AtomicCell::new(None),
             current_process_mode: AtomicCell::new(ProcessMode::Realtime),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
+            block_position: BlockPositionCounter::default(),
+            seek_detector: SeekDetector::default(),
+            carried_input_events: CarriedInputEvents::default(),
             current_latency: AtomicU32::new(0),
+            plugin_latency: PluginLatency::default(),
+            restart_request: PendingRequest::default(),
+            first_process: FirstProcess::default(),
+            state_dirty_request: PendingRequest::default(),
+            param_rescan_request: PendingRequest::default(),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            padded_buffer: AtomicRefCell::new(PaddedBuffer::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+                P::SIMD_BLOCK_MULTIPLE,
+            )),
+            scratch_buffer_count: AtomicUsize::new(0),
+            scratch_buffers: AtomicRefCell::new(ScratchBuffers::default()),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
+            input_event_ports: AtomicRefCell::new(InputEventPorts::with_capacity(1024)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
+            output_param_changes: AtomicRefCell::new(Vec::with_capacity(1024)),
             note_expression_controller: AtomicRefCell::new(NoteExpressionController::default()),
             process_events: AtomicRefCell::new(Vec::with_capacity(4096)),
             updated_state_sender,
@@ -318,6 +449,14 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             param_units,
             param_id_to_hash,
             param_ptr_to_hash,
+            param_change_sources,
+            param_write_locks,
+            param_gestures,
+            bypass_state,
+            editor_state: EditorStateStore::default(),
+            transport_snapshot: TransportSnapshot::default(),
+            factory_presets: FactoryPresets::new::<P>(),
+            chain_hint: Mutex::new(ChainHint::default()),
         });
 
         // FIXME: Right now this is safe, but if we are going to have a singleton main thread queue
@@ -326,13 +465,19 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         *wrapper.event_loop.borrow_mut() =
             Some(OsEventLoop::new_and_spawn(Arc::downgrade(&wrapper)));
 
-        // The editor also needs to be initialized later so the Async executor can work.
-        *wrapper.editor.borrow_mut() = wrapper
+        wrapper
+    }
+
+    /// Query the plugin's editor. This is done in `IPluginBase::initialize()` rather than in the
+    /// constructor so [`Plugin::editor()`][crate::prelude::Plugin::editor()] can depend on the
+    /// host context. `create_view()` returns a null pointer if this did not return an editor.
+    pub fn init_editor(self: &Arc<Self>) {
+        *self.editor.borrow_mut() = self
             .plugin
             .lock()
             .editor(AsyncExecutor {
                 execute_background: Arc::new({
-                    let wrapper = wrapper.clone();
+                    let wrapper = self.clone();
 
                     move |task| {
                         let task_posted = wrapper.schedule_background(Task::PluginTask(task));
@@ -340,7 +485,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                     }
                 }),
                 execute_gui: Arc::new({
-                    let wrapper = wrapper.clone();
+                    let wrapper = self.clone();
 
                     move |task| {
                         let task_posted = wrapper.schedule_gui(Task::PluginTask(task));
@@ -349,8 +494,6 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 }),
             })
             .map(|editor| Arc::new(Mutex::new(editor)));
-
-        wrapper
     }
 
     pub fn make_gui_context(self: Arc<Self>) -> Arc<WrapperGuiContext<P>> {
@@ -372,12 +515,25 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         }
     }
 
-    pub fn make_process_context(&self, transport: Transport) -> WrapperProcessContext<'_, P> {
+    /// Create a process context for a block of `block_len` samples. This also advances the block
+    /// position counter, so this should be called exactly once per (split) block.
+    pub fn make_process_context(
+        &self,
+        mut transport: Transport,
+        block_len: usize,
+    ) -> WrapperProcessContext<'_, P> {
+        let block_start_samples = self.block_position.advance(&transport, block_len);
+        transport.just_seeked = self.seek_detector.advance(&transport, block_len);
+        self.transport_snapshot.store(&transport);
+
         WrapperProcessContext {
             inner: self,
             input_events_guard: self.input_events.borrow_mut(),
+            input_event_ports_guard: self.input_event_ports.borrow_mut(),
             output_events_guard: self.output_events.borrow_mut(),
+            scratch_buffers_guard: self.scratch_buffers.borrow_mut(),
             transport,
+            block_start_samples,
         }
     }
 
@@ -469,6 +625,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.editor_state,
             )
         }
     }
@@ -526,6 +683,18 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
     }
 
+    /// The host context passed to `IPluginBase::initialize()` for
+    /// [`GuiContext::raw_host_handle()`][crate::prelude::GuiContext::raw_host_handle()] and friends.
+    pub fn raw_host_handle(&self) -> RawHostHandle {
+        RawHostHandle::Vst3(self.host_context.load(Ordering::SeqCst))
+    }
+
+    /// Record the number of scratch buffers the plugin needs. These are allocated after the plugin
+    /// has been initialized.
+    pub fn request_scratch_buffers(&self, count: usize) {
+        self.scratch_buffer_count.store(count, Ordering::Relaxed);
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // Only trigger a restart if it's actually needed
         let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
@@ -536,6 +705,27 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         }
     }
 
+    pub fn request_restart(&self) {
+        if self.restart_request.request() {
+            let task_posted = self.schedule_gui(Task::RestartRequested);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
+    pub fn mark_state_dirty(&self) {
+        if self.state_dirty_request.request() {
+            let task_posted = self.schedule_gui(Task::StateDirty);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
+    pub fn request_param_rescan(&self) {
+        if self.param_rescan_request.request() {
+            let task_posted = self.schedule_gui(Task::ParamRescanRequested);
+            nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        }
+    }
+
     /// Immediately set the plugin state. Returns `false` if the deserialization failed. The plugin
     /// state is set from a couple places, so this function aims to deduplicate that. Includes
     /// `permit_alloc()`s around the deserialization and initialization for the use case where
@@ -561,6 +751,13 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 state,
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                |param_ptr| {
+                    self.param_ptr_to_hash
+                        .get(&param_ptr)
+                        .map(|hash| self.param_gestures.preserves_on_state_load::<P>(hash))
+                        .unwrap_or(false)
+                },
+                &self.editor_state,
                 buffer_config.as_ref(),
             )
         });
@@ -569,6 +766,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             return false;
         }
 
+        // The plugin's state no longer matches the factory preset that was loaded last, if any
+        self.factory_presets.state_loaded();
+
         // If the plugin was already initialized then it needs to be reinitialized
         if let Some(buffer_config) = buffer_config {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -580,7 +780,12 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context)
             });
             if success {
+                self.block_position.reset();
+                self.seek_detector.reset();
+                self.carried_input_events.reset();
                 process_wrapper(|| plugin.reset());
+                self.plugin_latency
+                    .update(&*plugin, |samples| self.set_latency_samples(samples));
             }
         }
 
@@ -648,6 +853,50 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                 },
                 None => nih_debug_assert_failure!("Can't resize a closed editor"),
             },
+            Task::RestartRequested => {
+                // Further requests made from now on will trigger another restart
+                if self.restart_request.take() {
+                    self.execute(
+                        Task::TriggerRestart(RestartFlags::kReloadComponent as i32),
+                        is_gui_thread,
+                    );
+                }
+            }
+            Task::ParamRescanRequested => {
+                // Further requests made from now on will trigger another rescan
+                if self.param_rescan_request.take() {
+                    self.execute(
+                        Task::TriggerRestart(
+                            RestartFlags::kParamValuesChanged as i32
+                                | RestartFlags::kParamTitlesChanged as i32,
+                        ),
+                        is_gui_thread,
+                    );
+                }
+            }
+            Task::StateDirty => {
+                if self.state_dirty_request.take() {
+                    // `setDirty()` is part of the optional `IComponentHandler2` interface
+                    let handler = self.component_handler.borrow();
+                    match handler
+                        .as_ref()
+                        .and_then(|handler| handler.cast::<dyn IComponentHandler2>())
+                    {
+                        Some(handler) => unsafe {
+                            nih_debug_assert!(is_gui_thread);
+                            let result = handler.set_dirty(1);
+                            nih_debug_assert_eq!(
+                                result,
+                                kResultOk,
+                                "Failed to mark the state dirty"
+                            );
+                        },
+                        None => {
+                            nih_log!("The host does not support IComponentHandler2::setDirty()")
+                        }
+                    }
+                }
+            }
         }
     }
 }
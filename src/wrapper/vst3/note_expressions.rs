@@ -135,7 +135,8 @@ impl NoteExpressionController {
                 channel,
                 note,
                 // This denormalized to the same [-120, 120] range used by CLAP and our expression
-                // events
+                // events. Like with CLAP, every point on the host's tuning curve results in its own
+                // timed event.
                 tuning: 240.0 * (event.value as f32 - 0.5),
             }),
             VIBRATO_EXPRESSION_ID => Some(NoteEvent::PolyVibrato {
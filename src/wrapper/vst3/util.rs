@@ -1,10 +1,13 @@
 use std::cmp;
+use std::ffi::CStr;
 use std::ops::Deref;
 use vst3_sys::interfaces::IUnknown;
 use vst3_sys::vst::TChar;
 use vst3_sys::ComInterface;
 use widestring::U16CString;
 
+use crate::prelude::{ChainHint, IndicationColor, TrackKind};
+
 /// When `Plugin::MIDI_INPUT` is set to `MidiConfig::MidiCCs` or higher then we'll register 130*16
 /// additional parameters to handle MIDI CCs, channel pressure, and pitch bend, in that order.
 /// vst3-sys doesn't expose these constants.
@@ -19,6 +22,13 @@ pub const VST3_MIDI_PARAMS_START: u32 = VST3_MIDI_PARAMS_END - VST3_MIDI_NUM_PAR
 /// The (exclusive) end of the MIDI CC parameter range. Anything above this is reserved by the host.
 pub const VST3_MIDI_PARAMS_END: u32 = 1 << 31;
 
+/// The attribute list keys for the track name and color sent through
+/// `IInfoListener::setChannelContextInfos()`. vst3-sys doesn't expose these constants.
+pub const VST3_CHANNEL_NAME_KEY: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"channel name\0") };
+pub const VST3_CHANNEL_COLOR_KEY: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"channel color\0") };
+
 /// Early exit out of a VST3 function when one of the passed pointers is null
 macro_rules! check_null_ptr {
     ($ptr:expr $(, $ptrs:expr)* $(, )?) => {
@@ -59,6 +69,30 @@ pub fn u16strlcpy(dest: &mut [TChar], src: &str) {
     dest[copy_len] = 0;
 }
 
+/// Convert the channel context information the host passes to `IInfoListener` to a [`ChainHint`].
+/// `get_string` and `get_int` look up a key in the host's attribute list, and they should return
+/// `None` if the host did not set that key. VST3 doesn't tell the plugin what kind of track it's on
+/// or how many channels the track has, so those fields are left unknown.
+pub fn chain_hint_from_channel_context(
+    get_string: impl Fn(&CStr) -> Option<String>,
+    get_int: impl Fn(&CStr) -> Option<i64>,
+) -> ChainHint {
+    // Colors are stored as `0xAARRGGBB` integers
+    let track_color = get_int(VST3_CHANNEL_COLOR_KEY).map(|color| IndicationColor {
+        red: (color >> 16) as u8,
+        green: (color >> 8) as u8,
+        blue: color as u8,
+        alpha: (color >> 24) as u8,
+    });
+
+    ChainHint {
+        track_kind: TrackKind::Unknown,
+        track_name: get_string(VST3_CHANNEL_NAME_KEY),
+        track_channel_count: None,
+        track_color,
+    }
+}
+
 /// Send+Sync wrapper for these interface pointers.
 #[repr(transparent)]
 pub struct VstPtr<T: vst3_sys::ComInterface + ?Sized> {
@@ -118,6 +152,7 @@ unsafe impl<T: IUnknown> Sync for ObjectPtr<T> {}
 
 #[cfg(test)]
 mod miri {
+    use std::collections::HashMap;
     use widestring::U16CStr;
 
     use super::*;
@@ -147,4 +182,35 @@ mod miri {
             "Hello"
         );
     }
+
+    #[test]
+    fn chain_hint_from_channel_context_info() {
+        // This is what a host would pass to `IInfoListener::setChannelContextInfos()`
+        let strings = HashMap::from([(VST3_CHANNEL_NAME_KEY, String::from("Drums"))]);
+        let ints = HashMap::from([(VST3_CHANNEL_COLOR_KEY, 0xff_c8_64_32i64)]);
+
+        assert_eq!(
+            chain_hint_from_channel_context(
+                |key| strings.get(key).cloned(),
+                |key| ints.get(key).copied()
+            ),
+            ChainHint {
+                track_kind: TrackKind::Unknown,
+                track_name: Some(String::from("Drums")),
+                track_channel_count: None,
+                track_color: Some(IndicationColor {
+                    red: 200,
+                    green: 100,
+                    blue: 50,
+                    alpha: 255,
+                }),
+            }
+        );
+
+        // Missing keys are left unknown
+        assert_eq!(
+            chain_hint_from_channel_context(|_| None, |_| None),
+            ChainHint::default()
+        );
+    }
 }
@@ -15,6 +15,7 @@ use super::inner::{Task, WrapperInner};
 use super::util::{ObjectPtr, VstPtr};
 use crate::plugin::vst3::Vst3Plugin;
 use crate::prelude::{Editor, ParentWindowHandle};
+use crate::wrapper::util::{adjust_editor_size, KeyboardFocusClaims};
 
 // Alias needed for the VST3 attribute macro
 use vst3_sys as vst3_com;
@@ -68,6 +69,9 @@ pub(crate) struct WrapperView<P: Vst3Plugin> {
     /// the sizes communicated to and from the DAW should be scaled by this factor since NIH-plug's
     /// APIs only deal in logical pixels.
     scaling_factor: AtomicF32,
+    /// The key presses forwarded by the host that were claimed by the editor through
+    /// [`Editor::wants_exclusive_keyboard_focus()`], so the matching releases can be claimed too.
+    keyboard_focus_claims: KeyboardFocusClaims<(vst3_sys::base::char16, i16)>,
 }
 
 /// Allow handling tasks on the host's GUI thread on Linux. This doesn't need to be a separate
@@ -111,6 +115,7 @@ impl<P: Vst3Plugin> WrapperView<P> {
             #[cfg(not(target_os = "linux"))]
             RunLoopEventHandlerWrapper(Default::default()),
             AtomicF32::new(1.0),
+            KeyboardFocusClaims::default(),
         )
     }
 
@@ -336,6 +341,7 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
         if editor_handle.is_some() {
             *self.inner.plug_view.write() = None;
             *editor_handle = None;
+            self.keyboard_focus_claims.reset();
 
             kResultOk
         } else {
@@ -353,20 +359,33 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
 
     unsafe fn on_key_down(
         &self,
-        _key: vst3_sys::base::char16,
-        _key_code: i16,
+        key: vst3_sys::base::char16,
+        key_code: i16,
         _modifiers: i16,
     ) -> tresult {
-        kResultOk
+        // The editor receives its keyboard input through the OS, but claiming the key here keeps
+        // the host from also treating it as a keyboard shortcut
+        if self
+            .keyboard_focus_claims
+            .key_down(&**self.editor.lock(), (key, key_code))
+        {
+            kResultOk
+        } else {
+            kResultFalse
+        }
     }
 
     unsafe fn on_key_up(
         &self,
-        _key: vst3_sys::base::char16,
-        _key_code: i16,
+        key: vst3_sys::base::char16,
+        key_code: i16,
         _modifiers: i16,
     ) -> tresult {
-        kResultOk
+        if self.keyboard_focus_claims.key_up((key, key_code)) {
+            kResultOk
+        } else {
+            kResultFalse
+        }
     }
 
     unsafe fn get_size(&self, size: *mut ViewRect) -> tresult {
@@ -408,7 +427,11 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
         }
     }
 
-    unsafe fn on_focus(&self, _state: TBool) -> tresult {
+    unsafe fn on_focus(&self, state: TBool) -> tresult {
+        if state == 0 {
+            self.keyboard_focus_claims.reset();
+        }
+
         kResultOk
     }
 
@@ -447,12 +470,23 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
     unsafe fn check_size_constraint(&self, rect: *mut ViewRect) -> tresult {
         check_null_ptr!(rect);
 
-        // TODO: Implement Host->Plugin resizing
-        if (*rect).right - (*rect).left > 0 && (*rect).bottom - (*rect).top > 0 {
-            kResultOk
-        } else {
-            kResultFalse
+        let rect = &mut *rect;
+        let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+        if width <= 0 || height <= 0 {
+            return kResultFalse;
         }
+
+        // The editor gets to snap the host's proposed size to a size it can actually take on
+        let scaling_factor = self.scaling_factor.load(Ordering::Relaxed);
+        let (width, height) = adjust_editor_size(
+            &**self.editor.lock(),
+            (width as u32, height as u32),
+            scaling_factor,
+        );
+        rect.right = rect.left + width as i32;
+        rect.bottom = rect.top + height as i32;
+
+        kResultOk
     }
 }
 
@@ -10,11 +10,11 @@ use vst3_sys::base::{kInvalidArgument, kNoInterface, kResultFalse, kResultOk, tr
 use vst3_sys::base::{IBStream, IPluginBase};
 use vst3_sys::utils::SharedVstPtr;
 use vst3_sys::vst::{
-    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAudioProcessor,
-    IComponent, IEditController, IEventList, IMidiMapping, INoteExpressionController,
-    IParamValueQueue, IParameterChanges, IProcessContextRequirements, IUnitInfo,
-    LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription, NoteOffEvent,
-    NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
+    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAttributeList,
+    IAudioProcessor, IComponent, IEditController, IEventList, IInfoListener, IMidiMapping,
+    INoteExpressionController, IParamValueQueue, IParameterChanges, IProcessContextRequirements,
+    IUnitInfo, LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription,
+    NoteOffEvent, NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
 };
 use vst3_sys::VST3;
 use widestring::U16CStr;
@@ -22,7 +22,8 @@ use widestring::U16CStr;
 use super::inner::{ProcessEvent, WrapperInner};
 use super::note_expressions::{self, NoteExpressionController};
 use super::util::{
-    u16strlcpy, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS, VST3_MIDI_PARAMS_START,
+    chain_hint_from_channel_context, u16strlcpy, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS,
+    VST3_MIDI_PARAMS_START,
 };
 use super::util::{VST3_MIDI_CHANNELS, VST3_MIDI_PARAMS_END};
 use super::view::WrapperView;
@@ -32,8 +33,14 @@ use crate::prelude::{
 };
 use crate::util::permit_alloc;
 use crate::wrapper::state;
-use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
-use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing, process_wrapper};
+use crate::wrapper::util::buffer_management::{
+    BufferManager, ChannelPointers, PaddedBuffer, ScratchBuffers,
+};
+use crate::wrapper::util::{
+    check_process_mode, clamp_input_event_timing, clamp_output_event_timing,
+    is_sample_rate_supported, note_input_port_name, process_wrapper, reset_param_smoothers,
+    skip_silent_block, sort_output_events,
+};
 
 // Alias needed for the VST3 attribute macro
 use vst3_sys as vst3_com;
@@ -45,7 +52,8 @@ use vst3_sys as vst3_com;
     IMidiMapping,
     INoteExpressionController,
     IProcessContextRequirements,
-    IUnitInfo
+    IUnitInfo,
+    IInfoListener
 ))]
 pub(crate) struct Wrapper<P: Vst3Plugin> {
     inner: Arc<WrapperInner<P>>,
@@ -64,12 +72,21 @@ impl<P: Vst3Plugin> Drop for Wrapper<P> {
 }
 
 impl<P: Vst3Plugin> IPluginBase for Wrapper<P> {
-    unsafe fn initialize(&self, _context: *mut c_void) -> tresult {
-        // We currently don't need or allow any initialization logic
+    unsafe fn initialize(&self, context: *mut c_void) -> tresult {
+        // The plugin can access the host context through `raw_host_handle()`
+        self.inner.host_context.store(context, Ordering::SeqCst);
+
+        // The editor is created here so its presence can depend on the host context
+        self.inner.init_editor();
+
         kResultOk
     }
 
     unsafe fn terminate(&self) -> tresult {
+        self.inner
+            .host_context
+            .store(std::ptr::null_mut(), Ordering::SeqCst);
+
         kResultOk
     }
 }
@@ -124,7 +141,7 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 && dir == vst3_sys::vst::BusDirections::kInput as i32
                 && P::MIDI_INPUT >= MidiConfig::Basic =>
             {
-                1
+                P::MIDI_INPUT_PORTS as i32
             }
             x if x == vst3_sys::vst::MediaTypes::kEvent as i32
                 && dir == vst3_sys::vst::BusDirections::kOutput as i32
@@ -226,10 +243,11 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                     kInvalidArgument
                 }
             }
-            (t, d, 0)
+            (t, d, index)
                 if t == vst3_sys::vst::MediaTypes::kEvent as i32
                     && d == vst3_sys::vst::BusDirections::kInput as i32
-                    && P::MIDI_INPUT >= MidiConfig::Basic =>
+                    && P::MIDI_INPUT >= MidiConfig::Basic
+                    && (0..P::MIDI_INPUT_PORTS as i32).contains(&index) =>
             {
                 *info = mem::zeroed();
 
@@ -237,8 +255,16 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 info.media_type = vst3_sys::vst::MediaTypes::kEvent as i32;
                 info.direction = vst3_sys::vst::BusDirections::kInput as i32;
                 info.channel_count = 16;
-                u16strlcpy(&mut info.name, "Note Input");
-                info.bus_type = vst3_sys::vst::BusTypes::kMain as i32;
+                u16strlcpy(
+                    &mut info.name,
+                    &note_input_port_name(index as u32, P::MIDI_INPUT_PORTS),
+                );
+                // Only the first note input is the main bus
+                info.bus_type = if index == 0 {
+                    vst3_sys::vst::BusTypes::kMain as i32
+                } else {
+                    vst3_sys::vst::BusTypes::kAux as i32
+                };
                 info.flags = vst3_sys::vst::BusFlags::kDefaultActive as u32;
                 kResultOk
             }
@@ -349,10 +375,11 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                     kInvalidArgument
                 }
             }
-            (t, d, 0)
+            (t, d, index)
                 if t == vst3_sys::vst::MediaTypes::kEvent as i32
                     && d == vst3_sys::vst::BusDirections::kInput as i32
-                    && P::MIDI_INPUT >= MidiConfig::Basic =>
+                    && P::MIDI_INPUT >= MidiConfig::Basic
+                    && (0..P::MIDI_INPUT_PORTS as i32).contains(&index) =>
             {
                 kResultOk
             }
@@ -382,6 +409,7 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 let mut init_context = self.inner.make_init_context();
                 let audio_io_layout = self.inner.current_audio_io_layout.load();
                 let mut plugin = self.inner.plugin.lock();
+                self.inner.scratch_buffer_count.store(0, Ordering::Relaxed);
                 if plugin.initialize(&audio_io_layout, &buffer_config, &mut init_context) {
                     // NOTE: We don't call `Plugin::reset()` here. The call is done in `set_process()`
                     //       instead. Otherwise we would call the function twice, and `set_process()` needs
@@ -394,6 +422,19 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                         buffer_config.max_buffer_size as usize,
                         audio_io_layout,
                     );
+                    *self.inner.padded_buffer.borrow_mut() = PaddedBuffer::for_audio_io_layout(
+                        buffer_config.max_buffer_size as usize,
+                        audio_io_layout,
+                        P::SIMD_BLOCK_MULTIPLE,
+                    );
+                    *self.inner.scratch_buffers.borrow_mut() = ScratchBuffers::new(
+                        self.inner.scratch_buffer_count.load(Ordering::Relaxed),
+                        buffer_config.max_buffer_size as usize,
+                    );
+                    self.inner.first_process.activated();
+                    self.inner
+                        .plugin_latency
+                        .update(&*plugin, |samples| self.inner.set_latency_samples(samples));
 
                     kResultOk
                 } else {
@@ -402,6 +443,7 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             }
             (true, None) => kResultFalse,
             (false, _) => {
+                self.inner.first_process.deactivated();
                 self.inner.plugin.lock().deactivate();
 
                 kResultOk
@@ -467,9 +509,13 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
 
         let state = state.upgrade().unwrap();
 
+        // Unlike with CLAP there's no queue of editor parameter changes to flush here. When the
+        // plugin is not processing audio the editor's changes are applied immediately, and
+        // otherwise the host sends them back during the next process call.
         let serialized = state::serialize_json::<P>(
             self.inner.params.clone(),
             state::make_params_iter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
+            &self.inner.editor_state,
         );
         match serialized {
             Ok(serialized) => {
@@ -677,6 +723,19 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             .current_buffer_config
             .load()
             .map(|c| c.sample_rate);
+        // Changes that would overwrite the plugin's own recent writes are ignored, see
+        // `Plugin::PARAM_WRITE_LOCK`
+        if !self
+            .inner
+            .param_write_locks
+            .allows_host_change(&id, value as f32)
+        {
+            return kResultOk;
+        }
+
+        self.inner
+            .param_change_sources
+            .host_changed(&id, value as f32);
         self.inner
             .set_normalized_value_by_hash(id, value as f32, sample_rate)
     }
@@ -870,11 +929,22 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
         );
 
+        // Hosts will either pick another sample rate or report an error to the user
+        if !is_sample_rate_supported(P::SUPPORTED_SAMPLE_RATES, setup.sample_rate as f32) {
+            nih_log!(
+                "The host tried to set up processing with an unsupported sample rate of {} Hz",
+                setup.sample_rate
+            );
+            return kResultFalse;
+        }
+
         // This is needed when activating the plugin and when restoring state
         self.inner.current_buffer_config.store(Some(BufferConfig {
             sample_rate: setup.sample_rate as f32,
             min_buffer_size: None,
             max_buffer_size: setup.max_samples_per_block as u32,
+            // VST3 hosts only report the maximum block size
+            preferred_buffer_size: None,
             process_mode: self.inner.current_process_mode.load(),
         }));
 
@@ -887,6 +957,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 ProcessMode::Realtime
             }
         };
+        // Hosts that ignore the `OnlyRT` subcategory may still try to render offline
+        check_process_mode(P::HARD_REALTIME_ONLY, mode);
         self.inner.current_process_mode.store(mode);
 
         // Initializing the plugin happens in `IAudioProcessor::set_active()` because the host may
@@ -921,6 +993,18 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
             };
 
+            if P::RESET_SMOOTHERS_ON_RESET {
+                if let Some(buffer_config) = self.inner.current_buffer_config.load() {
+                    reset_param_smoothers(
+                        self.inner.param_by_hash.values(),
+                        buffer_config.sample_rate,
+                    );
+                }
+            }
+
+            self.inner.block_position.reset();
+            self.inner.seek_detector.reset();
+            self.inner.carried_input_events.reset();
             process_wrapper(|| plugin.reset());
         }
 
@@ -1013,6 +1097,16 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 );
                                 let value = value as f32;
 
+                                // Changes that would overwrite the plugin's own recent writes are
+                                // ignored, see `Plugin::PARAM_WRITE_LOCK`
+                                if !self
+                                    .inner
+                                    .param_write_locks
+                                    .allows_host_change(&param_hash, value)
+                                {
+                                    continue;
+                                }
+
                                 // MIDI CC messages, channel pressure, and pitch bend are also sent
                                 // as parameter changes
                                 if P::MIDI_INPUT >= MidiConfig::MidiCCs
@@ -1025,7 +1119,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     let midi_cc = (midi_param_relative_idx % VST3_MIDI_CCS) as u8;
                                     let midi_channel =
                                         (midi_param_relative_idx / VST3_MIDI_CCS) as u8;
-                                    process_events.push(ProcessEvent::NoteEvent(match midi_cc {
+                                    // CCs can only be mapped to parameters on the first event bus
+                                    let note_event = match midi_cc {
                                         // kAfterTouch
                                         128 => NoteEvent::MidiChannelPressure {
                                             timing,
@@ -1044,14 +1139,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                             cc: n,
                                             value,
                                         },
-                                    }));
+                                    };
+                                    process_events.push(ProcessEvent::NoteEvent(note_event, 0));
                                 } else if P::SAMPLE_ACCURATE_AUTOMATION {
+                                    self.inner
+                                        .param_change_sources
+                                        .host_changed(&param_hash, value);
                                     process_events.push(ProcessEvent::ParameterChange {
                                         timing,
                                         hash: param_hash,
                                         normalized_value: value,
                                     });
                                 } else {
+                                    self.inner
+                                        .param_change_sources
+                                        .host_changed(&param_hash, value);
                                     self.inner.set_normalized_value_by_hash(
                                         param_hash,
                                         value,
@@ -1081,6 +1183,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             event.sample_offset as u32,
                             total_buffer_len as u32,
                         );
+                        // The event bus the event arrived on, see `Plugin::MIDI_INPUT_PORTS`
+                        let port = event.bus_index.max(0) as u32;
 
                         if event.type_ == EventTypes::kNoteOnEvent as u16 {
                             let event = event.event.note_on;
@@ -1089,49 +1193,57 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             // expression value events
                             note_expression_controller.register_note(&event);
 
-                            process_events.push(ProcessEvent::NoteEvent(NoteEvent::NoteOn {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.pitch as u8,
-                                velocity: event.velocity,
-                            }));
+                            process_events.push(ProcessEvent::NoteEvent(
+                                NoteEvent::note_on_or_off(
+                                    timing,
+                                    if event.note_id != -1 {
+                                        Some(event.note_id)
+                                    } else {
+                                        None
+                                    },
+                                    event.channel as u8,
+                                    event.pitch as u8,
+                                    event.velocity,
+                                ),
+                                port,
+                            ));
                         } else if event.type_ == EventTypes::kNoteOffEvent as u16 {
                             let event = event.event.note_off;
-                            process_events.push(ProcessEvent::NoteEvent(NoteEvent::NoteOff {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
-                                },
-                                channel: event.channel as u8,
-                                note: event.pitch as u8,
-                                velocity: event.velocity,
-                            }));
+                            process_events.push(ProcessEvent::NoteEvent(
+                                NoteEvent::note_off(
+                                    timing,
+                                    if event.note_id != -1 {
+                                        Some(event.note_id)
+                                    } else {
+                                        None
+                                    },
+                                    event.channel as u8,
+                                    event.pitch as u8,
+                                    event.velocity,
+                                ),
+                                port,
+                            ));
                         } else if event.type_ == EventTypes::kPolyPressureEvent as u16 {
                             let event = event.event.poly_pressure;
-                            process_events.push(ProcessEvent::NoteEvent(NoteEvent::PolyPressure {
-                                timing,
-                                voice_id: if event.note_id != -1 {
-                                    Some(event.note_id)
-                                } else {
-                                    None
+                            process_events.push(ProcessEvent::NoteEvent(
+                                NoteEvent::PolyPressure {
+                                    timing,
+                                    voice_id: if event.note_id != -1 {
+                                        Some(event.note_id)
+                                    } else {
+                                        None
+                                    },
+                                    channel: event.channel as u8,
+                                    note: event.pitch as u8,
+                                    pressure: event.pressure,
                                 },
-                                channel: event.channel as u8,
-                                note: event.pitch as u8,
-                                pressure: event.pressure,
-                            }));
+                                port,
+                            ));
                         } else if event.type_ == EventTypes::kNoteExpressionValueEvent as u16 {
                             let event = event.event.note_expression_value;
                             match note_expression_controller.translate_event(timing, &event) {
-                                Some(translated_event) => {
-                                    process_events.push(ProcessEvent::NoteEvent(translated_event))
-                                }
+                                Some(translated_event) => process_events
+                                    .push(ProcessEvent::NoteEvent(translated_event, port)),
                                 None => nih_debug_assert_failure!(
                                     "Unhandled note expression type: {}",
                                     event.type_id
@@ -1149,7 +1261,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             let sysex_buffer =
                                 std::slice::from_raw_parts(event.bytes, event.size as usize);
                             if let Ok(note_event) = NoteEvent::from_midi(timing, sysex_buffer) {
-                                process_events.push(ProcessEvent::NoteEvent(note_event));
+                                process_events.push(ProcessEvent::NoteEvent(note_event, port));
                             };
                         }
                     }
@@ -1166,7 +1278,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             permit_alloc(|| {
                 process_events.sort_by_key(|event| match event {
                     ProcessEvent::ParameterChange { timing, .. } => *timing,
-                    ProcessEvent::NoteEvent(event) => event.timing(),
+                    ProcessEvent::NoteEvent(event, _) => event.timing(),
                 })
             });
 
@@ -1183,7 +1295,11 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 // The extra scope is here to make sure we release the borrow on input_events
                 {
                     let mut input_events = self.inner.input_events.borrow_mut();
-                    input_events.clear();
+                    let mut input_event_ports = self.inner.input_event_ports.borrow_mut();
+                    self.inner
+                        .carried_input_events
+                        .begin_block(&mut input_events);
+                    input_event_ports.sync(&input_events, 0);
 
                     block_end = total_buffer_len;
                     for event_idx in event_start_idx..process_events.len() {
@@ -1208,18 +1324,27 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     Some(sample_rate),
                                 );
                             }
-                            ProcessEvent::NoteEvent(event) => {
+                            ProcessEvent::NoteEvent(event, port) => {
                                 // We need to make sure to compensate the event for any block splitting,
                                 // since we had to create the event object beforehand
                                 let mut event = event.clone();
                                 event.subtract_timing(block_start as u32);
                                 input_events.push_back(event);
+                                input_event_ports.sync(&input_events, *port);
                             }
                         }
                     }
                 }
 
                 let result = if is_param_flush {
+                    // The plugin's process function is not called during a parameter flush, so any
+                    // note events are delivered at the start of the next block instead
+                    if total_buffer_len == 0 {
+                        self.inner
+                            .carried_input_events
+                            .carry_over(&mut self.inner.input_events.borrow_mut());
+                    }
+
                     kResultOk
                 } else {
                     // After processing the events we now know where/if the block should be split,
@@ -1328,6 +1453,9 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     }
                     nih_debug_assert!(buffer_is_valid);
 
+                    // The bypass parameter's value for this block is now known
+                    self.inner.bypass_state.update(sample_rate);
+
                     // Some of the fields are left empty because VST3 does not provide this
                     // information, but the methods on [`Transport`] can reconstruct these values
                     // from the other fields
@@ -1389,7 +1517,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                         }
                     }
 
-                    let result = if buffer_is_valid {
+                    let skip_process = skip_silent_block(
+                        P::SILENCE_IN_SILENCE_OUT,
+                        !self.inner.input_events.borrow().is_empty(),
+                        buffers.main_buffer,
+                        buffers.aux_inputs,
+                    );
+                    let result = if buffer_is_valid && skip_process {
+                        // The plugin promised to output silence when its input is silent. The main
+                        // buffer is processed in place and the auxiliary outputs have already been
+                        // cleared, so the outputs are already silent.
+                        self.inner.block_position.advance(&transport, block_len);
+                        self.inner.seek_detector.skip(&transport, block_len);
+                        self.inner.last_process_status.store(ProcessStatus::Normal);
+                        ProcessStatus::Normal
+                    } else if buffer_is_valid {
                         // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use of
                         //       thread locals
                         let mut plugin = permit_alloc(|| self.inner.plugin.lock());
@@ -1397,9 +1539,22 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                             inputs: buffers.aux_inputs,
                             outputs: buffers.aux_outputs,
                         };
-                        let mut context = self.inner.make_process_context(transport);
-                        let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
+                        let mut context = self.inner.make_process_context(transport, block_len);
+                        self.inner
+                            .first_process
+                            .before_process(|| plugin.first_process(&mut context));
+                        let result = self
+                            .inner
+                            .padded_buffer
+                            .borrow_mut()
+                            .process(buffers.main_buffer, |buffer| {
+                                plugin.process(buffer, &mut aux, &mut context)
+                            });
                         self.inner.last_process_status.store(result);
+                        // Parameter changes from this block may have changed the plugin's latency
+                        self.inner
+                            .plugin_latency
+                            .update(&*plugin, |samples| self.inner.set_latency_samples(samples));
                         result
                     } else {
                         ProcessStatus::Normal
@@ -1417,7 +1572,9 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
 
                 // Send any events output by the plugin during the process cycle
                 if let Some(events) = data.output_events.upgrade() {
+                    // Hosts expect these events to be sorted by their timing
                     let mut output_events = self.inner.output_events.borrow_mut();
+                    sort_output_events(&mut output_events);
                     while let Some(event) = output_events.pop_front() {
                         // We'll set the correct variant on this struct, or skip to the next loop
                         // iteration if we don't handle the event type
@@ -1646,6 +1803,44 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
             };
 
+            // Automation points written by the plugin using
+            // `ProcessContext::set_parameter_automated()` are sent at the end of the buffer. Only
+            // the last value for each parameter is stored, so this doesn't preserve the sample
+            // offsets used by the plugin.
+            let mut output_param_changes = self.inner.output_param_changes.borrow_mut();
+            if !output_param_changes.is_empty() {
+                let sample_rate = self
+                    .inner
+                    .current_buffer_config
+                    .load()
+                    .map(|c| c.sample_rate);
+                let host_param_changes = data.output_param_changes.upgrade();
+                for (param_hash, normalized_value) in output_param_changes.drain(..) {
+                    self.inner.set_normalized_value_by_hash(
+                        param_hash,
+                        normalized_value,
+                        sample_rate,
+                    );
+
+                    if let Some(host_param_changes) = &host_param_changes {
+                        let mut queue_idx = 0;
+                        if let Some(param_queue) = host_param_changes
+                            .add_parameter_data(&param_hash, &mut queue_idx)
+                            .upgrade()
+                        {
+                            let mut point_idx = 0;
+                            let result = param_queue.add_point(
+                                total_buffer_len.saturating_sub(1) as i32,
+                                normalized_value as f64,
+                                &mut point_idx,
+                            );
+                            nih_debug_assert_eq!(result, kResultOk);
+                        }
+                    }
+                }
+            }
+            drop(output_param_changes);
+
             // After processing audio, we'll check if the editor has sent us updated plugin state.
             // We'll restore that here on the audio thread to prevent changing the values during the
             // process call and also to prevent inconsistent state when the host also wants to load
@@ -1893,3 +2088,44 @@ impl<P: Vst3Plugin> IUnitInfo for Wrapper<P> {
         kInvalidArgument
     }
 }
+
+impl<P: Vst3Plugin> IInfoListener for Wrapper<P> {
+    unsafe fn set_channel_context_infos(&self, list: SharedVstPtr<dyn IAttributeList>) -> tresult {
+        let list = match list.upgrade() {
+            Some(list) => list,
+            None => return kInvalidArgument,
+        };
+
+        let chain_hint = chain_hint_from_channel_context(
+            |key| {
+                let mut string = [0 as TChar; 128];
+                let result = list.get_string(
+                    key.as_ptr(),
+                    string.as_mut_ptr(),
+                    mem::size_of_val(&string) as u32,
+                );
+                if result != kResultOk {
+                    return None;
+                }
+
+                // Make sure the string is null terminated
+                let last_idx = string.len() - 1;
+                string[last_idx] = 0;
+                U16CStr::from_ptr_str(string.as_ptr() as *const u16)
+                    .to_string()
+                    .ok()
+            },
+            |key| {
+                let mut value = 0;
+                if list.get_int(key.as_ptr(), &mut value) == kResultOk {
+                    Some(value)
+                } else {
+                    None
+                }
+            },
+        );
+        *self.inner.chain_hint.lock() = chain_hint;
+
+        kResultOk
+    }
+}